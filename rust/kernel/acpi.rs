@@ -0,0 +1,64 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Advanced Configuration and Power Interface (ACPI) device ids.
+//!
+//! C header: [`include/linux/acpi.h`](../../../../include/linux/acpi.h)
+
+use crate::{bindings, driver, str::BStr};
+
+/// An ACPI device id.
+#[derive(Clone, Copy)]
+pub enum DeviceId {
+    /// An ACPI device id where only a hardware id (`_HID`) or compatible id (`_CID`) is specified,
+    /// e.g., `b"PNP0C0A"`.
+    Hid(&'static BStr),
+}
+
+/// Defines a const ACPI device id table that also carries per-entry data/context/info.
+///
+/// The name of the const is `ACPI_DEVICE_ID_TABLE`, which is what buses are expected to name their
+/// ACPI tables.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::define_acpi_id_table;
+/// use kernel::acpi;
+///
+/// define_acpi_id_table! {u32, [
+///     (acpi::DeviceId::Hid(b"PRP0001"), Some(0xff)),
+///     (acpi::DeviceId::Hid(b"BCM2E3A"), None),
+/// ]};
+/// ```
+#[macro_export]
+macro_rules! define_acpi_id_table {
+    ($data_type:ty, $($t:tt)*) => {
+        $crate::define_id_table!(ACPI_DEVICE_ID_TABLE, $crate::acpi::DeviceId, $data_type, $($t)*);
+    };
+}
+
+// SAFETY: `ZERO` is all zeroed-out and `to_rawid` stores `offset` in `acpi_device_id::driver_data`.
+unsafe impl const driver::RawDeviceId for DeviceId {
+    type RawType = bindings::acpi_device_id;
+    const ZERO: Self::RawType = bindings::acpi_device_id {
+        id: [0; 16],
+        driver_data: 0,
+        cls: 0,
+        cls_msk: 0,
+    };
+
+    fn to_rawid(&self, offset: isize) -> Self::RawType {
+        let DeviceId::Hid(hid) = self;
+        let mut id = Self::ZERO;
+        let mut i = 0;
+        while i < hid.len() {
+            // If `hid` does not fit in `id.id`, an "index out of bounds" build time error will be
+            // triggered.
+            id.id[i] = hid[i] as _;
+            i += 1;
+        }
+        id.id[i] = b'\0' as _;
+        id.driver_data = offset as _;
+        id
+    }
+}
@@ -18,6 +18,8 @@
     unsafe_op_in_unsafe_fn
 )]
 
+use crate::c_types;
+
 mod bindings_raw {
     // Use glob import here to expose all helpers.
     // Symbols defined within the module will take precedence to the glob import.
@@ -43,5 +45,33 @@ mod bindings_helper {
 pub use bindings_raw::*;
 
 pub const GFP_KERNEL: gfp_t = BINDINGS_GFP_KERNEL;
+pub const GFP_ATOMIC: gfp_t = BINDINGS_GFP_ATOMIC;
+pub const GFP_NOWAIT: gfp_t = BINDINGS_GFP_NOWAIT;
+pub const GFP_NOIO: gfp_t = BINDINGS_GFP_NOIO;
+pub const GFP_NOFS: gfp_t = BINDINGS_GFP_NOFS;
+pub const GFP_USER: gfp_t = BINDINGS_GFP_USER;
+pub const GFP_HIGHUSER: gfp_t = BINDINGS_GFP_HIGHUSER;
+pub const GFP_DMA: gfp_t = BINDINGS_GFP_DMA;
+pub const GFP_DMA32: gfp_t = BINDINGS_GFP_DMA32;
 pub const __GFP_ZERO: gfp_t = BINDINGS___GFP_ZERO;
+pub const __GFP_NOWARN: gfp_t = BINDINGS___GFP_NOWARN;
+pub const __GFP_RETRY_MAYFAIL: gfp_t = BINDINGS___GFP_RETRY_MAYFAIL;
+pub const __GFP_ACCOUNT: gfp_t = BINDINGS___GFP_ACCOUNT;
+pub const SLAB_HWCACHE_ALIGN: slab_flags_t = BINDINGS_SLAB_HWCACHE_ALIGN;
+pub const SLAB_RECLAIM_ACCOUNT: slab_flags_t = BINDINGS_SLAB_RECLAIM_ACCOUNT;
+pub const SLAB_ACCOUNT: slab_flags_t = BINDINGS_SLAB_ACCOUNT;
+pub const SLAB_PANIC: slab_flags_t = BINDINGS_SLAB_PANIC;
+pub const SLAB_TYPESAFE_BY_RCU: slab_flags_t = BINDINGS_SLAB_TYPESAFE_BY_RCU;
+pub const SLAB_POISON: slab_flags_t = BINDINGS_SLAB_POISON;
+pub const SLAB_RED_ZONE: slab_flags_t = BINDINGS_SLAB_RED_ZONE;
 pub const __GFP_HIGHMEM: gfp_t = ___GFP_HIGHMEM;
+pub const DEFAULT_RATELIMIT_INTERVAL: c_types::c_int = BINDINGS_DEFAULT_RATELIMIT_INTERVAL;
+pub const DEFAULT_RATELIMIT_BURST: c_types::c_int = BINDINGS_DEFAULT_RATELIMIT_BURST;
+pub const MAX_SCHEDULE_TIMEOUT: c_types::c_long = BINDINGS_MAX_SCHEDULE_TIMEOUT;
+pub const MIN_NICE: c_types::c_int = BINDINGS_MIN_NICE;
+pub const MAX_NICE: c_types::c_int = BINDINGS_MAX_NICE;
+pub const BLK_STS_OK: blk_status_t = BINDINGS_BLK_STS_OK;
+pub const BLK_STS_NOTSUPP: blk_status_t = BINDINGS_BLK_STS_NOTSUPP;
+pub const BLK_STS_IOERR: blk_status_t = BINDINGS_BLK_STS_IOERR;
+pub const BLK_STS_RESOURCE: blk_status_t = BINDINGS_BLK_STS_RESOURCE;
+pub const NLMSG_GOODSIZE: usize = BINDINGS_NLMSG_GOODSIZE;
@@ -7,30 +7,100 @@
  */
 
 #include <asm/io.h>
+#include <crypto/aead.h>
+#include <crypto/skcipher.h>
+#include <linux/acpi.h>
 #include <linux/amba/bus.h>
+#include <linux/atomic.h>
+#include <linux/bitmap.h>
+#include <linux/blk-mq.h>
+#include <linux/blkdev.h>
 #include <linux/cdev.h>
 #include <linux/clk.h>
+#include <linux/completion.h>
+#include <linux/console.h>
+#include <linux/cpumask.h>
+#include <linux/debugfs.h>
+#include <linux/delay.h>
+#include <linux/device.h>
+#include <linux/dma-buf.h>
+#include <linux/dma-mapping.h>
+#include <linux/dynamic_debug.h>
 #include <linux/errname.h>
+#include <linux/etherdevice.h>
+#include <linux/ethtool.h>
 #include <linux/file.h>
+#include <linux/firmware.h>
+#include <linux/freezer.h>
 #include <linux/fs.h>
+#include <linux/genalloc.h>
+#include <linux/gfp.h>
+#include <linux/gpio/consumer.h>
 #include <linux/gpio/driver.h>
+#include <linux/hashtable.h>
+#include <linux/highmem.h>
+#include <linux/hrtimer.h>
 #include <linux/hw_random.h>
+#include <linux/idr.h>
+#include <linux/in.h>
+#include <linux/in6.h>
 #include <linux/interrupt.h>
+#include <linux/io.h>
+#include <linux/ioport.h>
 #include <linux/irqdomain.h>
 #include <linux/irq.h>
+#include <linux/irq_work.h>
+#include <linux/irqflags.h>
+#include <linux/jiffies.h>
+#include <linux/kmsg_dump.h>
+#include <linux/kobject.h>
+#include <linux/kthread.h>
+#include <linux/llist.h>
+#include <linux/mempool.h>
 #include <linux/miscdevice.h>
 #include <linux/mm.h>
 #include <linux/module.h>
+#include <linux/net.h>
+#include <linux/netdevice.h>
+#include <linux/nodemask.h>
+#include <linux/notifier.h>
 #include <linux/of_platform.h>
+#include <linux/oom.h>
+#include <linux/pagemap.h>
+#include <linux/panic_notifier.h>
+#include <linux/percpu_counter.h>
 #include <linux/platform_device.h>
 #include <linux/poll.h>
+#include <linux/preempt.h>
+#include <linux/property.h>
 #include <linux/random.h>
+#include <linux/ratelimit.h>
+#include <linux/reboot.h>
+#include <linux/scatterlist.h>
+#include <linux/sched.h>
+#include <linux/sched/prio.h>
 #include <linux/security.h>
+#include <linux/semaphore.h>
+#include <linux/shmem_fs.h>
 #include <linux/slab.h>
+#include <linux/smp.h>
+#include <linux/spi/spi.h>
+#include <linux/suspend.h>
 #include <linux/sysctl.h>
+#include <linux/sysfs.h>
+#include <linux/timer.h>
+#include <linux/topology.h>
+#include <linux/trace_events.h>
 #include <linux/uaccess.h>
 #include <linux/uio.h>
+#include <linux/usb.h>
+#include <linux/vmalloc.h>
+#include <linux/wait.h>
+#include <linux/workqueue.h>
+#include <net/genetlink.h>
+#include <net/sock.h>
 #include <uapi/linux/android/binder.h>
+#include <uapi/linux/sched/types.h>
 #include <linux/netfilter.h>
 #include <linux/netfilter_ipv4.h>
 #include <linux/netfilter_ipv6.h>
@@ -38,4 +108,32 @@
 
 /* `bindgen` gets confused at certain things. */
 const gfp_t BINDINGS_GFP_KERNEL = GFP_KERNEL;
+const gfp_t BINDINGS_GFP_ATOMIC = GFP_ATOMIC;
+const gfp_t BINDINGS_GFP_NOWAIT = GFP_NOWAIT;
+const gfp_t BINDINGS_GFP_NOIO = GFP_NOIO;
+const gfp_t BINDINGS_GFP_NOFS = GFP_NOFS;
+const gfp_t BINDINGS_GFP_USER = GFP_USER;
+const gfp_t BINDINGS_GFP_HIGHUSER = GFP_HIGHUSER;
+const gfp_t BINDINGS_GFP_DMA = GFP_DMA;
+const gfp_t BINDINGS_GFP_DMA32 = GFP_DMA32;
 const gfp_t BINDINGS___GFP_ZERO = __GFP_ZERO;
+const gfp_t BINDINGS___GFP_NOWARN = __GFP_NOWARN;
+const gfp_t BINDINGS___GFP_RETRY_MAYFAIL = __GFP_RETRY_MAYFAIL;
+const gfp_t BINDINGS___GFP_ACCOUNT = __GFP_ACCOUNT;
+const slab_flags_t BINDINGS_SLAB_HWCACHE_ALIGN = SLAB_HWCACHE_ALIGN;
+const slab_flags_t BINDINGS_SLAB_RECLAIM_ACCOUNT = SLAB_RECLAIM_ACCOUNT;
+const slab_flags_t BINDINGS_SLAB_ACCOUNT = SLAB_ACCOUNT;
+const slab_flags_t BINDINGS_SLAB_PANIC = SLAB_PANIC;
+const slab_flags_t BINDINGS_SLAB_TYPESAFE_BY_RCU = SLAB_TYPESAFE_BY_RCU;
+const slab_flags_t BINDINGS_SLAB_POISON = SLAB_POISON;
+const slab_flags_t BINDINGS_SLAB_RED_ZONE = SLAB_RED_ZONE;
+const int BINDINGS_DEFAULT_RATELIMIT_INTERVAL = DEFAULT_RATELIMIT_INTERVAL;
+const int BINDINGS_DEFAULT_RATELIMIT_BURST = DEFAULT_RATELIMIT_BURST;
+const long BINDINGS_MAX_SCHEDULE_TIMEOUT = MAX_SCHEDULE_TIMEOUT;
+const int BINDINGS_MIN_NICE = MIN_NICE;
+const int BINDINGS_MAX_NICE = MAX_NICE;
+const blk_status_t BINDINGS_BLK_STS_OK = BLK_STS_OK;
+const blk_status_t BINDINGS_BLK_STS_NOTSUPP = BLK_STS_NOTSUPP;
+const blk_status_t BINDINGS_BLK_STS_IOERR = BLK_STS_IOERR;
+const blk_status_t BINDINGS_BLK_STS_RESOURCE = BLK_STS_RESOURCE;
+const size_t BINDINGS_NLMSG_GOODSIZE = NLMSG_GOODSIZE;
@@ -0,0 +1,330 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Bitmaps.
+//!
+//! [`Bitmap`] is allocated on the heap and has an arbitrary number of bits, [`InlineBitmap`] is
+//! stored inline and has a multiple of the word size bits. Both have the same operations, which
+//! are implemented by the kernel's bitmap functions.
+//!
+//! C header: [`include/linux/bitmap.h`](../../../../include/linux/bitmap.h)
+
+use crate::{bindings, c_types, error::code::*, Result};
+use core::{convert::TryInto, ptr::NonNull};
+
+/// The number of bits in a word of a bitmap.
+pub const BITS_PER_LONG: usize = c_types::c_ulong::BITS as usize;
+
+/// A bitmap allocated on the heap.
+///
+/// All bits are initially clear.
+///
+/// # Invariants
+///
+/// `ptr` was returned by `bitmap_zalloc` for `nbits` bits.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::bitmap::Bitmap;
+///
+/// fn example() -> Result {
+///     // A block allocation map.
+///     let mut blocks = Bitmap::try_new(1024)?;
+///
+///     let first = blocks.find_first_zero().ok_or(ENOSPC)?;
+///     blocks.set(first);
+///
+///     // Allocate 8 contiguous blocks.
+///     let start = blocks.find_zero_area(0, 8).ok_or(ENOSPC)?;
+///     blocks.set_range(start, 8);
+///     assert_eq!(blocks.weight(), 9);
+///
+///     blocks.clear_range(start, 8);
+///     assert!(!blocks.test(start));
+///     Ok(())
+/// }
+/// ```
+pub struct Bitmap {
+    ptr: NonNull<c_types::c_ulong>,
+    nbits: usize,
+}
+
+// SAFETY: `Bitmap` owns its memory, which can be freed from any thread.
+unsafe impl Send for Bitmap {}
+
+// SAFETY: `Bitmap` can only be modified through a mutable reference.
+unsafe impl Sync for Bitmap {}
+
+impl Bitmap {
+    /// Allocates a new bitmap of `nbits` bits, all clear.
+    pub fn try_new(nbits: usize) -> Result<Self> {
+        let nbits_c: c_types::c_uint = nbits.try_into()?;
+        // SAFETY: There are no safety requirements for this FFI call.
+        let ptr = unsafe { bindings::bitmap_zalloc(nbits_c, bindings::GFP_KERNEL) };
+
+        // INVARIANT: `ptr` was returned by `bitmap_zalloc` for `nbits` bits.
+        Ok(Self {
+            ptr: NonNull::new(ptr).ok_or(ENOMEM)?,
+            nbits,
+        })
+    }
+
+    /// Returns the number of bits in the bitmap.
+    pub fn len(&self) -> usize {
+        self.nbits
+    }
+
+    fn words(&self) -> *const c_types::c_ulong {
+        self.ptr.as_ptr()
+    }
+
+    fn words_mut(&mut self) -> *mut c_types::c_ulong {
+        self.ptr.as_ptr()
+    }
+}
+
+impl Drop for Bitmap {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `ptr` was returned by `bitmap_zalloc`.
+        unsafe { bindings::bitmap_free(self.ptr.as_ptr()) };
+    }
+}
+
+/// A bitmap of `WORDS` words, stored inline.
+///
+/// It has `WORDS * BITS_PER_LONG` bits, all initially clear. It is useful for small bitmaps of a
+/// size known at compile time, e.g., embedded in other structures.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::bitmap::{InlineBitmap, BITS_PER_LONG};
+///
+/// fn example() {
+///     let mut minors = InlineBitmap::<1>::new();
+///     assert_eq!(minors.len(), BITS_PER_LONG);
+///
+///     minors.set(0);
+///     assert_eq!(minors.find_first_zero(), Some(1));
+/// }
+/// ```
+pub struct InlineBitmap<const WORDS: usize> {
+    words: [c_types::c_ulong; WORDS],
+}
+
+impl<const WORDS: usize> InlineBitmap<WORDS> {
+    /// Creates a new bitmap with all bits clear.
+    pub const fn new() -> Self {
+        Self { words: [0; WORDS] }
+    }
+
+    /// Returns the number of bits in the bitmap.
+    pub const fn len(&self) -> usize {
+        WORDS * BITS_PER_LONG
+    }
+
+    fn words(&self) -> *const c_types::c_ulong {
+        self.words.as_ptr()
+    }
+
+    fn words_mut(&mut self) -> *mut c_types::c_ulong {
+        self.words.as_mut_ptr()
+    }
+}
+
+impl<const WORDS: usize> Default for InlineBitmap<WORDS> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+macro_rules! impl_bitmap_ops {
+    ([$($gen:tt)*] $t:ty) => {
+        impl<$($gen)*> $t {
+            fn nbits(&self) -> c_types::c_uint {
+                // The length is checked on allocation (or is small for inline bitmaps).
+                self.len() as _
+            }
+
+            /// Returns the result of a find operation, which is the bitmap length if nothing
+            /// was found.
+            fn found(&self, index: c_types::c_ulong) -> Option<usize> {
+                let index = index as usize;
+                if index < self.len() {
+                    Some(index)
+                } else {
+                    None
+                }
+            }
+
+            /// Returns whether the bitmap has no bits.
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            /// Sets the given bit.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `bit` is out of bounds.
+            pub fn set(&mut self, bit: usize) {
+                assert!(bit < self.len());
+                // SAFETY: `bit` is within the bitmap, which is valid for writes.
+                unsafe { bindings::__set_bit(bit as _, self.words_mut()) };
+            }
+
+            /// Clears the given bit.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `bit` is out of bounds.
+            pub fn clear(&mut self, bit: usize) {
+                assert!(bit < self.len());
+                // SAFETY: `bit` is within the bitmap, which is valid for writes.
+                unsafe { bindings::__clear_bit(bit as _, self.words_mut()) };
+            }
+
+            /// Returns whether the given bit is set.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `bit` is out of bounds.
+            pub fn test(&self, bit: usize) -> bool {
+                assert!(bit < self.len());
+                // SAFETY: `bit` is within the bitmap, which is valid for reads.
+                unsafe { bindings::test_bit(bit as _, self.words()) }
+            }
+
+            /// Sets `len` bits starting at `start`.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the range is out of bounds.
+            pub fn set_range(&mut self, start: usize, len: usize) {
+                assert!(start <= self.len() && len <= self.len() - start);
+                // SAFETY: The range is within the bitmap, which is valid for writes.
+                unsafe { bindings::bitmap_set(self.words_mut(), start as _, len as _) };
+            }
+
+            /// Clears `len` bits starting at `start`.
+            ///
+            /// # Panics
+            ///
+            /// Panics if the range is out of bounds.
+            pub fn clear_range(&mut self, start: usize, len: usize) {
+                assert!(start <= self.len() && len <= self.len() - start);
+                // SAFETY: The range is within the bitmap, which is valid for writes.
+                unsafe { bindings::bitmap_clear(self.words_mut(), start as _, len as _) };
+            }
+
+            /// Sets all bits.
+            pub fn fill(&mut self) {
+                let nbits = self.nbits();
+                // SAFETY: The bitmap is valid for writes of `nbits` bits.
+                unsafe { bindings::bitmap_fill(self.words_mut(), nbits) };
+            }
+
+            /// Clears all bits.
+            pub fn zero(&mut self) {
+                let nbits = self.nbits();
+                // SAFETY: The bitmap is valid for writes of `nbits` bits.
+                unsafe { bindings::bitmap_zero(self.words_mut(), nbits) };
+            }
+
+            /// Returns the number of set bits.
+            pub fn weight(&self) -> usize {
+                // SAFETY: The bitmap is valid for reads of `nbits` bits.
+                unsafe { bindings::bitmap_weight(self.words(), self.nbits()) as _ }
+            }
+
+            /// Returns the index of the first set bit, if any.
+            pub fn find_first_bit(&self) -> Option<usize> {
+                // SAFETY: The bitmap is valid for reads of `len` bits.
+                self.found(unsafe { bindings::find_first_bit(self.words(), self.len() as _) })
+            }
+
+            /// Returns the index of the first clear bit, if any.
+            pub fn find_first_zero(&self) -> Option<usize> {
+                // SAFETY: The bitmap is valid for reads of `len` bits.
+                self.found(unsafe {
+                    bindings::find_first_zero_bit(self.words(), self.len() as _)
+                })
+            }
+
+            /// Returns the index of the first set bit at or after `start`, if any.
+            pub fn find_next_bit(&self, start: usize) -> Option<usize> {
+                // SAFETY: The bitmap is valid for reads of `len` bits; `find_next_bit` handles
+                // out of bounds starting bits.
+                self.found(unsafe {
+                    bindings::find_next_bit(self.words(), self.len() as _, start as _)
+                })
+            }
+
+            /// Returns the index of the first clear bit at or after `start`, if any.
+            pub fn find_next_zero(&self, start: usize) -> Option<usize> {
+                // SAFETY: The bitmap is valid for reads of `len` bits; `find_next_zero_bit`
+                // handles out of bounds starting bits.
+                self.found(unsafe {
+                    bindings::find_next_zero_bit(self.words(), self.len() as _, start as _)
+                })
+            }
+
+            /// Returns the start of the first range of `len` clear bits at or after `start`, if
+            /// any.
+            ///
+            /// The range is not set, [`Self::set_range`] does that.
+            pub fn find_zero_area(&self, start: usize, len: usize) -> Option<usize> {
+                // SAFETY: The bitmap is valid for reads of `len` bits.
+                let index = unsafe {
+                    bindings::bitmap_find_next_zero_area_off(
+                        self.words() as _,
+                        self.len() as _,
+                        start as _,
+                        len as _,
+                        0,
+                        0,
+                    )
+                };
+                let index = index as usize;
+                if index < self.len() && len <= self.len() - index {
+                    Some(index)
+                } else {
+                    None
+                }
+            }
+
+            /// Finds a clear region of `1 << order` bits, aligned to its size, sets it and
+            /// returns its start.
+            ///
+            /// Returns [`ENOMEM`] if there is no such region.
+            pub fn alloc_region(&mut self, order: u32) -> Result<usize> {
+                let nbits = self.nbits();
+                // SAFETY: The bitmap is valid for reads and writes of `nbits` bits.
+                let ret = unsafe {
+                    bindings::bitmap_find_free_region(self.words_mut(), nbits, order as _)
+                };
+                if ret < 0 {
+                    return Err(ENOMEM);
+                }
+                Ok(ret as _)
+            }
+
+            /// Clears a region of `1 << order` bits starting at `pos`, previously returned by
+            /// [`Self::alloc_region`].
+            ///
+            /// # Panics
+            ///
+            /// Panics if the region is out of bounds.
+            pub fn release_region(&mut self, pos: usize, order: u32) {
+                let size = 1usize.checked_shl(order).unwrap_or(usize::MAX);
+                assert!(pos <= self.len() && size <= self.len() - pos);
+                // SAFETY: The region is within the bitmap, which is valid for writes.
+                unsafe { bindings::bitmap_release_region(self.words_mut(), pos as _, order as _) };
+            }
+        }
+    };
+}
+
+impl_bitmap_ops!([] Bitmap);
+impl_bitmap_ops!([const WORDS: usize] InlineBitmap<WORDS>);
@@ -0,0 +1,8 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Block devices.
+//!
+//! C headers: [`include/linux/blkdev.h`](../../../../include/linux/blkdev.h) and
+//! [`include/linux/blk-mq.h`](../../../../include/linux/blk-mq.h)
+
+pub mod mq;
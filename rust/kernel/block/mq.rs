@@ -0,0 +1,919 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Multi-queue block devices.
+//!
+//! A driver implements [`Operations`] to process the requests of a disk, creates a [`TagSet`]
+//! that holds the requests in flight on each of its hardware queues, and adds one or more disks
+//! with [`GenDiskBuilder`].
+//!
+//! # Examples
+//!
+//! A disk that completes all requests without doing anything:
+//!
+//! ```
+//! # use kernel::prelude::*;
+//! use kernel::block::mq::{self, GenDisk, GenDiskBuilder, OwnedRequest, QueueStatus, TagSet};
+//! use kernel::numa::NumaNode;
+//! use kernel::ThisModule;
+//!
+//! struct NullDisk;
+//!
+//! impl mq::Operations for NullDisk {
+//!     kernel::declare_blk_mq_operations!();
+//!
+//!     type QueueData = ();
+//!
+//!     fn queue_rq(_: (), mut rq: OwnedRequest, _is_last: bool) -> QueueStatus {
+//!         rq.start();
+//!         rq.end(Ok(()));
+//!         QueueStatus::Queued
+//!     }
+//!
+//!     fn complete(rq: OwnedRequest) {
+//!         rq.end(Ok(()));
+//!     }
+//! }
+//!
+//! fn add_disk(module: &'static ThisModule) -> Result<GenDisk<NullDisk>> {
+//!     let tag_set = TagSet::try_new(1, 256, NumaNode::ANY)?;
+//!     GenDiskBuilder::new()
+//!         .capacity_sectors(1 << 21)
+//!         .logical_block_size(4096)?
+//!         .rotational(false)
+//!         .build(fmt!("rnullb0"), module, tag_set, ())
+//! }
+//! ```
+
+use crate::{
+    bindings, c_types,
+    error::{code::*, from_kernel_err_ptr, to_result},
+    numa::NumaNode,
+    static_lock_class,
+    str::CString,
+    sync::{Ref, UniqueRef},
+    types::PointerWrapper,
+    Opaque, Result, ThisModule, PAGE_SIZE,
+};
+use alloc::boxed::Box;
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::Deref,
+    ptr::{self, NonNull},
+};
+
+/// The operations of a multi-queue block device.
+pub trait Operations: Sized {
+    /// The methods to use to populate [`struct blk_mq_ops`].
+    const TO_USE: ToUse;
+
+    /// The data of a disk, made available to the request callbacks.
+    type QueueData: PointerWrapper + Send + Sync;
+
+    /// Queues a request for processing.
+    ///
+    /// The driver owns the request, which must be started with [`OwnedRequest::start`] and
+    /// eventually ended with [`OwnedRequest::end`] (possibly after this function returns, e.g.,
+    /// from an interrupt handler), which is also how errors are reported. `is_last` is `false` if
+    /// more requests follow, in which case the driver may delay notifying the device until
+    /// [`Operations::commit_rqs`] is called.
+    ///
+    /// Returning [`QueueStatus::Busy`] gives the request back to the block layer, which retries it
+    /// later.
+    fn queue_rq(
+        queue_data: <Self::QueueData as PointerWrapper>::Borrowed<'_>,
+        rq: OwnedRequest,
+        is_last: bool,
+    ) -> QueueStatus;
+
+    /// Notifies the device of the requests queued so far, when the last one queued with
+    /// [`Operations::queue_rq`] didn't have `is_last` set.
+    fn commit_rqs(_queue_data: <Self::QueueData as PointerWrapper>::Borrowed<'_>) {}
+
+    /// Completes a request, after the driver called [`OwnedRequest::complete`].
+    ///
+    /// It runs on the CPU that submitted the request (if possible), and must end it with
+    /// [`OwnedRequest::end`].
+    fn complete(rq: OwnedRequest);
+
+    /// Polls for completed requests, and returns the number of requests completed.
+    fn poll(_queue_data: <Self::QueueData as PointerWrapper>::Borrowed<'_>) -> Result<u32> {
+        Err(EINVAL)
+    }
+}
+
+/// The result of [`Operations::queue_rq`].
+pub enum QueueStatus {
+    /// The driver took the request.
+    Queued,
+
+    /// The device is busy, so the request is given back to the block layer to be retried later.
+    Busy(OwnedRequest),
+}
+
+/// Represents which optional fields of [`struct blk_mq_ops`] should be populated with pointers.
+pub struct ToUse {
+    /// The `commit_rqs` field of [`struct blk_mq_ops`].
+    pub commit_rqs: bool,
+
+    /// The `poll` field of [`struct blk_mq_ops`].
+    pub poll: bool,
+}
+
+/// A constant version where all values are to set to `false`, that is, all supported fields will
+/// be set to null pointers.
+pub const USE_NONE: ToUse = ToUse {
+    commit_rqs: false,
+    poll: false,
+};
+
+/// Defines the [`Operations::TO_USE`] field based on a list of fields to be populated.
+#[macro_export]
+macro_rules! declare_blk_mq_operations {
+    () => {
+        const TO_USE: $crate::block::mq::ToUse = $crate::block::mq::USE_NONE;
+    };
+    ($($i:ident),+) => {
+        #[allow(clippy::needless_update)]
+        const TO_USE: kernel::block::mq::ToUse =
+            $crate::block::mq::ToUse {
+                $($i: true),+ ,
+                ..$crate::block::mq::USE_NONE
+            };
+    };
+}
+
+struct OperationsVtable<T: Operations>(PhantomData<T>);
+
+impl<T: Operations> OperationsVtable<T> {
+    /// # Safety
+    ///
+    /// `hctx` must be a valid hardware context of a disk created by [`GenDiskBuilder::build`].
+    unsafe fn queue_data<'a>(
+        hctx: *mut bindings::blk_mq_hw_ctx,
+    ) -> <T::QueueData as PointerWrapper>::Borrowed<'a> {
+        // SAFETY: The safety requirements guarantee that `queuedata` was initialised by
+        // `GenDiskBuilder::build` with a value returned by `T::QueueData::into_pointer`, which is
+        // only released after the queue is cleaned up.
+        unsafe { T::QueueData::borrow((*(*hctx).queue).queuedata) }
+    }
+
+    unsafe extern "C" fn queue_rq_callback(
+        hctx: *mut bindings::blk_mq_hw_ctx,
+        bd: *const bindings::blk_mq_queue_data,
+    ) -> bindings::blk_status_t {
+        // SAFETY: The block layer calls this with a valid hardware context, and hands the
+        // request over to the driver until it is ended (or given back below).
+        let (queue_data, rq, is_last) = unsafe {
+            (
+                Self::queue_data(hctx),
+                OwnedRequest::from_raw((*bd).rq),
+                (*bd).last,
+            )
+        };
+
+        match T::queue_rq(queue_data, rq, is_last) {
+            QueueStatus::Queued => bindings::BLK_STS_OK,
+            QueueStatus::Busy(rq) => {
+                // The block layer requeues the request, so the driver must not end it.
+                rq.into_raw();
+                bindings::BLK_STS_RESOURCE
+            }
+        }
+    }
+
+    unsafe extern "C" fn commit_rqs_callback(hctx: *mut bindings::blk_mq_hw_ctx) {
+        // SAFETY: The block layer calls this with a valid hardware context.
+        T::commit_rqs(unsafe { Self::queue_data(hctx) });
+    }
+
+    unsafe extern "C" fn complete_callback(rq: *mut bindings::request) {
+        // SAFETY: The block layer calls this with a valid request, which the driver handed over
+        // with `OwnedRequest::complete`.
+        T::complete(unsafe { OwnedRequest::from_raw(rq) });
+    }
+
+    unsafe extern "C" fn poll_callback(
+        hctx: *mut bindings::blk_mq_hw_ctx,
+        _iob: *mut bindings::io_comp_batch,
+    ) -> c_types::c_int {
+        // SAFETY: The block layer calls this with a valid hardware context.
+        match T::poll(unsafe { Self::queue_data(hctx) }) {
+            Ok(n) => n.try_into().unwrap_or(c_types::c_int::MAX),
+            Err(e) => e.to_kernel_errno(),
+        }
+    }
+
+    const VTABLE: bindings::blk_mq_ops = bindings::blk_mq_ops {
+        queue_rq: Some(Self::queue_rq_callback),
+        commit_rqs: if T::TO_USE.commit_rqs {
+            Some(Self::commit_rqs_callback)
+        } else {
+            None
+        },
+        queue_rqs: None,
+        get_budget: None,
+        put_budget: None,
+        set_rq_budget_token: None,
+        get_rq_budget_token: None,
+        timeout: None,
+        poll: if T::TO_USE.poll {
+            Some(Self::poll_callback)
+        } else {
+            None
+        },
+        complete: Some(Self::complete_callback),
+        init_hctx: None,
+        exit_hctx: None,
+        init_request: None,
+        exit_request: None,
+        cleanup_rq: None,
+        busy: None,
+        map_queues: None,
+        #[cfg(CONFIG_BLK_DEBUG_FS)]
+        show_rq: None,
+    };
+}
+
+/// A set of tags, which identify the requests in flight on the hardware queues of one or more
+/// disks.
+///
+/// # Invariants
+///
+/// `inner` was initialised with the operations of `T`, and allocated with `blk_mq_alloc_tag_set`
+/// if its `tags` are not null.
+pub struct TagSet<T: Operations> {
+    inner: Opaque<bindings::blk_mq_tag_set>,
+    _p: PhantomData<T>,
+}
+
+// SAFETY: The tag set functions are safe to call from any thread.
+unsafe impl<T: Operations> Send for TagSet<T> {}
+
+// SAFETY: The tag set is only modified by the block layer, with its own locking.
+unsafe impl<T: Operations> Sync for TagSet<T> {}
+
+impl<T: Operations> TagSet<T> {
+    /// Creates a new tag set for `nr_hw_queues` hardware queues of `queue_depth` requests each,
+    /// with its memory on the given NUMA node.
+    pub fn try_new(nr_hw_queues: u32, queue_depth: u32, node: NumaNode) -> Result<Ref<Self>> {
+        let set = UniqueRef::try_new(Self {
+            inner: Opaque::new(bindings::blk_mq_tag_set::default()),
+            _p: PhantomData,
+        })?;
+
+        let ptr = set.inner.get();
+
+        // SAFETY: `ptr` is valid, and not shared yet.
+        unsafe {
+            (*ptr).ops = &OperationsVtable::<T>::VTABLE;
+            (*ptr).nr_hw_queues = nr_hw_queues;
+            (*ptr).queue_depth = queue_depth;
+            (*ptr).numa_node = node.as_raw();
+            (*ptr).cmd_size = 0;
+            (*ptr).flags = bindings::BLK_MQ_F_SHOULD_MERGE;
+        }
+
+        // SAFETY: `ptr` is valid, and it doesn't move since it is on the heap. If the allocation
+        // fails, `tags` is left null, so the tag set isn't freed when dropped.
+        to_result(|| unsafe { bindings::blk_mq_alloc_tag_set(ptr) })?;
+
+        // INVARIANT: The tag set was initialised and allocated above.
+        Ok(set.into())
+    }
+}
+
+impl<T: Operations> Drop for TagSet<T> {
+    fn drop(&mut self) {
+        let ptr = self.inner.get();
+
+        // SAFETY: By the type invariants, the tag set was allocated if `tags` is not null. The
+        // disks that use it hold a reference to it, so they are all gone.
+        unsafe {
+            if !(*ptr).tags.is_null() {
+                bindings::blk_mq_free_tag_set(ptr);
+            }
+        }
+    }
+}
+
+/// A builder for [`GenDisk`].
+pub struct GenDiskBuilder {
+    capacity_sectors: u64,
+    logical_block_size: u32,
+    physical_block_size: u32,
+    rotational: bool,
+}
+
+impl Default for GenDiskBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GenDiskBuilder {
+    /// Creates a builder for a disk with no capacity, 512-byte blocks, and that is not
+    /// rotational.
+    pub fn new() -> Self {
+        Self {
+            capacity_sectors: 0,
+            logical_block_size: bindings::SECTOR_SIZE,
+            physical_block_size: bindings::SECTOR_SIZE,
+            rotational: false,
+        }
+    }
+
+    /// Sets the capacity of the disk, in 512-byte sectors.
+    pub fn capacity_sectors(mut self, capacity: u64) -> Self {
+        self.capacity_sectors = capacity;
+        self
+    }
+
+    fn validate_block_size(size: u32) -> Result {
+        if !(bindings::SECTOR_SIZE..=PAGE_SIZE as u32).contains(&size) || !size.is_power_of_two() {
+            return Err(EINVAL);
+        }
+        Ok(())
+    }
+
+    /// Sets the smallest unit the disk can address, in bytes.
+    ///
+    /// It must be a power of two between 512 and the page size.
+    pub fn logical_block_size(mut self, size: u32) -> Result<Self> {
+        Self::validate_block_size(size)?;
+        self.logical_block_size = size;
+        if self.physical_block_size < size {
+            self.physical_block_size = size;
+        }
+        Ok(self)
+    }
+
+    /// Sets the smallest unit the disk can write without a read-modify-write, in bytes.
+    ///
+    /// It must be a power of two between the logical block size and the page size.
+    pub fn physical_block_size(mut self, size: u32) -> Result<Self> {
+        Self::validate_block_size(size)?;
+        if size < self.logical_block_size {
+            return Err(EINVAL);
+        }
+        self.physical_block_size = size;
+        Ok(self)
+    }
+
+    /// Sets whether the disk is rotational, which makes the block layer favour sequential
+    /// accesses.
+    pub fn rotational(mut self, rotational: bool) -> Self {
+        self.rotational = rotational;
+        self
+    }
+
+    /// Creates the disk with the given name (shown in `/dev` and `/sys/block`), whose requests
+    /// are processed by `T` with `queue_data`, and adds it to the system.
+    ///
+    /// `module` is kept loaded while the disk is open.
+    pub fn build<T: Operations>(
+        self,
+        name: fmt::Arguments<'_>,
+        module: &'static ThisModule,
+        tag_set: Ref<TagSet<T>>,
+        queue_data: T::QueueData,
+    ) -> Result<GenDisk<T>> {
+        let name = CString::try_from_fmt(name)?;
+        let fops = Box::try_new(bindings::block_device_operations {
+            owner: module.0,
+            ..GenDisk::<T>::FOPS
+        })?;
+
+        // SAFETY: The tag set is allocated by its type invariants, and the lock class is static.
+        let disk = from_kernel_err_ptr(unsafe {
+            bindings::__blk_mq_alloc_disk(
+                tag_set.inner.get(),
+                ptr::null_mut(),
+                static_lock_class!().as_ptr(),
+            )
+        })?;
+
+        // INVARIANT: The disk was allocated above, and is not added yet. Its `queuedata` is set
+        // below before it is added.
+        let mut gd = GenDisk {
+            // SAFETY: `__blk_mq_alloc_disk` succeeded, so `disk` is not null.
+            disk: unsafe { NonNull::new_unchecked(disk) },
+            added: false,
+            _tag_set: tag_set,
+        };
+
+        // SAFETY: The disk is valid and not added yet, so nothing else uses it.
+        unsafe {
+            let disk = gd.disk.as_ptr();
+            let queue = (*disk).queue;
+
+            let len = name.len().min((*disk).disk_name.len() - 1);
+            ptr::copy_nonoverlapping(name.as_char_ptr(), (*disk).disk_name.as_mut_ptr(), len);
+            (*disk).disk_name[len] = 0;
+            (*disk).fops = &*fops;
+
+            bindings::set_capacity(disk, self.capacity_sectors);
+            bindings::blk_queue_logical_block_size(queue, self.logical_block_size);
+            bindings::blk_queue_physical_block_size(queue, self.physical_block_size);
+            if !self.rotational {
+                bindings::blk_queue_flag_set(bindings::QUEUE_FLAG_NONROT, queue);
+            }
+
+            (*queue).queuedata = queue_data.into_pointer() as _;
+        }
+
+        // SAFETY: The disk was set up above.
+        to_result(|| unsafe {
+            bindings::device_add_disk(ptr::null_mut(), gd.disk.as_ptr(), ptr::null_mut())
+        })?;
+        gd.added = true;
+
+        // The operations are leaked on purpose: the block layer uses them until the last opener
+        // of the disk is gone (e.g., `owner` in `blkdev_put`), which may be after the `GenDisk`
+        // is dropped. Their `owner` keeps the module loaded until then.
+        Box::leak(fops);
+        Ok(gd)
+    }
+}
+
+/// A disk of a multi-queue block device, created with [`GenDiskBuilder`].
+///
+/// The disk is removed when the instance is dropped.
+///
+/// # Invariants
+///
+/// `disk` was allocated by `__blk_mq_alloc_disk` with the tag set, and its `queuedata` was set
+/// from a `T::QueueData` (unless the allocation is still being set up). `added` is `true` if it
+/// was added with `device_add_disk`.
+pub struct GenDisk<T: Operations> {
+    disk: NonNull<bindings::gendisk>,
+    added: bool,
+    _tag_set: Ref<TagSet<T>>,
+}
+
+// SAFETY: The disk functions are safe to call from any thread.
+unsafe impl<T: Operations> Send for GenDisk<T> {}
+
+// SAFETY: The disk functions are safe to call concurrently.
+unsafe impl<T: Operations> Sync for GenDisk<T> {}
+
+impl<T: Operations> GenDisk<T> {
+    const FOPS: bindings::block_device_operations = bindings::block_device_operations {
+        submit_bio: None,
+        open: None,
+        release: None,
+        rw_page: None,
+        ioctl: None,
+        compat_ioctl: None,
+        check_events: None,
+        unlock_native_capacity: None,
+        getgeo: None,
+        set_read_only: None,
+        swap_slot_free_notify: None,
+        report_zones: None,
+        devnode: None,
+        owner: ptr::null_mut(),
+        pr_ops: ptr::null(),
+        alternative_gpt_sector: None,
+    };
+
+    /// Sets the capacity of the disk, in 512-byte sectors, e.g., after the medium changed.
+    pub fn set_capacity_sectors(&self, capacity: u64) {
+        // SAFETY: By the type invariants, the disk is valid.
+        unsafe { bindings::set_capacity_and_notify(self.disk.as_ptr(), capacity) };
+    }
+
+    /// Returns the capacity of the disk, in 512-byte sectors.
+    pub fn capacity_sectors(&self) -> u64 {
+        // SAFETY: By the type invariants, the disk is valid.
+        unsafe { bindings::get_capacity(self.disk.as_ptr()) }
+    }
+}
+
+impl<T: Operations> Drop for GenDisk<T> {
+    fn drop(&mut self) {
+        let disk = self.disk.as_ptr();
+
+        // SAFETY: By the type invariants, the disk is valid, and it was added if `added` is true.
+        // Once the queue is cleaned up, no callbacks use `queuedata` anymore.
+        unsafe {
+            if self.added {
+                bindings::del_gendisk(disk);
+            }
+            let queue_data = (*(*disk).queue).queuedata;
+            bindings::blk_cleanup_disk(disk);
+            if !queue_data.is_null() {
+                T::QueueData::from_pointer(queue_data);
+            }
+        }
+    }
+}
+
+/// The operation of a [`Request`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Op {
+    /// Reads data from the disk.
+    Read,
+
+    /// Writes data to the disk.
+    Write,
+
+    /// Flushes the volatile cache of the disk.
+    Flush,
+
+    /// Discards sectors.
+    Discard,
+
+    /// Writes zeroes to sectors.
+    WriteZeroes,
+
+    /// Another operation, with the given `REQ_OP_*` value.
+    Other(u32),
+}
+
+/// A request to a block device.
+///
+/// # Invariants
+///
+/// The request is valid, and it belongs to a disk whose driver is processing it.
+#[repr(transparent)]
+pub struct Request(Opaque<bindings::request>);
+
+impl Request {
+    /// Creates a reference to a request from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is a valid request that the driver is processing, and
+    /// that it is not ended while the returned reference is alive.
+    pub unsafe fn from_ptr<'a>(ptr: *mut bindings::request) -> &'a Self {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `Request` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns a raw pointer to the underlying `struct request`.
+    pub fn as_ptr(&self) -> *mut bindings::request {
+        self.0.get()
+    }
+
+    /// Returns the operation of the request.
+    pub fn op(&self) -> Op {
+        // SAFETY: By the type invariants, the request is valid.
+        match unsafe { bindings::req_op(self.as_ptr()) } {
+            bindings::req_opf_REQ_OP_READ => Op::Read,
+            bindings::req_opf_REQ_OP_WRITE => Op::Write,
+            bindings::req_opf_REQ_OP_FLUSH => Op::Flush,
+            bindings::req_opf_REQ_OP_DISCARD => Op::Discard,
+            bindings::req_opf_REQ_OP_WRITE_ZEROES => Op::WriteZeroes,
+            op => Op::Other(op),
+        }
+    }
+
+    /// Returns the first sector of the request, in 512-byte units.
+    pub fn sector(&self) -> u64 {
+        // SAFETY: By the type invariants, the request is valid.
+        unsafe { bindings::blk_rq_pos(self.as_ptr()) }
+    }
+
+    /// Returns the number of bytes left in the request.
+    pub fn bytes(&self) -> u32 {
+        // SAFETY: By the type invariants, the request is valid.
+        unsafe { bindings::blk_rq_bytes(self.as_ptr()) }
+    }
+
+    /// Returns an iterator over the bios of the request, which hold its data.
+    pub fn bios(&self) -> BioIter<'_> {
+        BioIter {
+            // SAFETY: By the type invariants, the request is valid.
+            bio: unsafe { (*self.as_ptr()).bio },
+            _p: PhantomData,
+        }
+    }
+}
+
+/// A request owned by the driver, from [`Operations::queue_rq`] until it is ended.
+///
+/// Ending (or completing) the request consumes it, since the block layer may reuse it for another
+/// I/O afterwards. If it is dropped without being ended, it is ended with an I/O error.
+///
+/// # Invariants
+///
+/// `ptr` is a valid request that was handed over to the driver and hasn't been ended.
+pub struct OwnedRequest {
+    ptr: NonNull<bindings::request>,
+}
+
+// SAFETY: The request can be ended from any thread, e.g., from an interrupt handler.
+unsafe impl Send for OwnedRequest {}
+
+// SAFETY: Shared references only give access to a `Request`, which only reads the request.
+unsafe impl Sync for OwnedRequest {}
+
+impl OwnedRequest {
+    /// Takes ownership of a request.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be a valid request that the driver is processing and that isn't owned by
+    /// anything else, e.g., one returned by [`OwnedRequest::into_raw`].
+    pub unsafe fn from_raw(ptr: *mut bindings::request) -> Self {
+        // INVARIANT: The safety requirements guarantee that the request is valid and that it is
+        // owned by the driver.
+        Self {
+            // SAFETY: The safety requirements guarantee that `ptr` is valid, so it isn't null.
+            ptr: unsafe { NonNull::new_unchecked(ptr) },
+        }
+    }
+
+    /// Releases ownership of the request and returns a pointer to it, e.g., to keep it in a
+    /// structure shared with the device.
+    ///
+    /// It must be converted back with [`OwnedRequest::from_raw`] to be ended.
+    pub fn into_raw(self) -> *mut bindings::request {
+        let ptr = self.ptr.as_ptr();
+        mem::forget(self);
+        ptr
+    }
+
+    /// Returns an iterator over the segments of all the bios of the request, into which the data
+    /// of a read is copied.
+    ///
+    /// It fails with [`EINVAL`] if the request isn't a read, since the data of other requests
+    /// (e.g., the page cache pages of a write) must not be modified.
+    pub fn segments_mut(&mut self) -> Result<SegmentIterMut<'_>> {
+        if self.op() != Op::Read {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: By the type invariants, the request is valid, and so are its bios.
+        let (bio, iter) = unsafe {
+            let bio = (*self.ptr.as_ptr()).bio;
+            if bio.is_null() {
+                (bio, bindings::bvec_iter::default())
+            } else {
+                (bio, (*bio).bi_iter)
+            }
+        };
+
+        Ok(SegmentIterMut {
+            bio,
+            iter,
+            _p: PhantomData,
+        })
+    }
+
+    /// Marks the request as started, which must be done before the device processes it.
+    pub fn start(&mut self) {
+        // SAFETY: By the type invariants, the request is valid and owned by the driver.
+        unsafe { bindings::blk_mq_start_request(self.ptr.as_ptr()) };
+    }
+
+    /// Ends the request with the given status.
+    pub fn end(self, status: Result) {
+        let status = match status {
+            Ok(()) => bindings::BLK_STS_OK,
+            Err(e) if e == EOPNOTSUPP => bindings::BLK_STS_NOTSUPP,
+            Err(_) => bindings::BLK_STS_IOERR,
+        };
+
+        // SAFETY: By the type invariants, the request is valid and hasn't been ended. `into_raw`
+        // consumes `self`, so it isn't used afterwards.
+        unsafe { bindings::blk_mq_end_request(self.into_raw(), status) };
+    }
+
+    /// Completes the request, which calls [`Operations::complete`] on the CPU that submitted it,
+    /// e.g., from the interrupt handler of the device.
+    pub fn complete(self) {
+        // SAFETY: By the type invariants, the request is valid and hasn't been ended. Its
+        // ownership is handed over to `Operations::complete` by `complete_callback`.
+        unsafe { bindings::blk_mq_complete_request(self.into_raw()) };
+    }
+}
+
+impl Deref for OwnedRequest {
+    type Target = Request;
+
+    fn deref(&self) -> &Request {
+        // SAFETY: By the type invariants, the request is valid, and it cannot be ended while
+        // `self` is borrowed.
+        unsafe { Request::from_ptr(self.ptr.as_ptr()) }
+    }
+}
+
+impl Drop for OwnedRequest {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, the request is valid and hasn't been ended.
+        unsafe { bindings::blk_mq_end_request(self.ptr.as_ptr(), bindings::BLK_STS_IOERR) };
+    }
+}
+
+/// An iterator over the bios of a [`Request`], returned by [`Request::bios`].
+pub struct BioIter<'a> {
+    bio: *mut bindings::bio,
+    _p: PhantomData<&'a Request>,
+}
+
+impl<'a> Iterator for BioIter<'a> {
+    type Item = &'a Bio;
+
+    fn next(&mut self) -> Option<&'a Bio> {
+        let bio = NonNull::new(self.bio)?;
+
+        // SAFETY: The bios of a request are valid while the request is being processed.
+        unsafe {
+            self.bio = (*bio.as_ptr()).bi_next;
+            Some(Bio::from_ptr(bio.as_ptr()))
+        }
+    }
+}
+
+/// A block I/O, a range of sectors and the memory segments that hold its data.
+///
+/// # Invariants
+///
+/// The bio is valid, and its data is not modified while it is borrowed.
+#[repr(transparent)]
+pub struct Bio(Opaque<bindings::bio>);
+
+impl Bio {
+    /// Creates a reference to a bio from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid for the lifetime of the returned reference.
+    pub unsafe fn from_ptr<'a>(ptr: *mut bindings::bio) -> &'a Self {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `Bio` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns the first sector of the bio, in 512-byte units.
+    pub fn sector(&self) -> u64 {
+        // SAFETY: By the type invariants, the bio is valid.
+        unsafe { (*self.0.get()).bi_iter.bi_sector }
+    }
+
+    /// Returns the number of bytes of the bio.
+    pub fn size(&self) -> u32 {
+        // SAFETY: By the type invariants, the bio is valid.
+        unsafe { (*self.0.get()).bi_iter.bi_size }
+    }
+
+    /// Returns an iterator over the memory segments of the bio, each within a single page.
+    pub fn segments(&self) -> SegmentIter<'_> {
+        SegmentIter {
+            bio: self,
+            // SAFETY: By the type invariants, the bio is valid.
+            iter: unsafe { (*self.0.get()).bi_iter },
+        }
+    }
+}
+
+/// An iterator over the segments of a [`Bio`], returned by [`Bio::segments`].
+pub struct SegmentIter<'a> {
+    bio: &'a Bio,
+    iter: bindings::bvec_iter,
+}
+
+impl<'a> Iterator for SegmentIter<'a> {
+    type Item = Segment<'a>;
+
+    fn next(&mut self) -> Option<Segment<'a>> {
+        if self.iter.bi_size == 0 {
+            return None;
+        }
+
+        let bio = self.bio.0.get();
+
+        // SAFETY: By the type invariants, the bio is valid, and `iter` is within it since it has
+        // bytes left.
+        let bvec = unsafe { bindings::bio_iter_iovec(bio, self.iter) };
+
+        // SAFETY: As above, and the length of `bvec` is within the bytes left.
+        unsafe { bindings::bio_advance_iter_single(bio, &mut self.iter, bvec.bv_len) };
+
+        Some(Segment {
+            bvec,
+            _p: PhantomData,
+        })
+    }
+}
+
+/// A segment of a [`Bio`]: a range of memory within a single page.
+pub struct Segment<'a> {
+    bvec: bindings::bio_vec,
+    _p: PhantomData<&'a Bio>,
+}
+
+impl Segment<'_> {
+    /// Returns the page that holds the segment.
+    pub fn page(&self) -> *mut bindings::page {
+        self.bvec.bv_page
+    }
+
+    /// Returns the offset of the segment in its page.
+    pub fn offset(&self) -> usize {
+        self.bvec.bv_offset as _
+    }
+
+    /// Returns the length of the segment in bytes.
+    pub fn len(&self) -> usize {
+        self.bvec.bv_len as _
+    }
+
+    /// Returns whether the segment is empty.
+    pub fn is_empty(&self) -> bool {
+        self.bvec.bv_len == 0
+    }
+
+    /// Copies the segment into `dest`, which must be at least as long as the segment.
+    pub fn copy_to(&self, dest: &mut [u8]) -> Result {
+        if dest.len() < self.len() {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: The page of a segment is valid while the bio is borrowed, and the segment is
+        // within it.
+        unsafe {
+            let src = bindings::kmap_local_page(self.page()) as *const u8;
+            ptr::copy_nonoverlapping(src.add(self.offset()), dest.as_mut_ptr(), self.len());
+            bindings::kunmap_local(src as _);
+        }
+        Ok(())
+    }
+}
+
+/// An iterator over the segments of all the bios of a read request, returned by
+/// [`OwnedRequest::segments_mut`].
+pub struct SegmentIterMut<'a> {
+    bio: *mut bindings::bio,
+    iter: bindings::bvec_iter,
+    _p: PhantomData<&'a mut OwnedRequest>,
+}
+
+impl<'a> Iterator for SegmentIterMut<'a> {
+    type Item = SegmentMut<'a>;
+
+    fn next(&mut self) -> Option<SegmentMut<'a>> {
+        loop {
+            let bio = NonNull::new(self.bio)?.as_ptr();
+            if self.iter.bi_size != 0 {
+                // SAFETY: The bios of a request are valid while the request is being processed,
+                // and `iter` is within `bio` since it has bytes left.
+                let bvec = unsafe { bindings::bio_iter_iovec(bio, self.iter) };
+
+                // SAFETY: As above, and the length of `bvec` is within the bytes left.
+                unsafe { bindings::bio_advance_iter_single(bio, &mut self.iter, bvec.bv_len) };
+
+                return Some(SegmentMut {
+                    seg: Segment {
+                        bvec,
+                        _p: PhantomData,
+                    },
+                });
+            }
+
+            // SAFETY: The bios of a request are valid while the request is being processed.
+            unsafe {
+                self.bio = (*bio).bi_next;
+                if !self.bio.is_null() {
+                    self.iter = (*self.bio).bi_iter;
+                }
+            }
+        }
+    }
+}
+
+/// A segment of a read request, into which the driver copies the data that was read.
+pub struct SegmentMut<'a> {
+    seg: Segment<'a>,
+}
+
+impl<'a> Deref for SegmentMut<'a> {
+    type Target = Segment<'a>;
+
+    fn deref(&self) -> &Segment<'a> {
+        &self.seg
+    }
+}
+
+impl SegmentMut<'_> {
+    /// Copies `src`, which must be at least as long as the segment, into the segment.
+    pub fn copy_from(&mut self, src: &[u8]) -> Result {
+        if src.len() < self.len() {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: The page of a segment is valid while the request is borrowed, and the segment
+        // is within it. The request is a read that is mutably borrowed, so nothing else accesses
+        // its data.
+        unsafe {
+            let dest = bindings::kmap_local_page(self.page()) as *mut u8;
+            ptr::copy_nonoverlapping(src.as_ptr(), dest.add(self.offset()), self.len());
+            bindings::kunmap_local(dest as _);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Warnings, bugs and stack dumps.
+//!
+//! C header: [`include/asm-generic/bug.h`](../../../../include/asm-generic/bug.h)
+
+#[cfg(not(testlib))]
+use crate::bindings;
+use crate::str::CStr;
+use core::fmt;
+
+/// Dumps the stack of the current task to the kernel log.
+///
+/// Equivalent to the kernel's `dump_stack`.
+pub fn dump_stack() {
+    // SAFETY: FFI call without safety requirements.
+    #[cfg(not(testlib))]
+    unsafe {
+        bindings::dump_stack()
+    };
+}
+
+/// Reports a warning through the kernel's `__warn`.
+///
+/// The message (if any) is printed first. `__warn` then prints the location of the warning and a
+/// backtrace, panics if `panic_on_warn` is set, and taints the kernel with `TAINT_WARN`.
+///
+/// Public but hidden since it should only be used from public macros.
+#[doc(hidden)]
+#[cold]
+#[inline(never)]
+#[cfg_attr(any(testlib, not(CONFIG_BUG)), allow(unused_variables))]
+pub fn warn_slowpath(file: &'static CStr, line: u32, args: Option<fmt::Arguments<'_>>) {
+    crate::pr_warn!("------------[ cut here ]------------\n");
+    if let Some(args) = args {
+        crate::pr_warn!("{}", args);
+    }
+
+    // SAFETY: `file` is a valid C string. `__warn` accepts a null caller, registers and arguments,
+    // the message having been printed above.
+    #[cfg(all(CONFIG_BUG, not(testlib)))]
+    unsafe {
+        bindings::__warn(
+            file.as_char_ptr(),
+            line as _,
+            core::ptr::null_mut(),
+            bindings::TAINT_WARN,
+            core::ptr::null_mut(),
+            core::ptr::null_mut(),
+        )
+    };
+}
+
+/// Reports a bug the way the kernel's `BUG` does and does not return.
+///
+/// Public but hidden since it should only be used from public macros.
+#[doc(hidden)]
+#[cold]
+#[inline(never)]
+pub fn bug_slowpath(file: &'static str, line: u32) -> ! {
+    crate::pr_crit!("kernel BUG at {}:{}!\n", file, line);
+
+    // SAFETY: FFI call.
+    #[cfg(not(testlib))]
+    unsafe {
+        bindings::BUG()
+    };
+
+    // Bindgen currently does not recognize `__noreturn` so `BUG` returns `()` instead of `!`.
+    // https://github.com/rust-lang/rust-bindgen/issues/2094
+    #[allow(clippy::empty_loop)]
+    loop {}
+}
+
+/// Warns (with a backtrace and `TAINT_WARN`) if the given condition is true.
+///
+/// Evaluates to the value of the condition, so it can be used in `if` statements.
+///
+/// Equivalent to the kernel's `WARN_ON` and `WARN` macros; the latter is used when a message is
+/// given. The message mimics the interface of [`std::print!`].
+///
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// fn example(len: usize, max: usize) -> Result {
+///     if warn_on!(len > max) {
+///         return Err(EINVAL);
+///     }
+///
+///     warn_on!(len == 0, "unexpected empty buffer\n");
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! warn_on (
+    ($cond:expr $(,)?) => ({
+        let cond: bool = $cond;
+        if cond {
+            $crate::bug::warn_slowpath($crate::c_str!(core::file!()), core::line!(), None);
+        }
+        cond
+    });
+
+    ($cond:expr, $($arg:tt)+) => ({
+        let cond: bool = $cond;
+        if cond {
+            $crate::bug::warn_slowpath(
+                $crate::c_str!(core::file!()),
+                core::line!(),
+                Some(core::format_args!($($arg)+)),
+            );
+        }
+        cond
+    });
+);
+
+/// Warns (with a backtrace and `TAINT_WARN`) the first time the given condition is true.
+///
+/// Evaluates to the value of the condition, so it can be used in `if` statements.
+///
+/// Equivalent to the kernel's `WARN_ON_ONCE` and `WARN_ONCE` macros; the latter is used when a
+/// message is given. Like in C, the state is kept in the `.data.once` section, so it is reset by
+/// writing to `<debugfs>/clear_warn_once`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// fn example(value: u32) {
+///     warn_once!(value > 10, "value out of range: {}\n", value);
+/// }
+/// ```
+#[macro_export]
+macro_rules! warn_once (
+    ($cond:expr $(, $($arg:tt)+)?) => ({
+        #[link_section = ".data.once"]
+        static WARNED: core::sync::atomic::AtomicBool = core::sync::atomic::AtomicBool::new(false);
+
+        let cond: bool = $cond;
+        if cond && !WARNED.swap(true, core::sync::atomic::Ordering::Relaxed) {
+            $crate::warn_on!(true $(, $($arg)+)?);
+        }
+        cond
+    });
+);
+
+/// Reports a bug and stops the current task if the given condition is true.
+///
+/// Equivalent to the kernel's `BUG_ON` macro. As in C, prefer [`warn_on`] and recovering whenever
+/// possible.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// fn example(ptr: *const u8) {
+///     bug_on!(ptr.is_null());
+/// }
+/// ```
+#[macro_export]
+macro_rules! bug_on (
+    ($cond:expr $(,)?) => ({
+        if $cond {
+            $crate::bug::bug_slowpath(core::file!(), core::line!());
+        }
+    });
+);
@@ -10,11 +10,13 @@
 
 use alloc::boxed::Box;
 use core::convert::TryInto;
+use core::fmt;
 use core::marker::PhantomPinned;
 use core::pin::Pin;
 
 use crate::bindings;
 use crate::c_types;
+use crate::class;
 use crate::error::{code::*, Error, Result};
 use crate::file;
 use crate::str::CStr;
@@ -80,6 +82,7 @@ struct RegistrationInner<const N: usize> {
     dev: bindings::dev_t,
     used: usize,
     cdevs: [Option<Cdev>; N],
+    nodes: [Option<class::Device>; N],
     _pin: PhantomPinned,
 }
 
@@ -153,10 +156,12 @@ impl<const N: usize> Registration<{ N }> {
                 return Err(Error::from_kernel_errno(res));
             }
             const NONE: Option<Cdev> = None;
+            const NO_NODE: Option<class::Device> = None;
             this.inner = Some(RegistrationInner {
                 dev,
                 used: 0,
                 cdevs: [NONE; N],
+                nodes: [NO_NODE; N],
                 _pin: PhantomPinned,
             });
         }
@@ -175,6 +180,61 @@ impl<const N: usize> Registration<{ N }> {
         inner.used += 1;
         Ok(())
     }
+
+    /// Registers a character device and creates its node in `/dev`, as a device of `class`
+    /// with the given name and permissions.
+    ///
+    /// The node is removed when the registration is dropped.
+    pub fn register_with_node<T: file::Operations<OpenData = ()>>(
+        mut self: Pin<&mut Self>,
+        class: &class::Class,
+        node: class::DevNode,
+        name: fmt::Arguments<'_>,
+    ) -> Result {
+        self.as_mut().register::<T>()?;
+
+        // SAFETY: We never move out of `this`.
+        let this = unsafe { self.get_unchecked_mut() };
+        let inner = this.inner.as_mut().unwrap();
+        let index = inner.used - 1;
+        let devt = inner.dev + index as bindings::dev_t;
+        match class.create_device(None, devt, node, name) {
+            Ok(dev) => {
+                inner.nodes[index] = Some(dev);
+                Ok(())
+            }
+            Err(e) => {
+                inner.cdevs[index].take();
+                inner.used -= 1;
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the number of devices registered so far.
+    pub fn len(&self) -> usize {
+        self.inner.as_ref().map_or(0, |inner| inner.used)
+    }
+
+    /// Returns whether no devices were registered yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the major number of the region, once the first device is registered.
+    pub fn major(&self) -> Option<u32> {
+        Some(self.inner.as_ref()?.dev >> bindings::MINORBITS)
+    }
+
+    /// Returns the device number of the `index`-th registered device, e.g., to create its
+    /// `/dev` node.
+    pub fn dev_t(&self, index: usize) -> Option<bindings::dev_t> {
+        let inner = self.inner.as_ref()?;
+        if index >= inner.used {
+            return None;
+        }
+        Some(inner.dev + index as bindings::dev_t)
+    }
 }
 
 impl<const N: usize> file::OpenAdapter<()> for Registration<{ N }> {
@@ -192,9 +252,10 @@ unsafe impl<const N: usize> Sync for Registration<{ N }> {}
 impl<const N: usize> Drop for Registration<{ N }> {
     fn drop(&mut self) {
         if let Some(inner) = self.inner.as_mut() {
-            // Replicate kernel C behaviour: drop [`Cdev`]s before calling
+            // Replicate kernel C behaviour: remove the nodes, then drop [`Cdev`]s before calling
             // [`bindings::unregister_chrdev_region`].
             for i in 0..inner.used {
+                inner.nodes[i].take();
                 inner.cdevs[i].take();
             }
             // SAFETY: [`self.inner`] is Some, so [`inner.dev`] was previously
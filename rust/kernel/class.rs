@@ -0,0 +1,265 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Device classes.
+//!
+//! A [`Class`] groups devices by what they do rather than by how they are connected, e.g.,
+//! `/sys/class/input`. Devices created in a class that have a device number get a node in `/dev`
+//! (created by devtmpfs or udev), whose permissions are given by [`DevNode`].
+//!
+//! C header: [`include/linux/device/class.h`](../../../../include/linux/device/class.h)
+
+use crate::{
+    bindings, c_str,
+    device::RawDevice,
+    error::{from_kernel_err_ptr, Error, Result},
+    str::{CStr, CString},
+    sync::Ref,
+};
+use alloc::boxed::Box;
+use core::{fmt, ptr};
+
+struct ClassInner {
+    ptr: *mut bindings::class,
+}
+
+impl Drop for ClassInner {
+    fn drop(&mut self) {
+        // SAFETY: `ptr` was returned by `class_create`, and all devices of the class are gone
+        // since they hold references to it.
+        unsafe { bindings::class_destroy(self.ptr) };
+    }
+}
+
+/// A device class.
+///
+/// Clones refer to the same class, which is destroyed when the last clone and the last device of
+/// the class are dropped.
+///
+/// # Invariants
+///
+/// `inner.ptr` is a class returned by `class_create`, which is destroyed when `inner` is dropped.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::{c_str, chrdev, class::{Class, DevNode}, file};
+///
+/// struct Example;
+///
+/// impl file::Operations for Example {
+///     kernel::declare_file_operations!();
+///
+///     fn open(_: &(), _: &file::File) -> Result {
+///         Ok(())
+///     }
+/// }
+///
+/// // Creates `/sys/class/example` and `/dev/example0`, readable and writable by group 44.
+/// fn register(reg: Pin<&mut chrdev::Registration<1>>) -> Result<Class> {
+///     let class = Class::new(c_str!("example"))?;
+///     let node = DevNode::new(0o660).owner(0, 44);
+///     reg.register_with_node::<Example>(&class, node, fmt!("example{}", 0))?;
+///     Ok(class)
+/// }
+/// ```
+#[derive(Clone)]
+pub struct Class {
+    inner: Ref<ClassInner>,
+}
+
+// SAFETY: The class functions are safe to call from any thread.
+unsafe impl Send for ClassInner {}
+
+// SAFETY: The class isn't modified after it is created.
+unsafe impl Sync for ClassInner {}
+
+impl Class {
+    /// Creates a new class, shown in `/sys/class/<name>`.
+    pub fn new(name: &'static CStr) -> Result<Self> {
+        let key = crate::static_lock_class!();
+        // SAFETY: `name` and `key` are static, so they outlive the class.
+        let ptr = from_kernel_err_ptr(unsafe {
+            bindings::__class_create(ptr::null_mut(), name.as_char_ptr(), key.as_ptr())
+        })?;
+
+        // INVARIANT: `ptr` was returned by `class_create` above.
+        Ok(Self {
+            inner: Ref::try_new(ClassInner { ptr })?,
+        })
+    }
+
+    /// Returns a raw pointer to the underlying `struct class`.
+    pub fn as_ptr(&self) -> *mut bindings::class {
+        self.inner.ptr
+    }
+
+    /// Creates a device of the class, with the given parent and name.
+    ///
+    /// If `devt` is non-zero, a node for it is created in `/dev` with the permissions of `node`.
+    /// The device is removed when the returned value is dropped.
+    pub fn create_device(
+        &self,
+        parent: Option<&dyn RawDevice>,
+        devt: bindings::dev_t,
+        node: DevNode,
+        name: fmt::Arguments<'_>,
+    ) -> Result<Device> {
+        let name = CString::try_from_fmt(name)?;
+        let inner = Box::into_raw(Box::try_new(DeviceInner {
+            // SAFETY: `struct device` is a C struct, for which zero is a valid value before
+            // `device_initialize` is called.
+            dev: unsafe { core::mem::zeroed() },
+            node,
+            _class: self.clone(),
+        })?);
+
+        // SAFETY: `inner` is valid. Once the device is initialised, it is freed by
+        // `release_callback` when its last reference is dropped.
+        let dev = unsafe {
+            let dev = ptr::addr_of_mut!((*inner).dev);
+            bindings::device_initialize(dev);
+            (*dev).class = self.as_ptr();
+            (*dev).type_ = &DEVICE_TYPE;
+            (*dev).parent = parent.map_or(ptr::null_mut(), |p| p.raw_device());
+            (*dev).devt = devt;
+            (*dev).release = Some(release_callback);
+            dev
+        };
+
+        // SAFETY: `dev` was initialised above, and the format string consumes the single string
+        // argument.
+        let mut ret =
+            unsafe { bindings::dev_set_name(dev, c_str!("%s").as_char_ptr(), name.as_char_ptr()) };
+        if ret == 0 {
+            // SAFETY: `dev` was initialised and named above.
+            ret = unsafe { bindings::device_add(dev) };
+        }
+        if ret != 0 {
+            // SAFETY: `dev` was initialised above; dropping its only reference frees it.
+            unsafe { bindings::put_device(dev) };
+            return Err(Error::from_kernel_errno(ret));
+        }
+
+        // INVARIANT: `dev` was added above, and its reference is owned by the returned value.
+        Ok(Device { ptr: dev })
+    }
+}
+
+/// The permissions of the node of a device in `/dev`.
+#[derive(Clone, Copy, Debug)]
+pub struct DevNode {
+    mode: u16,
+    uid: u32,
+    gid: u32,
+}
+
+impl DevNode {
+    /// Creates node permissions with the given mode, e.g., `0o600`, owned by root.
+    pub const fn new(mode: u16) -> Self {
+        Self {
+            mode,
+            uid: 0,
+            gid: 0,
+        }
+    }
+
+    /// Sets the owner of the node, as user and group ids in the initial namespace.
+    pub const fn owner(mut self, uid: u32, gid: u32) -> Self {
+        self.uid = uid;
+        self.gid = gid;
+        self
+    }
+}
+
+impl Default for DevNode {
+    fn default() -> Self {
+        Self::new(0o600)
+    }
+}
+
+/// A device created with [`Class::create_device`].
+#[repr(C)]
+struct DeviceInner {
+    dev: bindings::device,
+    node: DevNode,
+    _class: Class,
+}
+
+const DEVICE_TYPE: bindings::device_type = bindings::device_type {
+    name: ptr::null(),
+    groups: ptr::null_mut(),
+    uevent: None,
+    devnode: Some(devnode_callback),
+    release: None,
+    pm: ptr::null(),
+};
+
+unsafe extern "C" fn devnode_callback(
+    dev: *mut bindings::device,
+    mode: *mut bindings::umode_t,
+    uid: *mut bindings::kuid_t,
+    gid: *mut bindings::kgid_t,
+) -> *mut crate::c_types::c_char {
+    // SAFETY: Only devices created by `Class::create_device` have `DEVICE_TYPE` as their type.
+    let inner = unsafe { &*crate::container_of!(dev, DeviceInner, dev) };
+    // SAFETY: The pointers are either null or valid for writes, as provided by the driver core.
+    unsafe {
+        if !mode.is_null() {
+            *mode = inner.node.mode;
+        }
+        if !uid.is_null() {
+            (*uid).val = inner.node.uid;
+        }
+        if !gid.is_null() {
+            (*gid).val = inner.node.gid;
+        }
+    }
+
+    // Use the name of the device as the name of the node.
+    ptr::null_mut()
+}
+
+unsafe extern "C" fn release_callback(dev: *mut bindings::device) {
+    let inner = crate::container_of!(dev, DeviceInner, dev) as *mut DeviceInner;
+    // SAFETY: `inner` was allocated with a box in `Class::create_device`, and this is called once
+    // the last reference to the device is dropped.
+    drop(unsafe { Box::from_raw(inner) });
+}
+
+/// A device of a [`Class`].
+///
+/// # Invariants
+///
+/// `ptr` is a device added by [`Class::create_device`], and `self` owns a reference to it.
+pub struct Device {
+    ptr: *mut bindings::device,
+}
+
+// SAFETY: The device functions are safe to call from any thread.
+unsafe impl Send for Device {}
+
+// SAFETY: `Device` has no methods that modify the device.
+unsafe impl Sync for Device {}
+
+impl Device {
+    /// Returns the device number of the device, or zero if it has no node.
+    pub fn devt(&self) -> bindings::dev_t {
+        // SAFETY: By the type invariants, `ptr` is valid.
+        unsafe { (*self.ptr).devt }
+    }
+}
+
+// SAFETY: The device returned by `raw_device` is the one for which we hold a reference.
+unsafe impl RawDevice for Device {
+    fn raw_device(&self) -> *mut bindings::device {
+        self.ptr
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, the device was added and `self` owns a reference.
+        unsafe { bindings::device_unregister(self.ptr) };
+    }
+}
@@ -2,18 +2,47 @@
 
 //! Common clock framework.
 //!
+//! Drivers get the clocks of their devices with [`RawDevice::clk_get`], and enable them for as
+//! long as they use the device with [`Clk::prepare_enable`].
+//!
 //! C header: [`include/linux/clk.h`](../../../../include/linux/clk.h)
 
-use crate::{bindings, error::Result, to_result};
-use core::mem::ManuallyDrop;
+use crate::{
+    bindings,
+    error::{Error, Result},
+    to_result,
+};
+use core::{mem::ManuallyDrop, ops::Deref};
 
 /// Represents `struct clk *`.
 ///
 /// # Invariants
 ///
 /// The pointer is valid.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::{c_str, clk::EnabledClk, device::RawDevice};
+///
+/// fn enable_bus_clock(dev: &dyn RawDevice) -> Result<EnabledClk> {
+///     let clk = dev.clk_get(Some(c_str!("bus")))?;
+///     clk.set_rate(clk.round_rate(48_000_000)?)?;
+///     clk.prepare_enable()
+/// }
+/// ```
+///
+/// [`RawDevice::clk_get`]: crate::device::RawDevice::clk_get
 pub struct Clk(*mut bindings::clk);
 
+// SAFETY: The clock API is safe to call from any thread, and the clock can be released from any
+// thread.
+unsafe impl Send for Clk {}
+
+// SAFETY: The clock API serialises concurrent calls on the same clock internally.
+unsafe impl Sync for Clk {}
+
 impl Clk {
     /// Creates new clock structure from a raw pointer.
     ///
@@ -30,6 +59,26 @@ impl Clk {
         unsafe { bindings::clk_get_rate(self.0) as usize }
     }
 
+    /// Sets the rate of the clock, in Hz.
+    ///
+    /// The clock may not be able to run at exactly that rate; see [`Clk::round_rate`]. This
+    /// function should not be called in atomic context.
+    pub fn set_rate(&self, rate: usize) -> Result {
+        // SAFETY: The pointer is valid by the type invariant.
+        to_result(|| unsafe { bindings::clk_set_rate(self.0, rate as _) })
+    }
+
+    /// Returns the rate, in Hz, the clock would run at if [`Clk::set_rate`] was called with
+    /// `rate`.
+    pub fn round_rate(&self, rate: usize) -> Result<usize> {
+        // SAFETY: The pointer is valid by the type invariant.
+        let ret = unsafe { bindings::clk_round_rate(self.0, rate as _) };
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret as _));
+        }
+        Ok(ret as _)
+    }
+
     /// Prepares and enables the underlying hardware clock.
     ///
     /// This function should not be called in atomic context.
@@ -67,6 +116,14 @@ impl EnabledClk {
     }
 }
 
+impl Deref for EnabledClk {
+    type Target = Clk;
+
+    fn deref(&self) -> &Clk {
+        &self.0
+    }
+}
+
 impl Drop for EnabledClk {
     fn drop(&mut self) {
         // SAFETY: The pointer is valid by the type invariant.
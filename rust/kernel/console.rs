@@ -0,0 +1,341 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Console drivers.
+//!
+//! C header: [`include/linux/console.h`](../../../../include/linux/console.h)
+//!
+//! Reference: <https://www.kernel.org/doc/html/latest/driver-api/console.html>
+
+use crate::{
+    bindings, c_types,
+    error::{code::*, from_kernel_result},
+    str::CStr,
+    to_result,
+    types::PointerWrapper,
+    Result,
+};
+use alloc::boxed::Box;
+use core::{cell::UnsafeCell, marker::PhantomData, pin::Pin};
+
+/// Corresponds to the kernel's `struct console`.
+///
+/// Implement this trait to write a console driver. Messages printed to the kernel log are passed
+/// to [`Console::write`] once the console is registered and enabled.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// # use kernel::{c_str, console};
+/// struct Example;
+///
+/// impl console::Console for Example {
+///     kernel::declare_console_operations!();
+///
+///     fn write(_data: (), _index: i16, s: &[u8]) {
+///         for _b in s {
+///             // Send `_b` to the hardware.
+///         }
+///     }
+/// }
+///
+/// fn example() -> Result<Pin<Box<console::Registration<Example>>>> {
+///     console::Registration::new_pinned(
+///         c_str!("ttyEX"),
+///         -1,
+///         console::flags::PRINTBUFFER,
+///         (),
+///     )
+/// }
+/// ```
+pub trait Console {
+    /// The methods to use to populate [`struct console`].
+    const TO_USE: ToUse;
+
+    /// The pointer type that will be used to hold user-defined data type.
+    type Data: PointerWrapper + Send + Sync = ();
+
+    /// Writes the given message to the console.
+    ///
+    /// This is called with the console lock held and may be called in atomic context (e.g., from
+    /// an interrupt handler or while the kernel is panicking), so it must not sleep.
+    fn write(data: <Self::Data as PointerWrapper>::Borrowed<'_>, index: i16, s: &[u8]);
+
+    /// Sets up the console, for example, parsing `options` given on the kernel command line via
+    /// `console=<name><index>,<options>`.
+    ///
+    /// Returning an error prevents the console from being enabled.
+    fn setup(
+        _data: <Self::Data as PointerWrapper>::Borrowed<'_>,
+        _index: i16,
+        _options: Option<&CStr>,
+    ) -> Result {
+        Ok(())
+    }
+
+    /// Checks whether the console matches the given `console=` name and index.
+    ///
+    /// This allows a console to be selected by a name other than its own, e.g., `uart8250`.
+    /// Returning an error makes the kernel fall back to matching by name and index.
+    fn match_name(
+        _data: <Self::Data as PointerWrapper>::Borrowed<'_>,
+        _name: &CStr,
+        _index: i32,
+        _options: Option<&CStr>,
+    ) -> Result {
+        Err(ENODEV)
+    }
+}
+
+/// Contains the flags that can be passed when registering a console.
+pub mod flags {
+    use crate::bindings;
+
+    /// Prints the messages that were already in the log buffer when the console is registered.
+    pub const PRINTBUFFER: i16 = bindings::CON_PRINTBUFFER as _;
+
+    /// The console is enabled.
+    pub const ENABLED: i16 = bindings::CON_ENABLED as _;
+
+    /// The console is an early (boot) console, which is unregistered once a real one is.
+    pub const BOOT: i16 = bindings::CON_BOOT as _;
+
+    /// The console can be called while a CPU is coming online, before it is marked as such.
+    pub const ANYTIME: i16 = bindings::CON_ANYTIME as _;
+
+    /// The console is a braille device.
+    pub const BRL: i16 = bindings::CON_BRL as _;
+}
+
+/// A registration of a console driver.
+///
+/// # Invariants
+///
+/// `console.data` holds a pointer returned by [`PointerWrapper::into_pointer`] when `registered`
+/// is `true`.
+pub struct Registration<T: Console> {
+    console: UnsafeCell<bindings::console>,
+    registered: bool,
+    _p: PhantomData<T>,
+}
+
+impl<T: Console> Registration<T> {
+    /// Creates a new [`Registration`] but does not register it yet.
+    ///
+    /// It is allowed to move.
+    pub fn new() -> Self {
+        // INVARIANT: `registered` is `false`.
+        Self {
+            console: UnsafeCell::new(bindings::console::default()),
+            registered: false,
+            _p: PhantomData,
+        }
+    }
+
+    /// Registers a console driver.
+    ///
+    /// Returns a pinned heap-allocated representation of the registration.
+    pub fn new_pinned(
+        name: &CStr,
+        index: i16,
+        flags: i16,
+        data: T::Data,
+    ) -> Result<Pin<Box<Self>>> {
+        let mut reg = Pin::from(Box::try_new(Self::new())?);
+        reg.as_mut().register(name, index, flags, data)?;
+        Ok(reg)
+    }
+
+    /// Registers a console driver with the rest of the kernel.
+    ///
+    /// `name` must be shorter than 16 bytes. `index` is the console index, or -1 to let the
+    /// `console=` command line option choose it. `flags` is a combination of the constants in
+    /// [`flags`].
+    ///
+    /// It must be pinned because the memory block that represents the registration is linked
+    /// into the kernel's list of consoles.
+    pub fn register(
+        self: Pin<&mut Self>,
+        name: &CStr,
+        index: i16,
+        flags: i16,
+        data: T::Data,
+    ) -> Result {
+        // SAFETY: We never move out of `this`.
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.registered {
+            return Err(EINVAL);
+        }
+
+        let console = this.console.get_mut();
+        let name = name.as_bytes_with_nul();
+        if name.len() > console.name.len() {
+            return Err(EINVAL);
+        }
+
+        for (dst, src) in console.name.iter_mut().zip(name) {
+            *dst = *src as _;
+        }
+        console.write = Some(Self::write_callback);
+        console.setup = if T::TO_USE.setup {
+            Some(Self::setup_callback)
+        } else {
+            None
+        };
+        console.match_ = if T::TO_USE.match_name {
+            Some(Self::match_callback)
+        } else {
+            None
+        };
+        console.index = index;
+        console.flags = flags;
+        console.data = data.into_pointer() as _;
+
+        // SAFETY: `console` is fully initialised above and, since `this` is pinned, remains valid
+        // until it is unregistered in `drop`.
+        unsafe { bindings::register_console(this.console.get()) };
+
+        // INVARIANT: `data` was stored in `console.data` above. A console that no `console=`
+        // option matches is left disabled but remains registered, like in C; see `is_enabled`.
+        this.registered = true;
+        Ok(())
+    }
+
+    /// Returns whether the console has been enabled by the kernel.
+    pub fn is_enabled(&self) -> bool {
+        // SAFETY: `flags` is only modified by the console core while holding the console lock; a
+        // racy read is acceptable for a hint.
+        let current = unsafe { core::ptr::addr_of!((*self.console.get()).flags).read_volatile() };
+        current & flags::ENABLED != 0
+    }
+
+    /// Returns the data of the console.
+    ///
+    /// # Safety
+    ///
+    /// `con` must be the console embedded in a registered [`Registration`].
+    unsafe fn data<'a>(con: *mut bindings::console) -> <T::Data as PointerWrapper>::Borrowed<'a> {
+        // SAFETY: By the safety requirements and the type invariants, `data` holds a pointer
+        // returned by `into_pointer`, which is only reclaimed after unregistering the console.
+        unsafe { T::Data::borrow((*con).data) }
+    }
+
+    unsafe extern "C" fn write_callback(
+        con: *mut bindings::console,
+        s: *const c_types::c_char,
+        count: c_types::c_uint,
+    ) {
+        // SAFETY: The console core only calls this function on registered consoles.
+        let data = unsafe { Self::data(con) };
+
+        // SAFETY: `con` is valid, as above.
+        let index = unsafe { (*con).index };
+
+        // SAFETY: The console core guarantees that `s` is valid for reads of `count` bytes.
+        let s = unsafe { core::slice::from_raw_parts(s as *const u8, count as _) };
+        T::write(data, index, s);
+    }
+
+    unsafe extern "C" fn setup_callback(
+        con: *mut bindings::console,
+        options: *mut c_types::c_char,
+    ) -> c_types::c_int {
+        from_kernel_result! {
+            // SAFETY: The console core only calls this function on registered consoles.
+            let data = unsafe { Self::data(con) };
+
+            // SAFETY: `con` is valid, as above.
+            let index = unsafe { (*con).index };
+
+            // SAFETY: `options`, when not null, is a valid `NUL`-terminated string that remains
+            // valid for the duration of the call.
+            let options = (!options.is_null()).then(|| unsafe { CStr::from_char_ptr(options) });
+            T::setup(data, index, options)?;
+            Ok(0)
+        }
+    }
+
+    unsafe extern "C" fn match_callback(
+        con: *mut bindings::console,
+        name: *mut c_types::c_char,
+        index: c_types::c_int,
+        options: *mut c_types::c_char,
+    ) -> c_types::c_int {
+        from_kernel_result! {
+            // SAFETY: The console core only calls this function on registered consoles.
+            let data = unsafe { Self::data(con) };
+
+            // SAFETY: `name` is a valid `NUL`-terminated string that remains valid for the
+            // duration of the call.
+            let name = unsafe { CStr::from_char_ptr(name) };
+
+            // SAFETY: `options`, when not null, is a valid `NUL`-terminated string that remains
+            // valid for the duration of the call.
+            let options = (!options.is_null()).then(|| unsafe { CStr::from_char_ptr(options) });
+            T::match_name(data, name, index, options)?;
+            Ok(0)
+        }
+    }
+}
+
+impl<T: Console> Default for Registration<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Represents which callbacks of [`struct console`] should be populated with pointers.
+pub struct ToUse {
+    /// The `setup` field of [`struct console`].
+    pub setup: bool,
+
+    /// The `match` field of [`struct console`].
+    pub match_name: bool,
+}
+
+/// A constant version where all values are to set to `false`, that is, all supported fields will
+/// be set to null pointers.
+pub const USE_NONE: ToUse = ToUse {
+    setup: false,
+    match_name: false,
+};
+
+/// Defines the [`Console::TO_USE`] field based on a list of fields to be populated.
+#[macro_export]
+macro_rules! declare_console_operations {
+    () => {
+        const TO_USE: $crate::console::ToUse = $crate::console::USE_NONE;
+    };
+    ($($i:ident),+) => {
+        #[allow(clippy::needless_update)]
+        const TO_USE: kernel::console::ToUse =
+            $crate::console::ToUse {
+                $($i: true),+ ,
+                ..$crate::console::USE_NONE
+            };
+    };
+}
+
+// SAFETY: `Registration` does not expose any of its state across threads.
+unsafe impl<T: Console> Sync for Registration<T> {}
+
+// SAFETY: `Registration` is not restricted to a single thread, its `T::Data` is also `Send` so it
+// may be moved to different threads.
+#[allow(clippy::non_send_fields_in_send_ty)]
+unsafe impl<T: Console> Send for Registration<T> {}
+
+impl<T: Console> Drop for Registration<T> {
+    /// Removes the registration from the kernel if it has completed successfully before.
+    fn drop(&mut self) {
+        if self.registered {
+            // SAFETY: The console was registered in `register`. `unregister_console` returns an
+            // error only if the console isn't registered anymore (e.g., a boot console replaced
+            // by a real one), in which case it isn't used either.
+            let _ = to_result(|| unsafe { bindings::unregister_console(self.console.get()) });
+
+            // SAFETY: By the type invariants, `data` was returned by `into_pointer`, and the
+            // console core won't call into the driver anymore.
+            unsafe { T::Data::from_pointer(self.console.get_mut().data) };
+        }
+    }
+}
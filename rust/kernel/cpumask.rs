@@ -0,0 +1,330 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! CPU masks.
+//!
+//! [`CpuMask`] is a set of CPUs, e.g., the CPUs that are online or the CPUs a task may run on. The
+//! kernel's masks are borrowed with [`CpuMask::possible`] and similar functions, and new masks are
+//! allocated with [`CpuMaskVar`].
+//!
+//! CPUs are numbered from zero to [`nr_cpu_ids`] (exclusive).
+//!
+//! C header: [`include/linux/cpumask.h`](../../../../include/linux/cpumask.h)
+
+use crate::{bindings, c_types, error::code::*, Opaque, Result};
+use core::{
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+};
+
+/// Returns the number of CPU ids, that is, one more than the highest possible CPU number.
+///
+/// It is fixed during boot.
+pub fn nr_cpu_ids() -> u32 {
+    // SAFETY: `nr_cpu_ids` is only written during boot.
+    unsafe { bindings::nr_cpu_ids }
+}
+
+/// A set of CPUs.
+///
+/// Wraps the kernel's `struct cpumask`. It is only used by reference: either to one of the
+/// kernel's masks or to one owned by a [`CpuMaskVar`].
+///
+/// # Invariants
+///
+/// The mask has at least [`nr_cpu_ids`] bits.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::cpumask::{CpuMask, CpuMaskVar};
+///
+/// fn even_online_cpus() -> Result<CpuMaskVar> {
+///     let mut mask = CpuMaskVar::try_new()?;
+///     for cpu in CpuMask::online().iter().filter(|cpu| cpu % 2 == 0) {
+///         mask.set(cpu);
+///     }
+///     Ok(mask)
+/// }
+/// ```
+#[repr(transparent)]
+pub struct CpuMask(Opaque<bindings::cpumask>);
+
+// SAFETY: A `CpuMask` can only be modified through a mutable reference, so it can be used from
+// any thread. The kernel's masks (e.g., the online one) are updated concurrently with atomic
+// bit operations, which is what C code expects as well.
+unsafe impl Send for CpuMask {}
+
+// SAFETY: See above.
+unsafe impl Sync for CpuMask {}
+
+impl CpuMask {
+    /// Creates a reference to a mask from a raw pointer.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a valid `struct cpumask` that remains valid for the lifetime `'a`.
+    /// While the reference is alive, the mask must only be modified with atomic bit operations.
+    pub unsafe fn from_raw<'a>(ptr: *const bindings::cpumask) -> &'a Self {
+        // SAFETY: `CpuMask` is transparent, so the cast is ok. The safety requirements guarantee
+        // the validity of the reference.
+        unsafe { &*(ptr as *const Self) }
+    }
+
+    /// Creates a mutable reference to a mask from a raw pointer.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a valid `struct cpumask` that remains valid for the lifetime `'a` and
+    /// isn't accessed by anyone else during that time.
+    pub unsafe fn from_raw_mut<'a>(ptr: *mut bindings::cpumask) -> &'a mut Self {
+        // SAFETY: `CpuMask` is transparent, so the cast is ok. The safety requirements guarantee
+        // the validity of the reference.
+        unsafe { &mut *(ptr as *mut Self) }
+    }
+
+    /// Returns a raw pointer to the underlying `struct cpumask`.
+    pub fn as_ptr(&self) -> *mut bindings::cpumask {
+        self.0.get()
+    }
+
+    /// Returns the mask of the CPUs that may ever be brought online.
+    ///
+    /// It is fixed during boot.
+    pub fn possible() -> &'static Self {
+        // SAFETY: `cpu_possible_mask` is always valid.
+        unsafe { Self::from_raw(bindings::cpu_possible_mask) }
+    }
+
+    /// Returns the mask of the CPUs that are online, that is, available for scheduling.
+    ///
+    /// CPUs may be brought online or offline concurrently, so the mask may be stale by the time
+    /// the caller acts on it.
+    pub fn online() -> &'static Self {
+        // SAFETY: `cpu_online_mask` is always valid.
+        unsafe { Self::from_raw(bindings::cpu_online_mask) }
+    }
+
+    /// Returns the mask of the CPUs that are present in the system, though maybe not online.
+    pub fn present() -> &'static Self {
+        // SAFETY: `cpu_present_mask` is always valid.
+        unsafe { Self::from_raw(bindings::cpu_present_mask) }
+    }
+
+    /// Returns a mask with only the given CPU.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cpu` is not a valid CPU number.
+    pub fn of(cpu: u32) -> &'static Self {
+        assert!(cpu < nr_cpu_ids());
+        // SAFETY: `cpu` was checked above, so `cpumask_of` returns a static mask.
+        unsafe { Self::from_raw(bindings::cpumask_of(cpu)) }
+    }
+
+    /// Adds the given CPU to the mask.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cpu` is not a valid CPU number.
+    pub fn set(&mut self, cpu: u32) {
+        assert!(cpu < nr_cpu_ids());
+        // SAFETY: `cpu` is within the mask by the type invariants, and the mask is valid for
+        // writes.
+        unsafe { bindings::__cpumask_set_cpu(cpu as _, self.as_ptr()) };
+    }
+
+    /// Removes the given CPU from the mask.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cpu` is not a valid CPU number.
+    pub fn clear(&mut self, cpu: u32) {
+        assert!(cpu < nr_cpu_ids());
+        // SAFETY: `cpu` is within the mask by the type invariants, and the mask is valid for
+        // writes.
+        unsafe { bindings::__cpumask_clear_cpu(cpu as _, self.as_ptr()) };
+    }
+
+    /// Returns whether the given CPU is in the mask.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cpu` is not a valid CPU number.
+    pub fn test(&self, cpu: u32) -> bool {
+        assert!(cpu < nr_cpu_ids());
+        // SAFETY: `cpu` is within the mask by the type invariants, and the mask is valid for
+        // reads.
+        unsafe { bindings::cpumask_test_cpu(cpu as _, self.as_ptr()) }
+    }
+
+    /// Adds all CPUs to the mask.
+    pub fn set_all(&mut self) {
+        // SAFETY: The mask is valid for writes.
+        unsafe { bindings::cpumask_setall(self.as_ptr()) };
+    }
+
+    /// Removes all CPUs from the mask.
+    pub fn clear_all(&mut self) {
+        // SAFETY: The mask is valid for writes.
+        unsafe { bindings::cpumask_clear(self.as_ptr()) };
+    }
+
+    /// Replaces the CPUs of the mask with those of `other`.
+    pub fn copy_from(&mut self, other: &CpuMask) {
+        // SAFETY: Both masks are valid, and `self` is valid for writes. They can't overlap since
+        // `self` is a mutable reference.
+        unsafe { bindings::cpumask_copy(self.as_ptr(), other.as_ptr()) };
+    }
+
+    /// Removes the CPUs that are not in `other` from the mask.
+    pub fn and(&mut self, other: &CpuMask) {
+        // SAFETY: Both masks are valid, and `self` is valid for writes.
+        unsafe { bindings::cpumask_and(self.as_ptr(), self.as_ptr(), other.as_ptr()) };
+    }
+
+    /// Adds the CPUs of `other` to the mask.
+    pub fn or(&mut self, other: &CpuMask) {
+        // SAFETY: Both masks are valid, and `self` is valid for writes.
+        unsafe { bindings::cpumask_or(self.as_ptr(), self.as_ptr(), other.as_ptr()) };
+    }
+
+    /// Returns whether the mask and `other` have CPUs in common.
+    pub fn intersects(&self, other: &CpuMask) -> bool {
+        // SAFETY: Both masks are valid for reads.
+        unsafe { bindings::cpumask_intersects(self.as_ptr(), other.as_ptr()) }
+    }
+
+    /// Returns the number of CPUs in the mask.
+    pub fn weight(&self) -> u32 {
+        // SAFETY: The mask is valid for reads.
+        unsafe { bindings::cpumask_weight(self.as_ptr()) }
+    }
+
+    /// Returns whether the mask has no CPUs.
+    pub fn is_empty(&self) -> bool {
+        // SAFETY: The mask is valid for reads.
+        unsafe { bindings::cpumask_empty(self.as_ptr()) }
+    }
+
+    /// Returns the lowest CPU in the mask, if any.
+    pub fn first(&self) -> Option<u32> {
+        self.next(-1)
+    }
+
+    /// Returns the lowest CPU in the mask after `prev`, if any.
+    fn next(&self, prev: c_types::c_int) -> Option<u32> {
+        // SAFETY: The mask is valid for reads, and `cpumask_next` accepts -1 to start from the
+        // first CPU.
+        let cpu = unsafe { bindings::cpumask_next(prev, self.as_ptr()) };
+        if cpu < nr_cpu_ids() {
+            Some(cpu)
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over the CPUs of the mask, in increasing order.
+    ///
+    /// Equivalent to the kernel's `for_each_cpu`.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            mask: self,
+            prev: -1,
+        }
+    }
+}
+
+/// An iterator over the CPUs of a [`CpuMask`], returned by [`CpuMask::iter`].
+pub struct Iter<'a> {
+    mask: &'a CpuMask,
+    prev: c_types::c_int,
+}
+
+impl Iterator for Iter<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        let cpu = self.mask.next(self.prev)?;
+        self.prev = cpu as _;
+        Some(cpu)
+    }
+}
+
+impl<'a> IntoIterator for &'a CpuMask {
+    type Item = u32;
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// A [`CpuMask`] allocated on the heap.
+///
+/// The mask is initially empty. It is the equivalent of the kernel's `cpumask_var_t`, which is
+/// also allocated on the heap on systems with many CPUs.
+///
+/// # Invariants
+///
+/// `ptr` was allocated by `krealloc` with the size of a whole [`CpuMask`].
+pub struct CpuMaskVar {
+    ptr: NonNull<CpuMask>,
+}
+
+// SAFETY: `CpuMaskVar` owns its memory, which can be freed from any thread.
+unsafe impl Send for CpuMaskVar {}
+
+// SAFETY: `CpuMaskVar` can only be modified through a mutable reference.
+unsafe impl Sync for CpuMaskVar {}
+
+impl CpuMaskVar {
+    /// Allocates a new empty mask.
+    pub fn try_new() -> Result<Self> {
+        // `cpumask_size` only covers `nr_cpu_ids` bits with `CONFIG_CPUMASK_OFFSTACK`, but the
+        // mask is handed out as a `&CpuMask`, so it must have the size of the whole type.
+        // SAFETY: There are no safety requirements for this FFI call.
+        let ptr = unsafe {
+            bindings::krealloc(
+                ptr::null(),
+                core::mem::size_of::<CpuMask>(),
+                bindings::GFP_KERNEL | bindings::__GFP_ZERO,
+            )
+        };
+
+        // INVARIANT: `ptr` was allocated by `krealloc` with the size of a whole `CpuMask`.
+        Ok(Self {
+            ptr: NonNull::new(ptr as *mut CpuMask).ok_or(ENOMEM)?,
+        })
+    }
+
+    /// Allocates a new mask with the CPUs of `other`.
+    pub fn try_clone_from(other: &CpuMask) -> Result<Self> {
+        let mut mask = Self::try_new()?;
+        mask.copy_from(other);
+        Ok(mask)
+    }
+}
+
+impl Deref for CpuMaskVar {
+    type Target = CpuMask;
+
+    fn deref(&self) -> &CpuMask {
+        // SAFETY: By the type invariants, `ptr` is a valid mask owned by `self`.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl DerefMut for CpuMaskVar {
+    fn deref_mut(&mut self) -> &mut CpuMask {
+        // SAFETY: By the type invariants, `ptr` is a valid mask owned by `self`.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl Drop for CpuMaskVar {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `ptr` was allocated by `krealloc`.
+        unsafe { bindings::kfree(self.ptr.as_ptr() as *const c_types::c_void) };
+    }
+}
@@ -0,0 +1,469 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Crypto API.
+//!
+//! Ciphers are allocated by algorithm name, e.g., `xts(aes)` for [`Skcipher`] or `gcm(aes)` for
+//! [`Aead`], and operate on [`ScatterList`]s through requests. The requests in this module wait
+//! for the operation to complete, so they must be used in process context.
+//!
+//! C headers: [`include/crypto/skcipher.h`](../../../../include/crypto/skcipher.h) and
+//! [`include/crypto/aead.h`](../../../../include/crypto/aead.h)
+//!
+//! Reference: <https://www.kernel.org/doc/html/latest/crypto/index.html>
+
+use crate::{
+    bindings, c_types,
+    error::{code::*, from_kernel_err_ptr, to_result, Result},
+    scatterlist::ScatterList,
+    str::CStr,
+};
+use core::ptr::NonNull;
+
+/// Waits for the completion of a request submitted with `crypto_req_done` as its callback.
+///
+/// # Safety
+///
+/// `submit` must submit a request whose callback completes `wait`, and return its result.
+unsafe fn submit_and_wait(
+    submit: impl FnOnce(*mut bindings::crypto_wait) -> c_types::c_int,
+) -> Result {
+    let mut wait = bindings::crypto_wait::default();
+    // SAFETY: `wait` is valid for writes.
+    unsafe { bindings::crypto_init_wait(&mut wait) };
+    let ret = submit(&mut wait);
+    // SAFETY: `wait` was initialised above, and the safety requirements guarantee that it is
+    // completed by the request, which `crypto_wait_req` waits for if it is in progress.
+    to_result(|| unsafe { bindings::crypto_wait_req(ret, &mut wait) })
+}
+
+const REQ_FLAGS: u32 = bindings::CRYPTO_TFM_REQ_MAY_BACKLOG | bindings::CRYPTO_TFM_REQ_MAY_SLEEP;
+
+/// A symmetric key cipher, e.g., `cbc(aes)`.
+///
+/// # Invariants
+///
+/// `tfm` was allocated by `crypto_alloc_skcipher`, and is owned by `self`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::{c_str, crypto::Skcipher, scatterlist::ScatterList};
+///
+/// // Encrypts `data` in place, whose length must be a multiple of the block size.
+/// fn encrypt(key: &[u8], iv: &mut [u8], data: &mut Vec<u8>) -> Result {
+///     let mut cipher = Skcipher::new(c_str!("cbc(aes)"))?;
+///     cipher.set_key(key)?;
+///     let mut req = cipher.request()?;
+///     let mut sg = ScatterList::from_slice(data)?;
+///     let len = sg.len();
+///     req.encrypt_in_place(&mut sg, len, iv)
+/// }
+/// ```
+pub struct Skcipher {
+    tfm: NonNull<bindings::crypto_skcipher>,
+}
+
+// SAFETY: The cipher can be used and freed from any thread.
+unsafe impl Send for Skcipher {}
+
+// SAFETY: Requests can be submitted concurrently; the key is only changed through a mutable
+// reference.
+unsafe impl Sync for Skcipher {}
+
+impl Skcipher {
+    /// Allocates a cipher implementing the algorithm `name`.
+    pub fn new(name: &CStr) -> Result<Self> {
+        // SAFETY: `name` is a valid C string.
+        let tfm = from_kernel_err_ptr(unsafe {
+            bindings::crypto_alloc_skcipher(name.as_char_ptr(), 0, 0)
+        })?;
+
+        // INVARIANT: `tfm` was allocated above.
+        Ok(Self {
+            // SAFETY: `crypto_alloc_skcipher` doesn't return null on success.
+            tfm: unsafe { NonNull::new_unchecked(tfm) },
+        })
+    }
+
+    /// Sets the key of the cipher.
+    pub fn set_key(&mut self, key: &[u8]) -> Result {
+        // SAFETY: By the type invariants, `tfm` is valid, and `key` is valid for its length.
+        to_result(|| unsafe {
+            bindings::crypto_skcipher_setkey(self.tfm.as_ptr(), key.as_ptr(), key.len() as _)
+        })
+    }
+
+    /// Returns the size of the initialisation vector.
+    pub fn iv_size(&self) -> usize {
+        // SAFETY: By the type invariants, `tfm` is valid.
+        unsafe { bindings::crypto_skcipher_ivsize(self.tfm.as_ptr()) as _ }
+    }
+
+    /// Returns the block size of the cipher.
+    pub fn block_size(&self) -> usize {
+        // SAFETY: By the type invariants, `tfm` is valid.
+        unsafe { bindings::crypto_skcipher_blocksize(self.tfm.as_ptr()) as _ }
+    }
+
+    /// Allocates a request, which can be reused for several operations.
+    pub fn request(&self) -> Result<SkcipherRequest<'_>> {
+        // SAFETY: By the type invariants, `tfm` is valid.
+        let req =
+            unsafe { bindings::skcipher_request_alloc(self.tfm.as_ptr(), bindings::GFP_KERNEL) };
+
+        // INVARIANT: `req` was allocated above for `cipher`.
+        Ok(SkcipherRequest {
+            req: NonNull::new(req).ok_or(ENOMEM)?,
+            cipher: self,
+        })
+    }
+}
+
+impl Drop for Skcipher {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `tfm` is owned by `self`, and no requests are left since
+        // they borrow `self`.
+        unsafe { bindings::crypto_free_skcipher(self.tfm.as_ptr()) };
+    }
+}
+
+/// A request of an [`Skcipher`].
+///
+/// # Invariants
+///
+/// `req` was allocated by `skcipher_request_alloc` for `cipher`, and is owned by `self`.
+pub struct SkcipherRequest<'a> {
+    req: NonNull<bindings::skcipher_request>,
+    cipher: &'a Skcipher,
+}
+
+// SAFETY: The request can be used and freed from any thread.
+unsafe impl Send for SkcipherRequest<'_> {}
+
+impl SkcipherRequest<'_> {
+    /// Runs `f` from `src` into `dst`, or in place in `dst` if `src` is `None`.
+    fn crypt(
+        &mut self,
+        src: Option<&ScatterList<'_>>,
+        dst: &mut ScatterList<'_>,
+        len: usize,
+        iv: &mut [u8],
+        f: unsafe extern "C" fn(*mut bindings::skcipher_request) -> c_types::c_int,
+    ) -> Result {
+        let src_len = src.map_or(dst.len(), |src| src.len());
+        if len > src_len || len > dst.len() || iv.len() < self.cipher.iv_size() {
+            return Err(EINVAL);
+        }
+        let src = src.map_or(dst.as_ptr(), |src| src.as_ptr());
+
+        let req = self.req.as_ptr();
+        // SAFETY: By the type invariants, `req` is valid. The lists are valid for `len` bytes and
+        // `iv` for the size of the cipher's initialisation vector, and they outlive the request,
+        // which is waited for.
+        unsafe {
+            submit_and_wait(|wait| {
+                bindings::skcipher_request_set_callback(
+                    req,
+                    REQ_FLAGS,
+                    Some(bindings::crypto_req_done),
+                    wait.cast(),
+                );
+                bindings::skcipher_request_set_crypt(
+                    req,
+                    src,
+                    dst.as_ptr(),
+                    len as _,
+                    iv.as_mut_ptr().cast(),
+                );
+                f(req)
+            })
+        }
+    }
+
+    /// Encrypts the first `len` bytes of `src` into `dst`.
+    ///
+    /// `iv` must be at least [`Skcipher::iv_size`] bytes long. It is updated for chaining modes
+    /// like CBC, so that it can be used to encrypt the following data.
+    pub fn encrypt(
+        &mut self,
+        src: &ScatterList<'_>,
+        dst: &mut ScatterList<'_>,
+        len: usize,
+        iv: &mut [u8],
+    ) -> Result {
+        self.crypt(Some(src), dst, len, iv, bindings::crypto_skcipher_encrypt)
+    }
+
+    /// Encrypts the first `len` bytes of `data` in place, see [`SkcipherRequest::encrypt`].
+    pub fn encrypt_in_place(
+        &mut self,
+        data: &mut ScatterList<'_>,
+        len: usize,
+        iv: &mut [u8],
+    ) -> Result {
+        self.crypt(None, data, len, iv, bindings::crypto_skcipher_encrypt)
+    }
+
+    /// Decrypts the first `len` bytes of `src` into `dst`.
+    ///
+    /// `iv` must be at least [`Skcipher::iv_size`] bytes long.
+    pub fn decrypt(
+        &mut self,
+        src: &ScatterList<'_>,
+        dst: &mut ScatterList<'_>,
+        len: usize,
+        iv: &mut [u8],
+    ) -> Result {
+        self.crypt(Some(src), dst, len, iv, bindings::crypto_skcipher_decrypt)
+    }
+
+    /// Decrypts the first `len` bytes of `data` in place, see [`SkcipherRequest::decrypt`].
+    pub fn decrypt_in_place(
+        &mut self,
+        data: &mut ScatterList<'_>,
+        len: usize,
+        iv: &mut [u8],
+    ) -> Result {
+        self.crypt(None, data, len, iv, bindings::crypto_skcipher_decrypt)
+    }
+}
+
+impl Drop for SkcipherRequest<'_> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `req` is owned by `self`, and no operation is in
+        // progress since they are waited for.
+        unsafe { bindings::skcipher_request_free(self.req.as_ptr()) };
+    }
+}
+
+/// An authenticated encryption cipher, e.g., `gcm(aes)`.
+///
+/// Data is laid out in the lists as the associated data, which is authenticated but not
+/// encrypted, followed by the text, and, in the ciphertext, by the authentication tag.
+///
+/// # Invariants
+///
+/// `tfm` was allocated by `crypto_alloc_aead`, and is owned by `self`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::{c_str, crypto::Aead, scatterlist::ScatterList};
+///
+/// // Seals `buf`, which holds 16 bytes of header followed by the payload and room for the tag.
+/// fn seal(key: &[u8], iv: &mut [u8], buf: &mut Vec<u8>) -> Result {
+///     let mut cipher = Aead::new(c_str!("gcm(aes)"))?;
+///     cipher.set_key(key)?;
+///     cipher.set_auth_size(16)?;
+///     let payload_len = buf.len() - 16 - cipher.auth_size();
+///     let mut req = cipher.request()?;
+///     let mut sg = ScatterList::from_slice(buf)?;
+///     req.encrypt_in_place(&mut sg, 16, payload_len, iv)
+/// }
+/// ```
+pub struct Aead {
+    tfm: NonNull<bindings::crypto_aead>,
+}
+
+// SAFETY: The cipher can be used and freed from any thread.
+unsafe impl Send for Aead {}
+
+// SAFETY: Requests can be submitted concurrently; the key and the authentication tag size are
+// only changed through a mutable reference.
+unsafe impl Sync for Aead {}
+
+impl Aead {
+    /// Allocates a cipher implementing the algorithm `name`.
+    pub fn new(name: &CStr) -> Result<Self> {
+        // SAFETY: `name` is a valid C string.
+        let tfm =
+            from_kernel_err_ptr(unsafe { bindings::crypto_alloc_aead(name.as_char_ptr(), 0, 0) })?;
+
+        // INVARIANT: `tfm` was allocated above.
+        Ok(Self {
+            // SAFETY: `crypto_alloc_aead` doesn't return null on success.
+            tfm: unsafe { NonNull::new_unchecked(tfm) },
+        })
+    }
+
+    /// Sets the key of the cipher.
+    pub fn set_key(&mut self, key: &[u8]) -> Result {
+        // SAFETY: By the type invariants, `tfm` is valid, and `key` is valid for its length.
+        to_result(|| unsafe {
+            bindings::crypto_aead_setkey(self.tfm.as_ptr(), key.as_ptr(), key.len() as _)
+        })
+    }
+
+    /// Sets the size of the authentication tag.
+    pub fn set_auth_size(&mut self, size: usize) -> Result {
+        let size = size.try_into()?;
+        // SAFETY: By the type invariants, `tfm` is valid.
+        to_result(|| unsafe { bindings::crypto_aead_setauthsize(self.tfm.as_ptr(), size) })
+    }
+
+    /// Returns the size of the authentication tag.
+    pub fn auth_size(&self) -> usize {
+        // SAFETY: By the type invariants, `tfm` is valid.
+        unsafe { bindings::crypto_aead_authsize(self.tfm.as_ptr()) as _ }
+    }
+
+    /// Returns the size of the initialisation vector.
+    pub fn iv_size(&self) -> usize {
+        // SAFETY: By the type invariants, `tfm` is valid.
+        unsafe { bindings::crypto_aead_ivsize(self.tfm.as_ptr()) as _ }
+    }
+
+    /// Allocates a request, which can be reused for several operations.
+    pub fn request(&self) -> Result<AeadRequest<'_>> {
+        // SAFETY: By the type invariants, `tfm` is valid.
+        let req = unsafe { bindings::aead_request_alloc(self.tfm.as_ptr(), bindings::GFP_KERNEL) };
+
+        // INVARIANT: `req` was allocated above for `cipher`.
+        Ok(AeadRequest {
+            req: NonNull::new(req).ok_or(ENOMEM)?,
+            cipher: self,
+        })
+    }
+}
+
+impl Drop for Aead {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `tfm` is owned by `self`, and no requests are left since
+        // they borrow `self`.
+        unsafe { bindings::crypto_free_aead(self.tfm.as_ptr()) };
+    }
+}
+
+/// A request of an [`Aead`].
+///
+/// # Invariants
+///
+/// `req` was allocated by `aead_request_alloc` for `cipher`, and is owned by `self`.
+pub struct AeadRequest<'a> {
+    req: NonNull<bindings::aead_request>,
+    cipher: &'a Aead,
+}
+
+// SAFETY: The request can be used and freed from any thread.
+unsafe impl Send for AeadRequest<'_> {}
+
+impl AeadRequest<'_> {
+    /// Encrypts or decrypts from `src` into `dst`, or in place in `dst` if `src` is `None`.
+    fn crypt(
+        &mut self,
+        src: Option<&ScatterList<'_>>,
+        dst: &mut ScatterList<'_>,
+        assoc_len: usize,
+        src_len: usize,
+        iv: &mut [u8],
+        encrypt: bool,
+    ) -> Result {
+        // The tag is appended to the ciphertext when encrypting, and consumed when decrypting.
+        let auth_size = self.cipher.auth_size();
+        let dst_len = if encrypt {
+            src_len.checked_add(auth_size)
+        } else {
+            src_len.checked_sub(auth_size)
+        }
+        .ok_or(EINVAL)?;
+        let src_total = assoc_len.checked_add(src_len).ok_or(EINVAL)?;
+        let dst_total = assoc_len.checked_add(dst_len).ok_or(EINVAL)?;
+        let src_size = src.map_or(dst.len(), |src| src.len());
+        if src_total > src_size || dst_total > dst.len() || iv.len() < self.cipher.iv_size() {
+            return Err(EINVAL);
+        }
+        let src = src.map_or(dst.as_ptr(), |src| src.as_ptr());
+
+        let req = self.req.as_ptr();
+        // SAFETY: By the type invariants, `req` is valid. The lists are valid for the lengths of
+        // the operation and `iv` for the size of the cipher's initialisation vector, and they
+        // outlive the request, which is waited for.
+        unsafe {
+            submit_and_wait(|wait| {
+                bindings::aead_request_set_callback(
+                    req,
+                    REQ_FLAGS,
+                    Some(bindings::crypto_req_done),
+                    wait.cast(),
+                );
+                bindings::aead_request_set_crypt(
+                    req,
+                    src,
+                    dst.as_ptr(),
+                    src_len as _,
+                    iv.as_mut_ptr().cast(),
+                );
+                bindings::aead_request_set_ad(req, assoc_len as _);
+                if encrypt {
+                    bindings::crypto_aead_encrypt(req)
+                } else {
+                    bindings::crypto_aead_decrypt(req)
+                }
+            })
+        }
+    }
+
+    /// Encrypts and authenticates `assoc_len` bytes of associated data followed by `len` bytes of
+    /// plaintext from `src`.
+    ///
+    /// `dst` receives the associated data, the ciphertext, and the authentication tag. `iv` must
+    /// be at least [`Aead::iv_size`] bytes long.
+    pub fn encrypt(
+        &mut self,
+        src: &ScatterList<'_>,
+        dst: &mut ScatterList<'_>,
+        assoc_len: usize,
+        len: usize,
+        iv: &mut [u8],
+    ) -> Result {
+        self.crypt(Some(src), dst, assoc_len, len, iv, true)
+    }
+
+    /// Encrypts and authenticates the contents of `data` in place, see [`AeadRequest::encrypt`].
+    pub fn encrypt_in_place(
+        &mut self,
+        data: &mut ScatterList<'_>,
+        assoc_len: usize,
+        len: usize,
+        iv: &mut [u8],
+    ) -> Result {
+        self.crypt(None, data, assoc_len, len, iv, true)
+    }
+
+    /// Authenticates and decrypts `assoc_len` bytes of associated data followed by `len` bytes of
+    /// ciphertext, which include the authentication tag, from `src`.
+    ///
+    /// `dst` receives the associated data and the plaintext. It fails with [`EBADMSG`] if the
+    /// authentication fails. `iv` must be at least [`Aead::iv_size`] bytes long.
+    pub fn decrypt(
+        &mut self,
+        src: &ScatterList<'_>,
+        dst: &mut ScatterList<'_>,
+        assoc_len: usize,
+        len: usize,
+        iv: &mut [u8],
+    ) -> Result {
+        self.crypt(Some(src), dst, assoc_len, len, iv, false)
+    }
+
+    /// Authenticates and decrypts the contents of `data` in place, see [`AeadRequest::decrypt`].
+    pub fn decrypt_in_place(
+        &mut self,
+        data: &mut ScatterList<'_>,
+        assoc_len: usize,
+        len: usize,
+        iv: &mut [u8],
+    ) -> Result {
+        self.crypt(None, data, assoc_len, len, iv, false)
+    }
+}
+
+impl Drop for AeadRequest<'_> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `req` is owned by `self`, and no operation is in
+        // progress since they are waited for.
+        unsafe { bindings::aead_request_free(self.req.as_ptr()) };
+    }
+}
@@ -0,0 +1,445 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Debugfs.
+//!
+//! Debugfs is a filesystem, usually mounted at `/sys/kernel/debug`, where drivers expose state
+//! that is useful for debugging. Unlike sysfs, it has no rules about what files contain, and isn't
+//! meant to be a stable interface.
+//!
+//! Directories are created with [`Dir`], and typed files in them with its `create_*` methods,
+//! which mirror the C `debugfs_create_*` helpers. Files are removed when they are dropped, and
+//! directories when they and all their files and subdirectories are dropped.
+//!
+//! C header: [`include/linux/debugfs.h`](../../../../include/linux/debugfs.h)
+//!
+//! Reference: <https://www.kernel.org/doc/html/latest/filesystems/debugfs.html>
+//!
+//! # Examples
+//!
+//! ```
+//! # use kernel::prelude::*;
+//! use core::sync::atomic::{AtomicBool, AtomicU32};
+//! use kernel::{c_str, debugfs, sync::Ref};
+//!
+//! struct Stats {
+//!     errors: Ref<AtomicU32>,
+//!     verbose: Ref<AtomicBool>,
+//! }
+//!
+//! // Creates `<debugfs>/example/errors` and `<debugfs>/example/verbose`.
+//! fn expose(stats: &Stats) -> Result<Vec<debugfs::File>> {
+//!     let dir = debugfs::Dir::new(c_str!("example"))?;
+//!     let mut files = Vec::new();
+//!     files.try_push(dir.create_u32(c_str!("errors"), 0o444, stats.errors.clone())?)?;
+//!     files.try_push(dir.create_bool(c_str!("verbose"), 0o644, stats.verbose.clone())?)?;
+//!     Ok(files)
+//! }
+//! ```
+
+use crate::{
+    bindings, c_str, c_types,
+    error::{code::*, from_kernel_err_ptr, Result},
+    str::CStr,
+    sync::{Atomic32, Ref},
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    marker::PhantomData,
+    ptr,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+};
+
+struct DirInner {
+    dentry: *mut bindings::dentry,
+    _parent: Option<Dir>,
+}
+
+// SAFETY: Debugfs directories can be removed from any thread.
+unsafe impl Send for DirInner {}
+
+// SAFETY: Files can be created in a directory concurrently.
+unsafe impl Sync for DirInner {}
+
+impl Drop for DirInner {
+    fn drop(&mut self) {
+        // SAFETY: `dentry` was created by `debugfs_create_dir`, and all the files and directories
+        // in it are gone since they hold references to it.
+        unsafe { bindings::debugfs_remove(self.dentry) };
+    }
+}
+
+/// A debugfs directory.
+///
+/// Clones refer to the same directory, which is removed when the last clone and the last file or
+/// subdirectory in it are dropped.
+///
+/// # Invariants
+///
+/// `inner.dentry` is a directory created by `debugfs_create_dir`, which is removed when `inner`
+/// is dropped.
+#[derive(Clone)]
+pub struct Dir {
+    inner: Ref<DirInner>,
+}
+
+impl Dir {
+    fn create(name: &CStr, parent: Option<&Dir>) -> Result<Self> {
+        let parent_dentry = parent.map_or(ptr::null_mut(), |p| p.inner.dentry);
+        // SAFETY: `name` is a valid C string, and `parent_dentry` is either null (for the root of
+        // debugfs) or a valid directory.
+        let dentry = from_kernel_err_ptr(unsafe {
+            bindings::debugfs_create_dir(name.as_char_ptr(), parent_dentry)
+        })?;
+
+        // INVARIANT: `dentry` was created above.
+        Ok(Self {
+            inner: Ref::try_new(DirInner {
+                dentry,
+                _parent: parent.cloned(),
+            })?,
+        })
+    }
+
+    /// Creates a directory at the root of debugfs.
+    ///
+    /// It fails with [`ENODEV`] if debugfs is disabled.
+    pub fn new(name: &CStr) -> Result<Self> {
+        Self::create(name, None)
+    }
+
+    /// Creates a subdirectory.
+    pub fn subdir(&self, name: &CStr) -> Result<Self> {
+        Self::create(name, Some(self))
+    }
+
+    /// Creates the file `name`, whose operations `fops` get `ptr` as the `i_private` of its inode.
+    ///
+    /// # Safety
+    ///
+    /// `fops` must only access `ptr` (which is kept alive by `data`) while the file exists, using
+    /// `debugfs_file_get`.
+    unsafe fn create_file(
+        &self,
+        name: &CStr,
+        mode: u16,
+        data: Box<dyn Send + Sync>,
+        ptr: *mut c_types::c_void,
+        fops: &'static bindings::file_operations,
+    ) -> Result<File> {
+        // SAFETY: `name` is a valid C string, and `dentry` is valid by the type invariants. The
+        // safety requirements guarantee that `fops` only uses `ptr` while it is valid.
+        let dentry = from_kernel_err_ptr(unsafe {
+            bindings::debugfs_create_file_unsafe(
+                name.as_char_ptr(),
+                mode,
+                self.inner.dentry,
+                ptr,
+                fops,
+            )
+        })?;
+
+        // SAFETY: `dentry` was just created, and a reference to it is dropped with the file.
+        unsafe { bindings::dget(dentry) };
+
+        // INVARIANT: The file owns the reference acquired above.
+        Ok(File {
+            dentry,
+            _data: data,
+            _dir: self.clone(),
+        })
+    }
+
+    fn create_number<T: Number>(&self, name: &CStr, mode: u16, value: Ref<T>) -> Result<File> {
+        let ptr = &*value as *const T as *mut c_types::c_void;
+        // SAFETY: `ptr` points to a `T`, which is kept alive by the file, and the operations of
+        // `NumberFile` only access it with `debugfs_attr_read` and `debugfs_attr_write`.
+        unsafe {
+            self.create_file(
+                name,
+                mode,
+                Box::try_new(value)?,
+                ptr,
+                &NumberFile::<T>::FOPS,
+            )
+        }
+    }
+
+    /// Creates a file showing `value` as a decimal number, which can be changed by writing to the
+    /// file if `mode` allows it.
+    pub fn create_u32(&self, name: &CStr, mode: u16, value: Ref<AtomicU32>) -> Result<File> {
+        self.create_number(name, mode, value)
+    }
+
+    /// Creates a file showing `value` as a decimal number, which can be changed by writing to the
+    /// file if `mode` allows it.
+    pub fn create_u64(&self, name: &CStr, mode: u16, value: Ref<AtomicU64>) -> Result<File> {
+        self.create_number(name, mode, value)
+    }
+
+    /// Creates a file showing `value` as `Y` or `N`, which can be changed by writing to the file
+    /// if `mode` allows it.
+    pub fn create_bool(&self, name: &CStr, mode: u16, value: Ref<AtomicBool>) -> Result<File> {
+        let ptr = &*value as *const AtomicBool as *mut c_types::c_void;
+        // SAFETY: `ptr` points to an `AtomicBool`, which has the same layout as `bool` and is kept
+        // alive by the file. `BOOL_FOPS` only accesses it with `debugfs_read_file_bool` and
+        // `debugfs_write_file_bool`.
+        unsafe { self.create_file(name, mode, Box::try_new(value)?, ptr, &BOOL_FOPS) }
+    }
+
+    /// Creates a file showing `value` as a decimal number, which can be changed by writing to the
+    /// file if `mode` allows it.
+    pub fn create_atomic(&self, name: &CStr, mode: u16, value: Ref<Atomic32>) -> Result<File> {
+        self.create_number(name, mode, value)
+    }
+
+    /// Creates a read-only file whose contents are `data`.
+    pub fn create_blob(&self, name: &CStr, mode: u16, data: Vec<u8>) -> Result<File> {
+        let mut blob = Box::try_new(Blob {
+            wrapper: bindings::debugfs_blob_wrapper {
+                data: ptr::null_mut(),
+                size: data.len() as _,
+            },
+            data,
+        })?;
+        blob.wrapper.data = blob.data.as_mut_ptr().cast();
+
+        // SAFETY: `dentry` is valid by the type invariants, and `blob` is kept alive by the file.
+        // It is boxed, so the wrapper doesn't move, and it points to `data`, which isn't modified.
+        let dentry = from_kernel_err_ptr(unsafe {
+            bindings::debugfs_create_blob(
+                name.as_char_ptr(),
+                mode,
+                self.inner.dentry,
+                &mut blob.wrapper,
+            )
+        })?;
+
+        // SAFETY: `dentry` was just created, and a reference to it is dropped with the file.
+        unsafe { bindings::dget(dentry) };
+
+        // INVARIANT: The file owns the reference acquired above.
+        Ok(File {
+            dentry,
+            _data: blob,
+            _dir: self.clone(),
+        })
+    }
+}
+
+/// File operations with no callbacks, to build others from.
+const EMPTY_FOPS: bindings::file_operations = bindings::file_operations {
+    open: None,
+    release: None,
+    read: None,
+    write: None,
+    llseek: None,
+    check_flags: None,
+    compat_ioctl: None,
+    copy_file_range: None,
+    fallocate: None,
+    fadvise: None,
+    fasync: None,
+    flock: None,
+    flush: None,
+    fsync: None,
+    get_unmapped_area: None,
+    iterate: None,
+    iterate_shared: None,
+    iopoll: None,
+    lock: None,
+    mmap: None,
+    mmap_supported_flags: 0,
+    owner: ptr::null_mut(),
+    poll: None,
+    read_iter: None,
+    remap_file_range: None,
+    sendpage: None,
+    setlease: None,
+    show_fdinfo: None,
+    splice_read: None,
+    splice_write: None,
+    unlocked_ioctl: None,
+    write_iter: None,
+};
+
+/// Returns whether the mode of `inode` allows the accesses in `bits`, e.g., `0o444` for reads.
+///
+/// Like the C helpers, files can only be read or written if their mode allows it, even by root.
+///
+/// # Safety
+///
+/// `inode` must be valid.
+unsafe fn mode_allows(inode: *const bindings::inode, bits: bindings::umode_t) -> bool {
+    // SAFETY: `inode` is valid by the safety requirements.
+    unsafe { (*inode).i_mode & bits != 0 }
+}
+
+/// A value shown by a file as a number.
+trait Number: Send + Sync + 'static {
+    /// The format of the value, for `simple_attr_open`.
+    const FORMAT: &'static CStr;
+
+    fn get(&self) -> u64;
+    fn set(&self, value: u64);
+}
+
+impl Number for AtomicU32 {
+    const FORMAT: &'static CStr = c_str!("%llu\n");
+
+    fn get(&self) -> u64 {
+        self.load(Ordering::Relaxed).into()
+    }
+
+    fn set(&self, value: u64) {
+        self.store(value as _, Ordering::Relaxed);
+    }
+}
+
+impl Number for AtomicU64 {
+    const FORMAT: &'static CStr = c_str!("%llu\n");
+
+    fn get(&self) -> u64 {
+        self.load(Ordering::Relaxed)
+    }
+
+    fn set(&self, value: u64) {
+        self.store(value, Ordering::Relaxed);
+    }
+}
+
+impl Number for Atomic32 {
+    const FORMAT: &'static CStr = c_str!("%lld\n");
+
+    fn get(&self) -> u64 {
+        self.read() as _
+    }
+
+    fn set(&self, value: u64) {
+        Atomic32::set(self, value as _);
+    }
+}
+
+/// The operations of files showing a [`Number`], which is the `i_private` of their inode.
+///
+/// They are the equivalent of the ones defined with `DEFINE_DEBUGFS_ATTRIBUTE` in C.
+struct NumberFile<T>(PhantomData<T>);
+
+impl<T: Number> NumberFile<T> {
+    const FOPS: bindings::file_operations = bindings::file_operations {
+        open: Some(Self::open),
+        release: Some(bindings::simple_attr_release),
+        read: Some(bindings::debugfs_attr_read),
+        write: Some(bindings::debugfs_attr_write),
+        llseek: Some(bindings::no_llseek),
+        ..EMPTY_FOPS
+    };
+
+    unsafe extern "C" fn open(
+        inode: *mut bindings::inode,
+        file: *mut bindings::file,
+    ) -> c_types::c_int {
+        // SAFETY: `inode` is valid while the file is being opened.
+        let (read, write) = unsafe { (mode_allows(inode, 0o444), mode_allows(inode, 0o222)) };
+        let get = read.then(|| Self::get as _);
+        let set = write.then(|| Self::set as _);
+
+        // SAFETY: `inode` and `file` are valid while the file is being opened, and the format is
+        // static.
+        unsafe { bindings::simple_attr_open(inode, file, get, set, T::FORMAT.as_char_ptr()) }
+    }
+
+    unsafe extern "C" fn get(data: *mut c_types::c_void, value: *mut u64) -> c_types::c_int {
+        // SAFETY: `data` is the `i_private` of a file created by `Dir::create_number`, which
+        // points to a `T` while the file exists, and `value` is valid for writes.
+        unsafe { *value = (*(data as *const T)).get() };
+        0
+    }
+
+    unsafe extern "C" fn set(data: *mut c_types::c_void, value: u64) -> c_types::c_int {
+        // SAFETY: `data` is the `i_private` of a file created by `Dir::create_number`, which
+        // points to a `T` while the file exists.
+        unsafe { (*(data as *const T)).set(value) };
+        0
+    }
+}
+
+/// The operations of files created by [`Dir::create_bool`].
+const BOOL_FOPS: bindings::file_operations = bindings::file_operations {
+    open: Some(bindings::simple_open),
+    read: Some(bool_read),
+    write: Some(bool_write),
+    llseek: Some(bindings::default_llseek),
+    ..EMPTY_FOPS
+};
+
+unsafe extern "C" fn bool_read(
+    file: *mut bindings::file,
+    buf: *mut c_types::c_char,
+    count: c_types::c_size_t,
+    ppos: *mut bindings::loff_t,
+) -> c_types::c_ssize_t {
+    // SAFETY: `file` is valid while it is being read.
+    if !unsafe { mode_allows((*file).f_inode, 0o444) } {
+        return EACCES.to_kernel_errno() as _;
+    }
+
+    // SAFETY: The arguments are valid by the contract with the C code.
+    unsafe { bindings::debugfs_read_file_bool(file, buf, count, ppos) }
+}
+
+unsafe extern "C" fn bool_write(
+    file: *mut bindings::file,
+    buf: *const c_types::c_char,
+    count: c_types::c_size_t,
+    ppos: *mut bindings::loff_t,
+) -> c_types::c_ssize_t {
+    // SAFETY: `file` is valid while it is being written.
+    if !unsafe { mode_allows((*file).f_inode, 0o222) } {
+        return EACCES.to_kernel_errno() as _;
+    }
+
+    // SAFETY: The arguments are valid by the contract with the C code.
+    unsafe { bindings::debugfs_write_file_bool(file, buf, count, ppos) }
+}
+
+struct Blob {
+    wrapper: bindings::debugfs_blob_wrapper,
+    data: Vec<u8>,
+}
+
+// SAFETY: The blob isn't modified after the file is created.
+unsafe impl Send for Blob {}
+
+// SAFETY: The blob isn't modified after the file is created.
+unsafe impl Sync for Blob {}
+
+/// A debugfs file, created by one of the `create_*` methods of [`Dir`].
+///
+/// The file is removed when this is dropped, and the data it shows is released once it is gone.
+///
+/// # Invariants
+///
+/// `dentry` is a file in debugfs, to which `self` owns a reference.
+pub struct File {
+    dentry: *mut bindings::dentry,
+    _data: Box<dyn Send + Sync>,
+    _dir: Dir,
+}
+
+// SAFETY: Debugfs files can be removed from any thread, and the data is `Send`.
+unsafe impl Send for File {}
+
+// SAFETY: `File` has no methods, and the data is `Sync`.
+unsafe impl Sync for File {}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `dentry` is a file to which `self` owns a reference.
+        // Removal waits for the file's readers and writers to finish, so the data can be dropped
+        // afterwards.
+        unsafe {
+            bindings::debugfs_remove(self.dentry);
+            bindings::dput(self.dentry);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Delays and sleeps.
+//!
+//! Delays busy-wait, so they can be used in atomic context but waste CPU time; they should only be
+//! used for short waits (e.g., between register writes). Sleeps schedule other tasks instead, so
+//! they can only be used in process context.
+//!
+//! The [`udelay!`] and [`ndelay!`] macros check constant delays at compile time, like the C
+//! macros do.
+//!
+//! C header: [`include/linux/delay.h`](../../../../include/linux/delay.h)
+//!
+//! Reference: <https://www.kernel.org/doc/html/latest/timers/timers-howto.html>
+
+use crate::{bindings, time::Ktime};
+
+/// The longest delay, in microseconds, that [`udelay`] handles without being split.
+pub const MAX_UDELAY_US: u32 = bindings::MAX_UDELAY_MS * 1000;
+
+/// Busy-waits for a constant number of microseconds, checking at compile time that it is not
+/// longer than [`MAX_UDELAY_US`].
+///
+/// Longer delays should use [`mdelay`] or, in process context, a sleep.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// fn reset_device() {
+///     // The device needs 10us to come out of reset.
+///     kernel::udelay!(10);
+/// }
+/// ```
+///
+/// ```compile_fail
+/// # use kernel::prelude::*;
+/// fn too_long() {
+///     kernel::udelay!(100_000);
+/// }
+/// ```
+#[macro_export]
+macro_rules! udelay {
+    ($us:expr) => {{
+        const US: u32 = $us;
+        $crate::static_assert!(US <= $crate::delay::MAX_UDELAY_US);
+        $crate::delay::udelay(US)
+    }};
+}
+
+/// Busy-waits for a constant number of nanoseconds, checking at compile time that it is not
+/// longer than [`MAX_UDELAY_US`].
+#[macro_export]
+macro_rules! ndelay {
+    ($ns:expr) => {{
+        const NS: u32 = $ns;
+        $crate::static_assert!(NS / 1000 <= $crate::delay::MAX_UDELAY_US);
+        $crate::delay::ndelay(NS)
+    }};
+}
+
+/// Busy-waits for the given number of nanoseconds.
+///
+/// Delays longer than [`MAX_UDELAY_US`] trigger a warning; use [`ndelay!`] for constant delays
+/// so they are checked at compile time instead.
+///
+/// Equivalent to the kernel's `ndelay`.
+pub fn ndelay(ns: u32) {
+    crate::warn_once!(ns / 1000 > MAX_UDELAY_US);
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::ndelay(ns as _) };
+}
+
+/// Busy-waits for the given number of microseconds.
+///
+/// Delays longer than [`MAX_UDELAY_US`] trigger a warning; use [`udelay!`] for constant delays
+/// so they are checked at compile time instead.
+///
+/// Equivalent to the kernel's `udelay`.
+pub fn udelay(us: u32) {
+    crate::warn_once!(us > MAX_UDELAY_US);
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::udelay(us as _) };
+}
+
+/// Busy-waits for the given number of milliseconds.
+///
+/// It should only be used in atomic context; process context should use [`msleep`] instead.
+///
+/// Equivalent to the kernel's `mdelay`.
+pub fn mdelay(ms: u32) {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::mdelay(ms as _) };
+}
+
+/// Sleeps for at least the given number of milliseconds.
+///
+/// It is based on jiffies, so it may sleep up to one jiffy longer; use [`usleep_range`] for
+/// sleeps shorter than 20ms. It is uninterruptible.
+///
+/// Equivalent to the kernel's `msleep`.
+pub fn msleep(ms: u32) {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::msleep(ms) };
+}
+
+/// Sleeps for at least the given number of milliseconds, unless a signal is received.
+///
+/// Returns the number of milliseconds left if a signal woke the task up, or zero otherwise.
+///
+/// Equivalent to the kernel's `msleep_interruptible`.
+pub fn msleep_interruptible(ms: u32) -> u32 {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::msleep_interruptible(ms) as _ }
+}
+
+/// Sleeps for at least the given number of seconds.
+///
+/// Equivalent to the kernel's `ssleep`.
+pub fn ssleep(secs: u32) {
+    msleep(secs.saturating_mul(1000));
+}
+
+/// Sleeps for a number of microseconds between `min` and `max`.
+///
+/// It uses high resolution timers, so it is suitable for sleeps between 10us and 20ms. The range
+/// allows the wake-up to be coalesced with other timers.
+///
+/// Equivalent to the kernel's `usleep_range`.
+pub fn usleep_range(min: u64, max: u64) {
+    crate::warn_once!(min > max);
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::usleep_range(min as _, max as _) };
+}
+
+/// Waits for at least the given interval, choosing the best mechanism for its length.
+///
+/// It busy-waits for intervals up to 10us and sleeps otherwise, so it can only be used in process
+/// context.
+///
+/// Equivalent to the kernel's `fsleep`.
+pub fn fsleep(delta: Ktime) {
+    let ns = delta.to_ns().max(0) as u64;
+    // Round up so that the wait is never shorter than requested.
+    let us = (ns + 999) / 1000;
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::fsleep(us as _) };
+}
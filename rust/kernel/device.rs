@@ -9,6 +9,8 @@ use crate::{clk::Clk, error::from_kernel_err_ptr};
 
 use crate::{
     bindings,
+    kobject::{Action, UeventEnv},
+    property::FwNode,
     revocable::{Revocable, RevocableGuard},
     str::CStr,
     sync::{NeedsLockClass, RevocableMutex, RevocableMutexGuard, UniqueRef},
@@ -23,6 +25,9 @@ use core::{
 #[cfg(CONFIG_PRINTK)]
 use crate::{c_str, c_types};
 
+#[cfg(CONFIG_DYNAMIC_DEBUG)]
+use crate::print::dynamic_debug::Descriptor;
+
 /// A raw device.
 ///
 /// # Safety
@@ -51,6 +56,29 @@ pub unsafe trait RawDevice {
         unsafe { CStr::from_char_ptr(name) }
     }
 
+    /// Returns the firmware node (from the devicetree or ACPI) that describes this device, if
+    /// any.
+    fn fwnode(&self) -> Option<&FwNode> {
+        // SAFETY: `raw_device` is valid by the requirements of this trait.
+        let ptr = unsafe { bindings::dev_fwnode(self.raw_device()) };
+        if ptr.is_null() {
+            return None;
+        }
+
+        // SAFETY: The node of a device lives at least as long as the device.
+        Some(unsafe { FwNode::from_ptr(ptr) })
+    }
+
+    /// Notifies user space (e.g., udev) of an action on the device, with extra environment
+    /// variables.
+    fn emit_uevent(&self, action: Action, env: &UeventEnv) -> Result {
+        let ptr = self.raw_device();
+
+        // SAFETY: `ptr` is valid by the requirements of this trait, and devices are added to
+        // sysfs before they are made available to drivers.
+        unsafe { env.emit(core::ptr::addr_of_mut!((*ptr).kobj), action) }
+    }
+
     /// Lookups a clock producer consumed by this device.
     ///
     /// Returns a managed reference to the clock producer.
@@ -69,6 +97,27 @@ pub unsafe trait RawDevice {
         unsafe { Ok(Clk::new(clk_ptr)) }
     }
 
+    /// Lookups an optional clock producer consumed by this device.
+    ///
+    /// If the device has no such clock, the returned one is a dummy clock, on which all
+    /// operations succeed and do nothing.
+    #[cfg(CONFIG_COMMON_CLK)]
+    fn clk_get_optional(&self, id: Option<&CStr>) -> Result<Clk> {
+        let id_ptr = match id {
+            Some(cstr) => cstr.as_char_ptr(),
+            None => core::ptr::null(),
+        };
+
+        // SAFETY: `id_ptr` is optional and may be either a valid pointer
+        // from the type invariant or NULL otherwise.
+        let clk_ptr =
+            unsafe { from_kernel_err_ptr(bindings::clk_get_optional(self.raw_device(), id_ptr)) }?;
+
+        // SAFETY: Clock is initialized with a pointer returned from `bindings::clk_get_optional`,
+        // which is either valid or null, and the clock API accepts null clocks.
+        unsafe { Ok(Clk::new(clk_ptr)) }
+    }
+
     /// Prints an emergency-level message (level 0) prefixed with device information.
     ///
     /// More details are available from [`dev_emerg`].
@@ -135,6 +184,30 @@ pub unsafe trait RawDevice {
         }
     }
 
+    /// Prints a debug-level message (level 7) prefixed with device information via dynamic debug.
+    ///
+    /// Public but hidden since it should only be used from public macros.
+    ///
+    /// # Safety
+    ///
+    /// `descriptor` must be placed in the `__dyndbg` section (e.g., by
+    /// [`crate::dynamic_debug_descriptor`]).
+    #[doc(hidden)]
+    #[cfg(CONFIG_DYNAMIC_DEBUG)]
+    unsafe fn dynamic_dbg(&self, descriptor: &'static Descriptor, args: fmt::Arguments<'_>) {
+        // SAFETY: The safety requirements guarantee that `descriptor` is a valid call site.
+        // `self.raw_device` is valid because `self` is valid. The "%pA" format string expects a
+        // pointer to `fmt::Arguments`, which is what we're passing as the last argument.
+        unsafe {
+            bindings::__dynamic_dev_dbg(
+                descriptor.as_ptr(),
+                self.raw_device(),
+                c_str!("%pA").as_char_ptr(),
+                &args as *const _ as *const c_types::c_void,
+            )
+        };
+    }
+
     /// Prints the provided message to the console.
     ///
     /// # Safety
@@ -240,25 +313,16 @@ pub struct Data<T, U, V> {
 #[macro_export]
 macro_rules! new_device_data {
     ($reg:expr, $res:expr, $gen:expr, $name:literal) => {{
-        static mut CLASS1: core::mem::MaybeUninit<$crate::bindings::lock_class_key> =
-            core::mem::MaybeUninit::uninit();
-        static mut CLASS2: core::mem::MaybeUninit<$crate::bindings::lock_class_key> =
-            core::mem::MaybeUninit::uninit();
+        let class1: &'static $crate::sync::LockClassKey = $crate::static_lock_class!();
+        let class2: &'static $crate::sync::LockClassKey = $crate::static_lock_class!();
         let regs = $reg;
         let res = $res;
         let gen = $gen;
         let name = $crate::c_str!($name);
-        // SAFETY: `CLASS1` and `CLASS2` are never used by Rust code directly; the C portion of the
-        // kernel may change it though.
+        // SAFETY: `class1` and `class2` are statics, so they remain valid until the data is
+        // dropped; the C portion of the kernel may change them though.
         unsafe {
-            $crate::device::Data::try_new(
-                regs,
-                res,
-                gen,
-                name,
-                CLASS1.as_mut_ptr(),
-                CLASS2.as_mut_ptr(),
-            )
+            $crate::device::Data::try_new(regs, res, gen, name, class1.as_ptr(), class2.as_ptr())
         }
     }};
 }
@@ -531,7 +595,8 @@ macro_rules! dev_info {
 ///
 /// This level should be used for debug messages.
 ///
-/// Equivalent to the kernel's `dev_dbg` macro, except that it doesn't support dynamic debug yet.
+/// Equivalent to the kernel's `dev_dbg` macro. Like [`pr_debug`](crate::pr_debug), call sites can
+/// be controlled via dynamic debug when `CONFIG_DYNAMIC_DEBUG` is enabled.
 ///
 /// Mimics the interface of [`std::print!`]. More information about the syntax is available from
 /// [`core::fmt`] and [`alloc::format!`].
@@ -550,5 +615,39 @@ macro_rules! dev_info {
 /// ```
 #[macro_export]
 macro_rules! dev_dbg {
+    ($($f:tt)*) => { $crate::dev_printk_dynamic!($($f)*); }
+}
+
+#[doc(hidden)]
+#[cfg(all(CONFIG_DYNAMIC_DEBUG, not(testlib)))]
+#[macro_export]
+macro_rules! dev_printk_dynamic {
+    (@emit $dev:expr, $format:expr, $($f:tt)+) => {
+        {
+            // We have an explicity `use` statement here so that callers of this macro are not
+            // required to explicitly use the `RawDevice` trait to use its functions.
+            use $crate::device::RawDevice;
+            let descriptor = $crate::dynamic_debug_descriptor!($format);
+            if descriptor.is_enabled() {
+                // SAFETY: `descriptor` is placed in the `__dyndbg` section by
+                // `dynamic_debug_descriptor`.
+                unsafe { ($dev).dynamic_dbg(descriptor, core::format_args!($($f)+)) };
+            }
+        }
+    };
+
+    ($dev:expr, $format:literal $($f:tt)*) => {
+        $crate::dev_printk_dynamic!(@emit $dev, $crate::c_str!($format), $format $($f)*)
+    };
+
+    ($dev:expr, $($f:tt)+) => {
+        $crate::dev_printk_dynamic!(@emit $dev, $crate::c_str!(""), $($f)+)
+    };
+}
+
+#[doc(hidden)]
+#[cfg(any(not(CONFIG_DYNAMIC_DEBUG), testlib))]
+#[macro_export]
+macro_rules! dev_printk_dynamic {
     ($($f:tt)*) => { $crate::dev_printk!(pr_dbg, $($f)*); }
 }
@@ -0,0 +1,122 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Device-managed resources.
+//!
+//! A [`Devres`] ties the lifetime of a resource (e.g., an [`IoMem`] mapping or an enabled clock)
+//! to the binding of a driver to a device: the resource is released when the driver is unbound,
+//! even if the [`Devres`] itself is still referenced, for example, by a file that remains open.
+//! After that, accessing it fails instead of touching hardware that may be gone.
+//!
+//! C header: [`include/linux/device.h`](../../../../include/linux/device.h)
+//!
+//! [`IoMem`]: crate::io_mem::IoMem
+
+use crate::{
+    bindings, c_types,
+    device::RawDevice,
+    error::to_result,
+    revocable::{Revocable, RevocableGuard},
+    sync::Ref,
+    types::PointerWrapper,
+    Result,
+};
+
+struct DevresInner<T> {
+    data: Revocable<T>,
+}
+
+/// A resource that is released when the driver of the device it belongs to is unbound.
+///
+/// The resource is released by whichever comes first: the device being unbound, or the
+/// [`Devres`] being dropped. The device-managed action that releases it on unbind holds a
+/// reference to a small allocation that is freed when the action runs.
+///
+/// # Invariants
+///
+/// A device-managed action holding a reference to `inner` (obtained with
+/// [`PointerWrapper::into_pointer`]) was registered with `devm_add_action`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::{devres::Devres, io_mem::IoMem, platform};
+///
+/// const SIZE: usize = 0x100;
+///
+/// fn map(pdev: &platform::Device) -> Result<Devres<IoMem<SIZE>>> {
+///     let res = pdev.resource(0).ok_or(ENXIO)?;
+///     // SAFETY: The mapping is not used to initiate DMA.
+///     let mem = unsafe { IoMem::<SIZE>::try_new(res) }?;
+///     Devres::try_new(pdev, mem)
+/// }
+///
+/// fn read_id(mem: &Devres<IoMem<SIZE>>) -> Result<u32> {
+///     // Fails with `ENXIO` once the device is unbound.
+///     let mem = mem.try_access().ok_or(ENXIO)?;
+///     Ok(mem.readl(0))
+/// }
+/// ```
+pub struct Devres<T: Send + 'static> {
+    inner: Ref<DevresInner<T>>,
+}
+
+impl<T: Send + 'static> Devres<T> {
+    /// Ties `data` to the current binding of `dev`.
+    ///
+    /// `data` is dropped when the driver of `dev` is unbound, or when the returned value is
+    /// dropped, whichever comes first. It must be called from the driver's `probe`, or while the
+    /// driver is otherwise known to be bound.
+    pub fn try_new(dev: &dyn RawDevice, data: T) -> Result<Self> {
+        let inner = Ref::try_new(DevresInner {
+            data: Revocable::new(data),
+        })?;
+        let ptr = inner.clone().into_pointer();
+
+        // SAFETY: `dev` is valid by the safety requirements of `RawDevice`. `ptr` is a reference
+        // owned by the action, which `devres_callback` takes back.
+        let ret = to_result(|| unsafe {
+            bindings::devm_add_action(dev.raw_device(), Some(devres_callback::<T>), ptr as _)
+        });
+        if let Err(e) = ret {
+            // SAFETY: The action wasn't registered, so the reference is still owned by us.
+            drop(unsafe { Ref::<DevresInner<T>>::from_pointer(ptr) });
+            return Err(e);
+        }
+
+        // INVARIANT: The action was registered above.
+        Ok(Self { inner })
+    }
+
+    /// Returns a guard that gives access to the resource, or `None` if it has been released.
+    ///
+    /// Like [`Revocable::try_access`], callers must not sleep while holding the guard.
+    pub fn try_access(&self) -> Option<RevocableGuard<'_, T>> {
+        self.inner.data.try_access()
+    }
+
+    /// Calls `f` with a reference to the resource, if it hasn't been released yet.
+    pub fn try_access_with<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        self.inner.data.try_access_with(f)
+    }
+
+    /// Returns whether the resource has been released.
+    pub fn is_revoked(&self) -> bool {
+        self.inner.data.is_revoked()
+    }
+}
+
+impl<T: Send + 'static> Drop for Devres<T> {
+    fn drop(&mut self) {
+        // The action may be running concurrently on unbind, so it can't be removed safely; it
+        // only drops its reference to `inner` when it runs.
+        self.inner.data.revoke();
+    }
+}
+
+unsafe extern "C" fn devres_callback<T: Send + 'static>(ptr: *mut c_types::c_void) {
+    // SAFETY: By the type invariants of `Devres`, `ptr` is a reference owned by the action, which
+    // only runs once.
+    let inner = unsafe { Ref::<DevresInner<T>>::from_pointer(ptr) };
+    inner.data.revoke();
+}
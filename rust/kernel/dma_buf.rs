@@ -0,0 +1,496 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! DMA buffer sharing.
+//!
+//! A [`DmaBuf`] is a buffer that can be shared between drivers (e.g., a GPU and a camera) and with
+//! user space, as a file descriptor, without copying. The exporter of a buffer implements
+//! [`Operations`] to map it for the devices of the importers, which attach to it.
+//!
+//! C header: [`include/linux/dma-buf.h`](../../../../include/linux/dma-buf.h)
+
+use crate::{
+    bindings, c_types,
+    device::RawDevice,
+    error::{code::*, from_kernel_err_ptr, from_kernel_result, to_kernel_err_ptr, Error},
+    mm,
+    pages::DmaDirection,
+    str::CStr,
+    types::{ARef, AlwaysRefCounted, PointerWrapper},
+    Opaque, Result, ScopeGuard, ThisModule,
+};
+use core::{marker::PhantomData, ptr, ptr::NonNull};
+
+/// The operations of the exporter of a DMA buffer.
+///
+/// # Safety
+///
+/// Implementers must ensure that:
+///   - The tables returned by [`Operations::map`] are valid `sg_table`s describing the whole
+///     buffer, mapped for the device of the attachment in the given direction, and that they
+///     remain valid until they are passed to [`Operations::unmap`].
+///   - The addresses returned by [`Operations::vmap`] map the whole buffer in the kernel's address
+///     space until they are passed to [`Operations::vunmap`].
+pub unsafe trait Operations {
+    /// The methods to use to populate [`struct dma_buf_ops`].
+    const TO_USE: ToUse;
+
+    /// The pointer type that will be used to hold the exporter's data about the buffer.
+    type Data: PointerWrapper + Send + Sync;
+
+    /// Maps the buffer for the device of the given attachment, and returns the scatter-gather
+    /// table describing the mapping.
+    ///
+    /// The table is released with [`Operations::unmap`].
+    fn map(
+        data: <Self::Data as PointerWrapper>::Borrowed<'_>,
+        attachment: &Attachment,
+        dir: DmaDirection,
+    ) -> Result<*mut bindings::sg_table>;
+
+    /// Unmaps a table returned by [`Operations::map`].
+    fn unmap(
+        data: <Self::Data as PointerWrapper>::Borrowed<'_>,
+        attachment: &Attachment,
+        sgt: *mut bindings::sg_table,
+        dir: DmaDirection,
+    );
+
+    /// Maps the buffer into the address space of a user process.
+    fn mmap(
+        _data: <Self::Data as PointerWrapper>::Borrowed<'_>,
+        _area: &mut mm::virt::Area,
+    ) -> Result {
+        Err(EINVAL)
+    }
+
+    /// Maps the buffer into the kernel's address space, and returns its address.
+    ///
+    /// The mapping is released with [`Operations::vunmap`].
+    fn vmap(_data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Result<*mut c_types::c_void> {
+        Err(EINVAL)
+    }
+
+    /// Unmaps an address returned by [`Operations::vmap`].
+    fn vunmap(_data: <Self::Data as PointerWrapper>::Borrowed<'_>, _vaddr: *mut c_types::c_void) {}
+}
+
+/// Represents which callbacks of [`struct dma_buf_ops`] should be populated with pointers.
+pub struct ToUse {
+    /// The `mmap` field of [`struct dma_buf_ops`].
+    pub mmap: bool,
+
+    /// The `vmap` and `vunmap` fields of [`struct dma_buf_ops`].
+    pub vmap: bool,
+}
+
+/// A constant version where all values are to set to `false`, that is, all supported fields will
+/// be set to null pointers.
+pub const USE_NONE: ToUse = ToUse {
+    mmap: false,
+    vmap: false,
+};
+
+/// Defines the [`Operations::TO_USE`] field based on a list of fields to be populated.
+#[macro_export]
+macro_rules! declare_dma_buf_operations {
+    () => {
+        const TO_USE: $crate::dma_buf::ToUse = $crate::dma_buf::USE_NONE;
+    };
+    ($($i:ident),+) => {
+        #[allow(clippy::needless_update)]
+        const TO_USE: kernel::dma_buf::ToUse =
+            $crate::dma_buf::ToUse {
+                $($i: true),+ ,
+                ..$crate::dma_buf::USE_NONE
+            };
+    };
+}
+
+struct OperationsVtable<T>(PhantomData<T>);
+
+impl<T: Operations> OperationsVtable<T> {
+    unsafe extern "C" fn map_callback(
+        attach: *mut bindings::dma_buf_attachment,
+        dir: bindings::dma_data_direction,
+    ) -> *mut bindings::sg_table {
+        to_kernel_err_ptr((|| {
+            // SAFETY: The C API guarantees that `attach` is valid for the duration of this call,
+            // and `priv_` was initialised by `DmaBuf::export` with a value returned by
+            // `T::Data::into_pointer`, which is only released after the last attachment is gone.
+            let data = unsafe { T::Data::borrow((*(*attach).dmabuf).priv_) };
+            // SAFETY: As above, `attach` is valid for the duration of this call.
+            let attachment = unsafe { Attachment::from_ptr(attach) };
+            T::map(data, attachment, DmaDirection::try_from_raw(dir)?)
+        })())
+    }
+
+    unsafe extern "C" fn unmap_callback(
+        attach: *mut bindings::dma_buf_attachment,
+        sgt: *mut bindings::sg_table,
+        dir: bindings::dma_data_direction,
+    ) {
+        // SAFETY: As in `map_callback`.
+        let data = unsafe { T::Data::borrow((*(*attach).dmabuf).priv_) };
+        // SAFETY: As in `map_callback`.
+        let attachment = unsafe { Attachment::from_ptr(attach) };
+        // `dir` is the one `sgt` was mapped with, so it was already converted successfully.
+        if let Ok(dir) = DmaDirection::try_from_raw(dir) {
+            T::unmap(data, attachment, sgt, dir);
+        }
+    }
+
+    unsafe extern "C" fn release_callback(dmabuf: *mut bindings::dma_buf) {
+        // SAFETY: `priv_` was initialised by `DmaBuf::export` with a value returned by
+        // `T::Data::into_pointer`. The buffer is being released, so it won't be used anymore.
+        drop(unsafe { T::Data::from_pointer((*dmabuf).priv_) });
+    }
+
+    unsafe extern "C" fn mmap_callback(
+        dmabuf: *mut bindings::dma_buf,
+        vma: *mut bindings::vm_area_struct,
+    ) -> c_types::c_int {
+        from_kernel_result! {
+            // SAFETY: As in `map_callback`.
+            let data = unsafe { T::Data::borrow((*dmabuf).priv_) };
+
+            // SAFETY: The C API guarantees that `vma` is valid for the duration of this call.
+            // `area` only lives within this call, so it is guaranteed to be valid.
+            let mut area = unsafe { mm::virt::Area::from_ptr(vma) };
+            T::mmap(data, &mut area)?;
+            Ok(0)
+        }
+    }
+
+    unsafe extern "C" fn vmap_callback(
+        dmabuf: *mut bindings::dma_buf,
+        map: *mut bindings::dma_buf_map,
+    ) -> c_types::c_int {
+        from_kernel_result! {
+            // SAFETY: As in `map_callback`.
+            let data = unsafe { T::Data::borrow((*dmabuf).priv_) };
+            let vaddr = T::vmap(data)?;
+            // SAFETY: The C API guarantees that `map` is valid for writes.
+            unsafe { bindings::dma_buf_map_set_vaddr(map, vaddr) };
+            Ok(0)
+        }
+    }
+
+    unsafe extern "C" fn vunmap_callback(
+        dmabuf: *mut bindings::dma_buf,
+        map: *mut bindings::dma_buf_map,
+    ) {
+        // SAFETY: As in `map_callback`.
+        let data = unsafe { T::Data::borrow((*dmabuf).priv_) };
+        // SAFETY: The C API guarantees that `map` is valid, and it was set by `vmap_callback` to
+        // a system memory address.
+        T::vunmap(data, unsafe { (*map).__bindgen_anon_1.vaddr });
+    }
+
+    const VTABLE: bindings::dma_buf_ops = bindings::dma_buf_ops {
+        cache_sgt_mapping: false,
+        attach: None,
+        detach: None,
+        pin: None,
+        unpin: None,
+        map_dma_buf: Some(Self::map_callback),
+        unmap_dma_buf: Some(Self::unmap_callback),
+        release: Some(Self::release_callback),
+        begin_cpu_access: None,
+        end_cpu_access: None,
+        mmap: if T::TO_USE.mmap {
+            Some(Self::mmap_callback)
+        } else {
+            None
+        },
+        vmap: if T::TO_USE.vmap {
+            Some(Self::vmap_callback)
+        } else {
+            None
+        },
+        vunmap: if T::TO_USE.vmap {
+            Some(Self::vunmap_callback)
+        } else {
+            None
+        },
+    };
+}
+
+/// A shared DMA buffer.
+///
+/// Instances are always reference-counted, through the file that represents the buffer.
+///
+/// # Invariants
+///
+/// The pointer is valid and the reference count of the buffer's file is nonzero.
+///
+/// # Examples
+///
+/// Importing a buffer given by user space:
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::device::RawDevice;
+/// use kernel::dma_buf::DmaBuf;
+/// use kernel::pages::DmaDirection;
+///
+/// fn import(dev: &dyn RawDevice, fd: i32) -> Result {
+///     let buf = DmaBuf::from_fd(fd)?;
+///     let attachment = buf.attach(dev)?;
+///     let mapping = attachment.map(DmaDirection::FromDevice)?;
+///     pr_debug!("imported {} bytes at {:p}\n", buf.size(), mapping.sg_table());
+///     Ok(())
+/// }
+/// ```
+#[repr(transparent)]
+pub struct DmaBuf(Opaque<bindings::dma_buf>);
+
+// SAFETY: The buffer is reference-counted, and its functions are safe to call from any thread.
+unsafe impl Send for DmaBuf {}
+
+// SAFETY: The buffer functions are safe to call concurrently.
+unsafe impl Sync for DmaBuf {}
+
+impl DmaBuf {
+    /// Exports a new buffer of `size` bytes, implemented by `T`.
+    ///
+    /// `flags` are the flags of the file that represents the buffer, e.g., `O_RDWR`. `data` is
+    /// given to the callbacks of `T`, and dropped when the last reference to the buffer is gone.
+    pub fn export<T: Operations>(
+        name: &'static CStr,
+        module: &'static ThisModule,
+        size: usize,
+        flags: u32,
+        data: T::Data,
+    ) -> Result<ARef<Self>> {
+        let data_pointer = data.into_pointer();
+
+        // SAFETY: `data_pointer` comes from the call to `data.into_pointer()` above.
+        let guard = ScopeGuard::new(|| unsafe {
+            T::Data::from_pointer(data_pointer);
+        });
+
+        let info = bindings::dma_buf_export_info {
+            exp_name: name.as_char_ptr(),
+            owner: module.0,
+            ops: &OperationsVtable::<T>::VTABLE,
+            size,
+            flags: flags as _,
+            resv: ptr::null_mut(),
+            priv_: data_pointer as _,
+        };
+
+        // SAFETY: `info` is valid for the duration of the call, and the ops and name are static.
+        let ptr = from_kernel_err_ptr(unsafe { bindings::dma_buf_export(&info) })?;
+
+        // The buffer now owns the data, which is released by `release_callback`.
+        guard.dismiss();
+
+        // SAFETY: `dma_buf_export` returns a buffer with a reference owned by the caller.
+        Ok(unsafe { ARef::from_raw(NonNull::new_unchecked(ptr).cast()) })
+    }
+
+    /// Returns the buffer referred to by a file descriptor of the current process.
+    pub fn from_fd(fd: i32) -> Result<ARef<Self>> {
+        // SAFETY: FFI call, there are no requirements on `fd`.
+        let ptr = from_kernel_err_ptr(unsafe { bindings::dma_buf_get(fd) })?;
+
+        // SAFETY: `dma_buf_get` increments the refcount before returning.
+        Ok(unsafe { ARef::from_raw(NonNull::new_unchecked(ptr).cast()) })
+    }
+
+    /// Creates a reference to a buffer from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the lifetime of the
+    /// returned reference.
+    pub(crate) unsafe fn from_ptr<'a>(ptr: *const bindings::dma_buf) -> &'a Self {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `DmaBuf` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns a raw pointer to the underlying `struct dma_buf`.
+    pub fn as_ptr(&self) -> *mut bindings::dma_buf {
+        self.0.get()
+    }
+
+    /// Returns the size of the buffer in bytes.
+    pub fn size(&self) -> usize {
+        // SAFETY: The size is fixed when the buffer is exported.
+        unsafe { (*self.as_ptr()).size }
+    }
+
+    /// Installs a new file descriptor for the buffer in the current process, e.g., to return it
+    /// to user space. The descriptor holds a reference to the buffer.
+    ///
+    /// `flags` are the flags of the descriptor, e.g., `O_CLOEXEC`.
+    pub fn fd(&self, flags: u32) -> Result<i32> {
+        // The new descriptor takes over a reference on success.
+        self.inc_ref();
+
+        // SAFETY: The buffer is valid by the type invariants.
+        let fd = unsafe { bindings::dma_buf_fd(self.as_ptr(), flags as _) };
+        if fd < 0 {
+            // SAFETY: The reference taken above wasn't taken over by a descriptor.
+            unsafe { Self::dec_ref(NonNull::from(self)) };
+            return Err(Error::from_kernel_errno(fd));
+        }
+        Ok(fd)
+    }
+
+    /// Attaches the given device to the buffer, so that it can be mapped for the device.
+    pub fn attach(&self, dev: &dyn RawDevice) -> Result<OwnedAttachment> {
+        // SAFETY: The buffer and the device are valid.
+        let ptr = from_kernel_err_ptr(unsafe {
+            bindings::dma_buf_attach(self.as_ptr(), dev.raw_device())
+        })?;
+
+        // INVARIANT: `ptr` was attached above to the buffer, which is kept alive by `buf`.
+        Ok(OwnedAttachment {
+            // SAFETY: `dma_buf_attach` succeeded, so `ptr` is not null.
+            ptr: unsafe { NonNull::new_unchecked(ptr) },
+            buf: self.into(),
+        })
+    }
+}
+
+// SAFETY: The type invariants guarantee that `DmaBuf` is always ref-counted.
+unsafe impl AlwaysRefCounted for DmaBuf {
+    fn inc_ref(&self) {
+        // SAFETY: The existence of a shared reference means that the refcount is nonzero.
+        unsafe { bindings::get_dma_buf(self.as_ptr()) };
+    }
+
+    unsafe fn dec_ref(obj: NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee that the refcount is nonzero.
+        unsafe { bindings::dma_buf_put(obj.cast().as_ptr()) }
+    }
+}
+
+/// The attachment of a device to a [`DmaBuf`].
+///
+/// # Invariants
+///
+/// The attachment is valid, and its device remains valid while it is attached.
+#[repr(transparent)]
+pub struct Attachment(Opaque<bindings::dma_buf_attachment>);
+
+impl Attachment {
+    /// Creates a reference to an attachment from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the lifetime of the
+    /// returned reference.
+    unsafe fn from_ptr<'a>(ptr: *const bindings::dma_buf_attachment) -> &'a Self {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `Attachment` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns a raw pointer to the underlying `struct dma_buf_attachment`.
+    pub fn as_ptr(&self) -> *mut bindings::dma_buf_attachment {
+        self.0.get()
+    }
+
+    /// Returns the buffer the device is attached to.
+    pub fn dma_buf(&self) -> &DmaBuf {
+        // SAFETY: The buffer outlives its attachments.
+        unsafe { DmaBuf::from_ptr((*self.as_ptr()).dmabuf) }
+    }
+}
+
+// SAFETY: By the type invariants, the device of the attachment is valid.
+unsafe impl RawDevice for Attachment {
+    fn raw_device(&self) -> *mut bindings::device {
+        // SAFETY: The device is fixed when the attachment is created.
+        unsafe { (*self.as_ptr()).dev }
+    }
+}
+
+/// An attachment created by an importer with [`DmaBuf::attach`].
+///
+/// The device is detached when the instance is dropped.
+///
+/// # Invariants
+///
+/// `ptr` was returned by `dma_buf_attach` for `buf`.
+pub struct OwnedAttachment {
+    ptr: NonNull<bindings::dma_buf_attachment>,
+    buf: ARef<DmaBuf>,
+}
+
+// SAFETY: The attachment may be used and detached from any thread.
+unsafe impl Send for OwnedAttachment {}
+
+// SAFETY: The attachment functions are safe to call concurrently.
+unsafe impl Sync for OwnedAttachment {}
+
+impl OwnedAttachment {
+    /// Maps the buffer for the attached device, for transfers in the given direction.
+    ///
+    /// The buffer is unmapped when the returned mapping is dropped.
+    pub fn map(&self, dir: DmaDirection) -> Result<Mapping<'_>> {
+        // SAFETY: By the type invariants, `ptr` is a valid attachment.
+        let sgt = from_kernel_err_ptr(unsafe {
+            bindings::dma_buf_map_attachment(self.ptr.as_ptr(), dir as _)
+        })?;
+
+        // INVARIANT: `sgt` was mapped above for the attachment, in direction `dir`.
+        Ok(Mapping {
+            attachment: self,
+            sgt,
+            dir,
+        })
+    }
+}
+
+impl core::ops::Deref for OwnedAttachment {
+    type Target = Attachment;
+
+    fn deref(&self) -> &Attachment {
+        // SAFETY: By the type invariants, `ptr` is a valid attachment owned by `self`.
+        unsafe { Attachment::from_ptr(self.ptr.as_ptr()) }
+    }
+}
+
+impl Drop for OwnedAttachment {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `ptr` was attached to `buf`. The mappings were all
+        // released, since they borrow the attachment.
+        unsafe { bindings::dma_buf_detach(self.buf.as_ptr(), self.ptr.as_ptr()) };
+    }
+}
+
+/// A mapping of a [`DmaBuf`] for an attached device, returned by [`OwnedAttachment::map`].
+///
+/// # Invariants
+///
+/// `sgt` was returned by `dma_buf_map_attachment` for `attachment`, in direction `dir`.
+pub struct Mapping<'a> {
+    attachment: &'a OwnedAttachment,
+    sgt: *mut bindings::sg_table,
+    dir: DmaDirection,
+}
+
+impl Mapping<'_> {
+    /// Returns the scatter-gather table describing the mapping, with the DMA addresses for the
+    /// device.
+    pub fn sg_table(&self) -> *mut bindings::sg_table {
+        self.sgt
+    }
+}
+
+impl Drop for Mapping<'_> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `sgt` was mapped for the attachment in direction `dir`.
+        unsafe {
+            bindings::dma_buf_unmap_attachment(
+                self.attachment.ptr.as_ptr(),
+                self.sgt,
+                self.dir as _,
+            )
+        };
+    }
+}
@@ -44,7 +44,7 @@ pub mod code {
 
     declare_err!(EBADF, "Bad file number.");
 
-    declare_err!(ECHILD, "Exec format error.");
+    declare_err!(ECHILD, "No child processes.");
 
     declare_err!(EAGAIN, "Try again.");
 
@@ -94,11 +94,11 @@ pub mod code {
 
     declare_err!(ERANGE, "Math result not representable.");
 
-    declare_err!(EDEADLK, "Resource deadlock would occur");
+    declare_err!(EDEADLK, "Resource deadlock would occur.");
 
-    declare_err!(ENAMETOOLONG, "File name too long");
+    declare_err!(ENAMETOOLONG, "File name too long.");
 
-    declare_err!(ENOLCK, "No record locks available");
+    declare_err!(ENOLCK, "No record locks available.");
 
     declare_err!(
         ENOSYS,
@@ -303,7 +303,66 @@ pub mod code {
 
     declare_err!(ERESTARTSYS, "Restart the system call.");
 
+    declare_err!(
+        ERESTARTNOINTR,
+        "System call was interrupted by a signal and will be restarted."
+    );
+
+    declare_err!(
+        ERESTARTNOHAND,
+        "Restart if no handler.",
+        "",
+        "The system call is restarted if the signal that interrupted it had no handler."
+    );
+
+    declare_err!(
+        ENOIOCTLCMD,
+        "No ioctl command.",
+        "",
+        "Returned by `ioctl` handlers for unknown commands so that callers can fall back to",
+        "generic handling. It is converted to [`ENOTTY`] before reaching userspace."
+    );
+
+    declare_err!(
+        ERESTART_RESTARTBLOCK,
+        "Restart by calling `sys_restart_syscall`."
+    );
+
+    declare_err!(
+        EPROBE_DEFER,
+        "Driver requests probe retry.",
+        "",
+        "Returned by `probe` when a resource it depends on is not available yet."
+    );
+
+    declare_err!(EOPENSTALE, "Open found a stale dentry.");
+
+    declare_err!(ENOPARAM, "Parameter not supported.");
+
+    declare_err!(EBADHANDLE, "Illegal NFS file handle.");
+
+    declare_err!(ENOTSYNC, "Update synchronization mismatch.");
+
+    declare_err!(EBADCOOKIE, "Cookie is stale.");
+
     declare_err!(ENOTSUPP, "Operation is not supported.");
+
+    declare_err!(ETOOSMALL, "Buffer or request is too small.");
+
+    declare_err!(ESERVERFAULT, "An untranslatable error occurred.");
+
+    declare_err!(EBADTYPE, "Type not supported by server.");
+
+    declare_err!(
+        EJUKEBOX,
+        "Request initiated, but will not complete before timeout."
+    );
+
+    declare_err!(EIOCBQUEUED, "iocb queued, will get completion event.");
+
+    declare_err!(ERECALLCONFLICT, "Conflict with recalled state.");
+
+    declare_err!(ENOGRACE, "NFS file lock reclaim refused.");
 }
 
 /// Generic integer kernel error.
@@ -324,9 +383,9 @@ impl Error {
     /// be returned in such a case.
     pub(crate) fn from_kernel_errno(errno: c_types::c_int) -> Error {
         if errno < -(bindings::MAX_ERRNO as i32) || errno >= 0 {
-            // TODO: Make it a `WARN_ONCE` once available.
-            crate::pr_warn!(
-                "attempted to create `Error` with out of range `errno`: {}",
+            crate::warn_once!(
+                true,
+                "attempted to create `Error` with out of range `errno`: {}\n",
                 errno
             );
             return code::EINVAL;
@@ -353,7 +412,9 @@ impl Error {
         self.0
     }
 
-    /// Returns a string representing the error, if one exists.
+    /// Returns the symbolic name of the error (e.g., `ENOENT`), if one exists.
+    ///
+    /// Names are only known if `CONFIG_SYMBOLIC_ERRNAME` is enabled.
     #[cfg(not(testlib))]
     pub fn name(&self) -> Option<&'static CStr> {
         // SAFETY: Just an FFI call, there are no extra safety requirements.
@@ -366,7 +427,7 @@ impl Error {
         }
     }
 
-    /// Returns a string representing the error, if one exists.
+    /// Returns the symbolic name of the error (e.g., `ENOENT`), if one exists.
     ///
     /// When `testlib` is configured, this always returns `None` to avoid the dependency on a
     /// kernel function so that tests that use this (e.g., by calling [`Result::unwrap`]) can still
@@ -390,6 +451,26 @@ impl fmt::Debug for Error {
     }
 }
 
+/// Prints the symbolic name of the error (e.g., `ENOENT`), or `errno -N` if it has none (see
+/// [`Error::name`]).
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// // Prints "open failed: ENOENT".
+/// pr_err!("open failed: {}\n", ENOENT);
+/// ```
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            None => write!(f, "errno {}", self.0),
+            // SAFETY: These strings are ASCII-only.
+            Some(name) => f.write_str(unsafe { str::from_utf8_unchecked(name) }),
+        }
+    }
+}
+
 impl From<TryFromIntError> for Error {
     fn from(_: TryFromIntError) -> Error {
         code::EINVAL
@@ -457,11 +538,35 @@ impl From<AllocError> for Error {
 // # Invariant: `-bindings::MAX_ERRNO` fits in an `i16`.
 crate::static_assert!(bindings::MAX_ERRNO <= -(i16::MIN as i32) as u32);
 
-pub(crate) fn from_kernel_result_helper<T>(r: Result<T>) -> T
+/// Calls `f` and transforms its [`Result`] into a kernel C integer result.
+///
+/// This is useful when calling Rust functions that return [`crate::error::Result<T>`]
+/// from inside `extern "C"` functions that need to return an integer
+/// error result: the body of the callback goes in the closure, where `?` can be used.
+///
+/// `T` should be convertible from an `i16` via `From<i16>`.
+///
+/// # Examples
+///
+/// ```ignore
+/// # use kernel::error::from_result;
+/// # use kernel::c_types;
+/// # use kernel::bindings;
+/// unsafe extern "C" fn probe_callback(
+///     pdev: *mut bindings::platform_device,
+/// ) -> c_types::c_int {
+///     from_result(|| {
+///         let ptr = devm_alloc(pdev)?;
+///         bindings::platform_set_drvdata(pdev, ptr);
+///         Ok(0)
+///     })
+/// }
+/// ```
+pub fn from_result<T>(f: impl FnOnce() -> Result<T>) -> T
 where
     T: From<i16>,
 {
-    match r {
+    match f() {
         Ok(v) => v,
         // NO-OVERFLOW: negative `errno`s are no smaller than `-bindings::MAX_ERRNO`,
         // `-bindings::MAX_ERRNO` fits in an `i16` as per invariant above,
@@ -470,13 +575,18 @@ where
     }
 }
 
-/// Transforms a [`crate::error::Result<T>`] to a kernel C integer result.
+/// Calls `f` and transforms its [`Result`] into a pointer or a kernel "error pointer".
 ///
-/// This is useful when calling Rust functions that return [`crate::error::Result<T>`]
-/// from inside `extern "C"` functions that need to return an integer
-/// error result.
+/// This is the counterpart of [`from_result`] for `extern "C"` functions that need to return
+/// either a valid pointer or an `errno` encoded with `ERR_PTR`. See also [`to_kernel_err_ptr`].
+pub fn from_result_ptr<T>(f: impl FnOnce() -> Result<*mut T>) -> *mut T {
+    to_kernel_err_ptr(f())
+}
+
+/// Transforms a [`crate::error::Result<T>`] to a kernel C integer result.
 ///
-/// `T` should be convertible to an `i16` via `From<i16>`.
+/// This is a shorthand for calling [`from_result`] with a closure containing the given
+/// statements.
 ///
 /// # Examples
 ///
@@ -496,9 +606,9 @@ where
 /// ```
 macro_rules! from_kernel_result {
     ($($tt:tt)*) => {{
-        $crate::error::from_kernel_result_helper((|| {
+        $crate::error::from_result(|| {
             $($tt)*
-        })())
+        })
     }};
 }
 
@@ -515,7 +625,7 @@ pub(crate) use from_kernel_result;
 ///
 /// ```ignore
 /// # use kernel::prelude::*;
-/// # use kernel::from_kernel_err_ptr;
+/// # use kernel::error::from_kernel_err_ptr;
 /// # use kernel::c_types;
 /// # use kernel::bindings;
 /// fn devm_platform_ioremap_resource(
@@ -531,9 +641,7 @@ pub(crate) use from_kernel_result;
 ///     }
 /// }
 /// ```
-// TODO: Remove `dead_code` marker once an in-kernel client is available.
-#[allow(dead_code)]
-pub(crate) fn from_kernel_err_ptr<T>(ptr: *mut T) -> Result<*mut T> {
+pub fn from_kernel_err_ptr<T>(ptr: *mut T) -> Result<*mut T> {
     // CAST: Casting a pointer to `*const c_types::c_void` is always valid.
     let const_ptr: *const c_types::c_void = ptr.cast();
     // SAFETY: The FFI function does not deref the pointer.
@@ -554,6 +662,35 @@ pub(crate) fn from_kernel_err_ptr<T>(ptr: *mut T) -> Result<*mut T> {
     Ok(ptr)
 }
 
+/// Transforms a [`Result`] holding a pointer into a kernel "error pointer".
+///
+/// This is the inverse of [`from_kernel_err_ptr`]: it is useful when implementing, in Rust,
+/// callbacks that C code expects to return either a valid pointer or an `errno` encoded with
+/// `ERR_PTR`.
+///
+/// # Examples
+///
+/// ```ignore
+/// # use kernel::prelude::*;
+/// # use kernel::error::to_kernel_err_ptr;
+/// # use kernel::bindings;
+/// unsafe extern "C" fn lookup_callback(
+///     dir: *mut bindings::inode,
+///     dentry: *mut bindings::dentry,
+///     flags: u32,
+/// ) -> *mut bindings::dentry {
+///     to_kernel_err_ptr(lookup(dir, dentry, flags))
+/// }
+/// ```
+pub fn to_kernel_err_ptr<T>(r: Result<*mut T>) -> *mut T {
+    match r {
+        Ok(ptr) => ptr,
+        // SAFETY: The FFI function does not deref the pointer, it only encodes the error code,
+        // which is in the valid range by the type invariants of `Error`.
+        Err(e) => unsafe { bindings::ERR_PTR(e.to_kernel_errno() as _) }.cast(),
+    }
+}
+
 /// Calls a kernel function that returns an integer error code on failure and converts the result
 /// to a [`Result`].
 pub fn to_result(func: impl FnOnce() -> c_types::c_int) -> Result {
@@ -10,9 +10,11 @@ use crate::{
     cred::Credential,
     error::{code::*, from_kernel_result, Error, Result},
     io_buffer::{IoBufferReader, IoBufferWriter},
+    ioctl,
     iov_iter::IovIter,
     mm,
-    sync::CondVar,
+    sync::{CondVar, WaitQueue},
+    task::Task,
     types::PointerWrapper,
     user_ptr::{UserSlicePtr, UserSlicePtrReader, UserSlicePtrWriter},
     ARef, AlwaysRefCounted,
@@ -186,6 +188,30 @@ impl PollTable {
             unsafe { proc(file.0.get() as _, cv.wait_list.get(), self.ptr) }
         }
     }
+
+    /// Associates the given file and wait queue to this poll table. It means waking up the wait
+    /// queue will notify the poll table as well; additionally, the association between the wait
+    /// queue and the file will automatically be undone by the kernel when the file is destructed.
+    /// To unilaterally remove the association before then, one can call
+    /// [`WaitQueue::free_waiters`].
+    ///
+    /// # Safety
+    ///
+    /// If the wait queue is destroyed before the file, then [`WaitQueue::free_waiters`] must be
+    /// called to ensure that all waiters are flushed out.
+    pub unsafe fn register_wait_queue<'a>(&self, file: &'a File, wq: &'a WaitQueue) {
+        if self.ptr.is_null() {
+            return;
+        }
+
+        // SAFETY: `PollTable::ptr` is guaranteed to be valid by the type invariants and the null
+        // check above.
+        let table = unsafe { &*self.ptr };
+        if let Some(proc) = table._qproc {
+            // SAFETY: All pointers are known to be valid.
+            unsafe { proc(file.0.get() as _, wq.wait_list.get(), self.ptr) }
+        }
+    }
 }
 
 /// Equivalent to [`std::io::SeekFrom`].
@@ -202,6 +228,33 @@ pub enum SeekFrom {
     Current(i64),
 }
 
+/// Returns the result of a read or write that may have been interrupted by a signal.
+///
+/// Like in C drivers, a transfer that is interrupted after some bytes were transferred returns the
+/// number of bytes instead of the error, so the data is not lost; the signal is then handled on
+/// the way back to userspace.
+///
+/// [`ERESTARTSYS`] must only be returned with a signal pending, otherwise it reaches userspace as
+/// an unknown error code, so it is converted to [`EINTR`] (with a warning) in that case.
+fn interrupted_transfer(res: Result<usize>, transferred: usize) -> Result<usize> {
+    match res {
+        Err(e) if is_interruption(e) && transferred > 0 => Ok(transferred),
+        Err(ERESTARTSYS) if !Task::current().signal_pending() => {
+            crate::warn_once!(true, "ERESTARTSYS returned without a pending signal\n");
+            Err(EINTR)
+        }
+        res => res,
+    }
+}
+
+fn is_interruption(e: Error) -> bool {
+    e == EINTR
+        || e == ERESTARTSYS
+        || e == ERESTARTNOINTR
+        || e == ERESTARTNOHAND
+        || e == ERESTART_RESTARTBLOCK
+}
+
 pub(crate) struct OperationsVtable<A, T>(marker::PhantomData<A>, marker::PhantomData<T>);
 
 impl<A: OpenAdapter<T::OpenData>, T: Operations> OperationsVtable<A, T> {
@@ -254,12 +307,13 @@ impl<A: OpenAdapter<T::OpenData>, T: Operations> OperationsVtable<A, T> {
             let f = unsafe { T::Data::borrow((*file).private_data) };
             // No `FMODE_UNSIGNED_OFFSET` support, so `offset` must be in [0, 2^63).
             // See discussion in https://github.com/fishinabarrel/linux-kernel-module-rust/pull/113
-            let read = T::read(
+            let res = T::read(
                 f,
                 unsafe { File::from_ptr(file) },
                 &mut data,
                 unsafe { *offset }.try_into()?,
-            )?;
+            );
+            let read = interrupted_transfer(res, len - data.len())?;
             unsafe { (*offset) += bindings::loff_t::try_from(read).unwrap() };
             Ok(read as _)
         }
@@ -279,8 +333,9 @@ impl<A: OpenAdapter<T::OpenData>, T: Operations> OperationsVtable<A, T> {
             // references to `file` have been released, so we know it can't be called while this
             // function is running.
             let f = unsafe { T::Data::borrow((*file).private_data) };
-            let read =
-                T::read(f, unsafe { File::from_ptr(file) }, &mut iter, offset.try_into()?)?;
+            let len = IoBufferWriter::len(&iter);
+            let res = T::read(f, unsafe { File::from_ptr(file) }, &mut iter, offset.try_into()?);
+            let read = interrupted_transfer(res, len - IoBufferWriter::len(&iter))?;
             unsafe { (*iocb).ki_pos += bindings::loff_t::try_from(read).unwrap() };
             Ok(read as _)
         }
@@ -302,12 +357,13 @@ impl<A: OpenAdapter<T::OpenData>, T: Operations> OperationsVtable<A, T> {
             let f = unsafe { T::Data::borrow((*file).private_data) };
             // No `FMODE_UNSIGNED_OFFSET` support, so `offset` must be in [0, 2^63).
             // See discussion in https://github.com/fishinabarrel/linux-kernel-module-rust/pull/113
-            let written = T::write(
+            let res = T::write(
                 f,
                 unsafe { File::from_ptr(file) },
                 &mut data,
                 unsafe { *offset }.try_into()?
-            )?;
+            );
+            let written = interrupted_transfer(res, len - data.len())?;
             unsafe { (*offset) += bindings::loff_t::try_from(written).unwrap() };
             Ok(written as _)
         }
@@ -327,8 +383,9 @@ impl<A: OpenAdapter<T::OpenData>, T: Operations> OperationsVtable<A, T> {
             // references to `file` have been released, so we know it can't be called while this
             // function is running.
             let f = unsafe { T::Data::borrow((*file).private_data) };
-            let written =
-                T::write(f, unsafe { File::from_ptr(file) }, &mut iter, offset.try_into()?)?;
+            let len = IoBufferReader::len(&iter);
+            let res = T::write(f, unsafe { File::from_ptr(file) }, &mut iter, offset.try_into()?);
+            let written = interrupted_transfer(res, len - IoBufferReader::len(&iter))?;
             unsafe { (*iocb).ki_pos += bindings::loff_t::try_from(written).unwrap() };
             Ok(written as _)
         }
@@ -673,7 +730,7 @@ pub struct IoctlCommand {
 impl IoctlCommand {
     /// Constructs a new [`IoctlCommand`].
     fn new(cmd: u32, arg: usize) -> Self {
-        let size = (cmd >> bindings::_IOC_SIZESHIFT) & bindings::_IOC_SIZEMASK;
+        let size = ioctl::_IOC_SIZE(cmd);
 
         // SAFETY: We only create one instance of the user slice per ioctl call, so TOCTOU issues
         // are not possible.
@@ -696,7 +753,7 @@ impl IoctlCommand {
         handler: T::Target<'_>,
         file: &File,
     ) -> Result<i32> {
-        let dir = (self.cmd >> bindings::_IOC_DIRSHIFT) & bindings::_IOC_DIRMASK;
+        let dir = ioctl::_IOC_DIR(self.cmd);
         if dir == bindings::_IOC_NONE {
             return T::pure(handler, file, self.cmd, self.arg);
         }
@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Firmware loading.
+//!
+//! Drivers request firmware images by name, e.g., `vendor/device-fw.bin`, which are looked up in
+//! the firmware search path (usually `/lib/firmware`) or in images built into the kernel.
+//!
+//! C header: [`include/linux/firmware.h`](../../../../include/linux/firmware.h)
+
+use crate::{bindings, c_types, device::RawDevice, error::to_result, str::CStr, Result};
+use core::{ops::Deref, ptr};
+
+/// A firmware image loaded for a device.
+///
+/// The image is released when the [`Firmware`] is dropped.
+///
+/// # Invariants
+///
+/// `ptr` is valid and was returned by one of the `request_firmware` variants.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::{c_str, device::RawDevice, firmware::Firmware};
+///
+/// fn load(dev: &dyn RawDevice) -> Result {
+///     let fw = Firmware::request(c_str!("vendor/device-fw.bin"), dev)?;
+///     if fw.len() < 4 {
+///         return Err(EINVAL);
+///     }
+///     pr_info!("Firmware version {:?}\n", &fw[..4]);
+///     Ok(())
+/// }
+/// ```
+pub struct Firmware {
+    ptr: *const bindings::firmware,
+}
+
+// SAFETY: The image is not modified once loaded, and it can be released from any thread.
+unsafe impl Send for Firmware {}
+
+// SAFETY: The image is not modified once loaded.
+unsafe impl Sync for Firmware {}
+
+impl Firmware {
+    fn request_with(
+        name: &CStr,
+        dev: &dyn RawDevice,
+        f: unsafe extern "C" fn(
+            *mut *const bindings::firmware,
+            *const c_types::c_char,
+            *mut bindings::device,
+        ) -> c_types::c_int,
+    ) -> Result<Self> {
+        let mut fw = ptr::null();
+        // SAFETY: `fw` is valid for writes, `name` is a valid C string and `dev` is valid by the
+        // safety requirements of `RawDevice`.
+        to_result(|| unsafe { f(&mut fw, name.as_char_ptr(), dev.raw_device()) })?;
+
+        // INVARIANT: On success, `fw` is a valid image returned by `f`.
+        Ok(Self { ptr: fw })
+    }
+
+    /// Loads the firmware image `name` for `dev`.
+    ///
+    /// If the image isn't found in the filesystem, it may be loaded by the user-mode helper (if
+    /// enabled). A warning is printed if the image can't be found.
+    pub fn request(name: &CStr, dev: &dyn RawDevice) -> Result<Self> {
+        Self::request_with(name, dev, bindings::request_firmware)
+    }
+
+    /// Loads the firmware image `name` for `dev`, without printing a warning if it can't be found.
+    ///
+    /// It is useful for optional images, e.g., calibration data the device can work without.
+    pub fn request_nowarn(name: &CStr, dev: &dyn RawDevice) -> Result<Self> {
+        Self::request_with(name, dev, bindings::firmware_request_nowarn)
+    }
+
+    /// Loads the firmware image `name` for `dev` directly from the filesystem, without falling
+    /// back to the user-mode helper.
+    pub fn request_direct(name: &CStr, dev: &dyn RawDevice) -> Result<Self> {
+        Self::request_with(name, dev, bindings::request_firmware_direct)
+    }
+
+    /// Returns the contents of the image.
+    pub fn data(&self) -> &[u8] {
+        // SAFETY: By the type invariants, `ptr` is valid, and `data` points to `size` bytes that
+        // remain valid until the image is released.
+        unsafe { core::slice::from_raw_parts((*self.ptr).data, (*self.ptr).size) }
+    }
+}
+
+impl Deref for Firmware {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.data()
+    }
+}
+
+impl Drop for Firmware {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `ptr` was returned by `request_firmware` and is released
+        // only once.
+        unsafe { bindings::release_firmware(self.ptr) };
+    }
+}
@@ -0,0 +1,208 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Folios.
+//!
+//! A folio is a physically contiguous set of pages (of which it is the head), mostly used by the
+//! page cache. File systems read and write the contents of files in units of folios.
+//!
+//! C headers: [`include/linux/mm_types.h`](../../../../include/linux/mm_types.h) and
+//! [`include/linux/pagemap.h`](../../../../include/linux/pagemap.h)
+
+use crate::{bindings, error::code::*, ARef, AlwaysRefCounted, Result, PAGE_SIZE};
+use core::{cell::UnsafeCell, ops::Deref, ptr};
+
+/// Wraps the kernel's `struct folio`.
+///
+/// # Invariants
+///
+/// Instances of this type are always ref-counted, that is, a call to `folio_get` ensures that the
+/// allocation remains valid at least until the matching call to `folio_put`.
+#[repr(transparent)]
+pub struct Folio(pub(crate) UnsafeCell<bindings::folio>);
+
+// SAFETY: Folios are reference-counted and their functions are safe to call from any thread.
+unsafe impl Send for Folio {}
+
+// SAFETY: The folio fields that are accessed are only modified with the folio locked, which is
+// only mutated through `LockedFolio`.
+unsafe impl Sync for Folio {}
+
+impl Folio {
+    /// Creates a reference to a [`Folio`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the lifetime of the
+    /// returned [`Folio`] reference.
+    pub unsafe fn from_ptr<'a>(ptr: *const bindings::folio) -> &'a Self {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `Folio` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns a raw pointer to the underlying `struct folio`.
+    pub fn as_ptr(&self) -> *mut bindings::folio {
+        self.0.get()
+    }
+
+    /// Returns the index of the folio in its file, in pages.
+    pub fn index(&self) -> u64 {
+        // SAFETY: The folio is valid because the shared reference guarantees a nonzero refcount.
+        unsafe { ptr::addr_of!((*self.as_ptr()).index).read() as _ }
+    }
+
+    /// Returns the position of the folio in its file, in bytes.
+    pub fn pos(&self) -> i64 {
+        // SAFETY: The folio is valid because the shared reference guarantees a nonzero refcount.
+        unsafe { bindings::folio_pos(self.as_ptr()) }
+    }
+
+    /// Returns the size of the folio in bytes.
+    pub fn size(&self) -> usize {
+        // SAFETY: The folio is valid because the shared reference guarantees a nonzero refcount.
+        unsafe { bindings::folio_size(self.as_ptr()) }
+    }
+
+    /// Returns whether the folio holds the current contents of the file.
+    pub fn is_uptodate(&self) -> bool {
+        // SAFETY: The folio is valid because the shared reference guarantees a nonzero refcount.
+        unsafe { bindings::folio_test_uptodate(self.as_ptr()) }
+    }
+
+    /// Locks the folio, sleeping until it is available.
+    ///
+    /// The folio is unlocked when the returned guard is dropped.
+    pub fn lock(&self) -> LockedFolio {
+        crate::might_sleep!();
+        // SAFETY: The folio is valid because the shared reference guarantees a nonzero refcount.
+        unsafe { bindings::folio_lock(self.as_ptr()) };
+
+        // INVARIANT: The folio was locked above.
+        LockedFolio { folio: self.into() }
+    }
+
+    /// Tries to lock the folio, returning `None` if it is already locked.
+    pub fn try_lock(&self) -> Option<LockedFolio> {
+        // SAFETY: The folio is valid because the shared reference guarantees a nonzero refcount.
+        if !unsafe { bindings::folio_trylock(self.as_ptr()) } {
+            return None;
+        }
+
+        // INVARIANT: The folio was locked above.
+        Some(LockedFolio { folio: self.into() })
+    }
+}
+
+// SAFETY: The type invariants guarantee that `Folio` is always ref-counted.
+unsafe impl AlwaysRefCounted for Folio {
+    fn inc_ref(&self) {
+        // SAFETY: The existence of a shared reference means that the refcount is nonzero.
+        unsafe { bindings::folio_get(self.as_ptr()) };
+    }
+
+    unsafe fn dec_ref(obj: ptr::NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee that the refcount is nonzero.
+        unsafe { bindings::folio_put(obj.cast().as_ptr()) };
+    }
+}
+
+/// A locked [`Folio`].
+///
+/// Its contents can only be modified while it is locked. The folio is unlocked when the instance
+/// is dropped.
+///
+/// # Invariants
+///
+/// The folio is locked, and the lock is owned by the instance.
+pub struct LockedFolio {
+    folio: ARef<Folio>,
+}
+
+impl LockedFolio {
+    /// Creates an instance from a folio that the caller locked.
+    ///
+    /// # Safety
+    ///
+    /// The caller must own the lock of the folio, which is transferred to the new instance.
+    pub(crate) unsafe fn from_locked(folio: ARef<Folio>) -> Self {
+        // INVARIANT: The safety requirements guarantee that the folio is locked.
+        Self { folio }
+    }
+
+    /// Marks the folio as holding the current contents of the file, e.g., after reading them.
+    pub fn mark_uptodate(&self) {
+        // SAFETY: The folio is valid, and it is locked by the type invariants.
+        unsafe { bindings::folio_mark_uptodate(self.as_ptr()) };
+    }
+
+    /// Copies `data` into the folio, starting at `offset`.
+    pub fn write(&self, offset: usize, data: &[u8]) -> Result {
+        self.for_each_chunk(offset, data.len(), |ptr, start, len| {
+            // SAFETY: `ptr` is valid for `len` bytes, and the folio is locked, so no one else
+            // writes to it.
+            unsafe { ptr::copy_nonoverlapping(data.as_ptr().add(start), ptr, len) };
+        })
+    }
+
+    /// Fills `len` bytes of the folio, starting at `offset`, with zeroes.
+    pub fn zero(&self, offset: usize, len: usize) -> Result {
+        self.for_each_chunk(offset, len, |ptr, _, len| {
+            // SAFETY: `ptr` is valid for `len` bytes, and the folio is locked, so no one else
+            // writes to it.
+            unsafe { ptr::write_bytes(ptr, 0, len) };
+        })
+    }
+
+    /// Copies the contents of the folio, starting at `offset`, into `data`.
+    pub fn read(&self, offset: usize, data: &mut [u8]) -> Result {
+        let dest = data.as_mut_ptr();
+        self.for_each_chunk(offset, data.len(), |ptr, start, len| {
+            // SAFETY: `ptr` is valid for `len` bytes, and `dest` for `start + len` bytes.
+            unsafe { ptr::copy_nonoverlapping(ptr, dest.add(start), len) };
+        })
+    }
+
+    /// Maps the folio one page at a time and calls `f` with the address, the offset into the
+    /// range and the length of each chunk.
+    fn for_each_chunk(
+        &self,
+        offset: usize,
+        len: usize,
+        mut f: impl FnMut(*mut u8, usize, usize),
+    ) -> Result {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.size() => {}
+            _ => return Err(EINVAL),
+        }
+
+        let mut done = 0;
+        while done < len {
+            let pos = offset + done;
+            let chunk = (len - done).min(PAGE_SIZE - pos % PAGE_SIZE);
+
+            // SAFETY: The folio is valid, and `pos` is within it as checked above.
+            let ptr = unsafe { bindings::kmap_local_folio(self.as_ptr(), pos) } as *mut u8;
+            f(ptr, done, chunk);
+            // SAFETY: `ptr` was mapped above, and it is the last mapping of the current task.
+            unsafe { bindings::kunmap_local(ptr as _) };
+
+            done += chunk;
+        }
+        Ok(())
+    }
+}
+
+impl Deref for LockedFolio {
+    type Target = Folio;
+
+    fn deref(&self) -> &Folio {
+        &self.folio
+    }
+}
+
+impl Drop for LockedFolio {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, the folio is locked and the lock is owned by `self`.
+        unsafe { bindings::folio_unlock(self.as_ptr()) };
+    }
+}
@@ -0,0 +1,539 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! File system objects: inodes, directory entries and page cache mappings.
+//!
+//! C headers: [`include/linux/fs.h`](../../../../include/linux/fs.h),
+//! [`include/linux/dcache.h`](../../../../include/linux/dcache.h) and
+//! [`include/linux/pagemap.h`](../../../../include/linux/pagemap.h)
+
+use crate::{
+    bindings, c_types,
+    error::{from_kernel_err_ptr, from_kernel_result, to_result},
+    file::File,
+    folio::{Folio, LockedFolio},
+    pages::Page,
+    str::CStr,
+    ARef, AlwaysRefCounted, Result,
+};
+use core::{cell::UnsafeCell, marker::PhantomData, ptr};
+
+/// Wraps the kernel's `struct inode`.
+///
+/// # Invariants
+///
+/// Instances of this type are always ref-counted, that is, a call to `ihold` ensures that the
+/// allocation remains valid at least until the matching call to `iput`.
+#[repr(transparent)]
+pub struct INode(pub(crate) UnsafeCell<bindings::inode>);
+
+impl INode {
+    /// Creates a reference to an [`INode`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the lifetime of the
+    /// returned [`INode`] reference.
+    pub unsafe fn from_ptr<'a>(ptr: *const bindings::inode) -> &'a Self {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `INode` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns the inode number (`struct inode::i_ino`).
+    pub fn ino(&self) -> u64 {
+        // SAFETY: The inode is valid because the shared reference guarantees a nonzero refcount.
+        unsafe { ptr::addr_of!((*self.0.get()).i_ino).read() as _ }
+    }
+
+    /// Finds a hashed alias of the inode.
+    ///
+    /// Directories have at most one alias. For other inodes, a connected alias is preferred over
+    /// a disconnected one, and `None` is returned if the inode has no hashed aliases.
+    ///
+    /// Equivalent to the kernel's `d_find_alias`.
+    pub fn find_alias(&self) -> Option<ARef<DEntry>> {
+        // SAFETY: The inode is valid because the shared reference guarantees a nonzero refcount.
+        let ptr = ptr::NonNull::new(unsafe { bindings::d_find_alias(self.0.get()) })?;
+
+        // SAFETY: `d_find_alias` increments the refcount of the dentry it returns.
+        Some(unsafe { ARef::from_raw(ptr.cast()) })
+    }
+
+    /// Finds any alias of the inode, hashed or not.
+    ///
+    /// This is useful when the caller only needs a dentry to get to the super block or to the
+    /// inode, for example, when invalidating cached data.
+    ///
+    /// Equivalent to the kernel's `d_find_any_alias`.
+    pub fn find_any_alias(&self) -> Option<ARef<DEntry>> {
+        // SAFETY: The inode is valid because the shared reference guarantees a nonzero refcount.
+        let ptr = ptr::NonNull::new(unsafe { bindings::d_find_any_alias(self.0.get()) })?;
+
+        // SAFETY: `d_find_any_alias` increments the refcount of the dentry it returns.
+        Some(unsafe { ARef::from_raw(ptr.cast()) })
+    }
+
+    /// Returns the page cache mapping of the inode's contents (`struct inode::i_mapping`).
+    pub fn mapping(&self) -> &AddressSpace {
+        // SAFETY: The inode is valid because the shared reference guarantees a nonzero refcount,
+        // and its mapping lives as long as it.
+        unsafe { AddressSpace::from_ptr(ptr::addr_of!((*self.0.get()).i_mapping).read()) }
+    }
+}
+
+// SAFETY: The type invariants guarantee that `INode` is always ref-counted.
+unsafe impl AlwaysRefCounted for INode {
+    fn inc_ref(&self) {
+        // SAFETY: The existence of a shared reference means that the refcount is nonzero.
+        unsafe { bindings::ihold(self.0.get()) };
+    }
+
+    unsafe fn dec_ref(obj: ptr::NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee that the refcount is nonzero.
+        unsafe { bindings::iput(obj.cast().as_ptr()) };
+    }
+}
+
+/// Wraps the kernel's `struct dentry`.
+///
+/// # Invariants
+///
+/// Instances of this type are always ref-counted, that is, a call to `dget` ensures that the
+/// allocation remains valid at least until the matching call to `dput`.
+#[repr(transparent)]
+pub struct DEntry(pub(crate) UnsafeCell<bindings::dentry>);
+
+impl DEntry {
+    /// Creates a reference to a [`DEntry`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the lifetime of the
+    /// returned [`DEntry`] reference.
+    pub unsafe fn from_ptr<'a>(ptr: *const bindings::dentry) -> &'a Self {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `DEntry` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns the name of the directory entry.
+    ///
+    /// The name may change concurrently on rename, so callers that need a stable name must hold
+    /// the appropriate locks (e.g., the parent directory's `i_rwsem`).
+    pub fn name(&self) -> &CStr {
+        // SAFETY: The dentry is valid because the shared reference guarantees a nonzero refcount.
+        let name = unsafe { ptr::addr_of!((*self.0.get()).d_name.name).read() };
+
+        // SAFETY: `d_name.name` is always `NUL`-terminated, and it remains valid while the caller
+        // prevents the dentry from being renamed.
+        unsafe { CStr::from_char_ptr(name as _) }
+    }
+
+    /// Returns the inode the directory entry refers to, or `None` if it is a negative entry.
+    pub fn inode(&self) -> Option<&INode> {
+        // SAFETY: The dentry is valid because the shared reference guarantees a nonzero refcount.
+        let inode = unsafe { ptr::addr_of!((*self.0.get()).d_inode).read() };
+        if inode.is_null() {
+            None
+        } else {
+            // SAFETY: A positive dentry holds a reference to its inode, and the lifetime of the
+            // returned reference is tied to `self`.
+            Some(unsafe { INode::from_ptr(inode) })
+        }
+    }
+
+    /// Returns the parent directory entry.
+    ///
+    /// The root of a file system is its own parent.
+    ///
+    /// Equivalent to the kernel's `dget_parent`.
+    pub fn parent(&self) -> ARef<DEntry> {
+        // SAFETY: The dentry is valid because the shared reference guarantees a nonzero refcount.
+        let ptr = unsafe { bindings::dget_parent(self.0.get()) };
+
+        // SAFETY: `dget_parent` never returns null and increments the refcount of the dentry it
+        // returns.
+        unsafe { ARef::from_raw(ptr::NonNull::new_unchecked(ptr.cast())) }
+    }
+
+    /// Calls `f` on each child of the directory entry, until `f` returns `false`.
+    ///
+    /// The directory entry's `d_lock` is held while walking the children, so `f` must not sleep.
+    /// Cursors used by `readdir` are skipped. Callers that need to operate on a child outside of
+    /// `f` must take a reference to it, for example, with [`ARef::from`].
+    pub fn for_each_child(&self, mut f: impl FnMut(&DEntry) -> bool) {
+        let parent = self.0.get();
+
+        // SAFETY: The dentry is valid because the shared reference guarantees a nonzero refcount.
+        unsafe { bindings::spin_lock(ptr::addr_of_mut!((*parent).d_lock)) };
+
+        // SAFETY: The list of children is protected by `d_lock`, which is held.
+        let head = unsafe { ptr::addr_of_mut!((*parent).d_subdirs) };
+        // SAFETY: `head` is a valid, initialised list head.
+        let mut next = unsafe { (*head).next };
+        while next != head {
+            let child =
+                crate::container_of!(next, bindings::dentry, d_child) as *mut bindings::dentry;
+
+            // SAFETY: `next` is an entry of the list of children, so `child` is a valid dentry
+            // that cannot be freed while the parent's `d_lock` is held.
+            let flags = unsafe { ptr::addr_of!((*child).d_flags).read() };
+            if flags & bindings::DCACHE_DENTRY_CURSOR == 0 {
+                // SAFETY: As above, `child` is valid for the duration of the call to `f`.
+                if !f(unsafe { DEntry::from_ptr(child) }) {
+                    break;
+                }
+            }
+
+            // SAFETY: `next` is still in the list because the lock was held throughout.
+            next = unsafe { (*next).next };
+        }
+
+        // SAFETY: The lock was acquired above.
+        unsafe { bindings::spin_unlock(ptr::addr_of_mut!((*parent).d_lock)) };
+    }
+
+    /// Removes the directory entry from the dcache hash, so that subsequent lookups miss it.
+    ///
+    /// This is typically used by network file systems when the server reports that the object
+    /// has gone away or changed.
+    ///
+    /// Equivalent to the kernel's `d_drop`.
+    pub fn drop_from_hash(&self) {
+        // SAFETY: The dentry is valid because the shared reference guarantees a nonzero refcount.
+        unsafe { bindings::d_drop(self.0.get()) };
+    }
+}
+
+// SAFETY: The type invariants guarantee that `DEntry` is always ref-counted.
+unsafe impl AlwaysRefCounted for DEntry {
+    fn inc_ref(&self) {
+        // SAFETY: The existence of a shared reference means that the refcount is nonzero.
+        unsafe { bindings::dget(self.0.get()) };
+    }
+
+    unsafe fn dec_ref(obj: ptr::NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee that the refcount is nonzero.
+        unsafe { bindings::dput(obj.cast().as_ptr()) };
+    }
+}
+
+/// Wraps the kernel's `struct address_space`, the page cache mapping of an inode.
+///
+/// # Invariants
+///
+/// Instances of this type are only used by reference, and the mapping lives as long as its
+/// inode.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::fs::INode;
+///
+/// fn first_byte(inode: &INode) -> Result<u8> {
+///     let folio = inode.mapping().read_folio(0)?;
+///     let mut byte = [0];
+///     folio.lock().read(0, &mut byte)?;
+///     Ok(byte[0])
+/// }
+/// ```
+#[repr(transparent)]
+pub struct AddressSpace(pub(crate) UnsafeCell<bindings::address_space>);
+
+// SAFETY: The mapping functions are safe to call from any thread.
+unsafe impl Send for AddressSpace {}
+
+// SAFETY: The mapping functions are safe to call concurrently.
+unsafe impl Sync for AddressSpace {}
+
+impl AddressSpace {
+    /// Creates a reference to an [`AddressSpace`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the lifetime of the
+    /// returned [`AddressSpace`] reference.
+    pub unsafe fn from_ptr<'a>(ptr: *const bindings::address_space) -> &'a Self {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `AddressSpace` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns the inode that owns the mapping.
+    pub fn host(&self) -> &INode {
+        // SAFETY: The mapping is valid by the type invariants, and it lives as long as its inode.
+        unsafe { INode::from_ptr(ptr::addr_of!((*self.0.get()).host).read()) }
+    }
+
+    /// Returns the folio cached at the given index (in pages), if any.
+    ///
+    /// The folio may not be up to date, e.g., if it is being read.
+    ///
+    /// Equivalent to the kernel's `filemap_get_folio`.
+    pub fn get_folio(&self, index: u64) -> Option<ARef<Folio>> {
+        // SAFETY: The mapping is valid by the type invariants.
+        let ptr =
+            ptr::NonNull::new(unsafe { bindings::filemap_get_folio(self.0.get(), index as _) })?;
+
+        // SAFETY: `filemap_get_folio` increments the refcount of the folio it returns.
+        Some(unsafe { ARef::from_raw(ptr.cast()) })
+    }
+
+    /// Adds a newly allocated page to the mapping at the given index (in pages), allocating the
+    /// cache's metadata with the given flags (see [`crate::gfp::Flags`]).
+    ///
+    /// The page is returned locked, so that it can be filled (e.g., by reading it from the disk)
+    /// and marked up to date before other tasks use it.
+    ///
+    /// Equivalent to the kernel's `add_to_page_cache_lru`.
+    pub fn add_page(&self, page: Page, index: u64, flags: u32) -> Result<LockedFolio> {
+        // SAFETY: The mapping is valid by the type invariants, and `page` is a newly allocated
+        // page that isn't in any mapping yet.
+        to_result(|| unsafe {
+            bindings::add_to_page_cache_lru(page.as_ptr(), self.0.get(), index as _, flags)
+        })?;
+
+        // The reference of the allocation becomes the reference to the folio, which frees the
+        // page when it is dropped and removed from the cache.
+        let page = page.into_raw();
+
+        // SAFETY: `page` is valid, and it is the head of its folio since it was allocated on its
+        // own.
+        let folio = unsafe { bindings::page_folio(page) };
+
+        // SAFETY: The reference of the allocation is transferred to the folio, and
+        // `add_to_page_cache_lru` locked the page.
+        Ok(unsafe {
+            LockedFolio::from_locked(ARef::from_raw(ptr::NonNull::new_unchecked(folio).cast()))
+        })
+    }
+
+    /// Returns the folio at the given index (in pages), reading it with the mapping's
+    /// `read_folio` (or `readpage`) operation if it isn't cached or up to date.
+    ///
+    /// Equivalent to the kernel's `read_mapping_folio`.
+    pub fn read_folio(&self, index: u64) -> Result<ARef<Folio>> {
+        // SAFETY: The mapping is valid by the type invariants. The file may be null.
+        let ptr = from_kernel_err_ptr(unsafe {
+            bindings::read_mapping_folio(self.0.get(), index as _, ptr::null_mut())
+        })?;
+
+        // SAFETY: `read_mapping_folio` increments the refcount of the folio it returns.
+        Ok(unsafe { ARef::from_raw(ptr::NonNull::new_unchecked(ptr).cast()) })
+    }
+
+    /// Sets the operations of the mapping to the ones implemented by `T`.
+    ///
+    /// # Safety
+    ///
+    /// The mapping must not be in use yet, e.g., because its inode is still being initialised.
+    pub unsafe fn set_operations<T: AddressSpaceOperations>(&self) {
+        // SAFETY: The mapping is valid by the type invariants, and the safety requirements
+        // guarantee that no one else accesses its operations.
+        unsafe { (*self.0.get()).a_ops = &AddressSpaceOperationsVtable::<T>::VTABLE };
+    }
+
+    /// Writes back the dirty pages in the given byte range (inclusive) and waits for the writes
+    /// to complete.
+    ///
+    /// Equivalent to the kernel's `filemap_write_and_wait_range`.
+    pub fn write_and_wait_range(&self, start: i64, end: i64) -> Result {
+        crate::might_sleep!();
+        // SAFETY: The mapping is valid by the type invariants.
+        to_result(|| unsafe { bindings::filemap_write_and_wait_range(self.0.get(), start, end) })
+    }
+}
+
+/// The operations of a page cache mapping, which read the contents of files from their backing
+/// store.
+pub trait AddressSpaceOperations {
+    /// The methods to use to populate [`struct address_space_operations`].
+    const TO_USE: AddressSpaceToUse;
+
+    /// Reads the contents of a folio, e.g., when a user reads from the file.
+    ///
+    /// The folio must be marked up to date once its contents have been read. It is unlocked when
+    /// `folio` is dropped, which may happen after this function returns (e.g., when the read
+    /// completes).
+    fn readpage(file: Option<&File>, folio: LockedFolio) -> Result;
+
+    /// Reads the contents of the folios of a readahead request, e.g., when a user reads a file
+    /// sequentially.
+    ///
+    /// The folios are taken from `ractl` (see [`ReadaheadControl::next_folio`]), and must be
+    /// marked up to date once their contents have been read. Folios that are not taken are read
+    /// later with [`AddressSpaceOperations::readpage`].
+    fn readahead(_ractl: &mut ReadaheadControl) {}
+}
+
+/// Represents which optional fields of [`struct address_space_operations`] should be populated
+/// with pointers.
+pub struct AddressSpaceToUse {
+    /// The `readahead` field of [`struct address_space_operations`].
+    pub readahead: bool,
+}
+
+/// A constant version where all values are to set to `false`, that is, all supported fields will
+/// be set to null pointers.
+pub const ADDRESS_SPACE_USE_NONE: AddressSpaceToUse = AddressSpaceToUse { readahead: false };
+
+/// Defines the [`AddressSpaceOperations::TO_USE`] field based on a list of fields to be populated.
+#[macro_export]
+macro_rules! declare_address_space_operations {
+    () => {
+        const TO_USE: $crate::fs::AddressSpaceToUse = $crate::fs::ADDRESS_SPACE_USE_NONE;
+    };
+    ($($i:ident),+) => {
+        #[allow(clippy::needless_update)]
+        const TO_USE: kernel::fs::AddressSpaceToUse =
+            $crate::fs::AddressSpaceToUse {
+                $($i: true),+ ,
+                ..$crate::fs::ADDRESS_SPACE_USE_NONE
+            };
+    };
+}
+
+struct AddressSpaceOperationsVtable<T>(PhantomData<T>);
+
+impl<T: AddressSpaceOperations> AddressSpaceOperationsVtable<T> {
+    unsafe extern "C" fn readpage_callback(
+        file: *mut bindings::file,
+        page: *mut bindings::page,
+    ) -> c_types::c_int {
+        from_kernel_result! {
+            // SAFETY: The C API guarantees that `file` is either null or valid for the duration
+            // of this call.
+            let file = (!file.is_null()).then(|| unsafe { File::from_ptr(file) });
+
+            // SAFETY: The C API guarantees that `page` is a valid and locked page cache page, so
+            // it is the head of its folio. The lock is transferred to `LockedFolio`, with a new
+            // reference to the folio.
+            let folio = unsafe {
+                let folio = Folio::from_ptr(bindings::page_folio(page));
+                LockedFolio::from_locked(folio.into())
+            };
+            T::readpage(file, folio)?;
+            Ok(0)
+        }
+    }
+
+    unsafe extern "C" fn readahead_callback(ractl: *mut bindings::readahead_control) {
+        // SAFETY: The C API guarantees that `ractl` is valid for the duration of this call, and
+        // `ReadaheadControl` only lives within it.
+        let ractl = unsafe { &mut *ractl.cast() };
+        T::readahead(ractl);
+    }
+
+    const VTABLE: bindings::address_space_operations = bindings::address_space_operations {
+        writepage: None,
+        readpage: Some(Self::readpage_callback),
+        writepages: None,
+        set_page_dirty: None,
+        readpages: None,
+        readahead: if T::TO_USE.readahead {
+            Some(Self::readahead_callback)
+        } else {
+            None
+        },
+        write_begin: None,
+        write_end: None,
+        bmap: None,
+        invalidatepage: None,
+        releasepage: None,
+        freepage: None,
+        direct_IO: None,
+        migratepage: None,
+        isolate_page: None,
+        putback_page: None,
+        launder_page: None,
+        is_partially_uptodate: None,
+        is_dirty_writeback: None,
+        error_remove_page: None,
+        swap_activate: None,
+        swap_deactivate: None,
+    };
+}
+
+/// Wraps the kernel's `struct readahead_control`, a request to read a range of a file ahead of
+/// its use.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::fs::ReadaheadControl;
+///
+/// fn readahead(ractl: &mut ReadaheadControl) {
+///     pr_debug!("reading {} bytes at {}\n", ractl.length(), ractl.pos());
+///     while let Some(folio) = ractl.next_folio() {
+///         // A real file system would read the folio from its backing store.
+///         if folio.zero(0, folio.size()).is_ok() {
+///             folio.mark_uptodate();
+///         }
+///     }
+/// }
+/// ```
+#[repr(transparent)]
+pub struct ReadaheadControl(UnsafeCell<bindings::readahead_control>);
+
+impl ReadaheadControl {
+    /// Returns the file being read, if any.
+    pub fn file(&self) -> Option<&File> {
+        // SAFETY: The request is valid while it is borrowed, and so is its file.
+        let file = unsafe { (*self.0.get()).file };
+        // SAFETY: `file` is valid while the request is, as above.
+        (!file.is_null()).then(|| unsafe { File::from_ptr(file) })
+    }
+
+    /// Returns the mapping the folios are added to.
+    pub fn mapping(&self) -> &AddressSpace {
+        // SAFETY: The request is valid while it is borrowed, and so is its mapping.
+        unsafe { AddressSpace::from_ptr((*self.0.get()).mapping) }
+    }
+
+    /// Returns the number of pages left in the request.
+    pub fn count(&self) -> u32 {
+        // SAFETY: The request is valid while it is borrowed.
+        unsafe { bindings::readahead_count(self.0.get()) }
+    }
+
+    /// Returns the position in the file of the remaining range, in bytes.
+    pub fn pos(&self) -> i64 {
+        // SAFETY: The request is valid while it is borrowed.
+        unsafe { bindings::readahead_pos(self.0.get()) }
+    }
+
+    /// Returns the length of the remaining range, in bytes.
+    pub fn length(&self) -> usize {
+        // SAFETY: The request is valid while it is borrowed.
+        unsafe { bindings::readahead_length(self.0.get()) as _ }
+    }
+
+    /// Returns the index in the file of the remaining range, in pages.
+    pub fn index(&self) -> u64 {
+        // SAFETY: The request is valid while it is borrowed.
+        unsafe { bindings::readahead_index(self.0.get()) as _ }
+    }
+
+    /// Takes the next folio of the request, if any.
+    ///
+    /// The folio is locked and in the page cache. It must be marked up to date once its contents
+    /// have been read, and is unlocked when the returned instance is dropped.
+    pub fn next_folio(&mut self) -> Option<LockedFolio> {
+        // SAFETY: The request is valid while it is borrowed.
+        let folio = ptr::NonNull::new(unsafe { bindings::__readahead_folio(self.0.get()) })?;
+
+        // SAFETY: `__readahead_folio` returns a locked folio, with a reference that is
+        // transferred to the caller.
+        Some(unsafe { LockedFolio::from_locked(ARef::from_raw(folio.cast())) })
+    }
+}
+
+impl Iterator for ReadaheadControl {
+    type Item = LockedFolio;
+
+    fn next(&mut self) -> Option<LockedFolio> {
+        self.next_folio()
+    }
+}
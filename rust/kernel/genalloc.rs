@@ -0,0 +1,257 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! General-purpose allocator for special memory regions.
+//!
+//! A [`GenPool`] manages memory the page allocator doesn't know about, e.g., on-chip SRAM or a
+//! carve-out reserved for a device. The regions are added to the pool as chunks, and allocations
+//! return both their virtual and physical addresses.
+//!
+//! C header: [`include/linux/genalloc.h`](../../../../include/linux/genalloc.h)
+
+use crate::{bindings, c_types, error::code::*, numa::NumaNode, to_result, Result};
+use core::ptr::{self, NonNull};
+
+/// A pool of special memory.
+///
+/// All allocations must be freed before the pool is dropped; otherwise the pool is leaked with a
+/// warning.
+///
+/// # Invariants
+///
+/// `ptr` was returned by `gen_pool_create`, and `min_alloc_order` is its minimum allocation
+/// order.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::genalloc::GenPool;
+/// use kernel::io_mem::MemRemap;
+/// use kernel::numa::NumaNode;
+///
+/// fn sram_pool(sram: &MemRemap, phys: kernel::bindings::phys_addr_t) -> Result<GenPool> {
+///     // Allocate in units of 32 bytes.
+///     let pool = GenPool::try_new(5, NumaNode::ANY)?;
+///
+///     // SAFETY: The SRAM is only used through the pool, and it outlives it.
+///     unsafe { pool.try_add(sram.as_ptr() as usize, phys, sram.size(), NumaNode::ANY) }?;
+///
+///     let buf = pool.try_alloc_aligned(256, 64)?;
+///     pr_debug!("SRAM buffer at {:#x}\n", buf.phys_addr());
+///     drop(buf);
+///     Ok(pool)
+/// }
+/// ```
+pub struct GenPool {
+    ptr: NonNull<bindings::gen_pool>,
+    min_alloc_order: u32,
+}
+
+// SAFETY: The pool functions are safe to call from any thread.
+unsafe impl Send for GenPool {}
+
+// SAFETY: The pool functions are safe to call concurrently, since allocations are lock-free and
+// adding chunks takes a lock.
+unsafe impl Sync for GenPool {}
+
+impl GenPool {
+    /// Creates a new empty pool whose allocations are made in units of `2^min_alloc_order` bytes,
+    /// with its metadata on the given NUMA node.
+    pub fn try_new(min_alloc_order: u32, node: NumaNode) -> Result<Self> {
+        if min_alloc_order >= usize::BITS {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: There are no safety requirements for this FFI call.
+        let ptr = unsafe { bindings::gen_pool_create(min_alloc_order as _, node.as_raw()) };
+
+        // INVARIANT: `ptr` was returned by `gen_pool_create` above.
+        Ok(Self {
+            ptr: NonNull::new(ptr).ok_or(ENOMEM)?,
+            min_alloc_order,
+        })
+    }
+
+    /// Adds a chunk of `size` bytes to the pool, mapped at `virt`, at the physical address
+    /// `phys`. The chunk's metadata is allocated on the given NUMA node.
+    ///
+    /// # Safety
+    ///
+    /// `virt` must be mapped for `size` bytes for the lifetime of the pool, and the memory must
+    /// only be used through the pool.
+    pub unsafe fn try_add(
+        &self,
+        virt: usize,
+        phys: bindings::phys_addr_t,
+        size: usize,
+        node: NumaNode,
+    ) -> Result {
+        // SAFETY: By the type invariants, `ptr` is a valid pool, and the safety requirements
+        // guarantee that the chunk can be handed out by the pool.
+        to_result(|| unsafe {
+            bindings::gen_pool_add_owner(
+                self.ptr.as_ptr(),
+                virt as _,
+                phys,
+                size,
+                node.as_raw(),
+                ptr::null_mut(),
+            )
+        })
+    }
+
+    /// Allocates `size` bytes from the pool.
+    ///
+    /// Allocations are aligned to the minimum allocation unit of the pool.
+    pub fn try_alloc(&self, size: usize) -> Result<GenAlloc<'_>> {
+        // SAFETY: By the type invariants, `ptr` is a valid pool. Its default algorithm ignores
+        // the data.
+        unsafe {
+            let pool = self.ptr.as_ptr();
+            self.alloc(size, (*pool).algo, (*pool).data)
+        }
+    }
+
+    /// Allocates `size` bytes from the pool, aligned to `align` bytes.
+    ///
+    /// Returns [`EINVAL`] if `align` is not a power of two.
+    pub fn try_alloc_aligned(&self, size: usize, align: usize) -> Result<GenAlloc<'_>> {
+        if !align.is_power_of_two() {
+            return Err(EINVAL);
+        }
+
+        let mut data = bindings::genpool_data_align {
+            align: align.try_into()?,
+        };
+
+        // SAFETY: `gen_pool_first_fit_align` takes a `genpool_data_align` as its data, which is
+        // only used during the call.
+        unsafe {
+            self.alloc(
+                size,
+                Some(bindings::gen_pool_first_fit_align),
+                ptr::addr_of_mut!(data) as _,
+            )
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `data` must be valid for `algo`.
+    unsafe fn alloc(
+        &self,
+        size: usize,
+        algo: bindings::genpool_algo_t,
+        data: *mut c_types::c_void,
+    ) -> Result<GenAlloc<'_>> {
+        if size == 0 {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: By the type invariants, `ptr` is a valid pool, and the safety requirements
+        // guarantee that `data` is valid for `algo`.
+        let addr = unsafe {
+            bindings::gen_pool_alloc_algo_owner(
+                self.ptr.as_ptr(),
+                size,
+                algo,
+                data,
+                ptr::null_mut(),
+            )
+        };
+        if addr == 0 {
+            return Err(ENOMEM);
+        }
+
+        // INVARIANT: `addr` was allocated from the pool above with `size` bytes.
+        Ok(GenAlloc {
+            addr: addr as _,
+            size,
+            pool: self,
+        })
+    }
+
+    /// Returns the minimum allocation unit of the pool in bytes.
+    pub fn min_alloc_size(&self) -> usize {
+        1 << self.min_alloc_order
+    }
+
+    /// Returns the number of bytes available for allocation.
+    pub fn avail(&self) -> usize {
+        // SAFETY: By the type invariants, `ptr` is a valid pool.
+        unsafe { bindings::gen_pool_avail(self.ptr.as_ptr()) }
+    }
+
+    /// Returns the total size of the chunks of the pool in bytes.
+    pub fn size(&self) -> usize {
+        // SAFETY: By the type invariants, `ptr` is a valid pool.
+        unsafe { bindings::gen_pool_size(self.ptr.as_ptr()) }
+    }
+}
+
+impl Drop for GenPool {
+    fn drop(&mut self) {
+        // Allocations borrow the pool, but they may have been leaked (e.g., with `mem::forget`).
+        // `gen_pool_destroy` hits a `BUG_ON` if memory is still allocated, so leak the pool too.
+        if crate::warn_on!(
+            self.avail() != self.size(),
+            "destroying a gen_pool with outstanding allocations\n"
+        ) {
+            return;
+        }
+
+        // SAFETY: By the type invariants, `ptr` is a valid pool. No memory is allocated from it,
+        // as checked above.
+        unsafe { bindings::gen_pool_destroy(self.ptr.as_ptr()) };
+    }
+}
+
+/// Memory allocated from a [`GenPool`].
+///
+/// The memory is returned to the pool when dropped.
+///
+/// # Invariants
+///
+/// `addr` was allocated from `pool` with `size` bytes.
+pub struct GenAlloc<'a> {
+    addr: usize,
+    size: usize,
+    pool: &'a GenPool,
+}
+
+impl GenAlloc<'_> {
+    /// Returns the virtual address of the memory.
+    pub fn addr(&self) -> usize {
+        self.addr
+    }
+
+    /// Returns the physical address of the memory.
+    pub fn phys_addr(&self) -> bindings::phys_addr_t {
+        // SAFETY: By the type invariants, `addr` was allocated from the pool.
+        unsafe { bindings::gen_pool_virt_to_phys(self.pool.ptr.as_ptr(), self.addr as _) }
+    }
+
+    /// Returns the size of the memory in bytes.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Returns whether the allocation is empty.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+}
+
+impl Drop for GenAlloc<'_> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `addr` was allocated from `pool` with `size` bytes.
+        unsafe {
+            bindings::gen_pool_free_owner(
+                self.pool.ptr.as_ptr(),
+                self.addr as _,
+                self.size,
+                ptr::null_mut(),
+            )
+        };
+    }
+}
@@ -0,0 +1,209 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Allocation flags.
+//!
+//! The global allocator, used by [`Box::try_new`] and [`Vec::try_push`], allocates with
+//! [`Flags::KERNEL`], which may sleep and may recurse into file systems and block devices to
+//! reclaim memory. Code that runs in atomic context, or that the reclaim may wait for (e.g., the
+//! write-back path of a file system), must allocate with other flags, using the constructors of
+//! [`BoxExt`] and [`VecExt`].
+//!
+//! C header: [`include/linux/gfp.h`](../../../../include/linux/gfp.h)
+//!
+//! [`Box::try_new`]: alloc::boxed::Box::try_new
+//! [`Vec::try_push`]: alloc::vec::Vec::try_push
+
+use crate::{bindings, c_types, error::code::*, numa::NumaNode, Result};
+use alloc::{boxed::Box, vec::Vec};
+use core::{mem, mem::ManuallyDrop, ptr};
+
+/// Contains constants for the allocation flags (`gfp_t`).
+///
+/// One of the flags with a lowercase-named C equivalent (e.g., `GFP_KERNEL`) must be used, and
+/// the ones with a C name that starts with underscores (e.g., `__GFP_ZERO`) may be added to it.
+///
+/// It is tagged with `non_exhaustive` to prevent users from instantiating it.
+#[non_exhaustive]
+pub struct Flags;
+
+impl Flags {
+    /// Allocations in process context, which may sleep and reclaim memory.
+    pub const KERNEL: u32 = bindings::GFP_KERNEL;
+
+    /// Allocations that must not sleep, e.g., in interrupt handlers or with spinlocks held. They
+    /// may use the memory reserves.
+    pub const ATOMIC: u32 = bindings::GFP_ATOMIC;
+
+    /// Allocations that must not sleep and that fail rather than use the memory reserves.
+    pub const NOWAIT: u32 = bindings::GFP_NOWAIT;
+
+    /// Allocations that may sleep but must not start I/O to reclaim memory, e.g., in block
+    /// drivers.
+    pub const NOIO: u32 = bindings::GFP_NOIO;
+
+    /// Allocations that may sleep but must not call into file systems to reclaim memory, e.g., in
+    /// file systems with locks held.
+    pub const NOFS: u32 = bindings::GFP_NOFS;
+
+    /// Allocations for user space that the kernel also accesses directly.
+    pub const USER: u32 = bindings::GFP_USER;
+
+    /// Allocations for user space that the kernel doesn't access directly (e.g., pages mapped to
+    /// user space), which may come from high memory.
+    pub const HIGHUSER: u32 = bindings::GFP_HIGHUSER;
+
+    /// Zeroes the allocated memory.
+    pub const ZERO: u32 = bindings::__GFP_ZERO;
+
+    /// Doesn't warn when the allocation fails.
+    pub const NOWARN: u32 = bindings::__GFP_NOWARN;
+
+    /// Tries harder to allocate, but fails eventually rather than invoking the OOM killer.
+    pub const RETRY_MAYFAIL: u32 = bindings::__GFP_RETRY_MAYFAIL;
+
+    /// Accounts the allocation to the memory cgroup of the current task.
+    pub const ACCOUNT: u32 = bindings::__GFP_ACCOUNT;
+
+    /// Allocates from the DMA zone, for devices that can only address the lowest 16MB.
+    pub const DMA: u32 = bindings::GFP_DMA;
+
+    /// Allocates from the DMA32 zone, for devices that can only address the lowest 4GB.
+    pub const DMA32: u32 = bindings::GFP_DMA32;
+}
+
+/// Allocation of boxes with flags or on a given NUMA node.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::gfp::{BoxExt, Flags};
+///
+/// struct Event {
+///     code: u32,
+/// }
+///
+/// // Called from an interrupt handler.
+/// fn queue_event(code: u32) -> Result<Box<Event>> {
+///     Box::try_new_with(Event { code }, Flags::ATOMIC)
+/// }
+/// ```
+pub trait BoxExt<T>: Sized {
+    /// Allocates memory with the given flags and places `value` into it.
+    fn try_new_with(value: T, flags: u32) -> Result<Self>;
+
+    /// Allocates memory on the given node and places `value` into it.
+    ///
+    /// If the node has no memory available, the memory is allocated on another node.
+    fn try_new_node(value: T, node: NumaNode) -> Result<Self>;
+
+    /// Allocates memory with the given flags on the given node and places `value` into it.
+    fn try_new_node_with(value: T, flags: u32, node: NumaNode) -> Result<Self>;
+}
+
+impl<T> BoxExt<T> for Box<T> {
+    fn try_new_with(value: T, flags: u32) -> Result<Self> {
+        Self::try_new_node_with(value, flags, NumaNode::ANY)
+    }
+
+    fn try_new_node(value: T, node: NumaNode) -> Result<Self> {
+        Self::try_new_node_with(value, Flags::KERNEL, node)
+    }
+
+    fn try_new_node_with(value: T, flags: u32, node: NumaNode) -> Result<Self> {
+        // Zero-sized values don't need memory. `kmalloc` also guarantees enough alignment for
+        // the same types as the global allocator, which is based on it.
+        if mem::size_of::<T>() == 0 {
+            return Ok(Box::try_new(value)?);
+        }
+
+        // SAFETY: There are no safety requirements for this FFI call.
+        let ptr = unsafe { bindings::__kmalloc_node(mem::size_of::<T>(), flags, node.as_raw()) }
+            as *mut T;
+        if ptr.is_null() {
+            return Err(ENOMEM);
+        }
+
+        // SAFETY: `ptr` is valid for writes of a `T`. The global allocator frees memory with
+        // `kfree`, so it can be owned by a `Box`.
+        unsafe {
+            ptr.write(value);
+            Ok(Box::from_raw(ptr))
+        }
+    }
+}
+
+/// Allocation of vectors with flags.
+///
+/// Only the allocations made by these functions use the flags; later ones made by the vector
+/// itself (e.g., when it grows in [`Vec::try_push`]) use [`Flags::KERNEL`]. Capacity should be
+/// reserved in advance so that the vector doesn't grow.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::gfp::{Flags, VecExt};
+///
+/// // Called with a spinlock held.
+/// fn record(log: &mut Vec<u32>, value: u32) -> Result {
+///     log.try_push_with(value, Flags::ATOMIC)
+/// }
+/// ```
+pub trait VecExt<T>: Sized {
+    /// Creates a new empty vector with room for at least `capacity` elements, allocated with the
+    /// given flags.
+    fn try_with_capacity_with(capacity: usize, flags: u32) -> Result<Self>;
+
+    /// Makes room for at least `additional` more elements, allocating with the given flags if
+    /// the vector needs to grow.
+    fn try_reserve_with(&mut self, additional: usize, flags: u32) -> Result;
+
+    /// Appends an element to the vector, allocating with the given flags if it needs to grow.
+    fn try_push_with(&mut self, value: T, flags: u32) -> Result;
+}
+
+impl<T> VecExt<T> for Vec<T> {
+    fn try_with_capacity_with(capacity: usize, flags: u32) -> Result<Self> {
+        let mut v = Vec::new();
+        v.try_reserve_with(capacity, flags)?;
+        Ok(v)
+    }
+
+    fn try_reserve_with(&mut self, additional: usize, flags: u32) -> Result {
+        let needed = self.len().checked_add(additional).ok_or(ENOMEM)?;
+        if needed <= self.capacity() || mem::size_of::<T>() == 0 {
+            return Ok(());
+        }
+
+        let cap = needed.max(self.capacity().saturating_mul(2));
+        let size = cap.checked_mul(mem::size_of::<T>()).ok_or(ENOMEM)?;
+        let old = if self.capacity() == 0 {
+            ptr::null()
+        } else {
+            self.as_ptr() as *const c_types::c_void
+        };
+
+        // SAFETY: `old` is either null or was allocated by the global allocator, that is, with
+        // `krealloc`. If `krealloc` fails, the old memory is left untouched.
+        let new = unsafe { bindings::krealloc(old, size, flags) } as *mut T;
+        if new.is_null() {
+            return Err(ENOMEM);
+        }
+
+        // The old memory was freed by `krealloc`, so the old vector must not be dropped.
+        let old = ManuallyDrop::new(mem::take(self));
+
+        // SAFETY: `new` was allocated by `krealloc` for `cap` elements, which the global allocator
+        // can free, and the initialised elements were moved to it.
+        *self = unsafe { Vec::from_raw_parts(new, old.len(), cap) };
+        Ok(())
+    }
+
+    fn try_push_with(&mut self, value: T, flags: u32) -> Result {
+        self.try_reserve_with(1, flags)?;
+        // The capacity was reserved above, so this doesn't allocate.
+        self.try_push(value)?;
+        Ok(())
+    }
+}
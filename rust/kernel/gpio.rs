@@ -2,10 +2,19 @@
 
 //! Support for gpio device drivers.
 //!
-//! C header: [`include/linux/gpio/driver.h`](../../../../include/linux/gpio/driver.h)
+//! Drivers of gpio controllers implement [`Chip`], and drivers that use gpio lines request them
+//! as [`Desc`].
+//!
+//! C headers: [`include/linux/gpio/driver.h`](../../../../include/linux/gpio/driver.h) and
+//! [`include/linux/gpio/consumer.h`](../../../../include/linux/gpio/consumer.h)
 
 use crate::{
-    bindings, c_types, device, error::code::*, error::from_kernel_result, types::PointerWrapper,
+    bindings, c_types, device,
+    error::code::*,
+    error::{from_kernel_err_ptr, from_kernel_result},
+    str::CStr,
+    to_result,
+    types::PointerWrapper,
     Error, Result,
 };
 use core::{
@@ -305,6 +314,210 @@ unsafe extern "C" fn set_callback<T: Chip>(
     T::set(data, offset, value != 0);
 }
 
+/// The initial configuration of a gpio line requested with [`Desc::get`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GetFlags {
+    /// Leaves the line as it is.
+    AsIs,
+
+    /// Configures the line as input.
+    In,
+
+    /// Configures the line as output, initially inactive.
+    OutLow,
+
+    /// Configures the line as output, initially active.
+    OutHigh,
+
+    /// Configures the line as open-drain output, initially inactive.
+    OutLowOpenDrain,
+
+    /// Configures the line as open-drain output, initially active.
+    OutHighOpenDrain,
+}
+
+impl GetFlags {
+    fn to_raw(self) -> bindings::gpiod_flags {
+        match self {
+            Self::AsIs => bindings::gpiod_flags_GPIOD_ASIS,
+            Self::In => bindings::gpiod_flags_GPIOD_IN,
+            Self::OutLow => bindings::gpiod_flags_GPIOD_OUT_LOW,
+            Self::OutHigh => bindings::gpiod_flags_GPIOD_OUT_HIGH,
+            Self::OutLowOpenDrain => bindings::gpiod_flags_GPIOD_OUT_LOW_OPEN_DRAIN,
+            Self::OutHighOpenDrain => bindings::gpiod_flags_GPIOD_OUT_HIGH_OPEN_DRAIN,
+        }
+    }
+}
+
+/// A gpio line used by a driver, i.e., a gpio descriptor.
+///
+/// Values are logical: `true` means active, which is a low level for lines described as active-low
+/// (e.g., in the devicetree). The line is released when the descriptor is dropped.
+///
+/// # Invariants
+///
+/// `ptr` was returned by one of the `gpiod_get` variants, and is owned by the instance.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::{c_str, device::RawDevice, gpio};
+///
+/// fn reset(dev: &dyn RawDevice) -> Result {
+///     // The `reset-gpios` property of the device.
+///     let reset = gpio::Desc::get(dev, Some(c_str!("reset")), gpio::GetFlags::OutHigh)?;
+///     kernel::delay::msleep(10);
+///     reset.set_value_cansleep(false);
+///     Ok(())
+/// }
+/// ```
+pub struct Desc {
+    ptr: *mut bindings::gpio_desc,
+}
+
+// SAFETY: The gpio descriptor functions are safe to call from any thread.
+unsafe impl Send for Desc {}
+
+// SAFETY: The gpio descriptor functions are safe to call concurrently.
+unsafe impl Sync for Desc {}
+
+impl Desc {
+    /// Requests the gpio line of `dev` with the given function, e.g., `reset` for the
+    /// `reset-gpios` property in the devicetree, or the unnamed one (`gpios`) if `con_id` is
+    /// `None`.
+    pub fn get(
+        dev: &dyn device::RawDevice,
+        con_id: Option<&CStr>,
+        flags: GetFlags,
+    ) -> Result<Self> {
+        Self::get_index(dev, con_id, 0, flags)
+    }
+
+    /// Requests the index-th gpio line of `dev` with the given function.
+    pub fn get_index(
+        dev: &dyn device::RawDevice,
+        con_id: Option<&CStr>,
+        index: u32,
+        flags: GetFlags,
+    ) -> Result<Self> {
+        let con_id = con_id.map_or(core::ptr::null(), |c| c.as_char_ptr());
+
+        // SAFETY: `dev` is valid by the requirements of `RawDevice`, and `con_id` is either null
+        // or a valid C string.
+        let ptr = from_kernel_err_ptr(unsafe {
+            bindings::gpiod_get_index(dev.raw_device(), con_id, index, flags.to_raw())
+        })?;
+
+        // INVARIANT: `ptr` was returned by `gpiod_get_index` above.
+        Ok(Self { ptr })
+    }
+
+    /// Requests the gpio line of `dev` with the given function, if it has one.
+    pub fn get_optional(
+        dev: &dyn device::RawDevice,
+        con_id: Option<&CStr>,
+        flags: GetFlags,
+    ) -> Result<Option<Self>> {
+        let con_id = con_id.map_or(core::ptr::null(), |c| c.as_char_ptr());
+
+        // SAFETY: `dev` is valid by the requirements of `RawDevice`, and `con_id` is either null
+        // or a valid C string.
+        let ptr = from_kernel_err_ptr(unsafe {
+            bindings::gpiod_get_optional(dev.raw_device(), con_id, flags.to_raw())
+        })?;
+
+        // INVARIANT: `ptr` was returned by `gpiod_get_optional` above, if not null.
+        Ok((!ptr.is_null()).then(|| Self { ptr }))
+    }
+
+    /// Configures the line as input.
+    pub fn direction_input(&self) -> Result {
+        // SAFETY: By the type invariants, `ptr` is valid.
+        to_result(|| unsafe { bindings::gpiod_direction_input(self.ptr) })
+    }
+
+    /// Configures the line as output, with the given initial value.
+    pub fn direction_output(&self, value: bool) -> Result {
+        // SAFETY: By the type invariants, `ptr` is valid.
+        to_result(|| unsafe { bindings::gpiod_direction_output(self.ptr, value as _) })
+    }
+
+    /// Returns the direction of the line.
+    pub fn direction(&self) -> Result<LineDirection> {
+        // SAFETY: By the type invariants, `ptr` is valid.
+        let ret = unsafe { bindings::gpiod_get_direction(self.ptr) };
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+        if ret == bindings::GPIO_LINE_DIRECTION_IN as _ {
+            Ok(LineDirection::In)
+        } else {
+            Ok(LineDirection::Out)
+        }
+    }
+
+    /// Returns whether accessing the value of the line may sleep, e.g., because it is on an I2C
+    /// expander.
+    ///
+    /// If so, the `_cansleep` variants of the accessors must be used.
+    pub fn can_sleep(&self) -> bool {
+        // SAFETY: By the type invariants, `ptr` is valid.
+        unsafe { bindings::gpiod_cansleep(self.ptr) != 0 }
+    }
+
+    /// Returns the value of the line, without sleeping.
+    pub fn value(&self) -> Result<bool> {
+        // SAFETY: By the type invariants, `ptr` is valid.
+        Self::to_value(unsafe { bindings::gpiod_get_value(self.ptr) })
+    }
+
+    /// Sets the value of the line, without sleeping.
+    pub fn set_value(&self, value: bool) {
+        // SAFETY: By the type invariants, `ptr` is valid.
+        unsafe { bindings::gpiod_set_value(self.ptr, value as _) };
+    }
+
+    /// Returns the value of the line, possibly sleeping.
+    pub fn value_cansleep(&self) -> Result<bool> {
+        // SAFETY: By the type invariants, `ptr` is valid.
+        Self::to_value(unsafe { bindings::gpiod_get_value_cansleep(self.ptr) })
+    }
+
+    /// Sets the value of the line, possibly sleeping.
+    pub fn set_value_cansleep(&self, value: bool) {
+        // SAFETY: By the type invariants, `ptr` is valid.
+        unsafe { bindings::gpiod_set_value_cansleep(self.ptr, value as _) };
+    }
+
+    fn to_value(ret: c_types::c_int) -> Result<bool> {
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+        Ok(ret != 0)
+    }
+
+    /// Returns the irq of the line, to be requested with [`irq::Registration`], if the gpio
+    /// controller supports interrupts.
+    ///
+    /// [`irq::Registration`]: crate::irq::Registration
+    pub fn to_irq(&self) -> Result<u32> {
+        // SAFETY: By the type invariants, `ptr` is valid.
+        let ret = unsafe { bindings::gpiod_to_irq(self.ptr) };
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+        Ok(ret as _)
+    }
+}
+
+impl Drop for Desc {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `ptr` is valid and owned by the instance.
+        unsafe { bindings::gpiod_put(self.ptr) };
+    }
+}
+
 #[cfg(CONFIG_GPIOLIB_IRQCHIP)]
 mod irqchip {
     use super::*;
@@ -474,5 +687,13 @@ mod irqchip {
             let data = unsafe { T::Data::borrow(bindings::gpiochip_get_data(gc as _)) };
             T::set_wake(data, irq_data, on)
         }
+
+        fn eoi(gc: *mut bindings::gpio_chip, irq_data: &irq::IrqData) {
+            // SAFETY: `IrqChipAdapter` is a private struct, only used when the data stored in the
+            // gpio chip is known to come from `T::Data`, and only valid while the gpio chip is
+            // registered, so `gc` is valid.
+            let data = unsafe { T::Data::borrow(bindings::gpiochip_get_data(gc as _)) };
+            T::eoi(data, irq_data);
+        }
     }
 }
@@ -0,0 +1,366 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Fixed-size hash tables.
+//!
+//! The [`HashTable`] type is the equivalent of C's `DECLARE_HASHTABLE`: an array of `hlist`
+//! buckets, into which entries that embed [`HashLinks`] are linked.
+//!
+//! Lookups are RCU readers, like with the `_rcu` variants of the C functions: they only need an
+//! [`rcu::Guard`], so they can run concurrently with insertions and removals, which are
+//! serialised by the table.
+//!
+//! C header: [`include/linux/hashtable.h`](../../../../include/linux/hashtable.h)
+
+use crate::{
+    bindings, build_assert, c_types,
+    linked_list::Wrapper,
+    sync::{rcu, smutex::Mutex},
+    Result,
+};
+use alloc::boxed::Box;
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Describes the entries of a hash table.
+///
+/// It determines how entries are owned while in the table, how to get their key and the links
+/// used to insert them, and how to hash keys.
+pub trait HashAdapter {
+    /// The type of the entries in the table.
+    type EntryType;
+
+    /// The wrapper (e.g., `Box` or `Ref`) that owns entries while they are in the table.
+    type Wrapped: Wrapper<Self::EntryType>;
+
+    /// The type of the keys used to look up entries.
+    type Key: PartialEq + ?Sized;
+
+    /// Returns the links used to insert an entry in the table.
+    fn links(data: &Self::EntryType) -> &HashLinks;
+
+    /// Returns the key of an entry.
+    ///
+    /// It must not change while the entry is in the table.
+    fn key(data: &Self::EntryType) -> &Self::Key;
+
+    /// Hashes a key.
+    ///
+    /// Only the top bits of the returned value are used to pick a bucket, so integer keys may
+    /// be returned as is.
+    fn hash(key: &Self::Key) -> u64;
+}
+
+/// The links used to insert an object in a [`HashTable`].
+///
+/// Instances of this type are usually embedded in structures and returned in calls to
+/// [`HashAdapter::links`]. Wraps the kernel's `struct hlist_node`.
+pub struct HashLinks {
+    inserted: AtomicBool,
+    node: UnsafeCell<bindings::hlist_node>,
+    entry: UnsafeCell<*const c_types::c_void>,
+}
+
+// SAFETY: `node` is only accessed by the table the links are inserted in, which serialises the
+// changes to it; `inserted` ensures that it is in at most one table.
+unsafe impl Send for HashLinks {}
+
+// SAFETY: See above.
+unsafe impl Sync for HashLinks {}
+
+impl HashLinks {
+    /// Constructs new links that aren't inserted in any table yet.
+    pub const fn new() -> Self {
+        Self {
+            inserted: AtomicBool::new(false),
+            node: UnsafeCell::new(bindings::hlist_node {
+                next: ptr::null_mut(),
+                pprev: ptr::null_mut(),
+            }),
+            entry: UnsafeCell::new(ptr::null()),
+        }
+    }
+}
+
+impl Default for HashLinks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A hash table with `N` buckets, where `N` is a power of two.
+///
+/// Entries are owned by the table while they are in it. Multiple entries with the same key may be
+/// inserted; lookups return the most recently inserted one.
+///
+/// Lookups take an [`rcu::Guard`], and the entries they return remain valid while it is held:
+/// [`HashTable::remove`] waits for an RCU grace period before handing entries back.
+///
+/// # Invariants
+///
+/// `buckets` only contains entries that were converted with [`Wrapper::into_pointer`] and whose
+/// links have `inserted` set. The buckets are only modified with `writer` held (or through a
+/// mutable reference), with the `_rcu` variants of the `hlist` functions.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::hashtable::{HashAdapter, HashLinks, HashTable};
+/// use kernel::sync::rcu;
+///
+/// struct Inode {
+///     ino: u64,
+///     links: HashLinks,
+/// }
+///
+/// struct InodeAdapter;
+///
+/// impl HashAdapter for InodeAdapter {
+///     type EntryType = Inode;
+///     type Wrapped = Box<Inode>;
+///     type Key = u64;
+///
+///     fn links(data: &Inode) -> &HashLinks {
+///         &data.links
+///     }
+///
+///     fn key(data: &Inode) -> &u64 {
+///         &data.ino
+///     }
+///
+///     fn hash(key: &u64) -> u64 {
+///         *key
+///     }
+/// }
+///
+/// fn example() -> Result {
+///     let table = HashTable::<InodeAdapter, 64>::try_new()?;
+///     table.insert(Box::try_new(Inode { ino: 10, links: HashLinks::new() })?);
+///     table.insert(Box::try_new(Inode { ino: 20, links: HashLinks::new() })?);
+///
+///     {
+///         let guard = rcu::read_lock();
+///         assert_eq!(table.get(&10, &guard).map(|i| i.ino), Some(10));
+///     }
+///
+///     assert!(table.remove(&10).is_some());
+///
+///     let guard = rcu::read_lock();
+///     assert!(table.get(&10, &guard).is_none());
+///     assert_eq!(table.iter(&guard).count(), 1);
+///     Ok(())
+/// }
+/// ```
+pub struct HashTable<A: HashAdapter, const N: usize> {
+    buckets: Box<UnsafeCell<[bindings::hlist_head; N]>>,
+    writer: Mutex<()>,
+    _p: PhantomData<A::Wrapped>,
+}
+
+// SAFETY: The table owns its entries through `A::Wrapped`, so it can be sent to another thread if
+// they can.
+unsafe impl<A: HashAdapter, const N: usize> Send for HashTable<A, N> where A::Wrapped: Send {}
+
+// SAFETY: Shared references to the table give shared references to the entries, and allow
+// entries to be inserted and removed from any thread, which is serialised by `writer`.
+unsafe impl<A: HashAdapter, const N: usize> Sync for HashTable<A, N>
+where
+    A::EntryType: Sync,
+    A::Wrapped: Send,
+{
+}
+
+impl<A: HashAdapter, const N: usize> HashTable<A, N> {
+    /// Creates a new empty hash table.
+    ///
+    /// The buckets are allocated on the heap so that the table can be moved.
+    pub fn try_new() -> Result<Self> {
+        build_assert!(
+            N.is_power_of_two(),
+            "The number of buckets must be a power of two"
+        );
+
+        // INVARIANT: All buckets are empty.
+        Ok(Self {
+            buckets: Box::try_new(UnsafeCell::new([bindings::hlist_head::default(); N]))?,
+            writer: Mutex::new(()),
+            _p: PhantomData,
+        })
+    }
+
+    fn bucket(key: &A::Key) -> usize {
+        // SAFETY: `hash_64` has no safety requirements.
+        unsafe { bindings::hash_64(A::hash(key), N.trailing_zeros()) as usize }
+    }
+
+    fn bucket_ptr(&self, index: usize) -> *mut bindings::hlist_head {
+        // SAFETY: Callers pass indices below `N` (`bucket` returns a value of `log2(N)` bits), so
+        // the result is within the buckets.
+        unsafe { (self.buckets.get() as *mut bindings::hlist_head).add(index) }
+    }
+
+    /// Inserts the given entry in the table.
+    ///
+    /// The entry is dropped if it's already in this (or another) table; this can happen for
+    /// reference-counted entries, so dropping means decrementing the reference count.
+    pub fn insert(&self, data: A::Wrapped) {
+        let entry = data.as_ref();
+        let links = A::links(entry);
+        if links
+            .inserted
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return;
+        }
+
+        let bucket = self.bucket_ptr(Self::bucket(A::key(entry)));
+        let node = links.node.get();
+        let entry = links.entry.get();
+
+        // SAFETY: We own the links now that `inserted` is set. Readers only see `entry` after the
+        // node is published below.
+        unsafe { *entry = data.into_pointer().as_ptr() as _ };
+
+        let _writer = self.writer.lock();
+
+        // INVARIANT: The entry was converted with `into_pointer` and `inserted` was set above,
+        // and the bucket is modified with `writer` held.
+        // SAFETY: `bucket` is valid, and `node` remains valid while the entry is in the table
+        // because we took ownership of it.
+        unsafe { bindings::hlist_add_head_rcu(node, bucket) };
+    }
+
+    fn find(&self, key: &A::Key) -> Option<NonNull<A::EntryType>> {
+        // SAFETY: The bucket is valid while `self` is borrowed, and the callers guarantee that
+        // its entries are valid while the iterator is used.
+        unsafe { BucketIter::<A>::new(self.bucket_ptr(Self::bucket(key))) }
+            .find(|e| A::key(e) == key)
+            .map(NonNull::from)
+    }
+
+    /// Returns a reference to the most recently inserted entry with the given key, if any.
+    ///
+    /// The entry remains valid while `guard` is held, even if it is removed from the table.
+    pub fn get<'a>(&'a self, key: &A::Key, _guard: &'a rcu::Guard) -> Option<&'a A::EntryType> {
+        // SAFETY: Entries are only released after an RCU grace period once they are removed from
+        // the table, and the guard holds the RCU read-side lock for `'a`.
+        self.find(key).map(|e| unsafe { &*e.as_ptr() })
+    }
+
+    /// Removes the most recently inserted entry with the given key from the table and returns it.
+    ///
+    /// It waits for the lookups that may still see the entry to complete, so it may sleep.
+    pub fn remove(&self, key: &A::Key) -> Option<A::Wrapped> {
+        let writer = self.writer.lock();
+
+        // The entries are valid while `writer` is held, since only writers remove them.
+        let entry = self.find(key)?;
+
+        // SAFETY: `entry` is in the table, so it is valid.
+        let links = A::links(unsafe { entry.as_ref() });
+
+        // SAFETY: The node is in the table, which is modified with `writer` held.
+        unsafe { bindings::hlist_del_init_rcu(links.node.get()) };
+        drop(writer);
+
+        // Readers may still be walking through the node, so it can't be reused or freed yet.
+        rcu::synchronize();
+        links.inserted.store(false, Ordering::Release);
+
+        // SAFETY: By the type invariants, the entry was converted with `into_pointer`, and it
+        // was removed from the table above. No readers can see it anymore.
+        Some(unsafe { A::Wrapped::from_pointer(entry) })
+    }
+
+    /// Returns whether the table is empty.
+    ///
+    /// Entries may be inserted or removed concurrently, so it may be stale when it returns.
+    pub fn is_empty(&self) -> bool {
+        // SAFETY: The buckets are valid, and their first entries are read with `READ_ONCE`
+        // semantics since they may be modified concurrently.
+        (0..N).all(|i| unsafe { ptr::read_volatile(&(*self.bucket_ptr(i)).first) }.is_null())
+    }
+
+    /// Returns an iterator over all the entries in the table, in no particular order.
+    ///
+    /// The entries remain valid while `guard` is held. Entries inserted or removed concurrently
+    /// may or may not be returned.
+    pub fn iter<'a>(&'a self, _guard: &'a rcu::Guard) -> impl Iterator<Item = &'a A::EntryType> {
+        // SAFETY: The buckets are valid while `self` is borrowed, and entries are only released
+        // after an RCU grace period once they are removed, which the guard holds off for `'a`.
+        (0..N).flat_map(move |i| unsafe { BucketIter::<A>::new(self.bucket_ptr(i)) })
+    }
+}
+
+impl<A: HashAdapter, const N: usize> Drop for HashTable<A, N> {
+    fn drop(&mut self) {
+        let buckets = self.buckets.get_mut();
+        for i in 0..N {
+            while !buckets[i].first.is_null() {
+                let node = buckets[i].first;
+                let links = crate::container_of!(node, HashLinks, node);
+
+                // SAFETY: `node` is in the table, so it is valid. There are no readers since the
+                // table is mutably borrowed.
+                unsafe { bindings::hlist_del_init(node) };
+
+                // SAFETY: `links` was in the table, so it is valid.
+                unsafe { (*links).inserted.store(false, Ordering::Release) };
+
+                // SAFETY: `links` was in the table, so its `entry` was set on insertion.
+                let entry = unsafe { *(*links).entry.get() } as *mut A::EntryType;
+
+                // SAFETY: By the type invariants, the entry was converted with `into_pointer`,
+                // and it was removed from the table above.
+                drop(unsafe { A::Wrapped::from_pointer(NonNull::new_unchecked(entry)) });
+            }
+        }
+    }
+}
+
+struct BucketIter<'a, A: HashAdapter> {
+    next: *mut bindings::hlist_node,
+    _p: PhantomData<&'a A::EntryType>,
+}
+
+impl<'a, A: HashAdapter> BucketIter<'a, A> {
+    /// Creates an iterator over the entries of `bucket`, like `hlist_for_each_entry_rcu`.
+    ///
+    /// # Safety
+    ///
+    /// `bucket` must be valid, and its entries must remain valid for `'a`, e.g., because the RCU
+    /// read-side lock is held.
+    unsafe fn new(bucket: *const bindings::hlist_head) -> Self {
+        Self {
+            // SAFETY: `bucket` is valid by the safety requirements. The read has `READ_ONCE`
+            // semantics, like `rcu_dereference`, since the bucket may be modified concurrently.
+            next: unsafe { ptr::read_volatile(&(*bucket).first) },
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<'a, A: HashAdapter> Iterator for BucketIter<'a, A> {
+    type Item = &'a A::EntryType;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_null() {
+            return None;
+        }
+
+        let links = crate::container_of!(self.next, HashLinks, node);
+
+        // SAFETY: By the safety requirements of `new`, all the nodes reachable from the bucket
+        // are valid for `'a`. As above, the read is like `rcu_dereference`.
+        self.next = unsafe { ptr::read_volatile(&(*self.next).next) };
+
+        // SAFETY: As above, the links are valid, and their `entry` was set on insertion and is
+        // valid for `'a`.
+        Some(unsafe { &*(*(*links).entry.get() as *const A::EntryType) })
+    }
+}
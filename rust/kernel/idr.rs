@@ -0,0 +1,284 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! ID allocation.
+//!
+//! [`IdAllocator`] allocates integer IDs (e.g., minor numbers), and [`IdMap`] additionally maps
+//! them to objects (e.g., handles given to userspace).
+//!
+//! C header: [`include/linux/idr.h`](../../../../include/linux/idr.h)
+//!
+//! Reference: <https://www.kernel.org/doc/html/latest/core-api/idr.html>
+
+use crate::{
+    bindings, c_types,
+    error::{code::*, from_kernel_err_ptr},
+    types::PointerWrapper,
+    Error, Result,
+};
+use alloc::boxed::Box;
+use core::{cell::UnsafeCell, convert::TryInto, marker::PhantomData};
+
+/// Allocates unique integer IDs.
+///
+/// Wraps the kernel's `struct ida`. It has its own locking, so IDs can be allocated and freed
+/// concurrently through shared references. The `ida` is allocated on the heap so that the
+/// allocator can be moved: the root of its xarray must not move once it is initialised.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::idr::IdAllocator;
+///
+/// fn example() -> Result {
+///     let minors = IdAllocator::try_new()?;
+///     let a = minors.alloc_range(0, 255)?;
+///     let b = minors.alloc_range(0, 255)?;
+///     assert_ne!(a, b);
+///
+///     minors.free(a);
+///     minors.free(b);
+///     Ok(())
+/// }
+/// ```
+pub struct IdAllocator {
+    ida: Box<UnsafeCell<bindings::ida>>,
+}
+
+// SAFETY: `ida` can be used from any thread.
+unsafe impl Send for IdAllocator {}
+
+// SAFETY: The `ida` functions serialise concurrent accesses with the lock embedded in it.
+unsafe impl Sync for IdAllocator {}
+
+impl IdAllocator {
+    /// Creates a new ID allocator, with no IDs allocated.
+    pub fn try_new() -> Result<Self> {
+        let ida = Box::try_new(UnsafeCell::new(bindings::ida::default()))?;
+        // SAFETY: `ida` is valid for writes, and it doesn't move since it is heap-allocated.
+        unsafe { bindings::ida_init(ida.get()) };
+        Ok(Self { ida })
+    }
+
+    /// Allocates the smallest unused ID in the inclusive range `[min, max]`.
+    ///
+    /// Returns [`ENOSPC`] if all IDs in the range are in use. It may sleep to allocate memory.
+    pub fn alloc_range(&self, min: u32, max: u32) -> Result<u32> {
+        // SAFETY: `ida` was initialised in `try_new`.
+        let ret =
+            unsafe { bindings::ida_alloc_range(self.ida.get(), min, max, bindings::GFP_KERNEL) };
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+        Ok(ret as _)
+    }
+
+    /// Allocates the smallest unused ID.
+    pub fn alloc(&self) -> Result<u32> {
+        self.alloc_range(0, i32::MAX as _)
+    }
+
+    /// Frees an ID previously returned by [`IdAllocator::alloc`] or
+    /// [`IdAllocator::alloc_range`].
+    ///
+    /// Freeing an ID that is not allocated triggers a warning.
+    pub fn free(&self, id: u32) {
+        // SAFETY: `ida` was initialised in `try_new`.
+        unsafe { bindings::ida_free(self.ida.get(), id) };
+    }
+}
+
+impl Drop for IdAllocator {
+    fn drop(&mut self) {
+        // SAFETY: `ida` was initialised in `try_new` and it isn't used anymore.
+        unsafe { bindings::ida_destroy(self.ida.get()) };
+    }
+}
+
+/// Maps integer IDs to objects.
+///
+/// Wraps the kernel's `struct idr`. Objects are owned by the map while they are in it.
+/// Modifications require a mutable reference, so the map is usually protected by a lock (e.g.,
+/// [`crate::sync::Mutex`]). Like in [`IdAllocator`], the `idr` is allocated on the heap so that
+/// the map can be moved.
+///
+/// # Invariants
+///
+/// All the pointers stored in `idr` were returned by [`PointerWrapper::into_pointer`] and are
+/// not null.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::idr::IdMap;
+///
+/// struct Handle {
+///     value: u32,
+/// }
+///
+/// fn example() -> Result {
+///     let mut handles = IdMap::<Box<Handle>>::try_new()?;
+///     let id = handles.insert(Box::try_new(Handle { value: 42 })?)?;
+///     assert_eq!(handles.get(id).map(|h| h.value), Some(42));
+///
+///     let handle = handles.remove(id).ok_or(EINVAL)?;
+///     assert_eq!(handle.value, 42);
+///     assert!(handles.get(id).is_none());
+///     Ok(())
+/// }
+/// ```
+pub struct IdMap<T: PointerWrapper> {
+    idr: Box<bindings::idr>,
+    _p: PhantomData<T>,
+}
+
+// SAFETY: The map owns its objects, so it can be sent to another thread if they can.
+unsafe impl<T: PointerWrapper + Send> Send for IdMap<T> {}
+
+// SAFETY: Shared references to the map only allow borrowing the objects.
+unsafe impl<T: PointerWrapper + Sync> Sync for IdMap<T> {}
+
+impl<T: PointerWrapper> IdMap<T> {
+    /// Creates a new empty map.
+    pub fn try_new() -> Result<Self> {
+        let mut idr = Box::try_new(bindings::idr::default())?;
+        // SAFETY: `idr` is valid for writes, and it doesn't move since it is heap-allocated.
+        unsafe { bindings::idr_init(&mut *idr) };
+
+        // INVARIANT: The map is empty.
+        Ok(Self {
+            idr,
+            _p: PhantomData,
+        })
+    }
+
+    /// Inserts an object with an ID in the inclusive range `[min, max]`, and returns the ID.
+    ///
+    /// Returns [`ENOSPC`] if all IDs in the range are in use. It may sleep to allocate memory.
+    pub fn insert_range(&mut self, data: T, min: u32, max: u32) -> Result<u32> {
+        let min: c_types::c_int = min.try_into()?;
+        // `idr_alloc` takes an exclusive end, where 0 means no limit.
+        let end: c_types::c_int = if max >= i32::MAX as u32 {
+            0
+        } else {
+            (max + 1) as _
+        };
+
+        let ptr = data.into_pointer();
+        if ptr.is_null() {
+            // SAFETY: `ptr` was just returned by `into_pointer`.
+            unsafe { T::from_pointer(ptr) };
+            return Err(EINVAL);
+        }
+
+        // INVARIANT: `ptr` was returned by `into_pointer` and is not null.
+        // SAFETY: `idr` was initialised in `try_new`.
+        let ret = unsafe {
+            bindings::idr_alloc(&mut *self.idr, ptr as _, min, end, bindings::GFP_KERNEL)
+        };
+        if ret < 0 {
+            // SAFETY: `ptr` was returned by `into_pointer` and wasn't inserted.
+            unsafe { T::from_pointer(ptr) };
+            return Err(Error::from_kernel_errno(ret));
+        }
+        Ok(ret as _)
+    }
+
+    /// Inserts an object with the smallest unused ID, and returns the ID.
+    ///
+    /// The object must not be represented by a null pointer, e.g., it cannot be `()`.
+    pub fn insert(&mut self, data: T) -> Result<u32> {
+        self.insert_range(data, 0, i32::MAX as _)
+    }
+
+    /// Returns the object with the given ID, if any.
+    pub fn get(&self, id: u32) -> Option<T::Borrowed<'_>> {
+        // SAFETY: `idr` was initialised in `try_new`. `idr_find` doesn't modify it.
+        let ptr = unsafe { bindings::idr_find(&*self.idr, id as _) };
+        if ptr.is_null() {
+            return None;
+        }
+
+        // SAFETY: By the type invariants, `ptr` was returned by `into_pointer`. It is only
+        // reclaimed by `remove` or `drop`, which cannot be called while `self` is borrowed.
+        Some(unsafe { T::borrow(ptr) })
+    }
+
+    /// Replaces the object with the given ID, and returns the previous one.
+    ///
+    /// Returns [`ENOENT`] if there is no object with the given ID.
+    pub fn replace(&mut self, id: u32, data: T) -> Result<T> {
+        let ptr = data.into_pointer();
+        if ptr.is_null() {
+            // SAFETY: `ptr` was just returned by `into_pointer`.
+            unsafe { T::from_pointer(ptr) };
+            return Err(EINVAL);
+        }
+
+        // INVARIANT: `ptr` was returned by `into_pointer` and is not null.
+        // SAFETY: `idr` was initialised in `try_new`.
+        let old = from_kernel_err_ptr(unsafe {
+            bindings::idr_replace(&mut *self.idr, ptr as _, id as _)
+        });
+        let old = match old {
+            Ok(old) => old,
+            Err(e) => {
+                // SAFETY: `ptr` was returned by `into_pointer` and wasn't inserted.
+                unsafe { T::from_pointer(ptr) };
+                return Err(e);
+            }
+        };
+        if old.is_null() {
+            // The ID was reserved but had no object.
+            return Err(ENOENT);
+        }
+
+        // SAFETY: By the type invariants, `old` was returned by `into_pointer`, and it was
+        // removed from the map above.
+        Ok(unsafe { T::from_pointer(old) })
+    }
+
+    /// Removes the object with the given ID from the map, and returns it.
+    pub fn remove(&mut self, id: u32) -> Option<T> {
+        // SAFETY: `idr` was initialised in `try_new`.
+        let ptr = unsafe { bindings::idr_remove(&mut *self.idr, id as _) };
+        if ptr.is_null() {
+            return None;
+        }
+
+        // SAFETY: By the type invariants, `ptr` was returned by `into_pointer`, and it was
+        // removed from the map above.
+        Some(unsafe { T::from_pointer(ptr) })
+    }
+
+    /// Returns whether the map is empty.
+    pub fn is_empty(&self) -> bool {
+        // SAFETY: `idr` was initialised in `try_new`.
+        unsafe { bindings::idr_is_empty(&*self.idr) }
+    }
+}
+
+impl<T: PointerWrapper> Drop for IdMap<T> {
+    fn drop(&mut self) {
+        let mut id: c_types::c_int = 0;
+        loop {
+            // SAFETY: `idr` was initialised in `try_new`.
+            let ptr = unsafe { bindings::idr_get_next(&mut *self.idr, &mut id) };
+            if ptr.is_null() {
+                break;
+            }
+
+            // SAFETY: By the type invariants, `ptr` was returned by `into_pointer`. It isn't used
+            // anymore because the map is being destroyed.
+            unsafe { T::from_pointer(ptr) };
+            id = match id.checked_add(1) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+
+        // SAFETY: `idr` was initialised in `try_new` and it isn't used anymore.
+        unsafe { bindings::idr_destroy(&mut *self.idr) };
+    }
+}
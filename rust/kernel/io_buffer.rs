@@ -126,6 +126,10 @@ unsafe impl ReadableFromBytes for i32 {}
 unsafe impl ReadableFromBytes for i64 {}
 unsafe impl ReadableFromBytes for isize {}
 
+// SAFETY: If all bit patterns are acceptable for individual values in an array, then all bit
+// patterns are also acceptable for arrays of that type.
+unsafe impl<T: ReadableFromBytes, const N: usize> ReadableFromBytes for [T; N] {}
+
 /// Specifies that a type is safely writable to byte slices.
 ///
 /// This means that we don't read undefined values (which leads to UB) in preparation for writing
@@ -151,3 +155,80 @@ unsafe impl WritableToBytes for i16 {}
 unsafe impl WritableToBytes for i32 {}
 unsafe impl WritableToBytes for i64 {}
 unsafe impl WritableToBytes for isize {}
+
+// SAFETY: Arrays have no padding between their elements, so if the elements have no uninitialised
+// portions, neither does the array.
+unsafe impl<T: WritableToBytes, const N: usize> WritableToBytes for [T; N] {}
+
+/// Declares a `#[repr(C)]` plain old data (POD) structure that implements both
+/// [`ReadableFromBytes`] and [`WritableToBytes`].
+///
+/// This allows the whole structure to be copied from or to an io buffer (e.g., userspace memory)
+/// in one call. The conditions required by the unsafe traits are checked at compile time: all
+/// fields must implement both traits, and the structure must not have padding, that is, its size
+/// must be the sum of the sizes of its fields. Explicit padding fields (e.g., `_pad: [u8; 4]`)
+/// must be added where the C definition has implicit padding.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// # use kernel::io_buffer::{IoBufferReader, IoBufferWriter};
+/// # use kernel::user_ptr::UserSlicePtr;
+/// kernel::declare_pod_struct! {
+///     /// Arguments of the `EXAMPLE_UPDATE` ioctl.
+///     #[derive(Clone, Copy, Default)]
+///     pub struct UpdateArgs {
+///         pub index: u32,
+///         pub flags: u32,
+///         pub value: u64,
+///     }
+/// }
+///
+/// fn update(data: UserSlicePtr) -> Result {
+///     let (mut reader, mut writer) = data.reader_writer();
+///     let mut args: UpdateArgs = reader.read()?;
+///     args.value += 1;
+///     writer.write(&args)
+/// }
+/// ```
+#[macro_export]
+macro_rules! declare_pod_struct {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$fmeta:meta])* $fvis:vis $field:ident: $fty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[repr(C)]
+        $vis struct $name {
+            $($(#[$fmeta])* $fvis $field: $fty),*
+        }
+
+        const _: () = {
+            fn assert_pod<T>()
+            where
+                T: $crate::io_buffer::ReadableFromBytes + $crate::io_buffer::WritableToBytes,
+            {
+            }
+
+            #[allow(dead_code)]
+            fn assert_fields() {
+                $(assert_pod::<$fty>();)*
+            }
+        };
+
+        $crate::static_assert!(
+            core::mem::size_of::<$name>() == 0 $(+ core::mem::size_of::<$fty>())*
+        );
+
+        // SAFETY: All fields implement `ReadableFromBytes` (checked above), so all bit patterns
+        // are acceptable for them, and there is no padding whose value would matter.
+        unsafe impl $crate::io_buffer::ReadableFromBytes for $name {}
+
+        // SAFETY: All fields implement `WritableToBytes` (checked above), and the size check
+        // above guarantees that there are no padding bytes.
+        unsafe impl $crate::io_buffer::WritableToBytes for $name {}
+    };
+}
@@ -2,12 +2,22 @@
 
 //! Memory-mapped IO.
 //!
-//! C header: [`include/asm-generic/io.h`](../../../../include/asm-generic/io.h)
+//! [`IoMem`] maps device registers, which must be accessed with the i/o accessors. [`MemRemap`]
+//! maps regions that behave like memory, e.g., carve-outs reserved for firmware or persistent
+//! memory.
+//!
+//! C headers: [`include/asm-generic/io.h`](../../../../include/asm-generic/io.h) and
+//! [`include/linux/io.h`](../../../../include/linux/io.h)
 
 #![allow(dead_code)]
 
-use crate::{bindings, error::code::*, Result};
-use core::convert::TryInto;
+use crate::{
+    bindings,
+    error::code::*,
+    io_buffer::{ReadableFromBytes, WritableToBytes},
+    Result,
+};
+use core::{convert::TryInto, mem, ptr, ptr::NonNull};
 
 /// Represents a memory resource.
 pub struct Resource {
@@ -28,6 +38,16 @@ impl Resource {
             size: end.checked_sub(start)?.checked_add(1)?,
         })
     }
+
+    /// Returns the physical address of the start of the resource.
+    pub fn start(&self) -> bindings::resource_size_t {
+        self.offset
+    }
+
+    /// Returns the size of the resource in bytes.
+    pub fn size(&self) -> bindings::resource_size_t {
+        self.size
+    }
 }
 
 /// Represents a memory block of at least `SIZE` bytes.
@@ -226,6 +246,62 @@ impl<const SIZE: usize> IoMem<SIZE> {
         Ok(())
     }
 
+    /// Copy memory block to an i/o memory from the specified buffer.
+    ///
+    /// # Examples
+    /// ```
+    /// # use kernel::prelude::*;
+    /// use kernel::io_mem::{self, IoMem, Resource};
+    ///
+    /// fn test(res: Resource) -> Result {
+    ///     // Create an i/o memory block of at least 100 bytes.
+    ///     let mem = unsafe { IoMem::<100>::try_new(res) }?;
+    ///
+    ///     let buffer: [u8; 32] = [0xff; 32];
+    ///
+    ///     // Memcpy 16 bytes from the buffer into an offset 10 of i/o memory block.
+    ///     mem.try_memcpy_toio(&buffer[..16], 10)?;
+    ///
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn try_memcpy_toio(&self, buffer: &[u8], offset: usize) -> Result {
+        if !Self::offset_ok_of_val(offset, buffer) {
+            return Err(EINVAL);
+        }
+
+        let ptr = self.ptr.wrapping_add(offset);
+
+        // SAFETY:
+        //   - The type invariants guarantee that `ptr` is a valid pointer.
+        //   - The bounds of `buffer` are checked with a call to `offset_ok_of_val()`.
+        unsafe {
+            bindings::memcpy_toio(
+                ptr as *mut _,
+                buffer.as_ptr() as *const _,
+                buffer.len() as _,
+            )
+        };
+        Ok(())
+    }
+
+    /// Fills `len` bytes of the i/o memory block, starting at `offset`, with `value`.
+    ///
+    /// It fails if/when the range is out of bounds.
+    pub fn try_memset_io(&self, value: u8, offset: usize, len: usize) -> Result {
+        match offset.checked_add(len) {
+            Some(end) if end <= SIZE => {}
+            _ => return Err(EINVAL),
+        }
+
+        let ptr = self.ptr.wrapping_add(offset);
+
+        // SAFETY: The type invariants guarantee that `ptr` is a valid pointer, and the range was
+        // checked above.
+        unsafe { bindings::memset_io(ptr as *mut _, value as _, len as _) };
+        Ok(())
+    }
+
     define_read!(readb, try_readb, u8);
     define_read!(readw, try_readw, u16);
     define_read!(readl, try_readl, u32);
@@ -274,3 +350,168 @@ impl<const SIZE: usize> Drop for IoMem<SIZE> {
         unsafe { bindings::iounmap(self.ptr as _) };
     }
 }
+
+/// Contains constants for the caching modes of [`MemRemap`].
+///
+/// It is tagged with `non_exhaustive` to prevent users from instantiating it.
+#[non_exhaustive]
+pub struct MemRemapFlags;
+
+impl MemRemapFlags {
+    /// Write-back caching, as for system memory. It is the mode to use for memory that is not
+    /// accessed by devices.
+    pub const WB: u32 = bindings::MEMREMAP_WB;
+
+    /// Write-through caching, e.g., for memory that devices read without snooping the caches.
+    pub const WT: u32 = bindings::MEMREMAP_WT;
+
+    /// Write-combining, e.g., for frame buffers.
+    pub const WC: u32 = bindings::MEMREMAP_WC;
+}
+
+/// A region of memory mapped with `memremap`.
+///
+/// Unlike [`IoMem`], the region behaves like memory, so it is accessed with regular loads and
+/// stores. The region may still be accessed concurrently by devices or firmware, so it is only
+/// accessed by copying values in and out of it, never through references. Writes require a mutable
+/// reference, so they don't race with other accesses from the kernel.
+///
+/// # Invariants
+///
+/// `ptr` was returned by `memremap` for a region of `size` bytes.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::io_mem::{MemRemap, MemRemapFlags, Resource};
+///
+/// fn read_log_head(res: Resource) -> Result<u32> {
+///     // SAFETY: The resource is a carve-out reserved for the firmware log.
+///     let log = unsafe { MemRemap::try_from_resource(res, MemRemapFlags::WB) }?;
+///     log.try_read::<u32>(0)
+/// }
+/// ```
+pub struct MemRemap {
+    ptr: NonNull<u8>,
+    size: usize,
+}
+
+// SAFETY: The mapping can be used and unmapped from any thread.
+unsafe impl Send for MemRemap {}
+
+// SAFETY: Shared references only allow reading the region, by copying values out of it. Writes
+// require a mutable reference, so they cannot race with reads from other threads.
+unsafe impl Sync for MemRemap {}
+
+impl MemRemap {
+    /// Maps the `size` bytes at physical address `start`, with the given caching mode (see
+    /// [`MemRemapFlags`]).
+    ///
+    /// # Safety
+    ///
+    /// The region must not be used by the kernel for other purposes (e.g., it must not be system
+    /// memory handed to the page allocator). Callers must also ensure that DMA operations
+    /// initiated through the region follow the same rules as for [`IoMem::try_new`].
+    pub unsafe fn try_new(
+        start: bindings::resource_size_t,
+        size: usize,
+        flags: u32,
+    ) -> Result<Self> {
+        // SAFETY: Just mapping the memory range; the safety requirements guarantee that the
+        // region is not used otherwise.
+        let ptr = unsafe { bindings::memremap(start, size as _, flags as _) };
+
+        // INVARIANT: `ptr` was returned by `memremap` above for `size` bytes.
+        Ok(Self {
+            ptr: NonNull::new(ptr as *mut u8).ok_or(ENOMEM)?,
+            size,
+        })
+    }
+
+    /// Maps the region described by `res`, consuming it so that it can't be mapped again.
+    ///
+    /// # Safety
+    ///
+    /// The same as for [`MemRemap::try_new`].
+    pub unsafe fn try_from_resource(res: Resource, flags: u32) -> Result<Self> {
+        // SAFETY: The safety requirements are the same.
+        unsafe { Self::try_new(res.offset, res.size.try_into()?, flags) }
+    }
+
+    /// Returns the size of the region in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the address at which the region is mapped.
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    fn range_ok(&self, offset: usize, len: usize) -> bool {
+        matches!(offset.checked_add(len), Some(end) if end <= self.size)
+    }
+
+    /// Reads a value from the given offset, which doesn't need to be aligned.
+    ///
+    /// It fails if/when the offset (plus the type size) is out of bounds.
+    pub fn try_read<T: ReadableFromBytes>(&self, offset: usize) -> Result<T> {
+        if !self.range_ok(offset, mem::size_of::<T>()) {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: By the type invariants, `ptr` is valid for `size` bytes, and the range was
+        // checked above. Any bit pattern is a valid `T`.
+        Ok(unsafe { ptr::read_unaligned(self.as_ptr().add(offset) as *const T) })
+    }
+
+    /// Writes a value at the given offset, which doesn't need to be aligned.
+    ///
+    /// It fails if/when the offset (plus the type size) is out of bounds.
+    pub fn try_write<T: WritableToBytes>(&mut self, value: &T, offset: usize) -> Result {
+        if !self.range_ok(offset, mem::size_of::<T>()) {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: By the type invariants, `ptr` is valid for `size` bytes, and the range was
+        // checked above.
+        unsafe { ptr::write_unaligned(self.as_ptr().add(offset) as *mut T, ptr::read(value)) };
+        Ok(())
+    }
+
+    /// Copies bytes from the region, starting at `offset`, into `buffer`.
+    pub fn try_copy_from(&self, buffer: &mut [u8], offset: usize) -> Result {
+        if !self.range_ok(offset, buffer.len()) {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: The range was checked above, and `buffer` can't overlap with the region since
+        // the region is never borrowed.
+        unsafe {
+            ptr::copy_nonoverlapping(self.as_ptr().add(offset), buffer.as_mut_ptr(), buffer.len())
+        };
+        Ok(())
+    }
+
+    /// Copies `buffer` into the region, starting at `offset`.
+    pub fn try_copy_to(&mut self, buffer: &[u8], offset: usize) -> Result {
+        if !self.range_ok(offset, buffer.len()) {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: The range was checked above, and `buffer` can't overlap with the region since
+        // the region is never borrowed.
+        unsafe {
+            ptr::copy_nonoverlapping(buffer.as_ptr(), self.as_ptr().add(offset), buffer.len())
+        };
+        Ok(())
+    }
+}
+
+impl Drop for MemRemap {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `ptr` was returned by `memremap`.
+        unsafe { bindings::memunmap(self.ptr.as_ptr() as _) };
+    }
+}
@@ -0,0 +1,163 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Port I/O.
+//!
+//! Legacy devices (e.g., on the ISA or LPC buses) are accessed through a separate I/O address
+//! space on some architectures, with dedicated instructions (`in` and `out` on x86). Drivers must
+//! claim the range of ports they use, which is shown in `/proc/ioports`.
+//!
+//! C headers: [`include/linux/ioport.h`](../../../../include/linux/ioport.h) and
+//! [`include/asm-generic/io.h`](../../../../include/asm-generic/io.h)
+
+use crate::{bindings, c_types, error::code::*, str::CStr, Result};
+use core::ptr;
+
+/// A claimed range of `SIZE` I/O ports.
+///
+/// The range is released when the instance is dropped.
+///
+/// # Invariants
+///
+/// The ports from `start` to `start + SIZE` (exclusive) were claimed with `__request_region`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::c_str;
+/// use kernel::io_port::PortIo;
+///
+/// fn read_rtc_seconds() -> Result<u8> {
+///     let rtc = PortIo::<2>::try_new(0x70, c_str!("example_rtc"))?;
+///
+///     // Select the seconds register, then read it.
+///     rtc.outb(0, 0);
+///     Ok(rtc.inb(1))
+/// }
+/// ```
+pub struct PortIo<const SIZE: usize> {
+    start: c_types::c_ulong,
+}
+
+macro_rules! define_in {
+    ($name:ident, $try_name:ident, $type_name:ty) => {
+        /// Reads from the port at the given offset, known at compile time.
+        ///
+        /// If the offset is not known at compile time, the build will fail.
+        pub fn $name(&self, offset: usize) -> $type_name {
+            Self::check_offset::<$type_name>(offset);
+            // SAFETY: By the type invariants, the port was claimed. The check above guarantees
+            // that the code won't build if `offset` makes the read go out of bounds (including
+            // the type size).
+            unsafe { bindings::$name(self.start + offset as c_types::c_ulong) }
+        }
+
+        /// Reads from the port at the given offset.
+        ///
+        /// It fails if/when the offset (plus the type size) is out of bounds.
+        pub fn $try_name(&self, offset: usize) -> Result<$type_name> {
+            if !Self::offset_ok::<$type_name>(offset) {
+                return Err(EINVAL);
+            }
+            // SAFETY: By the type invariants, the port was claimed. The check above returns an
+            // error if `offset` would make the read go out of bounds (including the type size).
+            Ok(unsafe { bindings::$name(self.start + offset as c_types::c_ulong) })
+        }
+    };
+}
+
+macro_rules! define_out {
+    ($name:ident, $try_name:ident, $type_name:ty) => {
+        /// Writes to the port at the given offset, known at compile time.
+        ///
+        /// If the offset is not known at compile time, the build will fail.
+        pub fn $name(&self, value: $type_name, offset: usize) {
+            Self::check_offset::<$type_name>(offset);
+            // SAFETY: By the type invariants, the port was claimed. The check above guarantees
+            // that the code won't build if `offset` makes the write go out of bounds (including
+            // the type size).
+            unsafe { bindings::$name(value, self.start + offset as c_types::c_ulong) }
+        }
+
+        /// Writes to the port at the given offset.
+        ///
+        /// It fails if/when the offset (plus the type size) is out of bounds.
+        pub fn $try_name(&self, value: $type_name, offset: usize) -> Result {
+            if !Self::offset_ok::<$type_name>(offset) {
+                return Err(EINVAL);
+            }
+            // SAFETY: By the type invariants, the port was claimed. The check above returns an
+            // error if `offset` would make the write go out of bounds (including the type size).
+            unsafe { bindings::$name(value, self.start + offset as c_types::c_ulong) };
+            Ok(())
+        }
+    };
+}
+
+impl<const SIZE: usize> PortIo<SIZE> {
+    /// Claims the `SIZE` ports starting at `start`, with the given name.
+    ///
+    /// Returns [`EBUSY`] if some of the ports were already claimed.
+    pub fn try_new(start: c_types::c_ulong, name: &'static CStr) -> Result<Self> {
+        let size: c_types::c_ulong = SIZE.try_into()?;
+        start.checked_add(size).ok_or(EINVAL)?;
+
+        // SAFETY: `ioport_resource` is the root of the I/O port resources, and `name` is static,
+        // so it outlives the claim.
+        let res = unsafe {
+            bindings::__request_region(
+                ptr::addr_of_mut!(bindings::ioport_resource),
+                start as _,
+                size as _,
+                name.as_char_ptr(),
+                0,
+            )
+        };
+        if res.is_null() {
+            return Err(EBUSY);
+        }
+
+        // INVARIANT: The ports were claimed above.
+        Ok(Self { start })
+    }
+
+    /// Returns the first port of the range.
+    pub fn start(&self) -> c_types::c_ulong {
+        self.start
+    }
+
+    const fn offset_ok<T>(offset: usize) -> bool {
+        let type_size = core::mem::size_of::<T>();
+        if let Some(end) = offset.checked_add(type_size) {
+            end <= SIZE
+        } else {
+            false
+        }
+    }
+
+    const fn check_offset<T>(offset: usize) {
+        crate::build_assert!(Self::offset_ok::<T>(offset), "PortIo offset overflow");
+    }
+
+    define_in!(inb, try_inb, u8);
+    define_in!(inw, try_inw, u16);
+    define_in!(inl, try_inl, u32);
+
+    define_out!(outb, try_outb, u8);
+    define_out!(outw, try_outw, u16);
+    define_out!(outl, try_outl, u32);
+}
+
+impl<const SIZE: usize> Drop for PortIo<SIZE> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, the ports were claimed with `__request_region` on
+        // `ioport_resource`.
+        unsafe {
+            bindings::__release_region(
+                ptr::addr_of_mut!(bindings::ioport_resource),
+                self.start as _,
+                SIZE as _,
+            )
+        };
+    }
+}
@@ -8,7 +8,8 @@ use crate::{
     bindings,
     error::code::*,
     io_buffer::{IoBufferReader, IoBufferWriter},
-    Result,
+    pages::Pages,
+    Result, PAGE_SIZE,
 };
 
 /// Wraps the kernel's `struct iov_iter`.
@@ -28,13 +29,117 @@ impl IovIter {
 
     /// Constructs a new [`struct iov_iter`] wrapper.
     ///
+    /// This allows the [`IoBufferReader`] and [`IoBufferWriter`] interfaces to be used on an
+    /// `iov_iter` that comes from C, for example, in `read_iter` and `write_iter` callbacks.
+    ///
     /// # Safety
     ///
-    /// The pointer `ptr` must be non-null and valid for the lifetime of the object.
-    pub(crate) unsafe fn from_ptr(ptr: *mut bindings::iov_iter) -> Self {
+    /// The pointer `ptr` must be non-null and valid for the lifetime of the object, and no other
+    /// code may use the iterator concurrently.
+    pub unsafe fn from_ptr(ptr: *mut bindings::iov_iter) -> Self {
         // INVARIANTS: the safety contract ensures the type invariant will hold.
         Self { ptr }
     }
+
+    /// Writes as much of `data` as possible into the iterator.
+    ///
+    /// Unlike [`IoBufferWriter::write_slice`], a fault in the middle of the copy is not an error:
+    /// the number of bytes actually copied is returned instead, which may be less than
+    /// `data.len()`. This is what `read_iter` implementations must report as a short read.
+    pub fn write_partial(&mut self, data: &[u8]) -> usize {
+        // SAFETY: `IovIter::ptr` is guaranteed to be valid by the type invariants, and `data` is
+        // valid for reads of `data.len()` bytes.
+        unsafe { bindings::copy_to_iter(data.as_ptr() as _, data.len(), self.ptr) }
+    }
+
+    /// Reads as much as possible from the iterator into `out`.
+    ///
+    /// Unlike [`IoBufferReader::read_slice`], a fault in the middle of the copy is not an error:
+    /// the number of bytes actually copied is returned instead, which may be less than
+    /// `out.len()`. This is what `write_iter` implementations must report as a short write.
+    pub fn read_partial(&mut self, out: &mut [u8]) -> usize {
+        // SAFETY: `IovIter::ptr` is guaranteed to be valid by the type invariants, and `out` is
+        // valid for writes of `out.len()` bytes.
+        unsafe { bindings::copy_from_iter(out.as_mut_ptr() as _, out.len(), self.ptr) }
+    }
+
+    /// Copies `len` bytes between the iterator and `pages`, starting at `offset` within them, one
+    /// page at a time with `copy`.
+    ///
+    /// The C functions only accept ranges within a single page of allocations that aren't
+    /// compound pages, so the range is split at page boundaries.
+    fn copy_pages<const ORDER: u32>(
+        &mut self,
+        pages: *mut bindings::page,
+        mut offset: usize,
+        len: usize,
+        copy: unsafe extern "C" fn(
+            *mut bindings::page,
+            usize,
+            usize,
+            *mut bindings::iov_iter,
+        ) -> usize,
+    ) -> Result<usize> {
+        let end = offset.checked_add(len).ok_or(EINVAL)?;
+        if end > PAGE_SIZE << ORDER {
+            return Err(EINVAL);
+        }
+
+        let mut copied = 0;
+        while offset < end {
+            let in_page = offset % PAGE_SIZE;
+            let chunk = core::cmp::min(end - offset, PAGE_SIZE - in_page);
+            // SAFETY: `IovIter::ptr` is guaranteed to be valid by the type invariants. `pages`
+            // points to 2^ORDER contiguous pages, and `offset` was checked above to be within
+            // them. The range `in_page..in_page + chunk` is within a single page.
+            let n = unsafe { copy(pages.add(offset / PAGE_SIZE), in_page, chunk, self.ptr) };
+            copied += n;
+            if n < chunk {
+                break;
+            }
+            offset += chunk;
+        }
+        Ok(copied)
+    }
+
+    /// Copies `len` bytes starting at `offset` within `pages` into the iterator.
+    ///
+    /// Returns the number of bytes actually copied, which may be less than `len` if a fault
+    /// occurs. The pages are mapped as needed, so highmem pages are supported.
+    ///
+    /// Equivalent to the kernel's `copy_page_to_iter`.
+    pub fn copy_page_to_iter<const ORDER: u32>(
+        &mut self,
+        pages: &Pages<ORDER>,
+        offset: usize,
+        len: usize,
+    ) -> Result<usize> {
+        self.copy_pages::<ORDER>(pages.pages, offset, len, bindings::copy_page_to_iter)
+    }
+
+    /// Copies `len` bytes from the iterator into `pages`, starting at `offset` within them.
+    ///
+    /// Returns the number of bytes actually copied, which may be less than `len` if a fault
+    /// occurs. The pages are mapped as needed, so highmem pages are supported.
+    ///
+    /// Equivalent to the kernel's `copy_page_from_iter`.
+    pub fn copy_page_from_iter<const ORDER: u32>(
+        &mut self,
+        pages: &mut Pages<ORDER>,
+        offset: usize,
+        len: usize,
+    ) -> Result<usize> {
+        self.copy_pages::<ORDER>(pages.pages, offset, len, bindings::copy_page_from_iter)
+    }
+
+    /// Advances the iterator by `len` bytes without copying anything.
+    ///
+    /// Equivalent to the kernel's `iov_iter_advance`.
+    pub fn advance(&mut self, len: usize) {
+        // SAFETY: `IovIter::ptr` is guaranteed to be valid by the type invariants. The C function
+        // clamps `len` to the number of bytes left.
+        unsafe { bindings::iov_iter_advance(self.ptr, len) };
+    }
 }
 
 impl IoBufferWriter for IovIter {
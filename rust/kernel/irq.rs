@@ -9,8 +9,23 @@
 
 #![allow(dead_code)]
 
-use crate::{bindings, c_types, error::from_kernel_result, types::PointerWrapper, Error, Result};
-use core::ops::Deref;
+use crate::{
+    bindings, c_types,
+    device::RawDevice,
+    error::{code::*, from_kernel_result},
+    str::{CStr, CString},
+    types::PointerWrapper,
+    Error, Result,
+};
+use alloc::boxed::Box;
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    marker::{PhantomData, PhantomPinned},
+    ops::Deref,
+    pin::Pin,
+    ptr,
+};
 
 /// The type of irq hardware numbers.
 pub type HwNumber = bindings::irq_hw_number_t;
@@ -131,6 +146,9 @@ pub trait Chip: Sized {
     ) -> Result {
         Ok(())
     }
+
+    /// Signals the end of an interrupt to the controller, for chips used with the `fasteoi` flow.
+    fn eoi(_data: <Self::Data as PointerWrapper>::Borrowed<'_>, _irq_data: &IrqData) {}
 }
 
 /// Initialises `chip` with the callbacks defined in `T`.
@@ -151,6 +169,10 @@ pub(crate) unsafe fn init_chip<T: Chip>(chip: &mut bindings::irq_chip) {
     if T::TO_USE.set_wake {
         chip.irq_set_wake = Some(irq_set_wake_callback::<T>);
     }
+
+    if T::TO_USE.eoi {
+        chip.irq_eoi = Some(irq_eoi_callback::<T>);
+    }
 }
 
 /// Represents which fields of [`struct irq_chip`] should be populated with pointers.
@@ -162,6 +184,9 @@ pub struct ToUse {
 
     /// The `irq_set_wake` field of [`struct irq_chip`].
     pub set_wake: bool,
+
+    /// The `irq_eoi` field of [`struct irq_chip`].
+    pub eoi: bool,
 }
 
 /// A constant version where all values are to set to `false`, that is, all supported fields will
@@ -169,6 +194,7 @@ pub struct ToUse {
 pub const USE_NONE: ToUse = ToUse {
     set_type: false,
     set_wake: false,
+    eoi: false,
 };
 
 /// Defines the [`Chip::TO_USE`] field based on a list of fields to be populated.
@@ -265,6 +291,17 @@ unsafe extern "C" fn irq_set_wake_callback<T: Chip>(
     }
 }
 
+unsafe extern "C" fn irq_eoi_callback<T: Chip>(irq_data: *mut bindings::irq_data) {
+    // SAFETY: The safety requirements of `init_chip`, which is the only place that uses this
+    // callback, ensure that the value stored as irq chip data comes from a previous call to
+    // `PointerWrapper::into_pointer`.
+    let data = unsafe { T::Data::borrow(bindings::irq_data_get_irq_chip_data(irq_data)) };
+
+    // SAFETY: The value returned by `IrqData` is only valid until the end of this function, and
+    // `irq_data` is guaranteed to be valid until then (by the contract with C code).
+    T::eoi(data, unsafe { &IrqData::from_ptr(irq_data) })
+}
+
 /// Contains constants that describes how an interrupt can be triggered.
 ///
 /// It is tagged with `non_exhaustive` to prevent users from instantiating it.
@@ -377,6 +414,289 @@ impl Domain {
     }
 }
 
+/// The high-level flow handler of the irqs of a [`DomainRegistration`].
+///
+/// It can be changed for individual irqs with [`LockedIrqData`], e.g., in [`Chip::set_type`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlowType {
+    /// Level-triggered irqs, masked and acked before the handlers run and unmasked afterwards.
+    Level,
+
+    /// Edge-triggered irqs, acked before the handlers run.
+    Edge,
+
+    /// Irqs of controllers that only need an end-of-interrupt once the handlers ran (see
+    /// [`Chip::eoi`]).
+    FastEoi,
+
+    /// Irqs of controllers behind a slow bus (e.g., i2c), which are handled with
+    /// [`DomainRegistration::handle_nested`] from the threaded handler of the parent interrupt.
+    /// Their handlers run in that thread too, so they can sleep.
+    Nested,
+}
+
+impl FlowType {
+    fn to_raw(self) -> bindings::irq_flow_handler_t {
+        match self {
+            Self::Level => Some(bindings::handle_level_irq),
+            Self::Edge => Some(bindings::handle_edge_irq),
+            Self::FastEoi => Some(bindings::handle_fasteoi_irq),
+            Self::Nested => Some(bindings::handle_simple_irq),
+        }
+    }
+}
+
+/// A linear irq domain, which maps the hardware irqs of an interrupt controller (e.g., a gpio
+/// controller with interrupts) to Linux irq numbers, and handles them with the chip `T`.
+///
+/// The domain translates devicetree interrupt specifiers with two cells: the hardware irq and the
+/// trigger type (see [`Type`]).
+///
+/// # Invariants
+///
+/// `domain` is a valid domain created with `self` as host data and `ops` as operations, and
+/// `data` was returned by `T::Data::into_pointer`. Both are released when the registration is
+/// dropped.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::{c_str, device::RawDevice, irq};
+///
+/// struct Expander;
+///
+/// impl irq::Chip for Expander {
+///     type Data = ();
+///     kernel::declare_irq_chip_operations!();
+///
+///     fn ack(_: (), _: &irq::IrqData) {}
+///     fn mask(_: (), _: &irq::IrqData) {}
+///     fn unmask(_: (), _: &irq::IrqData) {}
+/// }
+///
+/// fn setup(dev: &dyn RawDevice) -> Result<Pin<Box<irq::DomainRegistration<Expander>>>> {
+///     irq::DomainRegistration::try_new(dev, 16, c_str!("expander"), irq::FlowType::Nested, ())
+/// }
+///
+/// // Called from the (threaded) handler of the parent interrupt.
+/// fn demux(reg: &irq::DomainRegistration<Expander>, pending: u16) {
+///     for hwirq in 0..16 {
+///         if pending & (1 << hwirq) != 0 {
+///             let _ = reg.handle_nested(hwirq);
+///         }
+///     }
+/// }
+/// ```
+pub struct DomainRegistration<T: Chip> {
+    domain: *mut bindings::irq_domain,
+    chip: UnsafeCell<bindings::irq_chip>,
+    ops: UnsafeCell<bindings::irq_domain_ops>,
+    flow: FlowType,
+    size: u32,
+    data: *const c_types::c_void,
+    _p: PhantomData<T>,
+    _pin: PhantomPinned,
+}
+
+impl<T: Chip> DomainRegistration<T> {
+    /// Creates a domain for `size` hardware irqs of the interrupt controller `dev`, handled by
+    /// `T` with the given context data.
+    ///
+    /// `name` is shown in `/proc/interrupts`.
+    pub fn try_new(
+        dev: &dyn RawDevice,
+        size: u32,
+        name: &'static CStr,
+        flow: FlowType,
+        data: T::Data,
+    ) -> Result<Pin<Box<Self>>> {
+        let mut reg = Pin::from(Box::try_new(Self {
+            domain: ptr::null_mut(),
+            chip: UnsafeCell::new(bindings::irq_chip::default()),
+            ops: UnsafeCell::new(bindings::irq_domain_ops::default()),
+            flow,
+            size,
+            data: ptr::null(),
+            _p: PhantomData,
+            _pin: PhantomPinned,
+        })?);
+
+        // SAFETY: `reg` is not moved out of below, and not shared yet.
+        let this = unsafe { reg.as_mut().get_unchecked_mut() };
+
+        // SAFETY: The chip data of the irqs of the domain is set to `this.data` by
+        // `irq_domain_map_callback`, which comes from `T::Data::into_pointer` below.
+        unsafe { init_chip::<T>(this.chip.get_mut()) };
+        this.chip.get_mut().name = name.as_char_ptr();
+
+        let ops = this.ops.get_mut();
+        ops.map = Some(irq_domain_map_callback::<T>);
+        ops.unmap = Some(irq_domain_unmap_callback::<T>);
+        ops.xlate = Some(bindings::irq_domain_xlate_twocell);
+
+        this.data = data.into_pointer();
+
+        // SAFETY: `dev` is valid by the requirements of `RawDevice`. `ops` and `this` are pinned,
+        // so they remain valid until the domain is removed when the registration is dropped.
+        let domain = unsafe {
+            bindings::irq_domain_create_linear(
+                bindings::dev_fwnode(dev.raw_device()),
+                size,
+                this.ops.get(),
+                this as *mut Self as _,
+            )
+        };
+        if domain.is_null() {
+            // SAFETY: `data` was returned by `into_pointer` above, and no irq uses it.
+            unsafe { T::Data::from_pointer(this.data) };
+            this.data = ptr::null();
+            return Err(ENOMEM);
+        }
+
+        // INVARIANT: The domain was created above with `this` and `ops`.
+        this.domain = domain;
+        Ok(reg)
+    }
+
+    /// Returns the domain.
+    pub fn domain(&self) -> Domain {
+        // SAFETY: By the type invariants, `domain` is valid while `self` is alive.
+        unsafe { Domain::from_ptr(self.domain) }
+    }
+
+    /// Returns the Linux irq number of the given hardware irq, creating the mapping if needed,
+    /// e.g., to implement `gpio::Chip::to_irq`.
+    pub fn create_mapping(&self, hwirq: u32) -> Result<u32> {
+        if hwirq >= self.size {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: By the type invariants, `domain` is valid.
+        match unsafe { bindings::irq_create_mapping(self.domain, hwirq as _) } {
+            0 => Err(ENOMEM),
+            irq => Ok(irq),
+        }
+    }
+
+    /// Returns the Linux irq number of the given hardware irq, if it is mapped.
+    pub fn find_mapping(&self, hwirq: u32) -> Option<u32> {
+        // SAFETY: By the type invariants, `domain` is valid.
+        match unsafe { bindings::irq_find_mapping(self.domain, hwirq as _) } {
+            0 => None,
+            irq => Some(irq),
+        }
+    }
+
+    /// Handles the given hardware irq from the interrupt handler of the parent interrupt, in hard
+    /// interrupt context.
+    ///
+    /// Returns [`ENOENT`] if the hardware irq is not mapped, and [`EINVAL`] if the domain uses
+    /// [`FlowType::Nested`].
+    pub fn handle(&self, hwirq: u32) -> Result {
+        if self.flow == FlowType::Nested {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: By the type invariants, `domain` is valid.
+        let ret = unsafe { bindings::generic_handle_domain_irq(self.domain, hwirq) };
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+        Ok(())
+    }
+
+    /// Handles the given hardware irq from the threaded handler of the parent interrupt, e.g.,
+    /// for controllers behind a slow bus.
+    ///
+    /// Returns [`ENOENT`] if the hardware irq is not mapped, and [`EINVAL`] if the domain doesn't
+    /// use [`FlowType::Nested`].
+    pub fn handle_nested(&self, hwirq: u32) -> Result {
+        if self.flow != FlowType::Nested {
+            return Err(EINVAL);
+        }
+
+        let irq = self.find_mapping(hwirq).ok_or(ENOENT)?;
+        // SAFETY: `irq` is mapped in the domain, so it is valid.
+        unsafe { bindings::handle_nested_irq(irq) };
+        Ok(())
+    }
+}
+
+impl<T: Chip> Drop for DomainRegistration<T> {
+    fn drop(&mut self) {
+        if self.domain.is_null() {
+            return;
+        }
+
+        // SAFETY: By the type invariants, `domain` is valid. Once all mappings are disposed of and
+        // the domain is removed, the chip and its data are no longer used.
+        unsafe {
+            for hwirq in 0..self.size {
+                let irq = bindings::irq_find_mapping(self.domain, hwirq as _);
+                if irq != 0 {
+                    bindings::irq_dispose_mapping(irq);
+                }
+            }
+            bindings::irq_domain_remove(self.domain);
+            T::Data::from_pointer(self.data);
+        }
+    }
+}
+
+// SAFETY: The domain can be created and removed from any thread, and `T::Data`, which is dropped
+// with it, is `Send`.
+unsafe impl<T: Chip> Send for DomainRegistration<T> where T::Data: Send {}
+
+// SAFETY: The methods of the registration are safe to call concurrently, and the chip callbacks
+// may access `T::Data` from any CPU, so it is `Sync`.
+unsafe impl<T: Chip> Sync for DomainRegistration<T> where T::Data: Sync {}
+
+unsafe extern "C" fn irq_domain_map_callback<T: Chip>(
+    d: *mut bindings::irq_domain,
+    virq: c_types::c_uint,
+    _hw: bindings::irq_hw_number_t,
+) -> c_types::c_int {
+    // SAFETY: The host data of the domain is the registration that created it, which outlives it.
+    let reg = unsafe { &*((*d).host_data as *const DomainRegistration<T>) };
+
+    // SAFETY: `virq` is being mapped in the domain, so it is valid. The chip and its data are
+    // valid until the mapping is disposed of, when the registration is dropped.
+    unsafe {
+        bindings::irq_set_chip_data(virq, reg.data as _);
+        bindings::irq_set_chip_and_handler_name(
+            virq,
+            reg.chip.get(),
+            reg.flow.to_raw(),
+            ptr::null(),
+        );
+
+        // Nested irqs must be requested with a threaded handler only, which is run by
+        // `handle_nested_irq`.
+        if reg.flow == FlowType::Nested {
+            bindings::irq_set_nested_thread(virq, true);
+        }
+    }
+    0
+}
+
+unsafe extern "C" fn irq_domain_unmap_callback<T: Chip>(
+    d: *mut bindings::irq_domain,
+    virq: c_types::c_uint,
+) {
+    // SAFETY: The host data of the domain is the registration that created it, which outlives it.
+    let reg = unsafe { &*((*d).host_data as *const DomainRegistration<T>) };
+
+    // SAFETY: `virq` is being unmapped from the domain, so it is still valid.
+    unsafe {
+        if reg.flow == FlowType::Nested {
+            bindings::irq_set_nested_thread(virq, false);
+        }
+        bindings::irq_set_chip_and_handler_name(virq, ptr::null_mut(), None, ptr::null());
+        bindings::irq_set_chip_data(virq, ptr::null_mut());
+    }
+}
+
 /// A high-level irq flow handler.
 pub trait FlowHandler {
     /// The data associated with the handler.
@@ -407,3 +727,286 @@ unsafe extern "C" fn irq_flow_handler<T: FlowHandler>(desc: *mut bindings::irq_d
     // outlives the lifetime returned by `from_desc`.
     T::handle_irq_flow(data, &unsafe { Descriptor::from_ptr(desc) });
 }
+
+/// The return value from interrupt handlers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Return {
+    /// The interrupt was not from this device or was not handled.
+    None = bindings::irqreturn_IRQ_NONE,
+
+    /// The interrupt was handled by this device.
+    Handled = bindings::irqreturn_IRQ_HANDLED,
+
+    /// The handler wants the handler thread to wake up.
+    WakeThread = bindings::irqreturn_IRQ_WAKE_THREAD,
+}
+
+/// Contains constants for the flags used when registering interrupt handlers.
+///
+/// It is tagged with `non_exhaustive` to prevent users from instantiating it.
+#[non_exhaustive]
+pub struct Flags;
+
+impl Flags {
+    /// Use the trigger type already configured for the interrupt (e.g., by the device tree).
+    pub const TRIGGER_NONE: u32 = bindings::IRQF_TRIGGER_NONE;
+
+    /// The interrupt is triggered when the signal goes from low to high.
+    pub const TRIGGER_RISING: u32 = bindings::IRQF_TRIGGER_RISING;
+
+    /// The interrupt is triggered when the signal goes from high to low.
+    pub const TRIGGER_FALLING: u32 = bindings::IRQF_TRIGGER_FALLING;
+
+    /// The interrupt is triggered while the signal is held high.
+    pub const TRIGGER_HIGH: u32 = bindings::IRQF_TRIGGER_HIGH;
+
+    /// The interrupt is triggered while the signal is held low.
+    pub const TRIGGER_LOW: u32 = bindings::IRQF_TRIGGER_LOW;
+
+    /// The interrupt may be shared with other devices, whose handlers must all return
+    /// [`Return::None`] for interrupts that are not theirs.
+    pub const SHARED: u32 = bindings::IRQF_SHARED;
+
+    /// The interrupt is kept disabled after the primary handler returns until the threaded
+    /// handler completes. It is required for threaded handlers without a primary handler.
+    pub const ONESHOT: u32 = bindings::IRQF_ONESHOT;
+
+    /// The interrupt is not disabled during suspend.
+    pub const NO_SUSPEND: u32 = bindings::IRQF_NO_SUSPEND;
+
+    /// The handler is never threaded, even when interrupt handlers are forced to be threaded.
+    pub const NO_THREAD: u32 = bindings::IRQF_NO_THREAD;
+
+    /// The interrupt is excluded from irq balancing.
+    pub const NOBALANCING: u32 = bindings::IRQF_NOBALANCING;
+}
+
+/// An interrupt handler that runs in hard interrupt context.
+pub trait Handler {
+    /// The context data associated with and made available to the handler.
+    type Data: PointerWrapper + Sync + Send;
+
+    /// Called from hard interrupt context when the irq happens.
+    ///
+    /// It must not sleep, and it may run concurrently on other CPUs for shared interrupts.
+    fn handle_irq(data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Return;
+}
+
+/// The registration of an interrupt handler.
+///
+/// The handler is unregistered with `free_irq` when the registration is dropped, which waits for
+/// running handlers to complete.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::irq;
+///
+/// struct Example;
+///
+/// impl irq::Handler for Example {
+///     type Data = Box<u32>;
+///
+///     fn handle_irq(_data: &u32) -> irq::Return {
+///         irq::Return::None
+///     }
+/// }
+///
+/// fn request_irq(irq: u32, data: Box<u32>) -> Result<irq::Registration<Example>> {
+///     irq::Registration::try_new(irq, data, irq::Flags::SHARED, fmt!("example_{irq}"))
+/// }
+/// ```
+pub struct Registration<H: Handler>(InternalRegistration<H::Data>);
+
+impl<H: Handler> Registration<H> {
+    /// Registers a new irq handler.
+    ///
+    /// The valid values of `flags` come from [`Flags`].
+    pub fn try_new(irq: u32, data: H::Data, flags: u32, name: fmt::Arguments<'_>) -> Result<Self> {
+        // SAFETY: `handler` only calls `H::Data::borrow` on `raw_data`.
+        Ok(Self(unsafe {
+            InternalRegistration::try_new(irq, Some(Self::handler), None, flags, data, name)?
+        }))
+    }
+
+    unsafe extern "C" fn handler(
+        _irq: c_types::c_int,
+        raw_data: *mut c_types::c_void,
+    ) -> bindings::irqreturn_t {
+        // SAFETY: On registration, `into_pointer` was called, so it is safe to borrow from it here
+        // because `from_pointer` is called only after the irq is unregistered.
+        let data = unsafe { H::Data::borrow(raw_data) };
+        H::handle_irq(data) as _
+    }
+}
+
+/// A threaded interrupt handler.
+///
+/// The primary handler runs in hard interrupt context and the threaded one in a kernel thread,
+/// where it may sleep (e.g., to talk to the device over a slow bus).
+pub trait ThreadedHandler {
+    /// The context data associated with and made available to the handlers.
+    type Data: PointerWrapper + Sync + Send;
+
+    /// Called from hard interrupt context when the irq happens.
+    ///
+    /// It must not sleep. It returns [`Return::WakeThread`] to run the threaded handler, which is
+    /// what the default implementation does.
+    fn handle_primary_irq(_data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Return {
+        Return::WakeThread
+    }
+
+    /// Called from the handler thread.
+    fn handle_threaded_irq(data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Return;
+}
+
+/// The registration of a threaded interrupt handler.
+///
+/// The handlers are unregistered with `free_irq` when the registration is dropped, which waits
+/// for running handlers to complete.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::irq;
+///
+/// struct Example;
+///
+/// impl irq::ThreadedHandler for Example {
+///     type Data = Box<u32>;
+///
+///     fn handle_threaded_irq(_data: &u32) -> irq::Return {
+///         // Read the status from the device, which may sleep.
+///         irq::Return::Handled
+///     }
+/// }
+///
+/// fn request_irq(irq: u32, data: Box<u32>) -> Result<irq::ThreadedRegistration<Example>> {
+///     irq::ThreadedRegistration::try_new(irq, data, irq::Flags::ONESHOT, fmt!("example_{irq}"))
+/// }
+/// ```
+pub struct ThreadedRegistration<H: ThreadedHandler>(InternalRegistration<H::Data>);
+
+impl<H: ThreadedHandler> ThreadedRegistration<H> {
+    /// Registers a new threaded irq handler.
+    ///
+    /// The valid values of `flags` come from [`Flags`].
+    pub fn try_new(irq: u32, data: H::Data, flags: u32, name: fmt::Arguments<'_>) -> Result<Self> {
+        // SAFETY: both `primary_handler` and `threaded_handler` only call `H::Data::borrow` on
+        // `raw_data`.
+        Ok(Self(unsafe {
+            InternalRegistration::try_new(
+                irq,
+                Some(Self::primary_handler),
+                Some(Self::threaded_handler),
+                flags,
+                data,
+                name,
+            )?
+        }))
+    }
+
+    unsafe extern "C" fn primary_handler(
+        _irq: c_types::c_int,
+        raw_data: *mut c_types::c_void,
+    ) -> bindings::irqreturn_t {
+        // SAFETY: On registration, `into_pointer` was called, so it is safe to borrow from it here
+        // because `from_pointer` is called only after the irq is unregistered.
+        let data = unsafe { H::Data::borrow(raw_data) };
+        H::handle_primary_irq(data) as _
+    }
+
+    unsafe extern "C" fn threaded_handler(
+        _irq: c_types::c_int,
+        raw_data: *mut c_types::c_void,
+    ) -> bindings::irqreturn_t {
+        // SAFETY: On registration, `into_pointer` was called, so it is safe to borrow from it here
+        // because `from_pointer` is called only after the irq is unregistered.
+        let data = unsafe { H::Data::borrow(raw_data) };
+        H::handle_threaded_irq(data) as _
+    }
+}
+
+/// The registration of an irq handler, shared by [`Registration`] and [`ThreadedRegistration`].
+///
+/// # Invariants
+///
+/// `data` was returned by `T::into_pointer` and is the `dev_id` of a handler registered for
+/// `irq`.
+struct InternalRegistration<T: PointerWrapper> {
+    irq: u32,
+    data: *mut c_types::c_void,
+    _name: CString,
+    _p: PhantomData<T>,
+}
+
+impl<T: PointerWrapper> InternalRegistration<T> {
+    /// Registers a new irq handler.
+    ///
+    /// # Safety
+    ///
+    /// Callers must ensure that `handler` and `thread_fn` are compatible with the registration,
+    /// that is, that they only use their second argument while the call is happening and that they
+    /// only call [`PointerWrapper::borrow`] on it (e.g., they shouldn't call
+    /// [`PointerWrapper::from_pointer`] and pass ownership to the caller).
+    unsafe fn try_new(
+        irq: u32,
+        handler: bindings::irq_handler_t,
+        thread_fn: bindings::irq_handler_t,
+        flags: u32,
+        data: T,
+        name: fmt::Arguments<'_>,
+    ) -> Result<Self> {
+        // The name must outlive the registration because the kernel keeps a pointer to it (e.g.,
+        // for `/proc/interrupts`).
+        let name = CString::try_from_fmt(name)?;
+        let ptr = data.into_pointer() as *mut _;
+
+        // SAFETY: `name` and `ptr` remain valid as long as the registration is alive.
+        let ret = unsafe {
+            bindings::request_threaded_irq(
+                irq,
+                handler,
+                thread_fn,
+                flags as _,
+                name.as_char_ptr(),
+                ptr,
+            )
+        };
+        if ret != 0 {
+            // SAFETY: `ptr` came from a previous call to `into_pointer`, and the handler wasn't
+            // registered.
+            unsafe { T::from_pointer(ptr) };
+            return Err(Error::from_kernel_errno(ret));
+        }
+
+        // INVARIANT: The handler was just registered with `ptr` as its `dev_id`.
+        Ok(Self {
+            irq,
+            data: ptr,
+            _name: name,
+            _p: PhantomData,
+        })
+    }
+}
+
+impl<T: PointerWrapper> Drop for InternalRegistration<T> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, the handler was registered for `irq` with `data` as its
+        // `dev_id`. `free_irq` waits for running handlers to complete.
+        unsafe { bindings::free_irq(self.irq, self.data) };
+
+        // SAFETY: By the type invariants, `data` was returned by `into_pointer`, and the handlers
+        // that used it are no longer registered.
+        unsafe { T::from_pointer(self.data) };
+    }
+}
+
+// SAFETY: The data is `Send`, and the registration can be dropped from any thread.
+unsafe impl<T: PointerWrapper + Send> Send for InternalRegistration<T> {}
+
+// SAFETY: Shared references to the registration don't give access to anything.
+unsafe impl<T: PointerWrapper> Sync for InternalRegistration<T> {}
@@ -0,0 +1,135 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Work items run in hard interrupt context.
+//!
+//! An [`IrqWork`] can be queued from any context, including NMI handlers, to run a closure soon
+//! after in hard interrupt context on the same CPU (e.g., to take locks or wake up tasks, which
+//! NMI handlers cannot do). Work that needs to sleep should use [`crate::workqueue`] instead.
+//!
+//! C header: [`include/linux/irq_work.h`](../../../../include/linux/irq_work.h)
+
+use crate::{bindings, Opaque};
+use core::{marker::PhantomPinned, pin::Pin};
+
+/// A work item that calls a closure in hard interrupt context.
+///
+/// Wraps the kernel's `struct irq_work`. Queuing a work item that is already pending has no
+/// effect, so the closure runs once for any number of calls to [`IrqWork::queue`] before it runs.
+///
+/// The work item must first be initialised with a call to [`IrqWork::init_irq_work`] before it
+/// can be used. It is synchronised with when it is dropped, so the closure never runs after that.
+/// The closure must be `'static` because a work item that is leaked is never synchronised with.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// # use alloc::boxed::Box;
+/// # use core::pin::Pin;
+/// use core::sync::atomic::{AtomicU64, Ordering};
+/// use kernel::irq_work::IrqWork;
+///
+/// static OVERFLOWS: AtomicU64 = AtomicU64::new(0);
+///
+/// fn example() -> Result<Pin<Box<IrqWork<impl Fn() + Sync>>>> {
+///     // SAFETY: `init_irq_work` is called below.
+///     let mut work = Pin::from(Box::try_new(unsafe {
+///         IrqWork::new(|| {
+///             // Printing is not safe from NMI context, but it is here.
+///             pr_warn!("{} counter overflows\n", OVERFLOWS.load(Ordering::Relaxed));
+///         })
+///     })?);
+///     // SAFETY: The work item was just created, so it isn't pending.
+///     unsafe { work.as_mut().init_irq_work() };
+///
+///     // From an NMI handler.
+///     OVERFLOWS.fetch_add(1, Ordering::Relaxed);
+///     work.queue();
+///     Ok(work)
+/// }
+/// ```
+pub struct IrqWork<F> {
+    /// The kernel `struct irq_work` object.
+    work: Opaque<bindings::irq_work>,
+
+    func: F,
+
+    /// A work item needs to be pinned because the kernel keeps pointers to it while it is pending.
+    _pin: PhantomPinned,
+}
+
+// SAFETY: The work item can be used from any thread, and the closure is moved with it.
+unsafe impl<F: Send> Send for IrqWork<F> {}
+
+// SAFETY: The closure is called from hard interrupt context while other threads may hold shared
+// references to the work item, so it must be `Sync`. The `irq_work` functions are safe to call
+// concurrently.
+unsafe impl<F: Sync> Sync for IrqWork<F> {}
+
+impl<F: Fn() + Sync + 'static> IrqWork<F> {
+    /// Constructs a new work item that calls `func` when it runs.
+    ///
+    /// # Safety
+    ///
+    /// The caller must call [`IrqWork::init_irq_work`] before using or dropping the work item.
+    pub unsafe fn new(func: F) -> Self {
+        Self {
+            work: Opaque::uninit(),
+            func,
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Initialises the work item.
+    ///
+    /// # Safety
+    ///
+    /// The work item must not be pending or running, since `init_irq_work` would reset the flags
+    /// and the list node that the per-CPU work list relies on.
+    pub unsafe fn init_irq_work(self: Pin<&mut Self>) {
+        // SAFETY: `work` is pinned and is being initialised here, and the caller guarantees that
+        // it isn't on a per-CPU work list.
+        unsafe { bindings::init_irq_work(self.work.get(), Some(Self::work_func)) };
+    }
+
+    /// Queues the work item to run on the current CPU, unless it is already pending.
+    ///
+    /// Returns `true` if it was queued. It can be called from any context, including NMI
+    /// handlers.
+    pub fn queue(&self) -> bool {
+        // SAFETY: The work item was initialised, as required by `new`.
+        unsafe { bindings::irq_work_queue(self.work.get()) }
+    }
+
+    /// Queues the work item to run on the given CPU, unless it is already pending.
+    ///
+    /// Returns `true` if it was queued. Unlike [`IrqWork::queue`], it cannot be called from NMI
+    /// handlers when `cpu` is not the current CPU.
+    pub fn queue_on(&self, cpu: u32) -> bool {
+        // SAFETY: The work item was initialised, as required by `new`.
+        unsafe { bindings::irq_work_queue_on(self.work.get(), cpu as _) }
+    }
+
+    /// Waits for the closure to complete if the work item is pending or running.
+    ///
+    /// It must not be called from the closure.
+    pub fn sync(&self) {
+        // SAFETY: The work item was initialised, as required by `new`.
+        unsafe { bindings::irq_work_sync(self.work.get()) };
+    }
+
+    unsafe extern "C" fn work_func(w: *mut bindings::irq_work) {
+        // SAFETY: `w` is the `work` field of an `IrqWork<F>`, which is pinned and remains valid
+        // until it is synchronised with when dropped.
+        let work = unsafe { &*crate::container_of!(w, Self, work) };
+        (work.func)();
+    }
+}
+
+impl<F> Drop for IrqWork<F> {
+    fn drop(&mut self) {
+        // SAFETY: The work item was initialised, as required by `new`. `irq_work_sync` waits for
+        // it to run if it is pending, so the closure isn't used afterwards.
+        unsafe { bindings::irq_work_sync(self.work.get()) };
+    }
+}
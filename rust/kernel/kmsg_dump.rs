@@ -0,0 +1,284 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Kernel log dumpers.
+//!
+//! Dumpers are called when the kernel oopses, panics or shuts down, and can save the tail of the
+//! kernel log somewhere persistent, as `pstore` does.
+//!
+//! C header: [`include/linux/kmsg_dump.h`](../../../../include/linux/kmsg_dump.h)
+
+use crate::{bindings, c_types, error::code::*, to_result, types::PointerWrapper, Result};
+use alloc::boxed::Box;
+use core::{marker::PhantomData, marker::PhantomPinned, pin::Pin};
+
+/// The reason why the kernel log is being dumped.
+///
+/// Corresponds to the kernel's `enum kmsg_dump_reason`. The reasons are ordered by severity, so
+/// that a dumper registered with a given maximum reason is called for that reason and all the
+/// more severe ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum Reason {
+    /// The reason is not known.
+    Undefined = bindings::kmsg_dump_reason_KMSG_DUMP_UNDEF,
+
+    /// The kernel panicked.
+    Panic = bindings::kmsg_dump_reason_KMSG_DUMP_PANIC,
+
+    /// The kernel oopsed.
+    Oops = bindings::kmsg_dump_reason_KMSG_DUMP_OOPS,
+
+    /// An emergency restart (e.g., via sysrq) is happening.
+    Emergency = bindings::kmsg_dump_reason_KMSG_DUMP_EMERG,
+
+    /// The system is shutting down (restart, halt or power off).
+    Shutdown = bindings::kmsg_dump_reason_KMSG_DUMP_SHUTDOWN,
+}
+
+impl Reason {
+    fn from_raw(reason: bindings::kmsg_dump_reason) -> Self {
+        match reason {
+            bindings::kmsg_dump_reason_KMSG_DUMP_PANIC => Self::Panic,
+            bindings::kmsg_dump_reason_KMSG_DUMP_OOPS => Self::Oops,
+            bindings::kmsg_dump_reason_KMSG_DUMP_EMERG => Self::Emergency,
+            bindings::kmsg_dump_reason_KMSG_DUMP_SHUTDOWN => Self::Shutdown,
+            _ => Self::Undefined,
+        }
+    }
+}
+
+/// An iterator over the records of the kernel log.
+///
+/// It starts at the oldest record in the log buffer. Each record is formatted as in the console
+/// output (or as in `/dev/kmsg` with the `<level>` syslog prefix when requested).
+///
+/// Wraps the kernel's `struct kmsg_dump_iter`.
+pub struct Records {
+    iter: bindings::kmsg_dump_iter,
+}
+
+impl Records {
+    fn new() -> Self {
+        let mut records = Self {
+            iter: bindings::kmsg_dump_iter::default(),
+        };
+        records.rewind();
+        records
+    }
+
+    /// Resets the iterator so that the whole log buffer is visited again.
+    ///
+    /// Equivalent to the kernel's `kmsg_dump_rewind`.
+    pub fn rewind(&mut self) {
+        // SAFETY: `self.iter` is valid for writes.
+        unsafe { bindings::kmsg_dump_rewind(&mut self.iter) };
+    }
+
+    /// Copies the next (oldest remaining) record into `buf`, returning the part of `buf` that was
+    /// filled, or `None` if there are no more records.
+    ///
+    /// Records that do not fit are truncated.
+    ///
+    /// Equivalent to the kernel's `kmsg_dump_get_line`.
+    pub fn next_line<'a>(&mut self, syslog: bool, buf: &'a mut [u8]) -> Option<&'a [u8]> {
+        let mut len = 0;
+
+        // SAFETY: `self.iter` is valid, and `buf` is valid for writes of `buf.len()` bytes.
+        let found = unsafe {
+            bindings::kmsg_dump_get_line(
+                &mut self.iter,
+                syslog,
+                buf.as_mut_ptr() as *mut c_types::c_char,
+                buf.len(),
+                &mut len,
+            )
+        };
+        if found {
+            Some(&buf[..len])
+        } else {
+            None
+        }
+    }
+
+    /// Copies as many of the newest remaining records as fit into `buf`, returning the part of
+    /// `buf` that was filled, or `None` if there are no more records.
+    ///
+    /// Records are copied in chronological order, so this is the tail of the log. A subsequent
+    /// call returns the records that precede the ones returned previously, so a log larger than
+    /// `buf` can be saved in chunks, from the newest to the oldest.
+    ///
+    /// Equivalent to the kernel's `kmsg_dump_get_buffer`.
+    pub fn tail<'a>(&mut self, syslog: bool, buf: &'a mut [u8]) -> Option<&'a [u8]> {
+        let mut len = 0;
+
+        // SAFETY: `self.iter` is valid, and `buf` is valid for writes of `buf.len()` bytes.
+        let found = unsafe {
+            bindings::kmsg_dump_get_buffer(
+                &mut self.iter,
+                syslog,
+                buf.as_mut_ptr() as *mut c_types::c_char,
+                buf.len(),
+                &mut len,
+            )
+        };
+        if found {
+            Some(&buf[..len])
+        } else {
+            None
+        }
+    }
+}
+
+/// Corresponds to the `dump` callback of the kernel's `struct kmsg_dumper`.
+pub trait Dumper {
+    /// The pointer type that will be used to hold user-defined data type.
+    type Data: PointerWrapper + Send + Sync = ();
+
+    /// Called when the kernel log is dumped.
+    ///
+    /// This may be called in atomic context, with interrupts disabled and other CPUs stopped
+    /// (e.g., on panic), so it must not sleep, allocate with `GFP_KERNEL` or take locks that may
+    /// be held elsewhere.
+    fn dump(
+        data: <Self::Data as PointerWrapper>::Borrowed<'_>,
+        reason: Reason,
+        records: &mut Records,
+    );
+}
+
+/// A registration of a kernel log dumper.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// # use kernel::kmsg_dump::{self, Reason, Records};
+/// struct Example;
+///
+/// impl kmsg_dump::Dumper for Example {
+///     fn dump(_data: (), reason: Reason, records: &mut Records) {
+///         let mut buf = [0u8; 256];
+///         if let Some(tail) = records.tail(false, &mut buf) {
+///             // Save `tail` somewhere persistent.
+///             let _ = (reason, tail);
+///         }
+///     }
+/// }
+///
+/// fn example() -> Result<Pin<Box<kmsg_dump::Registration<Example>>>> {
+///     kmsg_dump::Registration::new_pinned(Reason::Oops, ())
+/// }
+/// ```
+///
+/// # Invariants
+///
+/// `data` holds a pointer returned by [`PointerWrapper::into_pointer`] when `registered` is
+/// `true`.
+pub struct Registration<T: Dumper> {
+    dumper: bindings::kmsg_dumper,
+    data: *const c_types::c_void,
+    registered: bool,
+    _pin: PhantomPinned,
+    _p: PhantomData<T>,
+}
+
+impl<T: Dumper> Registration<T> {
+    /// Creates a new [`Registration`] but does not register it yet.
+    ///
+    /// It is allowed to move.
+    pub fn new() -> Self {
+        // INVARIANT: `registered` is `false`.
+        Self {
+            dumper: bindings::kmsg_dumper::default(),
+            data: core::ptr::null(),
+            registered: false,
+            _pin: PhantomPinned,
+            _p: PhantomData,
+        }
+    }
+
+    /// Registers a kernel log dumper.
+    ///
+    /// Returns a pinned heap-allocated representation of the registration.
+    pub fn new_pinned(max_reason: Reason, data: T::Data) -> Result<Pin<Box<Self>>> {
+        let mut reg = Pin::from(Box::try_new(Self::new())?);
+        reg.as_mut().register(max_reason, data)?;
+        Ok(reg)
+    }
+
+    /// Registers a kernel log dumper with the rest of the kernel.
+    ///
+    /// The dumper is called for `max_reason` and all the more severe reasons (e.g., for panics
+    /// and oopses if `max_reason` is [`Reason::Oops`]). The `printk.always_kmsg_dump` command
+    /// line option raises it to [`Reason::Shutdown`].
+    ///
+    /// It must be pinned because the memory block that represents the registration is linked
+    /// into the kernel's list of dumpers.
+    pub fn register(self: Pin<&mut Self>, max_reason: Reason, data: T::Data) -> Result {
+        // SAFETY: We never move out of `this`.
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.registered {
+            return Err(EINVAL);
+        }
+
+        this.dumper.dump = Some(Self::dump_callback);
+        this.dumper.max_reason = max_reason as _;
+        this.data = data.into_pointer();
+
+        // SAFETY: `this.dumper` is initialised above and, since `this` is pinned, remains valid
+        // until it is unregistered in `drop`.
+        let ret = to_result(|| unsafe { bindings::kmsg_dump_register(&mut this.dumper) });
+        if let Err(e) = ret {
+            // SAFETY: `data` was returned by `into_pointer` above and the dumper is not
+            // registered, so it cannot be used anymore.
+            unsafe { T::Data::from_pointer(this.data) };
+            return Err(e);
+        }
+
+        // INVARIANT: `data` was set above.
+        this.registered = true;
+        Ok(())
+    }
+
+    unsafe extern "C" fn dump_callback(
+        dumper: *mut bindings::kmsg_dumper,
+        reason: bindings::kmsg_dump_reason,
+    ) {
+        let reg = crate::container_of!(dumper, Self, dumper);
+
+        // SAFETY: The kernel only calls this function on registered dumpers, which are embedded
+        // in a `Registration<T>`. By the type invariants, `data` was returned by `into_pointer`
+        // and is only reclaimed after the dumper is unregistered.
+        let data = unsafe { T::Data::borrow((*reg).data) };
+        T::dump(data, Reason::from_raw(reason), &mut Records::new());
+    }
+}
+
+impl<T: Dumper> Default for Registration<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// SAFETY: `Registration` does not expose any of its state across threads.
+unsafe impl<T: Dumper> Sync for Registration<T> {}
+
+// SAFETY: `Registration` is not restricted to a single thread, its `T::Data` is also `Send` so it
+// may be moved to different threads.
+#[allow(clippy::non_send_fields_in_send_ty)]
+unsafe impl<T: Dumper> Send for Registration<T> {}
+
+impl<T: Dumper> Drop for Registration<T> {
+    /// Removes the registration from the kernel if it has completed successfully before.
+    fn drop(&mut self) {
+        if self.registered {
+            // SAFETY: The dumper was registered in `register`. `kmsg_dump_unregister` waits for
+            // concurrent calls to the dumper to complete.
+            unsafe { bindings::kmsg_dump_unregister(&mut self.dumper) };
+
+            // SAFETY: By the type invariants, `data` was returned by `into_pointer`, and the
+            // dumper won't be called anymore.
+            unsafe { T::Data::from_pointer(self.data) };
+        }
+    }
+}
@@ -0,0 +1,393 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Kernel objects.
+//!
+//! A [`KObject`] is a directory in sysfs that holds some data, shown and modified through the
+//! attributes of its [`KType`]. Subsystems use them to build their own hierarchies, e.g., under
+//! `/sys/kernel/<subsystem>/`, when the objects aren't devices.
+//!
+//! C header: [`include/linux/kobject.h`](../../../../include/linux/kobject.h)
+//!
+//! Reference: <https://www.kernel.org/doc/html/latest/core-api/kobject.html>
+
+use crate::{
+    bindings, c_str, c_types,
+    error::{to_result, Result},
+    str::{CStr, CString},
+    sysfs, ARef, AlwaysRefCounted, Opaque,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::{fmt, marker::PhantomData, ops::Deref, ptr, ptr::NonNull};
+
+/// The action of a uevent, i.e., the value of its `ACTION` variable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// The object was added.
+    Add,
+
+    /// The object is being removed.
+    Remove,
+
+    /// The state of the object changed, e.g., a medium was inserted.
+    Change,
+
+    /// The object was renamed or moved.
+    Move,
+
+    /// The object (e.g., a CPU) was brought online.
+    Online,
+
+    /// The object was taken offline.
+    Offline,
+
+    /// A driver was bound to the device.
+    Bind,
+
+    /// The driver of the device was unbound.
+    Unbind,
+}
+
+impl Action {
+    fn as_raw(self) -> bindings::kobject_action {
+        match self {
+            Self::Add => bindings::kobject_action_KOBJ_ADD,
+            Self::Remove => bindings::kobject_action_KOBJ_REMOVE,
+            Self::Change => bindings::kobject_action_KOBJ_CHANGE,
+            Self::Move => bindings::kobject_action_KOBJ_MOVE,
+            Self::Online => bindings::kobject_action_KOBJ_ONLINE,
+            Self::Offline => bindings::kobject_action_KOBJ_OFFLINE,
+            Self::Bind => bindings::kobject_action_KOBJ_BIND,
+            Self::Unbind => bindings::kobject_action_KOBJ_UNBIND,
+        }
+    }
+}
+
+/// The extra environment variables of a uevent, which udev rules can match on.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::{device::RawDevice, kobject::{Action, UeventEnv}};
+///
+/// fn notify_temperature(dev: &dyn RawDevice, millicelsius: i32) -> Result {
+///     let mut env = UeventEnv::new();
+///     env.add("EVENT", fmt!("overheat"))?;
+///     env.add("TEMP", fmt!("{}", millicelsius))?;
+///     dev.emit_uevent(Action::Change, &env)
+/// }
+/// ```
+#[derive(Default)]
+pub struct UeventEnv {
+    vars: Vec<CString>,
+}
+
+impl UeventEnv {
+    /// Creates an empty environment.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the variable `key` with the given value.
+    pub fn add(&mut self, key: &str, value: fmt::Arguments<'_>) -> Result<&mut Self> {
+        self.vars
+            .try_push(CString::try_from_fmt(format_args!("{}={}", key, value))?)?;
+        Ok(self)
+    }
+
+    /// Emits a uevent for `kobj` with this environment.
+    ///
+    /// # Safety
+    ///
+    /// `kobj` must be valid and added to sysfs.
+    pub(crate) unsafe fn emit(&self, kobj: *mut bindings::kobject, action: Action) -> Result {
+        let mut envp = Vec::try_with_capacity(self.vars.len() + 1)?;
+        for var in &self.vars {
+            envp.try_push(var.as_char_ptr() as *mut c_types::c_char)?;
+        }
+        envp.try_push(ptr::null_mut())?;
+
+        // SAFETY: `kobj` is valid by the safety requirements, and `envp` is a null-terminated
+        // array of C strings, which are only read.
+        to_result(|| unsafe {
+            bindings::kobject_uevent_env(kobj, action.as_raw(), envp.as_mut_ptr())
+        })
+    }
+}
+
+/// The type of the data held by [`KObject`]s.
+///
+/// The data is dropped when the last reference to the object is dropped, after the object has
+/// been removed from sysfs; this is the `release` function of the `struct kobj_type`.
+pub trait KType: Send + Sync + Sized + 'static {
+    /// The attributes of the objects, which are the files in their directories.
+    const ATTRIBUTES: &'static [sysfs::Attribute<Self>] = &[];
+}
+
+/// An attribute of a [`KObject`].
+#[repr(C)]
+struct KAttribute<T: KType> {
+    attr: bindings::attribute,
+    def: &'static sysfs::Attribute<T>,
+}
+
+/// A kernel object holding data of type `T`, i.e., a directory in sysfs.
+///
+/// # Invariants
+///
+/// Instances of this type are always ref-counted by `kobject_get` and `kobject_put`, and `kobj`
+/// was initialised with the `struct kobj_type` of `T`, whose `release` function frees the object.
+/// `ptrs` is a null-terminated array of pointers to the `attr` fields of `_attrs`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use core::fmt::Write;
+/// use core::sync::atomic::{AtomicU32, Ordering};
+/// use kernel::{c_str, kobject::{KObject, KType}, sysfs::Attribute, ARef};
+///
+/// struct Port {
+///     id: u32,
+///     enabled: AtomicU32,
+/// }
+///
+/// impl KType for Port {
+///     const ATTRIBUTES: &'static [Attribute<Self>] = &[
+///         Attribute::ro(c_str!("id"), |port, buf| Ok(writeln!(buf, "{}", port.id)?)),
+///         Attribute::rw(
+///             c_str!("enabled"),
+///             |port, buf| Ok(writeln!(buf, "{}", port.enabled.load(Ordering::Relaxed))?),
+///             |port, input| {
+///                 let value = match input {
+///                     b"0" | b"0\n" => 0,
+///                     b"1" | b"1\n" => 1,
+///                     _ => return Err(EINVAL),
+///                 };
+///                 port.enabled.store(value, Ordering::Relaxed);
+///                 Ok(())
+///             },
+///         ),
+///     ];
+/// }
+///
+/// struct Switch;
+///
+/// impl KType for Switch {}
+///
+/// // Creates `/sys/kernel/switch/port0` and `/sys/kernel/switch/port1`.
+/// fn create() -> Result<(ARef<KObject<Switch>>, Vec<ARef<KObject<Port>>>)> {
+///     let switch = KObject::new_in_kernel(fmt!("switch"), Switch)?;
+///     let mut ports = Vec::new();
+///     for id in 0..2 {
+///         let enabled = AtomicU32::new(0);
+///         ports.try_push(switch.new_child(fmt!("port{}", id), Port { id, enabled })?)?;
+///     }
+///     Ok((switch, ports))
+/// }
+/// ```
+#[repr(C)]
+pub struct KObject<T: KType> {
+    kobj: Opaque<bindings::kobject>,
+    _attrs: Vec<KAttribute<T>>,
+    ptrs: Vec<*mut bindings::attribute>,
+    data: T,
+}
+
+// SAFETY: `KObject` only gives access to the data, which is `Send` and `Sync`, and it may be
+// released from any thread.
+unsafe impl<T: KType> Send for KObject<T> {}
+
+// SAFETY: The data is `Sync`, and the C functions of kobjects are safe to call concurrently.
+unsafe impl<T: KType> Sync for KObject<T> {}
+
+impl<T: KType> KObject<T> {
+    /// Creates a new object in `/sys/kernel`.
+    pub fn new_in_kernel(name: fmt::Arguments<'_>, data: T) -> Result<ARef<Self>> {
+        // SAFETY: `kernel_kobj` is initialised early during boot and never freed.
+        Self::create(name, unsafe { bindings::kernel_kobj }, data)
+    }
+
+    /// Creates a new object in the directory of `self`.
+    ///
+    /// The child keeps a reference to `self`, so `self` isn't released before its children.
+    pub fn new_child<U: KType>(
+        &self,
+        name: fmt::Arguments<'_>,
+        data: U,
+    ) -> Result<ARef<KObject<U>>> {
+        KObject::create(name, self.kobj.get(), data)
+    }
+
+    fn create(
+        name: fmt::Arguments<'_>,
+        parent: *mut bindings::kobject,
+        data: T,
+    ) -> Result<ARef<Self>> {
+        let name = CString::try_from_fmt(name)?;
+
+        // The attributes are allocated along with the object, so they use the static lockdep key
+        // set by `sysfs::Attribute::raw`.
+        let mut attrs = Vec::try_with_capacity(T::ATTRIBUTES.len())?;
+        for def in T::ATTRIBUTES {
+            attrs.try_push(KAttribute {
+                attr: def.raw(),
+                def,
+            })?;
+        }
+
+        // The attributes don't move from now on, since `attrs` isn't modified anymore.
+        let mut ptrs = Vec::try_with_capacity(attrs.len() + 1)?;
+        for attr in attrs.iter_mut() {
+            ptrs.try_push(ptr::addr_of_mut!(attr.attr))?;
+        }
+        ptrs.try_push(ptr::null_mut())?;
+
+        let obj = Box::into_raw(Box::try_new(Self {
+            kobj: Opaque::uninit(),
+            _attrs: attrs,
+            ptrs,
+            data,
+        })?);
+
+        // SAFETY: `obj` is valid, and the ktype is static.
+        unsafe { bindings::kobject_init((*obj).kobj.get(), KTypeVtable::<T>::build()) };
+
+        // INVARIANT: The object was initialised above with the ktype of `T`, and the reference
+        // returned by `kobject_init` is owned by `obj`.
+        // SAFETY: `obj` is non-null since it comes from a box.
+        let obj = unsafe { ARef::from_raw(NonNull::new_unchecked(obj)) };
+
+        // SAFETY: The object was initialised above, `parent` is valid, and the format string
+        // consumes the single string argument. If it fails, dropping `obj` releases it.
+        to_result(|| unsafe {
+            bindings::kobject_add(
+                obj.kobj.get(),
+                parent,
+                c_str!("%s").as_char_ptr(),
+                name.as_char_ptr(),
+            )
+        })?;
+
+        let group = bindings::attribute_group {
+            attrs: obj.ptrs.as_ptr() as _,
+            ..Default::default()
+        };
+
+        // SAFETY: The object was added above, and the attributes remain valid until it is
+        // released, after it's removed from sysfs.
+        to_result(|| unsafe { bindings::sysfs_create_group(obj.kobj.get(), &group) })?;
+
+        // SAFETY: The object was added above.
+        unsafe { bindings::kobject_uevent(obj.kobj.get(), Action::Add.as_raw()) };
+        Ok(obj)
+    }
+
+    /// Returns the name of the object, which is the name of its directory.
+    pub fn name(&self) -> &CStr {
+        // SAFETY: The object was added when it was created, so it has a name that lives as long
+        // as it.
+        unsafe { CStr::from_char_ptr((*self.kobj.get()).name) }
+    }
+
+    /// Notifies user space (e.g., udev) of an action on the object, with extra environment
+    /// variables.
+    pub fn emit_uevent(&self, action: Action, env: &UeventEnv) -> Result {
+        // SAFETY: The object was added when it was created.
+        unsafe { env.emit(self.kobj.get(), action) }
+    }
+
+    /// Returns a raw pointer to the underlying `struct kobject`.
+    pub fn as_ptr(&self) -> *mut bindings::kobject {
+        self.kobj.get()
+    }
+}
+
+impl<T: KType> Deref for KObject<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.data
+    }
+}
+
+// SAFETY: The type invariants guarantee that `KObject` is always ref-counted.
+unsafe impl<T: KType> AlwaysRefCounted for KObject<T> {
+    fn inc_ref(&self) {
+        // SAFETY: The existence of a shared reference means that the refcount is nonzero.
+        unsafe { bindings::kobject_get(self.kobj.get()) };
+    }
+
+    unsafe fn dec_ref(obj: NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee that the refcount is nonzero.
+        unsafe { bindings::kobject_put((*obj.as_ptr()).kobj.get()) };
+    }
+}
+
+struct KTypeVtable<T: KType>(PhantomData<T>);
+
+impl<T: KType> KTypeVtable<T> {
+    /// Returns the object that contains `kobj`.
+    ///
+    /// # Safety
+    ///
+    /// `kobj` must be the `kobj` field of a live [`KObject<T>`].
+    unsafe fn object<'a>(kobj: *mut bindings::kobject) -> &'a KObject<T> {
+        // SAFETY: The safety requirements guarantee that the object is alive.
+        unsafe { &*crate::container_of!(kobj, KObject<T>, kobj) }
+    }
+
+    unsafe extern "C" fn release_callback(kobj: *mut bindings::kobject) {
+        let obj = crate::container_of!(kobj, KObject<T>, kobj) as *mut KObject<T>;
+        // SAFETY: By the type invariants of `KObject`, it was allocated with a box in `create`,
+        // and this is called once the last reference is dropped.
+        drop(unsafe { Box::from_raw(obj) });
+    }
+
+    unsafe extern "C" fn show_callback(
+        kobj: *mut bindings::kobject,
+        attr: *mut bindings::attribute,
+        buf: *mut c_types::c_char,
+    ) -> c_types::c_ssize_t {
+        // SAFETY: Sysfs calls `show` with the kobject whose directory contains `attr`, which is
+        // the `attr` field of one of its `KAttribute`s, and with a page as the buffer.
+        unsafe {
+            let attr = &*(attr as *const KAttribute<T>);
+            attr.def.show_raw(&Self::object(kobj).data, buf)
+        }
+    }
+
+    unsafe extern "C" fn store_callback(
+        kobj: *mut bindings::kobject,
+        attr: *mut bindings::attribute,
+        buf: *const c_types::c_char,
+        count: c_types::c_size_t,
+    ) -> c_types::c_ssize_t {
+        // SAFETY: Sysfs calls `store` with the kobject whose directory contains `attr`, which is
+        // the `attr` field of one of its `KAttribute`s, and with `count` bytes in `buf`.
+        unsafe {
+            let attr = &*(attr as *const KAttribute<T>);
+            attr.def.store_raw(&Self::object(kobj).data, buf, count)
+        }
+    }
+
+    const SYSFS_OPS: bindings::sysfs_ops = bindings::sysfs_ops {
+        show: Some(Self::show_callback),
+        store: Some(Self::store_callback),
+    };
+
+    const VTABLE: bindings::kobj_type = bindings::kobj_type {
+        release: Some(Self::release_callback),
+        sysfs_ops: &Self::SYSFS_OPS,
+        default_attrs: ptr::null_mut(),
+        default_groups: ptr::null_mut(),
+        child_ns_type: None,
+        namespace: None,
+        get_ownership: None,
+    };
+
+    /// Builds an instance of `struct kobj_type`.
+    const fn build() -> &'static bindings::kobj_type {
+        &Self::VTABLE
+    }
+}
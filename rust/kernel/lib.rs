@@ -41,32 +41,77 @@ mod allocator;
 #[doc(hidden)]
 pub mod bindings;
 
+pub mod acpi;
 #[cfg(CONFIG_ARM_AMBA)]
 pub mod amba;
+pub mod bitmap;
+#[cfg(CONFIG_BLOCK)]
+pub mod block;
+pub mod bug;
 pub mod c_types;
 pub mod chrdev;
+pub mod class;
 #[cfg(CONFIG_COMMON_CLK)]
 pub mod clk;
+pub mod console;
+pub mod cpumask;
 pub mod cred;
+#[cfg(CONFIG_CRYPTO)]
+pub mod crypto;
+pub mod debugfs;
+pub mod delay;
 pub mod device;
+pub mod devres;
+#[cfg(CONFIG_DMA_SHARED_BUFFER)]
+pub mod dma_buf;
 pub mod driver;
 pub mod error;
 pub mod file;
+pub mod firmware;
+pub mod folio;
+pub mod fs;
+#[cfg(CONFIG_GENERIC_ALLOCATOR)]
+pub mod genalloc;
+pub mod gfp;
 pub mod gpio;
+pub mod hashtable;
 pub mod hwrng;
+pub mod idr;
 pub mod irq;
+pub mod irq_work;
+#[cfg(CONFIG_PRINTK)]
+pub mod kmsg_dump;
+pub mod kobject;
+pub mod mempool;
 pub mod miscdev;
 pub mod mm;
 #[cfg(CONFIG_NET)]
 pub mod net;
+pub mod notifier;
+pub mod numa;
 pub mod pages;
+pub mod percpu_counter;
 pub mod power;
+pub mod preempt;
+pub mod property;
 pub mod revocable;
+pub mod scatterlist;
+pub mod sched;
 pub mod security;
+pub mod shmem;
+pub mod slab;
+pub mod smp;
+#[cfg(CONFIG_SPI)]
+pub mod spi;
 pub mod str;
 pub mod task;
+pub mod tasklet;
+pub mod time;
+#[cfg(CONFIG_EVENT_TRACING)]
+pub mod trace;
 
 pub mod linked_list;
+pub mod llist;
 mod raw_list;
 pub mod rbtree;
 
@@ -77,6 +122,7 @@ mod build_assert;
 pub mod prelude;
 pub mod print;
 pub mod random;
+pub mod ratelimit;
 mod static_assert;
 #[doc(hidden)]
 pub mod std_vendor;
@@ -85,14 +131,21 @@ pub mod sync;
 #[cfg(any(CONFIG_SYSCTL, doc))]
 #[doc(cfg(CONFIG_SYSCTL))]
 pub mod sysctl;
+pub mod sysfs;
 
 pub mod io_buffer;
 pub mod io_mem;
+pub mod io_port;
+pub mod ioctl;
 pub mod iov_iter;
 pub mod of;
 pub mod platform;
 mod types;
+#[cfg(CONFIG_USB)]
+pub mod usb;
 pub mod user_ptr;
+pub mod vmalloc;
+pub mod workqueue;
 
 #[doc(hidden)]
 pub use build_error::build_error;
@@ -160,6 +213,59 @@ impl ThisModule {
             phantom: PhantomData,
         }
     }
+
+    /// Tries to take a reference to the module, which prevents it from being unloaded until the
+    /// returned [`ModuleRef`] is dropped.
+    ///
+    /// Returns `None` if the module is being unloaded. Objects that outlive the call that created
+    /// them and that call back into the module (e.g., callbacks registered with other subsystems
+    /// that do not take an owner) should hold a reference.
+    ///
+    /// Equivalent to the kernel's `try_module_get`.
+    pub fn try_get(&'static self) -> Option<ModuleRef> {
+        // SAFETY: `try_module_get` accepts null pointers (i.e., built-in code), and otherwise the
+        // pointer is valid by the safety requirements of `from_ptr`.
+        if unsafe { bindings::try_module_get(self.0) } {
+            // INVARIANT: A reference was just taken.
+            Some(ModuleRef(self.0))
+        } else {
+            None
+        }
+    }
+}
+
+/// A reference to a module, which prevents it from being unloaded.
+///
+/// It is created by [`ThisModule::try_get`] and the reference is released when it is dropped.
+///
+/// # Invariants
+///
+/// The object owns a reference to the module (or the pointer is null for built-in code).
+pub struct ModuleRef(*mut bindings::module);
+
+// SAFETY: Module references can be taken and released from any thread.
+unsafe impl Send for ModuleRef {}
+
+// SAFETY: `ModuleRef` has no methods that take `&self`, other than `clone`, which can be called
+// concurrently.
+unsafe impl Sync for ModuleRef {}
+
+impl Clone for ModuleRef {
+    fn clone(&self) -> Self {
+        // SAFETY: The type invariants guarantee that a reference is already held, so the module
+        // cannot be unloaded and `__module_get` cannot fail.
+        unsafe { bindings::__module_get(self.0) };
+
+        // INVARIANT: A new reference was just taken.
+        Self(self.0)
+    }
+}
+
+impl Drop for ModuleRef {
+    fn drop(&mut self) {
+        // SAFETY: The type invariants guarantee that a reference is held.
+        unsafe { bindings::module_put(self.0) };
+    }
 }
 
 /// Scoped lock on the kernel parameters of [`ThisModule`].
@@ -0,0 +1,246 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Lock-less singly-linked lists.
+//!
+//! Entries can be added to an [`LList`] from any context, including interrupt handlers and NMIs,
+//! without locks; they are removed all at once with [`LList::take_all`], usually by a thread or
+//! work item that processes them.
+//!
+//! C header: [`include/linux/llist.h`](../../../../include/linux/llist.h)
+
+use crate::{bindings, c_types, linked_list::Wrapper};
+use core::{
+    cell::UnsafeCell,
+    marker::PhantomData,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Describes the entries of a lock-less list.
+pub trait LListAdapter {
+    /// The type of the entries in the list.
+    type EntryType;
+
+    /// The wrapper (e.g., `Box` or `Ref`) that owns entries while they are in the list.
+    type Wrapped: Wrapper<Self::EntryType>;
+
+    /// Returns the links used to add an entry to the list.
+    fn links(data: &Self::EntryType) -> &LListLinks;
+}
+
+/// The links used to add an object to an [`LList`].
+///
+/// Instances of this type are usually embedded in structures and returned in calls to
+/// [`LListAdapter::links`]. Wraps the kernel's `struct llist_node`.
+pub struct LListLinks {
+    inserted: AtomicBool,
+    node: UnsafeCell<bindings::llist_node>,
+    entry: UnsafeCell<*const c_types::c_void>,
+}
+
+// SAFETY: `node` and `entry` are only accessed by the list the links are in, and `inserted`
+// ensures that they are in at most one list.
+unsafe impl Send for LListLinks {}
+
+// SAFETY: See above.
+unsafe impl Sync for LListLinks {}
+
+impl LListLinks {
+    /// Constructs new links that aren't in any list yet.
+    pub const fn new() -> Self {
+        Self {
+            inserted: AtomicBool::new(false),
+            node: UnsafeCell::new(bindings::llist_node {
+                next: ptr::null_mut(),
+            }),
+            entry: UnsafeCell::new(ptr::null()),
+        }
+    }
+}
+
+impl Default for LListLinks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A lock-less singly-linked list.
+///
+/// Wraps the kernel's `struct llist_head`. Entries are owned by the list while they are in it.
+///
+/// # Invariants
+///
+/// `head` only contains entries that were converted with [`Wrapper::into_pointer`] and whose
+/// links have `inserted` set and `entry` pointing to the entry.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::llist::{LList, LListAdapter, LListLinks};
+///
+/// struct Event {
+///     code: u32,
+///     links: LListLinks,
+/// }
+///
+/// struct EventAdapter;
+///
+/// impl LListAdapter for EventAdapter {
+///     type EntryType = Event;
+///     type Wrapped = Box<Event>;
+///
+///     fn links(data: &Event) -> &LListLinks {
+///         &data.links
+///     }
+/// }
+///
+/// fn example(events: &LList<EventAdapter>) -> Result {
+///     // From an interrupt handler, with a preallocated event.
+///     let was_empty = events.push(Box::try_new(Event { code: 1, links: LListLinks::new() })?);
+///     if was_empty {
+///         // Schedule the processing thread.
+///     }
+///
+///     // From the processing thread.
+///     for event in events.take_all() {
+///         pr_info!("event {}\n", event.code);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct LList<A: LListAdapter> {
+    head: UnsafeCell<bindings::llist_head>,
+    _p: PhantomData<A::Wrapped>,
+}
+
+// SAFETY: The list owns its entries through `A::Wrapped`, so it can be sent to another thread if
+// they can.
+unsafe impl<A: LListAdapter> Send for LList<A> where A::Wrapped: Send {}
+
+// SAFETY: The list only allows adding and taking owned entries through shared references, which
+// `llist_add` and `llist_del_all` allow concurrently, so it can be shared if entries can be sent.
+unsafe impl<A: LListAdapter> Sync for LList<A> where A::Wrapped: Send {}
+
+impl<A: LListAdapter> LList<A> {
+    /// Creates a new empty list.
+    pub const fn new() -> Self {
+        // INVARIANT: The list is empty.
+        Self {
+            head: UnsafeCell::new(bindings::llist_head {
+                first: ptr::null_mut(),
+            }),
+            _p: PhantomData,
+        }
+    }
+
+    /// Adds the given entry to the list.
+    ///
+    /// Like `llist_add`, returns whether the list was empty before the entry was added, which is
+    /// usually when the consumer needs to be woken up. It can be called from any context.
+    ///
+    /// The entry is dropped if it's already in this (or another) list; this can happen for
+    /// reference-counted entries, so dropping means decrementing the reference count. `false` is
+    /// returned in that case since the list isn't modified.
+    pub fn push(&self, data: A::Wrapped) -> bool {
+        let links = A::links(data.as_ref());
+        if links
+            .inserted
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return false;
+        }
+
+        let node = links.node.get();
+        let entry = links.entry.get();
+
+        // SAFETY: We own the links now that `inserted` is set.
+        unsafe { *entry = data.into_pointer().as_ptr() as _ };
+
+        // INVARIANT: The entry was converted with `into_pointer`, and its links set above.
+        // SAFETY: `head` and `node` are valid, and `node` remains valid while the entry is in the
+        // list because we took ownership of it.
+        unsafe { bindings::llist_add(node, self.head.get()) }
+    }
+
+    /// Returns whether the list is empty.
+    ///
+    /// The result may be stale by the time the caller acts on it.
+    pub fn is_empty(&self) -> bool {
+        // SAFETY: `head` is valid.
+        unsafe { bindings::llist_empty(self.head.get()) }
+    }
+
+    /// Removes all the entries from the list and returns them, in the order they were added.
+    pub fn take_all(&self) -> LListBatch<A> {
+        // SAFETY: `head` is valid.
+        let first = unsafe { bindings::llist_del_all(self.head.get()) };
+
+        // SAFETY: The entries were removed from the list above, so we own them.
+        let first = unsafe { bindings::llist_reverse_order(first) };
+
+        // INVARIANT: The entries were in the list, so they satisfy the invariants.
+        LListBatch {
+            next: first,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<A: LListAdapter> Default for LList<A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: LListAdapter> Drop for LList<A> {
+    fn drop(&mut self) {
+        drop(self.take_all());
+    }
+}
+
+/// Entries removed from an [`LList`] by [`LList::take_all`].
+///
+/// Iterating over it yields the entries in the order they were added; the entries that aren't
+/// yielded are dropped with it.
+///
+/// # Invariants
+///
+/// All the nodes starting at `next` have links with `inserted` set and `entry` pointing to an
+/// entry converted with [`Wrapper::into_pointer`].
+pub struct LListBatch<A: LListAdapter> {
+    next: *mut bindings::llist_node,
+    _p: PhantomData<A::Wrapped>,
+}
+
+impl<A: LListAdapter> Iterator for LListBatch<A> {
+    type Item = A::Wrapped;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next.is_null() {
+            return None;
+        }
+
+        let links = crate::container_of!(self.next, LListLinks, node);
+
+        // SAFETY: By the type invariants, `links` is valid and we own it.
+        let entry = unsafe {
+            self.next = (*self.next).next;
+            *(*links).entry.get() as *mut A::EntryType
+        };
+
+        // SAFETY: As above.
+        unsafe { (*links).inserted.store(false, Ordering::Release) };
+
+        // SAFETY: By the type invariants, `entry` was converted with `into_pointer`, and it is no
+        // longer in any list.
+        Some(unsafe { A::Wrapped::from_pointer(NonNull::new_unchecked(entry)) })
+    }
+}
+
+impl<A: LListAdapter> Drop for LListBatch<A> {
+    fn drop(&mut self) {
+        for _ in self {}
+    }
+}
@@ -0,0 +1,276 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Memory pools.
+//!
+//! A memory pool keeps a minimum number of objects in reserve, so that allocations from it make
+//! forward progress even when memory is exhausted: if the underlying allocator fails, the caller
+//! waits for an object to be returned to the pool. It is meant for code that memory reclaim may
+//! wait for, e.g., the I/O submission and completion paths of block drivers.
+//!
+//! Allocations that are allowed to sleep (e.g., with [`crate::gfp::Flags::NOIO`]) never fail, but
+//! objects must be returned to the pool in bounded time, otherwise allocations may deadlock.
+//!
+//! C header: [`include/linux/mempool.h`](../../../../include/linux/mempool.h)
+
+use crate::{bindings, c_types, error::code::*, pages::Pages, slab::KmemCache, Result};
+use core::{
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+/// A memory pool of objects of type `T`, allocated from a slab cache.
+///
+/// # Invariants
+///
+/// `ptr` was returned by `mempool_create` with the slab functions and `cache` as the backend.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::gfp;
+/// use kernel::mempool::MemPool;
+/// use kernel::slab::{Flags, KmemCache};
+///
+/// struct Bio {
+///     sector: u64,
+/// }
+///
+/// fn example() -> Result {
+///     let cache = KmemCache::<Bio>::try_new(fmt!("example_bio"), 0, Flags::HWCACHE_ALIGN)?;
+///     let pool = MemPool::try_new(16, cache)?;
+///
+///     // Doesn't fail, since it may sleep until an object is returned to the pool.
+///     let bio = pool.try_alloc(Bio { sector: 8 }, gfp::Flags::NOIO)?;
+///     assert_eq!(bio.sector, 8);
+///     Ok(())
+/// }
+/// ```
+pub struct MemPool<T> {
+    ptr: NonNull<bindings::mempool_t>,
+    cache: KmemCache<T>,
+}
+
+// SAFETY: The pool functions are safe to call from any thread, and objects may be sent to other
+// threads, so `T` must be `Send`.
+unsafe impl<T: Send> Send for MemPool<T> {}
+
+// SAFETY: The pool functions are safe to call concurrently.
+unsafe impl<T: Send> Sync for MemPool<T> {}
+
+impl<T> MemPool<T> {
+    /// Creates a new pool that keeps at least `min_nr` objects of `cache` in reserve.
+    ///
+    /// The reserved objects are allocated right away.
+    pub fn try_new(min_nr: u32, cache: KmemCache<T>) -> Result<Self> {
+        // SAFETY: `cache` is a valid cache, and it outlives the pool since the pool owns it and
+        // destroys itself first.
+        let ptr = unsafe {
+            bindings::mempool_create(
+                min_nr.try_into()?,
+                Some(bindings::mempool_alloc_slab),
+                Some(bindings::mempool_free_slab),
+                cache.as_ptr() as _,
+            )
+        };
+
+        // INVARIANT: `ptr` was returned by `mempool_create` above, with `cache` as the backend.
+        Ok(Self {
+            ptr: NonNull::new(ptr).ok_or(ENOMEM)?,
+            cache,
+        })
+    }
+
+    /// Returns the slab cache the pool allocates from.
+    pub fn cache(&self) -> &KmemCache<T> {
+        &self.cache
+    }
+
+    /// Allocates an object with the given flags (see [`crate::gfp::Flags`]) and places `value`
+    /// into it.
+    ///
+    /// It only fails if the flags don't allow sleeping and both the allocator and the pool are
+    /// out of objects.
+    pub fn try_alloc(&self, value: T, flags: u32) -> Result<PoolBox<'_, T>> {
+        // SAFETY: By the type invariants, `ptr` is a valid pool.
+        let ptr = unsafe { bindings::mempool_alloc(self.ptr.as_ptr(), flags) } as *mut T;
+        let ptr = NonNull::new(ptr).ok_or(ENOMEM)?;
+
+        // SAFETY: `ptr` was allocated from the cache, so it is valid for writes of a `T`. If the
+        // cache has a constructor, the constructed value has no drop glue, so it can be
+        // overwritten.
+        unsafe { ptr.as_ptr().write(value) };
+
+        // INVARIANT: `ptr` was allocated from the pool and initialised above.
+        Ok(PoolBox { ptr, pool: self })
+    }
+}
+
+impl<T> Drop for MemPool<T> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `ptr` is a valid pool. The objects were all returned,
+        // since they borrow the pool. The cache is destroyed afterwards, when it is dropped.
+        unsafe { bindings::mempool_destroy(self.ptr.as_ptr()) };
+    }
+}
+
+/// An object allocated from a [`MemPool`].
+///
+/// The object is dropped and returned to the pool when the box is dropped.
+///
+/// # Invariants
+///
+/// `ptr` was allocated from `pool` and points to an initialised `T` owned by the box.
+pub struct PoolBox<'a, T> {
+    ptr: NonNull<T>,
+    pool: &'a MemPool<T>,
+}
+
+// SAFETY: The box owns the object, and the pool can free it from any thread.
+unsafe impl<T: Send> Send for PoolBox<'_, T> {}
+
+// SAFETY: The object is only accessed through shared references from shared references to the
+// box.
+unsafe impl<T: Sync> Sync for PoolBox<'_, T> {}
+
+impl<T> Deref for PoolBox<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: By the type invariants, `ptr` points to an initialised `T`.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for PoolBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: By the type invariants, `ptr` points to an initialised `T` owned by the box.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> Drop for PoolBox<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `ptr` points to an initialised `T` owned by the box, and
+        // it was allocated from `pool`.
+        unsafe {
+            core::ptr::drop_in_place(self.ptr.as_ptr());
+            bindings::mempool_free(self.ptr.as_ptr() as _, self.pool.ptr.as_ptr());
+        }
+    }
+}
+
+/// A memory pool of sets of pages of order `ORDER`.
+///
+/// # Invariants
+///
+/// `ptr` was returned by `mempool_create` with the page functions and `ORDER` as the order.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::gfp;
+/// use kernel::mempool::PagePool;
+///
+/// fn example() -> Result {
+///     let pool = PagePool::<0>::try_new(4)?;
+///     let page = pool.try_alloc(gfp::Flags::NOIO)?;
+///     pr_debug!("bounce page at {:#x}\n", page.phys_addr());
+///     Ok(())
+/// }
+/// ```
+pub struct PagePool<const ORDER: u32> {
+    ptr: NonNull<bindings::mempool_t>,
+}
+
+// SAFETY: The pool functions are safe to call from any thread.
+unsafe impl<const ORDER: u32> Send for PagePool<ORDER> {}
+
+// SAFETY: The pool functions are safe to call concurrently.
+unsafe impl<const ORDER: u32> Sync for PagePool<ORDER> {}
+
+impl<const ORDER: u32> PagePool<ORDER> {
+    /// Creates a new pool that keeps at least `min_nr` sets of pages in reserve.
+    pub fn try_new(min_nr: u32) -> Result<Self> {
+        // SAFETY: The page functions take the order as their data.
+        let ptr = unsafe {
+            bindings::mempool_create(
+                min_nr.try_into()?,
+                Some(bindings::mempool_alloc_pages),
+                Some(bindings::mempool_free_pages),
+                ORDER as usize as *mut c_types::c_void,
+            )
+        };
+
+        // INVARIANT: `ptr` was returned by `mempool_create` above, for pages of order `ORDER`.
+        Ok(Self {
+            ptr: NonNull::new(ptr).ok_or(ENOMEM)?,
+        })
+    }
+
+    /// Allocates a set of pages with the given flags (see [`crate::gfp::Flags`]).
+    ///
+    /// The pages are not zeroed. It only fails if the flags don't allow sleeping and both the
+    /// page allocator and the pool are out of pages.
+    pub fn try_alloc(&self, flags: u32) -> Result<PoolPages<'_, ORDER>> {
+        // SAFETY: By the type invariants, `ptr` is a valid pool.
+        let pages = unsafe { bindings::mempool_alloc(self.ptr.as_ptr(), flags) };
+        if pages.is_null() {
+            return Err(ENOMEM);
+        }
+
+        // SAFETY: The pool allocates pages of order `ORDER`. They are returned to the pool
+        // instead of being freed, since `PoolPages` never drops them.
+        let pages = unsafe { Pages::from_raw(pages as *mut bindings::page) };
+
+        // INVARIANT: The pages were allocated from the pool above.
+        Ok(PoolPages {
+            pages: ManuallyDrop::new(pages),
+            pool: self,
+        })
+    }
+}
+
+impl<const ORDER: u32> Drop for PagePool<ORDER> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `ptr` is a valid pool. The pages were all returned,
+        // since they borrow the pool.
+        unsafe { bindings::mempool_destroy(self.ptr.as_ptr()) };
+    }
+}
+
+/// A set of pages allocated from a [`PagePool`].
+///
+/// The pages are returned to the pool when dropped.
+///
+/// # Invariants
+///
+/// `pages` were allocated from `pool`.
+pub struct PoolPages<'a, const ORDER: u32> {
+    pages: ManuallyDrop<Pages<ORDER>>,
+    pool: &'a PagePool<ORDER>,
+}
+
+impl<const ORDER: u32> Deref for PoolPages<'_, ORDER> {
+    type Target = Pages<ORDER>;
+
+    fn deref(&self) -> &Pages<ORDER> {
+        &self.pages
+    }
+}
+
+impl<const ORDER: u32> DerefMut for PoolPages<'_, ORDER> {
+    fn deref_mut(&mut self) -> &mut Pages<ORDER> {
+        &mut self.pages
+    }
+}
+
+impl<const ORDER: u32> Drop for PoolPages<'_, ORDER> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, the pages were allocated from `pool`. They are not
+        // freed by `Pages`, since they are wrapped in `ManuallyDrop`.
+        unsafe { bindings::mempool_free(self.pages.as_ptr() as _, self.pool.ptr.as_ptr()) };
+    }
+}
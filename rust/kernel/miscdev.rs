@@ -2,6 +2,39 @@
 
 //! Miscellaneous devices.
 //!
+//! Misc devices share the major number 10 and get their `/dev` nodes created automatically. A
+//! module may register any number of them by keeping several [`Registration`]s:
+//!
+//! ```
+//! # use kernel::{c_str, file, miscdev, prelude::*};
+//! struct Control;
+//!
+//! impl file::Operations for Control {
+//!     kernel::declare_file_operations!();
+//!
+//!     fn open(_: &(), _: &file::File) -> Result {
+//!         Ok(())
+//!     }
+//! }
+//!
+//! struct Devices {
+//!     _control: Pin<Box<miscdev::Registration<Control>>>,
+//!     _status: Pin<Box<miscdev::Registration<Control>>>,
+//! }
+//!
+//! fn register() -> Result<Devices> {
+//!     Ok(Devices {
+//!         _control: miscdev::Options::new()
+//!             .mode(0o600)
+//!             .nodename(c_str!("example/control"))
+//!             .register_new(fmt!("example_control"), ())?,
+//!         _status: miscdev::Options::new()
+//!             .mode(0o444)
+//!             .register_new(fmt!("example_status"), ())?,
+//!     })
+//! }
+//! ```
+//!
 //! C header: [`include/linux/miscdevice.h`](../../../../include/linux/miscdevice.h)
 //!
 //! Reference: <https://www.kernel.org/doc/html/latest/driver-api/misc_devices.html>
@@ -36,6 +69,7 @@ pub struct Options<'a> {
     minor: Option<i32>,
     mode: Option<u16>,
     parent: Option<&'a dyn device::RawDevice>,
+    nodename: Option<&'static CStr>,
 }
 
 impl<'a> Options<'a> {
@@ -45,6 +79,7 @@ impl<'a> Options<'a> {
             minor: None,
             mode: None,
             parent: None,
+            nodename: None,
         }
     }
 
@@ -69,6 +104,14 @@ impl<'a> Options<'a> {
         self
     }
 
+    /// Sets the path of the device node relative to `/dev`, if different from the name.
+    ///
+    /// It may include directories, e.g., `"net/tun"`.
+    pub const fn nodename(&mut self, n: &'static CStr) -> &mut Self {
+        self.nodename = Some(n);
+        self
+    }
+
     /// Registers a misc device using the configured options.
     pub fn register<T: file::Operations>(
         &self,
@@ -170,6 +213,7 @@ impl<T: file::Operations> Registration<T> {
         this.mdev.parent = opts
             .parent
             .map_or(core::ptr::null_mut(), |p| p.raw_device());
+        this.mdev.nodename = opts.nodename.map_or(core::ptr::null(), |n| n.as_char_ptr());
 
         // We write to `open_data` here because as soon as `misc_register` succeeds, the file can be
         // opened, so we need `open_data` configured ahead of time.
@@ -191,6 +235,18 @@ impl<T: file::Operations> Registration<T> {
 
         Ok(())
     }
+
+    /// Returns the minor number of the device, which was allocated dynamically unless a fixed
+    /// one was given with [`Options::minor`].
+    ///
+    /// Returns `None` if the device is not registered.
+    pub fn minor(&self) -> Option<i32> {
+        if self.registered {
+            Some(self.mdev.minor)
+        } else {
+            None
+        }
+    }
 }
 
 impl<T: file::Operations> Default for Registration<T> {
@@ -4,7 +4,7 @@
 //!
 //! C header: [`include/linux/moduleparam.h`](../../../include/linux/moduleparam.h)
 
-use crate::error::{code::*, from_kernel_result};
+use crate::error::{code::*, from_kernel_result, from_result};
 use crate::str::{CStr, Formatter};
 use core::fmt::Write;
 
@@ -74,14 +74,12 @@ pub trait ModuleParam: core::fmt::Display + core::marker::Sized {
         } else {
             Some(unsafe { CStr::from_char_ptr(val).as_bytes() })
         };
-        match Self::try_from_param_arg(arg) {
-            Some(new_value) => {
-                let old_value = unsafe { (*param).__bindgen_anon_1.arg as *mut Self };
-                let _ = unsafe { core::ptr::replace(old_value, new_value) };
-                0
-            }
-            None => EINVAL.to_kernel_errno(),
-        }
+        from_result(|| {
+            let new_value = Self::try_from_param_arg(arg).ok_or(EINVAL)?;
+            let old_value = unsafe { (*param).__bindgen_anon_1.arg as *mut Self };
+            let _ = unsafe { core::ptr::replace(old_value, new_value) };
+            Ok(0)
+        })
     }
 
     /// Write a string representation of the current parameter value to `buf`.
@@ -6,16 +6,94 @@
 //! [`include/linux/netdevice.h`](../../../../include/linux/netdevice.h),
 //! [`include/linux/skbuff.h`](../../../../include/linux/skbuff.h).
 
-use crate::{bindings, str::CStr, ARef, AlwaysRefCounted};
+use crate::{bindings, c_types, error::code::*, str::CStr, ARef, AlwaysRefCounted, Result};
 use core::{cell::UnsafeCell, ptr::NonNull};
 
+pub mod dev;
+pub mod ethtool;
 #[cfg(CONFIG_NETFILTER)]
 pub mod filter;
+pub mod genetlink;
+pub mod socket;
+pub mod tcp;
+pub mod udp;
 
 /// Wraps the kernel's `struct net_device`.
 #[repr(transparent)]
 pub struct Device(UnsafeCell<bindings::net_device>);
 
+// SAFETY: Network devices are reference-counted and their functions are safe to call from any
+// thread.
+unsafe impl Send for Device {}
+
+// SAFETY: The functions of network devices that take shared references are safe to call
+// concurrently.
+unsafe impl Sync for Device {}
+
+impl Device {
+    /// Creates a reference to a [`Device`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the lifetime of the
+    /// returned [`Device`] instance.
+    pub unsafe fn from_ptr<'a>(ptr: *const bindings::net_device) -> &'a Device {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `Device` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Returns the name of the interface, e.g., `eth0`.
+    pub fn name(&self) -> &CStr {
+        // SAFETY: The existence of a shared reference means `self.0` is valid, and `name` is a
+        // null-terminated array.
+        unsafe { CStr::from_char_ptr((*self.0.get()).name.as_ptr()) }
+    }
+
+    /// Reports that the link is up, e.g., a cable was plugged in.
+    pub fn carrier_on(&self) {
+        // SAFETY: The existence of a shared reference means `self.0` is valid.
+        unsafe { bindings::netif_carrier_on(self.0.get()) };
+    }
+
+    /// Reports that the link is down.
+    pub fn carrier_off(&self) {
+        // SAFETY: The existence of a shared reference means `self.0` is valid.
+        unsafe { bindings::netif_carrier_off(self.0.get()) };
+    }
+
+    /// Returns whether the link is up.
+    pub fn carrier_ok(&self) -> bool {
+        // SAFETY: The existence of a shared reference means `self.0` is valid.
+        unsafe { bindings::netif_carrier_ok(self.0.get()) }
+    }
+
+    /// Allows the stack to transmit packets, usually when the interface is brought up.
+    pub fn start_queue(&self) {
+        // SAFETY: The existence of a shared reference means `self.0` is valid.
+        unsafe { bindings::netif_start_queue(self.0.get()) };
+    }
+
+    /// Stops the stack from transmitting packets, e.g., when the transmit ring is full.
+    pub fn stop_queue(&self) {
+        // SAFETY: The existence of a shared reference means `self.0` is valid.
+        unsafe { bindings::netif_stop_queue(self.0.get()) };
+    }
+
+    /// Allows the stack to transmit packets again after [`Device::stop_queue`], and schedules
+    /// the transmission of pending ones.
+    pub fn wake_queue(&self) {
+        // SAFETY: The existence of a shared reference means `self.0` is valid.
+        unsafe { bindings::netif_wake_queue(self.0.get()) };
+    }
+
+    /// Returns whether transmission was stopped with [`Device::stop_queue`].
+    pub fn queue_stopped(&self) -> bool {
+        // SAFETY: The existence of a shared reference means `self.0` is valid.
+        unsafe { bindings::netif_queue_stopped(self.0.get()) }
+    }
+}
+
 // SAFETY: Instances of `Device` are created on the C side. They are always refcounted.
 unsafe impl AlwaysRefCounted for Device {
     fn inc_ref(&self) {
@@ -115,3 +193,179 @@ unsafe impl AlwaysRefCounted for SkBuff {
         };
     }
 }
+
+/// An IPv4 address.
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct Ipv4Addr(bindings::in_addr);
+
+impl Ipv4Addr {
+    /// The unspecified address, `0.0.0.0`.
+    pub const ANY: Self = Self::new(0, 0, 0, 0);
+
+    /// The loopback address, `127.0.0.1`.
+    pub const LOOPBACK: Self = Self::new(127, 0, 0, 1);
+
+    /// Creates the address `a.b.c.d`.
+    pub const fn new(a: u8, b: u8, c: u8, d: u8) -> Self {
+        Self(bindings::in_addr {
+            s_addr: u32::from_ne_bytes([a, b, c, d]),
+        })
+    }
+
+    /// Returns the four octets of the address.
+    pub const fn octets(&self) -> [u8; 4] {
+        self.0.s_addr.to_ne_bytes()
+    }
+}
+
+/// An IPv6 address.
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct Ipv6Addr(bindings::in6_addr);
+
+impl Ipv6Addr {
+    /// The unspecified address, `::`.
+    pub const ANY: Self = Self::new(0, 0, 0, 0, 0, 0, 0, 0);
+
+    /// The loopback address, `::1`.
+    pub const LOOPBACK: Self = Self::new(0, 0, 0, 0, 0, 0, 0, 1);
+
+    /// Creates the address `a:b:c:d:e:f:g:h`.
+    #[allow(clippy::too_many_arguments)]
+    pub const fn new(a: u16, b: u16, c: u16, d: u16, e: u16, f: u16, g: u16, h: u16) -> Self {
+        let segments = [a, b, c, d, e, f, g, h];
+        let mut octets = [0u8; 16];
+        let mut i = 0;
+        while i < segments.len() {
+            let [hi, lo] = segments[i].to_be_bytes();
+            octets[2 * i] = hi;
+            octets[2 * i + 1] = lo;
+            i += 1;
+        }
+        Self(bindings::in6_addr {
+            in6_u: bindings::in6_addr__bindgen_ty_1 { u6_addr8: octets },
+        })
+    }
+
+    /// Returns the sixteen octets of the address.
+    pub fn octets(&self) -> [u8; 16] {
+        // SAFETY: All the variants of the union are plain arrays of integers covering the same
+        // bytes, so any of them may be read.
+        unsafe { self.0.in6_u.u6_addr8 }
+    }
+}
+
+/// An IPv4 socket address, i.e., an address and a port.
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct SocketAddrV4(bindings::sockaddr_in);
+
+impl SocketAddrV4 {
+    /// Creates a socket address from an address and a port.
+    pub const fn new(addr: Ipv4Addr, port: u16) -> Self {
+        Self(bindings::sockaddr_in {
+            sin_family: bindings::AF_INET as _,
+            sin_port: port.to_be(),
+            sin_addr: addr.0,
+            __pad: [0; 8],
+        })
+    }
+
+    /// Returns the address.
+    pub const fn ip(&self) -> Ipv4Addr {
+        Ipv4Addr(self.0.sin_addr)
+    }
+
+    /// Returns the port.
+    pub const fn port(&self) -> u16 {
+        u16::from_be(self.0.sin_port)
+    }
+}
+
+/// An IPv6 socket address, i.e., an address, a port, and the flow and scope ids.
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct SocketAddrV6(bindings::sockaddr_in6);
+
+impl SocketAddrV6 {
+    /// Creates a socket address from an address, a port, a flow label and a scope id.
+    pub const fn new(addr: Ipv6Addr, port: u16, flowinfo: u32, scope_id: u32) -> Self {
+        Self(bindings::sockaddr_in6 {
+            sin6_family: bindings::AF_INET6 as _,
+            sin6_port: port.to_be(),
+            sin6_flowinfo: flowinfo.to_be(),
+            sin6_addr: addr.0,
+            sin6_scope_id: scope_id,
+        })
+    }
+
+    /// Returns the address.
+    pub const fn ip(&self) -> Ipv6Addr {
+        Ipv6Addr(self.0.sin6_addr)
+    }
+
+    /// Returns the port.
+    pub const fn port(&self) -> u16 {
+        u16::from_be(self.0.sin6_port)
+    }
+}
+
+/// An IPv4 or IPv6 socket address.
+#[derive(Clone, Copy)]
+pub enum SocketAddr {
+    /// An IPv4 socket address.
+    V4(SocketAddrV4),
+
+    /// An IPv6 socket address.
+    V6(SocketAddrV6),
+}
+
+impl SocketAddr {
+    /// Returns the port.
+    pub const fn port(&self) -> u16 {
+        match self {
+            Self::V4(a) => a.port(),
+            Self::V6(a) => a.port(),
+        }
+    }
+
+    fn family(&self) -> c_types::c_int {
+        match self {
+            Self::V4(_) => bindings::AF_INET as _,
+            Self::V6(_) => bindings::AF_INET6 as _,
+        }
+    }
+
+    /// Returns a pointer to the C representation of the address and its length.
+    fn as_raw(&self) -> (*mut bindings::sockaddr, c_types::c_int) {
+        match self {
+            Self::V4(a) => (
+                &a.0 as *const _ as *mut _,
+                core::mem::size_of::<bindings::sockaddr_in>() as _,
+            ),
+            Self::V6(a) => (
+                &a.0 as *const _ as *mut _,
+                core::mem::size_of::<bindings::sockaddr_in6>() as _,
+            ),
+        }
+    }
+
+    /// Creates a socket address from its C representation.
+    ///
+    /// # Safety
+    ///
+    /// `addr` must be valid for reads of `struct sockaddr_storage`.
+    unsafe fn from_raw(addr: *const bindings::__kernel_sockaddr_storage) -> Result<Self> {
+        // SAFETY: All socket addresses start with their family.
+        let family = unsafe { *addr.cast::<bindings::sa_family_t>() };
+        // SAFETY: The address is of the type given by its family, which fits in the storage.
+        unsafe {
+            match u32::from(family) {
+                bindings::AF_INET => Ok(Self::V4(SocketAddrV4(*addr.cast()))),
+                bindings::AF_INET6 => Ok(Self::V6(SocketAddrV6(*addr.cast()))),
+                _ => Err(EAFNOSUPPORT),
+            }
+        }
+    }
+}
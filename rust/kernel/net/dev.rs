@@ -0,0 +1,437 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Network device drivers.
+//!
+//! A driver implements [`Operations`] to bring its interface up and down and transmit packets,
+//! and registers an Ethernet-like interface with [`Registration`]. Its ethtool operations are
+//! described in [`super::ethtool`].
+//!
+//! C headers: [`include/linux/netdevice.h`](../../../../include/linux/netdevice.h) and
+//! [`include/linux/etherdevice.h`](../../../../include/linux/etherdevice.h)
+//!
+//! # Examples
+//!
+//! An interface that drops all packets, like `dummy`:
+//!
+//! ```
+//! # use kernel::prelude::*;
+//! use core::sync::atomic::{AtomicU64, Ordering};
+//! use kernel::net::{dev, Device, SkBuff};
+//! use kernel::{c_str, ARef};
+//!
+//! struct Dummy;
+//!
+//! struct Counters {
+//!     packets: AtomicU64,
+//!     bytes: AtomicU64,
+//! }
+//!
+//! impl dev::Operations for Dummy {
+//!     kernel::declare_net_device_operations!(get_stats64);
+//!
+//!     type Data = Box<Counters>;
+//!
+//!     fn open(dev: &Device, _data: &Counters) -> Result {
+//!         dev.carrier_on();
+//!         dev.start_queue();
+//!         Ok(())
+//!     }
+//!
+//!     fn stop(dev: &Device, _data: &Counters) -> Result {
+//!         dev.stop_queue();
+//!         dev.carrier_off();
+//!         Ok(())
+//!     }
+//!
+//!     fn start_xmit(skb: ARef<SkBuff>, _dev: &Device, data: &Counters) -> dev::NetdevTx {
+//!         data.packets.fetch_add(1, Ordering::Relaxed);
+//!         data.bytes.fetch_add(skb.len().into(), Ordering::Relaxed);
+//!         dev::NetdevTx::Ok
+//!     }
+//!
+//!     fn get_stats64(_dev: &Device, data: &Counters, stats: &mut dev::Stats64) {
+//!         stats.tx_packets = data.packets.load(Ordering::Relaxed);
+//!         stats.tx_bytes = data.bytes.load(Ordering::Relaxed);
+//!     }
+//! }
+//!
+//! fn register() -> Result<dev::Registration<Dummy>> {
+//!     let counters = Box::try_new(Counters {
+//!         packets: AtomicU64::new(0),
+//!         bytes: AtomicU64::new(0),
+//!     })?;
+//!     let mut reg = dev::Registration::try_new(c_str!("rdummy%d"), counters)?;
+//!     reg.set_random_addr();
+//!     reg.register()?;
+//!     Ok(reg)
+//! }
+//! ```
+
+use super::{
+    ethtool::{EthtoolOperations, EthtoolVtable},
+    Device, SkBuff,
+};
+use crate::{
+    bindings, c_types,
+    error::{code::*, from_kernel_result, to_result, Result},
+    str::CStr,
+    types::PointerWrapper,
+    ARef,
+};
+use alloc::boxed::Box;
+use core::{marker::PhantomData, mem, ptr::NonNull};
+
+/// The result of [`Operations::start_xmit`].
+pub enum NetdevTx {
+    /// The driver took the packet.
+    Ok,
+
+    /// The driver can't take the packet now, and returns it to the stack to be retried later.
+    ///
+    /// The driver should have stopped the queue (see [`Device::stop_queue`]) before returning
+    /// this.
+    Busy(ARef<SkBuff>),
+}
+
+/// The statistics of an interface, reported by [`Operations::get_stats64`].
+///
+/// Counters the driver doesn't fill are reported as zero.
+#[derive(Default)]
+pub struct Stats64 {
+    /// Packets received.
+    pub rx_packets: u64,
+
+    /// Packets transmitted.
+    pub tx_packets: u64,
+
+    /// Bytes received.
+    pub rx_bytes: u64,
+
+    /// Bytes transmitted.
+    pub tx_bytes: u64,
+
+    /// Bad packets received.
+    pub rx_errors: u64,
+
+    /// Packets that couldn't be transmitted.
+    pub tx_errors: u64,
+
+    /// Packets received but dropped, e.g., for lack of buffers.
+    pub rx_dropped: u64,
+
+    /// Packets dropped before being transmitted.
+    pub tx_dropped: u64,
+
+    /// Multicast packets received.
+    pub multicast: u64,
+
+    /// Collisions on the medium.
+    pub collisions: u64,
+}
+
+/// The operations of a network device.
+pub trait Operations: Sized {
+    /// The methods to use to populate [`struct net_device_ops`].
+    const TO_USE: ToUse;
+
+    /// The data of the interface, made available to all callbacks.
+    type Data: PointerWrapper + Send + Sync;
+
+    /// Brings the interface up, e.g., with `ip link set <name> up`.
+    ///
+    /// The driver should start the queue (see [`Device::start_queue`]) and report the state of
+    /// the link (see [`Device::carrier_on`]).
+    fn open(_dev: &Device, _data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Result {
+        Ok(())
+    }
+
+    /// Brings the interface down.
+    fn stop(_dev: &Device, _data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Result {
+        Ok(())
+    }
+
+    /// Transmits a packet.
+    ///
+    /// It runs in atomic context, usually with bottom halves disabled.
+    fn start_xmit(
+        skb: ARef<SkBuff>,
+        dev: &Device,
+        data: <Self::Data as PointerWrapper>::Borrowed<'_>,
+    ) -> NetdevTx;
+
+    /// Reports the statistics of the interface.
+    fn get_stats64(
+        _dev: &Device,
+        _data: <Self::Data as PointerWrapper>::Borrowed<'_>,
+        _stats: &mut Stats64,
+    ) {
+    }
+}
+
+/// Represents which optional fields of [`struct net_device_ops`] should be populated with
+/// pointers.
+pub struct ToUse {
+    /// The `ndo_get_stats64` field of [`struct net_device_ops`].
+    pub get_stats64: bool,
+}
+
+/// A constant version where all values are to set to `false`, that is, all supported fields will
+/// be set to null pointers.
+pub const USE_NONE: ToUse = ToUse { get_stats64: false };
+
+/// Defines the [`Operations::TO_USE`] field based on a list of fields to be populated.
+#[macro_export]
+macro_rules! declare_net_device_operations {
+    () => {
+        const TO_USE: $crate::net::dev::ToUse = $crate::net::dev::USE_NONE;
+    };
+    ($($i:ident),+) => {
+        #[allow(clippy::needless_update)]
+        const TO_USE: kernel::net::dev::ToUse =
+            $crate::net::dev::ToUse {
+                $($i: true),+ ,
+                ..$crate::net::dev::USE_NONE
+            };
+    };
+}
+
+struct OperationsVtable<T: Operations>(PhantomData<T>);
+
+/// Returns the data of a device allocated by [`Registration::try_new`].
+///
+/// # Safety
+///
+/// `dev` must be a valid device allocated by [`Registration::try_new`] for operations whose data
+/// is of type `D`.
+pub(crate) unsafe fn data<'a, D: PointerWrapper>(
+    dev: *mut bindings::net_device,
+) -> D::Borrowed<'a> {
+    // SAFETY: The safety requirements guarantee that the private area of `dev` holds a value
+    // returned by `D::into_pointer`, which is only released after the device is unregistered.
+    unsafe { D::borrow(*(bindings::netdev_priv(dev) as *const *const c_types::c_void)) }
+}
+
+impl<T: Operations> OperationsVtable<T> {
+    /// # Safety
+    ///
+    /// `dev` must be a valid device allocated by [`Registration::try_new`].
+    unsafe fn data<'a>(
+        dev: *mut bindings::net_device,
+    ) -> <T::Data as PointerWrapper>::Borrowed<'a> {
+        // SAFETY: The safety requirements are the same.
+        unsafe { data::<T::Data>(dev) }
+    }
+
+    unsafe extern "C" fn open_callback(dev: *mut bindings::net_device) -> c_types::c_int {
+        from_kernel_result! {
+            // SAFETY: The networking core only calls this on devices registered with this table.
+            T::open(unsafe { Device::from_ptr(dev) }, unsafe { Self::data(dev) })?;
+            Ok(0)
+        }
+    }
+
+    unsafe extern "C" fn stop_callback(dev: *mut bindings::net_device) -> c_types::c_int {
+        from_kernel_result! {
+            // SAFETY: The networking core only calls this on devices registered with this table.
+            T::stop(unsafe { Device::from_ptr(dev) }, unsafe { Self::data(dev) })?;
+            Ok(0)
+        }
+    }
+
+    unsafe extern "C" fn start_xmit_callback(
+        skb: *mut bindings::sk_buff,
+        dev: *mut bindings::net_device,
+    ) -> bindings::netdev_tx_t {
+        // SAFETY: `skb` is a valid buffer whose reference is passed on to the driver.
+        let skb = unsafe { ARef::from_raw(NonNull::new_unchecked(skb).cast()) };
+
+        // SAFETY: The networking core only calls this on devices registered with this table.
+        match T::start_xmit(skb, unsafe { Device::from_ptr(dev) }, unsafe {
+            Self::data(dev)
+        }) {
+            NetdevTx::Ok => bindings::netdev_tx_NETDEV_TX_OK,
+            NetdevTx::Busy(skb) => {
+                // The reference goes back to the caller, which will retry later.
+                mem::forget(skb);
+                bindings::netdev_tx_NETDEV_TX_BUSY
+            }
+        }
+    }
+
+    unsafe extern "C" fn get_stats64_callback(
+        dev: *mut bindings::net_device,
+        storage: *mut bindings::rtnl_link_stats64,
+    ) {
+        let mut stats = Stats64::default();
+        // SAFETY: The networking core only calls this on devices registered with this table.
+        T::get_stats64(
+            unsafe { Device::from_ptr(dev) },
+            unsafe { Self::data(dev) },
+            &mut stats,
+        );
+
+        // SAFETY: `storage` is valid for writes, as provided by the networking core.
+        let storage = unsafe { &mut *storage };
+        storage.rx_packets = stats.rx_packets;
+        storage.tx_packets = stats.tx_packets;
+        storage.rx_bytes = stats.rx_bytes;
+        storage.tx_bytes = stats.tx_bytes;
+        storage.rx_errors = stats.rx_errors;
+        storage.tx_errors = stats.tx_errors;
+        storage.rx_dropped = stats.rx_dropped;
+        storage.tx_dropped = stats.tx_dropped;
+        storage.multicast = stats.multicast;
+        storage.collisions = stats.collisions;
+    }
+
+    fn build() -> bindings::net_device_ops {
+        bindings::net_device_ops {
+            ndo_open: Some(Self::open_callback),
+            ndo_stop: Some(Self::stop_callback),
+            ndo_start_xmit: Some(Self::start_xmit_callback),
+            ndo_get_stats64: if T::TO_USE.get_stats64 {
+                Some(Self::get_stats64_callback)
+            } else {
+                None
+            },
+            ndo_validate_addr: Some(bindings::eth_validate_addr),
+            ndo_set_mac_address: Some(bindings::eth_mac_addr),
+            ..Default::default()
+        }
+    }
+}
+
+/// A registration of a network device.
+///
+/// The interface is an Ethernet one (see `ether_setup`); drivers may change its properties through
+/// [`Registration::device`] before registering it.
+///
+/// # Invariants
+///
+/// `dev` was allocated by `alloc_netdev_mqs` with room for a pointer in its private area, which
+/// holds a value returned by `T::Data::into_pointer`. Its operations point to `_ops`, and to
+/// `_ethtool_ops` if set. It is registered if `registered` is `true`.
+pub struct Registration<T: Operations> {
+    dev: NonNull<bindings::net_device>,
+    registered: bool,
+    _ops: Box<bindings::net_device_ops>,
+    _ethtool_ops: Option<Box<bindings::ethtool_ops>>,
+    _p: PhantomData<T>,
+}
+
+// SAFETY: The registration only gives access to the device and `T::Data`, which are `Send`.
+unsafe impl<T: Operations> Send for Registration<T> {}
+
+// SAFETY: The registration only gives shared access to the device, which is `Sync`, and to
+// `T::Data`, which is `Sync`.
+unsafe impl<T: Operations> Sync for Registration<T> {}
+
+impl<T: Operations> Registration<T> {
+    /// Allocates a new Ethernet interface named `name`, which may contain a `%d` to be replaced
+    /// by the first free number, e.g., `eth%d`.
+    ///
+    /// It isn't registered until [`Registration::register`] is called.
+    pub fn try_new(name: &CStr, data: T::Data) -> Result<Self> {
+        let ops = Box::try_new(OperationsVtable::<T>::build())?;
+
+        // SAFETY: `name` is a valid C string, and `ether_setup` is a valid setup function.
+        let dev = unsafe {
+            bindings::alloc_netdev_mqs(
+                mem::size_of::<*const c_types::c_void>() as _,
+                name.as_char_ptr(),
+                bindings::NET_NAME_UNKNOWN as _,
+                Some(bindings::ether_setup),
+                1,
+                1,
+            )
+        };
+        let dev = NonNull::new(dev).ok_or(ENOMEM)?;
+
+        // SAFETY: `dev` was allocated above with room for a pointer in its private area, and
+        // `ops` lives until `dev` is freed.
+        unsafe {
+            *(bindings::netdev_priv(dev.as_ptr()) as *mut *const c_types::c_void) =
+                data.into_pointer();
+            (*dev.as_ptr()).netdev_ops = &*ops;
+        }
+
+        // INVARIANT: The private area and the operations were initialised above.
+        Ok(Self {
+            dev,
+            registered: false,
+            _ops: ops,
+            _ethtool_ops: None,
+            _p: PhantomData,
+        })
+    }
+
+    /// Returns the device, e.g., to change its properties before it is registered.
+    pub fn device(&self) -> &Device {
+        // SAFETY: By the type invariants, `dev` is valid while `self` is alive.
+        unsafe { Device::from_ptr(self.dev.as_ptr()) }
+    }
+
+    /// Assigns a random locally administered MAC address to the interface.
+    pub fn set_random_addr(&mut self) {
+        // SAFETY: By the type invariants, `dev` is valid, and it's an Ethernet device.
+        unsafe { bindings::eth_hw_addr_random(self.dev.as_ptr()) };
+    }
+
+    /// Sets the ethtool operations of the interface.
+    ///
+    /// # Safety
+    ///
+    /// The operations must be compatible with a device whose private area holds a value returned
+    /// by `T::Data::into_pointer`.
+    pub unsafe fn set_ethtool_ops(&mut self, ops: bindings::ethtool_ops) -> Result {
+        if self.registered {
+            return Err(EBUSY);
+        }
+
+        let ops = Box::try_new(ops)?;
+        // SAFETY: By the type invariants, `dev` is valid, and `ops` lives until it is freed.
+        unsafe { (*self.dev.as_ptr()).ethtool_ops = &*ops };
+        self._ethtool_ops = Some(ops);
+        Ok(())
+    }
+
+    /// Sets the ethtool operations of the interface to those of `E`.
+    ///
+    /// It fails with [`EBUSY`] if the interface is already registered.
+    pub fn set_ethtool_operations<E: EthtoolOperations<Data = T::Data>>(&mut self) -> Result {
+        // SAFETY: The callbacks of `E` expect the private area of the device to hold a value
+        // returned by `T::Data::into_pointer`, which it does by the type invariants.
+        unsafe { self.set_ethtool_ops(EthtoolVtable::<E>::build()) }
+    }
+
+    /// Registers the interface, which becomes visible to user space.
+    pub fn register(&mut self) -> Result {
+        if self.registered {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: By the type invariants, `dev` is valid and fully initialised.
+        to_result(|| unsafe { bindings::register_netdev(self.dev.as_ptr()) })?;
+
+        // INVARIANT: The device was registered above.
+        self.registered = true;
+        Ok(())
+    }
+}
+
+impl<T: Operations> Drop for Registration<T> {
+    fn drop(&mut self) {
+        let dev = self.dev.as_ptr();
+        // SAFETY: By the type invariants, `dev` is valid, registered if `registered` is `true`,
+        // and its private area holds a value returned by `T::Data::into_pointer`. No callbacks
+        // run once the device is unregistered, so the data and the operations can be freed.
+        unsafe {
+            if self.registered {
+                bindings::unregister_netdev(dev);
+            }
+            let data = *(bindings::netdev_priv(dev) as *const *const c_types::c_void);
+            drop(T::Data::from_pointer(data));
+            bindings::free_netdev(dev);
+        }
+    }
+}
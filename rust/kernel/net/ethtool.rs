@@ -0,0 +1,483 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Ethtool support for network device drivers.
+//!
+//! A driver implements [`EthtoolOperations`] to report information about its interfaces to tools
+//! like `ethtool`, and installs them with [`Registration::set_ethtool_operations`].
+//!
+//! C header: [`include/linux/ethtool.h`](../../../../include/linux/ethtool.h)
+//!
+//! # Examples
+//!
+//! ```
+//! # use kernel::prelude::*;
+//! use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+//! use kernel::c_str;
+//! use kernel::net::ethtool::{DrvInfo, Duplex, EthtoolOperations, LinkSettings, RingParam};
+//! use kernel::net::{dev, Device};
+//!
+//! struct Example;
+//!
+//! struct State {
+//!     rx_ring: AtomicU32,
+//!     tx_ring: AtomicU32,
+//!     restarts: AtomicU64,
+//! }
+//!
+//! impl EthtoolOperations for Example {
+//!     kernel::declare_ethtool_operations!(
+//!         get_link_ksettings,
+//!         get_ringparam,
+//!         set_ringparam,
+//!         get_ethtool_stats
+//!     );
+//!
+//!     type Data = Box<State>;
+//!
+//!     const STAT_NAMES: &'static [&'static CStr] = &[c_str!("restarts")];
+//!
+//!     fn get_drvinfo(_dev: &Device, _data: &State, info: &mut DrvInfo<'_>) {
+//!         info.set_driver(c_str!("rust_example"));
+//!         info.set_fw_version(c_str!("1.0"));
+//!     }
+//!
+//!     fn get_link_ksettings(_dev: &Device, _data: &State, settings: &mut LinkSettings) -> Result {
+//!         settings.speed = 10000;
+//!         settings.duplex = Duplex::Full;
+//!         Ok(())
+//!     }
+//!
+//!     fn get_ringparam(_dev: &Device, data: &State, ring: &mut RingParam) {
+//!         ring.rx_max_pending = 4096;
+//!         ring.tx_max_pending = 4096;
+//!         ring.rx_pending = data.rx_ring.load(Ordering::Relaxed);
+//!         ring.tx_pending = data.tx_ring.load(Ordering::Relaxed);
+//!     }
+//!
+//!     fn set_ringparam(_dev: &Device, data: &State, ring: &RingParam) -> Result {
+//!         data.rx_ring.store(ring.rx_pending, Ordering::Relaxed);
+//!         data.tx_ring.store(ring.tx_pending, Ordering::Relaxed);
+//!         Ok(())
+//!     }
+//!
+//!     fn get_ethtool_stats(_dev: &Device, data: &State, stats: &mut [u64]) {
+//!         stats[0] = data.restarts.load(Ordering::Relaxed);
+//!     }
+//! }
+//!
+//! fn install<T: dev::Operations<Data = Box<State>>>(reg: &mut dev::Registration<T>) -> Result {
+//!     reg.set_ethtool_operations::<Example>()
+//! }
+//! ```
+//!
+//! [`Registration::set_ethtool_operations`]: super::dev::Registration::set_ethtool_operations
+
+use super::{dev, Device};
+use crate::{
+    bindings, c_types,
+    error::{code::*, from_kernel_result, Result},
+    str::CStr,
+    types::PointerWrapper,
+};
+use core::marker::PhantomData;
+
+/// Copies `src` into the C string buffer `dst`, truncating it if needed.
+fn copy_str(dst: &mut [c_types::c_char], src: &CStr) {
+    let len = src.len().min(dst.len() - 1);
+    for (d, s) in dst.iter_mut().zip(&src.as_bytes()[..len]) {
+        *d = *s as _;
+    }
+    dst[len] = 0;
+}
+
+/// Driver information, reported by [`EthtoolOperations::get_drvinfo`].
+///
+/// Strings longer than the fields are truncated.
+pub struct DrvInfo<'a>(&'a mut bindings::ethtool_drvinfo);
+
+impl DrvInfo<'_> {
+    /// Sets the name of the driver.
+    pub fn set_driver(&mut self, name: &CStr) {
+        copy_str(&mut self.0.driver, name);
+    }
+
+    /// Sets the version of the driver.
+    pub fn set_version(&mut self, version: &CStr) {
+        copy_str(&mut self.0.version, version);
+    }
+
+    /// Sets the version of the device's firmware.
+    pub fn set_fw_version(&mut self, version: &CStr) {
+        copy_str(&mut self.0.fw_version, version);
+    }
+
+    /// Sets the bus location of the device, e.g., its PCI address.
+    pub fn set_bus_info(&mut self, bus_info: &CStr) {
+        copy_str(&mut self.0.bus_info, bus_info);
+    }
+}
+
+/// The duplex mode of a link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Duplex {
+    /// Half duplex.
+    Half,
+
+    /// Full duplex.
+    Full,
+
+    /// Unknown, e.g., because the link is down.
+    Unknown,
+}
+
+/// The connector type of a link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Port {
+    /// Twisted pair.
+    TwistedPair,
+
+    /// Optical fibre.
+    Fibre,
+
+    /// Direct attach copper.
+    DirectAttach,
+
+    /// No physical connector, e.g., for virtual devices.
+    None,
+
+    /// Another connector type.
+    Other,
+}
+
+/// Link settings, reported by [`EthtoolOperations::get_link_ksettings`].
+pub struct LinkSettings {
+    /// The speed of the link, in Mb/s, or `u32::MAX` if it is unknown.
+    pub speed: u32,
+
+    /// The duplex mode of the link.
+    pub duplex: Duplex,
+
+    /// The connector type of the link.
+    pub port: Port,
+
+    /// Whether autonegotiation is enabled.
+    pub autoneg: bool,
+}
+
+impl Default for LinkSettings {
+    fn default() -> Self {
+        Self {
+            speed: u32::MAX,
+            duplex: Duplex::Unknown,
+            port: Port::Other,
+            autoneg: false,
+        }
+    }
+}
+
+/// The sizes of the rx and tx rings, reported by [`EthtoolOperations::get_ringparam`] and changed
+/// by [`EthtoolOperations::set_ringparam`].
+#[derive(Default)]
+pub struct RingParam {
+    /// The maximum number of rx ring entries.
+    pub rx_max_pending: u32,
+
+    /// The maximum number of tx ring entries.
+    pub tx_max_pending: u32,
+
+    /// The number of rx ring entries.
+    pub rx_pending: u32,
+
+    /// The number of tx ring entries.
+    pub tx_pending: u32,
+}
+
+/// The ethtool operations of a network device.
+pub trait EthtoolOperations: Sized {
+    /// The methods to use to populate [`struct ethtool_ops`].
+    const TO_USE: ToUse;
+
+    /// The data of the interface, which must be the same as that of its [`dev::Operations`].
+    type Data: PointerWrapper + Send + Sync;
+
+    /// The names of the statistics reported by [`EthtoolOperations::get_ethtool_stats`].
+    ///
+    /// Names longer than 31 bytes are truncated.
+    const STAT_NAMES: &'static [&'static CStr] = &[];
+
+    /// Reports information about the driver and the device, e.g., with `ethtool -i`.
+    ///
+    /// The name of the driver must be set.
+    fn get_drvinfo(
+        dev: &Device,
+        data: <Self::Data as PointerWrapper>::Borrowed<'_>,
+        info: &mut DrvInfo<'_>,
+    );
+
+    /// Reports the settings of the link, e.g., with `ethtool <name>`.
+    fn get_link_ksettings(
+        _dev: &Device,
+        _data: <Self::Data as PointerWrapper>::Borrowed<'_>,
+        _settings: &mut LinkSettings,
+    ) -> Result {
+        Err(EOPNOTSUPP)
+    }
+
+    /// Reports the sizes of the rings, e.g., with `ethtool -g`.
+    fn get_ringparam(
+        _dev: &Device,
+        _data: <Self::Data as PointerWrapper>::Borrowed<'_>,
+        _ring: &mut RingParam,
+    ) {
+    }
+
+    /// Changes the sizes of the rings, e.g., with `ethtool -G`.
+    ///
+    /// The requested sizes were checked against the maximum reported by
+    /// [`EthtoolOperations::get_ringparam`].
+    fn set_ringparam(
+        _dev: &Device,
+        _data: <Self::Data as PointerWrapper>::Borrowed<'_>,
+        _ring: &RingParam,
+    ) -> Result {
+        Err(EOPNOTSUPP)
+    }
+
+    /// Reports the statistics named by [`EthtoolOperations::STAT_NAMES`], e.g., with `ethtool -S`.
+    ///
+    /// `stats` has one entry per name, in the same order.
+    fn get_ethtool_stats(
+        _dev: &Device,
+        _data: <Self::Data as PointerWrapper>::Borrowed<'_>,
+        _stats: &mut [u64],
+    ) {
+    }
+}
+
+/// Represents which optional fields of [`struct ethtool_ops`] should be populated with pointers.
+pub struct ToUse {
+    /// The `get_link_ksettings` field of [`struct ethtool_ops`].
+    pub get_link_ksettings: bool,
+
+    /// The `get_ringparam` field of [`struct ethtool_ops`].
+    pub get_ringparam: bool,
+
+    /// The `set_ringparam` field of [`struct ethtool_ops`].
+    pub set_ringparam: bool,
+
+    /// The `get_ethtool_stats`, `get_strings` and `get_sset_count` fields of
+    /// [`struct ethtool_ops`].
+    pub get_ethtool_stats: bool,
+}
+
+/// A constant version where all values are to set to `false`, that is, all supported fields will
+/// be set to null pointers.
+pub const USE_NONE: ToUse = ToUse {
+    get_link_ksettings: false,
+    get_ringparam: false,
+    set_ringparam: false,
+    get_ethtool_stats: false,
+};
+
+/// Defines the [`EthtoolOperations::TO_USE`] field based on a list of fields to be populated.
+#[macro_export]
+macro_rules! declare_ethtool_operations {
+    () => {
+        const TO_USE: $crate::net::ethtool::ToUse = $crate::net::ethtool::USE_NONE;
+    };
+    ($($i:ident),+) => {
+        #[allow(clippy::needless_update)]
+        const TO_USE: kernel::net::ethtool::ToUse =
+            $crate::net::ethtool::ToUse {
+                $($i: true),+ ,
+                ..$crate::net::ethtool::USE_NONE
+            };
+    };
+}
+
+pub(crate) struct EthtoolVtable<T: EthtoolOperations>(PhantomData<T>);
+
+impl<T: EthtoolOperations> EthtoolVtable<T> {
+    unsafe extern "C" fn get_drvinfo_callback(
+        dev: *mut bindings::net_device,
+        info: *mut bindings::ethtool_drvinfo,
+    ) {
+        // SAFETY: The networking core only calls this on devices registered with this table, and
+        // `info` is valid for writes.
+        unsafe {
+            T::get_drvinfo(
+                Device::from_ptr(dev),
+                dev::data::<T::Data>(dev),
+                &mut DrvInfo(&mut *info),
+            )
+        };
+    }
+
+    unsafe extern "C" fn get_link_ksettings_callback(
+        dev: *mut bindings::net_device,
+        cmd: *mut bindings::ethtool_link_ksettings,
+    ) -> c_types::c_int {
+        from_kernel_result! {
+            let mut settings = LinkSettings::default();
+            // SAFETY: The networking core only calls this on devices registered with this table.
+            T::get_link_ksettings(
+                unsafe { Device::from_ptr(dev) },
+                unsafe { dev::data::<T::Data>(dev) },
+                &mut settings,
+            )?;
+
+            // SAFETY: `cmd` is valid for writes, as provided by the networking core.
+            let base = unsafe { &mut (*cmd).base };
+            base.speed = settings.speed;
+            base.duplex = match settings.duplex {
+                Duplex::Half => bindings::DUPLEX_HALF,
+                Duplex::Full => bindings::DUPLEX_FULL,
+                Duplex::Unknown => bindings::DUPLEX_UNKNOWN,
+            } as _;
+            base.port = match settings.port {
+                Port::TwistedPair => bindings::PORT_TP,
+                Port::Fibre => bindings::PORT_FIBRE,
+                Port::DirectAttach => bindings::PORT_DA,
+                Port::None => bindings::PORT_NONE,
+                Port::Other => bindings::PORT_OTHER,
+            } as _;
+            base.autoneg = if settings.autoneg {
+                bindings::AUTONEG_ENABLE
+            } else {
+                bindings::AUTONEG_DISABLE
+            } as _;
+            Ok(0)
+        }
+    }
+
+    unsafe extern "C" fn get_ringparam_callback(
+        dev: *mut bindings::net_device,
+        ring: *mut bindings::ethtool_ringparam,
+        _kernel_ring: *mut bindings::kernel_ethtool_ringparam,
+        _extack: *mut bindings::netlink_ext_ack,
+    ) {
+        let mut param = RingParam::default();
+        // SAFETY: The networking core only calls this on devices registered with this table.
+        T::get_ringparam(
+            unsafe { Device::from_ptr(dev) },
+            unsafe { dev::data::<T::Data>(dev) },
+            &mut param,
+        );
+
+        // SAFETY: `ring` is valid for writes, as provided by the networking core.
+        let ring = unsafe { &mut *ring };
+        ring.rx_max_pending = param.rx_max_pending;
+        ring.tx_max_pending = param.tx_max_pending;
+        ring.rx_pending = param.rx_pending;
+        ring.tx_pending = param.tx_pending;
+    }
+
+    unsafe extern "C" fn set_ringparam_callback(
+        dev: *mut bindings::net_device,
+        ring: *mut bindings::ethtool_ringparam,
+        _kernel_ring: *mut bindings::kernel_ethtool_ringparam,
+        _extack: *mut bindings::netlink_ext_ack,
+    ) -> c_types::c_int {
+        from_kernel_result! {
+            // SAFETY: `ring` is valid for reads, as provided by the networking core.
+            let ring = unsafe { &*ring };
+            let param = RingParam {
+                rx_max_pending: ring.rx_max_pending,
+                tx_max_pending: ring.tx_max_pending,
+                rx_pending: ring.rx_pending,
+                tx_pending: ring.tx_pending,
+            };
+            // SAFETY: The networking core only calls this on devices registered with this table.
+            T::set_ringparam(
+                unsafe { Device::from_ptr(dev) },
+                unsafe { dev::data::<T::Data>(dev) },
+                &param,
+            )?;
+            Ok(0)
+        }
+    }
+
+    unsafe extern "C" fn get_sset_count_callback(
+        _dev: *mut bindings::net_device,
+        sset: c_types::c_int,
+    ) -> c_types::c_int {
+        if sset == bindings::ethtool_stringset_ETH_SS_STATS as _ {
+            T::STAT_NAMES.len() as _
+        } else {
+            EOPNOTSUPP.to_kernel_errno()
+        }
+    }
+
+    unsafe extern "C" fn get_strings_callback(
+        _dev: *mut bindings::net_device,
+        sset: u32,
+        buf: *mut u8,
+    ) {
+        if sset != bindings::ethtool_stringset_ETH_SS_STATS {
+            return;
+        }
+
+        let len = bindings::ETH_GSTRING_LEN as usize;
+        // SAFETY: The networking core provides room for `ETH_GSTRING_LEN` bytes per statistic, as
+        // returned by `get_sset_count_callback`.
+        let buf = unsafe { core::slice::from_raw_parts_mut(buf.cast(), T::STAT_NAMES.len() * len) };
+        for (dst, name) in buf.chunks_exact_mut(len).zip(T::STAT_NAMES) {
+            copy_str(dst, name);
+        }
+    }
+
+    unsafe extern "C" fn get_ethtool_stats_callback(
+        dev: *mut bindings::net_device,
+        _stats: *mut bindings::ethtool_stats,
+        data: *mut u64,
+    ) {
+        // SAFETY: The networking core provides room for one value per statistic, as returned by
+        // `get_sset_count_callback`, and zeroes it.
+        let stats = unsafe { core::slice::from_raw_parts_mut(data, T::STAT_NAMES.len()) };
+        // SAFETY: The networking core only calls this on devices registered with this table.
+        T::get_ethtool_stats(
+            unsafe { Device::from_ptr(dev) },
+            unsafe { dev::data::<T::Data>(dev) },
+            stats,
+        );
+    }
+
+    pub(crate) fn build() -> bindings::ethtool_ops {
+        let stats = T::TO_USE.get_ethtool_stats;
+        bindings::ethtool_ops {
+            get_drvinfo: Some(Self::get_drvinfo_callback),
+            get_link: Some(bindings::ethtool_op_get_link),
+            get_link_ksettings: if T::TO_USE.get_link_ksettings {
+                Some(Self::get_link_ksettings_callback)
+            } else {
+                None
+            },
+            get_ringparam: if T::TO_USE.get_ringparam {
+                Some(Self::get_ringparam_callback)
+            } else {
+                None
+            },
+            set_ringparam: if T::TO_USE.set_ringparam {
+                Some(Self::set_ringparam_callback)
+            } else {
+                None
+            },
+            get_sset_count: if stats {
+                Some(Self::get_sset_count_callback)
+            } else {
+                None
+            },
+            get_strings: if stats {
+                Some(Self::get_strings_callback)
+            } else {
+                None
+            },
+            get_ethtool_stats: if stats {
+                Some(Self::get_ethtool_stats_callback)
+            } else {
+                None
+            },
+            ..Default::default()
+        }
+    }
+}
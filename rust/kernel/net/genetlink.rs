@@ -0,0 +1,596 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Generic netlink.
+//!
+//! A generic netlink family is a named channel between userspace and the kernel, e.g., for
+//! configuration requests and event notifications. Userspace resolves the family by name and sends
+//! it commands, whose attributes are validated against the family's policy before they reach the
+//! handlers.
+//!
+//! C header: [`include/net/genetlink.h`](../../../../include/net/genetlink.h)
+//!
+//! # Examples
+//!
+//! A family that keeps a counter, which userspace can read, add to, and be notified about:
+//!
+//! ```
+//! # use kernel::prelude::*;
+//! use core::sync::atomic::{AtomicU32, Ordering};
+//! use kernel::c_str;
+//! use kernel::net::genetlink::{AttrPolicy, AttrType, Command, Family, Info, Operations};
+//!
+//! const CMD_GET: u8 = 1;
+//! const CMD_ADD: u8 = 2;
+//! const CMD_CHANGED: u8 = 3;
+//!
+//! const ATTR_VALUE: u16 = 1;
+//!
+//! struct Counter;
+//!
+//! impl Operations for Counter {
+//!     type Data = Box<AtomicU32>;
+//!
+//!     const NAME: &'static CStr = c_str!("rust_counter");
+//!     const MAX_ATTR: u16 = ATTR_VALUE;
+//!     const POLICY: &'static [AttrPolicy] = &[AttrPolicy::new(ATTR_VALUE, AttrType::U32)];
+//!     const COMMANDS: &'static [Command<Self>] = &[
+//!         Command {
+//!             cmd: CMD_GET,
+//!             admin: false,
+//!             doit: get,
+//!         },
+//!         Command {
+//!             cmd: CMD_ADD,
+//!             admin: true,
+//!             doit: add,
+//!         },
+//!     ];
+//!     const MULTICAST_GROUPS: &'static [&'static CStr] = &[c_str!("events")];
+//! }
+//!
+//! fn get(counter: &AtomicU32, info: &Info<'_>) -> Result {
+//!     info.reply(CMD_GET, |msg| {
+//!         msg.put_u32(ATTR_VALUE, counter.load(Ordering::Relaxed))
+//!     })
+//! }
+//!
+//! fn add(counter: &AtomicU32, info: &Info<'_>) -> Result {
+//!     let value = info.attr(ATTR_VALUE).ok_or(EINVAL)?.as_u32()?;
+//!     counter.fetch_add(value, Ordering::Relaxed);
+//!     Ok(())
+//! }
+//!
+//! fn register() -> Result<Family<Counter>> {
+//!     let family = Family::register(Box::try_new(AtomicU32::new(0))?, &THIS_MODULE)?;
+//!
+//!     // Tells the listeners of the `events` group that the counter was reset.
+//!     family.notify(0, CMD_CHANGED, |msg| msg.put_u32(ATTR_VALUE, 0))?;
+//!     Ok(family)
+//! }
+//! ```
+
+use crate::{
+    bindings, c_types,
+    error::{code::*, from_kernel_result, to_result, Error, Result},
+    str::CStr,
+    types::PointerWrapper,
+    ThisModule,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::{convert::TryInto, marker::PhantomData, mem, ptr};
+
+/// The type of an attribute, which is validated by the policy before commands are handled.
+#[derive(Clone, Copy, Debug)]
+pub enum AttrType {
+    /// An 8-bit unsigned integer.
+    U8,
+
+    /// A 16-bit unsigned integer.
+    U16,
+
+    /// A 32-bit unsigned integer.
+    U32,
+
+    /// A 64-bit unsigned integer.
+    U64,
+
+    /// A flag, which carries no payload and is either present or absent.
+    Flag,
+
+    /// A null-terminated string of at most `max_len` bytes, excluding the terminator.
+    ///
+    /// If `max_len` is zero, the length isn't limited.
+    String {
+        /// The maximum length of the string.
+        max_len: u16,
+    },
+
+    /// A binary blob of at most `max_len` bytes.
+    ///
+    /// If `max_len` is zero, the length isn't limited.
+    Binary {
+        /// The maximum length of the blob.
+        max_len: u16,
+    },
+}
+
+impl AttrType {
+    fn to_policy(self) -> bindings::nla_policy {
+        let (type_, len) = match self {
+            Self::U8 => (bindings::NLA_U8, 0),
+            Self::U16 => (bindings::NLA_U16, 0),
+            Self::U32 => (bindings::NLA_U32, 0),
+            Self::U64 => (bindings::NLA_U64, 0),
+            Self::Flag => (bindings::NLA_FLAG, 0),
+            Self::String { max_len } => (bindings::NLA_NUL_STRING, max_len),
+            Self::Binary { max_len } => (bindings::NLA_BINARY, max_len),
+        };
+        let mut policy = bindings::nla_policy::default();
+        policy.type_ = type_ as _;
+        policy.len = len;
+        policy
+    }
+}
+
+/// The policy of a single attribute of a family.
+#[derive(Clone, Copy, Debug)]
+pub struct AttrPolicy {
+    id: u16,
+    ty: AttrType,
+}
+
+impl AttrPolicy {
+    /// Creates the policy of attribute `id`, which must be between 1 and [`Operations::MAX_ATTR`].
+    pub const fn new(id: u16, ty: AttrType) -> Self {
+        Self { id, ty }
+    }
+}
+
+/// A command of a family.
+pub struct Command<T: Operations> {
+    /// The command number, as sent by userspace.
+    pub cmd: u8,
+
+    /// Whether the sender needs `CAP_NET_ADMIN` to issue the command.
+    pub admin: bool,
+
+    /// The handler of the command.
+    pub doit: fn(<T::Data as PointerWrapper>::Borrowed<'_>, &Info<'_>) -> Result,
+}
+
+/// Describes a generic netlink family.
+pub trait Operations: Sized + 'static {
+    /// The data shared by the handlers of the family's commands.
+    type Data: PointerWrapper + Send + Sync = ();
+
+    /// The name of the family, which userspace uses to resolve its id.
+    const NAME: &'static CStr;
+
+    /// The version of the family.
+    const VERSION: u8 = 1;
+
+    /// The highest attribute id of the family.
+    const MAX_ATTR: u16 = 0;
+
+    /// The policy of the family's attributes.
+    ///
+    /// Commands are validated strictly, so those with attributes that don't have a policy are
+    /// rejected.
+    const POLICY: &'static [AttrPolicy] = &[];
+
+    /// The commands of the family.
+    const COMMANDS: &'static [Command<Self>];
+
+    /// The names of the family's multicast groups.
+    ///
+    /// Groups are referred to by their index in this slice in [`Family::notify`].
+    const MULTICAST_GROUPS: &'static [&'static CStr] = &[];
+}
+
+/// A received command.
+///
+/// # Invariants
+///
+/// `ptr` is valid for the duration of `'a`, and its attributes were validated against the policy
+/// of its family.
+pub struct Info<'a> {
+    ptr: *const bindings::genl_info,
+    _p: PhantomData<&'a bindings::genl_info>,
+}
+
+impl<'a> Info<'a> {
+    /// Returns the command number.
+    pub fn cmd(&self) -> u8 {
+        // SAFETY: By the type invariants, `ptr` is valid, and so is its generic netlink header.
+        unsafe { (*(*self.ptr).genlhdr).cmd }
+    }
+
+    /// Returns the port id of the sender.
+    pub fn snd_portid(&self) -> u32 {
+        // SAFETY: By the type invariants, `ptr` is valid.
+        unsafe { (*self.ptr).snd_portid }
+    }
+
+    /// Returns the sequence number of the request.
+    pub fn snd_seq(&self) -> u32 {
+        // SAFETY: By the type invariants, `ptr` is valid.
+        unsafe { (*self.ptr).snd_seq }
+    }
+
+    /// Returns attribute `id`, or [`None`] if it wasn't sent.
+    pub fn attr(&self, id: u16) -> Option<Attr<'a>> {
+        // SAFETY: By the type invariants, `ptr` is valid, and so is its family.
+        let (attrs, max_attr) = unsafe { ((*self.ptr).attrs, (*(*self.ptr).family).maxattr) };
+        if attrs.is_null() || u32::from(id) > max_attr {
+            return None;
+        }
+
+        // SAFETY: `attrs` has `maxattr + 1` entries, and `id` is in range.
+        let ptr = unsafe { *attrs.add(id.into()) };
+        if ptr.is_null() {
+            return None;
+        }
+
+        // INVARIANT: Parsed attributes remain valid while the command is handled.
+        Some(Attr {
+            ptr,
+            _p: PhantomData,
+        })
+    }
+
+    /// Returns whether the flag attribute `id` was sent.
+    pub fn flag(&self, id: u16) -> bool {
+        self.attr(id).is_some()
+    }
+
+    /// Replies to the sender with a message for `cmd`, whose attributes are added by `f`.
+    pub fn reply(&self, cmd: u8, f: impl FnOnce(&mut Message) -> Result) -> Result {
+        // SAFETY: By the type invariants, `ptr` is valid, and so is its family.
+        let mut msg =
+            unsafe { Message::new((*self.ptr).family, self.snd_portid(), self.snd_seq(), cmd)? };
+        f(&mut msg)?;
+
+        let skb = msg.finish();
+        // SAFETY: By the type invariants, `ptr` is valid. `genlmsg_reply` takes ownership of the
+        // message, even on failure.
+        to_result(|| unsafe { bindings::genlmsg_reply(skb, self.ptr as _) })
+    }
+}
+
+/// An attribute of a received command.
+///
+/// # Invariants
+///
+/// `ptr` is valid for the duration of `'a`.
+pub struct Attr<'a> {
+    ptr: *const bindings::nlattr,
+    _p: PhantomData<&'a bindings::nlattr>,
+}
+
+impl<'a> Attr<'a> {
+    /// Returns the payload of the attribute.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        // SAFETY: By the type invariants, `ptr` is valid, and its payload is `nla_len` bytes long.
+        unsafe {
+            let len = bindings::nla_len(self.ptr);
+            core::slice::from_raw_parts(bindings::nla_data(self.ptr).cast(), len as _)
+        }
+    }
+
+    fn as_array<const N: usize>(&self) -> Result<[u8; N]> {
+        self.as_bytes().try_into().map_err(|_| EINVAL)
+    }
+
+    /// Returns the payload of an [`AttrType::U8`] attribute.
+    pub fn as_u8(&self) -> Result<u8> {
+        Ok(u8::from_ne_bytes(self.as_array()?))
+    }
+
+    /// Returns the payload of an [`AttrType::U16`] attribute.
+    pub fn as_u16(&self) -> Result<u16> {
+        Ok(u16::from_ne_bytes(self.as_array()?))
+    }
+
+    /// Returns the payload of an [`AttrType::U32`] attribute.
+    pub fn as_u32(&self) -> Result<u32> {
+        Ok(u32::from_ne_bytes(self.as_array()?))
+    }
+
+    /// Returns the payload of an [`AttrType::U64`] attribute.
+    pub fn as_u64(&self) -> Result<u64> {
+        Ok(u64::from_ne_bytes(self.as_array()?))
+    }
+
+    /// Returns the payload of an [`AttrType::String`] attribute.
+    ///
+    /// The policy only guarantees that the payload contains a null byte, which may be followed by
+    /// other bytes (e.g., padding); the string ends at the first one.
+    pub fn as_str(&self) -> Result<&'a CStr> {
+        let bytes = self.as_bytes();
+        let len = bytes.iter().position(|&b| b == 0).ok_or(EINVAL)?;
+        CStr::from_bytes_with_nul(&bytes[..=len]).map_err(|_| EINVAL)
+    }
+}
+
+/// A message being built, e.g., a reply or a notification.
+///
+/// The message is freed if it is dropped before being sent.
+///
+/// # Invariants
+///
+/// `skb` is a valid netlink message owned by `self`, and `hdr` is its generic netlink header.
+pub struct Message {
+    skb: *mut bindings::sk_buff,
+    hdr: *mut c_types::c_void,
+}
+
+impl Message {
+    /// Allocates a message for `cmd` of `family`.
+    ///
+    /// # Safety
+    ///
+    /// `family` must be a valid registered family.
+    unsafe fn new(
+        family: *const bindings::genl_family,
+        portid: u32,
+        seq: u32,
+        cmd: u8,
+    ) -> Result<Self> {
+        // SAFETY: FFI call.
+        let skb = unsafe { bindings::genlmsg_new(bindings::NLMSG_GOODSIZE, bindings::GFP_KERNEL) };
+        if skb.is_null() {
+            return Err(ENOMEM);
+        }
+
+        // SAFETY: `skb` was just allocated and `family` is valid by the safety requirements.
+        let hdr = unsafe { bindings::genlmsg_put(skb, portid, seq, family, 0, cmd) };
+        if hdr.is_null() {
+            // SAFETY: `skb` was allocated above and isn't used anymore.
+            unsafe { bindings::nlmsg_free(skb) };
+            return Err(EMSGSIZE);
+        }
+
+        // INVARIANT: `skb` and `hdr` were initialised above.
+        Ok(Self { skb, hdr })
+    }
+
+    /// Adds attribute `id` with the given payload.
+    pub fn put_bytes(&mut self, id: u16, data: &[u8]) -> Result {
+        let len = data.len().try_into()?;
+        // SAFETY: By the type invariants, `skb` is valid, and `data` is valid for `len` bytes.
+        to_result(|| unsafe { bindings::nla_put(self.skb, id.into(), len, data.as_ptr().cast()) })
+    }
+
+    /// Adds an [`AttrType::U8`] attribute.
+    pub fn put_u8(&mut self, id: u16, value: u8) -> Result {
+        self.put_bytes(id, &value.to_ne_bytes())
+    }
+
+    /// Adds an [`AttrType::U16`] attribute.
+    pub fn put_u16(&mut self, id: u16, value: u16) -> Result {
+        self.put_bytes(id, &value.to_ne_bytes())
+    }
+
+    /// Adds an [`AttrType::U32`] attribute.
+    pub fn put_u32(&mut self, id: u16, value: u32) -> Result {
+        self.put_bytes(id, &value.to_ne_bytes())
+    }
+
+    /// Adds an [`AttrType::U64`] attribute.
+    pub fn put_u64(&mut self, id: u16, value: u64) -> Result {
+        self.put_bytes(id, &value.to_ne_bytes())
+    }
+
+    /// Adds an [`AttrType::Flag`] attribute.
+    pub fn put_flag(&mut self, id: u16) -> Result {
+        self.put_bytes(id, &[])
+    }
+
+    /// Adds an [`AttrType::String`] attribute.
+    pub fn put_str(&mut self, id: u16, value: &CStr) -> Result {
+        self.put_bytes(id, value.as_bytes_with_nul())
+    }
+
+    /// Finalises the message and returns it, transferring its ownership to the caller.
+    fn finish(self) -> *mut bindings::sk_buff {
+        let skb = self.skb;
+        // SAFETY: By the type invariants, `hdr` is the header of `skb`.
+        unsafe { bindings::genlmsg_end(skb, self.hdr) };
+        mem::forget(self);
+        skb
+    }
+}
+
+impl Drop for Message {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `skb` is owned by `self`.
+        unsafe { bindings::nlmsg_free(self.skb) };
+    }
+}
+
+struct FamilyInner {
+    family: bindings::genl_family,
+    data: *const c_types::c_void,
+    _ops: Vec<bindings::genl_ops>,
+    _policy: Vec<bindings::nla_policy>,
+    _mcgrps: Vec<bindings::genl_multicast_group>,
+}
+
+/// A registered generic netlink family.
+///
+/// The family is unregistered when this is dropped.
+///
+/// # Invariants
+///
+/// `inner.family` is registered, and `inner.data` was returned by `T::Data::into_pointer`.
+pub struct Family<T: Operations> {
+    inner: Box<FamilyInner>,
+    _p: PhantomData<T>,
+}
+
+// SAFETY: The family can be unregistered from any thread, and `T::Data` is `Send`.
+unsafe impl<T: Operations> Send for Family<T> {}
+
+// SAFETY: `notify` is safe to call concurrently, and `T::Data` is `Sync`.
+unsafe impl<T: Operations> Sync for Family<T> {}
+
+fn copy_name(dst: &mut [c_types::c_char], name: &CStr) -> Result {
+    let name = name.as_bytes_with_nul();
+    if name.len() > dst.len() {
+        return Err(EINVAL);
+    }
+    for (d, s) in dst.iter_mut().zip(name) {
+        *d = *s as _;
+    }
+    Ok(())
+}
+
+impl<T: Operations> Family<T> {
+    /// Registers the family described by `T`, whose commands are handled with `data`.
+    ///
+    /// Commands may be handled concurrently, and as soon as this function returns.
+    pub fn register(data: T::Data, module: &'static ThisModule) -> Result<Self> {
+        let mut policy = Vec::new();
+        if T::MAX_ATTR > 0 {
+            policy.try_resize(
+                usize::from(T::MAX_ATTR) + 1,
+                bindings::nla_policy::default(),
+            )?;
+            for p in T::POLICY {
+                if p.id == 0 || p.id > T::MAX_ATTR {
+                    return Err(EINVAL);
+                }
+                policy[usize::from(p.id)] = p.ty.to_policy();
+            }
+        }
+
+        let mut ops = Vec::try_with_capacity(T::COMMANDS.len())?;
+        for c in T::COMMANDS {
+            let mut op = bindings::genl_ops::default();
+            op.cmd = c.cmd;
+            op.flags = if c.admin {
+                bindings::GENL_ADMIN_PERM as _
+            } else {
+                0
+            };
+            op.doit = Some(doit_callback::<T>);
+            ops.try_push(op)?;
+        }
+
+        let mut mcgrps = Vec::try_with_capacity(T::MULTICAST_GROUPS.len())?;
+        for name in T::MULTICAST_GROUPS {
+            let mut grp = bindings::genl_multicast_group::default();
+            copy_name(&mut grp.name, name)?;
+            mcgrps.try_push(grp)?;
+        }
+
+        let mut family = bindings::genl_family::default();
+        copy_name(&mut family.name, T::NAME)?;
+        family.version = T::VERSION.into();
+        family.maxattr = T::MAX_ATTR.into();
+        family.policy = if policy.is_empty() {
+            ptr::null()
+        } else {
+            policy.as_ptr()
+        };
+        family.ops = ops.as_ptr();
+        family.n_ops = ops.len().try_into()?;
+        family.mcgrps = mcgrps.as_ptr();
+        family.n_mcgrps = mcgrps.len().try_into()?;
+        family.module = module.0;
+        // The data is `Sync`, so commands don't need to be serialised.
+        family.set_parallel_ops(1);
+
+        let mut inner = Box::try_new(FamilyInner {
+            family,
+            data: ptr::null(),
+            _ops: ops,
+            _policy: policy,
+            _mcgrps: mcgrps,
+        })?;
+        inner.data = data.into_pointer();
+
+        // SAFETY: `inner.family` and the arrays it points to are valid, and remain so until it is
+        // unregistered in `drop`, since they are boxed.
+        let ret = unsafe { bindings::genl_register_family(&mut inner.family) };
+        if ret != 0 {
+            // SAFETY: `data` was turned into a pointer above, and the family wasn't registered,
+            // so it isn't borrowed.
+            unsafe { T::Data::from_pointer(inner.data) };
+            return Err(Error::from_kernel_errno(ret));
+        }
+
+        // INVARIANT: The family was registered above, and `data` was set from `into_pointer`.
+        Ok(Self {
+            inner,
+            _p: PhantomData,
+        })
+    }
+
+    /// Sends a message for `cmd`, whose attributes are added by `f`, to the listeners of the
+    /// multicast group at index `group` of [`Operations::MULTICAST_GROUPS`].
+    ///
+    /// It succeeds if there are no listeners.
+    pub fn notify(&self, group: u32, cmd: u8, f: impl FnOnce(&mut Message) -> Result) -> Result {
+        if group as usize >= T::MULTICAST_GROUPS.len() {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: By the type invariants, the family is registered.
+        let mut msg = unsafe { Message::new(&self.inner.family, 0, 0, cmd)? };
+        f(&mut msg)?;
+
+        // SAFETY: By the type invariants, the family is registered, and `group` is one of its
+        // groups. `genlmsg_multicast` takes ownership of the message, even on failure.
+        let ret = unsafe {
+            bindings::genlmsg_multicast(
+                &self.inner.family,
+                msg.finish(),
+                0,
+                group,
+                bindings::GFP_KERNEL,
+            )
+        };
+        if ret != 0 && ret != ESRCH.to_kernel_errno() {
+            return Err(Error::from_kernel_errno(ret));
+        }
+        Ok(())
+    }
+}
+
+impl<T: Operations> Drop for Family<T> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, the family is registered. Once it is unregistered, no
+        // commands are being handled, so `data` isn't borrowed anymore.
+        unsafe {
+            bindings::genl_unregister_family(&self.inner.family);
+            T::Data::from_pointer(self.inner.data);
+        }
+    }
+}
+
+unsafe extern "C" fn doit_callback<T: Operations>(
+    _skb: *mut bindings::sk_buff,
+    info: *mut bindings::genl_info,
+) -> c_types::c_int {
+    from_kernel_result! {
+        // INVARIANT: `info` is valid while the command is handled, and its attributes were
+        // validated against the policy of the family.
+        let info = Info {
+            ptr: info,
+            _p: PhantomData,
+        };
+        let cmd = info.cmd();
+        let command = T::COMMANDS.iter().find(|c| c.cmd == cmd).ok_or(EOPNOTSUPP)?;
+
+        // SAFETY: The family of the command is the `family` field of a `FamilyInner` registered
+        // by `Family::<T>::register`, which remains registered while commands are handled.
+        let inner = unsafe { &*crate::container_of!((*info.ptr).family, FamilyInner, family) };
+
+        // SAFETY: `data` was returned by `into_pointer`, and `from_pointer` is only called once
+        // the family is unregistered, when no commands are being handled.
+        let data = unsafe { T::Data::borrow(inner.data) };
+        (command.doit)(data, &info)?;
+        Ok(0)
+    }
+}
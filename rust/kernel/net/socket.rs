@@ -0,0 +1,530 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Kernel sockets and their event callbacks.
+//!
+//! Sockets report events, e.g., that data was received, by calling back into their owner from
+//! softirq context. [`Callbacks`] attached to a [`TcpListener`], [`TcpStream`] or [`UdpSocket`]
+//! are called on these events, so servers can handle them without polling threads.
+//!
+//! Callbacks cannot sleep, so they usually defer the actual work to a work item, which is what
+//! [`WorkCallbacks`] does.
+//!
+//! C headers: [`include/linux/net.h`](../../../../include/linux/net.h) and
+//! [`include/net/sock.h`](../../../../include/net/sock.h)
+//!
+//! [`TcpListener`]: super::tcp::TcpListener
+//! [`TcpStream`]: super::tcp::TcpStream
+//! [`UdpSocket`]: super::udp::UdpSocket
+
+use super::{Namespace, SocketAddr};
+use crate::{
+    bindings, c_types,
+    error::{to_result, Error, Result},
+    sync::Ref,
+    workqueue::{Queue, WorkAdapter},
+    ARef,
+};
+use alloc::boxed::Box;
+use core::{
+    mem, ptr,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// An event of a socket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Data was received, or a connection is ready to be accepted by a listening socket.
+    DataReady,
+
+    /// The state of the socket changed, e.g., the connection was established or closed.
+    StateChange,
+
+    /// Space became available in the send buffer.
+    WriteSpace,
+}
+
+/// Handles the events of a socket.
+///
+/// It is implemented for closures that take an [`Event`].
+pub trait Callbacks: Send + Sync + 'static {
+    /// Called on each event of the socket.
+    ///
+    /// It is called from softirq context, so it must not sleep.
+    fn event(&self, event: Event);
+}
+
+impl<F: Fn(Event) + Send + Sync + 'static> Callbacks for F {
+    fn event(&self, event: Event) {
+        self(event)
+    }
+}
+
+/// Callbacks that queue a work item on each event.
+///
+/// The work item runs in process context, where it can read from or write to the socket. Events
+/// that occur while it is pending are coalesced.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::net::{socket::WorkCallbacks, tcp::TcpStream};
+/// use kernel::sync::UniqueRef;
+/// use kernel::workqueue::{self, Work};
+///
+/// struct Watcher {
+///     work: Work,
+/// }
+///
+/// kernel::impl_self_work_adapter!(Watcher, work, |_| {
+///     pr_info!("Something happened on the stream\n");
+/// });
+///
+/// fn watch(stream: &mut TcpStream) -> Result {
+///     let watcher = UniqueRef::try_new(Watcher {
+///         // SAFETY: `work` is initialised below.
+///         work: unsafe { Work::new() },
+///     })?;
+///     kernel::init_work_item!(&watcher);
+///     stream.set_callbacks(WorkCallbacks::new(watcher.into(), workqueue::system()))
+/// }
+/// ```
+pub struct WorkCallbacks<T: WorkAdapter<Target = T> + Send + Sync + 'static> {
+    work: Ref<T>,
+    queue: &'static Queue,
+}
+
+impl<T: WorkAdapter<Target = T> + Send + Sync + 'static> WorkCallbacks<T> {
+    /// Creates callbacks that queue `work` on `queue`.
+    pub fn new(work: Ref<T>, queue: &'static Queue) -> Self {
+        Self { work, queue }
+    }
+}
+
+impl<T: WorkAdapter<Target = T> + Send + Sync + 'static> Callbacks for WorkCallbacks<T> {
+    fn event(&self, _event: Event) {
+        self.queue.enqueue(self.work.clone());
+    }
+}
+
+type SockCallback = Option<unsafe extern "C" fn(*mut bindings::sock)>;
+
+/// The callbacks of a `struct sock` that are replaced when [`Callbacks`] are attached.
+#[derive(Clone, Copy)]
+struct SockCallbacks {
+    data_ready: SockCallback,
+    state_change: SockCallback,
+    write_space: SockCallback,
+}
+
+impl SockCallbacks {
+    /// Saves the current callbacks of `sk`.
+    ///
+    /// # Safety
+    ///
+    /// `sk` must be valid and its callback lock must be held.
+    unsafe fn save(sk: *mut bindings::sock) -> Self {
+        // SAFETY: `sk` is valid by the safety requirements.
+        unsafe {
+            Self {
+                data_ready: (*sk).sk_data_ready,
+                state_change: (*sk).sk_state_change,
+                write_space: (*sk).sk_write_space,
+            }
+        }
+    }
+
+    /// Restores the callbacks of `sk` and detaches its user data.
+    ///
+    /// # Safety
+    ///
+    /// `sk` must be valid and its callback lock must be held for writing.
+    unsafe fn restore(&self, sk: *mut bindings::sock) {
+        // SAFETY: `sk` is valid by the safety requirements.
+        unsafe {
+            (*sk).sk_data_ready = self.data_ready;
+            (*sk).sk_state_change = self.state_change;
+            (*sk).sk_write_space = self.write_space;
+            (*sk).sk_user_data = ptr::null_mut();
+        }
+    }
+}
+
+struct CallbackState {
+    callbacks: Box<dyn Callbacks>,
+    original: SockCallbacks,
+}
+
+/// The original callbacks of the sockets created by listeners.
+///
+/// These sockets inherit the callbacks of the listener until they are accepted, but the user data
+/// they also inherit may be freed by then, so the original callbacks are saved here when callbacks
+/// are attached to a listener. They are the same for all TCP sockets.
+struct ChildCallbacks {
+    data_ready: AtomicUsize,
+    state_change: AtomicUsize,
+    write_space: AtomicUsize,
+}
+
+static CHILD_CALLBACKS: ChildCallbacks = ChildCallbacks {
+    data_ready: AtomicUsize::new(0),
+    state_change: AtomicUsize::new(0),
+    write_space: AtomicUsize::new(0),
+};
+
+impl ChildCallbacks {
+    fn save(&self, original: &SockCallbacks) {
+        let raw = |f: SockCallback| f.map_or(0, |f| f as usize);
+        self.data_ready
+            .store(raw(original.data_ready), Ordering::Release);
+        self.state_change
+            .store(raw(original.state_change), Ordering::Release);
+        self.write_space
+            .store(raw(original.write_space), Ordering::Release);
+    }
+
+    fn get(&self, event: Event) -> SockCallback {
+        let f = match event {
+            Event::DataReady => &self.data_ready,
+            Event::StateChange => &self.state_change,
+            Event::WriteSpace => &self.write_space,
+        }
+        .load(Ordering::Acquire);
+        if f == 0 {
+            return None;
+        }
+
+        // SAFETY: Non-zero values were stored by `save` from callbacks of this type.
+        Some(unsafe { mem::transmute::<usize, unsafe extern "C" fn(*mut bindings::sock)>(f) })
+    }
+}
+
+/// A kernel socket.
+///
+/// # Invariants
+///
+/// `sock` is a valid socket owned by `self`, in namespace `ns`. If `callbacks` is set, it is the
+/// user data of the socket, whose callbacks were saved in `original`.
+pub(crate) struct Socket {
+    sock: *mut bindings::socket,
+    callbacks: Option<Box<CallbackState>>,
+    original: Option<SockCallbacks>,
+    // Kernel sockets don't hold a reference to their namespace, so it's held here instead. It is
+    // dropped after the socket is released in `drop`.
+    ns: ARef<Namespace>,
+}
+
+// SAFETY: Kernel sockets can be used and released from any thread.
+unsafe impl Send for Socket {}
+
+// SAFETY: The socket functions that take a shared reference lock the socket as needed.
+unsafe impl Sync for Socket {}
+
+fn to_len(ret: c_types::c_int) -> Result<usize> {
+    if ret < 0 {
+        Err(Error::from_kernel_errno(ret))
+    } else {
+        Ok(ret as _)
+    }
+}
+
+impl Socket {
+    /// Creates a socket in `ns`, which is kept alive until the socket is dropped.
+    pub(crate) fn new(
+        ns: &Namespace,
+        family: c_types::c_int,
+        type_: c_types::c_int,
+        proto: c_types::c_int,
+    ) -> Result<Self> {
+        let mut sock = ptr::null_mut();
+        // SAFETY: `ns` is valid, and `sock` is valid for writes.
+        to_result(|| unsafe {
+            bindings::sock_create_kern(ns.0.get(), family, type_, proto, &mut sock)
+        })?;
+
+        // INVARIANT: `sock` was created above in `ns`.
+        Ok(Self {
+            sock,
+            callbacks: None,
+            original: None,
+            ns: ns.into(),
+        })
+    }
+
+    fn sk(&self) -> *mut bindings::sock {
+        // SAFETY: By the type invariants, `sock` is valid.
+        unsafe { (*self.sock).sk }
+    }
+
+    /// Allows the address to be reused while connections of a previous socket linger.
+    pub(crate) fn set_reuseaddr(&self) {
+        // SAFETY: `sk` is valid.
+        unsafe { bindings::sock_set_reuseaddr(self.sk()) };
+    }
+
+    pub(crate) fn bind(&self, addr: &SocketAddr) -> Result {
+        let (addr, len) = addr.as_raw();
+        // SAFETY: By the type invariants, `sock` is valid, and `addr` is valid for `len` bytes.
+        to_result(|| unsafe { bindings::kernel_bind(self.sock, addr, len) })
+    }
+
+    pub(crate) fn listen(&self, backlog: c_types::c_int) -> Result {
+        // SAFETY: By the type invariants, `sock` is valid.
+        to_result(|| unsafe { bindings::kernel_listen(self.sock, backlog) })
+    }
+
+    pub(crate) fn connect(&self, addr: &SocketAddr) -> Result {
+        let (addr, len) = addr.as_raw();
+        // SAFETY: By the type invariants, `sock` is valid, and `addr` is valid for `len` bytes.
+        to_result(|| unsafe { bindings::kernel_connect(self.sock, addr, len, 0) })
+    }
+
+    pub(crate) fn accept(&self, block: bool) -> Result<Self> {
+        let mut new = ptr::null_mut();
+        let flags = if block { 0 } else { bindings::O_NONBLOCK as _ };
+        // SAFETY: By the type invariants, `sock` is valid, and `new` is valid for writes.
+        to_result(|| unsafe { bindings::kernel_accept(self.sock, &mut new, flags) })?;
+
+        // INVARIANT: `new` was accepted above in the namespace of the listener, and has no
+        // callbacks of its own yet.
+        let new = Self {
+            sock: new,
+            callbacks: None,
+            original: None,
+            ns: self.ns.clone(),
+        };
+
+        // Accepted sockets inherit the callbacks and user data of the listener, which are only
+        // meant for the listener.
+        if let Some(original) = &self.original {
+            let sk = new.sk();
+            // SAFETY: `sk` is valid. The user data of the new socket can only have come from the
+            // listener, whose callbacks were saved in `original`.
+            unsafe {
+                bindings::write_lock_bh(ptr::addr_of_mut!((*sk).sk_callback_lock));
+                if !(*sk).sk_user_data.is_null() {
+                    original.restore(sk);
+                }
+                bindings::write_unlock_bh(ptr::addr_of_mut!((*sk).sk_callback_lock));
+            }
+        }
+        Ok(new)
+    }
+
+    fn name(
+        &self,
+        f: unsafe extern "C" fn(*mut bindings::socket, *mut bindings::sockaddr) -> c_types::c_int,
+    ) -> Result<SocketAddr> {
+        // SAFETY: The address storage is a C struct, for which zero is a valid value.
+        let mut storage: bindings::__kernel_sockaddr_storage = unsafe { mem::zeroed() };
+        // SAFETY: By the type invariants, `sock` is valid, and `storage` is large enough for any
+        // address.
+        to_result(|| unsafe { f(self.sock, ptr::addr_of_mut!(storage).cast()) })?;
+
+        // SAFETY: `storage` is valid for reads.
+        unsafe { SocketAddr::from_raw(&storage) }
+    }
+
+    pub(crate) fn local_addr(&self) -> Result<SocketAddr> {
+        self.name(bindings::kernel_getsockname)
+    }
+
+    pub(crate) fn peer_addr(&self) -> Result<SocketAddr> {
+        self.name(bindings::kernel_getpeername)
+    }
+
+    fn recvmsg(
+        &self,
+        buf: &mut [u8],
+        block: bool,
+        addr: *mut bindings::__kernel_sockaddr_storage,
+    ) -> Result<usize> {
+        let mut msg = bindings::msghdr::default();
+        if !addr.is_null() {
+            msg.msg_name = addr.cast();
+            msg.msg_namelen = mem::size_of::<bindings::__kernel_sockaddr_storage>() as _;
+        }
+        let mut vec = bindings::kvec {
+            iov_base: buf.as_mut_ptr().cast(),
+            iov_len: buf.len(),
+        };
+        let flags = if block {
+            0
+        } else {
+            bindings::MSG_DONTWAIT as _
+        };
+        // SAFETY: By the type invariants, `sock` is valid, `vec` describes `buf`, and the name of
+        // `msg` is either null or valid for writes of its length.
+        to_len(unsafe {
+            bindings::kernel_recvmsg(self.sock, &mut msg, &mut vec, 1, buf.len(), flags)
+        })
+    }
+
+    pub(crate) fn recv(&self, buf: &mut [u8], block: bool) -> Result<usize> {
+        self.recvmsg(buf, block, ptr::null_mut())
+    }
+
+    pub(crate) fn recv_from(&self, buf: &mut [u8], block: bool) -> Result<(usize, SocketAddr)> {
+        // SAFETY: The address storage is a C struct, for which zero is a valid value.
+        let mut storage: bindings::__kernel_sockaddr_storage = unsafe { mem::zeroed() };
+        let len = self.recvmsg(buf, block, &mut storage)?;
+
+        // SAFETY: `storage` is valid for reads.
+        Ok((len, unsafe { SocketAddr::from_raw(&storage) }?))
+    }
+
+    pub(crate) fn send_to(
+        &self,
+        buf: &[u8],
+        block: bool,
+        addr: Option<&SocketAddr>,
+    ) -> Result<usize> {
+        let mut msg = bindings::msghdr::default();
+        if let Some(addr) = addr {
+            let (name, len) = addr.as_raw();
+            msg.msg_name = name.cast();
+            msg.msg_namelen = len;
+        }
+        if !block {
+            msg.msg_flags = bindings::MSG_DONTWAIT as _;
+        }
+        let mut vec = bindings::kvec {
+            iov_base: buf.as_ptr() as *mut _,
+            iov_len: buf.len(),
+        };
+        // SAFETY: By the type invariants, `sock` is valid, `vec` describes `buf`, which is only
+        // read from, and the name of `msg` is either null or a valid address.
+        to_len(unsafe { bindings::kernel_sendmsg(self.sock, &mut msg, &mut vec, 1, buf.len()) })
+    }
+
+    /// Attaches `callbacks` to the socket, replacing any that were attached before.
+    ///
+    /// `LISTENER` must be set for listening sockets.
+    pub(crate) fn set_callbacks<const LISTENER: bool>(
+        &mut self,
+        callbacks: impl Callbacks,
+    ) -> Result {
+        let mut state = Box::try_new(CallbackState {
+            callbacks: Box::try_new(callbacks)?,
+            original: SockCallbacks {
+                data_ready: None,
+                state_change: None,
+                write_space: None,
+            },
+        })?;
+        self.clear_callbacks();
+
+        let sk = self.sk();
+        // SAFETY: `sk` is valid, and its callbacks are updated with the lock held. The state is
+        // boxed, so its address doesn't change when it is moved into `self`.
+        unsafe {
+            bindings::write_lock_bh(ptr::addr_of_mut!((*sk).sk_callback_lock));
+            state.original = SockCallbacks::save(sk);
+            if LISTENER {
+                CHILD_CALLBACKS.save(&state.original);
+            }
+            (*sk).sk_user_data = &*state as *const CallbackState as *mut _;
+            (*sk).sk_data_ready = Some(data_ready_callback::<LISTENER>);
+            (*sk).sk_state_change = Some(state_change_callback::<LISTENER>);
+            (*sk).sk_write_space = Some(write_space_callback::<LISTENER>);
+            bindings::write_unlock_bh(ptr::addr_of_mut!((*sk).sk_callback_lock));
+        }
+
+        // INVARIANT: The state was made the user data of the socket above.
+        self.original = Some(state.original);
+        self.callbacks = Some(state);
+        Ok(())
+    }
+
+    /// Detaches the callbacks from the socket, if any.
+    pub(crate) fn clear_callbacks(&mut self) {
+        let state = match self.callbacks.take() {
+            Some(state) => state,
+            None => return,
+        };
+
+        let sk = self.sk();
+        // SAFETY: `sk` is valid, and its callbacks are restored with the lock held.
+        unsafe {
+            bindings::write_lock_bh(ptr::addr_of_mut!((*sk).sk_callback_lock));
+            state.original.restore(sk);
+            bindings::write_unlock_bh(ptr::addr_of_mut!((*sk).sk_callback_lock));
+        }
+
+        // Callbacks hold the lock while they use the state, so none of them is using it anymore.
+        drop(state);
+    }
+}
+
+impl Drop for Socket {
+    fn drop(&mut self) {
+        self.clear_callbacks();
+        // SAFETY: By the type invariants, `sock` is owned by `self`.
+        unsafe { bindings::sock_release(self.sock) };
+    }
+}
+
+/// Calls the callbacks attached to `sk`, followed by the original ones.
+///
+/// # Safety
+///
+/// `sk` must be valid.
+unsafe fn dispatch<const LISTENER: bool>(sk: *mut bindings::sock, event: Event) {
+    // Sockets created by a listener inherit its callbacks and user data until they are accepted.
+    // The user data may be gone by then, so it is only used by the listener itself; the others
+    // just get their original callbacks.
+    if LISTENER {
+        // SAFETY: `sk` is valid by the safety requirements.
+        let state = unsafe { ptr::read_volatile(ptr::addr_of!((*sk).__sk_common.skc_state)) };
+        if u32::from(state) != bindings::TCP_LISTEN {
+            if let Some(f) = CHILD_CALLBACKS.get(event) {
+                // SAFETY: `sk` is valid, and `f` is one of the original callbacks of the listener
+                // it was created by, which it inherited.
+                unsafe { f(sk) };
+            }
+            return;
+        }
+    }
+
+    // SAFETY: `sk` is valid by the safety requirements.
+    let lock = unsafe { ptr::addr_of_mut!((*sk).sk_callback_lock) };
+    // SAFETY: `lock` is valid.
+    unsafe { bindings::read_lock_bh(lock) };
+
+    // SAFETY: `sk` is valid by the safety requirements.
+    let state = unsafe { (*sk).sk_user_data } as *const CallbackState;
+    if !state.is_null() {
+        // SAFETY: The user data is a `CallbackState` attached by `Socket::set_callbacks`, which
+        // is only freed after it is detached with the lock held for writing.
+        let state = unsafe { &*state };
+        state.callbacks.event(event);
+
+        let original = match event {
+            Event::DataReady => state.original.data_ready,
+            Event::StateChange => state.original.state_change,
+            Event::WriteSpace => state.original.write_space,
+        };
+        if let Some(f) = original {
+            // SAFETY: `sk` is valid, and `f` is one of its original callbacks.
+            unsafe { f(sk) };
+        }
+    }
+
+    // SAFETY: The lock was acquired above.
+    unsafe { bindings::read_unlock_bh(lock) };
+}
+
+unsafe extern "C" fn data_ready_callback<const LISTENER: bool>(sk: *mut bindings::sock) {
+    // SAFETY: `sk` is valid while its callbacks run.
+    unsafe { dispatch::<LISTENER>(sk, Event::DataReady) };
+}
+
+unsafe extern "C" fn state_change_callback<const LISTENER: bool>(sk: *mut bindings::sock) {
+    // SAFETY: `sk` is valid while its callbacks run.
+    unsafe { dispatch::<LISTENER>(sk, Event::StateChange) };
+}
+
+unsafe extern "C" fn write_space_callback<const LISTENER: bool>(sk: *mut bindings::sock) {
+    // SAFETY: `sk` is valid while its callbacks run.
+    unsafe { dispatch::<LISTENER>(sk, Event::WriteSpace) };
+}
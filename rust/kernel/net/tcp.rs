@@ -0,0 +1,175 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! TCP sockets.
+//!
+//! C header: [`include/linux/net.h`](../../../../include/linux/net.h)
+//!
+//! # Examples
+//!
+//! An echo server that accepts and serves connections from work items, which are queued when a
+//! connection is pending or data was received:
+//!
+//! ```
+//! # use kernel::prelude::*;
+//! use kernel::net::{self, socket::Callbacks, socket::Event, tcp::TcpListener, tcp::TcpStream};
+//! use kernel::net::{SocketAddr, SocketAddrV4};
+//!
+//! fn listen() -> Result<TcpListener> {
+//!     let addr = SocketAddr::V4(SocketAddrV4::new(net::Ipv4Addr::ANY, 7));
+//!     let mut listener = TcpListener::try_new(net::init_ns(), &addr)?;
+//!     listener.set_callbacks(|event| {
+//!         if event == Event::DataReady {
+//!             // Queue a work item that calls `accept_all`.
+//!         }
+//!     })?;
+//!     Ok(listener)
+//! }
+//!
+//! fn accept_all(listener: &TcpListener, new: &mut Vec<TcpStream>) -> Result {
+//!     while let Ok(stream) = listener.accept(false) {
+//!         new.try_push(stream)?;
+//!     }
+//!     Ok(())
+//! }
+//!
+//! fn echo(stream: &TcpStream) -> Result<bool> {
+//!     let mut buf = [0u8; 1024];
+//!     loop {
+//!         let n = match stream.read(&mut buf, false) {
+//!             Ok(0) => return Ok(false),
+//!             Ok(n) => n,
+//!             Err(EAGAIN) => return Ok(true),
+//!             Err(e) => return Err(e),
+//!         };
+//!         stream.write_all(&buf[..n], true)?;
+//!     }
+//! }
+//! ```
+
+use super::{
+    socket::{Callbacks, Socket},
+    Namespace, SocketAddr,
+};
+use crate::{bindings, error::code::*, Result};
+
+/// A listening TCP socket.
+pub struct TcpListener {
+    sock: Socket,
+}
+
+impl TcpListener {
+    /// Creates a socket in `ns` that listens for connections on `addr`.
+    ///
+    /// The address may be reused while connections of a previous listener linger.
+    pub fn try_new(ns: &Namespace, addr: &SocketAddr) -> Result<Self> {
+        let sock = Socket::new(
+            ns,
+            addr.family(),
+            bindings::sock_type_SOCK_STREAM as _,
+            bindings::IPPROTO_TCP as _,
+        )?;
+        sock.set_reuseaddr();
+        sock.bind(addr)?;
+        sock.listen(bindings::SOMAXCONN as _)?;
+        Ok(Self { sock })
+    }
+
+    /// Returns the address the socket is listening on, e.g., to find the port chosen by the
+    /// kernel when listening on port zero.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.sock.local_addr()
+    }
+
+    /// Accepts a connection.
+    ///
+    /// If `block` is `false` and no connection is pending, it fails with [`EAGAIN`].
+    pub fn accept(&self, block: bool) -> Result<TcpStream> {
+        Ok(TcpStream {
+            sock: self.sock.accept(block)?,
+        })
+    }
+
+    /// Attaches `callbacks` to the socket, replacing any that were attached before.
+    ///
+    /// [`Event::DataReady`] is reported when a connection is pending. Accepted connections don't
+    /// inherit the callbacks.
+    ///
+    /// [`Event::DataReady`]: super::socket::Event::DataReady
+    pub fn set_callbacks(&mut self, callbacks: impl Callbacks) -> Result {
+        self.sock.set_callbacks::<true>(callbacks)
+    }
+
+    /// Detaches the callbacks from the socket, if any.
+    pub fn clear_callbacks(&mut self) {
+        self.sock.clear_callbacks();
+    }
+}
+
+/// A connected TCP socket.
+///
+/// The connection is closed when it is dropped.
+pub struct TcpStream {
+    sock: Socket,
+}
+
+impl TcpStream {
+    /// Creates a socket in `ns` and connects it to `addr`.
+    pub fn connect(ns: &Namespace, addr: &SocketAddr) -> Result<Self> {
+        let sock = Socket::new(
+            ns,
+            addr.family(),
+            bindings::sock_type_SOCK_STREAM as _,
+            bindings::IPPROTO_TCP as _,
+        )?;
+        sock.connect(addr)?;
+        Ok(Self { sock })
+    }
+
+    /// Returns the local address of the connection.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.sock.local_addr()
+    }
+
+    /// Returns the address of the peer.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.sock.peer_addr()
+    }
+
+    /// Reads data into `buf`, returning the number of bytes read.
+    ///
+    /// It returns zero once the peer has closed the connection. If `block` is `false` and no data
+    /// is available, it fails with [`EAGAIN`].
+    pub fn read(&self, buf: &mut [u8], block: bool) -> Result<usize> {
+        self.sock.recv(buf, block)
+    }
+
+    /// Writes data from `buf`, returning the number of bytes written.
+    ///
+    /// If `block` is `false` and the send buffer is full, it fails with [`EAGAIN`].
+    pub fn write(&self, buf: &[u8], block: bool) -> Result<usize> {
+        self.sock.send_to(buf, block, None)
+    }
+
+    /// Writes all of `buf`.
+    ///
+    /// If `block` is `false`, it may fail with [`EAGAIN`] after part of `buf` was written.
+    pub fn write_all(&self, mut buf: &[u8], block: bool) -> Result {
+        while !buf.is_empty() {
+            match self.write(buf, block)? {
+                0 => return Err(EPIPE),
+                n => buf = &buf[n..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Attaches `callbacks` to the socket, replacing any that were attached before.
+    pub fn set_callbacks(&mut self, callbacks: impl Callbacks) -> Result {
+        self.sock.set_callbacks::<false>(callbacks)
+    }
+
+    /// Detaches the callbacks from the socket, if any.
+    pub fn clear_callbacks(&mut self) {
+        self.sock.clear_callbacks();
+    }
+}
@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! UDP sockets.
+//!
+//! C header: [`include/linux/net.h`](../../../../include/linux/net.h)
+//!
+//! # Examples
+//!
+//! ```
+//! # use kernel::prelude::*;
+//! use kernel::net::{self, udp::UdpSocket, SocketAddr, SocketAddrV4};
+//!
+//! // Replies to each pending datagram with its length.
+//! fn serve(sock: &UdpSocket) -> Result {
+//!     let mut buf = [0u8; 1500];
+//!     while let Ok((n, peer)) = sock.recv_from(&mut buf, false) {
+//!         sock.send_to(&(n as u32).to_be_bytes(), &peer, false)?;
+//!     }
+//!     Ok(())
+//! }
+//!
+//! fn bind() -> Result<UdpSocket> {
+//!     let addr = SocketAddr::V4(SocketAddrV4::new(net::Ipv4Addr::ANY, 5000));
+//!     UdpSocket::bind(net::init_ns(), &addr)
+//! }
+//! ```
+
+use super::{
+    socket::{Callbacks, Socket},
+    Namespace, SocketAddr,
+};
+use crate::{bindings, Result};
+
+/// A UDP socket.
+pub struct UdpSocket {
+    sock: Socket,
+}
+
+impl UdpSocket {
+    /// Creates a socket in `ns` bound to `addr`.
+    pub fn bind(ns: &Namespace, addr: &SocketAddr) -> Result<Self> {
+        let sock = Socket::new(
+            ns,
+            addr.family(),
+            bindings::sock_type_SOCK_DGRAM as _,
+            bindings::IPPROTO_UDP as _,
+        )?;
+        sock.bind(addr)?;
+        Ok(Self { sock })
+    }
+
+    /// Returns the address the socket is bound to, e.g., to find the port chosen by the kernel
+    /// when binding to port zero.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.sock.local_addr()
+    }
+
+    /// Sets the default destination of datagrams, and only receives datagrams from it.
+    pub fn connect(&self, addr: &SocketAddr) -> Result {
+        self.sock.connect(addr)
+    }
+
+    /// Receives a datagram into `buf`, returning its length and sender.
+    ///
+    /// Datagrams longer than `buf` are truncated. If `block` is `false` and no datagram is
+    /// available, it fails with [`EAGAIN`].
+    ///
+    /// [`EAGAIN`]: crate::error::code::EAGAIN
+    pub fn recv_from(&self, buf: &mut [u8], block: bool) -> Result<(usize, SocketAddr)> {
+        self.sock.recv_from(buf, block)
+    }
+
+    /// Sends `buf` as a datagram to `addr`.
+    pub fn send_to(&self, buf: &[u8], addr: &SocketAddr, block: bool) -> Result<usize> {
+        self.sock.send_to(buf, block, Some(addr))
+    }
+
+    /// Sends `buf` as a datagram to the destination set by [`UdpSocket::connect`].
+    pub fn send(&self, buf: &[u8], block: bool) -> Result<usize> {
+        self.sock.send_to(buf, block, None)
+    }
+
+    /// Attaches `callbacks` to the socket, replacing any that were attached before.
+    pub fn set_callbacks(&mut self, callbacks: impl Callbacks) -> Result {
+        self.sock.set_callbacks::<false>(callbacks)
+    }
+
+    /// Detaches the callbacks from the socket, if any.
+    pub fn clear_callbacks(&mut self) {
+        self.sock.clear_callbacks();
+    }
+}
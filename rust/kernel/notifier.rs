@@ -0,0 +1,710 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Notifier chains.
+//!
+//! A notifier chain is a list of callbacks that are called, in priority order, when an event
+//! happens. Callbacks can be registered on the kernel's chains (e.g., [`Reboot`]) with a
+//! [`Registration`], which decodes the raw events into a type specific to each chain. New chains
+//! can also be created with [`BlockingChain`] and [`AtomicChain`].
+//!
+//! C header: [`include/linux/notifier.h`](../../../../include/linux/notifier.h)
+
+use crate::{bindings, c_types, str::CStr, sync::NeedsLockClass, to_result, Opaque, Result};
+use alloc::boxed::Box;
+use core::{marker::PhantomPinned, pin::Pin, ptr};
+
+/// The value returned by notifier callbacks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotifyResult {
+    /// The callback is not interested in the event.
+    Done,
+
+    /// The callback handled the event.
+    Ok,
+
+    /// The callback vetoes the event (e.g., a suspend), which stops the chain.
+    Bad,
+
+    /// The callback handled the event and the remaining callbacks must not be called.
+    Stop,
+}
+
+impl NotifyResult {
+    fn to_raw(self) -> c_types::c_int {
+        (match self {
+            Self::Done => bindings::NOTIFY_DONE,
+            Self::Ok => bindings::NOTIFY_OK,
+            Self::Bad => bindings::NOTIFY_BAD,
+            Self::Stop => bindings::NOTIFY_STOP,
+        }) as _
+    }
+
+    fn from_raw(ret: c_types::c_int) -> Self {
+        match ret as u32 {
+            bindings::NOTIFY_DONE => Self::Done,
+            bindings::NOTIFY_OK => Self::Ok,
+            bindings::NOTIFY_STOP => Self::Stop,
+            r if r & bindings::NOTIFY_STOP_MASK != 0 => Self::Bad,
+            _ => Self::Ok,
+        }
+    }
+}
+
+/// A notifier chain on which callbacks can be registered.
+///
+/// # Safety
+///
+/// Implementers must ensure that `register` and `unregister` add and remove the notifier block
+/// to and from the same chain, and that the chain only calls it with actions and data that
+/// `event` can decode.
+pub unsafe trait Chain: Sync {
+    /// The type of events the chain notifies about.
+    type Event<'a>;
+
+    /// Decodes the raw action and data passed to callbacks.
+    ///
+    /// # Safety
+    ///
+    /// `action` and `data` must come from a call to the chain's callbacks, and the returned event
+    /// must not outlive that call.
+    unsafe fn event<'a>(action: c_types::c_ulong, data: *mut c_types::c_void) -> Self::Event<'a>;
+
+    /// Adds the notifier block to the chain.
+    ///
+    /// # Safety
+    ///
+    /// `nb` must be valid until it is removed with [`Chain::unregister`].
+    unsafe fn register(&self, nb: *mut bindings::notifier_block) -> c_types::c_int;
+
+    /// Removes the notifier block from the chain, and waits for running callbacks to complete.
+    ///
+    /// # Safety
+    ///
+    /// `nb` must have been added to the chain with [`Chain::register`].
+    unsafe fn unregister(&self, nb: *mut bindings::notifier_block);
+}
+
+/// A callback registered on a notifier chain.
+///
+/// The callback is removed from the chain when the registration is dropped. It must be `'static`
+/// because a registration that is leaked is never removed.
+///
+/// # Invariants
+///
+/// If `registered` is `true`, `nb` is in `chain`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::notifier::{NotifyResult, Reboot, RebootEvent, Registration};
+///
+/// fn example() -> Result {
+///     let _reg = Registration::new_pinned(&Reboot, 0, |event| {
+///         if event == RebootEvent::PowerOff {
+///             pr_info!("powering off the device\n");
+///         }
+///         NotifyResult::Done
+///     })?;
+///     // The callback is removed when `_reg` goes out of scope.
+///     Ok(())
+/// }
+/// ```
+pub struct Registration<'a, C: Chain, F> {
+    nb: Opaque<bindings::notifier_block>,
+    chain: &'a C,
+    registered: bool,
+    func: F,
+    _pin: PhantomPinned,
+}
+
+// SAFETY: The registration can be dropped from any thread, and the closure is moved with it.
+unsafe impl<C: Chain, F: Send> Send for Registration<'_, C, F> {}
+
+// SAFETY: The closure may be called concurrently while other threads hold shared references to
+// the registration, so it must be `Sync`.
+unsafe impl<C: Chain, F: Sync> Sync for Registration<'_, C, F> {}
+
+impl<'a, C: Chain, F: Fn(C::Event<'_>) -> NotifyResult + Sync + 'static> Registration<'a, C, F> {
+    /// Registers `func` on the given chain.
+    ///
+    /// Callbacks with higher `priority` are called first. Returns a pinned heap-allocated
+    /// representation of the registration.
+    pub fn new_pinned(chain: &'a C, priority: i32, func: F) -> Result<Pin<Box<Self>>> {
+        let mut reg = Pin::from(Box::try_new(Self {
+            nb: Opaque::new(bindings::notifier_block {
+                notifier_call: Some(Self::notifier_call),
+                next: ptr::null_mut(),
+                priority,
+            }),
+            chain,
+            registered: false,
+            func,
+            _pin: PhantomPinned,
+        })?);
+
+        // SAFETY: `nb` is pinned, and it is removed from the chain when the registration is
+        // dropped.
+        to_result(|| unsafe { chain.register(reg.nb.get()) })?;
+
+        // INVARIANT: `nb` was added to `chain` above.
+        // SAFETY: `registered` is not structurally pinned.
+        unsafe { reg.as_mut().get_unchecked_mut().registered = true };
+        Ok(reg)
+    }
+
+    unsafe extern "C" fn notifier_call(
+        nb: *mut bindings::notifier_block,
+        action: c_types::c_ulong,
+        data: *mut c_types::c_void,
+    ) -> c_types::c_int {
+        // SAFETY: `nb` is the `nb` field of a `Registration`, which is pinned and remains valid
+        // while it is in the chain.
+        let reg = unsafe { &*crate::container_of!(nb, Self, nb) };
+
+        // SAFETY: `action` and `data` come from the chain, and the event doesn't outlive this
+        // call.
+        let event = unsafe { C::event(action, data) };
+        (reg.func)(event).to_raw()
+    }
+}
+
+impl<C: Chain, F> Drop for Registration<'_, C, F> {
+    fn drop(&mut self) {
+        if self.registered {
+            // SAFETY: By the type invariants, `nb` is in `chain`.
+            unsafe { self.chain.unregister(self.nb.get()) };
+        }
+    }
+}
+
+/// The chain notified when the system is about to restart, halt or power off.
+///
+/// Callbacks run in process context, so they may sleep, e.g., to quiesce hardware.
+pub struct Reboot;
+
+/// An event of the [`Reboot`] chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RebootEvent {
+    /// The system is restarting.
+    Restart,
+
+    /// The system is halting.
+    Halt,
+
+    /// The system is powering off.
+    PowerOff,
+}
+
+// SAFETY: The reboot chain is only called with the `SYS_*` actions and no data.
+unsafe impl Chain for Reboot {
+    type Event<'a> = RebootEvent;
+
+    unsafe fn event<'a>(action: c_types::c_ulong, _: *mut c_types::c_void) -> RebootEvent {
+        match action as u32 {
+            bindings::SYS_HALT => RebootEvent::Halt,
+            bindings::SYS_POWER_OFF => RebootEvent::PowerOff,
+            _ => RebootEvent::Restart,
+        }
+    }
+
+    unsafe fn register(&self, nb: *mut bindings::notifier_block) -> c_types::c_int {
+        // SAFETY: The safety requirements guarantee that `nb` remains valid.
+        unsafe { bindings::register_reboot_notifier(nb) }
+    }
+
+    unsafe fn unregister(&self, nb: *mut bindings::notifier_block) {
+        // SAFETY: The safety requirements guarantee that `nb` is in the chain.
+        unsafe { bindings::unregister_reboot_notifier(nb) };
+    }
+}
+
+/// The chain notified when the kernel panics.
+///
+/// Callbacks run in atomic context with other CPUs stopped, so they must not sleep nor take locks
+/// that may be held elsewhere. They are meant to dump state (e.g., to a persistent log) and to put
+/// hardware in a safe state.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::notifier::{NotifyResult, Panic, Registration};
+///
+/// fn example() -> Result {
+///     let _reg = Registration::new_pinned(&Panic, 0, |event| {
+///         pr_emerg!("panic: {}\n", event.message);
+///         NotifyResult::Done
+///     })?;
+///     Ok(())
+/// }
+/// ```
+pub struct Panic;
+
+/// An event of the [`Panic`] chain.
+pub struct PanicEvent<'a> {
+    /// The panic message.
+    pub message: &'a CStr,
+}
+
+// SAFETY: The panic chain is only called with the panic message as data.
+unsafe impl Chain for Panic {
+    type Event<'a> = PanicEvent<'a>;
+
+    unsafe fn event<'a>(_: c_types::c_ulong, data: *mut c_types::c_void) -> PanicEvent<'a> {
+        // SAFETY: The safety requirements guarantee that `data` is the NUL-terminated panic
+        // message, which is valid for the duration of the call.
+        PanicEvent {
+            message: unsafe { CStr::from_char_ptr(data as _) },
+        }
+    }
+
+    unsafe fn register(&self, nb: *mut bindings::notifier_block) -> c_types::c_int {
+        // SAFETY: The safety requirements guarantee that `nb` remains valid. The list is only
+        // accessed through the chain functions, which synchronise access to it.
+        unsafe {
+            bindings::atomic_notifier_chain_register(
+                ptr::addr_of_mut!(bindings::panic_notifier_list),
+                nb,
+            )
+        }
+    }
+
+    unsafe fn unregister(&self, nb: *mut bindings::notifier_block) {
+        // SAFETY: The safety requirements guarantee that `nb` is in the chain.
+        unsafe {
+            bindings::atomic_notifier_chain_unregister(
+                ptr::addr_of_mut!(bindings::panic_notifier_list),
+                nb,
+            )
+        };
+    }
+}
+
+/// The chain notified about system suspend and hibernation.
+///
+/// Callbacks may veto the transition by returning [`NotifyResult::Bad`] for the `*Prepare`
+/// events.
+#[cfg(CONFIG_PM_SLEEP)]
+pub struct Pm;
+
+/// An event of the [`Pm`] chain.
+#[cfg(CONFIG_PM_SLEEP)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PmEvent {
+    /// The system is about to hibernate.
+    HibernationPrepare,
+
+    /// The system resumed from hibernation, or hibernation failed.
+    PostHibernation,
+
+    /// The system is about to suspend.
+    SuspendPrepare,
+
+    /// The system resumed from suspend, or suspend failed.
+    PostSuspend,
+
+    /// The system is about to restore a hibernation image.
+    RestorePrepare,
+
+    /// Restoring a hibernation image failed.
+    PostRestore,
+
+    /// An event not known to Rust.
+    Other(c_types::c_ulong),
+}
+
+// SAFETY: The PM chain is only called with the `PM_*` actions and no data.
+#[cfg(CONFIG_PM_SLEEP)]
+unsafe impl Chain for Pm {
+    type Event<'a> = PmEvent;
+
+    unsafe fn event<'a>(action: c_types::c_ulong, _: *mut c_types::c_void) -> PmEvent {
+        match action as u32 {
+            bindings::PM_HIBERNATION_PREPARE => PmEvent::HibernationPrepare,
+            bindings::PM_POST_HIBERNATION => PmEvent::PostHibernation,
+            bindings::PM_SUSPEND_PREPARE => PmEvent::SuspendPrepare,
+            bindings::PM_POST_SUSPEND => PmEvent::PostSuspend,
+            bindings::PM_RESTORE_PREPARE => PmEvent::RestorePrepare,
+            bindings::PM_POST_RESTORE => PmEvent::PostRestore,
+            _ => PmEvent::Other(action),
+        }
+    }
+
+    unsafe fn register(&self, nb: *mut bindings::notifier_block) -> c_types::c_int {
+        // SAFETY: The safety requirements guarantee that `nb` remains valid.
+        unsafe { bindings::register_pm_notifier(nb) }
+    }
+
+    unsafe fn unregister(&self, nb: *mut bindings::notifier_block) {
+        // SAFETY: The safety requirements guarantee that `nb` is in the chain.
+        unsafe { bindings::unregister_pm_notifier(nb) };
+    }
+}
+
+/// The chain notified when the system is out of memory, before the OOM killer is invoked.
+///
+/// Callbacks should release memory they can do without (e.g., caches) and report the number of
+/// pages they freed with [`OomEvent::add_freed`]. If any memory was freed, the allocation is
+/// retried instead of killing a task.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::notifier::{NotifyResult, Oom, Registration};
+///
+/// fn drop_cache() -> usize {
+///     // Free the cached pages and return how many there were.
+///     0
+/// }
+///
+/// fn example() -> Result {
+///     let _reg = Registration::new_pinned(&Oom, 0, |mut event| {
+///         event.add_freed(drop_cache());
+///         NotifyResult::Ok
+///     })?;
+///     Ok(())
+/// }
+/// ```
+pub struct Oom;
+
+/// An event of the [`Oom`] chain.
+pub struct OomEvent<'a> {
+    freed: &'a mut c_types::c_ulong,
+}
+
+impl OomEvent<'_> {
+    /// Reports that the callback freed the given number of pages.
+    pub fn add_freed(&mut self, pages: usize) {
+        *self.freed = self.freed.saturating_add(pages as _);
+    }
+
+    /// Returns the number of pages freed so far by the callbacks of the chain.
+    pub fn freed(&self) -> usize {
+        *self.freed as _
+    }
+}
+
+// SAFETY: The OOM chain is only called with a pointer to the count of freed pages as data.
+unsafe impl Chain for Oom {
+    type Event<'a> = OomEvent<'a>;
+
+    unsafe fn event<'a>(_: c_types::c_ulong, data: *mut c_types::c_void) -> OomEvent<'a> {
+        // SAFETY: The safety requirements guarantee that `data` points to the count of freed
+        // pages, which is only accessed by one callback at a time for the duration of the call.
+        OomEvent {
+            freed: unsafe { &mut *(data as *mut c_types::c_ulong) },
+        }
+    }
+
+    unsafe fn register(&self, nb: *mut bindings::notifier_block) -> c_types::c_int {
+        // SAFETY: The safety requirements guarantee that `nb` remains valid.
+        unsafe { bindings::register_oom_notifier(nb) }
+    }
+
+    unsafe fn unregister(&self, nb: *mut bindings::notifier_block) {
+        // SAFETY: The safety requirements guarantee that `nb` is in the chain.
+        unsafe { bindings::unregister_oom_notifier(nb) };
+    }
+}
+
+/// The chain notified about changes to network devices.
+///
+/// When a callback is registered, it is first called with [`NetdevAction::Register`] and
+/// [`NetdevAction::Up`] for the devices that already exist. Callbacks are called with the RTNL
+/// lock held.
+#[cfg(CONFIG_NET)]
+pub struct Netdevice;
+
+/// An action of the [`Netdevice`] chain.
+#[cfg(CONFIG_NET)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetdevAction {
+    /// The device was brought up.
+    Up,
+
+    /// The device is going down.
+    GoingDown,
+
+    /// The device was brought down.
+    Down,
+
+    /// The device was registered.
+    Register,
+
+    /// The device is being unregistered.
+    Unregister,
+
+    /// The state of the device changed (e.g., its flags).
+    Change,
+
+    /// The MTU of the device changed.
+    ChangeMtu,
+
+    /// The device was renamed.
+    ChangeName,
+
+    /// An action not known to Rust.
+    Other(c_types::c_ulong),
+}
+
+/// An event of the [`Netdevice`] chain.
+#[cfg(CONFIG_NET)]
+pub struct NetdevEvent<'a> {
+    /// What happened to the device.
+    pub action: NetdevAction,
+
+    /// The device.
+    pub dev: &'a crate::net::Device,
+}
+
+// SAFETY: The netdevice chain is only called with the `NETDEV_*` actions and a
+// `struct netdev_notifier_info` as data.
+#[cfg(CONFIG_NET)]
+unsafe impl Chain for Netdevice {
+    type Event<'a> = NetdevEvent<'a>;
+
+    unsafe fn event<'a>(action: c_types::c_ulong, data: *mut c_types::c_void) -> NetdevEvent<'a> {
+        let action = match action as u32 {
+            bindings::netdev_cmd_NETDEV_UP => NetdevAction::Up,
+            bindings::netdev_cmd_NETDEV_GOING_DOWN => NetdevAction::GoingDown,
+            bindings::netdev_cmd_NETDEV_DOWN => NetdevAction::Down,
+            bindings::netdev_cmd_NETDEV_REGISTER => NetdevAction::Register,
+            bindings::netdev_cmd_NETDEV_UNREGISTER => NetdevAction::Unregister,
+            bindings::netdev_cmd_NETDEV_CHANGE => NetdevAction::Change,
+            bindings::netdev_cmd_NETDEV_CHANGEMTU => NetdevAction::ChangeMtu,
+            bindings::netdev_cmd_NETDEV_CHANGENAME => NetdevAction::ChangeName,
+            _ => NetdevAction::Other(action),
+        };
+
+        // SAFETY: The safety requirements guarantee that `data` is a valid
+        // `struct netdev_notifier_info`, whose device is valid for the duration of the call.
+        // `net::Device` is transparent, so the cast is ok.
+        let dev = unsafe { &*(bindings::netdev_notifier_info_to_dev(data as _) as *const _) };
+        NetdevEvent { action, dev }
+    }
+
+    unsafe fn register(&self, nb: *mut bindings::notifier_block) -> c_types::c_int {
+        // SAFETY: The safety requirements guarantee that `nb` remains valid.
+        unsafe { bindings::register_netdevice_notifier(nb) }
+    }
+
+    unsafe fn unregister(&self, nb: *mut bindings::notifier_block) {
+        // SAFETY: The safety requirements guarantee that `nb` is in the chain.
+        unsafe { bindings::unregister_netdevice_notifier(nb) };
+    }
+}
+
+/// An event of a chain created with [`BlockingChain`] or [`AtomicChain`].
+///
+/// The meaning of the action and data is defined by the chain's users.
+#[derive(Clone, Copy, Debug)]
+pub struct RawEvent {
+    /// The action.
+    pub action: c_types::c_ulong,
+
+    /// The data, which may be null.
+    pub data: *mut c_types::c_void,
+}
+
+/// A new notifier chain whose callbacks run in process context, so they may sleep.
+///
+/// Wraps the kernel's `struct blocking_notifier_head`. C code can use it through
+/// [`BlockingChain::as_ptr`].
+///
+/// The chain must first be initialised with a call to [`BlockingChain::init`] (or the
+/// [`init_with_lockdep`] macro, or [`init_static_sync`] for statics) before it can be used.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::init_static_sync;
+/// use kernel::notifier::{BlockingChain, NotifyResult, RawEvent, Registration};
+///
+/// init_static_sync! {
+///     static CHAIN: BlockingChain;
+/// }
+///
+/// fn example() -> Result {
+///     let _reg = Registration::new_pinned(&CHAIN, 0, |event: RawEvent| {
+///         pr_info!("action {}\n", event.action);
+///         NotifyResult::Ok
+///     })?;
+///     CHAIN.call_chain(1, core::ptr::null_mut());
+///     Ok(())
+/// }
+/// ```
+///
+/// [`init_with_lockdep`]: crate::init_with_lockdep
+/// [`init_static_sync`]: crate::init_static_sync
+pub struct BlockingChain {
+    head: Opaque<bindings::blocking_notifier_head>,
+    _pin: PhantomPinned,
+}
+
+// SAFETY: The chain has its own locking, so it can be used from any thread.
+unsafe impl Send for BlockingChain {}
+
+// SAFETY: The chain functions are safe to call concurrently.
+unsafe impl Sync for BlockingChain {}
+
+impl BlockingChain {
+    /// Constructs a new chain.
+    ///
+    /// # Safety
+    ///
+    /// The caller must call [`BlockingChain::init`] before using the chain.
+    pub const unsafe fn new() -> Self {
+        Self {
+            head: Opaque::uninit(),
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Returns a raw pointer to the underlying `blocking_notifier_head`.
+    pub fn as_ptr(&self) -> *mut bindings::blocking_notifier_head {
+        self.head.get()
+    }
+
+    /// Calls the callbacks of the chain, in priority order, until one of them returns
+    /// [`NotifyResult::Bad`] or [`NotifyResult::Stop`].
+    ///
+    /// Returns the value returned by the last callback that was called, or
+    /// [`NotifyResult::Done`] if there are none. It may sleep.
+    pub fn call_chain(&self, action: c_types::c_ulong, data: *mut c_types::c_void) -> NotifyResult {
+        // SAFETY: The chain was initialised, as required by `new`. `data` is only passed on to
+        // the callbacks.
+        NotifyResult::from_raw(unsafe {
+            bindings::blocking_notifier_call_chain(self.head.get(), action, data)
+        })
+    }
+}
+
+impl NeedsLockClass for BlockingChain {
+    unsafe fn init(
+        self: Pin<&mut Self>,
+        name: &'static CStr,
+        key: *mut bindings::lock_class_key,
+        _: *mut bindings::lock_class_key,
+    ) {
+        let head = self.head.get();
+
+        // SAFETY: `head` is pinned and is being initialised here. The caller guarantees that
+        // `key` remains valid.
+        unsafe {
+            bindings::__init_rwsem(&mut (*head).rwsem, name.as_char_ptr(), key);
+            (*head).head = ptr::null_mut();
+        }
+    }
+}
+
+// SAFETY: Callbacks are only called by `call_chain`, with a `RawEvent` as is.
+unsafe impl Chain for BlockingChain {
+    type Event<'a> = RawEvent;
+
+    unsafe fn event<'a>(action: c_types::c_ulong, data: *mut c_types::c_void) -> RawEvent {
+        RawEvent { action, data }
+    }
+
+    unsafe fn register(&self, nb: *mut bindings::notifier_block) -> c_types::c_int {
+        // SAFETY: The safety requirements guarantee that `nb` remains valid.
+        unsafe { bindings::blocking_notifier_chain_register(self.head.get(), nb) }
+    }
+
+    unsafe fn unregister(&self, nb: *mut bindings::notifier_block) {
+        // SAFETY: The safety requirements guarantee that `nb` is in the chain.
+        unsafe { bindings::blocking_notifier_chain_unregister(self.head.get(), nb) };
+    }
+}
+
+/// A new notifier chain whose callbacks run in atomic context, so they must not sleep.
+///
+/// Wraps the kernel's `struct atomic_notifier_head`. The chain can be called from any context,
+/// including interrupt handlers. C code can use it through [`AtomicChain::as_ptr`].
+///
+/// The chain must first be initialised with a call to [`AtomicChain::init`] (or the
+/// [`init_with_lockdep`] macro, or [`init_static_sync`] for statics) before it can be used.
+///
+/// [`init_with_lockdep`]: crate::init_with_lockdep
+/// [`init_static_sync`]: crate::init_static_sync
+pub struct AtomicChain {
+    head: Opaque<bindings::atomic_notifier_head>,
+    _pin: PhantomPinned,
+}
+
+// SAFETY: The chain has its own locking, so it can be used from any thread.
+unsafe impl Send for AtomicChain {}
+
+// SAFETY: The chain functions are safe to call concurrently.
+unsafe impl Sync for AtomicChain {}
+
+impl AtomicChain {
+    /// Constructs a new chain.
+    ///
+    /// # Safety
+    ///
+    /// The caller must call [`AtomicChain::init`] before using the chain.
+    pub const unsafe fn new() -> Self {
+        Self {
+            head: Opaque::uninit(),
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Returns a raw pointer to the underlying `atomic_notifier_head`.
+    pub fn as_ptr(&self) -> *mut bindings::atomic_notifier_head {
+        self.head.get()
+    }
+
+    /// Calls the callbacks of the chain, in priority order, until one of them returns
+    /// [`NotifyResult::Bad`] or [`NotifyResult::Stop`].
+    ///
+    /// Returns the value returned by the last callback that was called, or
+    /// [`NotifyResult::Done`] if there are none.
+    pub fn call_chain(&self, action: c_types::c_ulong, data: *mut c_types::c_void) -> NotifyResult {
+        // SAFETY: The chain was initialised, as required by `new`. `data` is only passed on to
+        // the callbacks.
+        NotifyResult::from_raw(unsafe {
+            bindings::atomic_notifier_call_chain(self.head.get(), action, data)
+        })
+    }
+}
+
+impl NeedsLockClass for AtomicChain {
+    unsafe fn init(
+        self: Pin<&mut Self>,
+        name: &'static CStr,
+        key: *mut bindings::lock_class_key,
+        _: *mut bindings::lock_class_key,
+    ) {
+        let head = self.head.get();
+
+        // SAFETY: `head` is pinned and is being initialised here. The caller guarantees that
+        // `key` remains valid.
+        unsafe {
+            bindings::__spin_lock_init(&mut (*head).lock, name.as_char_ptr(), key);
+            (*head).head = ptr::null_mut();
+        }
+    }
+}
+
+// SAFETY: Callbacks are only called by `call_chain`, with a `RawEvent` as is.
+unsafe impl Chain for AtomicChain {
+    type Event<'a> = RawEvent;
+
+    unsafe fn event<'a>(action: c_types::c_ulong, data: *mut c_types::c_void) -> RawEvent {
+        RawEvent { action, data }
+    }
+
+    unsafe fn register(&self, nb: *mut bindings::notifier_block) -> c_types::c_int {
+        // SAFETY: The safety requirements guarantee that `nb` remains valid.
+        unsafe { bindings::atomic_notifier_chain_register(self.head.get(), nb) }
+    }
+
+    unsafe fn unregister(&self, nb: *mut bindings::notifier_block) {
+        // SAFETY: The safety requirements guarantee that `nb` is in the chain.
+        unsafe { bindings::atomic_notifier_chain_unregister(self.head.get(), nb) };
+    }
+}
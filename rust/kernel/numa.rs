@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! NUMA nodes and node-local allocations.
+//!
+//! On NUMA systems, memory is faster to access from the CPUs of the node it belongs to. Data that
+//! is mostly used by the CPUs of one node can be allocated on it with the `_node` variants of the
+//! allocation functions, e.g., [`BoxExt::try_new_node`].
+//!
+//! C headers: [`include/linux/nodemask.h`](../../../../include/linux/nodemask.h) and
+//! [`include/linux/topology.h`](../../../../include/linux/topology.h)
+//!
+//! [`BoxExt::try_new_node`]: crate::gfp::BoxExt::try_new_node
+
+use crate::{bindings, c_types, error::code::*, Result};
+
+/// A NUMA node, or any node.
+///
+/// # Invariants
+///
+/// The id is either `NUMA_NO_NODE` or the id of a possible node.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::gfp::BoxExt;
+/// use kernel::numa::NumaNode;
+///
+/// struct Queue {
+///     head: u32,
+///     tail: u32,
+/// }
+///
+/// fn alloc_queue(cpu: u32) -> Result<Box<Queue>> {
+///     // The queue is mostly used by `cpu`, so keep it close to it.
+///     Box::try_new_node(Queue { head: 0, tail: 0 }, NumaNode::of_cpu(cpu))
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumaNode(c_types::c_int);
+
+impl NumaNode {
+    /// Any node, usually the one of the current CPU, with fallback to other nodes.
+    pub const ANY: Self = Self(bindings::NUMA_NO_NODE);
+
+    /// Returns the node with the given id.
+    ///
+    /// Returns [`EINVAL`] if the node doesn't exist.
+    pub fn new(id: u32) -> Result<Self> {
+        if id >= bindings::MAX_NUMNODES {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: `id` was checked above.
+        if !unsafe { bindings::node_state(id as _, bindings::node_states_N_POSSIBLE) } {
+            return Err(EINVAL);
+        }
+
+        // INVARIANT: `id` is the id of a possible node, as checked above.
+        Ok(Self(id as _))
+    }
+
+    /// Returns the node of the CPU the caller is running on.
+    ///
+    /// The result may be stale by the time the caller acts on it, unless preemption is disabled.
+    pub fn current() -> Self {
+        // SAFETY: There are no safety requirements for this FFI call.
+        Self(unsafe { bindings::numa_node_id() })
+    }
+
+    /// Returns the node of the given CPU.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cpu` is not a valid CPU number.
+    pub fn of_cpu(cpu: u32) -> Self {
+        assert!(cpu < crate::cpumask::nr_cpu_ids());
+        // SAFETY: `cpu` was checked above.
+        Self(unsafe { bindings::cpu_to_node(cpu as _) })
+    }
+
+    /// Returns the id of the node, or `None` for [`NumaNode::ANY`].
+    pub fn id(&self) -> Option<u32> {
+        if *self == Self::ANY {
+            None
+        } else {
+            Some(self.0 as _)
+        }
+    }
+
+    /// Returns whether the node is online, that is, has CPUs or memory available.
+    ///
+    /// [`NumaNode::ANY`] is always online.
+    pub fn is_online(&self) -> bool {
+        if *self == Self::ANY {
+            return true;
+        }
+
+        // SAFETY: By the type invariants, `self.0` is a valid node id.
+        unsafe { bindings::node_state(self.0, bindings::node_states_N_ONLINE) }
+    }
+
+    /// Returns the raw node id, as taken by the C allocation functions.
+    pub fn as_raw(&self) -> c_types::c_int {
+        self.0
+    }
+}
+
+impl Default for NumaNode {
+    fn default() -> Self {
+        Self::ANY
+    }
+}
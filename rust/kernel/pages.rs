@@ -5,11 +5,20 @@
 //! TODO: This module is a work in progress.
 
 use crate::{
-    bindings, c_types, error::code::*, io_buffer::IoBufferReader, user_ptr::UserSlicePtrReader,
+    bindings, c_types,
+    device::{Device, RawDevice},
+    error::{code::*, Error},
+    io_buffer::IoBufferReader,
+    numa::NumaNode,
+    user_ptr::UserSlicePtrReader,
     Result, PAGE_SIZE,
 };
+use alloc::vec::Vec;
 use core::{marker::PhantomData, ptr};
 
+/// A single physical page.
+pub type Page = Pages<0>;
+
 /// A set of physical pages.
 ///
 /// `Pages` holds a reference to a set of pages of order `ORDER`. Having the order as a generic
@@ -40,6 +49,112 @@ impl<const ORDER: u32> Pages<ORDER> {
         Ok(Self { pages })
     }
 
+    /// Allocates a new set of contiguous pages with the given flags (see
+    /// [`crate::gfp::Flags`]) on the given NUMA node.
+    ///
+    /// The pages are only zeroed if the flags include [`crate::gfp::Flags::ZERO`]; otherwise they
+    /// hold stale data, which must not be exposed to user space.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kernel::prelude::*;
+    /// use kernel::gfp::Flags;
+    /// use kernel::numa::NumaNode;
+    /// use kernel::pages::Pages;
+    ///
+    /// fn alloc_ring() -> Result<Pages<2>> {
+    ///     let ring = Pages::<2>::new_with(Flags::KERNEL | Flags::ZERO, NumaNode::ANY)?;
+    ///     pr_debug!("ring at {:#x}\n", ring.phys_addr());
+    ///     Ok(ring)
+    /// }
+    /// ```
+    pub fn new_with(flags: u32, node: NumaNode) -> Result<Self> {
+        // SAFETY: This only allocates pages. We check that it succeeds in the next statement.
+        let pages = unsafe { bindings::alloc_pages_node(node.as_raw(), flags, ORDER) };
+        if pages.is_null() {
+            return Err(ENOMEM);
+        }
+        // INVARIANTS: We checked that the allocation above succeeded.
+        Ok(Self { pages })
+    }
+
+    /// Creates a new instance from pages allocated by C code.
+    ///
+    /// # Safety
+    ///
+    /// `pages` must point to 2^ORDER contiguous pages allocated by `alloc_pages` (or similar)
+    /// with the same order, whose ownership is transferred to the new instance.
+    pub unsafe fn from_raw(pages: *mut bindings::page) -> Self {
+        // INVARIANTS: The safety requirements satisfy the invariants.
+        Self { pages }
+    }
+
+    /// Releases ownership of the pages and returns a pointer to the first one.
+    ///
+    /// The pages must be freed with `__free_pages` (or converted back with [`Pages::from_raw`]).
+    pub fn into_raw(self) -> *mut bindings::page {
+        let pages = self.pages;
+        core::mem::forget(self);
+        pages
+    }
+
+    /// Returns a pointer to the first page.
+    pub fn as_ptr(&self) -> *mut bindings::page {
+        self.pages
+    }
+
+    /// Returns the size in bytes of the pages.
+    pub const fn size() -> usize {
+        PAGE_SIZE << ORDER
+    }
+
+    /// Returns the page frame number of the first page.
+    pub fn pfn(&self) -> usize {
+        // SAFETY: By the type invariants, `pages` is valid.
+        unsafe { bindings::page_to_pfn(self.pages) as _ }
+    }
+
+    /// Returns the physical address of the first page.
+    ///
+    /// Devices should use the addresses returned by [`Pages::dma_map`] instead, since they may be
+    /// behind an IOMMU.
+    pub fn phys_addr(&self) -> bindings::phys_addr_t {
+        // SAFETY: By the type invariants, `pages` is valid.
+        unsafe { bindings::page_to_phys(self.pages) }
+    }
+
+    /// Maps the pages for DMA by the given device, in the given direction.
+    ///
+    /// The pages are unmapped when the returned mapping is dropped. They are borrowed mutably by
+    /// the mapping, since the CPU must not access them while the device may be accessing them;
+    /// [`DmaMapping::sync_for_cpu`] gives them back to the CPU temporarily.
+    pub fn dma_map(
+        &mut self,
+        dev: &dyn RawDevice,
+        dir: DmaDirection,
+    ) -> Result<DmaMapping<'_, ORDER>> {
+        let dev = Device::from_dev(dev);
+
+        // SAFETY: `dev` and `pages` are valid, and the range is within the pages.
+        let addr = unsafe {
+            bindings::dma_map_page_attrs(dev.ptr, self.pages, 0, Self::size(), dir as _, 0)
+        };
+
+        // SAFETY: `dev` is valid.
+        if unsafe { bindings::dma_mapping_error(dev.ptr, addr) } != 0 {
+            return Err(ENOMEM);
+        }
+
+        // INVARIANT: `addr` was mapped above for the pages, with `dir` as the direction.
+        Ok(DmaMapping {
+            pages: self,
+            dev,
+            addr,
+            dir,
+        })
+    }
+
     /// Copies data from the given [`UserSlicePtrReader`] into the pages.
     pub fn copy_into_page(
         &self,
@@ -129,6 +244,144 @@ impl<const ORDER: u32> Drop for Pages<ORDER> {
     }
 }
 
+impl Page {
+    /// Maps the page into the kernel's address space, for the current task only, and calls `f`
+    /// with its contents.
+    ///
+    /// The page is unmapped when `f` returns. The mapping is cheap (and a no-op on systems without
+    /// high memory), so it is suitable for short accesses, e.g., to copy the contents of a page in
+    /// `readpage` or `writepage`.
+    ///
+    /// Taking a closure rather than returning a guard ensures that nested mappings are unmapped in
+    /// the reverse order they were created, as `kunmap_local` requires.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kernel::prelude::*;
+    /// use kernel::pages::Page;
+    ///
+    /// fn fill(page: &mut Page, byte: u8) {
+    ///     page.with_mapped(|buf| buf.fill(byte));
+    /// }
+    /// ```
+    pub fn with_mapped<R>(&mut self, f: impl FnOnce(&mut [u8; PAGE_SIZE]) -> R) -> R {
+        // SAFETY: By the type invariants, `pages` is a valid page.
+        let ptr = unsafe { bindings::kmap_local_page(self.pages) } as *mut [u8; PAGE_SIZE];
+
+        // SAFETY: `ptr` maps the whole page, which is borrowed mutably, so it is only accessed
+        // through the mapping until it is unmapped below.
+        let ret = f(unsafe { &mut *ptr });
+
+        // SAFETY: `ptr` was mapped by `kmap_local_page` above. Local mappings created while `f`
+        // runs are unmapped before the calls that created them return, so it is the most recent
+        // mapping of the current task.
+        unsafe { bindings::kunmap_local(ptr as *const c_types::c_void) };
+        ret
+    }
+}
+
+/// The direction of the data transfers of a DMA mapping.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmaDirection {
+    /// The device both reads and writes the memory.
+    Bidirectional = bindings::dma_data_direction_DMA_BIDIRECTIONAL,
+
+    /// The device only reads the memory.
+    ToDevice = bindings::dma_data_direction_DMA_TO_DEVICE,
+
+    /// The device only writes the memory.
+    FromDevice = bindings::dma_data_direction_DMA_FROM_DEVICE,
+}
+
+impl DmaDirection {
+    /// Converts a raw `enum dma_data_direction` value, e.g., one given to a callback.
+    pub(crate) fn try_from_raw(dir: bindings::dma_data_direction) -> Result<Self> {
+        match dir {
+            bindings::dma_data_direction_DMA_BIDIRECTIONAL => Ok(Self::Bidirectional),
+            bindings::dma_data_direction_DMA_TO_DEVICE => Ok(Self::ToDevice),
+            bindings::dma_data_direction_DMA_FROM_DEVICE => Ok(Self::FromDevice),
+            _ => Err(EINVAL),
+        }
+    }
+}
+
+/// A DMA mapping of pages, returned by [`Pages::dma_map`].
+///
+/// The device owns the pages while they are mapped, except between calls to
+/// [`DmaMapping::sync_for_cpu`] and [`DmaMapping::sync_for_device`].
+///
+/// # Invariants
+///
+/// `addr` is the DMA address of `pages` mapped for `dev`, with `dir` as the direction.
+pub struct DmaMapping<'a, const ORDER: u32> {
+    pages: &'a mut Pages<ORDER>,
+    dev: Device,
+    addr: bindings::dma_addr_t,
+    dir: DmaDirection,
+}
+
+impl<const ORDER: u32> DmaMapping<'_, ORDER> {
+    /// Returns the address of the pages for the device.
+    pub fn dma_addr(&self) -> bindings::dma_addr_t {
+        self.addr
+    }
+
+    /// Gives ownership of the pages to the CPU, e.g., to read what the device wrote to them, and
+    /// returns them.
+    ///
+    /// The device must not access the pages until [`DmaMapping::sync_for_device`] is called,
+    /// which the borrow of the returned pages must end before.
+    ///
+    /// Equivalent to the kernel's `dma_sync_single_for_cpu`.
+    pub fn sync_for_cpu(&mut self) -> &mut Pages<ORDER> {
+        // SAFETY: By the type invariants, `addr` was mapped for `dev` with the size of the pages
+        // and `dir` as the direction.
+        unsafe {
+            bindings::dma_sync_single_for_cpu(
+                self.dev.ptr,
+                self.addr,
+                Pages::<ORDER>::size(),
+                self.dir as _,
+            )
+        };
+        &mut *self.pages
+    }
+
+    /// Gives ownership of the pages back to the device, e.g., after the CPU wrote to them.
+    ///
+    /// Equivalent to the kernel's `dma_sync_single_for_device`.
+    pub fn sync_for_device(&mut self) {
+        // SAFETY: By the type invariants, `addr` was mapped for `dev` with the size of the pages
+        // and `dir` as the direction.
+        unsafe {
+            bindings::dma_sync_single_for_device(
+                self.dev.ptr,
+                self.addr,
+                Pages::<ORDER>::size(),
+                self.dir as _,
+            )
+        };
+    }
+}
+
+impl<const ORDER: u32> Drop for DmaMapping<'_, ORDER> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `addr` was mapped for `dev` with the size of the pages
+        // and `dir` as the direction.
+        unsafe {
+            bindings::dma_unmap_page_attrs(
+                self.dev.ptr,
+                self.addr,
+                Pages::<ORDER>::size(),
+                self.dir as _,
+                0,
+            )
+        };
+    }
+}
+
 struct PageMapping<'a> {
     page: *mut bindings::page,
     ptr: *mut c_types::c_void,
@@ -142,3 +395,135 @@ impl Drop for PageMapping<'_> {
         unsafe { bindings::kunmap(self.page) };
     }
 }
+
+/// Contains constants for the flags of [`UserPages::try_pin`].
+///
+/// It is tagged with `non_exhaustive` to prevent users from instantiating it.
+#[non_exhaustive]
+pub struct PinFlags;
+
+impl PinFlags {
+    /// The pages will be written to (e.g., by a device), so they must be writable by the user and
+    /// are marked dirty when unpinned.
+    pub const WRITE: u32 = bindings::FOLL_WRITE;
+
+    /// The pages will be pinned for an indefinite time (e.g., registered with a device), so they
+    /// are first migrated out of movable zones and CMA areas.
+    pub const LONGTERM: u32 = bindings::FOLL_LONGTERM;
+}
+
+/// User pages pinned in memory, e.g., for zero-copy DMA to or from user buffers.
+///
+/// The pages stay in memory (and aren't moved) until the instance is dropped, which unpins them.
+///
+/// # Invariants
+///
+/// The pages in `pages` were pinned with `pin_user_pages_fast` and are unpinned on drop. `offset`
+/// is less than [`PAGE_SIZE`], and `len` bytes starting at `offset` in the first page are
+/// covered by the pages.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::pages::{PinFlags, UserPages};
+///
+/// fn pin_buffer(addr: usize, len: usize) -> Result<UserPages> {
+///     // The device writes to the buffer, and keeps it until the file is closed.
+///     let pages = UserPages::try_pin(addr, len, PinFlags::WRITE | PinFlags::LONGTERM)?;
+///     pr_debug!("pinned {} pages\n", pages.pages().len());
+///     Ok(pages)
+/// }
+/// ```
+pub struct UserPages {
+    pages: Vec<*mut bindings::page>,
+    offset: usize,
+    len: usize,
+    dirty: bool,
+}
+
+// SAFETY: Pinned pages may be used and unpinned from any thread.
+unsafe impl Send for UserPages {}
+
+// SAFETY: `UserPages` only gives access to the pointers to the pages, not to their contents.
+unsafe impl Sync for UserPages {}
+
+impl UserPages {
+    /// Pins the pages of the current task that cover `len` bytes at the user address `addr`,
+    /// with the given flags (see [`PinFlags`]).
+    ///
+    /// Returns [`EFAULT`] if some of the range isn't mapped (or not writable, with
+    /// [`PinFlags::WRITE`]).
+    pub fn try_pin(addr: usize, len: usize, flags: u32) -> Result<Self> {
+        let end = addr.checked_add(len).ok_or(EFAULT)?;
+        let start = addr & !(PAGE_SIZE - 1);
+        let nr_pages = if len == 0 {
+            0
+        } else {
+            (end - start + PAGE_SIZE - 1) / PAGE_SIZE
+        };
+        let nr: c_types::c_int = nr_pages.try_into()?;
+
+        let mut pages = Vec::try_with_capacity(nr_pages)?;
+
+        // SAFETY: `pages` has room for `nr` pointers. `pin_user_pages_fast` checks that the range
+        // is a valid user range.
+        let pinned =
+            unsafe { bindings::pin_user_pages_fast(start as _, nr, flags, pages.as_mut_ptr()) };
+        if pinned < 0 {
+            return Err(Error::from_kernel_errno(pinned as _));
+        }
+
+        // SAFETY: `pin_user_pages_fast` initialised the first `pinned` pointers, with `pinned` at
+        // most `nr`.
+        unsafe { pages.set_len(pinned as _) };
+
+        // INVARIANT: The pages were pinned above. If only some of the range was pinned, the
+        // pages are unpinned when dropped below.
+        let user_pages = Self {
+            pages,
+            offset: addr - start,
+            len,
+            dirty: flags & PinFlags::WRITE != 0,
+        };
+
+        if user_pages.pages.len() != nr_pages {
+            return Err(EFAULT);
+        }
+
+        Ok(user_pages)
+    }
+
+    /// Returns the pinned pages, e.g., to add them to a scatter-gather list.
+    pub fn pages(&self) -> &[*mut bindings::page] {
+        &self.pages
+    }
+
+    /// Returns the offset in the first page at which the range starts.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Returns the length of the range in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the range is empty, in which case no pages are pinned.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Drop for UserPages {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, the pages were pinned with `pin_user_pages_fast`.
+        unsafe {
+            bindings::unpin_user_pages_dirty_lock(
+                self.pages.as_mut_ptr(),
+                self.pages.len() as _,
+                self.dirty,
+            )
+        };
+    }
+}
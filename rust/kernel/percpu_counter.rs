@@ -0,0 +1,168 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Per-CPU counters.
+//!
+//! A [`PerCpuCounter`] keeps a local delta on each CPU and only folds it into the shared count
+//! once it exceeds a batch size. Updates are thus cheap and don't bounce cache lines between
+//! CPUs, but reading an exact value requires summing the deltas of all CPUs. It suits counters
+//! that are updated often and read rarely, e.g., the free blocks and inodes of a file system.
+//!
+//! C header: [`include/linux/percpu_counter.h`](../../../../include/linux/percpu_counter.h)
+
+use crate::{bindings, sync::LockClassKey, to_result, Opaque, Result};
+use alloc::boxed::Box;
+use core::{cmp::Ordering, marker::PhantomPinned, pin::Pin};
+
+/// Creates a new [`PerCpuCounter`] with the given initial value.
+///
+/// A new lock class is created for each call site. See [`PerCpuCounter::try_new`].
+#[macro_export]
+macro_rules! new_percpu_counter {
+    ($value:expr) => {
+        $crate::percpu_counter::PerCpuCounter::try_new($value, $crate::static_lock_class!())
+    };
+}
+
+/// A counter with per-CPU deltas.
+///
+/// Instances are pinned because the counter is linked into a global list (used to fold the deltas
+/// of CPUs that go offline).
+///
+/// # Invariants
+///
+/// The counter was initialised with `__percpu_counter_init`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::new_percpu_counter;
+///
+/// fn example() -> Result {
+///     let free_blocks = new_percpu_counter!(1024)?;
+///     free_blocks.sub(16);
+///     free_blocks.inc();
+///
+///     // The approximate value may be off by up to the batch size per CPU, but the sum is exact.
+///     assert_eq!(free_blocks.sum(), 1009);
+///     assert!(free_blocks.compare(1000).is_gt());
+///     Ok(())
+/// }
+/// ```
+pub struct PerCpuCounter {
+    counter: Opaque<bindings::percpu_counter>,
+    _pin: PhantomPinned,
+}
+
+// SAFETY: The counter functions are safe to call from any thread.
+unsafe impl Send for PerCpuCounter {}
+
+// SAFETY: The counter functions are safe to call concurrently.
+unsafe impl Sync for PerCpuCounter {}
+
+impl PerCpuCounter {
+    /// Creates a new counter with the given initial value, using `key` as the lock class of its
+    /// internal lock.
+    ///
+    /// Callers are encouraged to use the [`new_percpu_counter`] macro, which creates a new lock
+    /// class on each usage.
+    ///
+    /// [`new_percpu_counter`]: crate::new_percpu_counter
+    pub fn try_new(value: i64, key: &'static LockClassKey) -> Result<Pin<Box<Self>>> {
+        // A zeroed counter has no per-CPU deltas, which `percpu_counter_destroy` ignores, so it
+        // can be dropped if the initialisation fails.
+        let counter = Pin::from(Box::try_new(Self {
+            counter: Opaque::new(bindings::percpu_counter::default()),
+            _pin: PhantomPinned,
+        })?);
+
+        // SAFETY: The counter is pinned, and `key` is static, so it outlives the counter.
+        to_result(|| unsafe {
+            bindings::__percpu_counter_init(
+                counter.counter.get(),
+                value,
+                bindings::GFP_KERNEL,
+                key.as_ptr(),
+            )
+        })?;
+
+        // INVARIANT: The counter was initialised above.
+        Ok(counter)
+    }
+
+    /// Adds `amount` (which may be negative) to the counter.
+    pub fn add(&self, amount: i64) {
+        // SAFETY: The counter is initialised by the type invariants.
+        unsafe { bindings::percpu_counter_add(self.counter.get(), amount) };
+    }
+
+    /// Subtracts `amount` from the counter.
+    pub fn sub(&self, amount: i64) {
+        self.add(-amount);
+    }
+
+    /// Increments the counter.
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// Decrements the counter.
+    pub fn dec(&self) {
+        self.add(-1);
+    }
+
+    /// Sets the counter to the given value.
+    ///
+    /// Concurrent updates may be lost.
+    pub fn set(&self, value: i64) {
+        // SAFETY: The counter is initialised by the type invariants.
+        unsafe { bindings::percpu_counter_set(self.counter.get(), value) };
+    }
+
+    /// Returns the approximate value of the counter, without the deltas of the CPUs.
+    ///
+    /// It is cheap but may be off by up to the batch size per CPU, and may be negative even if
+    /// the exact value isn't.
+    pub fn read(&self) -> i64 {
+        // SAFETY: The counter is initialised by the type invariants.
+        unsafe { bindings::percpu_counter_read(self.counter.get()) }
+    }
+
+    /// Returns the approximate value of the counter, clamped to zero.
+    pub fn read_positive(&self) -> i64 {
+        // SAFETY: The counter is initialised by the type invariants.
+        unsafe { bindings::percpu_counter_read_positive(self.counter.get()) }
+    }
+
+    /// Returns the exact value of the counter, summing the deltas of all CPUs.
+    ///
+    /// It is expensive on systems with many CPUs, and concurrent updates may or may not be
+    /// included.
+    pub fn sum(&self) -> i64 {
+        // SAFETY: The counter is initialised by the type invariants.
+        unsafe { bindings::percpu_counter_sum(self.counter.get()) }
+    }
+
+    /// Returns the exact value of the counter, clamped to zero.
+    pub fn sum_positive(&self) -> i64 {
+        // SAFETY: The counter is initialised by the type invariants.
+        unsafe { bindings::percpu_counter_sum_positive(self.counter.get()) }
+    }
+
+    /// Compares the counter with `rhs`.
+    ///
+    /// It only sums the deltas of all CPUs if the approximate value is too close to `rhs` to
+    /// decide, so it is cheaper than comparing [`PerCpuCounter::sum`] in the common case.
+    pub fn compare(&self, rhs: i64) -> Ordering {
+        // SAFETY: The counter is initialised by the type invariants.
+        unsafe { bindings::percpu_counter_compare(self.counter.get(), rhs) }.cmp(&0)
+    }
+}
+
+impl Drop for PerCpuCounter {
+    fn drop(&mut self) {
+        // SAFETY: The counter is either initialised or zeroed, which `percpu_counter_destroy`
+        // ignores.
+        unsafe { bindings::percpu_counter_destroy(self.counter.get()) };
+    }
+}
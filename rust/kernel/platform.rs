@@ -4,14 +4,19 @@
 //!
 //! Also called `platdev`, `pdev`.
 //!
+//! Drivers are matched with devices by the compatible strings of the devicetree (see
+//! [`Driver::OF_DEVICE_ID_TABLE`]) or by the ACPI hardware ids (see
+//! [`Driver::ACPI_DEVICE_ID_TABLE`]).
+//!
 //! C header: [`include/linux/platform_device.h`](../../../../include/linux/platform_device.h)
 
 use crate::{
-    bindings, c_types,
+    acpi, bindings, c_types,
     device::{self, RawDevice},
     driver,
-    error::{from_kernel_result, Result},
-    of,
+    error::{from_kernel_result, Error, Result},
+    io_mem::Resource,
+    of, power,
     str::CStr,
     to_result,
     types::PointerWrapper,
@@ -42,13 +47,22 @@ impl<T: Driver> driver::DriverOps for Adapter<T> {
         if let Some(t) = T::OF_DEVICE_ID_TABLE {
             pdrv.driver.of_match_table = t.as_ref();
         }
+        if let Some(t) = T::ACPI_DEVICE_ID_TABLE {
+            pdrv.driver.acpi_match_table = t.as_ref();
+        }
+        if cfg!(CONFIG_PM) {
+            // SAFETY: `probe_callback` sets the driver data after calling `T::Data::into_pointer`,
+            // and we guarantee that `T::Data` is the same as `T::PowerOps::Data` by a constraint
+            // in the type declaration.
+            pdrv.driver.pm = unsafe { power::OpsTable::<T::PowerOps>::build() };
+        }
         // SAFETY:
         //   - `pdrv` lives at least until the call to `platform_driver_unregister()` returns.
         //   - `name` pointer has static lifetime.
         //   - `module.0` lives at least as long as the module.
         //   - `probe()` and `remove()` are static functions.
-        //   - `of_match_table` is either a raw pointer with static lifetime,
-        //      as guaranteed by the [`driver::IdTable`] type, or null.
+        //   - `of_match_table` and `acpi_match_table` are either raw pointers with static
+        //      lifetime, as guaranteed by the [`driver::IdTable`] type, or null.
         to_result(|| unsafe { bindings::__platform_driver_register(reg, module.0) })
     }
 
@@ -62,6 +76,10 @@ impl<T: Driver> driver::DriverOps for Adapter<T> {
 
 impl<T: Driver> Adapter<T> {
     fn get_id_info(dev: &Device) -> Option<&'static T::IdInfo> {
+        Self::get_of_id_info(dev).or_else(|| Self::get_acpi_id_info(dev))
+    }
+
+    fn get_of_id_info(dev: &Device) -> Option<&'static T::IdInfo> {
         let table = T::OF_DEVICE_ID_TABLE?;
 
         // SAFETY: `table` has static lifetime, so it is valid for read. `dev` is guaranteed to be
@@ -72,18 +90,43 @@ impl<T: Driver> Adapter<T> {
         }
 
         // SAFETY: `id` is a pointer within the static table, so it's always valid.
-        let offset = unsafe { (*id).data };
-        if offset.is_null() {
+        let offset = unsafe { (*id).data } as isize;
+
+        // SAFETY: The offset was stored in the table by `of::DeviceId::to_rawid`.
+        unsafe { Self::id_info(id, offset) }
+    }
+
+    fn get_acpi_id_info(dev: &Device) -> Option<&'static T::IdInfo> {
+        let table = T::ACPI_DEVICE_ID_TABLE?;
+
+        // SAFETY: `table` has static lifetime, so it is valid for read. `dev` is guaranteed to be
+        // valid while it's alive, so is the raw device returned by it.
+        let id = unsafe { bindings::acpi_match_device(table.as_ref(), dev.raw_device()) };
+        if id.is_null() {
+            return None;
+        }
+
+        // SAFETY: `id` is a pointer within the static table, so it's always valid.
+        let offset = unsafe { (*id).driver_data } as isize;
+
+        // SAFETY: The offset was stored in the table by `acpi::DeviceId::to_rawid`.
+        unsafe { Self::id_info(id, offset) }
+    }
+
+    /// Returns the information of the entry `id` of an id table.
+    ///
+    /// # Safety
+    ///
+    /// `id` must point to an entry of a static id table of `T`, and `offset` must be the offset
+    /// stored in it by `IdArray::new`.
+    unsafe fn id_info<U>(id: *const U, offset: isize) -> Option<&'static T::IdInfo> {
+        if offset == 0 {
             return None;
         }
 
         // SAFETY: The offset comes from a previous call to `offset_from` in `IdArray::new`, which
         // guarantees that the resulting pointer is within the table.
-        let ptr = unsafe {
-            id.cast::<u8>()
-                .offset(offset as _)
-                .cast::<Option<T::IdInfo>>()
-        };
+        let ptr = unsafe { id.cast::<u8>().offset(offset).cast::<Option<T::IdInfo>>() };
 
         // SAFETY: The id table has a static lifetime, so `ptr` is guaranteed to be valid for read.
         unsafe { (&*ptr).as_ref() }
@@ -133,12 +176,22 @@ pub trait Driver {
     /// never move the underlying wrapped data structure. This allows
     type Data: PointerWrapper + Send + Sync + driver::DeviceRemoval = ();
 
+    /// The type that implements the power-management operations.
+    ///
+    /// The default is a type that implements no power-management operations. Drivers that do
+    /// implement them need to specify the type (commonly [`Self`]).
+    type PowerOps: power::Operations<Data = Self::Data> = power::NoOperations<Self::Data>;
+
     /// The type holding information about each device id supported by the driver.
     type IdInfo: 'static = ();
 
-    /// The table of device ids supported by the driver.
+    /// The table of devicetree compatible strings supported by the driver.
     const OF_DEVICE_ID_TABLE: Option<driver::IdTable<'static, of::DeviceId, Self::IdInfo>> = None;
 
+    /// The table of ACPI hardware ids supported by the driver.
+    const ACPI_DEVICE_ID_TABLE: Option<driver::IdTable<'static, acpi::DeviceId, Self::IdInfo>> =
+        None;
+
     /// Platform driver probe.
     ///
     /// Called when a new platform device is added or discovered.
@@ -180,6 +233,78 @@ impl Device {
         // SAFETY: By the type invariants, we know that `self.ptr` is non-null and valid.
         unsafe { (*self.ptr).id }
     }
+
+    /// Returns the index-th memory resource of the device, if there is one.
+    pub fn resource(&self, index: u32) -> Option<Resource> {
+        // SAFETY: By the type invariants, `self.ptr` is valid.
+        let res =
+            unsafe { bindings::platform_get_resource(self.ptr, bindings::IORESOURCE_MEM, index) };
+        Self::to_resource(res)
+    }
+
+    /// Returns the memory resource of the device with the given name, if there is one.
+    pub fn resource_by_name(&self, name: &CStr) -> Option<Resource> {
+        // SAFETY: By the type invariants, `self.ptr` is valid, and `name` is a valid C string.
+        let res = unsafe {
+            bindings::platform_get_resource_byname(
+                self.ptr,
+                bindings::IORESOURCE_MEM,
+                name.as_char_ptr(),
+            )
+        };
+        Self::to_resource(res)
+    }
+
+    fn to_resource(res: *mut bindings::resource) -> Option<Resource> {
+        if res.is_null() {
+            return None;
+        }
+
+        // SAFETY: `res` is a non-null resource of the device, which lives as long as it.
+        let res = unsafe { &*res };
+        Resource::new(res.start, res.end)
+    }
+
+    /// Returns the index-th irq of the device.
+    ///
+    /// Logs an error if there is no such irq. Returns [`EPROBE_DEFER`] if the irq controller
+    /// isn't ready yet, in which case probing should fail with it.
+    ///
+    /// [`EPROBE_DEFER`]: crate::error::code::EPROBE_DEFER
+    pub fn irq(&self, index: u32) -> Result<u32> {
+        // SAFETY: By the type invariants, `self.ptr` is valid.
+        Self::to_irq(unsafe { bindings::platform_get_irq(self.ptr, index) })
+    }
+
+    /// Returns the index-th irq of the device, which may not exist.
+    ///
+    /// Like [`Device::irq`], but returns [`ENXIO`] without logging an error if there is no such
+    /// irq.
+    ///
+    /// [`ENXIO`]: crate::error::code::ENXIO
+    pub fn irq_optional(&self, index: u32) -> Result<u32> {
+        // SAFETY: By the type invariants, `self.ptr` is valid.
+        Self::to_irq(unsafe { bindings::platform_get_irq_optional(self.ptr, index) })
+    }
+
+    /// Returns the irq of the device with the given name.
+    pub fn irq_by_name(&self, name: &CStr) -> Result<u32> {
+        // SAFETY: By the type invariants, `self.ptr` is valid, and `name` is a valid C string.
+        Self::to_irq(unsafe { bindings::platform_get_irq_byname(self.ptr, name.as_char_ptr()) })
+    }
+
+    /// Returns the number of irqs of the device.
+    pub fn irq_count(&self) -> Result<u32> {
+        // SAFETY: By the type invariants, `self.ptr` is valid.
+        Self::to_irq(unsafe { bindings::platform_irq_count(self.ptr) })
+    }
+
+    fn to_irq(ret: c_types::c_int) -> Result<u32> {
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+        Ok(ret as _)
+    }
 }
 
 // SAFETY: The device returned by `raw_device` is the raw platform device.
@@ -31,10 +31,53 @@ pub trait Operations {
         Ok(())
     }
 
+    /// Called after creating a hibernation image, or if creating it failed.
+    fn thaw(_data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Result {
+        Ok(())
+    }
+
+    /// Called after the hibernation image is saved, before the system is powered off.
+    fn poweroff(_data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Result {
+        Ok(())
+    }
+
     /// Called after the system is restored from a hibernation image.
     fn restore(_data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Result {
         Ok(())
     }
+
+    /// Called after [`Operations::suspend`] has been called for all devices.
+    ///
+    /// The late and early variants run when runtime power management is disabled, so they are
+    /// useful for devices that others depend on (e.g., interrupt or clock controllers).
+    fn suspend_late(_data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Result {
+        Ok(())
+    }
+
+    /// Called before [`Operations::resume`] is called for any device.
+    fn resume_early(_data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Result {
+        Ok(())
+    }
+
+    /// Called after [`Operations::freeze`] has been called for all devices.
+    fn freeze_late(_data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Result {
+        Ok(())
+    }
+
+    /// Called before [`Operations::thaw`] is called for any device.
+    fn thaw_early(_data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Result {
+        Ok(())
+    }
+
+    /// Called after [`Operations::poweroff`] has been called for all devices.
+    fn poweroff_late(_data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Result {
+        Ok(())
+    }
+
+    /// Called before [`Operations::restore`] is called for any device.
+    fn restore_early(_data: <Self::Data as PointerWrapper>::Borrowed<'_>) -> Result {
+        Ok(())
+    }
 }
 
 macro_rules! pm_callback {
@@ -58,7 +101,15 @@ macro_rules! pm_callback {
 pm_callback!(suspend_callback, suspend);
 pm_callback!(resume_callback, resume);
 pm_callback!(freeze_callback, freeze);
+pm_callback!(thaw_callback, thaw);
+pm_callback!(poweroff_callback, poweroff);
 pm_callback!(restore_callback, restore);
+pm_callback!(suspend_late_callback, suspend_late);
+pm_callback!(resume_early_callback, resume_early);
+pm_callback!(freeze_late_callback, freeze_late);
+pm_callback!(thaw_early_callback, thaw_early);
+pm_callback!(poweroff_late_callback, poweroff_late);
+pm_callback!(restore_early_callback, restore_early);
 
 pub(crate) struct OpsTable<T: Operations>(PhantomData<*const T>);
 
@@ -69,15 +120,15 @@ impl<T: Operations> OpsTable<T> {
         suspend: Some(suspend_callback::<T>),
         resume: Some(resume_callback::<T>),
         freeze: Some(freeze_callback::<T>),
-        thaw: None,
-        poweroff: None,
+        thaw: Some(thaw_callback::<T>),
+        poweroff: Some(poweroff_callback::<T>),
         restore: Some(restore_callback::<T>),
-        suspend_late: None,
-        resume_early: None,
-        freeze_late: None,
-        thaw_early: None,
-        poweroff_late: None,
-        restore_early: None,
+        suspend_late: Some(suspend_late_callback::<T>),
+        resume_early: Some(resume_early_callback::<T>),
+        freeze_late: Some(freeze_late_callback::<T>),
+        thaw_early: Some(thaw_early_callback::<T>),
+        poweroff_late: Some(poweroff_late_callback::<T>),
+        restore_early: Some(restore_early_callback::<T>),
         suspend_noirq: None,
         resume_noirq: None,
         freeze_noirq: None,
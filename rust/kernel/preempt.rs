@@ -0,0 +1,150 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Preemption and local interrupt control, and execution context queries.
+//!
+//! The guards in this module re-enable preemption or restore interrupts when they are dropped, so
+//! the sections they protect are scoped. They cannot be sent to other threads since they refer to
+//! the state of the current CPU.
+//!
+//! C headers: [`include/linux/preempt.h`](../../../../include/linux/preempt.h) and
+//! [`include/linux/irqflags.h`](../../../../include/linux/irqflags.h)
+
+use crate::{bindings, c_types};
+use core::marker::PhantomData;
+
+/// A guard that keeps preemption disabled while it is alive.
+///
+/// It is created by [`disable`]. The current task cannot be migrated to another CPU nor
+/// preempted while it is alive, so it must not sleep.
+///
+/// # Invariants
+///
+/// Preemption was disabled when the guard was created and it hasn't been re-enabled by it yet.
+pub struct PreemptGuard {
+    _not_send: PhantomData<*mut ()>,
+}
+
+/// Disables preemption and returns a guard that re-enables it when dropped.
+///
+/// Calls can be nested.
+///
+/// Equivalent to the kernel's `preempt_disable`.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::preempt;
+///
+/// fn example() {
+///     let _guard = preempt::disable();
+///     // Access per-CPU data of the current CPU.
+/// }
+/// ```
+pub fn disable() -> PreemptGuard {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::preempt_disable() };
+
+    // INVARIANT: Preemption was disabled above.
+    PreemptGuard {
+        _not_send: PhantomData,
+    }
+}
+
+impl Drop for PreemptGuard {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, preemption was disabled by this guard.
+        unsafe { bindings::preempt_enable() };
+    }
+}
+
+/// A guard that keeps interrupts disabled on the local CPU while it is alive.
+///
+/// It is created by [`local_irq_save`]. It must not sleep while it is alive either.
+///
+/// # Invariants
+///
+/// `flags` holds the interrupt state saved by `local_irq_save` when the guard was created.
+pub struct LocalIrqGuard {
+    flags: c_types::c_ulong,
+    _not_send: PhantomData<*mut ()>,
+}
+
+/// Disables interrupts on the local CPU and returns a guard that restores their previous state
+/// when dropped.
+///
+/// Calls can be nested; guards must be dropped in the reverse order of their creation, which is
+/// the natural order for scoped guards.
+///
+/// Equivalent to the kernel's `local_irq_save`.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::preempt;
+///
+/// fn example() {
+///     let _guard = preempt::local_irq_save();
+///     assert!(preempt::irqs_disabled());
+///     // Access data shared with an interrupt handler of the current CPU.
+/// }
+/// ```
+pub fn local_irq_save() -> LocalIrqGuard {
+    // SAFETY: There are no safety requirements for this FFI call.
+    let flags = unsafe { bindings::local_irq_save() };
+
+    // INVARIANT: `flags` was returned by `local_irq_save` above.
+    LocalIrqGuard {
+        flags,
+        _not_send: PhantomData,
+    }
+}
+
+impl Drop for LocalIrqGuard {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `flags` was saved by `local_irq_save` on this CPU.
+        unsafe { bindings::local_irq_restore(self.flags) };
+    }
+}
+
+/// Returns whether interrupts are disabled on the local CPU.
+pub fn irqs_disabled() -> bool {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::irqs_disabled() }
+}
+
+/// Returns whether the caller is in atomic context, that is, it must not sleep.
+///
+/// Without `CONFIG_PREEMPT_COUNT`, the preemption count isn't maintained and it always returns
+/// `false`, even with preemption disabled or spinlocks held. It must therefore not be used to
+/// decide whether sleeping is allowed.
+///
+/// Equivalent to the kernel's `in_atomic`.
+pub fn in_atomic() -> bool {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::in_atomic() }
+}
+
+/// Returns whether the caller is in interrupt context (hard or soft interrupt, or NMI), or has
+/// bottom halves disabled.
+///
+/// Equivalent to the kernel's `in_interrupt`.
+pub fn in_interrupt() -> bool {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::in_interrupt() }
+}
+
+/// Returns whether the caller is in hard interrupt context.
+///
+/// Equivalent to the kernel's `in_hardirq`.
+pub fn in_hardirq() -> bool {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::in_hardirq() }
+}
+
+/// Returns whether the caller is in task context, that is, not in interrupt context.
+///
+/// Equivalent to the kernel's `in_task`.
+pub fn in_task() -> bool {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::in_task() }
+}
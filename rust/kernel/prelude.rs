@@ -24,6 +24,8 @@ pub use super::{
     pr_alert, pr_crit, pr_debug, pr_emerg, pr_err, pr_info, pr_notice, pr_warn,
 };
 
+pub use super::{bug_on, warn_on, warn_once};
+
 pub use super::module_misc_device;
 
 #[cfg(CONFIG_ARM_AMBA)]
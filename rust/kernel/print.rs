@@ -84,6 +84,150 @@ pub mod format_strings {
     pub static CONT: [u8; LENGTH] = generate(true, bindings::KERN_CONT);
 }
 
+/// Dynamic debug support.
+///
+/// Public but hidden since it should only be used from public macros.
+///
+/// C header: [`include/linux/dynamic_debug.h`](../../../../include/linux/dynamic_debug.h)
+#[doc(hidden)]
+#[cfg(CONFIG_DYNAMIC_DEBUG)]
+pub mod dynamic_debug {
+    use crate::{bindings, c_types, str::CStr};
+    use core::{cell::UnsafeCell, fmt};
+
+    /// The format string passed to `__dynamic_pr_debug`, which adds the `KERN_DEBUG` prefix.
+    static FORMAT: [u8; 8] = *b"%s: %pA\0";
+
+    /// The number of bits of `lineno` in `struct _ddebug`.
+    const LINENO_BITS: u32 = 18;
+
+    /// The number of bits of `flags` in `struct _ddebug`.
+    const FLAGS_BITS: u32 = 8;
+
+    /// The shift of `lineno` within the bitfield word.
+    #[cfg(target_endian = "little")]
+    const LINENO_SHIFT: u32 = 0;
+    #[cfg(target_endian = "big")]
+    const LINENO_SHIFT: u32 = u32::BITS - LINENO_BITS;
+
+    /// The shift of `flags` within the bitfield word.
+    #[cfg(target_endian = "little")]
+    const FLAGS_SHIFT: u32 = LINENO_BITS;
+    #[cfg(target_endian = "big")]
+    const FLAGS_SHIFT: u32 = u32::BITS - LINENO_BITS - FLAGS_BITS;
+
+    /// Mirrors `struct static_key`.
+    #[cfg(CONFIG_JUMP_LABEL)]
+    #[repr(C)]
+    struct StaticKey {
+        enabled: c_types::c_int,
+        type_: c_types::c_ulong,
+    }
+
+    /// Mirrors `struct _ddebug`.
+    ///
+    /// `bindgen` cannot generate `const` initialisers for bitfields, so the layout is replicated
+    /// here and `lineno` and `flags` are packed manually.
+    #[repr(C, align(8))]
+    struct RawDescriptor {
+        modname: *const c_types::c_char,
+        function: *const c_types::c_char,
+        filename: *const c_types::c_char,
+        format: *const c_types::c_char,
+        lineno_flags: u32,
+        #[cfg(CONFIG_JUMP_LABEL)]
+        key: StaticKey,
+    }
+
+    /// A dynamic debug call site descriptor.
+    ///
+    /// One instance is placed in the `__dyndbg` section for each call site so that the kernel can
+    /// enable and disable it via `<debugfs>/dynamic_debug/control`.
+    #[repr(transparent)]
+    pub struct Descriptor(UnsafeCell<RawDescriptor>);
+
+    // SAFETY: The descriptor is only modified by the dynamic debug core, under its own lock; Rust
+    // code only reads the flags.
+    unsafe impl Sync for Descriptor {}
+
+    impl Descriptor {
+        /// Creates a new call site descriptor.
+        ///
+        /// Call sites are enabled by default when `debug_assertions` is enabled, which mimics C
+        /// code defining `DEBUG`.
+        pub const fn new(
+            modname: &'static [u8],
+            function: &'static CStr,
+            filename: &'static CStr,
+            format: &'static CStr,
+            lineno: u32,
+        ) -> Self {
+            let flags = if cfg!(debug_assertions) {
+                bindings::_DPRINTK_FLAGS_PRINT
+            } else {
+                0
+            };
+            let lineno_flags = ((lineno & ((1 << LINENO_BITS) - 1)) << LINENO_SHIFT)
+                | ((flags & ((1 << FLAGS_BITS) - 1)) << FLAGS_SHIFT);
+
+            Self(UnsafeCell::new(RawDescriptor {
+                modname: modname.as_ptr() as _,
+                function: function.as_char_ptr(),
+                filename: filename.as_char_ptr(),
+                format: format.as_char_ptr(),
+                lineno_flags,
+                // Mimics `STATIC_KEY_TRUE_INIT` and `STATIC_KEY_FALSE_INIT`.
+                #[cfg(CONFIG_JUMP_LABEL)]
+                key: StaticKey {
+                    enabled: (flags != 0) as _,
+                    type_: (flags != 0) as _,
+                },
+            }))
+        }
+
+        /// Returns whether printing is enabled for this call site.
+        #[inline]
+        pub fn is_enabled(&self) -> bool {
+            // SAFETY: The descriptor is valid for reads. The dynamic debug core may update the
+            // flags concurrently, hence the volatile read.
+            let word = unsafe {
+                core::ptr::read_volatile(core::ptr::addr_of!((*self.0.get()).lineno_flags))
+            };
+            (word >> FLAGS_SHIFT) & bindings::_DPRINTK_FLAGS_PRINT != 0
+        }
+
+        /// Returns a raw pointer to the underlying `struct _ddebug`.
+        pub fn as_ptr(&self) -> *mut bindings::_ddebug {
+            self.0.get().cast()
+        }
+    }
+
+    /// Prints a message via the kernel's `__dynamic_pr_debug`.
+    ///
+    /// Public but hidden since it should only be used from public macros.
+    ///
+    /// # Safety
+    ///
+    /// The module name must be null-terminated, and `descriptor` must be placed in the `__dyndbg`
+    /// section (e.g., by [`dynamic_debug_descriptor`]).
+    pub unsafe fn call_dynamic_pr_debug(
+        descriptor: &'static Descriptor,
+        module_name: &[u8],
+        args: fmt::Arguments<'_>,
+    ) {
+        // SAFETY: The format string is fixed and expects a null-terminated string followed by a
+        // pointer to `fmt::Arguments`.
+        unsafe {
+            bindings::__dynamic_pr_debug(
+                descriptor.as_ptr(),
+                FORMAT.as_ptr() as _,
+                module_name.as_ptr(),
+                &args as *const _ as *const c_types::c_void,
+            );
+        }
+    }
+}
+
 /// Prints a message via the kernel's [`_printk`].
 ///
 /// Public but hidden since it should only be used from public macros.
@@ -112,6 +256,37 @@ pub unsafe fn call_printk(
     }
 }
 
+/// Prints a message via the kernel's [`_printk_deferred`].
+///
+/// The message is stored in the log buffer but the consoles are only flushed later from a safe
+/// context, so this can be called while holding scheduler locks.
+///
+/// Public but hidden since it should only be used from public macros.
+///
+/// # Safety
+///
+/// The format string must be one of the ones in [`format_strings`], and
+/// the module name must be null-terminated.
+///
+/// [`_printk_deferred`]: ../../../../include/linux/printk.h
+#[doc(hidden)]
+#[cfg_attr(not(CONFIG_PRINTK), allow(unused_variables))]
+pub unsafe fn call_printk_deferred(
+    format_string: &[u8; format_strings::LENGTH],
+    module_name: &[u8],
+    args: fmt::Arguments<'_>,
+) {
+    // `_printk_deferred` does not seem to fail in any path.
+    #[cfg(CONFIG_PRINTK)]
+    unsafe {
+        bindings::_printk_deferred(
+            format_string.as_ptr() as _,
+            module_name.as_ptr(),
+            &args as *const _ as *const c_void,
+        );
+    }
+}
+
 /// Prints a message via the kernel's [`_printk`] for the `CONT` level.
 ///
 /// Public but hidden since it should only be used from public macros.
@@ -132,6 +307,53 @@ pub fn call_printk_cont(args: fmt::Arguments<'_>) {
     }
 }
 
+/// Support for `trace_printk`.
+///
+/// Public but hidden since it should only be used from public macros.
+///
+/// C header: [`include/linux/kernel.h`](../../../../include/linux/kernel.h)
+#[doc(hidden)]
+#[cfg(CONFIG_TRACING)]
+pub mod trace_printk {
+    use crate::{bindings, c_types};
+    use core::{cell::UnsafeCell, fmt};
+
+    /// The format string passed to `__trace_printk`.
+    static FORMAT: [u8; 4] = *b"%pA\0";
+
+    /// An entry of the `__trace_printk_fmt` section.
+    ///
+    /// Like in C, the presence of entries in this section makes the kernel allocate the
+    /// `trace_printk` buffers when the module is loaded. The tracing core may update the pointer.
+    #[repr(transparent)]
+    pub struct Format(UnsafeCell<*const c_types::c_char>);
+
+    // SAFETY: The pointer is only updated by the tracing core while the module is being loaded,
+    // and is never read by Rust code.
+    unsafe impl Sync for Format {}
+
+    impl Format {
+        /// Creates a new entry pointing to the fixed format string.
+        pub const fn new() -> Self {
+            Self(UnsafeCell::new(FORMAT.as_ptr() as _))
+        }
+    }
+
+    /// Writes a message to the ftrace ring buffer via the kernel's `__trace_printk`.
+    ///
+    /// `ip` is the address reported as the origin of the message.
+    pub fn call_trace_printk(ip: usize, args: fmt::Arguments<'_>) {
+        // SAFETY: The format string is fixed and expects a pointer to `fmt::Arguments`.
+        unsafe {
+            bindings::__trace_printk(
+                ip as _,
+                FORMAT.as_ptr() as _,
+                &args as *const _ as *const c_types::c_void,
+            );
+        }
+    }
+}
+
 /// Performs formatting and forwards the string to [`call_printk`].
 ///
 /// Public but hidden since it should only be used from public macros.
@@ -361,8 +583,17 @@ macro_rules! pr_info (
 ///
 /// Use this level for debug messages.
 ///
-/// Equivalent to the kernel's [`pr_debug`] macro, except that it doesn't support dynamic debug
-/// yet.
+/// Equivalent to the kernel's [`pr_debug`] macro.
+///
+/// When `CONFIG_DYNAMIC_DEBUG` is enabled, each call site can be enabled and disabled at runtime
+/// via `<debugfs>/dynamic_debug/control`, for example:
+///
+/// ```text
+/// echo 'module rust_minimal +p' > /sys/kernel/debug/dynamic_debug/control
+/// ```
+///
+/// Call sites start enabled only if `debug_assertions` is enabled. Without
+/// `CONFIG_DYNAMIC_DEBUG`, messages are printed if and only if `debug_assertions` is enabled.
 ///
 /// Mimics the interface of [`std::print!`]. See [`core::fmt`] and
 /// [`alloc::format!`] for information about the formatting syntax.
@@ -379,11 +610,77 @@ macro_rules! pr_info (
 #[macro_export]
 #[doc(alias = "print")]
 macro_rules! pr_debug (
+    ($($arg:tt)*) => (
+        $crate::print_macro_dynamic!($($arg)*)
+    )
+);
+
+/// Emits a dynamic debug call site descriptor and returns a reference to it.
+///
+/// Public but hidden since it should only be used from public macros.
+#[doc(hidden)]
+#[cfg(all(CONFIG_DYNAMIC_DEBUG, not(testlib)))]
+#[macro_export]
+macro_rules! dynamic_debug_descriptor (
+    ($format:expr) => ({
+        #[link_section = "__dyndbg"]
+        #[used]
+        static DESCRIPTOR: $crate::print::dynamic_debug::Descriptor =
+            $crate::print::dynamic_debug::Descriptor::new(
+                crate::__LOG_PREFIX,
+                $crate::c_str!(core::module_path!()),
+                $crate::c_str!(core::file!()),
+                $format,
+                core::line!(),
+            );
+        &DESCRIPTOR
+    });
+);
+
+/// Emits a dynamic debug call site and prints the message if the call site is enabled.
+///
+/// Public but hidden since it should only be used from public macros.
+#[doc(hidden)]
+#[cfg(all(CONFIG_DYNAMIC_DEBUG, not(testlib)))]
+#[macro_export]
+macro_rules! print_macro_dynamic (
+    (@emit $format:expr, $($arg:tt)+) => ({
+        let descriptor = $crate::dynamic_debug_descriptor!($format);
+        if descriptor.is_enabled() {
+            // SAFETY: `descriptor` is placed in the `__dyndbg` section by
+            // `dynamic_debug_descriptor`, and all `__LOG_PREFIX`s are null-terminated as they are
+            // generated by the `module!` proc macro or fixed values defined in a kernel crate.
+            unsafe {
+                $crate::print::dynamic_debug::call_dynamic_pr_debug(
+                    descriptor,
+                    crate::__LOG_PREFIX,
+                    format_args!($($arg)+),
+                );
+            }
+        }
+    });
+
+    ($format:literal $($arg:tt)*) => (
+        $crate::print_macro_dynamic!(@emit $crate::c_str!($format), $format $($arg)*)
+    );
+
+    ($($arg:tt)+) => (
+        $crate::print_macro_dynamic!(@emit $crate::c_str!(""), $($arg)+)
+    );
+);
+
+/// Prints the message if `debug_assertions` is enabled.
+///
+/// Public but hidden since it should only be used from public macros.
+#[doc(hidden)]
+#[cfg(any(not(CONFIG_DYNAMIC_DEBUG), testlib))]
+#[macro_export]
+macro_rules! print_macro_dynamic (
     ($($arg:tt)*) => (
         if cfg!(debug_assertions) {
             $crate::print_macro!($crate::print::format_strings::DEBUG, false, $($arg)*)
         }
-    )
+    );
 );
 
 /// Continues a previous log message in the same line.
@@ -412,3 +709,609 @@ macro_rules! pr_cont (
         $crate::print_macro!($crate::print::format_strings::CONT, true, $($arg)*)
     )
 );
+
+/// Writes a message to the ftrace ring buffer.
+///
+/// This is a debugging aid with much lower overhead than printing to the kernel log, and which
+/// does not interfere with the latter. Messages can be read from `<tracefs>/trace`.
+///
+/// Like in C, using it in a module causes the kernel to allocate the `trace_printk` buffers and
+/// to print a notice about it being for debugging only, so it must not be left in production code.
+///
+/// Equivalent to the kernel's [`trace_printk`] macro. Does nothing if `CONFIG_TRACING` is not
+/// enabled.
+///
+/// Mimics the interface of [`std::print!`]. See [`core::fmt`] and
+/// [`alloc::format!`] for information about the formatting syntax.
+///
+/// [`trace_printk`]: https://www.kernel.org/doc/html/latest/trace/ftrace.html
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::trace_printk;
+/// trace_printk!("hello {}\n", "there");
+/// ```
+#[cfg(all(CONFIG_TRACING, not(testlib)))]
+#[macro_export]
+macro_rules! trace_printk (
+    ($($arg:tt)+) => ({
+        #[link_section = "__trace_printk_fmt"]
+        #[used]
+        static FORMAT: $crate::print::trace_printk::Format =
+            $crate::print::trace_printk::Format::new();
+
+        // Its address is used to identify the call site in the trace, which is similar to what
+        // `_THIS_IP_` does in C.
+        fn trace_printk_ip() {}
+
+        $crate::print::trace_printk::call_trace_printk(
+            trace_printk_ip as usize,
+            format_args!($($arg)+),
+        );
+    });
+);
+
+/// Writes a message to the ftrace ring buffer.
+///
+/// `CONFIG_TRACING` is not enabled, so the arguments are only type-checked, like [`pr_debug!`]
+/// does when it is disabled.
+#[cfg(any(not(CONFIG_TRACING), testlib))]
+#[macro_export]
+macro_rules! trace_printk (
+    ($($arg:tt)+) => (
+        if false {
+            let _ = core::format_args!($($arg)+);
+        }
+    );
+);
+
+/// Performs rate limiting and forwards the message to [`print_macro`].
+///
+/// Unless one is given with `name:`, the caller is identified by its module path and line, e.g.
+/// `my_module::my_submodule:42`, in the message that reports the number of suppressed messages.
+///
+/// Public but hidden since it should only be used from public macros.
+#[doc(hidden)]
+#[cfg(not(testlib))]
+#[macro_export]
+macro_rules! print_macro_ratelimited (
+    (@emit $format_string:path, $state:expr, $name:expr, $($arg:tt)+) => ({
+        let state: &$crate::ratelimit::RatelimitState = $state;
+        if state.check($crate::c_str!($name)) {
+            $crate::print_macro!($format_string, false, $($arg)+);
+        }
+    });
+
+    (@static $format_string:path, $name:expr, $($arg:tt)+) => ({
+        static STATE: $crate::ratelimit::RatelimitState = $crate::ratelimit::RatelimitState::new(
+            $crate::ratelimit::DEFAULT_INTERVAL,
+            $crate::ratelimit::DEFAULT_BURST,
+        );
+
+        $crate::print_macro_ratelimited!(@emit $format_string, &STATE, $name, $($arg)+)
+    });
+
+    ($format_string:path, state: $state:expr, name: $name:expr, $($arg:tt)+) => (
+        $crate::print_macro_ratelimited!(@emit $format_string, $state, $name, $($arg)+)
+    );
+
+    ($format_string:path, state: $state:expr, $($arg:tt)+) => (
+        $crate::print_macro_ratelimited!(
+            @emit $format_string,
+            $state,
+            core::concat!(core::module_path!(), ":", core::line!()),
+            $($arg)+
+        )
+    );
+
+    ($format_string:path, name: $name:expr, $($arg:tt)+) => (
+        $crate::print_macro_ratelimited!(@static $format_string, $name, $($arg)+)
+    );
+
+    ($format_string:path, $($arg:tt)+) => (
+        $crate::print_macro_ratelimited!(
+            @static $format_string,
+            core::concat!(core::module_path!(), ":", core::line!()),
+            $($arg)+
+        )
+    );
+);
+
+/// Stub for doctests
+#[cfg(testlib)]
+#[macro_export]
+macro_rules! print_macro_ratelimited (
+    ($($arg:tt)+) => (
+        ()
+    );
+);
+
+/// Prints an emergency-level message (level 0), rate limited.
+///
+/// Equivalent to the kernel's [`pr_emerg_ratelimited`] macro. At most
+/// [`ratelimit::DEFAULT_BURST`] messages are printed every [`ratelimit::DEFAULT_INTERVAL`]
+/// jiffies from each call site. A [`RatelimitState`] may be given with `state:` instead, for
+/// example, to share it among several call sites or to use a different interval or burst.
+///
+/// The caller is identified by its module path and line in the message that reports the number
+/// of suppressed messages. A different name may be given with `name:`. The rest mimics the
+/// interface of [`std::print!`].
+///
+/// [`pr_emerg_ratelimited`]: https://www.kernel.org/doc/html/latest/core-api/printk-basics.html
+/// [`ratelimit::DEFAULT_BURST`]: crate::ratelimit::DEFAULT_BURST
+/// [`ratelimit::DEFAULT_INTERVAL`]: crate::ratelimit::DEFAULT_INTERVAL
+/// [`RatelimitState`]: crate::ratelimit::RatelimitState
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_emerg_ratelimited;
+/// pr_emerg_ratelimited!("hello {}\n", "there");
+/// pr_emerg_ratelimited!(name: "example", "hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_emerg_ratelimited (
+    ($($arg:tt)*) => (
+        $crate::print_macro_ratelimited!($crate::print::format_strings::EMERG, $($arg)*)
+    )
+);
+
+/// Prints an alert-level message (level 1), rate limited.
+///
+/// Equivalent to the kernel's [`pr_alert_ratelimited`] macro. At most
+/// [`ratelimit::DEFAULT_BURST`] messages are printed every [`ratelimit::DEFAULT_INTERVAL`]
+/// jiffies from each call site. A [`RatelimitState`] may be given with `state:` instead, for
+/// example, to share it among several call sites or to use a different interval or burst.
+///
+/// The caller is identified by its module path and line in the message that reports the number
+/// of suppressed messages. A different name may be given with `name:`. The rest mimics the
+/// interface of [`std::print!`].
+///
+/// [`pr_alert_ratelimited`]: https://www.kernel.org/doc/html/latest/core-api/printk-basics.html
+/// [`ratelimit::DEFAULT_BURST`]: crate::ratelimit::DEFAULT_BURST
+/// [`ratelimit::DEFAULT_INTERVAL`]: crate::ratelimit::DEFAULT_INTERVAL
+/// [`RatelimitState`]: crate::ratelimit::RatelimitState
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_alert_ratelimited;
+/// pr_alert_ratelimited!("hello {}\n", "there");
+/// pr_alert_ratelimited!(name: "example", "hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_alert_ratelimited (
+    ($($arg:tt)*) => (
+        $crate::print_macro_ratelimited!($crate::print::format_strings::ALERT, $($arg)*)
+    )
+);
+
+/// Prints a critical-level message (level 2), rate limited.
+///
+/// Equivalent to the kernel's [`pr_crit_ratelimited`] macro. At most
+/// [`ratelimit::DEFAULT_BURST`] messages are printed every [`ratelimit::DEFAULT_INTERVAL`]
+/// jiffies from each call site. A [`RatelimitState`] may be given with `state:` instead, for
+/// example, to share it among several call sites or to use a different interval or burst.
+///
+/// The caller is identified by its module path and line in the message that reports the number
+/// of suppressed messages. A different name may be given with `name:`. The rest mimics the
+/// interface of [`std::print!`].
+///
+/// [`pr_crit_ratelimited`]: https://www.kernel.org/doc/html/latest/core-api/printk-basics.html
+/// [`ratelimit::DEFAULT_BURST`]: crate::ratelimit::DEFAULT_BURST
+/// [`ratelimit::DEFAULT_INTERVAL`]: crate::ratelimit::DEFAULT_INTERVAL
+/// [`RatelimitState`]: crate::ratelimit::RatelimitState
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_crit_ratelimited;
+/// pr_crit_ratelimited!("hello {}\n", "there");
+/// pr_crit_ratelimited!(name: "example", "hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_crit_ratelimited (
+    ($($arg:tt)*) => (
+        $crate::print_macro_ratelimited!($crate::print::format_strings::CRIT, $($arg)*)
+    )
+);
+
+/// Prints an error-level message (level 3), rate limited.
+///
+/// Equivalent to the kernel's [`pr_err_ratelimited`] macro. At most
+/// [`ratelimit::DEFAULT_BURST`] messages are printed every [`ratelimit::DEFAULT_INTERVAL`]
+/// jiffies from each call site. A [`RatelimitState`] may be given with `state:` instead, for
+/// example, to share it among several call sites or to use a different interval or burst.
+///
+/// The caller is identified by its module path and line in the message that reports the number
+/// of suppressed messages. A different name may be given with `name:`. The rest mimics the
+/// interface of [`std::print!`].
+///
+/// [`pr_err_ratelimited`]: https://www.kernel.org/doc/html/latest/core-api/printk-basics.html
+/// [`ratelimit::DEFAULT_BURST`]: crate::ratelimit::DEFAULT_BURST
+/// [`ratelimit::DEFAULT_INTERVAL`]: crate::ratelimit::DEFAULT_INTERVAL
+/// [`RatelimitState`]: crate::ratelimit::RatelimitState
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_err_ratelimited;
+/// pr_err_ratelimited!("hello {}\n", "there");
+/// pr_err_ratelimited!(name: "example", "hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_err_ratelimited (
+    ($($arg:tt)*) => (
+        $crate::print_macro_ratelimited!($crate::print::format_strings::ERR, $($arg)*)
+    )
+);
+
+/// Prints a warning-level message (level 4), rate limited.
+///
+/// Equivalent to the kernel's [`pr_warn_ratelimited`] macro. At most
+/// [`ratelimit::DEFAULT_BURST`] messages are printed every [`ratelimit::DEFAULT_INTERVAL`]
+/// jiffies from each call site. A [`RatelimitState`] may be given with `state:` instead, for
+/// example, to share it among several call sites or to use a different interval or burst.
+///
+/// The caller is identified by its module path and line in the message that reports the number
+/// of suppressed messages. A different name may be given with `name:`. The rest mimics the
+/// interface of [`std::print!`].
+///
+/// [`pr_warn_ratelimited`]: https://www.kernel.org/doc/html/latest/core-api/printk-basics.html
+/// [`ratelimit::DEFAULT_BURST`]: crate::ratelimit::DEFAULT_BURST
+/// [`ratelimit::DEFAULT_INTERVAL`]: crate::ratelimit::DEFAULT_INTERVAL
+/// [`RatelimitState`]: crate::ratelimit::RatelimitState
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_warn_ratelimited;
+/// pr_warn_ratelimited!("hello {}\n", "there");
+/// pr_warn_ratelimited!(name: "example", "hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_warn_ratelimited (
+    ($($arg:tt)*) => (
+        $crate::print_macro_ratelimited!($crate::print::format_strings::WARNING, $($arg)*)
+    )
+);
+
+/// Prints a notice-level message (level 5), rate limited.
+///
+/// Equivalent to the kernel's [`pr_notice_ratelimited`] macro. At most
+/// [`ratelimit::DEFAULT_BURST`] messages are printed every [`ratelimit::DEFAULT_INTERVAL`]
+/// jiffies from each call site. A [`RatelimitState`] may be given with `state:` instead, for
+/// example, to share it among several call sites or to use a different interval or burst.
+///
+/// The caller is identified by its module path and line in the message that reports the number
+/// of suppressed messages. A different name may be given with `name:`. The rest mimics the
+/// interface of [`std::print!`].
+///
+/// [`pr_notice_ratelimited`]: https://www.kernel.org/doc/html/latest/core-api/printk-basics.html
+/// [`ratelimit::DEFAULT_BURST`]: crate::ratelimit::DEFAULT_BURST
+/// [`ratelimit::DEFAULT_INTERVAL`]: crate::ratelimit::DEFAULT_INTERVAL
+/// [`RatelimitState`]: crate::ratelimit::RatelimitState
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_notice_ratelimited;
+/// pr_notice_ratelimited!("hello {}\n", "there");
+/// pr_notice_ratelimited!(name: "example", "hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_notice_ratelimited (
+    ($($arg:tt)*) => (
+        $crate::print_macro_ratelimited!($crate::print::format_strings::NOTICE, $($arg)*)
+    )
+);
+
+/// Prints an info-level message (level 6), rate limited.
+///
+/// Equivalent to the kernel's [`pr_info_ratelimited`] macro. At most
+/// [`ratelimit::DEFAULT_BURST`] messages are printed every [`ratelimit::DEFAULT_INTERVAL`]
+/// jiffies from each call site. A [`RatelimitState`] may be given with `state:` instead, for
+/// example, to share it among several call sites or to use a different interval or burst.
+///
+/// The caller is identified by its module path and line in the message that reports the number
+/// of suppressed messages. A different name may be given with `name:`. The rest mimics the
+/// interface of [`std::print!`].
+///
+/// [`pr_info_ratelimited`]: https://www.kernel.org/doc/html/latest/core-api/printk-basics.html
+/// [`ratelimit::DEFAULT_BURST`]: crate::ratelimit::DEFAULT_BURST
+/// [`ratelimit::DEFAULT_INTERVAL`]: crate::ratelimit::DEFAULT_INTERVAL
+/// [`RatelimitState`]: crate::ratelimit::RatelimitState
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_info_ratelimited;
+/// pr_info_ratelimited!("hello {}\n", "there");
+/// pr_info_ratelimited!(name: "example", "hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_info_ratelimited (
+    ($($arg:tt)*) => (
+        $crate::print_macro_ratelimited!($crate::print::format_strings::INFO, $($arg)*)
+    )
+);
+
+/// Prints a debug-level message (level 7), rate limited.
+///
+/// Equivalent to the kernel's [`pr_debug_ratelimited`] macro. At most
+/// [`ratelimit::DEFAULT_BURST`] messages are printed every [`ratelimit::DEFAULT_INTERVAL`]
+/// jiffies from each call site. A [`RatelimitState`] may be given with `state:` instead, for
+/// example, to share it among several call sites or to use a different interval or burst.
+///
+/// The caller is identified by its module path and line in the message that reports the number
+/// of suppressed messages. A different name may be given with `name:`. The rest mimics the
+/// interface of [`std::print!`].
+///
+/// Messages are only printed if `debug_assertions` is enabled.
+///
+/// [`pr_debug_ratelimited`]: https://www.kernel.org/doc/html/latest/core-api/printk-basics.html
+/// [`ratelimit::DEFAULT_BURST`]: crate::ratelimit::DEFAULT_BURST
+/// [`ratelimit::DEFAULT_INTERVAL`]: crate::ratelimit::DEFAULT_INTERVAL
+/// [`RatelimitState`]: crate::ratelimit::RatelimitState
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_debug_ratelimited;
+/// pr_debug_ratelimited!("hello {}\n", "there");
+/// pr_debug_ratelimited!(name: "example", "hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_debug_ratelimited (
+    ($($arg:tt)*) => (
+        if cfg!(debug_assertions) {
+            $crate::print_macro_ratelimited!($crate::print::format_strings::DEBUG, $($arg)*)
+        }
+    )
+);
+
+/// Performs formatting and forwards the string to [`call_printk_deferred`].
+///
+/// Public but hidden since it should only be used from public macros.
+#[doc(hidden)]
+#[cfg(not(testlib))]
+#[macro_export]
+macro_rules! print_macro_deferred (
+    ($format_string:path, $($arg:tt)+) => (
+        // SAFETY: This hidden macro should only be called by the documented
+        // printing macros which ensure the format string is one of the fixed
+        // ones. All `__LOG_PREFIX`s are null-terminated as they are generated
+        // by the `module!` proc macro or fixed values defined in a kernel
+        // crate.
+        unsafe {
+            $crate::print::call_printk_deferred(
+                &$format_string,
+                crate::__LOG_PREFIX,
+                format_args!($($arg)+),
+            );
+        }
+    );
+);
+
+/// Stub for doctests
+#[cfg(testlib)]
+#[macro_export]
+macro_rules! print_macro_deferred (
+    ($format_string:path, $($arg:tt)+) => (
+        ()
+    );
+);
+
+/// Prints an emergency-level message (level 0), deferring the console output.
+///
+/// Equivalent to the kernel's `printk_deferred` with the `KERN_EMERG` level. Unlike
+/// [`pr_emerg`], it does not call into the console drivers, so it is safe to use from contexts
+/// where that could deadlock, e.g., while holding a runqueue lock.
+///
+/// Mimics the interface of [`std::print!`]. See [`core::fmt`] and
+/// `alloc::format!` for information about the formatting syntax.
+///
+/// [`pr_emerg`]: crate::pr_emerg
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_emerg_deferred;
+/// pr_emerg_deferred!("hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_emerg_deferred (
+    ($($arg:tt)*) => (
+        $crate::print_macro_deferred!($crate::print::format_strings::EMERG, $($arg)*)
+    )
+);
+
+/// Prints an alert-level message (level 1), deferring the console output.
+///
+/// Equivalent to the kernel's `printk_deferred` with the `KERN_ALERT` level. Unlike
+/// [`pr_alert`], it does not call into the console drivers, so it is safe to use from contexts
+/// where that could deadlock, e.g., while holding a runqueue lock.
+///
+/// Mimics the interface of [`std::print!`]. See [`core::fmt`] and
+/// `alloc::format!` for information about the formatting syntax.
+///
+/// [`pr_alert`]: crate::pr_alert
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_alert_deferred;
+/// pr_alert_deferred!("hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_alert_deferred (
+    ($($arg:tt)*) => (
+        $crate::print_macro_deferred!($crate::print::format_strings::ALERT, $($arg)*)
+    )
+);
+
+/// Prints a critical-level message (level 2), deferring the console output.
+///
+/// Equivalent to the kernel's `printk_deferred` with the `KERN_CRIT` level. Unlike
+/// [`pr_crit`], it does not call into the console drivers, so it is safe to use from contexts
+/// where that could deadlock, e.g., while holding a runqueue lock.
+///
+/// Mimics the interface of [`std::print!`]. See [`core::fmt`] and
+/// `alloc::format!` for information about the formatting syntax.
+///
+/// [`pr_crit`]: crate::pr_crit
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_crit_deferred;
+/// pr_crit_deferred!("hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_crit_deferred (
+    ($($arg:tt)*) => (
+        $crate::print_macro_deferred!($crate::print::format_strings::CRIT, $($arg)*)
+    )
+);
+
+/// Prints an error-level message (level 3), deferring the console output.
+///
+/// Equivalent to the kernel's `printk_deferred` with the `KERN_ERR` level. Unlike
+/// [`pr_err`], it does not call into the console drivers, so it is safe to use from contexts
+/// where that could deadlock, e.g., while holding a runqueue lock.
+///
+/// Mimics the interface of [`std::print!`]. See [`core::fmt`] and
+/// `alloc::format!` for information about the formatting syntax.
+///
+/// [`pr_err`]: crate::pr_err
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_err_deferred;
+/// pr_err_deferred!("hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_err_deferred (
+    ($($arg:tt)*) => (
+        $crate::print_macro_deferred!($crate::print::format_strings::ERR, $($arg)*)
+    )
+);
+
+/// Prints a warning-level message (level 4), deferring the console output.
+///
+/// Equivalent to the kernel's `printk_deferred` with the `KERN_WARNING` level. Unlike
+/// [`pr_warn`], it does not call into the console drivers, so it is safe to use from contexts
+/// where that could deadlock, e.g., while holding a runqueue lock.
+///
+/// Mimics the interface of [`std::print!`]. See [`core::fmt`] and
+/// `alloc::format!` for information about the formatting syntax.
+///
+/// [`pr_warn`]: crate::pr_warn
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_warn_deferred;
+/// pr_warn_deferred!("hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_warn_deferred (
+    ($($arg:tt)*) => (
+        $crate::print_macro_deferred!($crate::print::format_strings::WARNING, $($arg)*)
+    )
+);
+
+/// Prints a notice-level message (level 5), deferring the console output.
+///
+/// Equivalent to the kernel's `printk_deferred` with the `KERN_NOTICE` level. Unlike
+/// [`pr_notice`], it does not call into the console drivers, so it is safe to use from contexts
+/// where that could deadlock, e.g., while holding a runqueue lock.
+///
+/// Mimics the interface of [`std::print!`]. See [`core::fmt`] and
+/// `alloc::format!` for information about the formatting syntax.
+///
+/// [`pr_notice`]: crate::pr_notice
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_notice_deferred;
+/// pr_notice_deferred!("hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_notice_deferred (
+    ($($arg:tt)*) => (
+        $crate::print_macro_deferred!($crate::print::format_strings::NOTICE, $($arg)*)
+    )
+);
+
+/// Prints an info-level message (level 6), deferring the console output.
+///
+/// Equivalent to the kernel's `printk_deferred` with the `KERN_INFO` level. Unlike
+/// [`pr_info`], it does not call into the console drivers, so it is safe to use from contexts
+/// where that could deadlock, e.g., while holding a runqueue lock.
+///
+/// Mimics the interface of [`std::print!`]. See [`core::fmt`] and
+/// `alloc::format!` for information about the formatting syntax.
+///
+/// [`pr_info`]: crate::pr_info
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_info_deferred;
+/// pr_info_deferred!("hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_info_deferred (
+    ($($arg:tt)*) => (
+        $crate::print_macro_deferred!($crate::print::format_strings::INFO, $($arg)*)
+    )
+);
+
+/// Prints a debug-level message (level 7), deferring the console output.
+///
+/// Equivalent to the kernel's `printk_deferred` with the `KERN_DEBUG` level. Unlike
+/// [`pr_debug`], it does not call into the console drivers, so it is safe to use from contexts
+/// where that could deadlock, e.g., while holding a runqueue lock.
+///
+/// Mimics the interface of [`std::print!`]. See [`core::fmt`] and
+/// `alloc::format!` for information about the formatting syntax.
+///
+/// Messages are only printed if `debug_assertions` is enabled.
+///
+/// [`pr_debug`]: crate::pr_debug
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::pr_debug_deferred;
+/// pr_debug_deferred!("hello {}\n", "there");
+/// ```
+#[macro_export]
+macro_rules! pr_debug_deferred (
+    ($($arg:tt)*) => (
+        if cfg!(debug_assertions) {
+            $crate::print_macro_deferred!($crate::print::format_strings::DEBUG, $($arg)*)
+        }
+    )
+);
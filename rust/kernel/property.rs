@@ -0,0 +1,297 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Firmware node properties.
+//!
+//! A [`FwNode`] is a node of the firmware description of the hardware, either the devicetree or
+//! ACPI, whose properties are read the same way regardless of where they come from. The node of
+//! a device is returned by [`RawDevice::fwnode`].
+//!
+//! C header: [`include/linux/property.h`](../../../../include/linux/property.h)
+//!
+//! [`RawDevice::fwnode`]: crate::device::RawDevice::fwnode
+
+use crate::{
+    bindings,
+    error::{code::*, from_kernel_err_ptr, to_result, Error},
+    str::CStr,
+    ARef, AlwaysRefCounted, Opaque, Result,
+};
+use alloc::vec::Vec;
+use core::ptr::{self, NonNull};
+
+/// Wraps the kernel's `struct fwnode_handle`.
+///
+/// # Invariants
+///
+/// Instances of this type are always ref-counted, that is, a call to `fwnode_handle_get` ensures
+/// that the allocation remains valid at least until the matching call to `fwnode_handle_put`.
+///
+/// # Examples
+///
+/// Parsing the devicetree binding of a device:
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::{c_str, device::RawDevice};
+///
+/// fn parse(dev: &dyn RawDevice) -> Result {
+///     let node = dev.fwnode().ok_or(ENODEV)?;
+///     let width = node.read_u32(c_str!("bus-width")).unwrap_or(4);
+///     let non_removable = node.read_bool(c_str!("non-removable"));
+///     let label = node.read_string(c_str!("label"))?;
+///     for child in node.children() {
+///         let reg = child.read_u32(c_str!("reg"))?;
+///         pr_info!("{}: slot {} ({}, {})\n", label, reg, width, non_removable);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[repr(transparent)]
+pub struct FwNode(Opaque<bindings::fwnode_handle>);
+
+// SAFETY: Firmware nodes are reference-counted and their functions are safe to call from any
+// thread.
+unsafe impl Send for FwNode {}
+
+// SAFETY: The properties of firmware nodes are not modified once the nodes are published.
+unsafe impl Sync for FwNode {}
+
+impl FwNode {
+    /// Creates a reference to a [`FwNode`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that `ptr` is valid and remains valid for the lifetime of the
+    /// returned [`FwNode`] reference.
+    pub unsafe fn from_ptr<'a>(ptr: *const bindings::fwnode_handle) -> &'a Self {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `FwNode` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Takes ownership of a reference to a node returned by the C API, if not null.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be null or valid, with a reference owned by the caller.
+    unsafe fn from_owned(ptr: *mut bindings::fwnode_handle) -> Option<ARef<Self>> {
+        // SAFETY: The safety requirements guarantee that the reference can be owned by `ARef`.
+        NonNull::new(ptr).map(|p| unsafe { ARef::from_raw(p.cast()) })
+    }
+
+    /// Returns a raw pointer to the underlying `struct fwnode_handle`.
+    pub fn as_ptr(&self) -> *mut bindings::fwnode_handle {
+        self.0.get()
+    }
+
+    /// Returns the name of the node, e.g., `i2c@7e205000`.
+    pub fn name(&self) -> &CStr {
+        // SAFETY: The node is valid, and its name lives as long as it.
+        let name = unsafe { bindings::fwnode_get_name(self.as_ptr()) };
+        if name.is_null() {
+            return crate::c_str!("");
+        }
+
+        // SAFETY: `name` is a valid C string that lives as long as the node.
+        unsafe { CStr::from_char_ptr(name) }
+    }
+
+    /// Returns whether the node has the given property.
+    pub fn property_present(&self, name: &CStr) -> bool {
+        // SAFETY: The node is valid, and `name` is a valid C string.
+        unsafe { bindings::fwnode_property_present(self.as_ptr(), name.as_char_ptr()) }
+    }
+
+    /// Reads a boolean property, which is `true` if the property is present.
+    pub fn read_bool(&self, name: &CStr) -> bool {
+        self.property_present(name)
+    }
+
+    /// Reads a `u32` property.
+    ///
+    /// Returns [`EINVAL`] if the property doesn't exist and [`EPROTO`] if it isn't a `u32`.
+    pub fn read_u32(&self, name: &CStr) -> Result<u32> {
+        let mut value = 0;
+        self.read_u32_array(name, core::slice::from_mut(&mut value))?;
+        Ok(value)
+    }
+
+    /// Reads a `u64` property.
+    pub fn read_u64(&self, name: &CStr) -> Result<u64> {
+        let mut value = 0;
+        // SAFETY: The node is valid, `name` is a valid C string, and `value` is valid for writes
+        // of one `u64`.
+        to_result(|| unsafe {
+            bindings::fwnode_property_read_u64_array(
+                self.as_ptr(),
+                name.as_char_ptr(),
+                &mut value,
+                1,
+            )
+        })?;
+        Ok(value)
+    }
+
+    /// Reads the first `values.len()` elements of a `u32` array property into `values`.
+    ///
+    /// Returns [`EOVERFLOW`] if the property has fewer elements.
+    pub fn read_u32_array(&self, name: &CStr, values: &mut [u32]) -> Result {
+        // SAFETY: The node is valid, `name` is a valid C string, and `values` is valid for writes
+        // of its length.
+        to_result(|| unsafe {
+            bindings::fwnode_property_read_u32_array(
+                self.as_ptr(),
+                name.as_char_ptr(),
+                values.as_mut_ptr(),
+                values.len(),
+            )
+        })
+    }
+
+    /// Returns the number of elements of a `u32` array property.
+    pub fn count_u32(&self, name: &CStr) -> Result<usize> {
+        // SAFETY: The node is valid, and `name` is a valid C string. A null buffer asks for the
+        // number of elements.
+        let ret = unsafe {
+            bindings::fwnode_property_read_u32_array(
+                self.as_ptr(),
+                name.as_char_ptr(),
+                ptr::null_mut(),
+                0,
+            )
+        };
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+        Ok(ret as _)
+    }
+
+    /// Reads all the elements of a `u32` array property.
+    pub fn read_u32_vec(&self, name: &CStr) -> Result<Vec<u32>> {
+        let mut values = Vec::new();
+        values.try_resize(self.count_u32(name)?, 0)?;
+        self.read_u32_array(name, &mut values)?;
+        Ok(values)
+    }
+
+    /// Reads a string property, or the first string of a string array property.
+    pub fn read_string(&self, name: &CStr) -> Result<&CStr> {
+        let mut value = ptr::null();
+        // SAFETY: The node is valid, `name` is a valid C string, and `value` is valid for writes.
+        to_result(|| unsafe {
+            bindings::fwnode_property_read_string(self.as_ptr(), name.as_char_ptr(), &mut value)
+        })?;
+
+        // SAFETY: On success, `value` is a valid C string that lives as long as the node.
+        Ok(unsafe { CStr::from_char_ptr(value) })
+    }
+
+    /// Returns the index of `s` in a string array property, e.g., to find the index of a named
+    /// clock in `clock-names`.
+    pub fn match_string(&self, name: &CStr, s: &CStr) -> Result<usize> {
+        // SAFETY: The node is valid, and `name` and `s` are valid C strings.
+        let ret = unsafe {
+            bindings::fwnode_property_match_string(
+                self.as_ptr(),
+                name.as_char_ptr(),
+                s.as_char_ptr(),
+            )
+        };
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+        Ok(ret as _)
+    }
+
+    /// Returns the parent of the node, if any.
+    pub fn parent(&self) -> Option<ARef<Self>> {
+        // SAFETY: The node is valid, and `fwnode_get_parent` returns a new reference.
+        unsafe { Self::from_owned(bindings::fwnode_get_parent(self.as_ptr())) }
+    }
+
+    /// Returns the child of the node with the given name, if any.
+    pub fn child(&self, name: &CStr) -> Option<ARef<Self>> {
+        // SAFETY: The node is valid, `name` is a valid C string, and
+        // `fwnode_get_named_child_node` returns a new reference.
+        unsafe {
+            Self::from_owned(bindings::fwnode_get_named_child_node(
+                self.as_ptr(),
+                name.as_char_ptr(),
+            ))
+        }
+    }
+
+    /// Returns an iterator over the children of the node.
+    pub fn children(&self) -> Children<'_> {
+        Children {
+            parent: self,
+            child: ptr::null_mut(),
+        }
+    }
+
+    /// Returns the node referenced by the index-th phandle of the given property, e.g., the
+    /// `index`-th entry of `clocks`, ignoring the argument cells.
+    ///
+    /// Returns [`ENOENT`] if there is no such reference.
+    pub fn find_reference(&self, name: &CStr, index: u32) -> Result<ARef<Self>> {
+        // SAFETY: The node is valid, `name` is a valid C string, and `fwnode_find_reference`
+        // returns a new reference on success.
+        let ptr = from_kernel_err_ptr(unsafe {
+            bindings::fwnode_find_reference(self.as_ptr(), name.as_char_ptr(), index)
+        })?;
+
+        // SAFETY: `ptr` is a reference owned by the caller, as above.
+        unsafe { Self::from_owned(ptr) }.ok_or(ENOENT)
+    }
+}
+
+// SAFETY: The type invariants guarantee that `FwNode` is always ref-counted.
+unsafe impl AlwaysRefCounted for FwNode {
+    fn inc_ref(&self) {
+        // SAFETY: The existence of a shared reference means that the refcount is nonzero.
+        unsafe { bindings::fwnode_handle_get(self.as_ptr()) };
+    }
+
+    unsafe fn dec_ref(obj: NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee that the refcount is nonzero.
+        unsafe { bindings::fwnode_handle_put(obj.cast().as_ptr()) };
+    }
+}
+
+/// An iterator over the children of a [`FwNode`], returned by [`FwNode::children`].
+///
+/// # Invariants
+///
+/// `child` is either null or a child of `parent` with a reference owned by the iterator.
+pub struct Children<'a> {
+    parent: &'a FwNode,
+    child: *mut bindings::fwnode_handle,
+}
+
+impl Iterator for Children<'_> {
+    type Item = ARef<FwNode>;
+
+    fn next(&mut self) -> Option<ARef<FwNode>> {
+        // SAFETY: The parent is valid. By the type invariants, `child` is null or a child of
+        // `parent` whose reference is released by `fwnode_get_next_child_node`, which returns a
+        // new reference to the next child.
+        let next =
+            unsafe { bindings::fwnode_get_next_child_node(self.parent.as_ptr(), self.child) };
+
+        // INVARIANT: The iterator owns the reference returned above.
+        self.child = next;
+
+        // SAFETY: `next` is null or a valid node, and an extra reference is taken for the caller.
+        let child = unsafe { FwNode::from_ptr(NonNull::new(next)?.as_ptr()) };
+        Some(child.into())
+    }
+}
+
+impl Drop for Children<'_> {
+    fn drop(&mut self) {
+        if !self.child.is_null() {
+            // SAFETY: By the type invariants, the iterator owns a reference to `child`.
+            unsafe { bindings::fwnode_handle_put(self.child) };
+        }
+    }
+}
@@ -37,6 +37,24 @@ pub fn getrandom_nonblock(dest: &mut [u8]) -> Result {
     getrandom(dest)
 }
 
+/// Returns a random `u32` generated from the kernel's CSPRNG, e.g., for a nonce or an id.
+///
+/// Unlike [`getrandom`], it doesn't wait for the CSPRNG to be seeded, so it shouldn't be used for
+/// long-term keys early during boot.
+pub fn get_random_u32() -> u32 {
+    // SAFETY: FFI call without safety requirements.
+    unsafe { bindings::get_random_u32() }
+}
+
+/// Returns a random `u64` generated from the kernel's CSPRNG, e.g., for a nonce or an id.
+///
+/// Unlike [`getrandom`], it doesn't wait for the CSPRNG to be seeded, so it shouldn't be used for
+/// long-term keys early during boot.
+pub fn get_random_u64() -> u64 {
+    // SAFETY: FFI call without safety requirements.
+    unsafe { bindings::get_random_u64() }
+}
+
 /// Contributes the contents of a byte slice to the kernel's entropy pool.
 ///
 /// Does *not* credit the kernel entropy counter though.
@@ -0,0 +1,118 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Rate limiting.
+//!
+//! C header: [`include/linux/ratelimit.h`](../../../../include/linux/ratelimit.h)
+
+use crate::{
+    bindings, c_types,
+    str::CStr,
+    sync::{Atomic32, Atomic64},
+    time::{self, Jiffies},
+};
+
+/// The default interval of a [`RatelimitState`], in jiffies.
+pub const DEFAULT_INTERVAL: c_types::c_int = bindings::DEFAULT_RATELIMIT_INTERVAL;
+
+/// The default number of events allowed per interval by a [`RatelimitState`].
+pub const DEFAULT_BURST: c_types::c_int = bindings::DEFAULT_RATELIMIT_BURST;
+
+/// A rate limit state, like the kernel's `struct ratelimit_state`.
+///
+/// It allows at most `burst` events every `interval` jiffies; further events within the same
+/// interval are suppressed and the number of suppressed events is reported when a new interval
+/// starts.
+///
+/// Unlike the C version, it is implemented with atomics instead of a spinlock, so it can be
+/// created in `const` contexts, e.g., for statics. Events that race with the start of a new
+/// interval may be counted in either interval.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// # use kernel::{c_str, pr_warn_ratelimited, ratelimit::{self, RatelimitState}};
+/// struct Device {
+///     rs: RatelimitState,
+/// }
+///
+/// impl Device {
+///     fn new() -> Self {
+///         // At most 2 messages every two default intervals.
+///         Self {
+///             rs: RatelimitState::new(2 * ratelimit::DEFAULT_INTERVAL, 2),
+///         }
+///     }
+///
+///     fn on_error(&self) {
+///         if self.rs.check(c_str!("on_error")) {
+///             pr_warn!("device error\n");
+///         }
+///
+///         // Equivalently, with the rate limited printing macros.
+///         pr_warn_ratelimited!(state: &self.rs, name: "on_error", "device error\n");
+///     }
+/// }
+/// ```
+pub struct RatelimitState {
+    interval: c_types::c_int,
+    burst: c_types::c_int,
+    begin: Atomic64,
+    printed: Atomic32,
+    missed: Atomic32,
+}
+
+impl RatelimitState {
+    /// Creates a new rate limit state allowing `burst` events every `interval` jiffies.
+    ///
+    /// If `interval` is zero, all events are allowed.
+    pub const fn new(interval: c_types::c_int, burst: c_types::c_int) -> Self {
+        Self {
+            interval,
+            burst,
+            begin: Atomic64::new(0),
+            printed: Atomic32::new(0),
+            missed: Atomic32::new(0),
+        }
+    }
+
+    /// Returns `true` if the event is allowed, or `false` if it should be suppressed.
+    ///
+    /// `name` identifies the caller in the message that reports the number of suppressed events.
+    ///
+    /// Equivalent to the kernel's `__ratelimit`.
+    pub fn check(&self, name: &CStr) -> bool {
+        if self.interval == 0 {
+            return true;
+        }
+
+        let now = time::jiffies();
+
+        // The first event starts the first interval; if several race, they all use the start
+        // stored by the one that won.
+        let mut begin = self.begin.read();
+        if begin == 0 {
+            begin = match self.begin.cmpxchg(0, now as _) {
+                0 => now as _,
+                old => old,
+            };
+        }
+
+        // Only the event that starts a new interval reports and resets the counters.
+        let end = (begin as Jiffies).wrapping_add(self.interval as _);
+        if time::time_after(now, end) && self.begin.cmpxchg(begin, now as _) == begin {
+            let missed = self.missed.xchg(0);
+            if missed > 0 {
+                crate::pr_warn!("{}: {} callbacks suppressed\n", name, missed);
+            }
+            self.printed.set(0);
+        }
+
+        if self.burst > 0 && self.printed.add_unless(1, self.burst) {
+            true
+        } else {
+            self.missed.inc();
+            false
+        }
+    }
+}
@@ -189,6 +189,38 @@ struct Node<K, V> {
 ///     Ok(())
 /// }
 /// ```
+///
+/// Ordered lookups make it suitable, for example, for extent maps:
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::rbtree::RBTree;
+///
+/// /// Maps the start offset of each extent to its length.
+/// fn extent_test() -> Result {
+///     let mut extents = RBTree::new();
+///     extents.try_insert(0u64, 4096u64)?;
+///     extents.try_insert(8192, 4096)?;
+///
+///     // Find the extent that may contain an offset.
+///     let contains = |offset| match extents.floor(&offset) {
+///         Some((start, len)) => offset < start + len,
+///         None => false,
+///     };
+///     assert!(contains(100));
+///     assert!(!contains(5000));
+///     assert!(contains(9000));
+///
+///     // Visit the extents that start at or after an offset.
+///     let mut iter = extents.iter_from(&100);
+///     assert_eq!(iter.next().unwrap(), (&8192, &4096));
+///     assert!(iter.next().is_none());
+///
+///     assert_eq!(extents.first().unwrap(), (&0, &4096));
+///     assert_eq!(extents.last().unwrap(), (&8192, &4096));
+///     Ok(())
+/// }
+/// ```
 pub struct RBTree<K, V> {
     root: bindings::rb_root,
     _p: PhantomData<Node<K, V>>,
@@ -308,6 +340,82 @@ impl<K, V> RBTree<K, V> {
         None
     }
 
+    /// Returns the node with the greatest key that is less than or equal to the given key if
+    /// `floor` is `true`, or the node with the smallest key that is greater than or equal to it
+    /// otherwise.
+    fn find_bound(&self, key: &K, floor: bool) -> *mut bindings::rb_node
+    where
+        K: Ord,
+    {
+        let mut node = self.root.rb_node;
+        let mut best = core::ptr::null_mut();
+        while !node.is_null() {
+            let this = crate::container_of!(node, Node<K, V>, links);
+            // SAFETY: `this` is a non-null node so it is valid by the type invariants.
+            node = match key.cmp(unsafe { &(*this).key }) {
+                Ordering::Less => {
+                    if !floor {
+                        best = node;
+                    }
+                    // SAFETY: `node` is a non-null node so it is valid by the type invariants.
+                    unsafe { (*node).rb_left }
+                }
+                Ordering::Greater => {
+                    if floor {
+                        best = node;
+                    }
+                    // SAFETY: `node` is a non-null node so it is valid by the type invariants.
+                    unsafe { (*node).rb_right }
+                }
+                Ordering::Equal => return node,
+            }
+        }
+        best
+    }
+
+    /// Returns the key/value pair at the given node, if it's not null.
+    fn entry(&self, node: *mut bindings::rb_node) -> Option<(&K, &V)> {
+        if node.is_null() {
+            return None;
+        }
+
+        let this = crate::container_of!(node, Node<K, V>, links);
+        // SAFETY: `this` is a non-null node so it is valid by the type invariants, and it remains
+        // valid while `self` is borrowed.
+        Some(unsafe { (&(*this).key, &(*this).value) })
+    }
+
+    /// Returns the entry with the greatest key that is less than or equal to the given key.
+    ///
+    /// For example, in a tree of extents keyed by their start offsets, this returns the extent
+    /// that may contain a given offset.
+    pub fn floor(&self, key: &K) -> Option<(&K, &V)>
+    where
+        K: Ord,
+    {
+        self.entry(self.find_bound(key, true))
+    }
+
+    /// Returns the entry with the smallest key that is greater than or equal to the given key.
+    pub fn ceiling(&self, key: &K) -> Option<(&K, &V)>
+    where
+        K: Ord,
+    {
+        self.entry(self.find_bound(key, false))
+    }
+
+    /// Returns the entry with the smallest key.
+    pub fn first(&self) -> Option<(&K, &V)> {
+        // SAFETY: `root` is valid as it's embedded in `self` and we have a valid `self`.
+        self.entry(unsafe { bindings::rb_first(&self.root) })
+    }
+
+    /// Returns the entry with the greatest key.
+    pub fn last(&self) -> Option<(&K, &V)> {
+        // SAFETY: `root` is valid as it's embedded in `self` and we have a valid `self`.
+        self.entry(unsafe { bindings::rb_last(&self.root) })
+    }
+
     /// Returns a reference to the value corresponding to the key.
     pub fn get(&self, key: &K) -> Option<&V>
     where
@@ -373,6 +481,18 @@ impl<K, V> RBTree<K, V> {
         }
     }
 
+    /// Returns an iterator over the tree nodes whose keys are greater than or equal to the given
+    /// key, sorted by key.
+    pub fn iter_from(&self, key: &K) -> RBTreeIterator<'_, K, V>
+    where
+        K: Ord,
+    {
+        RBTreeIterator {
+            _tree: PhantomData,
+            next: self.find_bound(key, false),
+        }
+    }
+
     /// Returns a mutable iterator over the tree nodes, sorted by key.
     pub fn iter_mut(&mut self) -> RBTreeIteratorMut<'_, K, V> {
         RBTreeIteratorMut {
@@ -84,6 +84,38 @@ impl<T: ?Sized> Revocable<T> {
         }
     }
 
+    /// Tries to access the \[revocable\] wrapped object and, if it is still available, calls `f`
+    /// with a reference to it.
+    ///
+    /// Returns `None` if the object has been revoked, or the return value of `f` otherwise. The
+    /// object is guaranteed to remain accessible while `f` runs; like with
+    /// [`Revocable::try_access`], `f` is not allowed to sleep.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kernel::revocable::Revocable;
+    /// fn example() {
+    ///     let v = Revocable::new(10u32);
+    ///     assert_eq!(v.try_access_with(|x| *x + 1), Some(11));
+    ///     v.revoke();
+    ///     assert_eq!(v.try_access_with(|x| *x + 1), None);
+    /// }
+    /// ```
+    pub fn try_access_with<R>(&self, f: impl FnOnce(&T) -> R) -> Option<R> {
+        let guard = self.try_access()?;
+        Some(f(&*guard))
+    }
+
+    /// Returns whether access to the wrapped object has been revoked.
+    ///
+    /// Once it returns `true`, it returns `true` forever; a result of `false` may be stale by the
+    /// time the caller acts on it, so [`Revocable::try_access`] must still be used to access the
+    /// object.
+    pub fn is_revoked(&self) -> bool {
+        !self.is_available.load(Ordering::Relaxed)
+    }
+
     /// Revokes access to and drops the wrapped object.
     ///
     /// Access to the object is revoked immediately to new callers of [`Revocable::try_access`]. If
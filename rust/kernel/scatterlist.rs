@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Scatter-gather lists.
+//!
+//! A scatter-gather list describes a buffer made of several physically contiguous segments, e.g.,
+//! for DMA or for the crypto API.
+//!
+//! C header: [`include/linux/scatterlist.h`](../../../../include/linux/scatterlist.h)
+
+use crate::{bindings, error::code::*, Result};
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+/// A scatter-gather list over borrowed buffers.
+///
+/// The buffers must be in the kernel's linear mapping, e.g., allocated with [`Box`] or [`Vec`],
+/// and not on the stack or allocated with `vmalloc`.
+///
+/// [`Box`]: alloc::boxed::Box
+///
+/// # Invariants
+///
+/// `entries` was initialised by `sg_init_table`, and its entries point to the buffers borrowed
+/// for `'a`, whose total length is `len`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::scatterlist::ScatterList;
+///
+/// fn example() -> Result {
+///     let mut header = Vec::new();
+///     header.try_resize(16, 0u8)?;
+///     let mut payload = Vec::new();
+///     payload.try_resize(4096, 0u8)?;
+///
+///     let sg = ScatterList::try_new(&mut [&mut header[..], &mut payload[..]])?;
+///     assert_eq!(sg.len(), 16 + 4096);
+///     Ok(())
+/// }
+/// ```
+pub struct ScatterList<'a> {
+    entries: Vec<bindings::scatterlist>,
+    len: usize,
+    _p: PhantomData<&'a mut [u8]>,
+}
+
+// SAFETY: The list only refers to the buffers, which are mutably borrowed by it.
+unsafe impl Send for ScatterList<'_> {}
+
+// SAFETY: The list isn't modified through shared references.
+unsafe impl Sync for ScatterList<'_> {}
+
+impl<'a> ScatterList<'a> {
+    /// Creates a list whose segments are the given buffers, in order.
+    ///
+    /// It fails with [`EINVAL`] if there are no buffers, or if one is empty or isn't in the
+    /// linear mapping.
+    pub fn try_new(bufs: &mut [&'a mut [u8]]) -> Result<Self> {
+        if bufs.is_empty() {
+            return Err(EINVAL);
+        }
+
+        let mut entries = Vec::try_with_capacity(bufs.len())?;
+        let mut len = 0usize;
+        for buf in bufs.iter() {
+            // SAFETY: `virt_addr_valid` only checks the address, which may be any value.
+            if buf.is_empty() || !unsafe { bindings::virt_addr_valid(buf.as_ptr().cast()) } {
+                return Err(EINVAL);
+            }
+            len = len.checked_add(buf.len()).ok_or(EINVAL)?;
+            entries.try_push(bindings::scatterlist::default())?;
+        }
+
+        // SAFETY: `entries` has room for `bufs.len()` entries.
+        unsafe { bindings::sg_init_table(entries.as_mut_ptr(), entries.len() as _) };
+        for (sg, buf) in entries.iter_mut().zip(bufs.iter_mut()) {
+            // SAFETY: `sg` was initialised above, and `buf` is in the linear mapping, as checked
+            // above, and borrowed for `'a`.
+            unsafe { bindings::sg_set_buf(sg, buf.as_mut_ptr().cast(), buf.len() as _) };
+        }
+
+        // INVARIANT: `entries` was initialised above with the buffers, whose total length is
+        // `len`.
+        Ok(Self {
+            entries,
+            len,
+            _p: PhantomData,
+        })
+    }
+
+    /// Creates a list with a single segment.
+    pub fn from_slice(buf: &'a mut [u8]) -> Result<Self> {
+        Self::try_new(&mut [buf])
+    }
+
+    /// Returns the total length of the segments.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns a raw pointer to the first entry of the list.
+    pub fn as_ptr(&self) -> *mut bindings::scatterlist {
+        self.entries.as_ptr() as _
+    }
+}
@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Scheduler helpers.
+//!
+//! C header: [`include/linux/sched.h`](../../../../include/linux/sched.h)
+
+use crate::{bindings, str::CStr};
+
+pub use crate::sync::cond_resched;
+
+/// Annotates a function that may sleep.
+///
+/// With `CONFIG_DEBUG_ATOMIC_SLEEP`, it prints a warning with a backtrace (pointing at the caller's
+/// location) if it is called in atomic context, e.g., with a spinlock held or interrupts
+/// disabled. It may also reschedule, like [`cond_resched`], with voluntary preemption.
+///
+/// Functions that only sleep in rare cases should use it, so that misuse is caught even when they
+/// don't sleep.
+///
+/// Equivalent to the kernel's `might_sleep`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::might_sleep;
+///
+/// fn lookup(cached: Option<u32>) -> u32 {
+///     might_sleep!();
+///     // Reading from the device sleeps, but only on cache misses.
+///     cached.unwrap_or(0)
+/// }
+/// ```
+#[macro_export]
+macro_rules! might_sleep {
+    () => {
+        $crate::sched::might_sleep_at($crate::c_str!(core::file!()), core::line!())
+    };
+}
+
+/// Implements [`might_sleep`] for the given location.
+///
+/// Public but hidden since it should only be used from public macros.
+#[doc(hidden)]
+#[cfg_attr(not(CONFIG_DEBUG_ATOMIC_SLEEP), allow(unused_variables))]
+pub fn might_sleep_at(file: &'static CStr, line: u32) {
+    // SAFETY: `file` is a valid nul-terminated string that lives forever.
+    #[cfg(CONFIG_DEBUG_ATOMIC_SLEEP)]
+    unsafe {
+        bindings::__might_sleep(file.as_char_ptr(), line as _)
+    };
+
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::might_resched() };
+}
@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Shared memory files.
+//!
+//! A [`ShmemFile`] is an unlinked file on the internal `tmpfs` mount, whose pages can be swapped
+//! out under memory pressure. It is useful for large buffers that are rarely accessed, and it
+//! can be handed to user space as a file descriptor (like the ones created by `memfd_create`).
+//!
+//! C header: [`include/linux/shmem_fs.h`](../../../../include/linux/shmem_fs.h)
+
+use crate::{
+    bindings,
+    error::{from_kernel_err_ptr, Error},
+    file::File,
+    str::CStr,
+    types::ARef,
+    Result,
+};
+use core::ptr::NonNull;
+
+/// An anonymous file backed by shared memory.
+///
+/// # Invariants
+///
+/// `file` was created by `shmem_file_setup`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::c_str;
+/// use kernel::shmem::ShmemFile;
+///
+/// fn example() -> Result {
+///     let file = ShmemFile::try_new(c_str!("example"), 1 << 20)?;
+///     file.write_at(b"hello", 4096)?;
+///
+///     let mut buf = [0; 5];
+///     file.read_at(&mut buf, 4096)?;
+///     assert_eq!(&buf, b"hello");
+///     Ok(())
+/// }
+/// ```
+pub struct ShmemFile {
+    file: ARef<File>,
+}
+
+impl ShmemFile {
+    /// Creates a new file of `size` bytes with the given name, which is only used for
+    /// debugging (e.g., in `/proc/<pid>/maps`).
+    ///
+    /// The memory is only allocated when it is written, and is accounted to the current task
+    /// then.
+    pub fn try_new(name: &CStr, size: u64) -> Result<Self> {
+        let size = size.try_into()?;
+
+        // SAFETY: `name` is a valid string, which is copied by the callee.
+        let ptr = from_kernel_err_ptr(unsafe {
+            bindings::shmem_file_setup(name.as_char_ptr(), size, bindings::VM_NORESERVE as _)
+        })?;
+
+        // INVARIANT: `ptr` was created by `shmem_file_setup` above.
+        Ok(Self {
+            // SAFETY: `shmem_file_setup` returns a file with a reference owned by the caller.
+            file: unsafe { ARef::from_raw(NonNull::new_unchecked(ptr).cast()) },
+        })
+    }
+
+    /// Returns the underlying file.
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+
+    /// Returns the underlying file, e.g., to install it as a file descriptor with
+    /// [`crate::file::FileDescriptorReservation`].
+    pub fn into_file(self) -> ARef<File> {
+        self.file
+    }
+
+    /// Returns the size of the file in bytes.
+    pub fn size(&self) -> u64 {
+        // SAFETY: The file and its inode are valid by the type invariants.
+        unsafe { bindings::i_size_read((*self.file.0.get()).f_inode) as _ }
+    }
+
+    /// Reads from the file at the given offset into `buf`.
+    ///
+    /// Returns the number of bytes read, which is less than the length of `buf` if the end of the
+    /// file is reached.
+    pub fn read_at(&self, buf: &mut [u8], pos: u64) -> Result<usize> {
+        let mut pos = pos.try_into()?;
+
+        // SAFETY: The file is valid by the type invariants, and `buf` is valid for writes of its
+        // length.
+        let ret = unsafe {
+            bindings::kernel_read(
+                self.file.0.get(),
+                buf.as_mut_ptr() as _,
+                buf.len(),
+                &mut pos,
+            )
+        };
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret as _));
+        }
+        Ok(ret as _)
+    }
+
+    /// Writes `buf` into the file at the given offset.
+    ///
+    /// Returns the number of bytes written. The file grows if the write extends past its end.
+    pub fn write_at(&self, buf: &[u8], pos: u64) -> Result<usize> {
+        let mut pos = pos.try_into()?;
+
+        // SAFETY: The file is valid by the type invariants, and `buf` is valid for reads of its
+        // length.
+        let ret = unsafe {
+            bindings::kernel_write(self.file.0.get(), buf.as_ptr() as _, buf.len(), &mut pos)
+        };
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret as _));
+        }
+        Ok(ret as _)
+    }
+}
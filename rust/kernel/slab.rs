@@ -0,0 +1,325 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Slab caches.
+//!
+//! A [`KmemCache`] allocates objects of a single type, which is faster and wastes less memory than
+//! the general-purpose allocator when many of them are allocated, e.g., inodes or requests.
+//!
+//! C header: [`include/linux/slab.h`](../../../../include/linux/slab.h)
+
+use crate::{
+    bindings, build_assert, c_types, error::code::*, gfp, numa::NumaNode, str::CString, Result,
+};
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::NonNull,
+};
+
+/// Contains constants for the flags of [`KmemCache`].
+///
+/// It is tagged with `non_exhaustive` to prevent users from instantiating it.
+#[non_exhaustive]
+pub struct Flags;
+
+impl Flags {
+    /// Aligns objects to cache lines, so that objects don't share them.
+    pub const HWCACHE_ALIGN: u32 = bindings::SLAB_HWCACHE_ALIGN;
+
+    /// The objects are reclaimable (e.g., caches of the file system), which groups them to reduce
+    /// fragmentation.
+    pub const RECLAIM_ACCOUNT: u32 = bindings::SLAB_RECLAIM_ACCOUNT;
+
+    /// Accounts the objects to the memory cgroup of the task that allocates them.
+    pub const ACCOUNT: u32 = bindings::SLAB_ACCOUNT;
+
+    /// Panics if the cache cannot be created.
+    pub const PANIC: u32 = bindings::SLAB_PANIC;
+
+    /// Delays freeing the memory of the objects until after an RCU grace period.
+    ///
+    /// Objects may still be reused for other objects of the same type right away, so lock-free
+    /// readers must check that they found the object they were looking for.
+    pub const TYPESAFE_BY_RCU: u32 = bindings::SLAB_TYPESAFE_BY_RCU;
+
+    /// Fills objects with a pattern when they are freed, to catch uses after free.
+    pub const POISON: u32 = bindings::SLAB_POISON;
+
+    /// Adds red zones around objects, to catch buffer overruns.
+    pub const RED_ZONE: u32 = bindings::SLAB_RED_ZONE;
+}
+
+/// A slab cache of objects of type `T`.
+///
+/// Objects are allocated with [`KmemCache::try_alloc`], which returns a [`CacheBox`] that frees
+/// the object when dropped. All objects must be freed before the cache is dropped.
+///
+/// # Invariants
+///
+/// `ptr` was returned by `kmem_cache_create` for objects of type `T`, with `name` as the name. If
+/// `constructed` is `true`, the cache has a constructor that initialises objects with
+/// `T::default()`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::slab::{Flags, KmemCache};
+///
+/// struct Request {
+///     sector: u64,
+///     len: u32,
+/// }
+///
+/// fn example() -> Result {
+///     let cache =
+///         KmemCache::<Request>::try_new(fmt!("example_request"), 0, Flags::HWCACHE_ALIGN)?;
+///     let req = cache.try_alloc(Request { sector: 8, len: 512 })?;
+///     assert_eq!(req.len, 512);
+///     Ok(())
+/// }
+/// ```
+pub struct KmemCache<T> {
+    ptr: NonNull<bindings::kmem_cache>,
+    constructed: bool,
+    _name: CString,
+    _p: PhantomData<T>,
+}
+
+// SAFETY: Objects can be allocated and freed from any thread, which may send them to other
+// threads, so `T` must be `Send`.
+unsafe impl<T: Send> Send for KmemCache<T> {}
+
+// SAFETY: The cache functions are safe to call concurrently.
+unsafe impl<T: Send> Sync for KmemCache<T> {}
+
+impl<T> KmemCache<T> {
+    /// Creates a new cache, with the given name (which is shown in `/proc/slabinfo`), alignment
+    /// and flags (see [`Flags`]).
+    ///
+    /// Objects are aligned to `align` bytes (e.g., for hardware that requires it), or to the
+    /// alignment of `T` if it is larger. `align` must be zero or a power of two, otherwise
+    /// [`EINVAL`] is returned.
+    pub fn try_new(name: fmt::Arguments<'_>, align: usize, flags: u32) -> Result<Self> {
+        Self::create(name, align, flags, None)
+    }
+
+    fn create(
+        name: fmt::Arguments<'_>,
+        align: usize,
+        flags: u32,
+        ctor: Option<unsafe extern "C" fn(*mut c_types::c_void)>,
+    ) -> Result<Self> {
+        if align != 0 && !align.is_power_of_two() {
+            return Err(EINVAL);
+        }
+
+        let name = CString::try_from_fmt(name)?;
+        let size = mem::size_of::<T>().try_into()?;
+        let align = align.max(mem::align_of::<T>()).try_into()?;
+
+        // SAFETY: `name` is kept alive until the cache is destroyed, since the cache refers to it.
+        let ptr = unsafe {
+            bindings::kmem_cache_create(name.as_char_ptr(), size, align, flags as _, ctor)
+        };
+
+        // INVARIANT: `ptr` was returned by `kmem_cache_create` above, and it only has a
+        // constructor if it was given one.
+        Ok(Self {
+            ptr: NonNull::new(ptr).ok_or(ENOMEM)?,
+            constructed: ctor.is_some(),
+            _name: name,
+            _p: PhantomData,
+        })
+    }
+
+    /// Returns a pointer to the underlying `struct kmem_cache`.
+    pub(crate) fn as_ptr(&self) -> *mut bindings::kmem_cache {
+        self.ptr.as_ptr()
+    }
+
+    /// Allocates an object and places `value` into it.
+    pub fn try_alloc(&self, value: T) -> Result<CacheBox<'_, T>> {
+        self.try_alloc_node(value, NumaNode::ANY)
+    }
+
+    /// Allocates an object with the given flags (see [`gfp::Flags`]) and places `value` into it.
+    ///
+    /// [`gfp::Flags::ZERO`] is rejected with [`EINVAL`] on caches with a constructor.
+    pub fn try_alloc_with(&self, value: T, flags: u32) -> Result<CacheBox<'_, T>> {
+        self.init(self.alloc_raw(flags, NumaNode::ANY)?, value)
+    }
+
+    /// Allocates an object on the given NUMA node and places `value` into it.
+    ///
+    /// If the node has no memory available, the object is allocated on another node.
+    pub fn try_alloc_node(&self, value: T, node: NumaNode) -> Result<CacheBox<'_, T>> {
+        self.init(self.alloc_raw(gfp::Flags::KERNEL, node)?, value)
+    }
+
+    fn init(&self, ptr: NonNull<T>, value: T) -> Result<CacheBox<'_, T>> {
+        // SAFETY: `ptr` is valid for writes of a `T`. If the cache has a constructor, the
+        // constructed value has no drop glue, so it can be overwritten.
+        unsafe { ptr.as_ptr().write(value) };
+
+        // INVARIANT: `ptr` was allocated from the cache and initialised above.
+        Ok(CacheBox { ptr, cache: self })
+    }
+
+    /// Allocates an object with the given flags on the given node, without initialising it.
+    ///
+    /// If the cache has a constructor (see [`KmemCache::try_new_constructed`]), the object was
+    /// initialised by it or was left in its constructed state when it was last freed.
+    ///
+    /// The object must be freed with [`KmemCache::free_raw`]. It is useful for objects owned by C
+    /// code, e.g., the ones returned by `alloc_inode`.
+    ///
+    /// [`gfp::Flags::ZERO`] is rejected with [`EINVAL`] on caches with a constructor, since
+    /// zeroing would destroy the constructed state.
+    pub fn alloc_raw(&self, flags: u32, node: NumaNode) -> Result<NonNull<T>> {
+        if self.constructed && flags & gfp::Flags::ZERO != 0 {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: By the type invariants, `ptr` is a valid cache.
+        let ptr =
+            unsafe { bindings::kmem_cache_alloc_node(self.ptr.as_ptr(), flags, node.as_raw()) };
+        NonNull::new(ptr as *mut T).ok_or(ENOMEM)
+    }
+
+    /// Frees an object returned by [`KmemCache::alloc_raw`], without dropping it.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been allocated from this cache, and it must not be used afterwards.
+    pub unsafe fn free_raw(&self, ptr: NonNull<T>) {
+        // SAFETY: By the type invariants, `ptr` is a valid cache, and the safety requirements
+        // guarantee that the object was allocated from it.
+        unsafe { bindings::kmem_cache_free(self.ptr.as_ptr(), ptr.as_ptr() as _) };
+    }
+}
+
+impl<T: Default> KmemCache<T> {
+    /// Creates a new cache whose objects are initialised with `T::default()` when the cache
+    /// allocates memory for them, rather than every time they are allocated.
+    ///
+    /// Objects allocated with [`KmemCache::try_alloc_constructed`] are in the state they were left
+    /// in when they were last freed, so users must leave them in their default state. It is
+    /// useful for objects that are expensive to initialise (e.g., with embedded locks and lists)
+    /// and for objects that lock-free readers may find after they are freed (see
+    /// [`Flags::TYPESAFE_BY_RCU`]).
+    ///
+    /// `T` must not need to be dropped, since objects are never destroyed individually; this is
+    /// checked at compile time.
+    ///
+    /// The name, alignment and flags are used like in [`KmemCache::try_new`].
+    pub fn try_new_constructed(name: fmt::Arguments<'_>, align: usize, flags: u32) -> Result<Self> {
+        build_assert!(
+            !mem::needs_drop::<T>(),
+            "Objects of caches with a constructor must not need to be dropped"
+        );
+
+        unsafe extern "C" fn ctor<T: Default>(ptr: *mut c_types::c_void) {
+            // SAFETY: The cache calls the constructor with memory valid for writes of a `T`.
+            unsafe { (ptr as *mut T).write(T::default()) };
+        }
+
+        Self::create(name, align, flags, Some(ctor::<T>))
+    }
+
+    /// Allocates an object in its constructed state.
+    ///
+    /// It must be called on caches created with [`KmemCache::try_new_constructed`].
+    pub fn try_alloc_constructed(&self) -> Result<CacheBox<'_, T>> {
+        if !self.constructed {
+            return Err(EINVAL);
+        }
+
+        // INVARIANT: By the type invariants, the cache has a constructor, so the object is
+        // initialised.
+        Ok(CacheBox {
+            ptr: self.alloc_raw(gfp::Flags::KERNEL, NumaNode::ANY)?,
+            cache: self,
+        })
+    }
+}
+
+impl<T> Drop for KmemCache<T> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `ptr` is a valid cache. The objects were all freed,
+        // since they borrow the cache.
+        unsafe { bindings::kmem_cache_destroy(self.ptr.as_ptr()) };
+    }
+}
+
+/// An object allocated from a [`KmemCache`].
+///
+/// The object is dropped and freed when the box is dropped.
+///
+/// # Invariants
+///
+/// `ptr` was allocated from `cache` and points to an initialised `T` owned by the box.
+pub struct CacheBox<'a, T> {
+    ptr: NonNull<T>,
+    cache: &'a KmemCache<T>,
+}
+
+// SAFETY: The box owns the object, and the cache can free it from any thread.
+unsafe impl<T: Send> Send for CacheBox<'_, T> {}
+
+// SAFETY: The object is only accessed through shared references from shared references to the
+// box.
+unsafe impl<T: Sync> Sync for CacheBox<'_, T> {}
+
+impl<'a, T> CacheBox<'a, T> {
+    /// Releases ownership of the object, without dropping or freeing it.
+    ///
+    /// It can be converted back with [`CacheBox::from_raw`].
+    pub fn into_raw(self) -> NonNull<T> {
+        let ptr = self.ptr;
+        mem::forget(self);
+        ptr
+    }
+
+    /// Takes ownership of an object previously released with [`CacheBox::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have been returned by [`CacheBox::into_raw`] for a box of `cache`, and it must
+    /// not be used afterwards.
+    pub unsafe fn from_raw(cache: &'a KmemCache<T>, ptr: NonNull<T>) -> Self {
+        // INVARIANT: The safety requirements guarantee that `ptr` was allocated from `cache` and
+        // is initialised.
+        Self { ptr, cache }
+    }
+}
+
+impl<T> Deref for CacheBox<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: By the type invariants, `ptr` points to an initialised `T`.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for CacheBox<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: By the type invariants, `ptr` points to an initialised `T` owned by the box.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> Drop for CacheBox<'_, T> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `ptr` points to an initialised `T` owned by the box, and
+        // it was allocated from `cache`. Objects of caches with a constructor don't need to be
+        // dropped, so their constructed state is preserved.
+        unsafe {
+            core::ptr::drop_in_place(self.ptr.as_ptr());
+            self.cache.free_raw(self.ptr);
+        }
+    }
+}
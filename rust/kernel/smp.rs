@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Running functions on other CPUs.
+//!
+//! The functions in this module run a closure on one or more CPUs, via inter-processor
+//! interrupts, and wait for it to complete everywhere before returning. The closure runs in hard
+//! interrupt context (also on the calling CPU, with interrupts disabled), so it must not sleep.
+//!
+//! These functions must not be called with interrupts disabled or from interrupt context.
+//!
+//! C header: [`include/linux/smp.h`](../../../../include/linux/smp.h)
+
+use crate::{bindings, c_types, cpumask::CpuMask, to_result, Result};
+
+unsafe extern "C" fn call<F: Fn() + Sync>(info: *mut c_types::c_void) {
+    // SAFETY: `info` was created from a reference to `F` that outlives the call, because the
+    // callers wait for completion.
+    let f = unsafe { &*(info as *const F) };
+    f();
+}
+
+unsafe extern "C" fn call_cond<C: Fn(u32) -> bool + Sync, F: Fn() + Sync>(
+    info: *mut c_types::c_void,
+) {
+    // SAFETY: `info` was created from a reference to `(C, F)` that outlives the call, because
+    // `on_each_cpu_cond` waits for completion.
+    let (_, f) = unsafe { &*(info as *const (C, F)) };
+    f();
+}
+
+unsafe extern "C" fn cond<C: Fn(u32) -> bool + Sync, F: Fn() + Sync>(
+    cpu: c_types::c_int,
+    info: *mut c_types::c_void,
+) -> bool {
+    // SAFETY: As above.
+    let (c, _) = unsafe { &*(info as *const (C, F)) };
+    c(cpu as _)
+}
+
+/// Runs `f` on all online CPUs, including the calling one, and waits for it to complete.
+///
+/// Equivalent to the kernel's `on_each_cpu` with `wait` set.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use core::sync::atomic::{AtomicU32, Ordering};
+/// use kernel::smp;
+///
+/// fn count_cpus() -> u32 {
+///     let count = AtomicU32::new(0);
+///     smp::on_each_cpu(|| {
+///         count.fetch_add(1, Ordering::Relaxed);
+///     });
+///     count.load(Ordering::Relaxed)
+/// }
+/// ```
+pub fn on_each_cpu<F: Fn() + Sync>(f: F) {
+    // SAFETY: `f` outlives the call because it waits for completion.
+    unsafe {
+        bindings::on_each_cpu_cond_mask(
+            None,
+            Some(call::<F>),
+            &f as *const F as *mut _,
+            true,
+            CpuMask::online().as_ptr(),
+        )
+    };
+}
+
+/// Runs `f` on the online CPUs of `mask`, and waits for it to complete.
+///
+/// Equivalent to the kernel's `on_each_cpu_mask` with `wait` set.
+pub fn on_each_cpu_mask<F: Fn() + Sync>(mask: &CpuMask, f: F) {
+    // SAFETY: `f` outlives the call because it waits for completion, and `mask` is valid.
+    unsafe {
+        bindings::on_each_cpu_cond_mask(
+            None,
+            Some(call::<F>),
+            &f as *const F as *mut _,
+            true,
+            mask.as_ptr(),
+        )
+    };
+}
+
+/// Runs `f` on the online CPUs for which `cond_fn` returns `true`, and waits for it to complete.
+///
+/// `cond_fn` is called on the calling CPU for each online CPU, with preemption disabled, so it
+/// must not sleep either.
+///
+/// Equivalent to the kernel's `on_each_cpu_cond` with `wait` set.
+pub fn on_each_cpu_cond<C: Fn(u32) -> bool + Sync, F: Fn() + Sync>(cond_fn: C, f: F) {
+    let info = (cond_fn, f);
+    // SAFETY: `info` outlives the call because it waits for completion.
+    unsafe {
+        bindings::on_each_cpu_cond_mask(
+            Some(cond::<C, F>),
+            Some(call_cond::<C, F>),
+            &info as *const (C, F) as *mut _,
+            true,
+            CpuMask::online().as_ptr(),
+        )
+    };
+}
+
+/// Runs `f` on the given CPU, and waits for it to complete.
+///
+/// Returns [`crate::error::code::ENXIO`] if the CPU is not online.
+///
+/// Equivalent to the kernel's `smp_call_function_single` with `wait` set.
+pub fn on_cpu<F: Fn() + Sync>(cpu: u32, f: F) -> Result {
+    to_result(|| {
+        // SAFETY: `f` outlives the call because it waits for completion.
+        unsafe {
+            bindings::smp_call_function_single(
+                cpu as _,
+                Some(call::<F>),
+                &f as *const F as *mut _,
+                1,
+            )
+        }
+    })
+}
+
+/// Returns the number of online CPUs.
+///
+/// The result may be stale by the time the caller acts on it, since CPUs may be brought online or
+/// offline concurrently.
+pub fn num_online_cpus() -> u32 {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::num_online_cpus() }
+}
+
+/// Returns the number of the CPU the caller is running on.
+///
+/// The result may be stale by the time the caller acts on it, unless preemption is disabled.
+pub fn current_cpu() -> u32 {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::raw_smp_processor_id() as _ }
+}
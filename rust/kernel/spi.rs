@@ -0,0 +1,450 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! SPI devices and drivers.
+//!
+//! Drivers are matched with devices by name (see [`Driver::ID_TABLE`]) or by the compatible
+//! strings of the devicetree (see [`Driver::OF_DEVICE_ID_TABLE`]).
+//!
+//! C header: [`include/linux/spi/spi.h`](../../../../include/linux/spi/spi.h)
+
+use crate::{
+    bindings, c_types,
+    device::{self, RawDevice},
+    driver,
+    error::{code::*, from_kernel_result},
+    of,
+    str::{BStr, CStr},
+    to_result,
+    types::PointerWrapper,
+    Result, ThisModule,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::marker::PhantomData;
+
+/// A registration of an SPI driver.
+pub type Registration<T> = driver::Registration<Adapter<T>>;
+
+/// An SPI device id.
+#[derive(Clone, Copy)]
+pub enum DeviceId {
+    /// An SPI device id where only the name of the device is specified, e.g., `b"mcp3008"`.
+    Name(&'static BStr),
+}
+
+// SAFETY: `ZERO` is all zeroed-out and `to_rawid` stores `offset` in `spi_device_id::driver_data`.
+unsafe impl const driver::RawDeviceId for DeviceId {
+    type RawType = bindings::spi_device_id;
+    const ZERO: Self::RawType = bindings::spi_device_id {
+        name: [0; 32],
+        driver_data: 0,
+    };
+
+    fn to_rawid(&self, offset: isize) -> Self::RawType {
+        let DeviceId::Name(name) = self;
+        let mut id = Self::ZERO;
+        let mut i = 0;
+        while i < name.len() {
+            // If `name` does not fit in `id.name`, an "index out of bounds" build time error will
+            // be triggered.
+            id.name[i] = name[i] as _;
+            i += 1;
+        }
+        id.name[i] = b'\0' as _;
+        id.driver_data = offset as _;
+        id
+    }
+}
+
+/// Defines the id table for SPI devices.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// # use kernel::{define_spi_id_table, spi};
+/// #
+/// # struct Sample;
+/// # impl kernel::spi::Driver for Sample {
+/// #   fn probe(_dev: &mut spi::Device, _id: Option<&Self::IdInfo>) -> Result {
+/// #       Ok(())
+/// #   }
+///     define_spi_id_table! {u32, [
+///         (spi::DeviceId::Name(b"mcp3004"), Some(4)),
+///         (spi::DeviceId::Name(b"mcp3008"), Some(8)),
+///     ]}
+/// # }
+/// ```
+#[macro_export]
+macro_rules! define_spi_id_table {
+    ($data_type:ty, $($t:tt)*) => {
+        type IdInfo = $data_type;
+        $crate::define_id_table!(ID_TABLE, $crate::spi::DeviceId, $data_type, $($t)*);
+    };
+}
+
+/// An SPI driver.
+pub trait Driver {
+    /// Data stored on device by driver.
+    type Data: PointerWrapper + Send + Sync + driver::DeviceRemoval = ();
+
+    /// The type holding information about each device id supported by the driver.
+    type IdInfo: 'static = ();
+
+    /// The table of device names supported by the driver.
+    const ID_TABLE: Option<driver::IdTable<'static, DeviceId, Self::IdInfo>> = None;
+
+    /// The table of devicetree compatible strings supported by the driver.
+    const OF_DEVICE_ID_TABLE: Option<driver::IdTable<'static, of::DeviceId, Self::IdInfo>> = None;
+
+    /// Probes for the device with the given id.
+    fn probe(dev: &mut Device, id_info: Option<&Self::IdInfo>) -> Result<Self::Data>;
+
+    /// Cleans any resources up that are associated with the device.
+    ///
+    /// This is called when the driver is detached from the device.
+    fn remove(_data: &Self::Data) {}
+}
+
+/// An adapter for the registration of SPI drivers.
+pub struct Adapter<T: Driver>(T);
+
+impl<T: Driver> driver::DriverOps for Adapter<T> {
+    type RegType = bindings::spi_driver;
+
+    unsafe fn register(
+        reg: *mut bindings::spi_driver,
+        name: &'static CStr,
+        module: &'static ThisModule,
+    ) -> Result {
+        // SAFETY: By the safety requirements of this function (defined in the trait defintion),
+        // `reg` is non-null and valid.
+        let sdrv = unsafe { &mut *reg };
+        sdrv.driver.name = name.as_char_ptr();
+        sdrv.probe = Some(probe_callback::<T>);
+        sdrv.remove = Some(remove_callback::<T>);
+        if let Some(t) = T::ID_TABLE {
+            sdrv.id_table = t.as_ref();
+        }
+        if let Some(t) = T::OF_DEVICE_ID_TABLE {
+            sdrv.driver.of_match_table = t.as_ref();
+        }
+        // SAFETY: By the safety requirements of this function, `reg` is valid and fully
+        // initialised. `module.0` lives at least as long as the module.
+        to_result(|| unsafe { bindings::__spi_register_driver(module.0, reg) })
+    }
+
+    unsafe fn unregister(reg: *mut bindings::spi_driver) {
+        // SAFETY: By the safety requirements of this function (defined in the trait definition),
+        // `reg` was passed (and updated) by a previous successful call to
+        // `__spi_register_driver`.
+        unsafe { bindings::spi_unregister_driver(reg) };
+    }
+}
+
+impl<T: Driver> Adapter<T> {
+    fn get_id_info(dev: &Device) -> Option<&'static T::IdInfo> {
+        if let Some(table) = T::OF_DEVICE_ID_TABLE {
+            // SAFETY: `table` has static lifetime, so it is valid for read. `dev` is guaranteed to
+            // be valid while it's alive, so is the raw device returned by it.
+            let id = unsafe { bindings::of_match_device(table.as_ref(), dev.raw_device()) };
+            if !id.is_null() {
+                // SAFETY: `id` is a pointer within the static table, so it's always valid, and
+                // its offset was stored by `of::DeviceId::to_rawid`.
+                return unsafe { Self::id_info(id, (*id).data as _) };
+            }
+        }
+
+        T::ID_TABLE?;
+
+        // SAFETY: `dev.ptr` is valid by the type invariants. The device was matched with an entry
+        // of `ID_TABLE` if it wasn't matched through the devicetree.
+        let id = unsafe { bindings::spi_get_device_id(dev.ptr) };
+        if id.is_null() {
+            return None;
+        }
+
+        // SAFETY: `id` is a pointer within the static table, so it's always valid, and its offset
+        // was stored by `DeviceId::to_rawid`.
+        unsafe { Self::id_info(id, (*id).driver_data as _) }
+    }
+
+    /// Returns the information of the entry `id` of an id table.
+    ///
+    /// # Safety
+    ///
+    /// `id` must point to an entry of a static id table of `T`, and `offset` must be the offset
+    /// stored in it by `IdArray::new`.
+    unsafe fn id_info<U>(id: *const U, offset: isize) -> Option<&'static T::IdInfo> {
+        if offset == 0 {
+            return None;
+        }
+
+        // SAFETY: The offset comes from a previous call to `offset_from` in `IdArray::new`, which
+        // guarantees that the resulting pointer is within the table.
+        let ptr = unsafe { id.cast::<u8>().offset(offset).cast::<Option<T::IdInfo>>() };
+
+        // SAFETY: The id table has a static lifetime, so `ptr` is guaranteed to be valid for read.
+        unsafe { (&*ptr).as_ref() }
+    }
+}
+
+unsafe extern "C" fn probe_callback<T: Driver>(spi: *mut bindings::spi_device) -> c_types::c_int {
+    from_kernel_result! {
+        // SAFETY: `spi` is valid by the contract with the C code. `dev` is alive only for the
+        // duration of this call, so it is guaranteed to remain alive for the lifetime of `dev`.
+        let mut dev = unsafe { Device::from_ptr(spi) };
+        let info = Adapter::<T>::get_id_info(&dev);
+        let data = T::probe(&mut dev, info)?;
+        let ptr = T::Data::into_pointer(data);
+        // SAFETY: `spi` is valid for write by the contract with the C code.
+        unsafe { bindings::spi_set_drvdata(spi, ptr as _) };
+        Ok(0)
+    }
+}
+
+unsafe extern "C" fn remove_callback<T: Driver>(spi: *mut bindings::spi_device) -> c_types::c_int {
+    // SAFETY: `spi` is valid by the contract with the C code.
+    let ptr = unsafe { bindings::spi_get_drvdata(spi) };
+    // SAFETY: The value returned by `spi_get_drvdata` was stored by a previous call to
+    // `spi_set_drvdata` in `probe_callback` above; the value comes from a call to
+    // `T::Data::into_pointer`.
+    let data = unsafe { T::Data::from_pointer(ptr) };
+    T::remove(&data);
+    <T::Data as driver::DeviceRemoval>::device_remove(&data);
+    0
+}
+
+/// An SPI device.
+///
+/// # Invariants
+///
+/// The field `ptr` is non-null and valid for the lifetime of the object.
+pub struct Device {
+    ptr: *mut bindings::spi_device,
+}
+
+impl Device {
+    /// Creates a new device from the given pointer.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be non-null and valid. It must remain valid for the lifetime of the returned
+    /// instance.
+    unsafe fn from_ptr(ptr: *mut bindings::spi_device) -> Self {
+        // INVARIANT: The safety requirements of the function ensure the lifetime invariant.
+        Self { ptr }
+    }
+
+    /// Returns the maximum clock rate of the device, in Hz.
+    pub fn max_speed_hz(&self) -> u32 {
+        // SAFETY: By the type invariants, `self.ptr` is valid.
+        unsafe { (*self.ptr).max_speed_hz }
+    }
+
+    /// Returns the SPI mode of the device (the `SPI_*` flags, e.g., clock polarity and phase).
+    pub fn mode(&self) -> u32 {
+        // SAFETY: By the type invariants, `self.ptr` is valid.
+        unsafe { (*self.ptr).mode as _ }
+    }
+
+    /// Returns the chip select line of the device on its controller.
+    pub fn chip_select(&self) -> u8 {
+        // SAFETY: By the type invariants, `self.ptr` is valid.
+        unsafe { (*self.ptr).chip_select }
+    }
+
+    /// Writes `tx` to the device and then reads `rx.len()` bytes from it, in a single message.
+    ///
+    /// The data is copied through a bounce buffer, so it is meant for small transfers (e.g., a
+    /// command followed by a register value); larger ones should use [`Device::transfer`].
+    pub fn write_then_read(&self, tx: &[u8], rx: &mut [u8]) -> Result {
+        let n_tx = tx.len().try_into()?;
+        let n_rx = rx.len().try_into()?;
+
+        // SAFETY: By the type invariants, `self.ptr` is valid. `tx` and `rx` are valid for reads
+        // and writes of their lengths, respectively, and are only used during the call.
+        to_result(|| unsafe {
+            bindings::spi_write_then_read(
+                self.ptr,
+                tx.as_ptr() as _,
+                n_tx,
+                rx.as_mut_ptr() as _,
+                n_rx,
+            )
+        })
+    }
+
+    /// Writes `tx` to the device.
+    pub fn write(&self, tx: &[u8]) -> Result {
+        self.write_then_read(tx, &mut [])
+    }
+
+    /// Reads `rx.len()` bytes from the device.
+    pub fn read(&self, rx: &mut [u8]) -> Result {
+        self.write_then_read(&[], rx)
+    }
+
+    /// Performs the given transfers in a single message, that is, with the chip select asserted
+    /// throughout (unless a transfer asks otherwise with [`Transfer::cs_change`]).
+    ///
+    /// It sleeps until the message is complete.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kernel::prelude::*;
+    /// use kernel::spi::{Device, Transfer};
+    ///
+    /// fn read_reg(dev: &Device, reg: u8, len: usize) -> Result<Vec<u8>> {
+    ///     let mut cmd = Vec::try_with_capacity(1)?;
+    ///     cmd.try_push(reg | 0x80)?;
+    ///     let mut value = Vec::new();
+    ///     value.try_resize(len, 0)?;
+    ///     dev.transfer(&mut [Transfer::write(&cmd), Transfer::read(&mut value)])?;
+    ///     Ok(value)
+    /// }
+    /// ```
+    pub fn transfer(&self, transfers: &mut [Transfer<'_>]) -> Result {
+        if transfers.is_empty() {
+            return Err(EINVAL);
+        }
+
+        let mut msg = bindings::spi_message::default();
+
+        // SAFETY: `msg` is valid, and each transfer outlives it, since it is only used during
+        // this call. The buffers of the transfers are borrowed for as long as the transfers.
+        unsafe {
+            bindings::spi_message_init(&mut msg);
+            for t in transfers.iter_mut() {
+                bindings::spi_message_add_tail(&mut t.raw, &mut msg);
+            }
+        }
+
+        // SAFETY: By the type invariants, `self.ptr` is valid, and `msg` was built above.
+        to_result(|| unsafe { bindings::spi_sync(self.ptr, &mut msg) })
+    }
+}
+
+// SAFETY: The device returned by `raw_device` is the raw SPI device.
+unsafe impl device::RawDevice for Device {
+    fn raw_device(&self) -> *mut bindings::device {
+        // SAFETY: By the type invariants, we know that `self.ptr` is non-null and valid.
+        unsafe { &mut (*self.ptr).dev }
+    }
+}
+
+/// A buffer that can be used in a [`Transfer`].
+///
+/// Controllers may transfer the data with DMA, so the buffer must be allocated on the heap: stack
+/// memory isn't suitable for DMA (e.g., with vmapped stacks).
+///
+/// # Safety
+///
+/// The slices returned by [`AsRef::as_ref`] and [`AsMut::as_mut`] must point to memory allocated
+/// with `kmalloc`.
+pub unsafe trait DmaBuffer: AsRef<[u8]> + AsMut<[u8]> {}
+
+// SAFETY: The contents of a `Vec` are allocated with `kmalloc`.
+unsafe impl DmaBuffer for Vec<u8> {}
+
+// SAFETY: The contents of a `Box` are allocated with `kmalloc`.
+unsafe impl DmaBuffer for Box<[u8]> {}
+
+/// A transfer of an SPI message, see [`Device::transfer`].
+///
+/// SPI is full-duplex: the device receives as many bytes as it sends. A write-only transfer
+/// discards the received bytes, and a read-only one sends zeroes.
+///
+/// The buffers must be [`DmaBuffer`]s since the controller may use DMA.
+pub struct Transfer<'a> {
+    raw: bindings::spi_transfer,
+    _p: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> Transfer<'a> {
+    fn new(tx: *const u8, rx: *mut u8, len: usize) -> Self {
+        let mut raw = bindings::spi_transfer::default();
+        raw.tx_buf = tx as _;
+        raw.rx_buf = rx as _;
+        raw.len = len as _;
+        Self {
+            raw,
+            _p: PhantomData,
+        }
+    }
+
+    /// Creates a transfer that writes `tx` to the device.
+    pub fn write(tx: &'a impl DmaBuffer) -> Self {
+        let tx = tx.as_ref();
+        Self::new(tx.as_ptr(), core::ptr::null_mut(), tx.len())
+    }
+
+    /// Creates a transfer that reads `rx.len()` bytes from the device.
+    pub fn read(rx: &'a mut impl DmaBuffer) -> Self {
+        let rx = rx.as_mut();
+        Self::new(core::ptr::null(), rx.as_mut_ptr(), rx.len())
+    }
+
+    /// Creates a transfer that writes `tx` to the device while reading into `rx`.
+    ///
+    /// Returns [`EINVAL`] if the buffers have different lengths.
+    pub fn duplex(tx: &'a impl DmaBuffer, rx: &'a mut impl DmaBuffer) -> Result<Self> {
+        let (tx, rx) = (tx.as_ref(), rx.as_mut());
+        if tx.len() != rx.len() {
+            return Err(EINVAL);
+        }
+        Ok(Self::new(tx.as_ptr(), rx.as_mut_ptr(), tx.len()))
+    }
+
+    /// Sets the clock rate of the transfer, instead of the default one of the device.
+    pub fn speed_hz(mut self, speed_hz: u32) -> Self {
+        self.raw.speed_hz = speed_hz;
+        self
+    }
+
+    /// Sets the word size of the transfer, instead of the default one of the device.
+    pub fn bits_per_word(mut self, bits: u8) -> Self {
+        self.raw.bits_per_word = bits;
+        self
+    }
+
+    /// Deasserts the chip select after the transfer (or, for the last transfer of the message,
+    /// leaves it asserted until the next message).
+    pub fn cs_change(mut self) -> Self {
+        self.raw.set_cs_change(1);
+        self
+    }
+}
+
+/// Declares a kernel module that exposes a single SPI driver.
+///
+/// # Examples
+///
+/// ```ignore
+/// # use kernel::prelude::*;
+/// # use kernel::{spi, define_spi_id_table, module_spi_driver};
+/// #
+/// struct MyDriver;
+/// impl spi::Driver for MyDriver {
+///     // [...]
+/// #   fn probe(_dev: &mut spi::Device, _id: Option<&Self::IdInfo>) -> Result {
+/// #       Ok(())
+/// #   }
+/// #   define_spi_id_table! {(), [
+/// #       (spi::DeviceId::Name(b"mcp3008"), None),
+/// #   ]}
+/// }
+///
+/// module_spi_driver! {
+///     type: MyDriver,
+///     name: b"module_name",
+///     author: b"Author name",
+///     license: b"GPL v2",
+/// }
+/// ```
+#[macro_export]
+macro_rules! module_spi_driver {
+    ($($f:tt)*) => {
+        $crate::module_driver!(<T>, $crate::spi::Adapter<T>, { $($f)* });
+    };
+}
@@ -2,6 +2,7 @@
 
 //! String representations.
 
+use alloc::alloc::AllocError;
 use alloc::vec::Vec;
 use core::fmt::{self, Write};
 use core::ops::{self, Deref, Index};
@@ -585,6 +586,189 @@ impl Deref for CString {
     }
 }
 
+impl AsRef<CStr> for CString {
+    fn as_ref(&self) -> &CStr {
+        self
+    }
+}
+
+impl<'a> TryFrom<&'a CStr> for CString {
+    type Error = AllocError;
+
+    /// Creates an owned copy of the given C string.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kernel::{c_str, str::CString};
+    /// let s = CString::try_from(c_str!("label")).unwrap();
+    /// assert_eq!(s.as_bytes_with_nul(), b"label\0");
+    /// ```
+    fn try_from(cstr: &'a CStr) -> Result<CString, AllocError> {
+        let mut buf = Vec::new();
+
+        buf.try_extend_from_slice(cstr.as_bytes_with_nul())
+            .map_err(|_| AllocError)?;
+
+        // INVARIANT: The `CStr` and `CString` types have the same invariants for
+        // the string data, and we copied it over without changes.
+        Ok(CString { buf })
+    }
+}
+
+impl fmt::Display for CString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl fmt::Debug for CString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+/// A fixed-capacity, `NUL`-terminated string buffer.
+///
+/// It can hold up to `N - 1` bytes plus the `NUL` terminator, and implements [`fmt::Write`] by
+/// truncating what does not fit, like the kernel's `scnprintf` and `seq_buf`. Truncation is
+/// tracked so that callers can detect it and, for example, fail with `EOVERFLOW`.
+///
+/// It does not allocate, so it is useful to build short strings in contexts where allocations are
+/// not desirable, e.g., sysfs `show` callbacks, `d_dname` and uevent variables.
+///
+/// # Invariants
+///
+/// `len < N` and `buf[len]` is `0`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use core::fmt::Write;
+/// use kernel::str::KString;
+///
+/// let mut s = KString::<8>::new();
+/// write!(s, "{}-{}", "abc", 10).unwrap();
+/// assert_eq!(s.as_bytes(), b"abc-10");
+/// assert!(!s.is_truncated());
+///
+/// write!(s, "{}", 1234).unwrap();
+/// assert_eq!(s.as_bytes_with_nul(), b"abc-101\0");
+/// assert!(s.is_truncated());
+/// ```
+pub struct KString<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+    truncated: bool,
+}
+
+impl<const N: usize> KString<N> {
+    /// Creates a new empty buffer.
+    pub const fn new() -> Self {
+        crate::build_assert!(N > 0, "`KString` needs room for the `NUL` terminator");
+
+        // INVARIANT: `0 < N` and the buffer is zero-initialised.
+        Self {
+            buf: [0; N],
+            len: 0,
+            truncated: false,
+        }
+    }
+
+    /// Creates a new buffer with the given formatted arguments, truncated if needed.
+    pub fn from_fmt(args: fmt::Arguments<'_>) -> Self {
+        let mut s = Self::new();
+        // `write_str` never fails, it truncates instead.
+        let _ = s.write_fmt(args);
+        s
+    }
+
+    /// Returns the maximum number of bytes the buffer can hold, excluding the `NUL` terminator.
+    pub const fn capacity(&self) -> usize {
+        N - 1
+    }
+
+    /// Returns the length of the contents, excluding the `NUL` terminator.
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the buffer is empty.
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if any write to the buffer was truncated since it was created or cleared.
+    pub const fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Empties the buffer and resets the truncation state.
+    pub fn clear(&mut self) {
+        // INVARIANT: `0 < N` and the terminator is written at index 0.
+        self.buf[0] = 0;
+        self.len = 0;
+        self.truncated = false;
+    }
+
+    /// Returns the contents of the buffer, excluding the `NUL` terminator.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Returns the contents of the buffer, including the `NUL` terminator.
+    pub fn as_bytes_with_nul(&self) -> &[u8] {
+        &self.buf[..=self.len]
+    }
+
+    /// Returns the contents of the buffer as a [`CStr`].
+    ///
+    /// Fails if an interior `NUL` byte was written to the buffer.
+    pub fn as_cstr(&self) -> Result<&CStr, CStrConvertError> {
+        CStr::from_bytes_with_nul(self.as_bytes_with_nul())
+    }
+}
+
+impl<const N: usize> Default for KString<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Write for KString<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let avail = N - 1 - self.len;
+        let len_to_copy = core::cmp::min(avail, s.len());
+        if len_to_copy < s.len() {
+            self.truncated = true;
+        }
+
+        self.buf[self.len..self.len + len_to_copy].copy_from_slice(&s.as_bytes()[..len_to_copy]);
+
+        // INVARIANT: `len + len_to_copy <= N - 1`, and the terminator is written right after the
+        // new contents.
+        self.len += len_to_copy;
+        self.buf[self.len] = 0;
+        Ok(())
+    }
+}
+
+impl<const N: usize> fmt::Display for KString<N> {
+    /// Formats printable ASCII characters, escaping the rest, like [`CStr`] does.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for &c in self.as_bytes() {
+            if (0x20..0x7f).contains(&c) {
+                // Printable character
+                f.write_char(c as char)?;
+            } else {
+                write!(f, "\\x{:02x}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// A convenience alias for [`core::format_args`].
 #[macro_export]
 macro_rules! fmt {
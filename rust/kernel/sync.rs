@@ -20,48 +20,114 @@
 //! pr_info!("{}\n", *data.lock());
 //! ```
 
-use crate::{bindings, str::CStr};
+use crate::{bindings, str::CStr, Opaque};
 use core::pin::Pin;
 
 mod arc;
+mod atomic;
+pub mod barrier;
+mod completion;
 mod condvar;
 mod guard;
 mod locked_by;
 mod mutex;
+pub mod rcu;
 mod revocable_mutex;
 mod rwsem;
+mod semaphore;
 mod seqlock;
 pub mod smutex;
 mod spinlock;
+mod waitqueue;
 
 pub use arc::{Ref, RefBorrow, UniqueRef};
+pub use atomic::{Atomic32, Atomic64};
+pub use completion::Completion;
 pub use condvar::CondVar;
 pub use guard::{CreatableLock, Guard, Lock, LockInfo, ReadLock, WriteLock};
 pub use locked_by::LockedBy;
 pub use mutex::Mutex;
 pub use revocable_mutex::{RevocableMutex, RevocableMutexGuard};
 pub use rwsem::RwSemaphore;
+pub use semaphore::Semaphore;
 pub use seqlock::{SeqLock, SeqLockReadGuard};
 pub use spinlock::{RawSpinLock, SpinLock};
+pub use waitqueue::WaitQueue;
+
+/// Represents a lockdep class. It wraps the kernel's `struct lock_class_key`.
+///
+/// Lockdep identifies lock classes by the address of their key, so keys must be statics; they are
+/// usually created with the [`static_lock_class`] macro.
+#[repr(transparent)]
+pub struct LockClassKey(Opaque<bindings::lock_class_key>);
+
+// SAFETY: `bindings::lock_class_key` is designed to be used concurrently from multiple threads
+// and is only accessed by lockdep.
+unsafe impl Sync for LockClassKey {}
+
+impl LockClassKey {
+    /// Creates a new lock class key.
+    ///
+    /// It is only meaningful when stored in a static, see [`static_lock_class`].
+    pub const fn new() -> Self {
+        Self(Opaque::uninit())
+    }
+
+    /// Returns a raw pointer to the underlying `lock_class_key`.
+    pub fn as_ptr(&self) -> *mut bindings::lock_class_key {
+        self.0.get()
+    }
+}
+
+/// Defines a new static lock class and returns a reference to its key.
+///
+/// Each invocation site gets a distinct class, so locks initialised from different places are
+/// tracked separately by lockdep.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::static_lock_class;
+/// # use kernel::sync::LockClassKey;
+/// let a: &'static LockClassKey = static_lock_class!();
+/// let b: &'static LockClassKey = static_lock_class!();
+/// assert!(!core::ptr::eq(a, b));
+/// ```
+#[macro_export]
+macro_rules! static_lock_class {
+    () => {{
+        static CLASS: $crate::sync::LockClassKey = $crate::sync::LockClassKey::new();
+        &CLASS
+    }};
+}
 
 /// Safely initialises an object that has an `init` function that takes a name and a lock class as
 /// arguments, examples of these are [`Mutex`] and [`SpinLock`]. Each of them also provides a more
 /// specialised name that uses this macro.
+///
+/// A new lock class is created for each call site with [`static_lock_class`], unless the keys are
+/// given explicitly.
 #[doc(hidden)]
 #[macro_export]
 macro_rules! init_with_lockdep {
-    ($obj:expr, $name:expr) => {{
-        static mut CLASS1: core::mem::MaybeUninit<$crate::bindings::lock_class_key> =
-            core::mem::MaybeUninit::uninit();
-        static mut CLASS2: core::mem::MaybeUninit<$crate::bindings::lock_class_key> =
-            core::mem::MaybeUninit::uninit();
+    ($obj:expr, $name:expr) => {
+        $crate::init_with_lockdep!(
+            $obj,
+            $name,
+            $crate::static_lock_class!(),
+            $crate::static_lock_class!()
+        )
+    };
+    ($obj:expr, $name:expr, $key1:expr, $key2:expr) => {{
         let obj = $obj;
         let name = $crate::c_str!($name);
-        // SAFETY: `CLASS1` and `CLASS2` are never used by Rust code directly; the C portion of the
-        // kernel may change it though.
+        let key1: &'static $crate::sync::LockClassKey = $key1;
+        let key2: &'static $crate::sync::LockClassKey = $key2;
+        // SAFETY: The keys are statics, so they remain valid until `obj` is dropped; the C
+        // portion of the kernel may change them though.
         #[allow(unused_unsafe)]
         unsafe {
-            $crate::sync::NeedsLockClass::init(obj, name, CLASS1.as_mut_ptr(), CLASS2.as_mut_ptr())
+            $crate::sync::NeedsLockClass::init(obj, name, key1.as_ptr(), key2.as_ptr())
         };
     }};
 }
@@ -97,7 +163,7 @@ pub trait NeedsLockClass {
 /// # Examples
 ///
 /// ```ignore
-/// # use kernel::{init_static_sync, sync::{CondVar, Mutex, RevocableMutex, SpinLock}};
+/// # use kernel::{init_static_sync, sync::{CondVar, Mutex, RevocableMutex, Semaphore, SpinLock}};
 /// struct Test {
 ///     a: u32,
 ///     b: u32,
@@ -113,6 +179,8 @@ pub trait NeedsLockClass {
 ///     static D: CondVar;
 ///
 ///     static E: RevocableMutex<Test> = Test { a: 30, b: 40 };
+///
+///     static F: Semaphore = 1;
 /// }
 /// ```
 #[macro_export]
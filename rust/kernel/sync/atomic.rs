@@ -0,0 +1,255 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Kernel atomic integers.
+//!
+//! These follow the kernel's memory model rather than Rust's: plain reads and writes
+//! ([`Atomic32::read`], [`Atomic32::set`]) and read-modify-write operations that do not return a
+//! value (e.g., [`Atomic32::inc`]) are unordered, while those that do return a value (e.g.,
+//! [`Atomic32::add_return`]) are fully ordered. This makes them compatible with counters that are
+//! also accessed by C code.
+//!
+//! C header: [`include/linux/atomic.h`](../../../../include/linux/atomic.h)
+//!
+//! Reference: <https://www.kernel.org/doc/html/latest/core-api/wrappers/atomic_t.html>
+
+use crate::bindings;
+use core::cell::UnsafeCell;
+
+macro_rules! declare_atomic {
+    (
+        $(#[$meta:meta])*
+        $name:ident, $c:ident, $t:ty,
+        $read:ident, $read_acquire:ident, $set:ident, $set_release:ident,
+        $add:ident, $sub:ident, $inc:ident, $dec:ident,
+        $add_return:ident, $sub_return:ident, $inc_return:ident, $dec_return:ident,
+        $fetch_add:ident, $xchg:ident, $cmpxchg:ident,
+        $add_unless:ident, $inc_not_zero:ident, $dec_and_test:ident, $sub_and_test:ident
+    ) => {
+        $(#[$meta])*
+        #[repr(transparent)]
+        pub struct $name(UnsafeCell<bindings::$c>);
+
+        // SAFETY: All accesses to the counter are atomic.
+        unsafe impl Send for $name {}
+
+        // SAFETY: All accesses to the counter are atomic.
+        unsafe impl Sync for $name {}
+
+        impl $name {
+            #[doc = concat!("Creates a new `", stringify!($c), "` with the given value.")]
+            pub const fn new(value: $t) -> Self {
+                Self(UnsafeCell::new(bindings::$c { counter: value }))
+            }
+
+            #[doc = concat!("Wraps an existing `", stringify!($c), "`, e.g., one embedded in a C")]
+            /// structure.
+            ///
+            /// # Safety
+            ///
+            /// `ptr` must be valid for the lifetime `'a` and must only be accessed atomically while
+            /// the returned reference is alive.
+            pub unsafe fn from_ptr<'a>(ptr: *mut bindings::$c) -> &'a Self {
+                // SAFETY: `Self` is `repr(transparent)` over the C type, and the safety
+                // requirements guarantee that the pointer is valid for `'a`.
+                unsafe { &*ptr.cast() }
+            }
+
+            /// Returns a raw pointer to the underlying C atomic.
+            pub fn as_ptr(&self) -> *mut bindings::$c {
+                self.0.get()
+            }
+
+            /// Returns the current value, unordered.
+            pub fn read(&self) -> $t {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$read(self.0.get()) }
+            }
+
+            /// Returns the current value, with acquire ordering.
+            pub fn read_acquire(&self) -> $t {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$read_acquire(self.0.get()) }
+            }
+
+            /// Sets the value, unordered.
+            pub fn set(&self, value: $t) {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$set(self.0.get(), value) };
+            }
+
+            /// Sets the value, with release ordering.
+            pub fn set_release(&self, value: $t) {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$set_release(self.0.get(), value) };
+            }
+
+            /// Adds `i` to the value, unordered.
+            pub fn add(&self, i: $t) {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$add(i, self.0.get()) };
+            }
+
+            /// Subtracts `i` from the value, unordered.
+            pub fn sub(&self, i: $t) {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$sub(i, self.0.get()) };
+            }
+
+            /// Increments the value, unordered.
+            pub fn inc(&self) {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$inc(self.0.get()) };
+            }
+
+            /// Decrements the value, unordered.
+            pub fn dec(&self) {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$dec(self.0.get()) };
+            }
+
+            /// Adds `i` to the value and returns the new value, fully ordered.
+            pub fn add_return(&self, i: $t) -> $t {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$add_return(i, self.0.get()) }
+            }
+
+            /// Subtracts `i` from the value and returns the new value, fully ordered.
+            pub fn sub_return(&self, i: $t) -> $t {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$sub_return(i, self.0.get()) }
+            }
+
+            /// Increments the value and returns the new value, fully ordered.
+            pub fn inc_return(&self) -> $t {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$inc_return(self.0.get()) }
+            }
+
+            /// Decrements the value and returns the new value, fully ordered.
+            pub fn dec_return(&self) -> $t {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$dec_return(self.0.get()) }
+            }
+
+            /// Adds `i` to the value and returns the old value, fully ordered.
+            pub fn fetch_add(&self, i: $t) -> $t {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$fetch_add(i, self.0.get()) }
+            }
+
+            /// Sets the value to `new` and returns the old value, fully ordered.
+            pub fn xchg(&self, new: $t) -> $t {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$xchg(self.0.get(), new) }
+            }
+
+            /// Sets the value to `new` if it is `old`, and returns the value that was read.
+            ///
+            /// The exchange succeeded if the returned value is `old`. It is fully ordered if it
+            /// succeeded, and unordered otherwise.
+            pub fn cmpxchg(&self, old: $t, new: $t) -> $t {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$cmpxchg(self.0.get(), old, new) }
+            }
+
+            /// Adds `a` to the value unless it is `u`, fully ordered if it was added.
+            ///
+            /// Returns `true` if `a` was added.
+            pub fn add_unless(&self, a: $t, u: $t) -> bool {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$add_unless(self.0.get(), a, u) }
+            }
+
+            /// Increments the value unless it is zero, fully ordered if it was incremented.
+            ///
+            /// Returns `true` if it was incremented. This is typically used to take a reference
+            /// to an object found by a lookup, unless it is already being freed.
+            pub fn inc_not_zero(&self) -> bool {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$inc_not_zero(self.0.get()) }
+            }
+
+            /// Decrements the value and returns `true` if the result is zero, fully ordered.
+            pub fn dec_and_test(&self) -> bool {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$dec_and_test(self.0.get()) }
+            }
+
+            /// Subtracts `i` from the value and returns `true` if the result is zero, fully
+            /// ordered.
+            pub fn sub_and_test(&self, i: $t) -> bool {
+                // SAFETY: The pointer is valid because it comes from a reference.
+                unsafe { bindings::$sub_and_test(i, self.0.get()) }
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new(0)
+            }
+        }
+    };
+}
+
+declare_atomic!(
+    /// A 32-bit atomic integer.
+    ///
+    /// Wraps the kernel's `atomic_t`.
+    ///
+    /// # Invariants
+    ///
+    /// The inner `atomic_t` is only accessed atomically.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kernel::sync::Atomic32;
+    /// fn example() {
+    ///     let users = Atomic32::new(1);
+    ///
+    ///     // Take an extra reference, unless the object is already being released.
+    ///     assert!(users.inc_not_zero());
+    ///     assert_eq!(users.add_return(2), 4);
+    ///
+    ///     users.sub(3);
+    ///     assert!(users.dec_and_test());
+    ///     assert!(!users.inc_not_zero());
+    /// }
+    /// ```
+    Atomic32, atomic_t, i32,
+    atomic_read, atomic_read_acquire, atomic_set, atomic_set_release,
+    atomic_add, atomic_sub, atomic_inc, atomic_dec,
+    atomic_add_return, atomic_sub_return, atomic_inc_return, atomic_dec_return,
+    atomic_fetch_add, atomic_xchg, atomic_cmpxchg,
+    atomic_add_unless, atomic_inc_not_zero, atomic_dec_and_test, atomic_sub_and_test
+);
+
+declare_atomic!(
+    /// A 64-bit atomic integer.
+    ///
+    /// Wraps the kernel's `atomic64_t`. On 32-bit architectures without native 64-bit atomics it
+    /// may be implemented with locks, so [`Atomic32`] should be preferred when its range suffices.
+    ///
+    /// # Invariants
+    ///
+    /// The inner `atomic64_t` is only accessed atomically.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kernel::sync::Atomic64;
+    /// fn example() {
+    ///     let bytes = Atomic64::new(0);
+    ///
+    ///     bytes.add(4096);
+    ///     assert_eq!(bytes.cmpxchg(4096, 0), 4096);
+    ///     assert_eq!(bytes.read(), 0);
+    /// }
+    /// ```
+    Atomic64, atomic64_t, i64,
+    atomic64_read, atomic64_read_acquire, atomic64_set, atomic64_set_release,
+    atomic64_add, atomic64_sub, atomic64_inc, atomic64_dec,
+    atomic64_add_return, atomic64_sub_return, atomic64_inc_return, atomic64_dec_return,
+    atomic64_fetch_add, atomic64_xchg, atomic64_cmpxchg,
+    atomic64_add_unless, atomic64_inc_not_zero, atomic64_dec_and_test, atomic64_sub_and_test
+);
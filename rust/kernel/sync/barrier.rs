@@ -0,0 +1,169 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Memory barriers.
+//!
+//! These follow the kernel's memory model, described in
+//! `tools/memory-model/Documentation/explanation.txt`, so that lock-free Rust code that shares
+//! data with C code uses the same primitives as the C side.
+//!
+//! C header: [`include/asm-generic/barrier.h`](../../../../include/asm-generic/barrier.h)
+//!
+//! Reference: <https://www.kernel.org/doc/html/latest/core-api/wrappers/memory-barriers.html>
+
+use crate::{bindings, build_assert};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// A compiler barrier.
+///
+/// Prevents the compiler from reordering memory accesses across it, but not the CPU.
+///
+/// Equivalent to the kernel's `barrier`.
+#[inline(always)]
+pub fn barrier() {
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// A full memory barrier between CPUs.
+///
+/// Orders all the memory accesses before it with all the ones after it, as seen by other CPUs.
+///
+/// Equivalent to the kernel's `smp_mb`.
+#[inline(always)]
+pub fn smp_mb() {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::smp_mb() };
+}
+
+/// A read memory barrier between CPUs.
+///
+/// Orders the loads before it with the loads after it, as seen by other CPUs. It usually pairs
+/// with [`smp_wmb`].
+///
+/// Equivalent to the kernel's `smp_rmb`.
+#[inline(always)]
+pub fn smp_rmb() {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::smp_rmb() };
+}
+
+/// A write memory barrier between CPUs.
+///
+/// Orders the stores before it with the stores after it, as seen by other CPUs. It usually pairs
+/// with [`smp_rmb`].
+///
+/// Equivalent to the kernel's `smp_wmb`.
+#[inline(always)]
+pub fn smp_wmb() {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::smp_wmb() };
+}
+
+#[inline(always)]
+fn check_size<T>() {
+    let size = core::mem::size_of::<T>();
+    build_assert!(
+        size == 1 || size == 2 || size == 4 || size == 8,
+        "Only scalars of 1, 2, 4 or 8 bytes can be accessed once"
+    );
+}
+
+/// Reads the value at `ptr` exactly once, with a single access.
+///
+/// Prevents the compiler from tearing, fusing or omitting the load, but doesn't order it.
+///
+/// Equivalent to the kernel's `READ_ONCE`.
+///
+/// # Safety
+///
+/// `ptr` must be valid for reads and properly aligned. Concurrent writers must also only use
+/// single-access stores (e.g., [`write_once`] or C's `WRITE_ONCE`).
+#[inline(always)]
+pub unsafe fn read_once<T: Copy>(ptr: *const T) -> T {
+    check_size::<T>();
+    // SAFETY: The safety requirements guarantee that `ptr` is valid for reads.
+    unsafe { ptr.read_volatile() }
+}
+
+/// Writes `value` at `ptr` exactly once, with a single access.
+///
+/// Prevents the compiler from tearing, fusing or omitting the store, but doesn't order it.
+///
+/// Equivalent to the kernel's `WRITE_ONCE`.
+///
+/// # Safety
+///
+/// `ptr` must be valid for writes and properly aligned. Concurrent readers and writers must also
+/// only use single-access loads and stores.
+#[inline(always)]
+pub unsafe fn write_once<T: Copy>(ptr: *mut T, value: T) {
+    check_size::<T>();
+    // SAFETY: The safety requirements guarantee that `ptr` is valid for writes.
+    unsafe { ptr.write_volatile(value) };
+}
+
+/// Reads the value at `ptr` with acquire ordering.
+///
+/// Memory accesses after it cannot be reordered before it. It pairs with
+/// [`smp_store_release`].
+///
+/// Equivalent to the kernel's `smp_load_acquire`.
+///
+/// # Safety
+///
+/// The same as [`read_once`].
+#[inline(always)]
+pub unsafe fn smp_load_acquire<T: Copy>(ptr: *const T) -> T {
+    // SAFETY: The safety requirements are the same.
+    let value = unsafe { read_once(ptr) };
+    // This is the generic implementation, which is stronger than needed on some architectures.
+    smp_mb();
+    value
+}
+
+/// Writes `value` at `ptr` with release ordering.
+///
+/// Memory accesses before it cannot be reordered after it, so a CPU that reads the value with
+/// [`smp_load_acquire`] also sees them.
+///
+/// Equivalent to the kernel's `smp_store_release`.
+///
+/// # Safety
+///
+/// The same as [`write_once`].
+///
+/// # Examples
+///
+/// ```
+/// use kernel::sync::barrier::{smp_load_acquire, smp_store_release};
+///
+/// struct Message {
+///     data: u64,
+///     ready: bool,
+/// }
+///
+/// fn publish(msg: *mut Message, data: u64) {
+///     // SAFETY: `msg` is valid, and `ready` is only accessed with the functions above.
+///     unsafe {
+///         (*msg).data = data;
+///         smp_store_release(core::ptr::addr_of_mut!((*msg).ready), true);
+///     }
+/// }
+///
+/// fn consume(msg: *const Message) -> Option<u64> {
+///     // SAFETY: As above. `data` was written before `ready` was released.
+///     unsafe {
+///         if smp_load_acquire(core::ptr::addr_of!((*msg).ready)) {
+///             Some((*msg).data)
+///         } else {
+///             None
+///         }
+///     }
+/// }
+/// ```
+#[inline(always)]
+pub unsafe fn smp_store_release<T: Copy>(ptr: *mut T, value: T) {
+    // This is the generic implementation, which is stronger than needed on some architectures.
+    smp_mb();
+    // SAFETY: The safety requirements are the same.
+    unsafe { write_once(ptr, value) };
+}
@@ -0,0 +1,223 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A kernel completion.
+//!
+//! This module allows Rust code to use the kernel's [`struct completion`], to wait for an event
+//! to happen once (or a number of times).
+//!
+//! C header: [`include/linux/completion.h`](../../../../include/linux/completion.h)
+
+use super::NeedsLockClass;
+use crate::{
+    bindings,
+    str::CStr,
+    time::{Ktime, TimeoutResult},
+    to_result, Error, Opaque, Result,
+};
+use core::{marker::PhantomPinned, pin::Pin};
+
+/// Safely initialises a [`Completion`] with the given name, generating a new lock class.
+#[macro_export]
+macro_rules! completion_init {
+    ($completion:expr, $name:literal) => {
+        $crate::init_with_lockdep!($completion, $name)
+    };
+}
+
+/// Exposes the kernel's [`struct completion`].
+///
+/// A task waits for an event with one of the `wait` functions, until another task (or an
+/// interrupt handler) signals that it happened with [`Completion::complete`]. Unlike
+/// [`super::WaitQueue`], the completion counts the events, so they are not missed if they happen
+/// before the wait starts.
+///
+/// The completion must first be initialised with a call to [`Completion::init_completion`] (or
+/// the [`completion_init`] macro) before it can be used.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// # use kernel::sync::Completion;
+/// use kernel::time::Ktime;
+///
+/// struct Device {
+///     reset_done: Completion,
+/// }
+///
+/// impl Device {
+///     fn reset(&self) -> Result {
+///         // Start the reset, then wait for the interrupt.
+///         if self.reset_done.wait_timeout(Ktime::from_ms(50)).timed_out() {
+///             return Err(ETIMEDOUT);
+///         }
+///         Ok(())
+///     }
+///
+///     fn handle_irq(&self) {
+///         self.reset_done.complete();
+///     }
+/// }
+/// ```
+///
+/// [`struct completion`]: ../../../include/linux/completion.h
+pub struct Completion {
+    completion: Opaque<bindings::completion>,
+
+    /// A completion needs to be pinned because it contains a wait queue, which is
+    /// self-referential, so it cannot be safely moved once it is initialised.
+    _pin: PhantomPinned,
+}
+
+// SAFETY: `Completion` only uses a `struct completion`, which is safe to use on any thread.
+unsafe impl Send for Completion {}
+
+// SAFETY: All the operations of `Completion` are safe to call concurrently from multiple threads.
+unsafe impl Sync for Completion {}
+
+impl Completion {
+    /// Constructs a new completion.
+    ///
+    /// # Safety
+    ///
+    /// The caller must call [`Completion::init_completion`] (or `NeedsLockClass::init`) before
+    /// using the completion.
+    pub const unsafe fn new() -> Self {
+        Self {
+            completion: Opaque::uninit(),
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Initialises the completion with the given name and lock class.
+    ///
+    /// Callers are encouraged to use the [`completion_init`] macro instead.
+    ///
+    /// # Safety
+    ///
+    /// `key` must point to a valid memory location and remain valid until `self` is dropped.
+    pub unsafe fn init_completion(
+        self: Pin<&mut Self>,
+        name: &'static CStr,
+        key: *mut bindings::lock_class_key,
+    ) {
+        let completion = self.completion.get();
+
+        // SAFETY: `completion` is pinned and is being initialised here, and `key` is valid by the
+        // safety requirements.
+        unsafe {
+            (*completion).done = 0;
+            bindings::__init_swait_queue_head(&mut (*completion).wait, name.as_char_ptr(), key);
+        }
+    }
+
+    /// Signals one event, waking up one waiter if there is one.
+    ///
+    /// It may be called from any context.
+    pub fn complete(&self) {
+        // SAFETY: `completion` is valid by the safety requirements of `new`.
+        unsafe { bindings::complete(self.completion.get()) };
+    }
+
+    /// Signals that the event happened for good, waking up all the waiters.
+    ///
+    /// All the waits complete immediately from now on, until [`Completion::reinit`] is called.
+    /// It may be called from any context.
+    pub fn complete_all(&self) {
+        // SAFETY: `completion` is valid by the safety requirements of `new`.
+        unsafe { bindings::complete_all(self.completion.get()) };
+    }
+
+    /// Resets the completion, discarding the events that haven't been waited for.
+    ///
+    /// It must not race with [`Completion::complete`] or [`Completion::complete_all`].
+    pub fn reinit(&self) {
+        // SAFETY: `completion` is valid by the safety requirements of `new`.
+        unsafe { bindings::reinit_completion(self.completion.get()) };
+    }
+
+    /// Sleeps uninterruptibly until an event is signalled, and consumes it.
+    pub fn wait(&self) {
+        crate::might_sleep!();
+        // SAFETY: `completion` is valid by the safety requirements of `new`.
+        unsafe { bindings::wait_for_completion(self.completion.get()) };
+    }
+
+    /// Sleeps interruptibly until an event is signalled, and consumes it.
+    ///
+    /// Returns [`ERESTARTSYS`] if a signal is pending first.
+    ///
+    /// [`ERESTARTSYS`]: crate::error::code::ERESTARTSYS
+    pub fn wait_interruptible(&self) -> Result {
+        crate::might_sleep!();
+        // SAFETY: `completion` is valid by the safety requirements of `new`.
+        to_result(|| unsafe { bindings::wait_for_completion_interruptible(self.completion.get()) })
+    }
+
+    /// Sleeps until an event is signalled, and consumes it, unless the task is killed.
+    ///
+    /// Returns [`ERESTARTSYS`] if a fatal signal is pending first.
+    ///
+    /// [`ERESTARTSYS`]: crate::error::code::ERESTARTSYS
+    pub fn wait_killable(&self) -> Result {
+        crate::might_sleep!();
+        // SAFETY: `completion` is valid by the safety requirements of `new`.
+        to_result(|| unsafe { bindings::wait_for_completion_killable(self.completion.get()) })
+    }
+
+    /// Sleeps uninterruptibly until an event is signalled, and consumes it, or `timeout` elapses.
+    pub fn wait_timeout(&self, timeout: Ktime) -> TimeoutResult {
+        crate::might_sleep!();
+        // SAFETY: `completion` is valid by the safety requirements of `new`.
+        let left = unsafe {
+            bindings::wait_for_completion_timeout(self.completion.get(), timeout.to_timeout() as _)
+        };
+        TimeoutResult::from_timeout(left as _)
+    }
+
+    /// Sleeps interruptibly until an event is signalled, and consumes it, or `timeout` elapses.
+    ///
+    /// Returns [`ERESTARTSYS`] if a signal is pending first.
+    ///
+    /// [`ERESTARTSYS`]: crate::error::code::ERESTARTSYS
+    pub fn wait_interruptible_timeout(&self, timeout: Ktime) -> Result<TimeoutResult> {
+        crate::might_sleep!();
+        // SAFETY: `completion` is valid by the safety requirements of `new`.
+        let ret = unsafe {
+            bindings::wait_for_completion_interruptible_timeout(
+                self.completion.get(),
+                timeout.to_timeout() as _,
+            )
+        };
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret as _));
+        }
+        Ok(TimeoutResult::from_timeout(ret))
+    }
+
+    /// Consumes an event if one was signalled, without sleeping.
+    ///
+    /// Returns whether an event was consumed. It may be called from any context.
+    pub fn try_wait(&self) -> bool {
+        // SAFETY: `completion` is valid by the safety requirements of `new`.
+        unsafe { bindings::try_wait_for_completion(self.completion.get()) }
+    }
+
+    /// Returns whether there are events that haven't been waited for.
+    pub fn is_done(&self) -> bool {
+        // SAFETY: `completion` is valid by the safety requirements of `new`.
+        unsafe { bindings::completion_done(self.completion.get()) }
+    }
+}
+
+impl NeedsLockClass for Completion {
+    unsafe fn init(
+        self: Pin<&mut Self>,
+        name: &'static CStr,
+        key: *mut bindings::lock_class_key,
+        _: *mut bindings::lock_class_key,
+    ) {
+        // SAFETY: The safety requirements of this function are the same as `init_completion`.
+        unsafe { self.init_completion(name, key) };
+    }
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Read-copy-update.
+//!
+//! Readers hold a [`Guard`] while they access RCU-protected data, and updaters wait for the
+//! readers that may still see old data with [`synchronize`] before freeing it.
+//!
+//! C header: [`include/linux/rcupdate.h`](../../../../include/linux/rcupdate.h)
+//!
+//! Reference: <https://www.kernel.org/doc/html/latest/RCU/whatisRCU.html>
+
+use crate::bindings;
+use core::marker::PhantomData;
+
+/// Evidence that the RCU read-side lock is held on the current CPU.
+///
+/// References to RCU-protected data borrow the guard, so they cannot outlive the read-side
+/// critical section. The guard is not `Send`, since the lock is held by the current CPU.
+///
+/// # Invariants
+///
+/// The RCU read-side lock is held while the guard exists.
+pub struct Guard {
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl Guard {
+    /// Acquires the RCU read-side lock and returns a guard that releases it when dropped.
+    pub fn new() -> Self {
+        // SAFETY: There are no safety requirements for this FFI call.
+        unsafe { bindings::rcu_read_lock() };
+
+        // INVARIANT: The lock was acquired above.
+        Self {
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Default for Guard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, the lock is held.
+        unsafe { bindings::rcu_read_unlock() };
+    }
+}
+
+/// Acquires the RCU read-side lock, see [`Guard`].
+pub fn read_lock() -> Guard {
+    Guard::new()
+}
+
+/// Waits until all the RCU read-side critical sections that are in progress have completed.
+///
+/// It may sleep.
+pub fn synchronize() {
+    crate::might_sleep!();
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::synchronize_rcu() };
+}
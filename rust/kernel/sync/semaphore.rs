@@ -0,0 +1,191 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A kernel counting semaphore.
+//!
+//! This module allows Rust code to use the kernel's [`struct semaphore`].
+//!
+//! C header: [`include/linux/semaphore.h`](../../../../include/linux/semaphore.h)
+
+use super::NeedsLockClass;
+use crate::{
+    bindings,
+    error::code::*,
+    str::CStr,
+    time::{self, Ktime, TimeoutResult},
+    Opaque, Result,
+};
+use core::{marker::PhantomPinned, pin::Pin};
+
+/// Safely initialises a [`Semaphore`] with the given name, generating a new lock class.
+#[macro_export]
+macro_rules! semaphore_init {
+    ($sem:expr, $name:literal) => {
+        $crate::init_with_lockdep!($sem, $name)
+    };
+}
+
+/// Exposes the kernel's [`struct semaphore`].
+///
+/// Unlike [`super::Mutex`], a semaphore does not protect any data and is not owned by the task
+/// that acquired it: it may be released by a different task (or from interrupt context) than the
+/// one that acquired it. It is mostly useful in drivers ported from C that use it to count
+/// resources; new code should usually prefer [`super::Mutex`] or [`super::CondVar`].
+///
+/// The semaphore must first be initialised with a call to [`Semaphore::init_semaphore`] (or the
+/// [`semaphore_init`] macro) before it can be used.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// # use kernel::semaphore_init;
+/// # use kernel::sync::Semaphore;
+/// # use alloc::boxed::Box;
+/// # use core::pin::Pin;
+/// fn example() -> Result {
+///     // SAFETY: `init` is called below.
+///     let mut sem = Pin::from(Box::try_new(unsafe { Semaphore::new(2) })?);
+///     semaphore_init!(sem.as_mut(), "example::sem");
+///
+///     sem.down_interruptible()?;
+///     if sem.down_trylock() {
+///         // Both resources are now in use.
+///         sem.up();
+///     }
+///     sem.up();
+///     Ok(())
+/// }
+/// ```
+///
+/// [`struct semaphore`]: ../../../include/linux/semaphore.h
+pub struct Semaphore {
+    /// The kernel `struct semaphore` object.
+    sema: Opaque<bindings::semaphore>,
+
+    /// The initial count, only used by [`Semaphore::init_semaphore`].
+    count: u32,
+
+    /// A semaphore needs to be pinned because it contains a `struct list_head` that is
+    /// self-referential, so it cannot be safely moved once it is initialised.
+    _pin: PhantomPinned,
+}
+
+// SAFETY: `Semaphore` does not protect any data, and the kernel allows it to be acquired and
+// released by any task.
+unsafe impl Send for Semaphore {}
+
+// SAFETY: All the operations of `Semaphore` are safe to call concurrently from multiple threads.
+unsafe impl Sync for Semaphore {}
+
+impl Semaphore {
+    /// Constructs a new semaphore with `count` resources available.
+    ///
+    /// # Safety
+    ///
+    /// The caller must call [`Semaphore::init_semaphore`] before using the semaphore.
+    pub const unsafe fn new(count: u32) -> Self {
+        Self {
+            sema: Opaque::uninit(),
+            count,
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Acquires the semaphore, sleeping uninterruptibly until a resource is available.
+    ///
+    /// Prefer [`Semaphore::down_interruptible`], which allows the task to be woken up by
+    /// signals.
+    pub fn down(&self) {
+        // SAFETY: `sema` is valid by the safety requirements of `new`.
+        unsafe { bindings::down(self.sema.get()) };
+    }
+
+    /// Acquires the semaphore, sleeping interruptibly until a resource is available.
+    ///
+    /// Returns [`EINTR`] if the sleep was interrupted by a signal, in which case the semaphore
+    /// was not acquired.
+    pub fn down_interruptible(&self) -> Result {
+        // SAFETY: `sema` is valid by the safety requirements of `new`.
+        if unsafe { bindings::down_interruptible(self.sema.get()) } != 0 {
+            return Err(EINTR);
+        }
+        Ok(())
+    }
+
+    /// Acquires the semaphore, sleeping until a resource is available unless the task is killed.
+    ///
+    /// Returns [`EINTR`] if the sleep was interrupted by a fatal signal, in which case the
+    /// semaphore was not acquired.
+    pub fn down_killable(&self) -> Result {
+        // SAFETY: `sema` is valid by the safety requirements of `new`.
+        if unsafe { bindings::down_killable(self.sema.get()) } != 0 {
+            return Err(EINTR);
+        }
+        Ok(())
+    }
+
+    /// Acquires the semaphore, sleeping uninterruptibly until a resource is available or `timeout`
+    /// elapses.
+    ///
+    /// The semaphore was acquired if it returns [`TimeoutResult::Completed`].
+    pub fn down_timeout(&self, timeout: Ktime) -> TimeoutResult {
+        let timeout = timeout.to_timeout();
+        let start = time::jiffies();
+
+        // SAFETY: `sema` is valid by the safety requirements of `new`.
+        if unsafe { bindings::down_timeout(self.sema.get(), timeout) } != 0 {
+            return TimeoutResult::TimedOut;
+        }
+
+        // `down_timeout` doesn't return the time left, so compute it.
+        let elapsed = time::jiffies().wrapping_sub(start) as _;
+        TimeoutResult::from_timeout(timeout.saturating_sub(elapsed).max(1))
+    }
+
+    /// Tries to acquire the semaphore without sleeping.
+    ///
+    /// Returns `true` if a resource was available and it was acquired, `false` otherwise. Note
+    /// that this is the opposite of the return value of the C `down_trylock`. It may be called
+    /// from interrupt context.
+    pub fn down_trylock(&self) -> bool {
+        // SAFETY: `sema` is valid by the safety requirements of `new`.
+        unsafe { bindings::down_trylock(self.sema.get()) == 0 }
+    }
+
+    /// Releases the semaphore, waking up a waiter if there is one.
+    ///
+    /// It may be called from any context, including by a task other than the one that acquired
+    /// it.
+    pub fn up(&self) {
+        // SAFETY: `sema` is valid by the safety requirements of `new`.
+        unsafe { bindings::up(self.sema.get()) };
+    }
+
+    /// Initialises the semaphore.
+    ///
+    /// Callers are encouraged to use the [`semaphore_init`] macro instead.
+    ///
+    /// # Safety
+    ///
+    /// The semaphore must not be in use, that is, no task may hold it or wait for it, since
+    /// re-initialising it would lose track of them.
+    pub unsafe fn init_semaphore(self: Pin<&mut Self>) {
+        // SAFETY: `sema` is pinned and is being initialised here, and it isn't in use by the
+        // safety requirements. `sema_init` registers the lock class of the internal spinlock
+        // itself.
+        unsafe { bindings::sema_init(self.sema.get(), self.count) };
+    }
+}
+
+impl NeedsLockClass for Semaphore {
+    unsafe fn init(
+        self: Pin<&mut Self>,
+        _name: &'static CStr,
+        _key1: *mut bindings::lock_class_key,
+        _key2: *mut bindings::lock_class_key,
+    ) {
+        // SAFETY: Like the other synchronisation primitives, the semaphore is initialised with
+        // `init_with_lockdep!` right after being created, before it can be in use.
+        unsafe { self.init_semaphore() };
+    }
+}
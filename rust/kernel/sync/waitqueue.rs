@@ -0,0 +1,366 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! A kernel wait queue.
+//!
+//! This module allows Rust code to use the kernel's [`struct wait_queue_head`], to sleep until a
+//! condition becomes true.
+//!
+//! C header: [`include/linux/wait.h`](../../../../include/linux/wait.h)
+
+use super::NeedsLockClass;
+use crate::{
+    bindings, c_types,
+    str::CStr,
+    time::{Ktime, TimeoutResult},
+    Error, Opaque, Result,
+};
+use core::{marker::PhantomPinned, pin::Pin};
+
+/// Safely initialises a [`WaitQueue`] with the given name, generating a new lock class.
+#[macro_export]
+macro_rules! waitqueue_init {
+    ($wq:expr, $name:literal) => {
+        $crate::init_with_lockdep!($wq, $name)
+    };
+}
+
+/// Sleeps uninterruptibly on a [`WaitQueue`] until the condition is true.
+///
+/// The condition is evaluated before sleeping and every time the task is woken up. It must be
+/// made true before the wait queue is woken up with [`WaitQueue::wake_up`] or similar, otherwise
+/// the wake-up may be missed.
+///
+/// Equivalent to the kernel's `wait_event` macro.
+#[macro_export]
+macro_rules! wait_event {
+    ($wq:expr, $cond:expr) => {
+        $wq.wait(|| $cond)
+    };
+}
+
+/// Sleeps interruptibly on a [`WaitQueue`] until the condition is true.
+///
+/// The condition is evaluated before sleeping and every time the task is woken up. Returns
+/// [`ERESTARTSYS`] if the sleep was interrupted by a signal before the condition became true,
+/// which file operations should usually propagate to let the system call be restarted.
+///
+/// Equivalent to the kernel's `wait_event_interruptible` macro.
+///
+/// [`ERESTARTSYS`]: crate::error::code::ERESTARTSYS
+#[macro_export]
+macro_rules! wait_event_interruptible {
+    ($wq:expr, $cond:expr) => {
+        $wq.wait_interruptible(|| $cond)
+    };
+}
+
+/// Sleeps on a [`WaitQueue`] until the condition is true, unless the task is killed.
+///
+/// Like [`wait_event_interruptible`], but only fatal signals (e.g., `SIGKILL`) interrupt the
+/// sleep, in which case [`ERESTARTSYS`] is returned.
+///
+/// Equivalent to the kernel's `wait_event_killable` macro.
+///
+/// [`ERESTARTSYS`]: crate::error::code::ERESTARTSYS
+#[macro_export]
+macro_rules! wait_event_killable {
+    ($wq:expr, $cond:expr) => {
+        $wq.wait_killable(|| $cond)
+    };
+}
+
+/// Sleeps uninterruptibly on a [`WaitQueue`] until the condition is true or the timeout expires.
+///
+/// The timeout is a [`Ktime`](crate::time::Ktime) interval. Returns a
+/// [`TimeoutResult`](crate::time::TimeoutResult); the wait completes if the condition is true when
+/// the timeout expires.
+///
+/// Equivalent to the kernel's `wait_event_timeout` macro.
+#[macro_export]
+macro_rules! wait_event_timeout {
+    ($wq:expr, $cond:expr, $timeout:expr) => {
+        $wq.wait_timeout(|| $cond, $timeout)
+    };
+}
+
+/// Sleeps interruptibly on a [`WaitQueue`] until the condition is true or the timeout expires.
+///
+/// Like [`wait_event_timeout`], but returns [`ERESTARTSYS`] if the sleep was interrupted by a
+/// signal first.
+///
+/// Equivalent to the kernel's `wait_event_interruptible_timeout` macro.
+///
+/// [`ERESTARTSYS`]: crate::error::code::ERESTARTSYS
+#[macro_export]
+macro_rules! wait_event_interruptible_timeout {
+    ($wq:expr, $cond:expr, $timeout:expr) => {
+        $wq.wait_interruptible_timeout(|| $cond, $timeout)
+    };
+}
+
+/// Exposes the kernel's [`struct wait_queue_head`].
+///
+/// Tasks sleep on it until a condition becomes true; the task that makes the condition true then
+/// wakes them up. Unlike [`super::CondVar`], the condition is not protected by a lock, so it is
+/// usually checked with atomics or with the lock taken inside the condition itself.
+///
+/// The wait queue must first be initialised with a call to [`WaitQueue::init_waitqueue`] (or the
+/// [`waitqueue_init`] macro) before it can be used.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// # use kernel::sync::{Atomic32, WaitQueue};
+/// # use kernel::wait_event_interruptible;
+/// struct Device {
+///     available: Atomic32,
+///     wq: WaitQueue,
+/// }
+///
+/// impl Device {
+///     fn wait_for_data(&self) -> Result {
+///         wait_event_interruptible!(self.wq, self.available.read() > 0)
+///     }
+///
+///     fn data_arrived(&self) {
+///         self.available.inc();
+///         self.wq.wake_up_interruptible();
+///     }
+/// }
+/// ```
+///
+/// [`struct wait_queue_head`]: ../../../include/linux/wait.h
+pub struct WaitQueue {
+    pub(crate) wait_list: Opaque<bindings::wait_queue_head>,
+
+    /// A wait queue needs to be pinned because it contains a `struct list_head` that is
+    /// self-referential, so it cannot be safely moved once it is initialised.
+    _pin: PhantomPinned,
+}
+
+// SAFETY: `WaitQueue` only uses a `struct wait_queue_head`, which is safe to use on any thread.
+unsafe impl Send for WaitQueue {}
+
+// SAFETY: `WaitQueue` only uses a `struct wait_queue_head`, which is safe to use on multiple
+// threads concurrently.
+unsafe impl Sync for WaitQueue {}
+
+impl WaitQueue {
+    /// Constructs a new wait queue.
+    ///
+    /// # Safety
+    ///
+    /// The caller must call [`WaitQueue::init_waitqueue`] (or `NeedsLockClass::init`) before
+    /// using the wait queue.
+    pub const unsafe fn new() -> Self {
+        Self {
+            wait_list: Opaque::uninit(),
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Initialises the wait queue with the given name and lock class.
+    ///
+    /// Callers are encouraged to use the [`waitqueue_init`] macro instead.
+    ///
+    /// # Safety
+    ///
+    /// `key` must point to a valid memory location and remain valid until `self` is dropped.
+    pub unsafe fn init_waitqueue(
+        self: Pin<&mut Self>,
+        name: &'static CStr,
+        key: *mut bindings::lock_class_key,
+    ) {
+        // SAFETY: `wait_list` is pinned, and `key` is valid by the safety requirements.
+        unsafe { bindings::__init_waitqueue_head(self.wait_list.get(), name.as_char_ptr(), key) };
+    }
+
+    /// Sleeps until `cond` returns `true` or `timeout` jiffies elapse, in the given task state.
+    ///
+    /// Returns the jiffies left, which are at least one if `cond` returned `true`.
+    fn wait_event(
+        &self,
+        state: u32,
+        timeout: c_types::c_long,
+        mut cond: impl FnMut() -> bool,
+    ) -> Result<c_types::c_long> {
+        crate::might_sleep!();
+        if cond() {
+            return Ok(timeout.max(1));
+        }
+
+        let wait = Opaque::<bindings::wait_queue_entry>::uninit();
+
+        // SAFETY: `wait` is valid for writes and doesn't move until it goes out of scope.
+        unsafe { bindings::init_wait_entry(wait.get(), 0) };
+
+        let mut left = timeout;
+        let ret = loop {
+            // SAFETY: Both `wait_list` and `wait` are valid.
+            let ret = unsafe {
+                bindings::prepare_to_wait_event(self.wait_list.get(), wait.get(), state as _)
+            };
+
+            // The condition is checked once more after the timeout expires, like C does.
+            if cond() {
+                break Ok(left.max(1));
+            }
+
+            // `prepare_to_wait_event` returns a negative error only for signals that interrupt
+            // the given task state.
+            if ret != 0 {
+                break Err(Error::from_kernel_errno(ret as _));
+            }
+
+            if left == 0 {
+                break Ok(0);
+            }
+
+            // SAFETY: Switches to another thread. `MAX_SCHEDULE_TIMEOUT` sleeps without a timeout.
+            left = unsafe { bindings::schedule_timeout(left) };
+        };
+
+        // SAFETY: `wait` was initialised above, and `finish_wait` handles entries that were
+        // already removed from the queue.
+        unsafe { bindings::finish_wait(self.wait_list.get(), wait.get()) };
+        ret
+    }
+
+    /// Sleeps uninterruptibly until `cond` returns `true`.
+    ///
+    /// Callers are encouraged to use the [`wait_event`] macro instead.
+    pub fn wait(&self, cond: impl FnMut() -> bool) {
+        // Uninterruptible waits cannot fail.
+        let _ = self.wait_event(bindings::TASK_UNINTERRUPTIBLE, MAX_TIMEOUT, cond);
+    }
+
+    /// Sleeps interruptibly until `cond` returns `true`.
+    ///
+    /// Returns [`ERESTARTSYS`] if a signal is pending before `cond` returns `true`. Callers are
+    /// encouraged to use the [`wait_event_interruptible`] macro instead.
+    ///
+    /// [`ERESTARTSYS`]: crate::error::code::ERESTARTSYS
+    pub fn wait_interruptible(&self, cond: impl FnMut() -> bool) -> Result {
+        self.wait_event(bindings::TASK_INTERRUPTIBLE, MAX_TIMEOUT, cond)?;
+        Ok(())
+    }
+
+    /// Sleeps until `cond` returns `true`, unless the task is killed.
+    ///
+    /// Returns [`ERESTARTSYS`] if a fatal signal is pending before `cond` returns `true`. Callers
+    /// are encouraged to use the [`wait_event_killable`] macro instead.
+    ///
+    /// [`ERESTARTSYS`]: crate::error::code::ERESTARTSYS
+    pub fn wait_killable(&self, cond: impl FnMut() -> bool) -> Result {
+        self.wait_event(bindings::TASK_KILLABLE, MAX_TIMEOUT, cond)?;
+        Ok(())
+    }
+
+    /// Sleeps uninterruptibly until `cond` returns `true` or `timeout` elapses.
+    ///
+    /// Callers are encouraged to use the [`wait_event_timeout`] macro instead.
+    pub fn wait_timeout(&self, cond: impl FnMut() -> bool, timeout: Ktime) -> TimeoutResult {
+        // Uninterruptible waits cannot fail.
+        let left = self
+            .wait_event(bindings::TASK_UNINTERRUPTIBLE, timeout.to_timeout(), cond)
+            .unwrap_or(0);
+        TimeoutResult::from_timeout(left)
+    }
+
+    /// Sleeps interruptibly until `cond` returns `true` or `timeout` elapses.
+    ///
+    /// Returns [`ERESTARTSYS`] if a signal is pending first. Callers are encouraged to use the
+    /// [`wait_event_interruptible_timeout`] macro instead.
+    ///
+    /// [`ERESTARTSYS`]: crate::error::code::ERESTARTSYS
+    pub fn wait_interruptible_timeout(
+        &self,
+        cond: impl FnMut() -> bool,
+        timeout: Ktime,
+    ) -> Result<TimeoutResult> {
+        let left = self.wait_event(bindings::TASK_INTERRUPTIBLE, timeout.to_timeout(), cond)?;
+        Ok(TimeoutResult::from_timeout(left))
+    }
+
+    /// Wakes up one exclusive waiter and all non-exclusive ones.
+    ///
+    /// Equivalent to the kernel's `wake_up`.
+    pub fn wake_up(&self) {
+        // SAFETY: `wait_list` points to valid memory.
+        unsafe {
+            bindings::__wake_up(
+                self.wait_list.get(),
+                bindings::TASK_NORMAL,
+                1,
+                core::ptr::null_mut(),
+            )
+        };
+    }
+
+    /// Wakes up all waiters.
+    ///
+    /// Equivalent to the kernel's `wake_up_all`.
+    pub fn wake_up_all(&self) {
+        // SAFETY: `wait_list` points to valid memory.
+        unsafe {
+            bindings::__wake_up(
+                self.wait_list.get(),
+                bindings::TASK_NORMAL,
+                0,
+                core::ptr::null_mut(),
+            )
+        };
+    }
+
+    /// Wakes up one exclusive waiter and all non-exclusive ones, only if they sleep
+    /// interruptibly.
+    ///
+    /// Equivalent to the kernel's `wake_up_interruptible`.
+    pub fn wake_up_interruptible(&self) {
+        // SAFETY: `wait_list` points to valid memory.
+        unsafe {
+            bindings::__wake_up(
+                self.wait_list.get(),
+                bindings::TASK_INTERRUPTIBLE,
+                1,
+                core::ptr::null_mut(),
+            )
+        };
+    }
+
+    /// Returns whether there are tasks sleeping on the wait queue.
+    ///
+    /// This is racy unless the caller serialises against the waiters, but allows skipping
+    /// wake-ups when nobody is waiting.
+    pub fn has_sleeper(&self) -> bool {
+        // SAFETY: `wait_list` points to valid memory.
+        unsafe { bindings::wq_has_sleeper(self.wait_list.get()) }
+    }
+
+    /// Wakes up all poll waiters and removes them from the wait queue.
+    ///
+    /// This must be called before the wait queue is freed if it was passed to
+    /// [`PollTable::register_wait_queue`] and the associated files may outlive it.
+    ///
+    /// [`PollTable::register_wait_queue`]: crate::file::PollTable::register_wait_queue
+    pub fn free_waiters(&self) {
+        // SAFETY: `wait_list` points to valid memory.
+        unsafe { bindings::__wake_up_pollfree(self.wait_list.get()) };
+    }
+}
+
+/// The timeout of waits without one, which `schedule_timeout` treats as infinite.
+const MAX_TIMEOUT: c_types::c_long = bindings::MAX_SCHEDULE_TIMEOUT as _;
+
+impl NeedsLockClass for WaitQueue {
+    unsafe fn init(
+        self: Pin<&mut Self>,
+        name: &'static CStr,
+        key: *mut bindings::lock_class_key,
+        _: *mut bindings::lock_class_key,
+    ) {
+        // SAFETY: The safety requirements of this function are the same as `init_waitqueue`.
+        unsafe { self.init_waitqueue(name, key) };
+    }
+}
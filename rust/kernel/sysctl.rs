@@ -14,7 +14,7 @@ use core::sync::atomic;
 
 use crate::{
     bindings, c_types,
-    error::code::*,
+    error::{code::*, from_result},
     io_buffer::IoBufferWriter,
     str::CStr,
     types,
@@ -103,31 +103,28 @@ unsafe extern "C" fn proc_handler<T: SysctlStorage>(
     len: *mut usize,
     ppos: *mut bindings::loff_t,
 ) -> c_types::c_int {
-    // If we are reading from some offset other than the beginning of the file,
-    // return an empty read to signal EOF.
-    if unsafe { *ppos } != 0 && write == 0 {
-        unsafe { *len = 0 };
-        return 0;
-    }
+    from_result(|| {
+        // If we are reading from some offset other than the beginning of the file,
+        // return an empty read to signal EOF.
+        if unsafe { *ppos } != 0 && write == 0 {
+            unsafe { *len = 0 };
+            return Ok(0);
+        }
 
-    let data = unsafe { UserSlicePtr::new(buffer, *len) };
-    let storage = unsafe { &*((*ctl).data as *const T) };
-    let (bytes_processed, result) = if write != 0 {
-        let data = match data.read_all() {
-            Ok(r) => r,
-            Err(e) => return e.to_kernel_errno(),
+        let data = unsafe { UserSlicePtr::new(buffer, *len) };
+        let storage = unsafe { &*((*ctl).data as *const T) };
+        let (bytes_processed, result) = if write != 0 {
+            let data = data.read_all()?;
+            storage.store_value(&data)
+        } else {
+            let mut writer = data.writer();
+            storage.read_value(&mut writer)
         };
-        storage.store_value(&data)
-    } else {
-        let mut writer = data.writer();
-        storage.read_value(&mut writer)
-    };
-    unsafe { *len = bytes_processed };
-    unsafe { *ppos += *len as bindings::loff_t };
-    match result {
-        Ok(()) => 0,
-        Err(e) => e.to_kernel_errno(),
-    }
+        unsafe { *len = bytes_processed };
+        unsafe { *ppos += *len as bindings::loff_t };
+        result?;
+        Ok(0)
+    })
 }
 
 impl<T: SysctlStorage> Sysctl<T> {
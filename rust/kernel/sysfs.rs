@@ -0,0 +1,452 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Sysfs attributes.
+//!
+//! An [`Attribute`] is a file in sysfs whose contents are produced by a `show` function and that
+//! may be written to with a `store` function. Attributes are registered in groups, either in the
+//! directory of a device or in the one of the module (`/sys/module/<name>`), with
+//! [`Registration`]. All functions of a group receive the same context, shared with the driver.
+//!
+//! C header: [`include/linux/sysfs.h`](../../../../include/linux/sysfs.h)
+
+use crate::{
+    bindings, c_types,
+    device::{self, RawDevice},
+    error::{code::*, Error, Result},
+    str::{CStr, Formatter},
+    sync::Ref,
+    ThisModule, PAGE_SIZE,
+};
+use alloc::vec::Vec;
+use core::{fmt, ptr};
+
+/// The buffer a `show` function writes the contents of an attribute to.
+///
+/// It holds up to a page minus one byte, which is what sysfs accepts from `show` functions; writes
+/// that don't fit fail.
+pub struct Buffer(Formatter);
+
+impl Buffer {
+    /// Returns the number of bytes written to the buffer.
+    pub fn len(&self) -> usize {
+        self.0.bytes_written()
+    }
+
+    /// Returns whether nothing was written to the buffer yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Write for Buffer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write_str(s)
+    }
+}
+
+/// The function that shows the contents of an attribute.
+pub type ShowFn<T> = fn(&T, &mut Buffer) -> Result;
+
+/// The function that stores the value written to an attribute.
+///
+/// It receives all the bytes written at once, including a trailing newline if there is one.
+pub type StoreFn<T> = fn(&T, &[u8]) -> Result;
+
+/// A sysfs attribute whose functions receive a context of type `T`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use core::fmt::Write;
+/// use core::sync::atomic::{AtomicU32, Ordering};
+/// use kernel::{c_str, device::RawDevice, sync::Ref, sysfs};
+///
+/// struct State {
+///     speed: AtomicU32,
+///     errors: AtomicU32,
+/// }
+///
+/// const ATTRS: [sysfs::Attribute<State>; 2] = [
+///     sysfs::Attribute::rw(
+///         c_str!("speed"),
+///         |state, buf| Ok(writeln!(buf, "{}", state.speed.load(Ordering::Relaxed))?),
+///         |state, input| {
+///             let input = core::str::from_utf8(input)?.trim();
+///             state.speed.store(input.parse().map_err(|_| EINVAL)?, Ordering::Relaxed);
+///             Ok(())
+///         },
+///     ),
+///     sysfs::Attribute::ro(c_str!("errors"), |state, buf| {
+///         Ok(writeln!(buf, "{}", state.errors.load(Ordering::Relaxed))?)
+///     }),
+/// ];
+///
+/// fn register(
+///     dev: &dyn RawDevice,
+///     state: Ref<State>,
+/// ) -> Result<sysfs::Registration<State>> {
+///     sysfs::Registration::new_device(dev, Some(c_str!("stats")), &ATTRS, state)
+/// }
+/// ```
+pub struct Attribute<T> {
+    name: &'static CStr,
+    mode: u16,
+    show: Option<ShowFn<T>>,
+    store: Option<StoreFn<T>>,
+}
+
+impl<T> Attribute<T> {
+    /// Creates a read-only attribute, readable by everyone.
+    pub const fn ro(name: &'static CStr, show: ShowFn<T>) -> Self {
+        Self {
+            name,
+            mode: 0o444,
+            show: Some(show),
+            store: None,
+        }
+    }
+
+    /// Creates a write-only attribute, writable by its owner (usually root).
+    pub const fn wo(name: &'static CStr, store: StoreFn<T>) -> Self {
+        Self {
+            name,
+            mode: 0o200,
+            show: None,
+            store: Some(store),
+        }
+    }
+
+    /// Creates an attribute readable by everyone and writable by its owner (usually root).
+    pub const fn rw(name: &'static CStr, show: ShowFn<T>, store: StoreFn<T>) -> Self {
+        Self {
+            name,
+            mode: 0o644,
+            show: Some(show),
+            store: Some(store),
+        }
+    }
+
+    /// Sets the permissions of the attribute, e.g., `0o400` to make it readable only by its
+    /// owner.
+    pub const fn mode(mut self, mode: u16) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Returns the C representation of the attribute.
+    pub(crate) fn raw(&self) -> bindings::attribute {
+        #[cfg_attr(not(CONFIG_DEBUG_LOCK_ALLOC), allow(unused_mut))]
+        let mut attr = bindings::attribute {
+            name: self.name.as_char_ptr(),
+            mode: self.mode,
+            ..Default::default()
+        };
+
+        // Attributes that aren't static need a static lockdep key, which `sysfs_attr_init` sets
+        // in C.
+        #[cfg(CONFIG_DEBUG_LOCK_ALLOC)]
+        {
+            attr.key = crate::static_lock_class!().as_ptr();
+        }
+        attr
+    }
+
+    /// Shows the attribute into the page `buf`.
+    ///
+    /// # Safety
+    ///
+    /// `buf` must be valid for writes of a page.
+    pub(crate) unsafe fn show_raw(
+        &self,
+        context: &T,
+        buf: *mut c_types::c_char,
+    ) -> c_types::c_ssize_t {
+        let show = match self.show {
+            Some(f) => f,
+            None => return EIO.to_kernel_errno() as _,
+        };
+
+        // SAFETY: The safety requirements guarantee that `buf` is valid for writes of a page. Sysfs
+        // truncates anything longer than `PAGE_SIZE - 1`, so writes beyond that fail instead.
+        let mut buffer = Buffer(unsafe { Formatter::from_buffer(buf.cast(), PAGE_SIZE - 1) });
+
+        match show(context, &mut buffer) {
+            Ok(()) => buffer.len() as _,
+            Err(e) => e.to_kernel_errno() as _,
+        }
+    }
+
+    /// Stores the `count` bytes at `buf` to the attribute.
+    ///
+    /// # Safety
+    ///
+    /// `buf` must be valid for reads of `count` bytes.
+    pub(crate) unsafe fn store_raw(
+        &self,
+        context: &T,
+        buf: *const c_types::c_char,
+        count: c_types::c_size_t,
+    ) -> c_types::c_ssize_t {
+        let store = match self.store {
+            Some(f) => f,
+            None => return EIO.to_kernel_errno() as _,
+        };
+
+        // SAFETY: The safety requirements guarantee that `buf` is valid for reads of `count`
+        // bytes.
+        let input = unsafe { core::slice::from_raw_parts(buf.cast(), count) };
+
+        match store(context, input) {
+            Ok(()) => count as _,
+            Err(e) => e.to_kernel_errno() as _,
+        }
+    }
+}
+
+impl<T> Clone for Attribute<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Attribute<T> {}
+
+/// The C attribute types whose `struct attribute` is the first field.
+#[repr(C)]
+union RawKind {
+    dev: bindings::device_attribute,
+    module: bindings::module_attribute,
+}
+
+/// An attribute registered with sysfs.
+///
+/// # Invariants
+///
+/// `context` is valid while the attribute is registered.
+#[repr(C)]
+struct RawAttribute<T> {
+    raw: RawKind,
+    attr: Attribute<T>,
+    context: *const T,
+}
+
+impl<T> RawAttribute<T> {
+    /// Returns the attribute that contains the given `struct attribute`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to the `struct attribute` of a registered [`RawAttribute<T>`].
+    unsafe fn from_ptr<'a, U>(ptr: *const U) -> &'a Self {
+        // SAFETY: The `struct attribute` is the first field of all variants of `RawKind`, which is
+        // the first field of `RawAttribute`, so the cast is valid. The safety requirements
+        // guarantee that the attribute is alive.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Shows the attribute into the page `buf`.
+    ///
+    /// # Safety
+    ///
+    /// `buf` must be valid for writes of a page.
+    unsafe fn show(&self, buf: *mut c_types::c_char) -> c_types::c_ssize_t {
+        // SAFETY: By the type invariants, `context` is valid while the attribute is registered.
+        // The safety requirements are the same as the callee's.
+        unsafe { self.attr.show_raw(&*self.context, buf) }
+    }
+
+    /// Stores the `count` bytes at `buf` to the attribute.
+    ///
+    /// # Safety
+    ///
+    /// `buf` must be valid for reads of `count` bytes.
+    unsafe fn store(
+        &self,
+        buf: *const c_types::c_char,
+        count: c_types::c_size_t,
+    ) -> c_types::c_ssize_t {
+        // SAFETY: By the type invariants, `context` is valid while the attribute is registered.
+        // The safety requirements are the same as the callee's.
+        unsafe { self.attr.store_raw(&*self.context, buf, count) }
+    }
+}
+
+unsafe extern "C" fn device_show<T>(
+    _dev: *mut bindings::device,
+    attr: *mut bindings::device_attribute,
+    buf: *mut c_types::c_char,
+) -> c_types::c_ssize_t {
+    // SAFETY: Sysfs only calls `show` on registered attributes, with a page as the buffer.
+    unsafe { RawAttribute::<T>::from_ptr(attr).show(buf) }
+}
+
+unsafe extern "C" fn device_store<T>(
+    _dev: *mut bindings::device,
+    attr: *mut bindings::device_attribute,
+    buf: *const c_types::c_char,
+    count: c_types::c_size_t,
+) -> c_types::c_ssize_t {
+    // SAFETY: Sysfs only calls `store` on registered attributes, with `count` bytes in `buf`.
+    unsafe { RawAttribute::<T>::from_ptr(attr).store(buf, count) }
+}
+
+unsafe extern "C" fn module_show<T>(
+    attr: *mut bindings::module_attribute,
+    _mk: *mut bindings::module_kobject,
+    buf: *mut c_types::c_char,
+) -> c_types::c_ssize_t {
+    // SAFETY: Sysfs only calls `show` on registered attributes, with a page as the buffer.
+    unsafe { RawAttribute::<T>::from_ptr(attr).show(buf) }
+}
+
+unsafe extern "C" fn module_store<T>(
+    attr: *mut bindings::module_attribute,
+    _mk: *mut bindings::module_kobject,
+    buf: *const c_types::c_char,
+    count: c_types::c_size_t,
+) -> c_types::c_ssize_t {
+    // SAFETY: Sysfs only calls `store` on registered attributes, with `count` bytes in `buf`.
+    unsafe { RawAttribute::<T>::from_ptr(attr).store(buf, count) }
+}
+
+/// A registration of a group of sysfs attributes.
+///
+/// The attributes are removed when the registration is dropped.
+///
+/// # Invariants
+///
+/// `group` is registered in `kobj`, which is kept alive by `_dev` if it belongs to a device, or
+/// is the kobject of a module otherwise. The `context` of all `_attrs` points to `_context`.
+pub struct Registration<T: Send + Sync + 'static> {
+    kobj: *mut bindings::kobject,
+    _dev: Option<device::Device>,
+    group: bindings::attribute_group,
+    _attrs: Vec<RawAttribute<T>>,
+    _ptrs: Vec<*mut bindings::attribute>,
+    _context: Ref<T>,
+}
+
+// SAFETY: The registration only gives access to the context, which is `Send` and `Sync`, and it
+// can be dropped from any thread.
+unsafe impl<T: Send + Sync + 'static> Send for Registration<T> {}
+
+// SAFETY: The registration has no methods that take `&self`.
+unsafe impl<T: Send + Sync + 'static> Sync for Registration<T> {}
+
+impl<T: Send + Sync + 'static> Registration<T> {
+    /// Registers the attributes in the directory of `dev`, or in its subdirectory `name`.
+    pub fn new_device(
+        dev: &dyn RawDevice,
+        name: Option<&'static CStr>,
+        attrs: &[Attribute<T>],
+        context: Ref<T>,
+    ) -> Result<Self> {
+        let ptr = dev.raw_device();
+        // SAFETY: `ptr` is valid by the safety requirements of `RawDevice`.
+        let kobj = unsafe { ptr::addr_of_mut!((*ptr).kobj) };
+        let raw = |attr: &mut RawAttribute<T>| {
+            attr.raw.dev = bindings::device_attribute {
+                attr: attr.attr.raw(),
+                show: attr.attr.show.map(|_| device_show::<T> as _),
+                store: attr.attr.store.map(|_| device_store::<T> as _),
+            };
+        };
+        Self::register(
+            kobj,
+            Some(device::Device::from_dev(dev)),
+            name,
+            attrs,
+            context,
+            raw,
+        )
+    }
+
+    /// Registers the attributes in the directory of `module`, or in its subdirectory `name`.
+    ///
+    /// Returns [`EINVAL`] if the module is built into the kernel.
+    pub fn new_module(
+        module: &'static ThisModule,
+        name: Option<&'static CStr>,
+        attrs: &[Attribute<T>],
+        context: Ref<T>,
+    ) -> Result<Self> {
+        if module.0.is_null() {
+            return Err(EINVAL);
+        }
+
+        // SAFETY: `module.0` is a valid module, which outlives the registration.
+        let kobj = unsafe { ptr::addr_of_mut!((*module.0).mkobj.kobj) };
+        let raw = |attr: &mut RawAttribute<T>| {
+            attr.raw.module = bindings::module_attribute {
+                attr: attr.attr.raw(),
+                show: attr.attr.show.map(|_| module_show::<T> as _),
+                store: attr.attr.store.map(|_| module_store::<T> as _),
+                ..Default::default()
+            };
+        };
+        Self::register(kobj, None, name, attrs, context, raw)
+    }
+
+    fn register(
+        kobj: *mut bindings::kobject,
+        dev: Option<device::Device>,
+        name: Option<&'static CStr>,
+        attrs: &[Attribute<T>],
+        context: Ref<T>,
+        init: impl Fn(&mut RawAttribute<T>),
+    ) -> Result<Self> {
+        let mut raw_attrs = Vec::try_with_capacity(attrs.len())?;
+        for attr in attrs {
+            let mut raw = RawAttribute {
+                // SAFETY: All variants of `RawKind` are C structs, for which zero is a valid
+                // value.
+                raw: unsafe { core::mem::zeroed() },
+                attr: *attr,
+                context: &*context,
+            };
+            init(&mut raw);
+            raw_attrs.try_push(raw)?;
+        }
+
+        // The attributes don't move from now on, since `raw_attrs` isn't modified anymore.
+        let mut ptrs = Vec::try_with_capacity(attrs.len() + 1)?;
+        for raw in raw_attrs.iter_mut() {
+            // SAFETY: The `struct attribute` is at the same place in all variants of `RawKind`.
+            ptrs.try_push(unsafe { ptr::addr_of_mut!(raw.raw.dev.attr) })?;
+        }
+        ptrs.try_push(ptr::null_mut())?;
+
+        let group = bindings::attribute_group {
+            name: name.map_or(ptr::null(), |n| n.as_char_ptr()),
+            attrs: ptrs.as_mut_ptr(),
+            ..Default::default()
+        };
+
+        // SAFETY: `kobj` is valid as guaranteed by the callers. The attributes that `group` points
+        // to are kept alive until they are removed in `drop`; sysfs doesn't keep pointers to the
+        // group itself, so it may move.
+        let ret = unsafe { bindings::sysfs_create_group(kobj, &group) };
+        if ret < 0 {
+            return Err(Error::from_kernel_errno(ret));
+        }
+
+        // INVARIANT: The group was registered above, and the contexts point to `context`, which
+        // is ref-counted so it doesn't move.
+        Ok(Self {
+            kobj,
+            _dev: dev,
+            group,
+            _attrs: raw_attrs,
+            _ptrs: ptrs,
+            _context: context,
+        })
+    }
+}
+
+impl<T: Send + Sync + 'static> Drop for Registration<T> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `group` is registered in `kobj`, which is still alive.
+        // Once it returns, no `show` or `store` functions are running, so `_context` may go away.
+        unsafe { bindings::sysfs_remove_group(self.kobj, &self.group) };
+    }
+}
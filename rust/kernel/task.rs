@@ -2,10 +2,23 @@
 
 //! Tasks (threads and processes).
 //!
-//! C header: [`include/linux/sched.h`](../../../../include/linux/sched.h).
+//! C headers: [`include/linux/sched.h`](../../../../include/linux/sched.h),
+//! [`include/linux/kthread.h`](../../../../include/linux/kthread.h) and
+//! [`include/linux/freezer.h`](../../../../include/linux/freezer.h).
 
-use crate::bindings;
-use core::{marker::PhantomData, mem::ManuallyDrop, ops::Deref};
+use crate::{
+    bindings, c_str, c_types,
+    cpumask::{self, CpuMask},
+    cred::Credential,
+    error::code::*,
+    error::from_kernel_err_ptr,
+    str::CStr,
+    to_result,
+    types::PointerWrapper,
+    ARef, Result,
+};
+use alloc::boxed::Box;
+use core::{fmt, marker::PhantomData, mem::ManuallyDrop, ops::Deref, ptr::NonNull};
 
 /// Wraps the kernel's `struct task_struct`.
 ///
@@ -38,6 +51,18 @@ use core::{marker::PhantomData, mem::ManuallyDrop, ops::Deref};
 /// # }
 /// ```
 ///
+/// Logging the name and process of the current task, e.g., when it mounts a file system:
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::task::Task;
+///
+/// # fn test() {
+/// let task = Task::current();
+/// pr_info!("mounted by {} (pid {}, tgid {})\n", task.comm(), task.pid(), task.tgid());
+/// # }
+/// ```
+///
 /// Getting the current task and storing it in some struct. The reference count is automatically
 /// incremented when creating `State` and decremented when it is dropped:
 ///
@@ -68,12 +93,55 @@ unsafe impl Send for Task {}
 
 // SAFETY: It's OK to access `Task` through references from other threads because we're either
 // accessing properties that don't change (e.g., `pid`, `group_leader`) or that are properly
-// synchronised by C code (e.g., `signal_pending`).
+// synchronised by C code (e.g., `signal_pending`, `comm`, `cred`).
 unsafe impl Sync for Task {}
 
 /// The type of process identifiers (PIDs).
 type Pid = bindings::pid_t;
 
+/// Contains constants for the task flags returned by [`Task::flags`].
+///
+/// It is tagged with `non_exhaustive` to prevent users from instantiating it.
+#[non_exhaustive]
+pub struct Flags;
+
+impl Flags {
+    /// The task is exiting.
+    pub const EXITING: u32 = bindings::PF_EXITING;
+
+    /// The task is a kernel thread.
+    pub const KTHREAD: u32 = bindings::PF_KTHREAD;
+
+    /// The task is a workqueue worker.
+    pub const WQ_WORKER: u32 = bindings::PF_WQ_WORKER;
+
+    /// The task is allocating memory to free memory, so it may use the reserves.
+    pub const MEMALLOC: u32 = bindings::PF_MEMALLOC;
+
+    /// The task is a kswapd thread.
+    pub const KSWAPD: u32 = bindings::PF_KSWAPD;
+}
+
+/// A scheduling policy, as set by [`Task::set_policy`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Policy {
+    /// The default time-sharing policy, where the CPU time of the task depends on its nice value
+    /// (see [`Task::set_priority`]).
+    Normal,
+
+    /// The first-in first-out real-time policy with the given priority, from 1 (lowest) to 99
+    /// (highest).
+    ///
+    /// The task runs until it blocks or a task with higher priority becomes runnable.
+    Fifo(u32),
+
+    /// The round-robin real-time policy with the given priority, from 1 (lowest) to 99
+    /// (highest).
+    ///
+    /// It is like [`Policy::Fifo`], except that tasks with the same priority take turns.
+    RoundRobin(u32),
+}
+
 impl Task {
     /// Returns a task reference for the currently executing task/thread.
     pub fn current<'a>() -> TaskRef<'a> {
@@ -103,11 +171,329 @@ impl Task {
         unsafe { (*self.ptr).pid }
     }
 
+    /// Returns the thread group ID (the PID of the process) of the given task.
+    pub fn tgid(&self) -> Pid {
+        // SAFETY: By the type invariant, we know that `self.ptr` is non-null and valid.
+        unsafe { (*self.ptr).tgid }
+    }
+
+    /// Returns a copy of the name of the executable of the given task.
+    ///
+    /// It is truncated to 15 bytes. It is a copy because the task may change its name
+    /// concurrently.
+    pub fn comm(&self) -> Comm {
+        let mut buf = [0; bindings::TASK_COMM_LEN as usize];
+
+        // SAFETY: By the type invariant, we know that `self.ptr` is non-null and valid. `buf` is
+        // valid for writes of its length. `__get_task_comm` takes the task lock so the copy is
+        // consistent, and always nul-terminates it.
+        unsafe { bindings::__get_task_comm(buf.as_mut_ptr() as _, buf.len() as _, self.ptr) };
+
+        // INVARIANT: `buf` was nul-terminated by `__get_task_comm`.
+        Comm { buf }
+    }
+
+    /// Returns the credentials of the given task.
+    ///
+    /// They are the credentials used when the task acts on other objects (e.g., opening a file).
+    pub fn cred(&self) -> ARef<Credential> {
+        // SAFETY: By the type invariant, we know that `self.ptr` is non-null and valid.
+        // `get_task_cred` returns a pointer with its reference count incremented.
+        let ptr = unsafe { bindings::get_task_cred(self.ptr) };
+
+        // SAFETY: `get_task_cred` never returns null, and it incremented the reference count,
+        // which is now owned by the returned `ARef`.
+        unsafe { ARef::from_raw(NonNull::new_unchecked(ptr as *mut Credential)) }
+    }
+
+    /// Returns the flags of the given task.
+    ///
+    /// Flags are defined in [`Flags`]. They may change concurrently unless the given
+    /// task is the current one.
+    pub fn flags(&self) -> u32 {
+        // SAFETY: By the type invariant, we know that `self.ptr` is non-null and valid. The
+        // volatile read is equivalent to `READ_ONCE`, since other tasks may update it.
+        unsafe { core::ptr::addr_of!((*self.ptr).flags).read_volatile() }
+    }
+
+    /// Determines whether the given task is a kernel thread.
+    pub fn is_kthread(&self) -> bool {
+        self.flags() & Flags::KTHREAD != 0
+    }
+
     /// Determines whether the given task has pending signals.
     pub fn signal_pending(&self) -> bool {
         // SAFETY: By the type invariant, we know that `self.ptr` is non-null and valid.
         unsafe { bindings::signal_pending(self.ptr) != 0 }
     }
+
+    /// Sets the scheduling policy of the task.
+    ///
+    /// Real-time policies should be used sparingly, since the task can starve all the
+    /// [`Policy::Normal`] ones. Returns [`EINVAL`] if the real-time priority is out of range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kernel::prelude::*;
+    /// use kernel::task::{Policy, Task};
+    ///
+    /// fn start_completion_thread(cpu: u32) -> Result {
+    ///     let thread = Task::spawn(fmt!("completion/{}", cpu), || {
+    ///         // Process completions.
+    ///     })?;
+    ///     thread.set_affinity(cpu)?;
+    ///     thread.set_policy(Policy::Fifo(50))?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn set_policy(&self, policy: Policy) -> Result {
+        let (policy, priority) = match policy {
+            Policy::Normal => (bindings::SCHED_NORMAL, 0),
+            Policy::Fifo(prio) => (bindings::SCHED_FIFO, prio),
+            Policy::RoundRobin(prio) => (bindings::SCHED_RR, prio),
+        };
+        let param = bindings::sched_param {
+            sched_priority: priority.try_into().map_err(|_| EINVAL)?,
+        };
+
+        // SAFETY: By the type invariant, we know that `self.ptr` is non-null and valid. `param`
+        // is only used during the call.
+        to_result(|| unsafe { bindings::sched_setscheduler_nocheck(self.ptr, policy as _, &param) })
+    }
+
+    /// Sets the nice value of the task, which is clamped to the range -20 (highest priority) to
+    /// 19 (lowest priority).
+    ///
+    /// It only affects tasks with the [`Policy::Normal`] policy.
+    pub fn set_priority(&self, nice: i32) {
+        let nice = nice.clamp(bindings::MIN_NICE, bindings::MAX_NICE);
+        // SAFETY: By the type invariant, we know that `self.ptr` is non-null and valid.
+        unsafe { bindings::set_user_nice(self.ptr, nice as _) };
+    }
+
+    /// Returns the nice value of the task.
+    pub fn priority(&self) -> i32 {
+        // SAFETY: By the type invariant, we know that `self.ptr` is non-null and valid.
+        unsafe { bindings::task_nice(self.ptr) }
+    }
+
+    /// Restricts the task to run on the given CPU.
+    ///
+    /// The task is migrated if it is running on another CPU. Returns [`EINVAL`] if the CPU
+    /// doesn't exist or is offline.
+    pub fn set_affinity(&self, cpu: u32) -> Result {
+        if cpu >= cpumask::nr_cpu_ids() {
+            return Err(EINVAL);
+        }
+        self.set_affinity_mask(CpuMask::of(cpu))
+    }
+
+    /// Restricts the task to run on the CPUs of the given mask.
+    ///
+    /// The task is migrated if it is running on another CPU. Returns [`EINVAL`] if none of the
+    /// CPUs are online.
+    pub fn set_affinity_mask(&self, mask: &CpuMask) -> Result {
+        // SAFETY: By the type invariant, we know that `self.ptr` is non-null and valid. `mask` is
+        // valid, and it is copied during the call.
+        to_result(|| unsafe { bindings::set_cpus_allowed_ptr(self.ptr, mask.as_ptr()) })
+    }
+
+    /// Starts a new kernel thread that runs `func`.
+    ///
+    /// The thread is named after `name`, truncated to fit in a [`Comm`]. It exits
+    /// when `func` returns; long-running threads should return when [`should_stop`] becomes
+    /// `true`, which happens when [`KThread::stop`] is called.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kernel::prelude::*;
+    /// use kernel::task::{self, KThread, Task};
+    ///
+    /// fn start_flusher(id: u32) -> Result<KThread> {
+    ///     Task::spawn(fmt!("flusher/{}", id), || {
+    ///         task::set_freezable();
+    ///         while !task::should_stop() {
+    ///             task::try_to_freeze();
+    ///             // Flush dirty data, then wait for more.
+    ///         }
+    ///     })
+    /// }
+    /// ```
+    pub fn spawn<T: FnOnce() + Send + 'static>(
+        name: fmt::Arguments<'_>,
+        func: T,
+    ) -> Result<KThread> {
+        unsafe extern "C" fn threadfn<T: FnOnce() + Send + 'static>(
+            arg: *mut c_types::c_void,
+        ) -> c_types::c_int {
+            // SAFETY: `arg` was returned by `Box::into_pointer` in `spawn`, and it is only
+            // converted back here, once.
+            let func = unsafe { Box::<T>::from_pointer(arg) };
+            func();
+            0
+        }
+
+        unsafe fn drop_arg<T: FnOnce() + Send + 'static>(arg: *mut c_types::c_void) {
+            // SAFETY: The caller guarantees that `arg` was returned by `Box::into_pointer` in
+            // `spawn`, and that `threadfn` didn't (and won't) take ownership of it.
+            drop(unsafe { Box::<T>::from_pointer(arg) });
+        }
+
+        let arg = Box::try_new(func)?.into_pointer();
+
+        // SAFETY: `threadfn` takes ownership of `arg` when it runs; if it never does, `arg` is
+        // released by `KThread::stop`. `name` is only used during the call, to name the thread.
+        let ptr = from_kernel_err_ptr(unsafe {
+            bindings::kthread_create_on_node(
+                Some(threadfn::<T>),
+                arg as _,
+                bindings::NUMA_NO_NODE,
+                c_str!("%pA").as_char_ptr(),
+                &name as *const _ as *const c_types::c_void,
+            )
+        })
+        .map_err(|e| {
+            // SAFETY: The thread wasn't created, so `threadfn` won't take ownership of `arg`.
+            drop(unsafe { Box::<T>::from_pointer(arg) });
+            e
+        })?;
+
+        // SAFETY: `ptr` is a newly-created thread, which hasn't been woken up yet, so it is valid.
+        // The reference taken here is owned by the new `Task`.
+        unsafe { bindings::get_task_struct(ptr) };
+
+        // INVARIANT: We took a reference to `ptr` above.
+        let task = Task { ptr };
+
+        // SAFETY: `ptr` is valid, as shown above.
+        unsafe { bindings::wake_up_process(ptr) };
+
+        // INVARIANT: `threadfn::<T>` always returns zero, and `drop_arg::<T>` releases `arg`.
+        Ok(KThread {
+            task,
+            arg,
+            drop_arg: drop_arg::<T>,
+        })
+    }
+}
+
+/// A kernel thread started by [`Task::spawn`].
+///
+/// The thread keeps running when it is dropped; it can be stopped with [`KThread::stop`].
+///
+/// # Invariants
+///
+/// `arg` is the argument of the thread function, which returns zero once it has taken ownership
+/// of it. If the thread function never runs, `drop_arg` can be called to release it.
+pub struct KThread {
+    task: Task,
+    arg: *mut c_types::c_void,
+    drop_arg: unsafe fn(*mut c_types::c_void),
+}
+
+// SAFETY: The thread function is `Send`, and `arg` is only used by `stop`, which takes `self` by
+// value.
+unsafe impl Send for KThread {}
+
+// SAFETY: Shared references only give access to the task, which is `Sync`.
+unsafe impl Sync for KThread {}
+
+impl KThread {
+    /// Asks the thread to stop and waits for it to exit.
+    ///
+    /// [`should_stop`] returns `true` in the thread from now on, and it is woken up (or thawed)
+    /// so that it notices. If the thread has already exited, it returns immediately. If it is
+    /// stopped before it gets to run, the function it was spawned with is dropped without being
+    /// called.
+    pub fn stop(self) {
+        // SAFETY: The thread was created by `kthread_create_on_node`, and we own a reference to
+        // it. `stop` takes `self` by value, so it is called at most once.
+        let ret = unsafe { bindings::kthread_stop(self.task.ptr) };
+
+        // `kthread_stop` returns `-EINTR` if the thread was stopped before its function could
+        // run, in which case it still owns its argument.
+        if ret == EINTR.to_kernel_errno() {
+            // SAFETY: By the type invariants, the thread function returns zero once it has run,
+            // so it never took ownership of `arg`, and the thread has exited.
+            unsafe { (self.drop_arg)(self.arg) };
+        }
+    }
+}
+
+impl Deref for KThread {
+    type Target = Task;
+
+    fn deref(&self) -> &Self::Target {
+        &self.task
+    }
+}
+
+/// Determines whether the current kernel thread should stop, that is, [`KThread::stop`] was
+/// called on it.
+///
+/// It is meant to be called from threads started by [`Task::spawn`]; it returns `false` in tasks
+/// that aren't kernel threads.
+pub fn should_stop() -> bool {
+    // `kthread_should_stop` assumes that the current task is a kernel thread.
+    if Task::current().flags() & Flags::KTHREAD == 0 {
+        return false;
+    }
+
+    // SAFETY: The current task is a kernel thread, as checked above.
+    unsafe { bindings::kthread_should_stop() }
+}
+
+/// Allows the system to freeze the current kernel thread, e.g., during suspend.
+///
+/// Kernel threads are not freezable by default, so the system can suspend while they run. Threads
+/// that may access devices or file systems should call it when they start and then call
+/// [`try_to_freeze`] regularly, otherwise suspend fails because they don't freeze.
+pub fn set_freezable() {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::set_freezable() };
+}
+
+/// Determines whether the system is trying to freeze the current task.
+pub fn freezing() -> bool {
+    // SAFETY: `get_current` returns the current task, which is valid.
+    unsafe { bindings::freezing(bindings::get_current()) }
+}
+
+/// Freezes the current task if the system is trying to freeze it, until it is thawed.
+///
+/// Returns whether the task was frozen. It may sleep, so no locks may be held while calling it.
+pub fn try_to_freeze() -> bool {
+    crate::might_sleep!();
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::try_to_freeze() }
+}
+
+/// A copy of the name of the executable of a task, as returned by [`Task::comm`].
+///
+/// # Invariants
+///
+/// `buf` is nul-terminated.
+pub struct Comm {
+    buf: [u8; bindings::TASK_COMM_LEN as usize],
+}
+
+impl Comm {
+    /// Returns the name as a C string.
+    pub fn as_cstr(&self) -> &CStr {
+        let len = self.buf.iter().position(|&c| c == 0).unwrap_or(0);
+
+        // SAFETY: By the type invariant, `buf` is nul-terminated, so `buf[..=len]` contains a
+        // single nul byte, at the end.
+        unsafe { CStr::from_bytes_with_nul_unchecked(&self.buf[..=len]) }
+    }
+}
+
+impl fmt::Display for Comm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_cstr(), f)
+    }
 }
 
 impl PartialEq for Task {
@@ -0,0 +1,142 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Tasklets.
+//!
+//! Tasklets defer work to softirq context, typically from an interrupt handler that must return
+//! quickly. They run soon after the interrupt, on the CPU that scheduled them, and never run
+//! concurrently with themselves. They cannot sleep; work that needs to sleep should use
+//! [`crate::workqueue`] instead.
+//!
+//! C header: [`include/linux/interrupt.h`](../../../../include/linux/interrupt.h)
+
+use crate::{bindings, Opaque};
+use core::{marker::PhantomPinned, pin::Pin};
+
+/// A tasklet that calls a closure in softirq context.
+///
+/// Wraps the kernel's `struct tasklet_struct`. Scheduling a tasklet that is already scheduled
+/// (but hasn't run yet) has no effect, so the closure runs once for any number of calls to
+/// [`Tasklet::schedule`] before it runs.
+///
+/// The tasklet must first be initialised with a call to [`Tasklet::init_tasklet`] before it can
+/// be used. It is killed when it is dropped, so the closure never runs after that. The closure must
+/// be `'static` because a tasklet that is leaked is never killed.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// # use alloc::boxed::Box;
+/// # use core::pin::Pin;
+/// use core::sync::atomic::{AtomicU32, Ordering};
+/// use kernel::tasklet::Tasklet;
+///
+/// struct Device {
+///     events: AtomicU32,
+/// }
+///
+/// fn example(dev: &'static Device) -> Result<Pin<Box<Tasklet<impl Fn() + Sync>>>> {
+///     // SAFETY: `init_tasklet` is called below.
+///     let mut tasklet = Pin::from(Box::try_new(unsafe {
+///         Tasklet::new(move || {
+///             let events = dev.events.swap(0, Ordering::Relaxed);
+///             pr_info!("processing {} events\n", events);
+///         })
+///     })?);
+///     // SAFETY: The tasklet was just created, so it isn't scheduled.
+///     unsafe { tasklet.as_mut().init_tasklet() };
+///
+///     // From the interrupt handler.
+///     dev.events.fetch_add(1, Ordering::Relaxed);
+///     tasklet.schedule();
+///     Ok(tasklet)
+/// }
+/// ```
+pub struct Tasklet<F> {
+    /// The kernel `struct tasklet_struct` object.
+    tasklet: Opaque<bindings::tasklet_struct>,
+
+    func: F,
+
+    /// A tasklet needs to be pinned because the kernel keeps pointers to it while it is scheduled.
+    _pin: PhantomPinned,
+}
+
+// SAFETY: The tasklet can be used from any thread, and the closure is moved with it.
+unsafe impl<F: Send> Send for Tasklet<F> {}
+
+// SAFETY: The closure is called from softirq context while other threads may hold shared
+// references to the tasklet, so it must be `Sync`. The tasklet functions are safe to call
+// concurrently.
+unsafe impl<F: Sync> Sync for Tasklet<F> {}
+
+impl<F: Fn() + Sync + 'static> Tasklet<F> {
+    /// Constructs a new tasklet that calls `func` when it runs.
+    ///
+    /// # Safety
+    ///
+    /// The caller must call [`Tasklet::init_tasklet`] before using or dropping the tasklet.
+    pub unsafe fn new(func: F) -> Self {
+        Self {
+            tasklet: Opaque::uninit(),
+            func,
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Initialises the tasklet.
+    ///
+    /// # Safety
+    ///
+    /// The tasklet must not be scheduled or running, since `tasklet_setup` would reset the state
+    /// and the link that the softirq code relies on.
+    pub unsafe fn init_tasklet(self: Pin<&mut Self>) {
+        // SAFETY: `tasklet` is pinned and is being initialised here, and the caller guarantees
+        // that the softirq code doesn't use it.
+        unsafe { bindings::tasklet_setup(self.tasklet.get(), Some(Self::tasklet_func)) };
+    }
+
+    /// Schedules the tasklet to run in softirq context, unless it is already scheduled.
+    ///
+    /// It can be called from any context, including interrupt handlers.
+    pub fn schedule(&self) {
+        // SAFETY: The tasklet was initialised, as required by `new`.
+        unsafe { bindings::tasklet_schedule(self.tasklet.get()) };
+    }
+
+    /// Schedules the tasklet to run with high priority, before other tasklets and softirqs.
+    pub fn hi_schedule(&self) {
+        // SAFETY: The tasklet was initialised, as required by `new`.
+        unsafe { bindings::tasklet_hi_schedule(self.tasklet.get()) };
+    }
+
+    /// Disables the tasklet, and waits for the closure to complete if it is running.
+    ///
+    /// The tasklet may still be scheduled while it is disabled, but it only runs once it is
+    /// enabled again. Calls nest, so each call must be balanced by a call to [`Tasklet::enable`].
+    pub fn disable(&self) {
+        // SAFETY: The tasklet was initialised, as required by `new`.
+        unsafe { bindings::tasklet_disable(self.tasklet.get()) };
+    }
+
+    /// Enables the tasklet after a call to [`Tasklet::disable`].
+    pub fn enable(&self) {
+        // SAFETY: The tasklet was initialised, as required by `new`.
+        unsafe { bindings::tasklet_enable(self.tasklet.get()) };
+    }
+
+    unsafe extern "C" fn tasklet_func(t: *mut bindings::tasklet_struct) {
+        // SAFETY: `t` is the `tasklet` field of a `Tasklet<F>`, which is pinned and remains valid
+        // until it is killed when dropped.
+        let tasklet = unsafe { &*crate::container_of!(t, Self, tasklet) };
+        (tasklet.func)();
+    }
+}
+
+impl<F> Drop for Tasklet<F> {
+    fn drop(&mut self) {
+        // SAFETY: The tasklet was initialised, as required by `new`. `tasklet_kill` waits for it
+        // to run if it is scheduled, so the closure isn't used afterwards.
+        unsafe { bindings::tasklet_kill(self.tasklet.get()) };
+    }
+}
@@ -0,0 +1,269 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Time keeping and timers.
+//!
+//! Time is measured either in jiffies, the kernel's tick counter, or in nanoseconds with
+//! [`Ktime`]. Intervals given to the timer and timeout APIs are [`Ktime`] values, which are
+//! converted to jiffies where needed.
+//!
+//! C headers: [`include/linux/jiffies.h`](../../../../include/linux/jiffies.h) and
+//! [`include/linux/ktime.h`](../../../../include/linux/ktime.h)
+
+use crate::{bindings, c_types};
+use core::ops::{Add, Neg, Sub};
+
+mod hrtimer;
+mod timer;
+
+pub use hrtimer::{HrTimer, HrTimerMode, HrTimerRestart};
+pub use timer::{Timer, TimerRestart};
+
+/// The type of the kernel's tick counter, [`jiffies`].
+///
+/// It wraps around, so instants must be compared with [`time_after`] and [`time_before`] rather
+/// than with the comparison operators.
+pub type Jiffies = c_types::c_ulong;
+
+/// Returns the current value of the kernel's tick counter.
+///
+/// It is incremented `HZ` times per second.
+pub fn jiffies() -> Jiffies {
+    // SAFETY: `jiffies` is always valid, and the volatile read is equivalent to `READ_ONCE`,
+    // since it is updated concurrently by the timer interrupt.
+    unsafe { core::ptr::addr_of!(bindings::jiffies).read_volatile() }
+}
+
+/// Returns whether the instant `a` is after `b`, taking wrap-around into account.
+///
+/// Equivalent to the kernel's `time_after`.
+pub fn time_after(a: Jiffies, b: Jiffies) -> bool {
+    (b.wrapping_sub(a) as c_types::c_long) < 0
+}
+
+/// Returns whether the instant `a` is before `b`, taking wrap-around into account.
+///
+/// Equivalent to the kernel's `time_before`.
+pub fn time_before(a: Jiffies, b: Jiffies) -> bool {
+    time_after(b, a)
+}
+
+/// Converts milliseconds to jiffies, rounding up.
+///
+/// Equivalent to the kernel's `msecs_to_jiffies`.
+pub fn msecs_to_jiffies(ms: u32) -> Jiffies {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::msecs_to_jiffies(ms) }
+}
+
+/// Converts microseconds to jiffies, rounding up.
+///
+/// Equivalent to the kernel's `usecs_to_jiffies`.
+pub fn usecs_to_jiffies(us: u32) -> Jiffies {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::usecs_to_jiffies(us) }
+}
+
+/// Converts jiffies to milliseconds.
+///
+/// Equivalent to the kernel's `jiffies_to_msecs`.
+pub fn jiffies_to_msecs(j: Jiffies) -> u32 {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::jiffies_to_msecs(j) }
+}
+
+/// Converts jiffies to microseconds.
+///
+/// Equivalent to the kernel's `jiffies_to_usecs`.
+pub fn jiffies_to_usecs(j: Jiffies) -> u32 {
+    // SAFETY: There are no safety requirements for this FFI call.
+    unsafe { bindings::jiffies_to_usecs(j) }
+}
+
+/// Returns the current time of `CLOCK_MONOTONIC`, which doesn't count time while suspended.
+///
+/// Equivalent to the kernel's `ktime_get`.
+pub fn ktime_get() -> Ktime {
+    // SAFETY: There are no safety requirements for this FFI call.
+    Ktime(unsafe { bindings::ktime_get() })
+}
+
+/// Returns the current time of `CLOCK_BOOTTIME`, which also counts time while suspended.
+///
+/// Equivalent to the kernel's `ktime_get_boottime`.
+pub fn ktime_get_boottime() -> Ktime {
+    // SAFETY: There are no safety requirements for this FFI call.
+    Ktime(unsafe { bindings::ktime_get_boottime() })
+}
+
+/// Returns the current wall-clock time (`CLOCK_REALTIME`), since the Unix epoch.
+///
+/// It may jump backwards or forwards when the system time is set, so it should not be used to
+/// measure intervals.
+///
+/// Equivalent to the kernel's `ktime_get_real`.
+pub fn ktime_get_real() -> Ktime {
+    // SAFETY: There are no safety requirements for this FFI call.
+    Ktime(unsafe { bindings::ktime_get_real() })
+}
+
+/// A time value in nanoseconds, either an instant or an interval.
+///
+/// Wraps the kernel's `ktime_t`. Arithmetic saturates instead of overflowing.
+///
+/// # Examples
+///
+/// ```
+/// use kernel::time::{self, Ktime};
+///
+/// fn elapsed_ms(f: impl FnOnce()) -> i64 {
+///     let start = time::ktime_get();
+///     f();
+///     (time::ktime_get() - start).to_ms()
+/// }
+///
+/// assert_eq!(Ktime::from_ms(1500), Ktime::from_secs(1) + Ktime::from_ms(500));
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Ktime(bindings::ktime_t);
+
+impl Ktime {
+    /// Creates a time value from the given number of nanoseconds.
+    pub const fn from_ns(ns: i64) -> Self {
+        Self(ns)
+    }
+
+    /// Creates a time value from the given number of microseconds.
+    pub const fn from_us(us: i64) -> Self {
+        Self(us.saturating_mul(NSEC_PER_USEC))
+    }
+
+    /// Creates a time value from the given number of milliseconds.
+    pub const fn from_ms(ms: i64) -> Self {
+        Self(ms.saturating_mul(NSEC_PER_MSEC))
+    }
+
+    /// Creates a time value from the given number of seconds.
+    pub const fn from_secs(secs: i64) -> Self {
+        Self(secs.saturating_mul(NSEC_PER_SEC))
+    }
+
+    /// Creates an interval from the given number of jiffies.
+    pub fn from_jiffies(j: Jiffies) -> Self {
+        // SAFETY: There are no safety requirements for this FFI call.
+        Self::from_ns(unsafe { bindings::jiffies64_to_nsecs(j as _) } as _)
+    }
+
+    /// Returns the time value in nanoseconds.
+    pub const fn to_ns(self) -> i64 {
+        self.0
+    }
+
+    /// Returns the time value in microseconds, rounded down.
+    pub const fn to_us(self) -> i64 {
+        self.0 / NSEC_PER_USEC
+    }
+
+    /// Returns the time value in milliseconds, rounded down.
+    pub const fn to_ms(self) -> i64 {
+        self.0 / NSEC_PER_MSEC
+    }
+
+    /// Returns the time value in seconds, rounded down.
+    pub const fn to_secs(self) -> i64 {
+        self.0 / NSEC_PER_SEC
+    }
+
+    /// Returns the interval in jiffies, rounded up. Negative intervals are zero jiffies.
+    pub fn to_jiffies(self) -> Jiffies {
+        if self.0 <= 0 {
+            return 0;
+        }
+        // Round up so that timeouts never expire early.
+        let tick = NSEC_PER_SEC / bindings::HZ as i64;
+        let ns = self.0.saturating_add(tick - 1);
+        // SAFETY: There are no safety requirements for this FFI call.
+        unsafe { bindings::nsecs_to_jiffies(ns as _) }
+    }
+
+    /// Returns the interval as a timeout in jiffies, as taken by `schedule_timeout` and the
+    /// functions based on it.
+    pub(crate) fn to_timeout(self) -> c_types::c_long {
+        self.to_jiffies().min(bindings::MAX_SCHEDULE_TIMEOUT as _) as _
+    }
+}
+
+/// The outcome of a wait with a timeout.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::sync::Completion;
+/// use kernel::time::{Ktime, TimeoutResult};
+///
+/// fn wait_for_reset(done: &Completion) -> Result {
+///     match done.wait_timeout(Ktime::from_ms(100)) {
+///         TimeoutResult::Completed(left) => {
+///             pr_debug!("reset with {}ms to spare\n", left.to_ms());
+///             Ok(())
+///         }
+///         TimeoutResult::TimedOut => Err(ETIMEDOUT),
+///     }
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeoutResult {
+    /// The wait completed before the timeout expired, with the given time left.
+    ///
+    /// The time left has the resolution of a jiffy and is never zero.
+    Completed(Ktime),
+
+    /// The timeout expired first.
+    TimedOut,
+}
+
+impl TimeoutResult {
+    /// Creates a result from the jiffies left, as returned by `schedule_timeout` and the
+    /// functions based on it.
+    pub(crate) fn from_timeout(left: c_types::c_long) -> Self {
+        if left <= 0 {
+            Self::TimedOut
+        } else {
+            Self::Completed(Ktime::from_jiffies(left as _))
+        }
+    }
+
+    /// Returns whether the timeout expired.
+    pub fn timed_out(&self) -> bool {
+        *self == Self::TimedOut
+    }
+}
+
+const NSEC_PER_USEC: i64 = 1_000;
+const NSEC_PER_MSEC: i64 = 1_000_000;
+const NSEC_PER_SEC: i64 = 1_000_000_000;
+
+impl Add for Ktime {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+}
+
+impl Sub for Ktime {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+}
+
+impl Neg for Ktime {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self(self.0.saturating_neg())
+    }
+}
@@ -0,0 +1,193 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! High resolution timers.
+//!
+//! This module allows Rust code to use the kernel's `struct hrtimer`, whose expiry is measured in
+//! nanoseconds of `CLOCK_MONOTONIC`. They are more precise than [`super::Timer`], but also more
+//! expensive, so they suit polling and watchdogs rather than timeouts.
+//!
+//! C header: [`include/linux/hrtimer.h`](../../../../include/linux/hrtimer.h)
+
+use super::Ktime;
+use crate::{bindings, Opaque};
+use core::{marker::PhantomPinned, pin::Pin};
+
+/// How the expiry time of an [`HrTimer`] is interpreted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u32)]
+pub enum HrTimerMode {
+    /// The expiry time is an instant of `CLOCK_MONOTONIC`.
+    Absolute = bindings::hrtimer_mode_HRTIMER_MODE_ABS,
+
+    /// The expiry time is an interval relative to the current time.
+    Relative = bindings::hrtimer_mode_HRTIMER_MODE_REL,
+}
+
+/// What an [`HrTimer`] does after its callback returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HrTimerRestart {
+    /// The timer is not restarted.
+    NoRestart,
+
+    /// The timer is restarted to expire after the given interval, counted from its previous
+    /// expiry time so that periodic timers don't drift.
+    Restart(Ktime),
+}
+
+/// A high resolution timer that calls a closure when it expires.
+///
+/// Wraps the kernel's `struct hrtimer`. The closure is called in hard interrupt context, so it
+/// must not sleep; its return value determines whether the timer is restarted. The closure must be
+/// `'static` because a timer that is leaked is never cancelled.
+///
+/// The timer must first be initialised with a call to [`HrTimer::init_hrtimer`] before it can be
+/// used. It is cancelled when it is dropped, so the closure never runs after that.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// # use alloc::boxed::Box;
+/// # use core::pin::Pin;
+/// use core::sync::atomic::{AtomicBool, Ordering};
+/// use kernel::time::{HrTimer, HrTimerMode, HrTimerRestart, Ktime};
+///
+/// // A watchdog that checks every 500us that the device is still alive. It is cancelled when it's
+/// // dropped, so the caller must keep the returned timer, e.g., in its device state.
+/// fn start_watchdog(
+///     alive: &'static AtomicBool,
+/// ) -> Result<Pin<Box<HrTimer<impl Fn() -> HrTimerRestart + Sync>>>> {
+///     // SAFETY: `init_hrtimer` is called below.
+///     let mut watchdog = Pin::from(Box::try_new(unsafe {
+///         HrTimer::new(HrTimerMode::Relative, move || {
+///             if alive.swap(false, Ordering::Relaxed) {
+///                 HrTimerRestart::Restart(Ktime::from_ns(500_000))
+///             } else {
+///                 pr_warn!("device is not responding\n");
+///                 HrTimerRestart::NoRestart
+///             }
+///         })
+///     })?);
+///     // SAFETY: The timer was just created, so it isn't started.
+///     unsafe { watchdog.as_mut().init_hrtimer() };
+///
+///     watchdog.start(Ktime::from_ns(500_000));
+///     Ok(watchdog)
+/// }
+/// ```
+pub struct HrTimer<F> {
+    /// The kernel `struct hrtimer` object.
+    timer: Opaque<bindings::hrtimer>,
+
+    mode: HrTimerMode,
+    func: F,
+
+    /// A timer needs to be pinned because the kernel keeps pointers to it while it is started.
+    _pin: PhantomPinned,
+}
+
+// SAFETY: The timer can be used from any thread, and the closure is moved with it.
+unsafe impl<F: Send> Send for HrTimer<F> {}
+
+// SAFETY: The closure is called from hard interrupt context while other threads may hold shared
+// references to the timer, so it must be `Sync`. The `hrtimer` functions are safe to call
+// concurrently.
+unsafe impl<F: Sync> Sync for HrTimer<F> {}
+
+impl<F: Fn() -> HrTimerRestart + Sync + 'static> HrTimer<F> {
+    /// Constructs a new timer that calls `func` when it expires.
+    ///
+    /// `mode` determines how the expiry times given to [`HrTimer::start`] are interpreted.
+    ///
+    /// # Safety
+    ///
+    /// The caller must call [`HrTimer::init_hrtimer`] before using or dropping the timer.
+    pub unsafe fn new(mode: HrTimerMode, func: F) -> Self {
+        Self {
+            timer: Opaque::uninit(),
+            mode,
+            func,
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Initialises the timer.
+    ///
+    /// # Safety
+    ///
+    /// The timer must not be started, since `hrtimer_init` would overwrite the node that links it
+    /// into the per-CPU timer queue.
+    pub unsafe fn init_hrtimer(self: Pin<&mut Self>) {
+        let timer = self.timer.get();
+
+        // SAFETY: `timer` is pinned, so the address the timer queue links to doesn't change, and
+        // the caller guarantees that it isn't queued. `timer_func` is only set here, before the
+        // timer can be started.
+        unsafe {
+            bindings::hrtimer_init(timer, bindings::CLOCK_MONOTONIC as _, self.mode as _);
+            (*timer).function = Some(Self::timer_func);
+        }
+    }
+
+    /// Starts the timer to expire at the given time, which is interpreted according to the mode
+    /// the timer was created with.
+    ///
+    /// If the timer is already started, its expiry time is modified.
+    pub fn start(&self, expires: Ktime) {
+        // SAFETY: The timer was initialised with `hrtimer_init`, as required by `new`. It is
+        // pinned and cancelled before being dropped, so it remains valid while queued.
+        unsafe { bindings::hrtimer_start_range_ns(self.timer.get(), expires.0, 0, self.mode as _) };
+    }
+
+    /// Returns whether the timer is started, or its closure is running.
+    ///
+    /// The result may be stale by the time the caller acts on it.
+    pub fn is_active(&self) -> bool {
+        // SAFETY: The timer was initialised with `hrtimer_init`, as required by `new`, and
+        // `hrtimer_active` only reads its state.
+        unsafe { bindings::hrtimer_active(self.timer.get()) }
+    }
+
+    /// Returns the time left until the timer expires.
+    ///
+    /// It is negative if the timer has already expired.
+    pub fn remaining(&self) -> Ktime {
+        // SAFETY: The timer was initialised with `hrtimer_init`, as required by `new`, and
+        // `hrtimer_get_remaining` takes the clock base lock to read its expiry time.
+        Ktime(unsafe { bindings::hrtimer_get_remaining(self.timer.get()) })
+    }
+
+    /// Cancels the timer, and waits for the closure to complete if it is running.
+    ///
+    /// Returns `true` if the timer was started. It also cancels the timer if the closure restarts
+    /// it. It must not be called from the closure.
+    pub fn cancel(&self) -> bool {
+        // SAFETY: The timer was initialised with `hrtimer_init`, as required by `new`. It isn't
+        // called from the closure, so `hrtimer_cancel` cannot wait for itself.
+        unsafe { bindings::hrtimer_cancel(self.timer.get()) != 0 }
+    }
+
+    unsafe extern "C" fn timer_func(t: *mut bindings::hrtimer) -> bindings::hrtimer_restart {
+        // SAFETY: `timer_func` is only installed by `init_hrtimer`, so `t` is the `timer` field of
+        // an `HrTimer<F>`. The timer is pinned, and dropping it waits for this callback in
+        // `hrtimer_cancel`, so it remains valid while the callback runs.
+        let timer = unsafe { &*crate::container_of!(t, Self, timer) };
+        match (timer.func)() {
+            HrTimerRestart::NoRestart => bindings::hrtimer_restart_HRTIMER_NORESTART,
+            HrTimerRestart::Restart(interval) => {
+                // SAFETY: `t` is valid, and the timer cannot be enqueued while its callback runs.
+                unsafe { bindings::hrtimer_forward_now(t, interval.0) };
+                bindings::hrtimer_restart_HRTIMER_RESTART
+            }
+        }
+    }
+}
+
+impl<F> Drop for HrTimer<F> {
+    fn drop(&mut self) {
+        // SAFETY: The timer was initialised with `hrtimer_init`, as required by `new`.
+        // `hrtimer_cancel` waits for the closure and cancels the timer again if the closure
+        // restarted it, so the kernel holds no pointers to it afterwards.
+        unsafe { bindings::hrtimer_cancel(self.timer.get()) };
+    }
+}
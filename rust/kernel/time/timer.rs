@@ -0,0 +1,227 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Low resolution timers.
+//!
+//! This module allows Rust code to use the kernel's `struct timer_list`, whose expiry is measured
+//! in jiffies. They are cheaper than high resolution timers, which makes them the usual choice
+//! for timeouts that are unlikely to expire.
+//!
+//! C header: [`include/linux/timer.h`](../../../../include/linux/timer.h)
+
+use super::{jiffies, Jiffies, Ktime};
+use crate::{bindings, str::CStr, sync::NeedsLockClass, Opaque};
+use core::{
+    marker::PhantomPinned,
+    pin::Pin,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Safely initialises a [`Timer`] with the given name, generating a new lock class.
+#[macro_export]
+macro_rules! timer_init {
+    ($timer:expr, $name:literal) => {
+        $crate::init_with_lockdep!($timer, $name)
+    };
+}
+
+/// What a [`Timer`] does after its callback returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerRestart {
+    /// The timer is not re-armed, so it was a one-shot timer.
+    NoRestart,
+
+    /// The timer is re-armed to expire again after the given interval, which is how periodic
+    /// timers are implemented.
+    RestartAfter(Ktime),
+}
+
+/// A low resolution timer that calls a closure when it expires.
+///
+/// Wraps the kernel's `struct timer_list`. The closure is called in softirq context, so it must
+/// not sleep; its return value determines whether the timer is re-armed. The closure must be
+/// `'static` because a timer that is leaked is never shut down.
+///
+/// The timer must first be initialised with a call to [`Timer::init_timer`] (or the
+/// [`timer_init`] macro) before it can be used. It is shut down when it is dropped, so the closure
+/// never runs after that.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// # use kernel::timer_init;
+/// # use alloc::boxed::Box;
+/// # use core::pin::Pin;
+/// use core::sync::atomic::{AtomicU32, Ordering};
+/// use kernel::time::{Ktime, Timer, TimerRestart};
+///
+/// // The timer is shut down when it's dropped, so the caller must keep the returned timer for as
+/// // long as it needs to tick, e.g., in its module state.
+/// fn start_ticking(
+///     ticks: &'static AtomicU32,
+/// ) -> Result<Pin<Box<Timer<impl Fn() -> TimerRestart + Sync>>>> {
+///     // SAFETY: `init` is called below.
+///     let mut timer = Pin::from(Box::try_new(unsafe {
+///         Timer::new(move || {
+///             // Run ten times, every 100ms.
+///             if ticks.fetch_add(1, Ordering::Relaxed) < 9 {
+///                 TimerRestart::RestartAfter(Ktime::from_ms(100))
+///             } else {
+///                 TimerRestart::NoRestart
+///             }
+///         })
+///     })?);
+///     timer_init!(timer.as_mut(), "example::timer");
+///
+///     timer.schedule_after(Ktime::from_ms(100));
+///     Ok(timer)
+/// }
+/// ```
+pub struct Timer<F> {
+    /// The kernel `struct timer_list` object.
+    timer: Opaque<bindings::timer_list>,
+
+    /// Whether the timer was shut down, in which case it is never re-armed.
+    shutdown: AtomicBool,
+
+    func: F,
+
+    /// A timer needs to be pinned because it contains a `struct timer_list` that is
+    /// self-referential, so it cannot be safely moved once it is initialised.
+    _pin: PhantomPinned,
+}
+
+// SAFETY: The timer can be used from any thread, and the closure is moved with it.
+unsafe impl<F: Send> Send for Timer<F> {}
+
+// SAFETY: The closure is called from softirq context while other threads may hold shared
+// references to the timer, so it must be `Sync`. The `timer_list` functions are safe to call
+// concurrently.
+unsafe impl<F: Sync> Sync for Timer<F> {}
+
+impl<F: Fn() -> TimerRestart + Sync + 'static> Timer<F> {
+    /// Constructs a new timer that calls `func` when it expires.
+    ///
+    /// # Safety
+    ///
+    /// The caller must call [`Timer::init_timer`] before using or dropping the timer.
+    pub unsafe fn new(func: F) -> Self {
+        Self {
+            timer: Opaque::uninit(),
+            shutdown: AtomicBool::new(false),
+            func,
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Initialises the timer.
+    ///
+    /// Callers are encouraged to use the [`timer_init`] macro instead.
+    ///
+    /// # Safety
+    ///
+    /// `key` must point to a valid memory location and remain valid until `self` is dropped.
+    pub unsafe fn init_timer(
+        self: Pin<&mut Self>,
+        name: &'static CStr,
+        key: *mut bindings::lock_class_key,
+    ) {
+        // SAFETY: `timer` is pinned and is being initialised here. The caller guarantees that
+        // `key` remains valid.
+        unsafe {
+            bindings::init_timer_key(
+                self.timer.get(),
+                Some(Self::timer_func),
+                0,
+                name.as_char_ptr(),
+                key,
+            )
+        };
+    }
+
+    /// Arms the timer to expire at the given time, or modifies its expiry if it is already armed.
+    ///
+    /// Returns `true` if the timer was armed. It does nothing once the timer has been shut down.
+    ///
+    /// Equivalent to the kernel's `mod_timer`.
+    pub fn schedule_at(&self, expires: Jiffies) -> bool {
+        if self.shutdown.load(Ordering::Acquire) {
+            return false;
+        }
+
+        // SAFETY: The timer was initialised, as required by `new`.
+        unsafe { bindings::mod_timer(self.timer.get(), expires) != 0 }
+    }
+
+    /// Arms the timer to expire after the given interval, or modifies its expiry if it is already
+    /// armed.
+    ///
+    /// The interval is rounded up to a whole number of jiffies.
+    ///
+    /// Returns `true` if the timer was armed. It does nothing once the timer has been shut down.
+    pub fn schedule_after(&self, delta: Ktime) -> bool {
+        self.schedule_at(jiffies().wrapping_add(delta.to_jiffies()))
+    }
+
+    /// Returns whether the timer is armed.
+    ///
+    /// The result may be stale by the time the caller acts on it.
+    pub fn is_pending(&self) -> bool {
+        // SAFETY: The timer was initialised, as required by `new`.
+        unsafe { bindings::timer_pending(self.timer.get()) }
+    }
+
+    /// Disarms the timer, and waits for the closure to complete if it is running.
+    ///
+    /// Returns `true` if the timer was armed. The timer may be armed again afterwards, including
+    /// by the closure if it was running. It may sleep unless the closure runs with interrupts
+    /// disabled, and it must not be called from the closure.
+    ///
+    /// Equivalent to the kernel's `del_timer_sync`.
+    pub fn cancel(&self) -> bool {
+        // SAFETY: The timer was initialised, as required by `new`.
+        unsafe { bindings::del_timer_sync(self.timer.get()) != 0 }
+    }
+
+    /// Disarms the timer for good, and waits for the closure to complete if it is running.
+    ///
+    /// Unlike [`Timer::cancel`], the timer cannot be armed again afterwards, not even by the
+    /// closure, which is useful for periodic timers. The callers of [`Timer::schedule_at`] must
+    /// still not race with this function.
+    pub fn shutdown(&self) {
+        self.shutdown.store(true, Ordering::Release);
+        // `del_timer_sync` retries until the timer is neither armed nor running, so it also
+        // disarms the timer if the closure re-armed it before seeing `shutdown`.
+        self.cancel();
+    }
+
+    unsafe extern "C" fn timer_func(t: *mut bindings::timer_list) {
+        // SAFETY: `t` is the `timer` field of a `Timer<F>`, which is pinned and remains valid
+        // until it is shut down when dropped.
+        let timer = unsafe { &*crate::container_of!(t, Self, timer) };
+        if let TimerRestart::RestartAfter(delta) = (timer.func)() {
+            timer.schedule_after(delta);
+        }
+    }
+}
+
+impl<F: Fn() -> TimerRestart + Sync + 'static> NeedsLockClass for Timer<F> {
+    unsafe fn init(
+        self: Pin<&mut Self>,
+        name: &'static CStr,
+        key: *mut bindings::lock_class_key,
+        _: *mut bindings::lock_class_key,
+    ) {
+        // SAFETY: The safety requirements of this function satisfy the ones of `init_timer`.
+        unsafe { self.init_timer(name, key) };
+    }
+}
+
+impl<F> Drop for Timer<F> {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Release);
+        // SAFETY: The timer was initialised, as required by `new`. As in `shutdown`, the timer
+        // cannot be armed again afterwards.
+        unsafe { bindings::del_timer_sync(self.timer.get()) };
+    }
+}
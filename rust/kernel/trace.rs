@@ -0,0 +1,544 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Trace events.
+//!
+//! Allows Rust code to define trace events that show up in `/sys/kernel/tracing/events` alongside
+//! the ones defined in C with `TRACE_EVENT`, and to emit them into the ftrace ring buffer.
+//!
+//! C header: [`include/linux/trace_events.h`](../../../../include/linux/trace_events.h)
+//!
+//! Reference: <https://www.kernel.org/doc/html/latest/trace/events.html>
+//!
+//! # Examples
+//!
+//! ```
+//! # use kernel::prelude::*;
+//! # use kernel::{declare_trace_event, trace, trace_event};
+//! declare_trace_event! {
+//!     /// Emitted when a request is completed.
+//!     pub struct RequestDone("rust_sample", "request_done") {
+//!         id: u32,
+//!         len: u64,
+//!         status: i32,
+//!     }
+//!     print("id={} len={} status={}", id, len, status);
+//! }
+//!
+//! fn example() -> Result {
+//!     let reg = trace::Registration::<RequestDone>::new_pinned()?;
+//!     trace_event!(reg, RequestDone { id: 1, len: 4096, status: 0 });
+//!     Ok(())
+//! }
+//! ```
+
+use crate::{
+    bindings, c_str, c_types,
+    error::{code::*, from_kernel_err_ptr, from_kernel_result, Error},
+    str::{CStr, CString},
+    to_result, Result,
+};
+use alloc::boxed::Box;
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    marker::{PhantomData, PhantomPinned},
+    mem::{self, MaybeUninit},
+    pin::Pin,
+    ptr,
+    sync::atomic::{AtomicPtr, Ordering},
+};
+
+/// A type that can be stored in a field of a trace event.
+///
+/// # Safety
+///
+/// Implementers must ensure that [`FieldType::C_TYPE`], [`FieldType::IS_SIGNED`] and
+/// [`FieldType::C_FORMAT`] describe the in-memory representation of the type, as they are used by
+/// the event filter and by user space tools to decode records.
+pub unsafe trait FieldType: Copy {
+    /// The name of the equivalent C type, as shown in the event's `format` file.
+    const C_TYPE: &'static CStr;
+
+    /// Whether the type is signed.
+    const IS_SIGNED: bool;
+
+    /// The `printf` conversion specification used in the event's `print fmt`.
+    const C_FORMAT: &'static str;
+}
+
+macro_rules! impl_field_type {
+    ($($t:ty => $ctype:literal, $signed:literal, $format:literal;)*) => {
+        $(
+            // SAFETY: The C type has the same size, alignment and signedness as the Rust type.
+            unsafe impl FieldType for $t {
+                const C_TYPE: &'static CStr = c_str!($ctype);
+                const IS_SIGNED: bool = $signed;
+                const C_FORMAT: &'static str = $format;
+            }
+        )*
+    };
+}
+
+impl_field_type! {
+    u8 => "u8", false, "%u";
+    u16 => "u16", false, "%u";
+    u32 => "u32", false, "%u";
+    u64 => "u64", false, "%llu";
+    usize => "unsigned long", false, "%lu";
+    i8 => "s8", true, "%d";
+    i16 => "s16", true, "%d";
+    i32 => "s32", true, "%d";
+    i64 => "s64", true, "%lld";
+    isize => "long", true, "%ld";
+    bool => "bool", false, "%d";
+}
+
+/// Describes the fields of a trace event to the tracing core.
+///
+/// An instance is passed to [`Event::define_fields`].
+pub struct Fields<'a> {
+    visit: &'a mut dyn FnMut(&'static CStr, &'static CStr, usize, usize, bool, &str) -> Result,
+}
+
+impl Fields<'_> {
+    /// Adds a field of type `U` called `name`, located `offset` bytes into the event.
+    ///
+    /// Callers are encouraged to use [`declare_trace_event`] instead of calling this directly.
+    pub fn add<U: FieldType>(&mut self, name: &'static CStr, offset: usize) -> Result {
+        (self.visit)(
+            name,
+            U::C_TYPE,
+            mem::size_of::<bindings::trace_entry>() + offset,
+            mem::size_of::<U>(),
+            U::IS_SIGNED,
+            U::C_FORMAT,
+        )
+    }
+}
+
+/// A trace event.
+///
+/// Implementations are usually generated by [`declare_trace_event`].
+pub trait Event: Copy + Send + Sync + 'static {
+    /// The trace system (i.e., the directory under `events/`) the event belongs to.
+    const SYSTEM: &'static CStr;
+
+    /// The name of the event.
+    const NAME: &'static CStr;
+
+    /// Describes all fields of the event.
+    fn define_fields(fields: &mut Fields<'_>) -> Result;
+
+    /// Formats an event record for the `trace` file, like `TP_printk` does in C.
+    fn print(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+/// The layout of an event record in the ring buffer.
+#[repr(C)]
+struct Entry<T> {
+    header: bindings::trace_entry,
+    payload: T,
+}
+
+/// Formats a record using [`Event::print`].
+struct Printer<'a, T: Event>(&'a T);
+
+impl<T: Event> fmt::Display for Printer<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.print(f)
+    }
+}
+
+/// Formats the `print fmt` shown in the event's `format` file, for example,
+/// `"id=%u len=%llu", REC->id, REC->len`.
+struct PrintFmt<T: Event>(PhantomData<T>);
+
+impl<T: Event> fmt::Display for PrintFmt<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sep = "";
+        f.write_str("\"")?;
+        T::define_fields(&mut Fields {
+            visit: &mut |name, _, _, _, _, format| {
+                write!(f, "{}{}={}", sep, name, format)?;
+                sep = " ";
+                Ok(())
+            },
+        })
+        .map_err(|_| fmt::Error)?;
+        f.write_str("\"")?;
+        T::define_fields(&mut Fields {
+            visit: &mut |name, _, _, _, _, _| Ok(write!(f, ", REC->{}", name)?),
+        })
+        .map_err(|_| fmt::Error)
+    }
+}
+
+/// A registration of a trace event.
+///
+/// The event can only be enabled in the top-level tracing instance.
+///
+/// # Invariants
+///
+/// Once registered, `top` is the `struct trace_event_file` of the event in the top-level tracing
+/// instance, to which `self` holds a reference. `file` is either null or `top` when the event is
+/// enabled; readers must be in an RCU read-side critical section.
+pub struct Registration<T: Event> {
+    call: UnsafeCell<bindings::trace_event_call>,
+    class: UnsafeCell<bindings::trace_event_class>,
+    funcs: UnsafeCell<bindings::trace_event_functions>,
+    print_fmt: Option<CString>,
+    file: AtomicPtr<bindings::trace_event_file>,
+    top: *mut bindings::trace_event_file,
+    registered: bool,
+    _pin: PhantomPinned,
+    _p: PhantomData<T>,
+}
+
+impl<T: Event> Registration<T> {
+    /// Creates a new [`Registration`] but does not register it yet.
+    ///
+    /// It is allowed to move.
+    pub fn new() -> Self {
+        Self {
+            call: UnsafeCell::new(bindings::trace_event_call::default()),
+            class: UnsafeCell::new(bindings::trace_event_class::default()),
+            funcs: UnsafeCell::new(bindings::trace_event_functions::default()),
+            print_fmt: None,
+            file: AtomicPtr::new(ptr::null_mut()),
+            top: ptr::null_mut(),
+            registered: false,
+            _pin: PhantomPinned,
+            _p: PhantomData,
+        }
+    }
+
+    /// Returns a registered and pinned, heap-allocated representation of the registration.
+    pub fn new_pinned() -> Result<Pin<Box<Self>>> {
+        let mut reg = Pin::from(Box::try_new(Self::new())?);
+        reg.as_mut().register()?;
+        Ok(reg)
+    }
+
+    /// Registers the trace event with the tracing core.
+    ///
+    /// It must be pinned because the memory block that represents the registration is
+    /// self-referential.
+    pub fn register(self: Pin<&mut Self>) -> Result {
+        // SAFETY: We never move out of `this`.
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.registered {
+            // Already registered.
+            return Err(EINVAL);
+        }
+
+        let print_fmt = CString::try_from_fmt(crate::fmt!("{}", PrintFmt::<T>(PhantomData)))?;
+
+        let class = this.class.get_mut();
+        class.system = T::SYSTEM.as_char_ptr();
+        class.define_fields = Some(Self::define_fields_callback);
+        class.reg = Some(Self::reg_callback);
+        // Mimics `INIT_LIST_HEAD`.
+        class.fields.next = ptr::addr_of_mut!(class.fields);
+        class.fields.prev = ptr::addr_of_mut!(class.fields);
+
+        this.funcs.get_mut().trace = Some(Self::print_callback);
+
+        let call = this.call.get_mut();
+        call.class = this.class.get();
+        call.__bindgen_anon_1.name = T::NAME.as_char_ptr() as _;
+        call.event.funcs = this.funcs.get();
+        call.print_fmt = print_fmt.as_char_ptr() as _;
+        this.print_fmt = Some(print_fmt);
+
+        // SAFETY: `call` is fully initialised above and remains valid until it is unregistered in
+        // `drop` because `this` is pinned.
+        if unsafe { bindings::register_trace_event(ptr::addr_of_mut!((*this.call.get()).event)) }
+            == 0
+        {
+            return Err(ENODEV);
+        }
+
+        // SAFETY: As above, `call` is initialised and pinned.
+        if let Err(e) = to_result(|| unsafe { bindings::trace_add_event_call(this.call.get()) }) {
+            // SAFETY: The event was registered above.
+            unsafe {
+                bindings::unregister_trace_event(ptr::addr_of_mut!((*this.call.get()).event))
+            };
+            return Err(e);
+        }
+
+        // Takes a reference to the file of the top-level instance, so that it can still be used
+        // to disable the event when the registration is dropped.
+        // SAFETY: The event was added above, and a null instance name selects the top-level one.
+        let top = unsafe {
+            bindings::trace_get_event_file(
+                ptr::null(),
+                T::SYSTEM.as_char_ptr(),
+                T::NAME.as_char_ptr(),
+            )
+        };
+        match from_kernel_err_ptr(top) {
+            // INVARIANT: `top` holds a reference to the file of the top-level instance.
+            Ok(top) => this.top = top,
+            Err(e) => {
+                // SAFETY: The event was added and registered above.
+                unsafe {
+                    bindings::trace_remove_event_call(this.call.get());
+                    bindings::unregister_trace_event(ptr::addr_of_mut!((*this.call.get()).event));
+                }
+                return Err(e);
+            }
+        }
+
+        this.registered = true;
+        Ok(())
+    }
+
+    /// Returns whether the event is currently enabled.
+    ///
+    /// This is a cheap check that callers may use to avoid computing the contents of an event that
+    /// would be discarded anyway; [`trace_event`] does so automatically.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        !self.file.load(Ordering::Relaxed).is_null()
+    }
+
+    /// Writes the given record to the ring buffer if the event is enabled.
+    pub fn emit(&self, record: &T) {
+        // SAFETY: FFI call without safety requirements.
+        unsafe { bindings::rcu_read_lock() };
+
+        let file = self.file.load(Ordering::Acquire);
+
+        // SAFETY: By the type invariants, `file` is either null or valid while in the RCU
+        // read-side critical section.
+        if !file.is_null() && !unsafe { bindings::trace_trigger_soft_disabled(file) } {
+            let mut fbuffer = MaybeUninit::<bindings::trace_event_buffer>::uninit();
+
+            // SAFETY: `fbuffer` is valid for writes and `file` is valid.
+            let entry = unsafe {
+                bindings::trace_event_buffer_reserve(
+                    fbuffer.as_mut_ptr(),
+                    file,
+                    mem::size_of::<Entry<T>>() as _,
+                )
+            } as *mut Entry<T>;
+
+            if !entry.is_null() {
+                // SAFETY: `trace_event_buffer_reserve` returned a buffer big enough to hold an
+                // `Entry<T>`, with the header already filled in. Ring buffer entries are not
+                // necessarily aligned for `T`, hence the unaligned write.
+                unsafe {
+                    ptr::addr_of_mut!((*entry).payload).write_unaligned(*record);
+                    bindings::trace_event_buffer_commit(fbuffer.as_mut_ptr());
+                }
+            }
+        }
+
+        // SAFETY: The RCU read-side critical section was entered above.
+        unsafe { bindings::rcu_read_unlock() };
+    }
+
+    unsafe extern "C" fn define_fields_callback(
+        call: *mut bindings::trace_event_call,
+    ) -> c_types::c_int {
+        from_kernel_result! {
+            T::define_fields(&mut Fields {
+                visit: &mut |name, c_type, offset, size, is_signed, _| {
+                    // SAFETY: `call` is valid for the duration of the callback, and the names are
+                    // static.
+                    to_result(|| unsafe {
+                        bindings::trace_define_field(
+                            call,
+                            c_type.as_char_ptr(),
+                            name.as_char_ptr(),
+                            offset as _,
+                            size as _,
+                            is_signed as _,
+                            bindings::FILTER_OTHER as _,
+                        )
+                    })
+                },
+            })?;
+            Ok(0)
+        }
+    }
+
+    unsafe extern "C" fn reg_callback(
+        call: *mut bindings::trace_event_call,
+        type_: bindings::trace_reg,
+        data: *mut c_types::c_void,
+    ) -> c_types::c_int {
+        // SAFETY: `call` is embedded in a `Registration<T>` that remains alive while registered.
+        let this = unsafe { &*crate::container_of!(call, Self, call) };
+        from_kernel_result! {
+            match type_ {
+                bindings::TRACE_REG_REGISTER => {
+                    if data.cast() != this.top {
+                        return Err(EOPNOTSUPP);
+                    }
+
+                    // INVARIANT: `data` is `top`, through which the event is being enabled.
+                    this.file
+                        .compare_exchange(
+                            ptr::null_mut(),
+                            data.cast(),
+                            Ordering::Release,
+                            Ordering::Relaxed,
+                        )
+                        .map_err(|_| EBUSY)?;
+                }
+                bindings::TRACE_REG_UNREGISTER => {
+                    let _ = this.file.compare_exchange(
+                        data.cast(),
+                        ptr::null_mut(),
+                        Ordering::Release,
+                        Ordering::Relaxed,
+                    );
+                    // Wait for emitters that may still be using the file.
+                    // SAFETY: FFI call without safety requirements.
+                    unsafe { bindings::synchronize_rcu() };
+                }
+                bindings::TRACE_REG_PERF_REGISTER => return Err(EOPNOTSUPP),
+                _ => {}
+            }
+            Ok(0)
+        }
+    }
+
+    unsafe extern "C" fn print_callback(
+        iter: *mut bindings::trace_iterator,
+        _flags: c_types::c_int,
+        _event: *mut bindings::trace_event,
+    ) -> bindings::print_line_t {
+        // SAFETY: The tracing core only calls this for records of this event, so `ent` points to
+        // an `Entry<T>`, which may not be aligned.
+        let record = unsafe {
+            let entry = (*iter).ent as *const Entry<T>;
+            ptr::addr_of!((*entry).payload).read_unaligned()
+        };
+
+        // SAFETY: `iter` is valid for the duration of the callback. The format string expects a
+        // null-terminated string followed by a pointer to `fmt::Arguments`.
+        unsafe {
+            bindings::trace_seq_printf(
+                ptr::addr_of_mut!((*iter).seq),
+                c_str!("%s: %pA\n").as_char_ptr(),
+                T::NAME.as_char_ptr(),
+                &crate::fmt!("{}", Printer(&record)) as *const _ as *const c_types::c_void,
+            );
+            bindings::trace_handle_return(ptr::addr_of_mut!((*iter).seq))
+        }
+    }
+}
+
+impl<T: Event> Default for Registration<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// SAFETY: `Registration` does not expose any of its state across threads, other than `file`,
+// which is atomic.
+unsafe impl<T: Event> Sync for Registration<T> {}
+
+// SAFETY: All functions work from any thread.
+unsafe impl<T: Event> Send for Registration<T> {}
+
+impl<T: Event> Drop for Registration<T> {
+    /// Removes the registration from the kernel if it has completed successfully before.
+    fn drop(&mut self) {
+        if !self.registered {
+            return;
+        }
+
+        // The tracing core refuses to remove enabled events, so disable it first.
+        // SAFETY: By the type invariants, `self` holds a reference to `top`, which keeps it and its
+        // trace array alive.
+        unsafe {
+            bindings::trace_array_set_clr_event(
+                (*self.top).tr,
+                T::SYSTEM.as_char_ptr(),
+                T::NAME.as_char_ptr(),
+                false,
+            );
+            bindings::trace_put_event_file(self.top);
+        }
+
+        // SAFETY: `registered` being `true` indicates that a previous call to
+        // `trace_add_event_call` succeeded.
+        let ret = unsafe { bindings::trace_remove_event_call(self.call.get()) };
+        crate::warn_on!(
+            ret != 0,
+            "failed to remove trace event: {:?}\n",
+            Error::from_kernel_errno(ret)
+        );
+
+        // SAFETY: The event was registered with `register_trace_event` in `register`.
+        unsafe { bindings::unregister_trace_event(ptr::addr_of_mut!((*self.call.get()).event)) };
+    }
+}
+
+/// Declares a trace event.
+///
+/// It generates a `#[repr(C)]` struct holding the fields of the event and implements [`Event`]
+/// for it. The `print` clause formats records for the `trace` file; it mimics the interface of
+/// [`std::print!`] and may refer to the fields by name.
+///
+/// [`std::print!`]: https://doc.rust-lang.org/std/macro.print.html
+///
+/// See the [module-level documentation](self) for an example.
+#[macro_export]
+macro_rules! declare_trace_event {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident($system:literal, $event:literal) {
+            $($(#[$fmeta:meta])* $fvis:vis $field:ident: $ty:ty),* $(,)?
+        }
+        print($($print:tt)+);
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy)]
+        #[repr(C)]
+        $vis struct $name {
+            $($(#[$fmeta])* $fvis $field: $ty),*
+        }
+
+        impl $crate::trace::Event for $name {
+            const SYSTEM: &'static $crate::str::CStr = $crate::c_str!($system);
+            const NAME: &'static $crate::str::CStr = $crate::c_str!($event);
+
+            fn define_fields(fields: &mut $crate::trace::Fields<'_>) -> $crate::Result {
+                $(
+                    fields.add::<$ty>(
+                        $crate::c_str!(core::stringify!($field)),
+                        $crate::offset_of!($name, $field) as usize,
+                    )?;
+                )*
+                Ok(())
+            }
+
+            fn print(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                #[allow(unused_variables)]
+                let Self { $($field),* } = *self;
+                core::write!(f, $($print)+)
+            }
+        }
+    };
+}
+
+/// Emits a trace event through the given [`Registration`].
+///
+/// The record is only evaluated if the event is enabled, so it is cheap to leave calls to this
+/// macro in hot paths.
+///
+/// See the [module-level documentation](crate::trace) for an example.
+#[macro_export]
+macro_rules! trace_event {
+    ($reg:expr, $record:expr $(,)?) => {{
+        let reg = &$reg;
+        if reg.is_enabled() {
+            reg.emit(&$record);
+        }
+    }};
+}
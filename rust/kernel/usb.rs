@@ -0,0 +1,650 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! USB interface drivers.
+//!
+//! A USB device has one or more interfaces, each implementing a function of the device (e.g., the
+//! keyboard and the touchpad of a combo device). Drivers bind to interfaces, and talk to them
+//! through their endpoints with USB request blocks (see [`Interface::submit_urb`]).
+//!
+//! C header: [`include/linux/usb.h`](../../../../include/linux/usb.h)
+
+use crate::{
+    bindings, c_types, device, driver,
+    error::{code::*, from_kernel_result, Error},
+    str::CStr,
+    to_result,
+    types::PointerWrapper,
+    ARef, AlwaysRefCounted, Opaque, Result, ThisModule,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::{
+    mem::{self, ManuallyDrop},
+    ptr::{self, NonNull},
+};
+
+/// A registration of a USB driver.
+pub type Registration<T> = driver::Registration<Adapter<T>>;
+
+/// Id of a USB interface, matched against the descriptors of the device and of its interfaces.
+#[derive(Clone, Copy)]
+pub struct DeviceId {
+    match_flags: u16,
+    vendor: u16,
+    product: u16,
+    class: u8,
+    subclass: u8,
+    protocol: u8,
+}
+
+impl DeviceId {
+    /// Matches all the interfaces of the devices with the given vendor and product ids.
+    pub const fn new(vendor: u16, product: u16) -> Self {
+        Self {
+            match_flags: bindings::USB_DEVICE_ID_MATCH_DEVICE as _,
+            vendor,
+            product,
+            class: 0,
+            subclass: 0,
+            protocol: 0,
+        }
+    }
+
+    /// Matches the interfaces with the given class, subclass and protocol, of any device.
+    pub const fn interface_info(class: u8, subclass: u8, protocol: u8) -> Self {
+        Self {
+            match_flags: bindings::USB_DEVICE_ID_MATCH_INT_INFO as _,
+            vendor: 0,
+            product: 0,
+            class,
+            subclass,
+            protocol,
+        }
+    }
+
+    /// Matches the interfaces with the given class, subclass and protocol, of the devices with
+    /// the given vendor and product ids.
+    pub const fn device_and_interface_info(
+        vendor: u16,
+        product: u16,
+        class: u8,
+        subclass: u8,
+        protocol: u8,
+    ) -> Self {
+        Self {
+            match_flags: (bindings::USB_DEVICE_ID_MATCH_DEVICE
+                | bindings::USB_DEVICE_ID_MATCH_INT_INFO) as _,
+            vendor,
+            product,
+            class,
+            subclass,
+            protocol,
+        }
+    }
+}
+
+// SAFETY: `ZERO` is all zeroed-out and `to_rawid` stores `offset` in `usb_device_id::driver_info`.
+unsafe impl const driver::RawDeviceId for DeviceId {
+    type RawType = bindings::usb_device_id;
+    const ZERO: Self::RawType = bindings::usb_device_id {
+        match_flags: 0,
+        idVendor: 0,
+        idProduct: 0,
+        bcdDevice_lo: 0,
+        bcdDevice_hi: 0,
+        bDeviceClass: 0,
+        bDeviceSubClass: 0,
+        bDeviceProtocol: 0,
+        bInterfaceClass: 0,
+        bInterfaceSubClass: 0,
+        bInterfaceProtocol: 0,
+        bInterfaceNumber: 0,
+        driver_info: 0,
+    };
+
+    fn to_rawid(&self, offset: isize) -> Self::RawType {
+        let mut id = Self::ZERO;
+        id.match_flags = self.match_flags;
+        id.idVendor = self.vendor;
+        id.idProduct = self.product;
+        id.bInterfaceClass = self.class;
+        id.bInterfaceSubClass = self.subclass;
+        id.bInterfaceProtocol = self.protocol;
+        id.driver_info = offset as _;
+        id
+    }
+}
+
+/// Defines the id table for USB interfaces.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// # use kernel::{define_usb_id_table, usb};
+/// #
+/// # struct Sample;
+/// # impl kernel::usb::Driver for Sample {
+/// #   fn probe(_intf: &mut usb::Interface, _id: Option<&Self::IdInfo>) -> Result {
+/// #       Ok(())
+/// #   }
+///     define_usb_id_table! {u32, [
+///         (usb::DeviceId::new(0x0403, 0x6001), Some(1)),
+///         (usb::DeviceId::interface_info(0xff, 0, 0), None),
+///     ]}
+/// # }
+/// ```
+#[macro_export]
+macro_rules! define_usb_id_table {
+    ($data_type:ty, $($t:tt)*) => {
+        type IdInfo = $data_type;
+        $crate::define_id_table!(ID_TABLE, $crate::usb::DeviceId, $data_type, $($t)*);
+    };
+}
+
+/// A USB interface driver.
+pub trait Driver {
+    /// Data stored on the interface by the driver.
+    type Data: PointerWrapper + Send + Sync + driver::DeviceRemoval = ();
+
+    /// The type holding information about each device id supported by the driver.
+    type IdInfo: 'static = ();
+
+    /// The table of device ids supported by the driver.
+    const ID_TABLE: Option<driver::IdTable<'static, DeviceId, Self::IdInfo>> = None;
+
+    /// Probes for the interface with the given id.
+    fn probe(intf: &mut Interface, id_info: Option<&Self::IdInfo>) -> Result<Self::Data>;
+
+    /// Cleans any resources up that are associated with the interface.
+    ///
+    /// This is called when the driver is detached from the interface, e.g., because the device
+    /// was unplugged. The USB core cancels the URBs still in flight before it is called.
+    fn disconnect(_data: &Self::Data) {}
+}
+
+/// An adapter for the registration of USB drivers.
+pub struct Adapter<T: Driver>(T);
+
+impl<T: Driver> driver::DriverOps for Adapter<T> {
+    type RegType = bindings::usb_driver;
+
+    unsafe fn register(
+        reg: *mut bindings::usb_driver,
+        name: &'static CStr,
+        module: &'static ThisModule,
+    ) -> Result {
+        // SAFETY: By the safety requirements of this function (defined in the trait defintion),
+        // `reg` is non-null and valid.
+        let udrv = unsafe { &mut *reg };
+        udrv.name = name.as_char_ptr();
+        udrv.probe = Some(probe_callback::<T>);
+        udrv.disconnect = Some(disconnect_callback::<T>);
+        if let Some(t) = T::ID_TABLE {
+            udrv.id_table = t.as_ref();
+        }
+        // SAFETY: By the safety requirements of this function, `reg` is valid and fully
+        // initialised. `name` and `module.0` live at least as long as the module.
+        to_result(|| unsafe { bindings::usb_register_driver(reg, module.0, name.as_char_ptr()) })
+    }
+
+    unsafe fn unregister(reg: *mut bindings::usb_driver) {
+        // SAFETY: By the safety requirements of this function (defined in the trait definition),
+        // `reg` was passed (and updated) by a previous successful call to `usb_register_driver`.
+        unsafe { bindings::usb_deregister(reg) };
+    }
+}
+
+unsafe extern "C" fn probe_callback<T: Driver>(
+    intf: *mut bindings::usb_interface,
+    id: *const bindings::usb_device_id,
+) -> c_types::c_int {
+    from_kernel_result! {
+        // SAFETY: `intf` is valid by the contract with the C code, and the USB core doesn't access
+        // it while the driver probes it.
+        let iface = unsafe { Interface::from_ptr(intf) };
+        // SAFETY: `id` is valid by the requirements the contract with the C code.
+        let offset = unsafe { (*id).driver_info };
+        let info = if offset == 0 {
+            None
+        } else {
+            // SAFETY: The offset comes from a previous call to `offset_from` in `IdArray::new`,
+            // which guarantees that the resulting pointer is within the table.
+            let ptr = unsafe { id.cast::<u8>().offset(offset as _).cast::<Option<T::IdInfo>>() };
+            // SAFETY: The id table has a static lifetime, so `ptr` is guaranteed to be valid for
+            // read.
+            unsafe { (&*ptr).as_ref() }
+        };
+        let data = T::probe(iface, info)?;
+        let ptr = T::Data::into_pointer(data);
+        // SAFETY: `intf` is valid for write by the contract with the C code.
+        unsafe { bindings::usb_set_intfdata(intf, ptr as _) };
+        Ok(0)
+    }
+}
+
+unsafe extern "C" fn disconnect_callback<T: Driver>(intf: *mut bindings::usb_interface) {
+    // SAFETY: `intf` is valid by the contract with the C code.
+    let ptr = unsafe { bindings::usb_get_intfdata(intf) };
+    // SAFETY: The value returned by `usb_get_intfdata` was stored by a previous call to
+    // `usb_set_intfdata` in `probe_callback` above; the value comes from a call to
+    // `T::Data::into_pointer`.
+    let data = unsafe { T::Data::from_pointer(ptr) };
+    T::disconnect(&data);
+    <T::Data as driver::DeviceRemoval>::device_remove(&data);
+    // SAFETY: `intf` is valid by the contract with the C code.
+    unsafe { bindings::usb_set_intfdata(intf, core::ptr::null_mut()) };
+}
+
+/// A USB interface.
+///
+/// Drivers can keep an [`ARef<Interface>`] to the interface they are bound to, e.g., in their
+/// [`Driver::Data`], to submit URBs to it after `probe` returns.
+///
+/// # Invariants
+///
+/// Instances of this type are always ref-counted, that is, a call to `usb_get_intf` ensures that
+/// the allocation remains valid at least until the matching call to `usb_put_intf`.
+#[repr(transparent)]
+pub struct Interface(Opaque<bindings::usb_interface>);
+
+// SAFETY: The interface is reference-counted, and its functions are safe to call from any thread.
+unsafe impl Send for Interface {}
+
+// SAFETY: The interface functions are safe to call concurrently.
+unsafe impl Sync for Interface {}
+
+impl Interface {
+    /// Creates a mutable reference to an [`Interface`] from a valid pointer.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be non-null and valid, and it must remain valid and not be accessed through
+    /// other references for the lifetime of the returned reference.
+    unsafe fn from_ptr<'a>(ptr: *mut bindings::usb_interface) -> &'a mut Self {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `Interface` type being transparent makes the cast ok.
+        unsafe { &mut *ptr.cast() }
+    }
+
+    fn altsetting(&self) -> &bindings::usb_host_interface {
+        // SAFETY: The interface is valid because the shared reference guarantees a nonzero
+        // refcount, and an interface always has a current setting.
+        unsafe { &*(*self.0.get()).cur_altsetting }
+    }
+
+    /// Returns the number of the interface within its device.
+    pub fn number(&self) -> u8 {
+        self.altsetting().desc.bInterfaceNumber
+    }
+
+    /// Returns the class, subclass and protocol of the interface.
+    pub fn class(&self) -> (u8, u8, u8) {
+        let desc = &self.altsetting().desc;
+        (
+            desc.bInterfaceClass,
+            desc.bInterfaceSubClass,
+            desc.bInterfaceProtocol,
+        )
+    }
+
+    /// Returns the vendor and product ids of the device the interface belongs to.
+    pub fn device_id(&self) -> (u16, u16) {
+        // SAFETY: The device of a valid interface is valid too.
+        let desc = unsafe { &(*self.usb_device()).descriptor };
+        (u16::from_le(desc.idVendor), u16::from_le(desc.idProduct))
+    }
+
+    fn usb_device(&self) -> *mut bindings::usb_device {
+        // SAFETY: The interface is valid because the shared reference guarantees a nonzero
+        // refcount.
+        unsafe { bindings::interface_to_usbdev(self.0.get()) }
+    }
+
+    /// Returns the descriptors of the endpoints of the interface, in its current setting.
+    pub fn endpoints(&self) -> impl Iterator<Item = EndpointDescriptor> + '_ {
+        let alt = self.altsetting();
+        (0..alt.desc.bNumEndpoints as usize).map(move |i| {
+            // SAFETY: `endpoint` has `bNumEndpoints` entries.
+            EndpointDescriptor(unsafe { (*alt.endpoint.add(i)).desc })
+        })
+    }
+
+    /// Returns the first endpoint of the interface with the given transfer type and direction.
+    pub fn find_endpoint(&self, ty: TransferType, is_in: bool) -> Option<EndpointDescriptor> {
+        self.endpoints()
+            .find(|ep| ep.transfer_type() == ty && ep.is_in() == is_in)
+    }
+
+    /// Submits a request to transfer `buffer` to or from the given endpoint of the interface.
+    ///
+    /// For endpoints whose direction is `OUT`, the whole buffer is sent. For `IN` ones, up to
+    /// `buffer.len()` bytes are received, so the buffer must be resized accordingly beforehand.
+    ///
+    /// Once the transfer is done, `handler` is called with the buffer back, from atomic context.
+    /// If the submission fails, the handler is dropped and the error returned. The URB holds a
+    /// reference to the interface until it is released.
+    ///
+    /// Only bulk and interrupt endpoints are supported.
+    pub fn submit_urb<H: UrbHandler>(
+        &self,
+        ep: &EndpointDescriptor,
+        buffer: Vec<u8>,
+        handler: H,
+        flags: u32,
+    ) -> Result {
+        let _: u32 = buffer.len().try_into()?;
+        let udev = self.usb_device();
+
+        // SAFETY: `udev` is valid, and the endpoint address comes from one of its descriptors.
+        let pipe = unsafe {
+            match (ep.transfer_type(), ep.is_in()) {
+                (TransferType::Bulk, true) => bindings::usb_rcvbulkpipe(udev, ep.address() as _),
+                (TransferType::Bulk, false) => bindings::usb_sndbulkpipe(udev, ep.address() as _),
+                (TransferType::Interrupt, true) => {
+                    bindings::usb_rcvintpipe(udev, ep.address() as _)
+                }
+                (TransferType::Interrupt, false) => {
+                    bindings::usb_sndintpipe(udev, ep.address() as _)
+                }
+                _ => return Err(EINVAL),
+            }
+        };
+
+        // SAFETY: There are no safety requirements for this FFI call.
+        let urb = NonNull::new(unsafe { bindings::usb_alloc_urb(0, flags) }).ok_or(ENOMEM)?;
+        let ctx = match Box::try_new(UrbContext {
+            intf: self.into(),
+            buffer,
+            handler: Some(handler),
+        }) {
+            Ok(ctx) => ctx,
+            Err(e) => {
+                // SAFETY: `urb` was allocated above and not submitted.
+                unsafe { bindings::usb_free_urb(urb.as_ptr()) };
+                return Err(e.into());
+            }
+        };
+
+        // SAFETY: `urb` and `udev` are valid. The buffer and the context are set by `submit`.
+        unsafe {
+            if ep.transfer_type() == TransferType::Interrupt {
+                bindings::usb_fill_int_urb(
+                    urb.as_ptr(),
+                    udev,
+                    pipe,
+                    ptr::null_mut(),
+                    0,
+                    Some(urb_complete_callback::<H>),
+                    ptr::null_mut(),
+                    ep.interval() as _,
+                );
+            } else {
+                bindings::usb_fill_bulk_urb(
+                    urb.as_ptr(),
+                    udev,
+                    pipe,
+                    ptr::null_mut(),
+                    0,
+                    Some(urb_complete_callback::<H>),
+                    ptr::null_mut(),
+                );
+            }
+        }
+
+        // SAFETY: `urb` was filled above with the completion callback for `H`, and we own the
+        // reference returned by `usb_alloc_urb`. The buffer length was checked above.
+        unsafe { submit(urb, ctx, flags) }
+    }
+}
+
+// SAFETY: The type invariants guarantee that `Interface` is always ref-counted.
+unsafe impl AlwaysRefCounted for Interface {
+    fn inc_ref(&self) {
+        // SAFETY: The existence of a shared reference means that the refcount is nonzero.
+        unsafe { bindings::usb_get_intf(self.0.get()) };
+    }
+
+    unsafe fn dec_ref(obj: NonNull<Self>) {
+        // SAFETY: The safety requirements guarantee that the refcount is nonzero.
+        unsafe { bindings::usb_put_intf(obj.cast().as_ptr()) };
+    }
+}
+
+// SAFETY: The device returned by `raw_device` is the raw USB interface device.
+unsafe impl device::RawDevice for Interface {
+    fn raw_device(&self) -> *mut bindings::device {
+        // SAFETY: The interface is valid because the shared reference guarantees a nonzero
+        // refcount.
+        unsafe { &mut (*self.0.get()).dev }
+    }
+}
+
+/// The transfer type of an endpoint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransferType {
+    /// Control transfers, used for configuration and commands.
+    Control,
+
+    /// Isochronous transfers, with a guaranteed bandwidth but no retries, e.g., for audio.
+    Isochronous,
+
+    /// Bulk transfers, for large amounts of data with no guaranteed latency.
+    Bulk,
+
+    /// Interrupt transfers, polled by the host at a fixed interval.
+    Interrupt,
+}
+
+/// The descriptor of an endpoint of an [`Interface`].
+#[derive(Clone, Copy)]
+pub struct EndpointDescriptor(bindings::usb_endpoint_descriptor);
+
+impl EndpointDescriptor {
+    /// Returns the address of the endpoint, including its direction bit.
+    pub fn address(&self) -> u8 {
+        self.0.bEndpointAddress
+    }
+
+    /// Returns the number of the endpoint, without its direction bit.
+    pub fn number(&self) -> u8 {
+        self.0.bEndpointAddress & bindings::USB_ENDPOINT_NUMBER_MASK as u8
+    }
+
+    /// Returns whether data flows from the device to the host.
+    pub fn is_in(&self) -> bool {
+        self.0.bEndpointAddress & bindings::USB_DIR_IN as u8 != 0
+    }
+
+    /// Returns the transfer type of the endpoint.
+    pub fn transfer_type(&self) -> TransferType {
+        match self.0.bmAttributes & bindings::USB_ENDPOINT_XFERTYPE_MASK as u8 {
+            0 => TransferType::Control,
+            1 => TransferType::Isochronous,
+            2 => TransferType::Bulk,
+            _ => TransferType::Interrupt,
+        }
+    }
+
+    /// Returns the maximum size of the packets of the endpoint, in bytes.
+    pub fn max_packet_size(&self) -> u16 {
+        u16::from_le(self.0.wMaxPacketSize) & 0x7ff
+    }
+
+    /// Returns the polling interval of interrupt and isochronous endpoints, in the encoding of
+    /// the speed of the device.
+    pub fn interval(&self) -> u8 {
+        self.0.bInterval
+    }
+}
+
+/// A handler for the completion of a USB request block (URB).
+pub trait UrbHandler: Send + Sized + 'static {
+    /// Called when the transfer is done, with the buffer and the number of bytes actually
+    /// transferred.
+    ///
+    /// `urb` can be resubmitted with [`Urb::resubmit`], e.g., to keep polling an interrupt
+    /// endpoint, or dropped to release it.
+    ///
+    /// `status` is an error if the transfer failed, e.g., with [`ENOENT`] or [`ESHUTDOWN`] if it
+    /// was cancelled or the device was unplugged, or [`EPIPE`] if the endpoint stalled.
+    ///
+    /// It is called from atomic context, so it must not sleep.
+    fn complete(self, urb: Urb<Self>, status: Result, buffer: Vec<u8>, actual_length: usize);
+}
+
+struct UrbContext<H: UrbHandler> {
+    intf: ARef<Interface>,
+    buffer: Vec<u8>,
+    handler: Option<H>,
+}
+
+/// A completed USB request block (URB), given to [`UrbHandler::complete`].
+///
+/// # Invariants
+///
+/// `ptr` was filled by [`Interface::submit_urb`] with the completion callback for `H`, it is not
+/// in flight, and we own a reference to it.
+pub struct Urb<H: UrbHandler> {
+    ptr: NonNull<bindings::urb>,
+    ctx: Box<UrbContext<H>>,
+}
+
+// SAFETY: The urb functions are safe to call from any thread, and the context is `Send` because
+// `H` is.
+unsafe impl<H: UrbHandler> Send for Urb<H> {}
+
+impl<H: UrbHandler> Urb<H> {
+    /// Returns the interface the URB was submitted to.
+    pub fn interface(&self) -> &ARef<Interface> {
+        &self.ctx.intf
+    }
+
+    /// Submits the URB again to the same endpoint, to transfer `buffer`.
+    ///
+    /// It behaves like [`Interface::submit_urb`], but it doesn't allocate memory, so it can be
+    /// called from [`UrbHandler::complete`] (with atomic `flags`).
+    pub fn resubmit(self, buffer: Vec<u8>, handler: H, flags: u32) -> Result {
+        let _: u32 = buffer.len().try_into()?;
+        let this = ManuallyDrop::new(self);
+
+        // SAFETY: `this` is never dropped, so the context is only moved out once.
+        let mut ctx = unsafe { ptr::read(&this.ctx) };
+        ctx.buffer = buffer;
+        ctx.handler = Some(handler);
+
+        // SAFETY: By the type invariants, the urb was filled with the completion callback for
+        // `H`, and the reference we own is passed on. The buffer length was checked above.
+        unsafe { submit(this.ptr, ctx, flags) }
+    }
+}
+
+impl<H: UrbHandler> Drop for Urb<H> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, we own a reference to the urb.
+        unsafe { bindings::usb_free_urb(self.ptr.as_ptr()) };
+    }
+}
+
+/// Submits `urb` to transfer the buffer of `ctx`, and drops the caller's reference to it.
+///
+/// # Safety
+///
+/// `urb` must have been filled by [`Interface::submit_urb`] with the completion callback for `H`,
+/// it must not be in flight, and the caller must own a reference to it. The length of the buffer
+/// must fit in a `u32`.
+unsafe fn submit<H: UrbHandler>(
+    urb: NonNull<bindings::urb>,
+    mut ctx: Box<UrbContext<H>>,
+    flags: u32,
+) -> Result {
+    let urb = urb.as_ptr();
+
+    // SAFETY: `urb` is valid and not in flight, so it can be updated. The buffer is owned by the
+    // context, which is only released once the transfer completes, so it outlives it. It was
+    // allocated with `kmalloc`, so it can be used for DMA.
+    let ret = unsafe {
+        (*urb).transfer_buffer = ctx.buffer.as_mut_ptr() as _;
+        (*urb).transfer_buffer_length = ctx.buffer.len() as _;
+        (*urb).context = Box::into_raw(ctx) as _;
+        bindings::usb_submit_urb(urb, flags)
+    };
+
+    // SAFETY: On success, the USB core holds its own reference to the urb until it completes,
+    // so ours can be dropped. On failure, the completion callback won't be called, so the
+    // context is released here.
+    unsafe {
+        if ret < 0 {
+            drop(Box::from_raw((*urb).context as *mut UrbContext<H>));
+        }
+        bindings::usb_free_urb(urb);
+    }
+
+    if ret < 0 {
+        return Err(Error::from_kernel_errno(ret));
+    }
+    Ok(())
+}
+
+unsafe extern "C" fn urb_complete_callback<H: UrbHandler>(urb: *mut bindings::urb) {
+    // SAFETY: The USB core calls this with the urb submitted by `submit`, whose context is a
+    // boxed `UrbContext<H>`, once, when it completes. It drops its reference to the urb once we
+    // return, so we take our own.
+    let (mut ctx, status, actual_length) = unsafe {
+        bindings::usb_get_urb(urb);
+        (
+            Box::from_raw((*urb).context as *mut UrbContext<H>),
+            (*urb).status,
+            (*urb).actual_length,
+        )
+    };
+    let status = if status < 0 {
+        Err(Error::from_kernel_errno(status))
+    } else {
+        Ok(())
+    };
+    let buffer = mem::take(&mut ctx.buffer);
+    let handler = ctx.handler.take();
+
+    // INVARIANT: The urb has completed, and we took a reference to it above.
+    // SAFETY: `urb` is non-null since the USB core passed it to us.
+    let urb = Urb {
+        ptr: unsafe { NonNull::new_unchecked(urb) },
+        ctx,
+    };
+    if let Some(handler) = handler {
+        handler.complete(urb, status, buffer, actual_length as _);
+    }
+}
+
+/// Declares a kernel module that exposes a single USB driver.
+///
+/// # Examples
+///
+/// ```ignore
+/// # use kernel::prelude::*;
+/// # use kernel::{usb, define_usb_id_table, module_usb_driver};
+/// #
+/// struct MyDriver;
+/// impl usb::Driver for MyDriver {
+///     // [...]
+/// #   fn probe(_intf: &mut usb::Interface, _id: Option<&Self::IdInfo>) -> Result {
+/// #       Ok(())
+/// #   }
+/// #   define_usb_id_table! {(), [
+/// #       (usb::DeviceId::new(0x0403, 0x6001), None),
+/// #   ]}
+/// }
+///
+/// module_usb_driver! {
+///     type: MyDriver,
+///     name: b"module_name",
+///     author: b"Author name",
+///     license: b"GPL v2",
+/// }
+/// ```
+#[macro_export]
+macro_rules! module_usb_driver {
+    ($($f:tt)*) => {
+        $crate::module_driver!(<T>, $crate::usb::Adapter<T>, { $($f)* });
+    };
+}
@@ -7,11 +7,18 @@
 use crate::{
     bindings, c_types,
     error::code::*,
-    io_buffer::{IoBufferReader, IoBufferWriter},
+    io_buffer::{IoBufferReader, IoBufferWriter, ReadableFromBytes, WritableToBytes},
     Result,
 };
 use alloc::vec::Vec;
 
+/// The maximum number of bytes copied from or to userspace by a single call to `copy_from_user`
+/// or `copy_to_user`.
+///
+/// Larger copies are split in chunks of this size, so that the length always fits in the
+/// `unsigned long` (or narrower) argument of the architecture-specific copy routines.
+const CHUNK_SIZE: usize = u32::MAX as usize;
+
 /// A reference to an area in userspace memory, which can be either
 /// read-only or read-write.
 ///
@@ -63,6 +70,47 @@ impl UserSlicePtr {
         self.reader().read_all()
     }
 
+    /// Reads the entirety of the user slice, provided it is at most `max` bytes long.
+    ///
+    /// Returns `EINVAL` if the user slice is larger than `max`, without reading from it; this
+    /// prevents userspace from making the kernel allocate arbitrarily large buffers. Returns
+    /// `EFAULT` if the address does not currently point to mapped, readable memory.
+    pub fn read_all_bounded(self, max: usize) -> Result<Vec<u8>> {
+        if self.1 > max {
+            return Err(EINVAL);
+        }
+        self.read_all()
+    }
+
+    /// Returns whether the user slice lies entirely within the userspace part of the address
+    /// space.
+    ///
+    /// Reads and writes already perform this check, so this is only useful to fail early, before
+    /// doing any work, e.g., when the slice is only going to be accessed later.
+    ///
+    /// Equivalent to the kernel's `access_ok`.
+    pub fn access_ok(&self) -> bool {
+        // SAFETY: `access_ok` only checks the address range, it does not access the memory.
+        unsafe { bindings::access_ok(self.0, self.1 as _) }
+    }
+
+    /// Reads the contents of a plain old data (POD) type from the user slice.
+    ///
+    /// Returns `EFAULT` if the user slice is smaller than `T` or if the address does not
+    /// currently point to mapped, readable memory. See [`crate::declare_pod_struct`] for how to
+    /// declare structures that can be read this way.
+    pub fn read<T: ReadableFromBytes>(self) -> Result<T> {
+        self.reader().read()
+    }
+
+    /// Writes the contents of a plain old data (POD) type into the user slice.
+    ///
+    /// Returns `EFAULT` if the user slice is smaller than `T` or if the address does not
+    /// currently point to mapped, writable memory.
+    pub fn write<T: WritableToBytes>(self, data: &T) -> Result {
+        self.writer().write(data)
+    }
+
     /// Constructs a [`UserSlicePtrReader`].
     pub fn reader(self) -> UserSlicePtrReader {
         UserSlicePtrReader(self.0, self.1)
@@ -107,20 +155,46 @@ impl IoBufferReader for UserSlicePtrReader {
 
     /// Reads raw data from the user slice into a raw kernel buffer.
     ///
+    /// If the copy fails partway, the reader is only advanced past the bytes that were copied.
+    ///
     /// # Safety
     ///
     /// The output buffer must be valid.
-    unsafe fn read_raw(&mut self, out: *mut u8, len: usize) -> Result {
-        if len > self.1 || len > u32::MAX as usize {
+    unsafe fn read_raw(&mut self, mut out: *mut u8, len: usize) -> Result {
+        if len > self.1 {
             return Err(EFAULT);
         }
-        let res = unsafe { bindings::copy_from_user(out as _, self.0, len as _) };
-        if res != 0 {
+
+        let mut left = len;
+        while left > 0 {
+            let chunk = core::cmp::min(left, CHUNK_SIZE);
+            // SAFETY: The user buffer is validated by `copy_from_user`; the safety requirements
+            // guarantee that `out` is valid for writes of `len` bytes, and `chunk <= left`.
+            let res = unsafe { bindings::copy_from_user(out as _, self.0, chunk as _) } as usize;
+            let copied = chunk - res;
+            // Since this is not a pointer to a valid object in our program,
+            // we cannot use `add`, which has C-style rules for defined
+            // behavior.
+            self.0 = self.0.wrapping_add(copied);
+            self.1 -= copied;
+            if res != 0 {
+                return Err(EFAULT);
+            }
+            out = out.wrapping_add(chunk);
+            left -= chunk;
+        }
+        Ok(())
+    }
+}
+
+impl UserSlicePtrReader {
+    /// Skips the next `len` bytes of the user slice without reading them.
+    ///
+    /// Returns `EFAULT` if there are fewer than `len` bytes left.
+    pub fn skip(&mut self, len: usize) -> Result {
+        if len > self.1 {
             return Err(EFAULT);
         }
-        // Since this is not a pointer to a valid object in our program,
-        // we cannot use `add`, which has C-style rules for defined
-        // behavior.
         self.0 = self.0.wrapping_add(len);
         self.1 -= len;
         Ok(())
@@ -157,19 +231,36 @@ impl IoBufferWriter for UserSlicePtrWriter {
         ret
     }
 
-    unsafe fn write_raw(&mut self, data: *const u8, len: usize) -> Result {
-        if len > self.1 || len > u32::MAX as usize {
+    /// Writes raw data from a raw kernel buffer into the user slice.
+    ///
+    /// If the copy fails partway, the writer is only advanced past the bytes that were copied.
+    ///
+    /// # Safety
+    ///
+    /// The input buffer must be valid.
+    unsafe fn write_raw(&mut self, mut data: *const u8, len: usize) -> Result {
+        if len > self.1 {
             return Err(EFAULT);
         }
-        let res = unsafe { bindings::copy_to_user(self.0, data as _, len as _) };
-        if res != 0 {
-            return Err(EFAULT);
+
+        let mut left = len;
+        while left > 0 {
+            let chunk = core::cmp::min(left, CHUNK_SIZE);
+            // SAFETY: The user buffer is validated by `copy_to_user`; the safety requirements
+            // guarantee that `data` is valid for reads of `len` bytes, and `chunk <= left`.
+            let res = unsafe { bindings::copy_to_user(self.0, data as _, chunk as _) } as usize;
+            let copied = chunk - res;
+            // Since this is not a pointer to a valid object in our program,
+            // we cannot use `add`, which has C-style rules for defined
+            // behavior.
+            self.0 = self.0.wrapping_add(copied);
+            self.1 -= copied;
+            if res != 0 {
+                return Err(EFAULT);
+            }
+            data = data.wrapping_add(chunk);
+            left -= chunk;
         }
-        // Since this is not a pointer to a valid object in our program,
-        // we cannot use `add`, which has C-style rules for defined
-        // behavior.
-        self.0 = self.0.wrapping_add(len);
-        self.1 -= len;
         Ok(())
     }
 }
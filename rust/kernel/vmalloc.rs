@@ -0,0 +1,380 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Allocations that are virtually but not physically contiguous.
+//!
+//! The general-purpose allocator (used by [`Box`] and [`Vec`]) returns physically contiguous
+//! memory, which may be hard to find for large allocations once memory is fragmented. [`VBox`]
+//! and [`VVec`] use `vmalloc` instead, which maps individual pages into a contiguous virtual
+//! range. Such memory cannot be used for DMA, and is slower to allocate and to access (because of
+//! TLB misses), so it should only be used for large allocations, e.g., hash tables.
+//!
+//! The `kv` variants (e.g., [`VBox::try_new_kv`]) first try the general-purpose allocator, and
+//! only fall back to `vmalloc` if it fails, which suits allocations whose size varies a lot.
+//!
+//! C header: [`include/linux/vmalloc.h`](../../../../include/linux/vmalloc.h)
+//!
+//! [`Box`]: alloc::boxed::Box
+//! [`Vec`]: alloc::vec::Vec
+
+use crate::{bindings, build_assert, c_types, error::code::*, Result};
+use core::{
+    mem::{self, MaybeUninit},
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    slice,
+};
+
+/// A value of type `T` allocated with `vmalloc`.
+///
+/// # Invariants
+///
+/// `ptr` was allocated by `vmalloc` or `kvmalloc` for a `T`, and points to an initialised `T`
+/// owned by the box.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::vmalloc::VBox;
+///
+/// struct Table {
+///     buckets: [u64; 4096],
+/// }
+///
+/// fn example() -> Result {
+///     // The table is zeroed in place, so it is never on the stack.
+///     let table = VBox::<Table>::try_new_kv_zeroed()?;
+///     // SAFETY: All-zero bytes are a valid `Table`.
+///     let mut table = unsafe { table.assume_init() };
+///     table.buckets[42] = 1;
+///     Ok(())
+/// }
+/// ```
+pub struct VBox<T> {
+    ptr: NonNull<T>,
+}
+
+// SAFETY: The box owns its value, and the memory can be freed from any thread.
+unsafe impl<T: Send> Send for VBox<T> {}
+
+// SAFETY: The value is only accessed through shared references from shared references to the box.
+unsafe impl<T: Sync> Sync for VBox<T> {}
+
+impl<T> VBox<T> {
+    /// Allocates memory with `vmalloc` and places `value` into it.
+    pub fn try_new(value: T) -> Result<Self> {
+        // SAFETY: There are no safety requirements for this FFI call.
+        Self::from_alloc(unsafe { bindings::vmalloc(Self::size() as _) }, value)
+    }
+
+    /// Allocates memory with `kvmalloc`, that is, with `vmalloc` only if the general-purpose
+    /// allocator fails, and places `value` into it.
+    pub fn try_new_kv(value: T) -> Result<Self> {
+        // SAFETY: There are no safety requirements for this FFI call.
+        Self::from_alloc(
+            unsafe { bindings::kvmalloc(Self::size(), bindings::GFP_KERNEL) },
+            value,
+        )
+    }
+
+    /// Allocates zeroed memory with `vzalloc`, without initialising it as a `T`.
+    ///
+    /// Unlike [`VBox::try_new`], the value doesn't need to be built on the stack first, which
+    /// matters for the large types `vmalloc` is meant for. [`VBox::assume_init`] converts the
+    /// result into a `VBox<T>` if all-zero bytes are a valid `T`.
+    pub fn try_new_zeroed() -> Result<VBox<MaybeUninit<T>>> {
+        // SAFETY: There are no safety requirements for this FFI call.
+        Self::from_zeroed(unsafe { bindings::vzalloc(Self::size() as _) })
+    }
+
+    /// Allocates zeroed memory with `kvmalloc`, without initialising it as a `T`.
+    ///
+    /// See [`VBox::try_new_kv`] and [`VBox::try_new_zeroed`].
+    pub fn try_new_kv_zeroed() -> Result<VBox<MaybeUninit<T>>> {
+        // SAFETY: There are no safety requirements for this FFI call.
+        Self::from_zeroed(unsafe {
+            bindings::kvmalloc(Self::size(), bindings::GFP_KERNEL | bindings::__GFP_ZERO)
+        })
+    }
+
+    fn size() -> usize {
+        build_assert!(
+            mem::size_of::<T>() != 0,
+            "Zero-sized types should be allocated with `Box`"
+        );
+        mem::size_of::<T>()
+    }
+
+    fn from_alloc(ptr: *mut c_types::c_void, value: T) -> Result<Self> {
+        let ptr = NonNull::new(ptr as *mut T).ok_or(ENOMEM)?;
+
+        // SAFETY: `ptr` was just allocated for a `T`, so it is valid for writes. Both allocators
+        // return page- or word-aligned memory, which suffices for the same types as the global
+        // allocator.
+        unsafe { ptr.as_ptr().write(value) };
+
+        // INVARIANT: `ptr` was allocated for a `T` and initialised above.
+        Ok(Self { ptr })
+    }
+
+    fn from_zeroed(ptr: *mut c_types::c_void) -> Result<VBox<MaybeUninit<T>>> {
+        // INVARIANT: `ptr` was allocated for a `T`, which has the same layout as a
+        // `MaybeUninit<T>`. The latter needs no initialisation.
+        Ok(VBox {
+            ptr: NonNull::new(ptr as *mut MaybeUninit<T>).ok_or(ENOMEM)?,
+        })
+    }
+}
+
+impl<T> VBox<MaybeUninit<T>> {
+    /// Converts the box into a `VBox<T>`.
+    ///
+    /// # Safety
+    ///
+    /// The value must have been initialised, e.g., it was allocated zeroed and all-zero bytes are
+    /// a valid `T`.
+    pub unsafe fn assume_init(self) -> VBox<T> {
+        let ptr = self.ptr.cast();
+        mem::forget(self);
+
+        // INVARIANT: `ptr` was allocated for a `MaybeUninit<T>`, which has the same layout as a
+        // `T`, and the safety requirements guarantee that it is initialised.
+        VBox { ptr }
+    }
+}
+
+impl<T> Deref for VBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: By the type invariants, `ptr` points to an initialised `T`.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for VBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // SAFETY: By the type invariants, `ptr` points to an initialised `T` owned by the box.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> Drop for VBox<T> {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `ptr` points to an initialised `T` owned by the box,
+        // which was allocated by `vmalloc` or `kvmalloc`; `kvfree` frees both.
+        unsafe {
+            ptr::drop_in_place(self.ptr.as_ptr());
+            bindings::kvfree(self.ptr.as_ptr() as _);
+        }
+    }
+}
+
+/// A growable array allocated with `kvmalloc`.
+///
+/// Small arrays use the general-purpose allocator, large ones fall back to `vmalloc`.
+///
+/// # Invariants
+///
+/// `ptr` is either dangling, with `cap` zero, or was allocated by `kvmalloc` (or `kvrealloc`) for
+/// `cap` elements. The first `len` elements are initialised and owned by the vector, and `len` is
+/// at most `cap`.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::vmalloc::VVec;
+///
+/// fn example() -> Result {
+///     let mut inodes = VVec::<u64>::try_with_capacity(1 << 16)?;
+///     inodes.try_push(2)?;
+///     inodes.try_extend_from_slice(&[3, 5, 7])?;
+///     assert_eq!(&inodes[..], &[2, 3, 5, 7]);
+///     assert_eq!(inodes.pop(), Some(7));
+///     Ok(())
+/// }
+/// ```
+pub struct VVec<T> {
+    ptr: NonNull<T>,
+    len: usize,
+    cap: usize,
+}
+
+// SAFETY: The vector owns its elements, and the memory can be freed from any thread.
+unsafe impl<T: Send> Send for VVec<T> {}
+
+// SAFETY: The elements are only accessed through shared references from shared references to the
+// vector.
+unsafe impl<T: Sync> Sync for VVec<T> {}
+
+impl<T> VVec<T> {
+    /// Creates a new empty vector, without allocating.
+    pub fn new() -> Self {
+        build_assert!(
+            mem::size_of::<T>() != 0,
+            "Zero-sized types should be stored in `Vec`"
+        );
+
+        // INVARIANT: The vector is empty and `ptr` is dangling.
+        Self {
+            ptr: NonNull::dangling(),
+            len: 0,
+            cap: 0,
+        }
+    }
+
+    /// Creates a new empty vector with room for at least `capacity` elements.
+    pub fn try_with_capacity(capacity: usize) -> Result<Self> {
+        let mut v = Self::new();
+        v.try_reserve(capacity)?;
+        Ok(v)
+    }
+
+    /// Returns the number of elements in the vector.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the vector has no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of elements the vector can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// Makes room for at least `additional` more elements.
+    ///
+    /// The capacity at least doubles when it grows, so that appending is amortised constant
+    /// time.
+    pub fn try_reserve(&mut self, additional: usize) -> Result {
+        let needed = self.len.checked_add(additional).ok_or(ENOMEM)?;
+        if needed <= self.cap {
+            return Ok(());
+        }
+
+        let cap = needed.max(self.cap.saturating_mul(2));
+        let new_size = cap.checked_mul(mem::size_of::<T>()).ok_or(ENOMEM)?;
+        let old_size = self.cap * mem::size_of::<T>();
+        let old_ptr = if self.cap == 0 {
+            ptr::null()
+        } else {
+            self.ptr.as_ptr() as *const c_types::c_void
+        };
+
+        // SAFETY: By the type invariants, `old_ptr` is either null or was allocated by `kvmalloc`
+        // with `old_size` bytes. `kvrealloc` keeps the old allocation if it fails.
+        let ptr = unsafe { bindings::kvrealloc(old_ptr, old_size, new_size, bindings::GFP_KERNEL) };
+
+        // INVARIANT: `ptr` was allocated for `cap` elements, and the initialised elements were
+        // copied to it.
+        self.ptr = NonNull::new(ptr as *mut T).ok_or(ENOMEM)?;
+        self.cap = cap;
+        Ok(())
+    }
+
+    /// Appends an element to the vector.
+    pub fn try_push(&mut self, value: T) -> Result {
+        self.try_reserve(1)?;
+
+        // SAFETY: There is room for one more element, as reserved above.
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+
+        // INVARIANT: The new element was initialised above.
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Removes the last element of the vector and returns it, if any.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        // INVARIANT: The last element is moved out below, so it is no longer owned by the vector.
+        self.len -= 1;
+
+        // SAFETY: By the type invariants, the element at `len` was initialised.
+        Some(unsafe { self.ptr.as_ptr().add(self.len).read() })
+    }
+
+    /// Removes all the elements of the vector, keeping its capacity.
+    pub fn clear(&mut self) {
+        let elems: *mut [T] = &mut self[..];
+
+        // INVARIANT: The elements are dropped below, so they are no longer owned by the vector.
+        self.len = 0;
+
+        // SAFETY: `elems` are initialised and no longer owned by the vector.
+        unsafe { ptr::drop_in_place(elems) };
+    }
+}
+
+impl<T: Clone> VVec<T> {
+    /// Appends clones of the elements of `other` to the vector.
+    pub fn try_extend_from_slice(&mut self, other: &[T]) -> Result {
+        self.try_reserve(other.len())?;
+        for item in other {
+            // The capacity was reserved above, so this doesn't fail.
+            self.try_push(item.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Resizes the vector to `len` elements, filling new ones with clones of `value`.
+    pub fn try_resize(&mut self, len: usize, value: T) -> Result {
+        if len <= self.len {
+            let tail: *mut [T] = &mut self[len..];
+
+            // INVARIANT: The elements after `len` are dropped below, so they are no longer owned
+            // by the vector.
+            self.len = len;
+
+            // SAFETY: `tail` is initialised and no longer owned by the vector.
+            unsafe { ptr::drop_in_place(tail) };
+            return Ok(());
+        }
+
+        self.try_reserve(len - self.len)?;
+        while self.len < len {
+            self.try_push(value.clone())?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> Default for VVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Deref for VVec<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // SAFETY: By the type invariants, the first `len` elements are initialised. `ptr` is
+        // dangling (but aligned) if the vector has no capacity, which is valid for empty slices.
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for VVec<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        // SAFETY: As above, and the elements are owned by the vector.
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> Drop for VVec<T> {
+    fn drop(&mut self) {
+        self.clear();
+        if self.cap != 0 {
+            // SAFETY: By the type invariants, `ptr` was allocated by `kvmalloc`.
+            unsafe { bindings::kvfree(self.ptr.as_ptr() as _) };
+        }
+    }
+}
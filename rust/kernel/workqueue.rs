@@ -0,0 +1,784 @@
+// SPDX-License-Identifier: GPL-2.0
+
+//! Work queues.
+//!
+//! Work items are functions that are run later, in process context, by kernel threads. They are
+//! usually used to defer processing from contexts that cannot sleep (e.g., interrupt handlers) or
+//! to run it asynchronously (e.g., from file operations).
+//!
+//! A work item is a [`Work`] field embedded in a reference-counted object, which is described by
+//! an implementation of [`WorkAdapter`]. While a work item is queued, the queue holds a reference
+//! to the object, which is then passed to [`WorkAdapter::run`].
+//!
+//! Work items that should only run after a delay are [`DelayedWork`] fields instead, described by
+//! an implementation of [`DelayedWorkAdapter`] and queued with [`Queue::enqueue_delayed`].
+//!
+//! C header: [`include/linux/workqueue.h`](../../../../include/linux/workqueue.h)
+//!
+//! Reference: <https://www.kernel.org/doc/html/latest/core-api/workqueue.html>
+
+use crate::{
+    bindings, c_str,
+    error::code::*,
+    str::CStr,
+    sync::{LockClassKey, Ref, UniqueRef},
+    time::Ktime,
+    Opaque, Result,
+};
+use core::{cell::UnsafeCell, fmt, marker::PhantomPinned, ops::Deref, ptr::NonNull};
+
+/// Initialises a work item whose adapter is its containing type.
+///
+/// A new lock class is created for each call site. See [`Work::init`].
+#[macro_export]
+macro_rules! init_work_item {
+    ($obj:expr) => {{
+        $crate::workqueue::Work::init(
+            $obj,
+            $crate::c_str!(concat!("work:", stringify!($obj))),
+            $crate::static_lock_class!(),
+        )
+    }};
+}
+
+/// Initialises a work item with the given adapter.
+///
+/// A new lock class is created for each call site. See [`Work::init_with_adapter`].
+#[macro_export]
+macro_rules! init_work_item_adapter {
+    ($adapter:ty, $obj:expr) => {{
+        $crate::workqueue::Work::init_with_adapter::<$adapter>(
+            $obj,
+            $crate::c_str!(concat!("work:", stringify!($obj))),
+            $crate::static_lock_class!(),
+        )
+    }};
+}
+
+/// Initialises a delayed work item whose adapter is its containing type.
+///
+/// New lock classes are created for each call site. See [`DelayedWork::init`].
+#[macro_export]
+macro_rules! init_delayed_work_item {
+    ($obj:expr) => {{
+        $crate::workqueue::DelayedWork::init(
+            $obj,
+            $crate::c_str!(concat!("delayed_work:", stringify!($obj))),
+            $crate::static_lock_class!(),
+            $crate::static_lock_class!(),
+        )
+    }};
+}
+
+/// Initialises a delayed work item with the given adapter.
+///
+/// New lock classes are created for each call site. See [`DelayedWork::init_with_adapter`].
+#[macro_export]
+macro_rules! init_delayed_work_item_adapter {
+    ($adapter:ty, $obj:expr) => {{
+        $crate::workqueue::DelayedWork::init_with_adapter::<$adapter>(
+            $obj,
+            $crate::c_str!(concat!("delayed_work:", stringify!($obj))),
+            $crate::static_lock_class!(),
+            $crate::static_lock_class!(),
+        )
+    }};
+}
+
+/// Implements [`WorkAdapter`] for a type that contains a [`Work`] field.
+///
+/// The closure is called with a [`Ref`] to the object when the work item runs.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use core::sync::atomic::{AtomicU32, Ordering};
+/// use kernel::sync::{Ref, UniqueRef};
+/// use kernel::workqueue::{self, Work};
+///
+/// struct Example {
+///     count: AtomicU32,
+///     work: Work,
+/// }
+///
+/// kernel::impl_self_work_adapter!(Example, work, |w| {
+///     w.count.fetch_add(1, Ordering::Relaxed);
+///     pr_info!("work ran\n");
+/// });
+///
+/// fn example() -> Result {
+///     let e = UniqueRef::try_new(Example {
+///         count: AtomicU32::new(0),
+///         // SAFETY: `work` is initialised below.
+///         work: unsafe { Work::new() },
+///     })?;
+///     kernel::init_work_item!(&e);
+///     let e: Ref<Example> = e.into();
+///
+///     // Queue it twice: the second call returns `false` unless the first one already ran.
+///     workqueue::system().enqueue(e.clone());
+///     workqueue::system().enqueue(e);
+///     Ok(())
+/// }
+/// ```
+#[macro_export]
+macro_rules! impl_self_work_adapter {
+    ($work_type:ty, $field:ident, $closure:expr) => {
+        $crate::impl_work_adapter!($work_type, $work_type, $field, $closure);
+    };
+}
+
+/// Implements [`WorkAdapter`] for an adapter type, for objects of the given type.
+///
+/// This allows an object to have more than one work item, each with its own adapter. The closure
+/// is called with a [`Ref`] to the object when the work item runs.
+#[macro_export]
+macro_rules! impl_work_adapter {
+    ($adapter:ty, $work_type:ty, $field:ident, $closure:expr) => {
+        // SAFETY: We use `offset_of` to ensure that the field is within the given type, and we
+        // also check below that its type is `Work`.
+        unsafe impl $crate::workqueue::WorkAdapter for $adapter {
+            type Target = $work_type;
+            const FIELD_OFFSET: isize = $crate::offset_of!($work_type, $field);
+
+            fn run(w: $crate::sync::Ref<Self::Target>) {
+                // Checks that the type of the field is actually `Work`.
+                let _: fn(&$work_type) -> &$crate::workqueue::Work = |obj| &obj.$field;
+
+                let closure: fn($crate::sync::Ref<Self::Target>) = $closure;
+                closure(w);
+            }
+        }
+    };
+}
+
+/// Describes a work item embedded in objects of some type.
+///
+/// Implementations are usually generated with [`impl_self_work_adapter`] or
+/// [`impl_work_adapter`].
+///
+/// # Safety
+///
+/// `FIELD_OFFSET` must be the offset of a field of type [`Work`] in `Target`.
+pub unsafe trait WorkAdapter {
+    /// The type of the objects that contain the work item.
+    type Target;
+
+    /// The offset of the [`Work`] field in `Target`.
+    const FIELD_OFFSET: isize;
+
+    /// Runs the work item.
+    ///
+    /// It is called in process context, so it may sleep. It receives the reference held by the
+    /// queue while the work item was pending.
+    fn run(w: Ref<Self::Target>);
+}
+
+/// A work item.
+///
+/// Wraps the kernel's `struct work_struct`. It is embedded in objects described by a
+/// [`WorkAdapter`].
+///
+/// # Invariants
+///
+/// `work` has been initialised by [`Work::init_with_adapter`] before being queued or cancelled.
+#[repr(transparent)]
+pub struct Work {
+    work: Opaque<bindings::work_struct>,
+    _pin: PhantomPinned,
+}
+
+// SAFETY: The work item has no state of its own besides `work`, which is used concurrently by the
+// workqueue code.
+unsafe impl Send for Work {}
+
+// SAFETY: All the functions that take shared references to a work item are safe to call
+// concurrently.
+unsafe impl Sync for Work {}
+
+impl Work {
+    /// Creates a new work item.
+    ///
+    /// # Safety
+    ///
+    /// Callers must call [`Work::init`] or [`Work::init_with_adapter`] (or one of the
+    /// [`init_work_item`] and [`init_work_item_adapter`] macros) before the object containing it
+    /// is converted into a [`Ref`].
+    pub unsafe fn new() -> Self {
+        Self {
+            work: Opaque::uninit(),
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Initialises the work item embedded in `obj`, using `obj`'s type as its adapter.
+    ///
+    /// Users should prefer the [`init_work_item`] macro, which creates the lock class.
+    pub fn init<T: WorkAdapter<Target = T>>(
+        obj: &UniqueRef<T>,
+        name: &'static CStr,
+        key: &'static LockClassKey,
+    ) {
+        Self::init_with_adapter::<T>(obj, name, key)
+    }
+
+    /// Initialises the work item embedded in `obj` at the offset given by the adapter.
+    ///
+    /// Users should prefer the [`init_work_item_adapter`] macro, which creates the lock class.
+    pub fn init_with_adapter<A: WorkAdapter>(
+        obj: &UniqueRef<A::Target>,
+        name: &'static CStr,
+        key: &'static LockClassKey,
+    ) {
+        let ptr = &**obj as *const A::Target as *const u8;
+        // SAFETY: `obj` is valid, and the safety requirements of `WorkAdapter` guarantee that
+        // there is a `Work` field at `FIELD_OFFSET`.
+        let field = unsafe { ptr.offset(A::FIELD_OFFSET) } as *const Work;
+
+        // SAFETY: `field` is valid. The work item cannot be queued yet because there is no `Ref`
+        // to `obj`, and it won't move once there is one, since `Ref` objects are heap-allocated.
+        unsafe {
+            bindings::__INIT_WORK_WITH_KEY(
+                (*field).work.get(),
+                Some(Self::work_func::<A>),
+                false,
+                name.as_char_ptr(),
+                key.as_ptr(),
+            )
+        };
+    }
+
+    /// Cancels the work item embedded in `obj` at the offset given by the adapter, and waits for
+    /// it to complete if it is running.
+    ///
+    /// Returns `true` if the work item was pending. In that case, the reference held by the queue
+    /// is dropped.
+    ///
+    /// It may sleep, and it must not be called from the work item itself.
+    pub fn cancel<A: WorkAdapter>(obj: &A::Target) -> bool {
+        let ptr = obj as *const A::Target as *const u8;
+        // SAFETY: `obj` is valid, and the safety requirements of `WorkAdapter` guarantee that
+        // there is a `Work` field at `FIELD_OFFSET`.
+        let field = unsafe { &*(ptr.offset(A::FIELD_OFFSET) as *const Work) };
+
+        // SAFETY: By the type invariants, `work` was initialised.
+        if !unsafe { bindings::cancel_work_sync(field.work.get()) } {
+            return false;
+        }
+
+        // SAFETY: The work item was pending, so the queue held a reference to `obj`, which is
+        // ours now that it was cancelled.
+        drop(unsafe { Ref::from_raw(obj) });
+        true
+    }
+
+    unsafe extern "C" fn work_func<A: WorkAdapter>(work: *mut bindings::work_struct) {
+        let field = work as *const u8;
+        // SAFETY: `work` is the field at `FIELD_OFFSET` in an object of type `A::Target`, so
+        // going back by the offset yields a pointer to the object.
+        let ptr = unsafe { field.offset(-A::FIELD_OFFSET) } as *const A::Target;
+
+        // SAFETY: The reference was converted into a raw pointer when the work item was queued,
+        // and it is returned to the adapter now that it is running.
+        let w = unsafe { Ref::from_raw(ptr) };
+        A::run(w);
+    }
+}
+
+/// Implements [`DelayedWorkAdapter`] for a type that contains a [`DelayedWork`] field.
+///
+/// The closure is called with a [`Ref`] to the object when the work item runs.
+///
+/// # Examples
+///
+/// ```
+/// # use kernel::prelude::*;
+/// use kernel::sync::{Ref, UniqueRef};
+/// use kernel::time::Ktime;
+/// use kernel::workqueue::{self, DelayedWork};
+///
+/// struct Poller {
+///     dwork: DelayedWork,
+/// }
+///
+/// kernel::impl_self_delayed_work_adapter!(Poller, dwork, |p| {
+///     pr_info!("polling\n");
+///
+///     // Poll again in a second.
+///     workqueue::system().enqueue_delayed(p, Ktime::from_ms(1000));
+/// });
+///
+/// fn start() -> Result<Ref<Poller>> {
+///     let p = UniqueRef::try_new(Poller {
+///         // SAFETY: `dwork` is initialised below.
+///         dwork: unsafe { DelayedWork::new() },
+///     })?;
+///     kernel::init_delayed_work_item!(&p);
+///     let p: Ref<Poller> = p.into();
+///     workqueue::system().enqueue_delayed(p.clone(), Ktime::from_ms(100));
+///     Ok(p)
+/// }
+///
+/// fn stop(p: &Poller) {
+///     DelayedWork::cancel::<Poller>(p);
+/// }
+/// ```
+#[macro_export]
+macro_rules! impl_self_delayed_work_adapter {
+    ($work_type:ty, $field:ident, $closure:expr) => {
+        $crate::impl_delayed_work_adapter!($work_type, $work_type, $field, $closure);
+    };
+}
+
+/// Implements [`DelayedWorkAdapter`] for an adapter type, for objects of the given type.
+///
+/// This allows an object to have more than one delayed work item, each with its own adapter. The
+/// closure is called with a [`Ref`] to the object when the work item runs.
+#[macro_export]
+macro_rules! impl_delayed_work_adapter {
+    ($adapter:ty, $work_type:ty, $field:ident, $closure:expr) => {
+        // SAFETY: We use `offset_of` to ensure that the field is within the given type, and we
+        // also check below that its type is `DelayedWork`.
+        unsafe impl $crate::workqueue::DelayedWorkAdapter for $adapter {
+            type Target = $work_type;
+            const FIELD_OFFSET: isize = $crate::offset_of!($work_type, $field);
+
+            fn run(w: $crate::sync::Ref<Self::Target>) {
+                // Checks that the type of the field is actually `DelayedWork`.
+                let _: fn(&$work_type) -> &$crate::workqueue::DelayedWork = |obj| &obj.$field;
+
+                let closure: fn($crate::sync::Ref<Self::Target>) = $closure;
+                closure(w);
+            }
+        }
+    };
+}
+
+/// Describes a delayed work item embedded in objects of some type.
+///
+/// Implementations are usually generated with [`impl_self_delayed_work_adapter`] or
+/// [`impl_delayed_work_adapter`].
+///
+/// # Safety
+///
+/// `FIELD_OFFSET` must be the offset of a field of type [`DelayedWork`] in `Target`.
+pub unsafe trait DelayedWorkAdapter {
+    /// The type of the objects that contain the work item.
+    type Target;
+
+    /// The offset of the [`DelayedWork`] field in `Target`.
+    const FIELD_OFFSET: isize;
+
+    /// Runs the work item.
+    ///
+    /// It is called in process context, so it may sleep. It receives the reference held by the
+    /// queue while the work item was pending.
+    fn run(w: Ref<Self::Target>);
+}
+
+/// A delayed work item, which is queued once a timer expires.
+///
+/// Wraps the kernel's `struct delayed_work`. It is embedded in objects described by a
+/// [`DelayedWorkAdapter`]. Like with [`Work`], the queue holds a reference to the object while
+/// the work item is pending, including while its timer is running.
+///
+/// # Invariants
+///
+/// `work` has been initialised by [`DelayedWork::init_with_adapter`] before being queued or
+/// cancelled.
+#[repr(transparent)]
+pub struct DelayedWork {
+    work: Opaque<bindings::delayed_work>,
+    _pin: PhantomPinned,
+}
+
+// SAFETY: The work item has no state of its own besides `work`, which is used concurrently by the
+// workqueue and timer code.
+unsafe impl Send for DelayedWork {}
+
+// SAFETY: All the functions that take shared references to a delayed work item are safe to call
+// concurrently.
+unsafe impl Sync for DelayedWork {}
+
+impl DelayedWork {
+    /// Creates a new delayed work item.
+    ///
+    /// # Safety
+    ///
+    /// Callers must call [`DelayedWork::init`] or [`DelayedWork::init_with_adapter`] (or one of
+    /// the [`init_delayed_work_item`] and [`init_delayed_work_item_adapter`] macros) before the
+    /// object containing it is converted into a [`Ref`].
+    pub unsafe fn new() -> Self {
+        Self {
+            work: Opaque::uninit(),
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Initialises the delayed work item embedded in `obj`, using `obj`'s type as its adapter.
+    ///
+    /// Users should prefer the [`init_delayed_work_item`] macro, which creates the lock classes.
+    pub fn init<T: DelayedWorkAdapter<Target = T>>(
+        obj: &UniqueRef<T>,
+        name: &'static CStr,
+        work_key: &'static LockClassKey,
+        timer_key: &'static LockClassKey,
+    ) {
+        Self::init_with_adapter::<T>(obj, name, work_key, timer_key)
+    }
+
+    /// Initialises the delayed work item embedded in `obj` at the offset given by the adapter.
+    ///
+    /// Users should prefer the [`init_delayed_work_item_adapter`] macro, which creates the lock
+    /// classes.
+    pub fn init_with_adapter<A: DelayedWorkAdapter>(
+        obj: &UniqueRef<A::Target>,
+        name: &'static CStr,
+        work_key: &'static LockClassKey,
+        timer_key: &'static LockClassKey,
+    ) {
+        let ptr = &**obj as *const A::Target as *const u8;
+        // SAFETY: `obj` is valid, and the safety requirements of `DelayedWorkAdapter` guarantee
+        // that there is a `DelayedWork` field at `FIELD_OFFSET`.
+        let field = unsafe { ptr.offset(A::FIELD_OFFSET) } as *const DelayedWork;
+
+        // SAFETY: `field` is valid. The work item cannot be queued yet because there is no `Ref`
+        // to `obj`, and it won't move once there is one, since `Ref` objects are heap-allocated.
+        // This is what `INIT_DELAYED_WORK` does.
+        unsafe {
+            let dwork = (*field).work.get();
+            bindings::__INIT_WORK_WITH_KEY(
+                &mut (*dwork).work,
+                Some(Self::work_func::<A>),
+                false,
+                name.as_char_ptr(),
+                work_key.as_ptr(),
+            );
+            bindings::init_timer_key(
+                &mut (*dwork).timer,
+                Some(bindings::delayed_work_timer_fn),
+                bindings::TIMER_IRQSAFE,
+                name.as_char_ptr(),
+                timer_key.as_ptr(),
+            );
+        }
+    }
+
+    /// Cancels the delayed work item embedded in `obj` at the offset given by the adapter, and
+    /// waits for it to complete if it is running.
+    ///
+    /// Returns `true` if the work item was pending, whether its timer was still running or not.
+    /// In that case, the reference held by the queue is dropped.
+    ///
+    /// It may sleep, and it must not be called from the work item itself.
+    pub fn cancel<A: DelayedWorkAdapter>(obj: &A::Target) -> bool {
+        let ptr = obj as *const A::Target as *const u8;
+        // SAFETY: `obj` is valid, and the safety requirements of `DelayedWorkAdapter` guarantee
+        // that there is a `DelayedWork` field at `FIELD_OFFSET`.
+        let field = unsafe { &*(ptr.offset(A::FIELD_OFFSET) as *const DelayedWork) };
+
+        // SAFETY: By the type invariants, `work` was initialised.
+        if !unsafe { bindings::cancel_delayed_work_sync(field.work.get()) } {
+            return false;
+        }
+
+        // SAFETY: The work item was pending, so the queue held a reference to `obj`, which is
+        // ours now that it was cancelled.
+        drop(unsafe { Ref::from_raw(obj) });
+        true
+    }
+
+    unsafe extern "C" fn work_func<A: DelayedWorkAdapter>(work: *mut bindings::work_struct) {
+        // SAFETY: `work` is embedded in a `delayed_work`, which is the field at `FIELD_OFFSET` in
+        // an object of type `A::Target`, so going back to the containing `delayed_work` and then
+        // by the offset yields a pointer to the object.
+        let ptr = unsafe {
+            let dwork = crate::container_of!(work, bindings::delayed_work, work) as *const u8;
+            dwork.offset(-A::FIELD_OFFSET) as *const A::Target
+        };
+
+        // SAFETY: The reference was converted into a raw pointer when the work item was queued,
+        // and it is returned to the adapter now that it is running.
+        let w = unsafe { Ref::from_raw(ptr) };
+        A::run(w);
+    }
+}
+
+/// Contains constants for the flags of work queues created with [`Queue::try_new`].
+///
+/// It is tagged with `non_exhaustive` to prevent users from instantiating it.
+#[non_exhaustive]
+pub struct Flags;
+
+impl Flags {
+    /// Work items are not bound to the CPU that queued them, they may run on any CPU.
+    pub const UNBOUND: u32 = bindings::WQ_UNBOUND;
+
+    /// Work items are not run while the system is frozen for suspend.
+    pub const FREEZABLE: u32 = bindings::WQ_FREEZABLE;
+
+    /// The queue may be used while reclaiming memory, so it has a rescuer thread that guarantees
+    /// forward progress.
+    pub const MEM_RECLAIM: u32 = bindings::WQ_MEM_RECLAIM;
+
+    /// Work items are run by high priority threads.
+    pub const HIGHPRI: u32 = bindings::WQ_HIGHPRI;
+
+    /// Work items are CPU intensive, so they don't delay other work items on the same CPU.
+    pub const CPU_INTENSIVE: u32 = bindings::WQ_CPU_INTENSIVE;
+
+    /// The queue is visible in sysfs.
+    pub const SYSFS: u32 = bindings::WQ_SYSFS;
+}
+
+/// A work queue.
+///
+/// Wraps the kernel's `struct workqueue_struct`. Queues are either the system ones (e.g.,
+/// [`system`]) or owned by a [`BoxedQueue`].
+#[repr(transparent)]
+pub struct Queue(Opaque<bindings::workqueue_struct>);
+
+// SAFETY: Work queues can be used from any thread.
+unsafe impl Send for Queue {}
+
+// SAFETY: The work queue functions that take shared references are safe to call concurrently.
+unsafe impl Sync for Queue {}
+
+impl Queue {
+    /// Creates a new work queue.
+    ///
+    /// `flags` is a combination of the constants in [`Flags`], and `max_active` is the maximum
+    /// number of work items of the queue running at the same time on each CPU, where 0 means the
+    /// default.
+    ///
+    /// Equivalent to the kernel's `alloc_workqueue`.
+    pub fn try_new(name: fmt::Arguments<'_>, flags: u32, max_active: i32) -> Result<BoxedQueue> {
+        // SAFETY: We use a format string that requires an `fmt::Arguments` pointer as the first
+        // and only argument.
+        let ptr = unsafe {
+            bindings::alloc_workqueue(
+                c_str!("%pA").as_char_ptr(),
+                flags,
+                max_active,
+                &name as *const _ as *const core::ffi::c_void,
+            )
+        };
+        let ptr = NonNull::new(ptr).ok_or(ENOMEM)?;
+
+        // INVARIANT: `ptr` was just allocated by `alloc_workqueue`.
+        Ok(BoxedQueue { ptr })
+    }
+
+    /// Creates a new ordered work queue, which runs at most one work item at a time, in the order
+    /// they were queued.
+    ///
+    /// Equivalent to the kernel's `alloc_ordered_workqueue`.
+    pub fn try_new_ordered(name: fmt::Arguments<'_>, flags: u32) -> Result<BoxedQueue> {
+        Self::try_new(
+            name,
+            Flags::UNBOUND | bindings::__WQ_ORDERED | bindings::__WQ_ORDERED_EXPLICIT | flags,
+            1,
+        )
+    }
+
+    /// Creates a reference to a work queue from a raw pointer.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must be non-null and valid for the lifetime of the returned reference.
+    pub unsafe fn from_raw<'a>(ptr: *const bindings::workqueue_struct) -> &'a Queue {
+        // SAFETY: The safety requirements guarantee the validity of the dereference, while the
+        // `Queue` type being transparent makes the cast ok.
+        unsafe { &*ptr.cast() }
+    }
+
+    /// Queues the given object, whose type is its own adapter.
+    ///
+    /// Returns `true` if it was queued; otherwise it was already pending and `w` is dropped.
+    pub fn enqueue<T: WorkAdapter<Target = T>>(&self, w: Ref<T>) -> bool {
+        self.enqueue_adapter::<T>(w)
+    }
+
+    /// Queues the work item of the given object described by the adapter.
+    ///
+    /// Returns `true` if it was queued; otherwise it was already pending and `w` is dropped.
+    pub fn enqueue_adapter<A: WorkAdapter>(&self, w: Ref<A::Target>) -> bool {
+        let ptr = Ref::into_raw(w);
+        // SAFETY: `ptr` is valid, and the safety requirements of `WorkAdapter` guarantee that
+        // there is a `Work` field at `FIELD_OFFSET`.
+        let field = unsafe { &*((ptr as *const u8).offset(A::FIELD_OFFSET) as *const Work) };
+
+        // SAFETY: `self` is valid, and the work item was initialised by the type invariants of
+        // `Work`. The queue owns the reference converted above until the work item runs.
+        let queued = unsafe {
+            bindings::queue_work_on(
+                bindings::WORK_CPU_UNBOUND as _,
+                self.0.get(),
+                field.work.get(),
+            )
+        };
+        if !queued {
+            // SAFETY: The work item was already pending, so the queue didn't take the reference.
+            drop(unsafe { Ref::from_raw(ptr) });
+        }
+        queued
+    }
+
+    /// Queues the given object, whose type is its own adapter, to run after `delay`.
+    ///
+    /// Returns `true` if it was queued; otherwise it was already pending and `w` is dropped.
+    pub fn enqueue_delayed<T: DelayedWorkAdapter<Target = T>>(
+        &self,
+        w: Ref<T>,
+        delay: Ktime,
+    ) -> bool {
+        self.enqueue_delayed_adapter::<T>(w, delay)
+    }
+
+    /// Queues the delayed work item of the given object described by the adapter, to run after
+    /// `delay`.
+    ///
+    /// The delay is rounded up to jiffies; if it is zero or negative, the work item is queued
+    /// immediately. Returns `true` if it was queued; otherwise it was already pending and `w` is
+    /// dropped.
+    pub fn enqueue_delayed_adapter<A: DelayedWorkAdapter>(
+        &self,
+        w: Ref<A::Target>,
+        delay: Ktime,
+    ) -> bool {
+        let ptr = Ref::into_raw(w);
+        // SAFETY: `ptr` is valid, and the safety requirements of `DelayedWorkAdapter` guarantee
+        // that there is a `DelayedWork` field at `FIELD_OFFSET`.
+        let field = unsafe { &*((ptr as *const u8).offset(A::FIELD_OFFSET) as *const DelayedWork) };
+
+        // SAFETY: `self` is valid, and the work item was initialised by the type invariants of
+        // `DelayedWork`. The queue owns the reference converted above until the work item runs.
+        let queued = unsafe {
+            bindings::queue_delayed_work_on(
+                bindings::WORK_CPU_UNBOUND as _,
+                self.0.get(),
+                field.work.get(),
+                delay.to_jiffies(),
+            )
+        };
+        if !queued {
+            // SAFETY: The work item was already pending, so the queue didn't take the reference.
+            drop(unsafe { Ref::from_raw(ptr) });
+        }
+        queued
+    }
+
+    /// Runs the given closure later, from this queue.
+    ///
+    /// It allocates memory for the work item, so it returns [`ENOMEM`] if that fails.
+    pub fn try_spawn<T: 'static + Send + FnOnce()>(&self, func: T) -> Result {
+        let w = UniqueRef::try_new(ClosureWork {
+            // SAFETY: `work` is initialised below.
+            work: unsafe { Work::new() },
+            func: UnsafeCell::new(Some(func)),
+        })?;
+        crate::init_work_item!(&w);
+        self.enqueue(w.into());
+        Ok(())
+    }
+
+    /// Waits for all the work items queued so far to complete.
+    ///
+    /// It may sleep, and it must not be called from a work item of this queue.
+    pub fn flush(&self) {
+        // SAFETY: `self` is valid.
+        unsafe { bindings::flush_workqueue(self.0.get()) };
+    }
+}
+
+struct ClosureWork<T> {
+    work: Work,
+    func: UnsafeCell<Option<T>>,
+}
+
+// SAFETY: `func` is only accessed by the work item, which runs once.
+unsafe impl<T: Send> Sync for ClosureWork<T> {}
+
+// SAFETY: `FIELD_OFFSET` is the offset of `work`, which is a `Work`.
+unsafe impl<T: 'static + Send + FnOnce()> WorkAdapter for ClosureWork<T> {
+    type Target = Self;
+    const FIELD_OFFSET: isize = crate::offset_of!(Self, work);
+
+    fn run(w: Ref<Self>) {
+        // SAFETY: The work item runs at most once and no one else accesses `func`.
+        if let Some(func) = unsafe { (*w.func.get()).take() } {
+            func();
+        }
+    }
+}
+
+/// An owned work queue.
+///
+/// The queue is drained and destroyed when it is dropped, so it waits for all its work items to
+/// complete.
+///
+/// # Invariants
+///
+/// `ptr` was allocated by `alloc_workqueue` and is owned by this object.
+pub struct BoxedQueue {
+    ptr: NonNull<bindings::workqueue_struct>,
+}
+
+// SAFETY: The queue can be destroyed from any thread.
+unsafe impl Send for BoxedQueue {}
+
+// SAFETY: Shared references only give access to `Queue`, which is `Sync`.
+unsafe impl Sync for BoxedQueue {}
+
+impl Deref for BoxedQueue {
+    type Target = Queue;
+
+    fn deref(&self) -> &Queue {
+        // SAFETY: By the type invariants, `ptr` is valid while `self` is alive.
+        unsafe { Queue::from_raw(self.ptr.as_ptr()) }
+    }
+}
+
+impl Drop for BoxedQueue {
+    fn drop(&mut self) {
+        // SAFETY: By the type invariants, `ptr` is owned by `self` and it isn't used anymore.
+        unsafe { bindings::destroy_workqueue(self.ptr.as_ptr()) };
+    }
+}
+
+/// Returns the system work queue (`system_wq`).
+///
+/// It is multi-CPU and used by most users. Work items should not run for a long time.
+pub fn system() -> &'static Queue {
+    // SAFETY: `system_wq` is initialised at boot and never destroyed.
+    unsafe { Queue::from_raw(bindings::system_wq) }
+}
+
+/// Returns the system high priority work queue (`system_highpri_wq`).
+pub fn system_highpri() -> &'static Queue {
+    // SAFETY: `system_highpri_wq` is initialised at boot and never destroyed.
+    unsafe { Queue::from_raw(bindings::system_highpri_wq) }
+}
+
+/// Returns the system work queue for long-running work items (`system_long_wq`).
+pub fn system_long() -> &'static Queue {
+    // SAFETY: `system_long_wq` is initialised at boot and never destroyed.
+    unsafe { Queue::from_raw(bindings::system_long_wq) }
+}
+
+/// Returns the system unbound work queue (`system_unbound_wq`).
+///
+/// Its work items are not bound to any CPU, which suits long-running or CPU intensive ones.
+pub fn system_unbound() -> &'static Queue {
+    // SAFETY: `system_unbound_wq` is initialised at boot and never destroyed.
+    unsafe { Queue::from_raw(bindings::system_unbound_wq) }
+}
+
+/// Returns the system freezable work queue (`system_freezable_wq`).
+///
+/// Its work items are not run while the system is frozen for suspend.
+pub fn system_freezable() -> &'static Queue {
+    // SAFETY: `system_freezable_wq` is initialised at boot and never destroyed.
+    unsafe { Queue::from_raw(bindings::system_freezable_wq) }
+}